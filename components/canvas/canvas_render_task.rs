@@ -2,49 +2,383 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use azure::AzFloat;
+use azure::{AZ_CAP_BUTT, AZ_CAP_ROUND, AZ_CAP_SQUARE};
+use azure::{AZ_JOIN_BEVEL, AZ_JOIN_MITER_OR_BEVEL, AZ_JOIN_ROUND};
 use azure::azure_hl::{DrawTarget, Color, B8G8R8A8, SkiaBackend, StrokeOptions, DrawOptions};
-use azure::azure_hl::{ColorPattern, ColorPatternRef};
+use azure::azure_hl::{ColorPattern, ColorPatternRef, DrawSurfaceOptions, ExtendClamp};
+use azure::azure_hl::{GradientStop, GradientStops, Linear, LinearGradientPattern};
+use azure::azure_hl::{LinearGradientPatternRef, Path, PatternRef, RadialGradientPattern};
+use azure::azure_hl::{InOp, RadialGradientPatternRef, SourceOp};
+use geom::matrix2d::Matrix2D;
+use geom::point::Point2D;
 use geom::rect::Rect;
 use geom::size::Size2D;
+use gfx::ScaledFontExtensionMethods;
+use gfx::font::SpecifiedFontStyle;
+use gfx::font_cache_task::FontCacheTask;
+use gfx::font_context::FontContext;
+use gfx::text::glyph::CharIndex;
+use gfx::text::text_run::TextRun;
+use servo_util::geometry::Au;
+use servo_util::opts;
+use servo_util::range::Range;
+use servo_util::smallvec::SmallVec;
 use servo_util::task::spawn_named;
+use style::RGBA;
+use style::computed_values::word_break;
+use sync::Arc;
 
+use std::cmp::min;
 use std::comm;
+use std::f32::consts::{FRAC_PI_2, PI_2};
+use std::num::{Float, FloatMath};
+
+/// The style used to fill or stroke shapes: `fillStyle` and `strokeStyle`.
+#[deriving(Clone)]
+pub enum FillOrStrokeStyle {
+    ColorStyle(RGBA),
+    LinearGradientStyle(LinearGradient),
+    RadialGradientStyle(RadialGradient),
+}
+
+/// A color stop of a `CanvasGradient`, with its offset between 0 and 1.
+#[deriving(Clone)]
+pub struct CanvasGradientStop {
+    pub offset: f64,
+    pub color: RGBA,
+}
+
+/// A gradient from `createLinearGradient()`.
+#[deriving(Clone)]
+pub struct LinearGradient {
+    pub start: Point2D<f64>,
+    pub end: Point2D<f64>,
+    pub stops: Vec<CanvasGradientStop>,
+}
+
+/// A gradient from `createRadialGradient()`, between two circles.
+#[deriving(Clone)]
+pub struct RadialGradient {
+    pub start_center: Point2D<f64>,
+    pub start_radius: f64,
+    pub end_center: Point2D<f64>,
+    pub end_radius: f64,
+    pub stops: Vec<CanvasGradientStop>,
+}
+
+/// The value of `lineCap`.
+#[deriving(Clone, PartialEq)]
+pub enum LineCapStyle {
+    ButtCap,
+    RoundCap,
+    SquareCap,
+}
+
+impl LineCapStyle {
+    pub fn from_str(string: &str) -> Option<LineCapStyle> {
+        match string {
+            "butt" => Some(ButtCap),
+            "round" => Some(RoundCap),
+            "square" => Some(SquareCap),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ButtCap => "butt",
+            RoundCap => "round",
+            SquareCap => "square",
+        }
+    }
+}
+
+/// The value of `lineJoin`.
+#[deriving(Clone, PartialEq)]
+pub enum LineJoinStyle {
+    RoundJoin,
+    BevelJoin,
+    MiterJoin,
+}
+
+impl LineJoinStyle {
+    pub fn from_str(string: &str) -> Option<LineJoinStyle> {
+        match string {
+            "round" => Some(RoundJoin),
+            "bevel" => Some(BevelJoin),
+            "miter" => Some(MiterJoin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            RoundJoin => "round",
+            BevelJoin => "bevel",
+            MiterJoin => "miter",
+        }
+    }
+}
+
+/// The value of `textAlign`.
+#[deriving(Clone, PartialEq)]
+pub enum TextAlign {
+    StartAlign,
+    EndAlign,
+    LeftAlign,
+    RightAlign,
+    CenterAlign,
+}
+
+impl TextAlign {
+    pub fn from_str(string: &str) -> Option<TextAlign> {
+        match string {
+            "start" => Some(StartAlign),
+            "end" => Some(EndAlign),
+            "left" => Some(LeftAlign),
+            "right" => Some(RightAlign),
+            "center" => Some(CenterAlign),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            StartAlign => "start",
+            EndAlign => "end",
+            LeftAlign => "left",
+            RightAlign => "right",
+            CenterAlign => "center",
+        }
+    }
+}
+
+/// The value of `textBaseline`.
+#[deriving(Clone, PartialEq)]
+pub enum TextBaseline {
+    TopBaseline,
+    HangingBaseline,
+    MiddleBaseline,
+    AlphabeticBaseline,
+    IdeographicBaseline,
+    BottomBaseline,
+}
+
+impl TextBaseline {
+    pub fn from_str(string: &str) -> Option<TextBaseline> {
+        match string {
+            "top" => Some(TopBaseline),
+            "hanging" => Some(HangingBaseline),
+            "middle" => Some(MiddleBaseline),
+            "alphabetic" => Some(AlphabeticBaseline),
+            "ideographic" => Some(IdeographicBaseline),
+            "bottom" => Some(BottomBaseline),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            TopBaseline => "top",
+            HangingBaseline => "hanging",
+            MiddleBaseline => "middle",
+            AlphabeticBaseline => "alphabetic",
+            IdeographicBaseline => "ideographic",
+            BottomBaseline => "bottom",
+        }
+    }
+}
 
 pub enum CanvasMsg {
     FillRect(Rect<f32>),
     ClearRect(Rect<f32>),
     StrokeRect(Rect<f32>),
+    BeginPath,
+    ClosePath,
+    MoveTo(Point2D<f32>),
+    LineTo(Point2D<f32>),
+    QuadraticCurveTo(Point2D<f32>, Point2D<f32>),
+    BezierCurveTo(Point2D<f32>, Point2D<f32>, Point2D<f32>),
+    /// Center, radius, start angle, end angle and whether the arc goes anticlockwise.
+    AddArc(Point2D<f32>, f32, f32, f32, bool),
+    AddRect(Rect<f32>),
+    Fill,
+    Stroke,
+    Clip,
+    SetFillStyle(FillOrStrokeStyle),
+    SetStrokeStyle(FillOrStrokeStyle),
+    SetLineWidth(f32),
+    SetLineCap(LineCapStyle),
+    SetLineJoin(LineJoinStyle),
+    SetMiterLimit(f32),
+    SetGlobalAlpha(f32),
+    SetTransform(Matrix2D<f32>),
+    SetFont(Arc<SpecifiedFontStyle>),
+    SetTextAlign(TextAlign),
+    SetTextBaseline(TextBaseline),
+    SaveContext,
+    RestoreContext,
+    /// The text, the point it is anchored at and the maximum width to squeeze it into.
+    FillText(String, Point2D<f32>, Option<f32>),
+    MeasureText(String, Sender<f32>),
+    /// Pixels in the format of the canvas, the size of the image they make up, and the source
+    /// and destination rectangles.
+    DrawImage(Vec<u8>, Size2D<i32>, Rect<f32>, Rect<f32>),
+    /// Draws part of the canvas onto itself, from the source to the destination rectangle.
+    DrawImageSelf(Rect<f32>, Rect<f32>),
+    /// Replies with the pixels of the given rectangle of the canvas, as non-premultiplied RGBA.
+    GetImageData(Rect<i32>, Sender<Vec<u8>>),
+    /// Replaces the pixels of the given rectangle with the given non-premultiplied RGBA pixels,
+    /// regardless of the drawing state.
+    PutImageData(Vec<u8>, Rect<i32>),
+    /// Replies with all the pixels of the canvas, in the format used by `DrawImage`.
+    SendPixelContents(Sender<Vec<u8>>),
     Recreate(Size2D<i32>),
     Close,
 }
 
+/// A segment of the current path, in the coordinate space of the canvas: points are transformed by
+/// the transform in effect when they are added. Arcs are approximated by Bézier curves, since an
+/// arc under a transform is not necessarily circular.
+#[deriving(Clone)]
+enum PathSegment {
+    MoveToSegment(Point2D<AzFloat>),
+    LineToSegment(Point2D<AzFloat>),
+    QuadraticCurveToSegment(Point2D<AzFloat>, Point2D<AzFloat>),
+    BezierCurveToSegment(Point2D<AzFloat>, Point2D<AzFloat>, Point2D<AzFloat>),
+    ClosePathSegment,
+}
+
+/// The drawing state that `save()` pushes and `restore()` pops. HTML § 4.12.4.1.
+#[deriving(Clone)]
+struct CanvasRenderState {
+    fill_style: FillOrStrokeStyle,
+    stroke_style: FillOrStrokeStyle,
+    line_width: f32,
+    line_cap: LineCapStyle,
+    line_join: LineJoinStyle,
+    miter_limit: f32,
+    global_alpha: f32,
+    transform: Matrix2D<AzFloat>,
+    font_style: Option<Arc<SpecifiedFontStyle>>,
+    text_align: TextAlign,
+    text_baseline: TextBaseline,
+    /// The number of clips pushed onto the draw target since the state was saved.
+    clip_count: uint,
+}
+
+impl CanvasRenderState {
+    fn new() -> CanvasRenderState {
+        let black = RGBA { red: 0., green: 0., blue: 0., alpha: 1. };
+        CanvasRenderState {
+            fill_style: ColorStyle(black),
+            stroke_style: ColorStyle(black),
+            line_width: 1.0,
+            line_cap: ButtCap,
+            line_join: MiterJoin,
+            miter_limit: 10.0,
+            global_alpha: 1.0,
+            transform: Matrix2D::identity(),
+            font_style: None,
+            text_align: StartAlign,
+            text_baseline: AlphabeticBaseline,
+            clip_count: 0,
+        }
+    }
+}
+
+/// An Azure pattern made from a fill or stroke style.
+enum CanvasPattern {
+    CanvasColorPattern(ColorPattern),
+    CanvasLinearGradientPattern(LinearGradientPattern),
+    CanvasRadialGradientPattern(RadialGradientPattern),
+}
+
+impl CanvasPattern {
+    fn as_pattern_ref<'a>(&'a self) -> PatternRef<'a> {
+        match *self {
+            CanvasColorPattern(ref pattern) => ColorPatternRef(pattern),
+            CanvasLinearGradientPattern(ref pattern) => LinearGradientPatternRef(pattern),
+            CanvasRadialGradientPattern(ref pattern) => RadialGradientPatternRef(pattern),
+        }
+    }
+}
+
 pub struct CanvasRenderTask {
     drawtarget: DrawTarget,
-    fill_color: ColorPattern,
-    stroke_color: ColorPattern,
-    stroke_opts: StrokeOptions,
+    size: Size2D<i32>,
+    font_context: FontContext,
+    state: CanvasRenderState,
+    saved_states: Vec<CanvasRenderState>,
+    path: Vec<PathSegment>,
 }
 
 impl CanvasRenderTask {
-    fn new(size: Size2D<i32>) -> CanvasRenderTask {
+    fn new(size: Size2D<i32>, font_cache_task: FontCacheTask) -> CanvasRenderTask {
         CanvasRenderTask {
             drawtarget: CanvasRenderTask::create(size),
-            fill_color: ColorPattern::new(Color::new(0., 0., 0., 1.)),
-            stroke_color: ColorPattern::new(Color::new(0., 0., 0., 1.)),
-            stroke_opts: StrokeOptions::new(1.0, 1.0),
+            size: size,
+            font_context: FontContext::new(font_cache_task),
+            state: CanvasRenderState::new(),
+            saved_states: vec!(),
+            path: vec!(),
         }
     }
 
-    pub fn start(size: Size2D<i32>) -> Sender<CanvasMsg> {
+    pub fn start(size: Size2D<i32>, font_cache_task: FontCacheTask) -> Sender<CanvasMsg> {
         let (chan, port) = comm::channel::<CanvasMsg>();
         spawn_named("CanvasTask", proc() {
-            let mut renderer = CanvasRenderTask::new(size);
+            let mut renderer = CanvasRenderTask::new(size, font_cache_task);
 
             loop {
                 match port.recv() {
                     FillRect(ref rect) => renderer.fill_rect(rect),
                     StrokeRect(ref rect) => renderer.stroke_rect(rect),
                     ClearRect(ref rect) => renderer.clear_rect(rect),
+                    BeginPath => renderer.path.clear(),
+                    ClosePath => renderer.path.push(ClosePathSegment),
+                    MoveTo(point) => renderer.move_to(point),
+                    LineTo(point) => renderer.line_to(point),
+                    QuadraticCurveTo(control_point, point) => {
+                        renderer.quadratic_curve_to(control_point, point)
+                    }
+                    BezierCurveTo(control_point_1, control_point_2, point) => {
+                        renderer.bezier_curve_to(control_point_1, control_point_2, point)
+                    }
+                    AddArc(center, radius, start_angle, end_angle, anticlockwise) => {
+                        renderer.add_arc(center, radius, start_angle, end_angle, anticlockwise)
+                    }
+                    AddRect(ref rect) => renderer.add_rect(rect),
+                    Fill => renderer.fill(),
+                    Stroke => renderer.stroke(),
+                    Clip => renderer.clip(),
+                    SetFillStyle(style) => renderer.state.fill_style = style,
+                    SetStrokeStyle(style) => renderer.state.stroke_style = style,
+                    SetLineWidth(width) => renderer.state.line_width = width,
+                    SetLineCap(cap) => renderer.state.line_cap = cap,
+                    SetLineJoin(join) => renderer.state.line_join = join,
+                    SetMiterLimit(limit) => renderer.state.miter_limit = limit,
+                    SetGlobalAlpha(alpha) => renderer.state.global_alpha = alpha,
+                    SetTransform(ref transform) => renderer.set_transform(transform),
+                    SetFont(font_style) => renderer.state.font_style = Some(font_style),
+                    SetTextAlign(text_align) => renderer.state.text_align = text_align,
+                    SetTextBaseline(baseline) => renderer.state.text_baseline = baseline,
+                    SaveContext => renderer.save_context(),
+                    RestoreContext => renderer.restore_context(),
+                    FillText(text, ref origin, max_width) => {
+                        renderer.fill_text(text, origin, max_width)
+                    }
+                    MeasureText(text, chan) => chan.send(renderer.measure_text(text)),
+                    DrawImage(pixels, image_size, ref source_rect, ref dest_rect) => {
+                        renderer.draw_image(pixels.as_slice(), image_size, source_rect, dest_rect)
+                    }
+                    DrawImageSelf(ref source_rect, ref dest_rect) => {
+                        renderer.draw_image_self(source_rect, dest_rect)
+                    }
+                    GetImageData(ref rect, chan) => chan.send(renderer.get_image_data(rect)),
+                    PutImageData(pixels, ref rect) => renderer.put_image_data(pixels, rect),
+                    SendPixelContents(chan) => chan.send(renderer.pixel_contents()),
                     Recreate(size) => renderer.recreate(size),
                     Close => break,
                 }
@@ -53,9 +387,106 @@ impl CanvasRenderTask {
         chan
     }
 
+    fn draw_options(&self) -> DrawOptions {
+        DrawOptions::new(self.state.global_alpha as AzFloat, 0)
+    }
+
+    fn stroke_options(&self) -> StrokeOptions {
+        let mut stroke_opts = StrokeOptions::new(self.state.line_width as AzFloat,
+                                                 self.state.miter_limit as AzFloat);
+        stroke_opts.set_cap_style(match self.state.line_cap {
+            ButtCap => AZ_CAP_BUTT,
+            RoundCap => AZ_CAP_ROUND,
+            SquareCap => AZ_CAP_SQUARE,
+        } as u8);
+        stroke_opts.set_join_style(match self.state.line_join {
+            RoundJoin => AZ_JOIN_ROUND,
+            BevelJoin => AZ_JOIN_BEVEL,
+            MiterJoin => AZ_JOIN_MITER_OR_BEVEL,
+        } as u8);
+        stroke_opts
+    }
+
+    /// Returns the Azure pattern for the given style.
+    fn pattern(&self, style: &FillOrStrokeStyle) -> CanvasPattern {
+        match *style {
+            ColorStyle(ref color) => CanvasColorPattern(ColorPattern::new(to_azure_color(color))),
+            LinearGradientStyle(ref gradient) => {
+                let stops = self.gradient_stops(gradient.stops.as_slice());
+                CanvasLinearGradientPattern(LinearGradientPattern::new(
+                    &Point2D(gradient.start.x as AzFloat, gradient.start.y as AzFloat),
+                    &Point2D(gradient.end.x as AzFloat, gradient.end.y as AzFloat),
+                    stops,
+                    &Matrix2D::identity()))
+            }
+            RadialGradientStyle(ref gradient) => {
+                let stops = self.gradient_stops(gradient.stops.as_slice());
+                CanvasRadialGradientPattern(RadialGradientPattern::new(
+                    &Point2D(gradient.start_center.x as AzFloat,
+                             gradient.start_center.y as AzFloat),
+                    &Point2D(gradient.end_center.x as AzFloat, gradient.end_center.y as AzFloat),
+                    gradient.start_radius as AzFloat,
+                    gradient.end_radius as AzFloat,
+                    stops,
+                    &Matrix2D::identity()))
+            }
+        }
+    }
+
+    fn gradient_stops(&self, stops: &[CanvasGradientStop]) -> GradientStops {
+        let stops: Vec<GradientStop> = stops.iter().map(|stop| {
+            GradientStop {
+                offset: stop.offset as AzFloat,
+                color: to_azure_color(&stop.color),
+            }
+        }).collect();
+        self.drawtarget.create_gradient_stops(stops.as_slice(), ExtendClamp)
+    }
+
+    /// Paints shapes with the given style. `paint` draws the shapes in the color and with the
+    /// alpha that it is given onto the draw target that it is given, whose transform is the given
+    /// one. Azure can only stroke paths and draw glyphs with a solid color, so for gradients the
+    /// shapes are painted into a mask, which only the parts of the gradient inside them are kept
+    /// from.
+    fn paint_with_style(&self,
+                        style: &FillOrStrokeStyle,
+                        transform: &Matrix2D<AzFloat>,
+                        paint: |&DrawTarget, Color, AzFloat|) {
+        match *style {
+            ColorStyle(ref color) => {
+                self.drawtarget.set_transform(transform);
+                paint(&self.drawtarget, to_azure_color(color), self.state.global_alpha as AzFloat);
+            }
+            _ => {
+                let mask = CanvasRenderTask::create(self.size);
+                mask.set_transform(transform);
+                paint(&mask, Color::new(1.0, 1.0, 1.0, 1.0), 1.0);
+
+                let pattern = self.pattern(style);
+                let mut draw_options = DrawOptions::new(1.0, 0);
+                draw_options.set_composition_op(InOp);
+                mask.set_transform(&self.state.transform);
+                mask.fill_rect(&self.canvas_rect_in_user_space(),
+                               pattern.as_pattern_ref(),
+                               Some(&draw_options));
+
+                let canvas_rect = Rect(Point2D(0.0, 0.0),
+                                       Size2D(self.size.width as AzFloat,
+                                              self.size.height as AzFloat));
+                self.drawtarget.set_transform(&Matrix2D::identity());
+                self.drawtarget.draw_surface(mask.snapshot(),
+                                             canvas_rect,
+                                             canvas_rect,
+                                             DrawSurfaceOptions::new(Linear, true),
+                                             self.draw_options());
+            }
+        }
+        self.drawtarget.set_transform(&self.state.transform);
+    }
+
     fn fill_rect(&self, rect: &Rect<f32>) {
-        let drawopts = DrawOptions::new(1.0, 0);
-        self.drawtarget.fill_rect(rect, ColorPatternRef(&self.fill_color), Some(&drawopts));
+        let pattern = self.pattern(&self.state.fill_style);
+        self.drawtarget.fill_rect(rect, pattern.as_pattern_ref(), Some(&self.draw_options()));
     }
 
     fn clear_rect(&self, rect: &Rect<f32>) {
@@ -63,8 +494,380 @@ impl CanvasRenderTask {
     }
 
     fn stroke_rect(&self, rect: &Rect<f32>) {
-        let drawopts = DrawOptions::new(1.0, 0);
-        self.drawtarget.stroke_rect(rect, &self.stroke_color, &self.stroke_opts, &drawopts);
+        let stroke_options = self.stroke_options();
+        let transform = self.state.transform;
+        self.paint_with_style(&self.state.stroke_style, &transform, |draw_target, color, alpha| {
+            draw_target.stroke_rect(rect,
+                                    &ColorPattern::new(color),
+                                    &stroke_options,
+                                    &DrawOptions::new(alpha, 0))
+        })
+    }
+
+    fn move_to(&mut self, point: Point2D<f32>) {
+        let point = transform_point(&self.state.transform, point);
+        self.path.push(MoveToSegment(point))
+    }
+
+    fn line_to(&mut self, point: Point2D<f32>) {
+        let point = transform_point(&self.state.transform, point);
+        self.path.push(LineToSegment(point))
+    }
+
+    fn quadratic_curve_to(&mut self, control_point: Point2D<f32>, point: Point2D<f32>) {
+        let transform = &self.state.transform;
+        self.path.push(QuadraticCurveToSegment(transform_point(transform, control_point),
+                                               transform_point(transform, point)))
+    }
+
+    fn bezier_curve_to(&mut self,
+                       control_point_1: Point2D<f32>,
+                       control_point_2: Point2D<f32>,
+                       point: Point2D<f32>) {
+        let transform = &self.state.transform;
+        self.path.push(BezierCurveToSegment(transform_point(transform, control_point_1),
+                                            transform_point(transform, control_point_2),
+                                            transform_point(transform, point)))
+    }
+
+    fn add_arc(&mut self,
+               center: Point2D<f32>,
+               radius: f32,
+               start_angle: f32,
+               end_angle: f32,
+               anticlockwise: bool) {
+        // An arc sweeps a full circle at most. HTML § 4.12.4.1.10.
+        let sweep = if anticlockwise {
+            if start_angle - end_angle >= PI_2 {
+                -PI_2
+            } else {
+                -positive_remainder(start_angle - end_angle, PI_2)
+            }
+        } else if end_angle - start_angle >= PI_2 {
+            PI_2
+        } else {
+            positive_remainder(end_angle - start_angle, PI_2)
+        };
+        let point_at = |angle: f32| {
+            Point2D(center.x + radius * angle.cos(), center.y + radius * angle.sin())
+        };
+
+        // The arc is connected to the previous point of the path by a straight line.
+        if self.path.is_empty() {
+            self.move_to(point_at(start_angle))
+        } else {
+            self.line_to(point_at(start_angle))
+        }
+
+        // Approximate the arc by a Bézier curve for every quarter circle or part of one.
+        let segment_count = (sweep.abs() / FRAC_PI_2).ceil().max(1.0);
+        let segment_sweep = sweep / segment_count;
+        let handle_length = radius * 4.0 / 3.0 * (segment_sweep / 4.0).tan();
+        for i in range(0, segment_count as uint) {
+            let (angle_1, angle_2) = (start_angle + segment_sweep * i as f32,
+                                      start_angle + segment_sweep * (i + 1) as f32);
+            let (point_1, point_2) = (point_at(angle_1), point_at(angle_2));
+            let control_point_1 = Point2D(point_1.x - handle_length * angle_1.sin(),
+                                          point_1.y + handle_length * angle_1.cos());
+            let control_point_2 = Point2D(point_2.x + handle_length * angle_2.sin(),
+                                          point_2.y - handle_length * angle_2.cos());
+            self.bezier_curve_to(control_point_1, control_point_2, point_2)
+        }
+    }
+
+    fn add_rect(&mut self, rect: &Rect<f32>) {
+        let (x, y) = (rect.origin.x, rect.origin.y);
+        let (width, height) = (rect.size.width, rect.size.height);
+        self.move_to(Point2D(x, y));
+        self.line_to(Point2D(x + width, y));
+        self.line_to(Point2D(x + width, y + height));
+        self.line_to(Point2D(x, y + height));
+        self.path.push(ClosePathSegment);
+        self.move_to(Point2D(x, y));
+    }
+
+    /// Builds an Azure path out of the segments of the current path, mapping their points
+    /// through the given transform.
+    fn build_path(&self, transform: &Matrix2D<AzFloat>) -> Path {
+        let path_builder = self.drawtarget.create_path_builder();
+        for segment in self.path.iter() {
+            match *segment {
+                MoveToSegment(point) => path_builder.move_to(transform_point(transform, point)),
+                LineToSegment(point) => path_builder.line_to(transform_point(transform, point)),
+                QuadraticCurveToSegment(control_point, point) => {
+                    path_builder.quadratic_curve_to(&transform_point(transform, control_point),
+                                                    &transform_point(transform, point))
+                }
+                BezierCurveToSegment(control_point_1, control_point_2, point) => {
+                    path_builder.bezier_curve_to(&transform_point(transform, control_point_1),
+                                                 &transform_point(transform, control_point_2),
+                                                 &transform_point(transform, point))
+                }
+                ClosePathSegment => path_builder.close(),
+            }
+        }
+        path_builder.finish()
+    }
+
+    fn fill(&self) {
+        // The path is already in the coordinate space of the canvas.
+        let path = self.build_path(&Matrix2D::identity());
+        self.drawtarget.set_transform(&Matrix2D::identity());
+        match self.state.fill_style {
+            ColorStyle(ref color) => {
+                let pattern = ColorPattern::new(to_azure_color(color));
+                self.drawtarget.fill(&path, &pattern, &self.draw_options());
+            }
+            _ => {
+                // Gradients can only fill rectangles, so clip one to the path.
+                let pattern = self.pattern(&self.state.fill_style);
+                self.drawtarget.push_clip(&path);
+                self.drawtarget.set_transform(&self.state.transform);
+                self.drawtarget.fill_rect(&self.canvas_rect_in_user_space(),
+                                          pattern.as_pattern_ref(),
+                                          Some(&self.draw_options()));
+                self.drawtarget.pop_clip();
+            }
+        }
+        self.drawtarget.set_transform(&self.state.transform);
+    }
+
+    fn stroke(&self) {
+        // The line width is in the current coordinate space, so the path is stroked in it.
+        let inverse_transform = match invert(&self.state.transform) {
+            Some(inverse_transform) => inverse_transform,
+            None => return,
+        };
+        let path = self.build_path(&inverse_transform);
+        let stroke_options = self.stroke_options();
+        let transform = self.state.transform;
+        self.paint_with_style(&self.state.stroke_style, &transform, |draw_target, color, alpha| {
+            draw_target.stroke(&path,
+                               &ColorPattern::new(color),
+                               &stroke_options,
+                               &DrawOptions::new(alpha, 0))
+        })
+    }
+
+    fn clip(&mut self) {
+        let path = self.build_path(&Matrix2D::identity());
+        self.drawtarget.set_transform(&Matrix2D::identity());
+        self.drawtarget.push_clip(&path);
+        self.drawtarget.set_transform(&self.state.transform);
+        self.state.clip_count += 1;
+    }
+
+    fn set_transform(&mut self, transform: &Matrix2D<f32>) {
+        self.state.transform = *transform;
+        self.drawtarget.set_transform(transform)
+    }
+
+    /// Returns a rectangle in the current coordinate space that covers the whole canvas.
+    fn canvas_rect_in_user_space(&self) -> Rect<AzFloat> {
+        let inverse_transform = match invert(&self.state.transform) {
+            Some(inverse_transform) => inverse_transform,
+            None => return Rect(Point2D(0.0, 0.0), Size2D(0.0, 0.0)),
+        };
+
+        // Map the corners of the canvas through the inverse of the current transform.
+        let (width, height) = (self.size.width as AzFloat, self.size.height as AzFloat);
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)];
+        let (mut min_x, mut min_y) = (Float::infinity(), Float::infinity());
+        let (mut max_x, mut max_y) = (Float::neg_infinity(), Float::neg_infinity());
+        for &(x, y) in corners.iter() {
+            let corner = transform_point(&inverse_transform, Point2D(x, y));
+            min_x = min_x.min(corner.x);
+            min_y = min_y.min(corner.y);
+            max_x = max_x.max(corner.x);
+            max_y = max_y.max(corner.y);
+        }
+        Rect(Point2D(min_x, min_y), Size2D(max_x - min_x, max_y - min_y))
+    }
+
+    fn save_context(&mut self) {
+        self.saved_states.push(self.state.clone());
+        self.state.clip_count = 0;
+    }
+
+    fn restore_context(&mut self) {
+        let state = match self.saved_states.pop() {
+            Some(state) => state,
+            None => return,
+        };
+        for _ in range(0, self.state.clip_count) {
+            self.drawtarget.pop_clip();
+        }
+        self.state = state;
+        self.drawtarget.set_transform(&self.state.transform);
+    }
+
+    /// Shapes the given text with the current font.
+    fn shape_text(&mut self, text: String) -> Option<Box<TextRun>> {
+        let font_style = match self.state.font_style {
+            Some(ref font_style) => font_style.clone(),
+            None => return None,
+        };
+        let font_group = self.font_context.get_layout_font_group_for_style(font_style);
+        if font_group.fonts.len() == 0 {
+            return None
+        }
+        Some(box font_group.create_textrun(text, word_break::normal, 0))
+    }
+
+    fn fill_text(&mut self, text: String, origin: &Point2D<f32>, max_width: Option<f32>) {
+        let run = match self.shape_text(text) {
+            Some(run) => run,
+            None => return,
+        };
+        let range = Range::new(CharIndex(0), run.char_len());
+        let advance = run.advance_for_range(&range).to_subpx() as f32;
+
+        // FIXME: `start` and `end` should depend on the direction of the canvas element.
+        let x = match self.state.text_align {
+            StartAlign | LeftAlign => origin.x,
+            EndAlign | RightAlign => origin.x - advance,
+            CenterAlign => origin.x - advance / 2.0,
+        };
+        let ascent = run.ascent().to_subpx() as f32;
+        let descent = run.descent().to_subpx() as f32;
+        let y = match self.state.text_baseline {
+            TopBaseline | HangingBaseline => origin.y + ascent,
+            MiddleBaseline => origin.y + (ascent - descent) / 2.0,
+            AlphabeticBaseline => origin.y,
+            IdeographicBaseline | BottomBaseline => origin.y - descent,
+        };
+
+        // Text wider than the maximum width is squeezed horizontally around its start.
+        let transform = match max_width {
+            Some(max_width) if max_width <= 0.0 => return,
+            Some(max_width) if advance > max_width => {
+                let scale = max_width / advance;
+                let squeeze = Matrix2D::new(scale, 0.0, 0.0, 1.0, x - x * scale, 0.0);
+                self.state.transform.mul(&squeeze)
+            }
+            _ => self.state.transform,
+        };
+
+        let render_font = self.font_context.get_render_font_from_template(&run.font_template,
+                                                                          run.actual_pt_size);
+        let origin = Point2D(Au::from_frac32_px(x), Au::from_frac32_px(y));
+        self.paint_with_style(&self.state.fill_style, &transform, |draw_target, color, alpha| {
+            let mut color = color;
+            color.a = color.a * alpha;
+            render_font.borrow().draw_text_into_context(draw_target,
+                                                        &run,
+                                                        &range,
+                                                        origin,
+                                                        color,
+                                                        opts::get().enable_text_antialiasing)
+        })
+    }
+
+    /// Returns the advance of the given text in the current font, in CSS pixels.
+    fn measure_text(&mut self, text: String) -> f32 {
+        match self.shape_text(text) {
+            Some(run) => {
+                let range = Range::new(CharIndex(0), run.char_len());
+                run.advance_for_range(&range).to_subpx() as f32
+            }
+            None => 0.0,
+        }
+    }
+
+    fn draw_image(&self,
+                  pixels: &[u8],
+                  image_size: Size2D<i32>,
+                  source_rect: &Rect<f32>,
+                  dest_rect: &Rect<f32>) {
+        let source_surface = self.drawtarget.create_source_surface_from_data(pixels,
+                                                                             image_size,
+                                                                             image_size.width * 4,
+                                                                             B8G8R8A8);
+        let draw_surface_options = DrawSurfaceOptions::new(Linear, true);
+        self.drawtarget.draw_surface(source_surface,
+                                     *dest_rect,
+                                     *source_rect,
+                                     draw_surface_options,
+                                     self.draw_options());
+    }
+
+    fn draw_image_self(&self, source_rect: &Rect<f32>, dest_rect: &Rect<f32>) {
+        // Draw from a copy, since the source and destination may overlap.
+        let pixels = self.pixel_contents();
+        self.draw_image(pixels.as_slice(), self.size, source_rect, dest_rect)
+    }
+
+    /// Returns the pixels of the canvas as premultiplied BGRA, one row after the other.
+    fn pixel_contents(&self) -> Vec<u8> {
+        let mut pixels = vec!();
+        self.drawtarget.snapshot().get_data_surface().with_data(|data| {
+            pixels.push_all(data);
+        });
+        pixels
+    }
+
+    fn get_image_data(&self, rect: &Rect<i32>) -> Vec<u8> {
+        let pixels = self.pixel_contents();
+        let mut image_data = Vec::from_elem((rect.size.width * rect.size.height * 4) as uint, 0u8);
+        for y in range(0, rect.size.height) {
+            let canvas_y = rect.origin.y + y;
+            if canvas_y < 0 || canvas_y >= self.size.height {
+                continue
+            }
+            for x in range(0, rect.size.width) {
+                let canvas_x = rect.origin.x + x;
+                if canvas_x < 0 || canvas_x >= self.size.width {
+                    continue
+                }
+                let source = ((canvas_y * self.size.width + canvas_x) * 4) as uint;
+                let dest = ((y * rect.size.width + x) * 4) as uint;
+                let alpha = pixels[source + 3];
+                let unpremultiply = |value: u8| {
+                    if alpha == 0 {
+                        0
+                    } else {
+                        min((value as u32 * 255 + alpha as u32 / 2) / alpha as u32, 255) as u8
+                    }
+                };
+                image_data[dest] = unpremultiply(pixels[source + 2]);
+                image_data[dest + 1] = unpremultiply(pixels[source + 1]);
+                image_data[dest + 2] = unpremultiply(pixels[source]);
+                image_data[dest + 3] = alpha;
+            }
+        }
+        image_data
+    }
+
+    fn put_image_data(&self, mut pixels: Vec<u8>, rect: &Rect<i32>) {
+        // Convert to premultiplied BGRA.
+        for pixel in pixels.as_mut_slice().chunks_mut(4) {
+            let alpha = pixel[3] as u32;
+            let premultiply = |value: u8| ((value as u32 * alpha + 127) / 255) as u8;
+            let (red, green, blue) = (pixel[0], pixel[1], pixel[2]);
+            pixel[0] = premultiply(blue);
+            pixel[1] = premultiply(green);
+            pixel[2] = premultiply(red);
+        }
+
+        let source_surface = self.drawtarget.create_source_surface_from_data(pixels.as_slice(),
+                                                                             rect.size,
+                                                                             rect.size.width * 4,
+                                                                             B8G8R8A8);
+        let source_rect = Rect(Point2D(0.0, 0.0),
+                               Size2D(rect.size.width as AzFloat, rect.size.height as AzFloat));
+        let dest_rect = Rect(Point2D(rect.origin.x as AzFloat, rect.origin.y as AzFloat),
+                             source_rect.size);
+
+        // FIXME: The pixels should not be clipped either.
+        let mut draw_options = DrawOptions::new(1.0, 0);
+        draw_options.set_composition_op(SourceOp);
+        self.drawtarget.set_transform(&Matrix2D::identity());
+        self.drawtarget.draw_surface(source_surface,
+                                     dest_rect,
+                                     source_rect,
+                                     DrawSurfaceOptions::new(Linear, true),
+                                     draw_options);
+        self.drawtarget.set_transform(&self.state.transform);
     }
 
     fn create(size: Size2D<i32>) -> DrawTarget {
@@ -72,6 +875,48 @@ impl CanvasRenderTask {
     }
 
     fn recreate(&mut self, size: Size2D<i32>) {
+        // Resizing a canvas resets its drawing state, as well as its pixels.
         self.drawtarget = CanvasRenderTask::create(size);
+        self.size = size;
+        let font_style = self.state.font_style.take();
+        self.state = CanvasRenderState::new();
+        self.state.font_style = font_style;
+        self.saved_states.clear();
+        self.path.clear();
+    }
+}
+
+/// Maps the given point through the given transform.
+fn transform_point(transform: &Matrix2D<AzFloat>, point: Point2D<AzFloat>) -> Point2D<AzFloat> {
+    let Matrix2D { m11: a, m12: b, m21: c, m22: d, m31: e, m32: f } = *transform;
+    Point2D(a * point.x + c * point.y + e, b * point.x + d * point.y + f)
+}
+
+/// Returns the inverse of the given transform, or `None` if it has none.
+fn invert(transform: &Matrix2D<AzFloat>) -> Option<Matrix2D<AzFloat>> {
+    let Matrix2D { m11: a, m12: b, m21: c, m22: d, m31: e, m32: f } = *transform;
+    let determinant = a * d - b * c;
+    if determinant == 0.0 {
+        return None
     }
+    Some(Matrix2D::new(d / determinant,
+                       -b / determinant,
+                       -c / determinant,
+                       a / determinant,
+                       (c * f - d * e) / determinant,
+                       (b * e - a * f) / determinant))
+}
+
+/// Returns the remainder of dividing the given value by the given divisor, which is never
+/// negative.
+fn positive_remainder(value: f32, divisor: f32) -> f32 {
+    let remainder = value % divisor;
+    if remainder < 0.0 { remainder + divisor } else { remainder }
+}
+
+fn to_azure_color(color: &RGBA) -> Color {
+    Color::new(color.red as AzFloat,
+               color.green as AzFloat,
+               color.blue as AzFloat,
+               color.alpha as AzFloat)
 }
@@ -7,6 +7,10 @@
 
 extern crate azure;
 extern crate geom;
+extern crate gfx;
+extern crate style;
+extern crate sync;
 extern crate "util" as servo_util;
 
 pub mod canvas_render_task;
+pub mod png_encoder;
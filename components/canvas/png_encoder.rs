@@ -0,0 +1,162 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A minimal PNG encoder for `HTMLCanvasElement.toDataURL()`.
+//!
+//! The image data is stored in uncompressed deflate blocks, which keeps the encoder small at the
+//! cost of larger files.
+
+use geom::size::Size2D;
+
+static PNG_SIGNATURE: [u8, ..8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// The largest amount of data a stored deflate block can hold.
+static MAX_STORED_BLOCK_LENGTH: uint = 65535;
+
+/// Encodes the given non-premultiplied RGBA pixels, one row after the other, as a PNG image.
+pub fn encode_png(size: Size2D<i32>, pixels: &[u8]) -> Vec<u8> {
+    let (width, height) = (size.width as uint, size.height as uint);
+    assert!(pixels.len() == width * height * 4);
+
+    let mut png = PNG_SIGNATURE.to_vec();
+
+    let mut header = vec!();
+    push_u32(&mut header, width as u32);
+    push_u32(&mut header, height as u32);
+    // A bit depth of 8, truecolor with alpha, and the only compression, filter and interlace
+    // methods there are.
+    header.push_all(&[8, 6, 0, 0, 0]);
+    push_chunk(&mut png, b"IHDR", header.as_slice());
+
+    // Every scanline starts with its filter type, which is always "none" here.
+    let mut scanlines = Vec::with_capacity(height * (width * 4 + 1));
+    for row in pixels.chunks(width * 4) {
+        scanlines.push(0);
+        scanlines.push_all(row);
+    }
+    push_chunk(&mut png, b"IDAT", zlib_store(scanlines.as_slice()).as_slice());
+
+    push_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.push((value >> 24) as u8);
+    buffer.push((value >> 16) as u8);
+    buffer.push((value >> 8) as u8);
+    buffer.push(value as u8);
+}
+
+fn push_chunk(png: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+    push_u32(png, data.len() as u32);
+    let start = png.len();
+    png.push_all(chunk_type);
+    png.push_all(data);
+    let crc = crc32(png.slice_from(start));
+    push_u32(png, crc);
+}
+
+/// Wraps the given data in a zlib stream made of stored deflate blocks.
+fn zlib_store(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32K window and no preset dictionary; the check bits make the header a
+    // multiple of 31.
+    let mut stream = vec!(0x78, 0x01);
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK_LENGTH).peekable();
+    if blocks.peek().is_none() {
+        stream.push_all(&[1, 0, 0, 0xff, 0xff]);
+    }
+    loop {
+        let block = match blocks.next() {
+            Some(block) => block,
+            None => break,
+        };
+        let is_final = blocks.peek().is_none();
+        let length = block.len() as u16;
+        stream.push(if is_final { 1 } else { 0 });
+        stream.push(length as u8);
+        stream.push((length >> 8) as u8);
+        stream.push(!length as u8);
+        stream.push((!length >> 8) as u8);
+        stream.push_all(block);
+    }
+
+    push_u32(&mut stream, adler32(data));
+    stream
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &byte in data.iter() {
+        crc = crc ^ byte as u32;
+        for _ in range(0u, 8) {
+            crc = if crc & 1 != 0 { 0xedb88320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data.iter() {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::size::Size2D;
+    use super::{adler32, crc32, encode_png, zlib_store, MAX_STORED_BLOCK_LENGTH};
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"abc"), 0x024d0127);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn test_zlib_store() {
+        assert_eq!(zlib_store(b""), vec!(0x78, 0x01, 1, 0, 0, 0xff, 0xff, 0, 0, 0, 1));
+        assert_eq!(zlib_store(b"abc"),
+                   vec!(0x78, 0x01, 1, 3, 0, 0xfc, 0xff, b'a', b'b', b'c', 0x02, 0x4d, 0x01, 0x27));
+
+        // Data that doesn't fit in one stored block is split, and only the last block is final.
+        let data = Vec::from_elem(MAX_STORED_BLOCK_LENGTH + 1, 7u8);
+        let stream = zlib_store(data.as_slice());
+        assert_eq!(stream.len(), 2 + 5 + MAX_STORED_BLOCK_LENGTH + 5 + 1 + 4);
+        assert_eq!(stream.slice(2, 7), [0, 0xff, 0xff, 0, 0].as_slice());
+        let second_block = 7 + MAX_STORED_BLOCK_LENGTH;
+        assert_eq!(stream.slice(second_block, second_block + 6),
+                   [1, 1, 0, 0xfe, 0xff, 7].as_slice());
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let png = encode_png(Size2D(1, 1), &[255, 0, 0, 255]);
+        assert_eq!(png.slice_to(8), [137, 80, 78, 71, 13, 10, 26, 10].as_slice());
+        assert_eq!(png.slice(8, 33),
+                   [0, 0, 0, 13, b'I', b'H', b'D', b'R', 0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0,
+                    0x1f, 0x15, 0xc4, 0x89].as_slice());
+
+        // The image data is one scanline: the filter type and the pixel.
+        let idat = zlib_store(&[0, 255, 0, 0, 255]);
+        assert_eq!(png.slice(33, 41),
+                   [0, 0, 0, idat.len() as u8, b'I', b'D', b'A', b'T'].as_slice());
+        assert_eq!(png.slice(41, 41 + idat.len()), idat.as_slice());
+
+        let iend = [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82];
+        assert_eq!(png.slice_from(png.len() - 12), iend.as_slice());
+        assert_eq!(png.len(), 33 + 12 + idat.len() + 12);
+    }
+}
@@ -4,15 +4,18 @@
 
 use compositor_layer::{CompositorData, CompositorLayer, DoesntWantScrollEvents};
 use compositor_layer::WantsScrollEvents;
-use compositor_task::{ChangeReadyState, ChangeRenderState, CompositorEventListener};
+use compositor_task::{ChangeReadyState, ChangeRenderState, ChangeRunningAnimationsState};
+use compositor_task::{CompositorEventListener};
 use compositor_task::{CompositorProxy, CompositorReceiver, CompositorTask};
 use compositor_task::{CreateOrUpdateDescendantLayer, CreateOrUpdateRootLayer, Exit};
 use compositor_task::{FrameTreeUpdateMsg, GetGraphicsMetadata, LayerProperties};
 use compositor_task::{LoadComplete, Msg, Paint, RenderMsgDiscarded, ScrollFragmentPoint};
-use compositor_task::{ScrollTimeout, SetIds, SetLayerOrigin, ShutdownComplete};
+use compositor_task::{ScrollTimeout, SetIds, SetLayerOrigin, SetScrollPosition};
+use compositor_task::{ShutdownComplete, VsyncTick};
 use constellation::{SendableFrameTree, FrameTreeDiff};
 use pipeline::CompositionPipeline;
 use scrolling::ScrollingTimerProxy;
+use vsync::VsyncTimerProxy;
 use windowing;
 use windowing::{IdleWindowEvent, LoadUrlWindowEvent, MouseWindowClickEvent};
 use windowing::{MouseWindowEvent, MouseWindowEventClass, MouseWindowMouseDownEvent};
@@ -38,11 +41,14 @@ use layers::scene::Scene;
 use png;
 use gleam::gl::types::{GLint, GLsizei};
 use gleam::gl;
-use script_traits::{ViewportMsg, ScriptControlChan};
+use script_traits::{ViewportMsg, ScriptControlChan, ScrollPositionEvent, SendEventMsg};
 use servo_msg::compositor_msg::{Blank, Epoch, FinishedLoading, IdleRenderState, LayerId};
 use servo_msg::compositor_msg::{ReadyState, RenderingRenderState, RenderState, Scrollable};
+use servo_msg::compositor_msg::{Pagination, Scrollport};
+use servo_msg::constellation_msg::{AnimationCallbacksPresent, AnimationState};
 use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, LoadUrlMsg};
 use servo_msg::constellation_msg::{NavigateMsg, LoadData, PipelineId, ResizedWindowMsg};
+use servo_msg::constellation_msg::{NoAnimationCallbacksPresent, TickAnimationMsg};
 use servo_msg::constellation_msg::{WindowSizeData, KeyState, Key, KeyModifiers};
 use servo_msg::constellation_msg;
 use servo_util::geometry::{PagePx, ScreenPx, ViewportPx};
@@ -50,7 +56,7 @@ use servo_util::memory::MemoryProfilerChan;
 use servo_util::opts;
 use servo_util::time::{profile, TimeProfilerChan};
 use servo_util::{memory, time};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::{Occupied, Vacant};
 use std::path::Path;
 use std::rc::Rc;
@@ -90,6 +96,17 @@ pub struct IOCompositor<Window: WindowMethods> {
     /// A handle to the scrolling timer.
     scrolling_timer: ScrollingTimerProxy,
 
+    /// A handle to the vsync timer, which ticks the pipelines that have animation frame
+    /// callbacks.
+    vsync_timer: VsyncTimerProxy,
+
+    /// The pipelines that have animation frame callbacks waiting for a tick.
+    animating_pipelines: HashSet<PipelineId>,
+
+    /// The pipelines that have been ticked and haven't run their animation frame callbacks yet.
+    /// They aren't ticked again until they have, so that slow callbacks don't pile ticks up.
+    ticking_pipelines: HashSet<PipelineId>,
+
     /// Tracks whether we should composite this frame.
     composition_request: CompositionRequest,
 
@@ -137,6 +154,12 @@ pub struct IOCompositor<Window: WindowMethods> {
 
     /// Pending scroll events.
     pending_scroll_events: Vec<ScrollEvent>,
+
+    /// The pages that the root pipeline is laid out in, if layout is paginated.
+    pagination: Option<Pagination>,
+
+    /// The index of the page that the next output image shows, if layout is paginated.
+    output_page_index: uint,
 }
 
 pub struct ScrollEvent {
@@ -191,6 +214,9 @@ impl<Window: WindowMethods> IOCompositor<Window> {
             }),
             window_size: window_size,
             hidpi_factor: hidpi_factor,
+            vsync_timer: VsyncTimerProxy::new(sender.clone_compositor_proxy()),
+            animating_pipelines: HashSet::new(),
+            ticking_pipelines: HashSet::new(),
             scrolling_timer: ScrollingTimerProxy::new(sender),
             composition_request: NoCompositingNecessary,
             pending_scroll_events: Vec::new(),
@@ -209,6 +235,8 @@ impl<Window: WindowMethods> IOCompositor<Window> {
             fragment_point: None,
             outstanding_render_msgs: 0,
             last_composite_time: 0,
+            pagination: None,
+            output_page_index: 0,
         }
     }
 
@@ -305,6 +333,10 @@ impl<Window: WindowMethods> IOCompositor<Window> {
                 self.scroll_fragment_to_point(pipeline_id, layer_id, point);
             }
 
+            (SetScrollPosition(pipeline_id, layer_id, point), NotShuttingDown) => {
+                self.set_scroll_position(pipeline_id, layer_id, point);
+            }
+
             (LoadComplete(..), NotShuttingDown) => {
                 self.got_load_complete_message = true;
 
@@ -324,6 +356,18 @@ impl<Window: WindowMethods> IOCompositor<Window> {
                 }
             }
 
+            (ChangeRunningAnimationsState(pipeline_id, animation_state), NotShuttingDown) => {
+                self.change_running_animations_state(pipeline_id, animation_state);
+            }
+
+            (VsyncTick, NotShuttingDown) => {
+                let pipelines: Vec<PipelineId> = self.animating_pipelines.iter().map(|&id| id)
+                                                                          .collect();
+                for pipeline_id in pipelines.into_iter() {
+                    self.tick_animations_for_pipeline(pipeline_id);
+                }
+            }
+
             // When we are shutting_down, we need to avoid performing operations
             // such as Paint that may crash because we have begun tearing down
             // the rest of our resources.
@@ -350,6 +394,41 @@ impl<Window: WindowMethods> IOCompositor<Window> {
         }
     }
 
+    fn change_running_animations_state(&mut self,
+                                       pipeline_id: PipelineId,
+                                       animation_state: AnimationState) {
+        // Script sends its state after running its callbacks, which acknowledges the last tick.
+        self.ticking_pipelines.remove(&pipeline_id);
+        match animation_state {
+            AnimationCallbacksPresent => {
+                // If we're rendering to a file, tick right away instead of waiting for the timer,
+                // so that the output doesn't depend on how fast the callbacks run.
+                if opts::get().output_file.is_some() {
+                    self.tick_animations_for_pipeline(pipeline_id);
+                    return;
+                }
+                if self.animating_pipelines.is_empty() {
+                    self.vsync_timer.start();
+                }
+                self.animating_pipelines.insert(pipeline_id);
+            }
+            NoAnimationCallbacksPresent => {
+                if self.animating_pipelines.remove(&pipeline_id) &&
+                        self.animating_pipelines.is_empty() {
+                    self.vsync_timer.stop();
+                }
+            }
+        }
+    }
+
+    fn tick_animations_for_pipeline(&mut self, pipeline_id: PipelineId) {
+        if !self.ticking_pipelines.insert(pipeline_id) {
+            return;
+        }
+        let ConstellationChan(ref chan) = self.constellation_chan;
+        chan.send(TickAnimationMsg(pipeline_id));
+    }
+
     fn get_earliest_pipeline_ready_state(&self) -> ReadyState {
         if self.ready_states.len() == 0 {
             return Blank;
@@ -489,15 +568,80 @@ impl<Window: WindowMethods> IOCompositor<Window> {
             root_layer.children().insert(0, first_child);
         }
 
+        let is_root_pipeline = match self.root_pipeline {
+            Some(ref pipeline) => pipeline.id == layer_properties.pipeline_id,
+            None => false,
+        };
+        if is_root_pipeline && layer_properties.pagination.is_some() {
+            self.set_pagination(layer_properties.pagination);
+        }
+
         self.scroll_layer_to_fragment_point_if_necessary(layer_properties.pipeline_id,
                                                          layer_properties.id);
         self.send_buffer_requests_for_all_layers();
     }
 
+    /// Records the pages that the root pipeline is laid out in, and makes the window the size of
+    /// one page so that each composite shows exactly one of them.
+    fn set_pagination(&mut self, pagination: Option<Pagination>) {
+        if self.pagination == pagination {
+            return;
+        }
+        self.pagination = pagination;
+
+        let page_size = match pagination {
+            Some(ref pagination) => pagination.page_size,
+            None => return,
+        };
+        let dppx = self.device_pixels_per_page_px().get();
+        let window_size = TypedSize2D((page_size.width * dppx).ceil() as uint,
+                                      (page_size.height * dppx).ceil() as uint);
+        self.on_resize_window_event(window_size);
+    }
+
+    /// Scrolls the root layer so that the window shows the page with the given index.
+    fn scroll_to_page(&mut self, page_index: uint) {
+        let page_height = match self.pagination {
+            Some(ref pagination) => pagination.page_size.height,
+            None => return,
+        };
+        match self.scene.root {
+            Some(ref layer) => {
+                let offset = TypedPoint2D(0f32, -page_height * (page_index as f32));
+                layer.clamp_scroll_offset_and_scroll_layer(offset);
+            }
+            None => return,
+        }
+        self.send_buffer_requests_for_all_layers();
+    }
+
+    /// Returns the path of the next output image. When layout is paginated, each page is written
+    /// to its own file, numbered from 1: `out.png` becomes `out-1.png`, `out-2.png` and so on.
+    fn output_path(&self) -> Path {
+        let path = from_str::<Path>(opts::get().output_file.as_ref().unwrap().as_slice()).unwrap();
+        if self.pagination.is_none() {
+            return path;
+        }
+        let stem = String::from_utf8_lossy(path.filestem().unwrap_or(b"out")).into_string();
+        let file_name = match path.extension_str() {
+            Some(extension) => format!("{}-{}.{}", stem, self.output_page_index + 1, extension),
+            None => format!("{}-{}", stem, self.output_page_index + 1),
+        };
+        path.with_filename(file_name)
+    }
+
     fn create_or_update_descendant_layer(&mut self, layer_properties: LayerProperties) {
         if !self.update_layer_if_exists(layer_properties) {
             self.create_descendant_layer(layer_properties);
         }
+        match layer_properties.scrollport {
+            Some(ref scrollport) => {
+                // Resizing the scroll root also clamps its scroll position to the new contents.
+                self.update_layer_if_exists(scroll_root_layer_properties(&layer_properties,
+                                                                         scrollport));
+            }
+            None => {}
+        }
         self.scroll_layer_to_fragment_point_if_necessary(layer_properties.pipeline_id,
                                                          layer_properties.id);
         self.send_buffer_requests_for_all_layers();
@@ -506,11 +650,30 @@ impl<Window: WindowMethods> IOCompositor<Window> {
     fn create_descendant_layer(&self, layer_properties: LayerProperties) {
         let root_layer = self.find_pipeline_root_layer(layer_properties.pipeline_id);
         let root_layer_pipeline = root_layer.extra_data.borrow().pipeline.clone();
-        let new_layer = CompositorData::new_layer(root_layer_pipeline,
+        let new_layer = CompositorData::new_layer(root_layer_pipeline.clone(),
                                                   layer_properties,
                                                   DoesntWantScrollEvents,
                                                   root_layer.tile_size);
-        root_layer.add_child(new_layer);
+        let scrollport = match layer_properties.scrollport {
+            None => {
+                root_layer.add_child(new_layer);
+                return
+            }
+            Some(scrollport) => scrollport,
+        };
+
+        // Layers whose contents scroll go inside a scroll root the size of the scrollport, which
+        // clips them and handles scroll events over them.
+        let scroll_root = CompositorData::new_layer(root_layer_pipeline,
+                                                    scroll_root_layer_properties(&layer_properties,
+                                                                                 &scrollport),
+                                                    WantsScrollEvents,
+                                                    root_layer.tile_size);
+        *scroll_root.masks_to_bounds.borrow_mut() = true;
+        new_layer.bounds.borrow_mut().origin =
+            Point2D::from_untyped(&(Point2D(0.0, 0.0) - scrollport.rect.origin));
+        scroll_root.add_child(new_layer);
+        root_layer.add_child(scroll_root);
     }
 
     fn send_window_size(&self) {
@@ -611,6 +774,20 @@ impl<Window: WindowMethods> IOCompositor<Window> {
         }
     }
 
+    fn set_scroll_position(&mut self,
+                           pipeline_id: PipelineId,
+                           layer_id: LayerId,
+                           point: Point2D<f32>) {
+        // Unlike scrolling to a fragment, there is nothing to do if the layer doesn't exist yet:
+        // the element isn't a scroll container.
+        if self.move_layer(pipeline_id, layer_id, Point2D::from_untyped(&point)) {
+            self.send_viewport_rects_for_all_layers();
+            if self.send_buffer_requests_for_all_layers() {
+                self.start_scrolling_timer_if_necessary();
+            }
+        }
+    }
+
     fn scroll_fragment_to_point(&mut self,
                                 pipeline_id: PipelineId,
                                 layer_id: LayerId,
@@ -887,12 +1064,21 @@ impl<Window: WindowMethods> IOCompositor<Window> {
     }
 
     fn send_viewport_rect_for_layer(&self, layer: Rc<Layer<CompositorData>>) {
-        if layer.extra_data.borrow().id == LayerId::null() {
+        let layer_id = layer.extra_data.borrow().id;
+        if layer_id == LayerId::null() {
             let layer_rect = Rect(-layer.extra_data.borrow().scroll_offset.to_untyped(),
                                   layer.bounds.borrow().size.to_untyped());
             let pipeline = &layer.extra_data.borrow().pipeline;
             let ScriptControlChan(ref chan) = pipeline.script_chan;
             chan.send(ViewportMsg(pipeline.id.clone(), layer_rect));
+        } else if layer_id.scroll_root_node_address().is_some() {
+            // Tell script how far the contents of the scroll root are scrolled, so that it can
+            // answer `scrollTop` and fire `scroll` events.
+            let scroll_position = -layer.extra_data.borrow().scroll_offset.to_untyped();
+            let pipeline = &layer.extra_data.borrow().pipeline;
+            let ScriptControlChan(ref chan) = pipeline.script_chan;
+            chan.send(SendEventMsg(pipeline.id.clone(),
+                                   ScrollPositionEvent(layer_id, scroll_position)));
         }
 
         for kid in layer.children().iter() {
@@ -1001,9 +1187,9 @@ impl<Window: WindowMethods> IOCompositor<Window> {
             }
         });
 
+        let mut has_more_pages = false;
         if output_image {
-            let path =
-                from_str::<Path>(opts::get().output_file.as_ref().unwrap().as_slice()).unwrap();
+            let path = self.output_path();
             let mut pixels = gl::read_pixels(0, 0,
                                              width as gl::GLsizei,
                                              height as gl::GLsizei,
@@ -1032,10 +1218,20 @@ impl<Window: WindowMethods> IOCompositor<Window> {
             let res = png::store_png(&mut img, &path);
             assert!(res.is_ok());
 
-            debug!("shutting down the constellation after generating an output file");
-            let ConstellationChan(ref chan) = self.constellation_chan;
-            chan.send(ExitMsg);
-            self.shutdown_state = ShuttingDown;
+            self.output_page_index += 1;
+            has_more_pages = match self.pagination {
+                Some(ref pagination) => self.output_page_index < pagination.page_count,
+                None => false,
+            };
+            if has_more_pages {
+                let page_index = self.output_page_index;
+                self.scroll_to_page(page_index);
+            } else {
+                debug!("shutting down the constellation after generating an output file");
+                let ConstellationChan(ref chan) = self.constellation_chan;
+                chan.send(ExitMsg);
+                self.shutdown_state = ShuttingDown;
+            }
         }
 
         // Perform the page flip. This will likely block for a while.
@@ -1045,6 +1241,11 @@ impl<Window: WindowMethods> IOCompositor<Window> {
 
         self.composition_request = NoCompositingNecessary;
         self.process_pending_scroll_events();
+
+        // Composite again to write out the next page once its tiles have been rendered.
+        if has_more_pages {
+            self.composite_if_necessary();
+        }
     }
 
     fn composite_if_necessary(&mut self) {
@@ -1118,6 +1319,22 @@ fn find_layer_with_pipeline_and_layer_id_for_layer(layer: Rc<Layer<CompositorDat
     return None;
 }
 
+/// Returns the properties of the scroll root layer that clips and scrolls the layer with the given
+/// properties and scrollport.
+fn scroll_root_layer_properties(layer_properties: &LayerProperties, scrollport: &Scrollport)
+                                -> LayerProperties {
+    LayerProperties {
+        pipeline_id: layer_properties.pipeline_id,
+        epoch: layer_properties.epoch,
+        id: scrollport.id,
+        rect: scrollport.rect.translate(&layer_properties.rect.origin),
+        background_color: azure_hl::Color::new(0., 0., 0., 0.),
+        scroll_policy: layer_properties.scroll_policy,
+        scrollport: None,
+        pagination: None,
+    }
+}
+
 fn create_root_layer_for_pipeline_and_rect(pipeline: &CompositionPipeline,
                                            frame_rect: Option<TypedRect<PagePx, f32>>)
                                            -> Rc<Layer<CompositorData>> {
@@ -1128,6 +1345,8 @@ fn create_root_layer_for_pipeline_and_rect(pipeline: &CompositionPipeline,
         rect: Rect::zero(),
         background_color: azure_hl::Color::new(0., 0., 0., 0.),
         scroll_policy: Scrollable,
+        scrollport: None,
+        pagination: None,
     };
 
     let root_layer = CompositorData::new_layer(pipeline.clone(),
@@ -1220,7 +1439,7 @@ impl<Window> CompositorEventListener for IOCompositor<Window> where Window: Wind
         // another task from finishing (i.e. SetIds)
         while self.port.try_recv_compositor_msg().is_some() {}
 
-        // Tell the profiler, memory profiler, and scrolling timer to shut down.
+        // Tell the profiler, memory profiler, scrolling timer, and vsync timer to shut down.
         let TimeProfilerChan(ref time_profiler_chan) = self.time_profiler_chan;
         time_profiler_chan.send(time::ExitMsg);
 
@@ -1228,5 +1447,6 @@ impl<Window> CompositorEventListener for IOCompositor<Window> where Window: Wind
         memory_profiler_chan.send(memory::ExitMsg);
 
         self.scrolling_timer.shutdown();
+        self.vsync_timer.shutdown();
     }
 }
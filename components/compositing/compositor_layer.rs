@@ -21,7 +21,8 @@ use layers::layers::{Layer, LayerBufferSet};
 use layers::platform::surface::NativeSurfaceMethods;
 use script_traits::{ClickEvent, MouseDownEvent, MouseMoveEvent, MouseUpEvent, SendEventMsg};
 use script_traits::{ScriptControlChan};
-use servo_msg::compositor_msg::{Epoch, FixedPosition, LayerId, ScrollPolicy};
+use servo_msg::compositor_msg::{Epoch, FixedPosition, LayerId, ScrollPolicy, Scrollable};
+use servo_msg::compositor_msg::{StickyPosition, StickyPositionConstraints};
 use std::rc::Rc;
 
 pub struct CompositorData {
@@ -115,7 +116,8 @@ pub trait CompositorLayer {
                                             -> ScrollEventResult;
 
     fn scroll_layer_and_all_child_layers(&self,
-                                         new_offset: TypedPoint2D<LayerPixel, f32>)
+                                         new_offset: TypedPoint2D<LayerPixel, f32>,
+                                         scrollport_size: TypedSize2D<LayerPixel, f32>)
                                          -> bool;
 }
 
@@ -301,7 +303,7 @@ impl CompositorLayer for Layer<CompositorData> {
 
         let mut result = false;
         for child in self.children().iter() {
-            result |= child.scroll_layer_and_all_child_layers(new_offset);
+            result |= child.scroll_layer_and_all_child_layers(new_offset, layer_size);
         }
 
         if result {
@@ -334,26 +336,127 @@ impl CompositorLayer for Layer<CompositorData> {
     }
 
     fn scroll_layer_and_all_child_layers(&self,
-                                         new_offset: TypedPoint2D<LayerPixel, f32>)
+                                         new_offset: TypedPoint2D<LayerPixel, f32>,
+                                         scrollport_size: TypedSize2D<LayerPixel, f32>)
                                          -> bool {
         let mut result = false;
 
-        // Only scroll this layer if it's not fixed-positioned.
-        if self.extra_data.borrow().scroll_policy != FixedPosition {
-            let new_offset = new_offset.to_untyped();
-            *self.transform.borrow_mut() = identity().translate(new_offset.x,
-                                                                 new_offset.y,
-                                                                 0.0);
-            *self.content_offset.borrow_mut() = Point2D::from_untyped(&new_offset);
-            result = true
+        // Only scroll this layer if it's not fixed-positioned. Sticky-positioned layers scroll,
+        // and are then moved back into the scrollport as far as their containing block allows.
+        let layer_offset = match self.extra_data.borrow().scroll_policy {
+            FixedPosition => None,
+            Scrollable => Some(new_offset.to_untyped()),
+            StickyPosition(ref constraints) => {
+                let layer_origin = self.bounds.borrow().origin.to_untyped();
+                let scrollport = Rect(Point2D(0.0, 0.0) - new_offset.to_untyped(),
+                                      scrollport_size.to_untyped());
+                Some(new_offset.to_untyped() +
+                     sticky_position_offset(constraints, &layer_origin, &scrollport))
+            }
+        };
+        match layer_offset {
+            None => {}
+            Some(layer_offset) => {
+                *self.transform.borrow_mut() = identity().translate(layer_offset.x,
+                                                                     layer_offset.y,
+                                                                     0.0);
+                *self.content_offset.borrow_mut() = Point2D::from_untyped(&layer_offset);
+                result = true
+            }
         }
 
         let offset_for_children = new_offset + self.extra_data.borrow().scroll_offset;
         for child in self.children().iter() {
-            result |= child.scroll_layer_and_all_child_layers(offset_for_children);
+            result |= child.scroll_layer_and_all_child_layers(offset_for_children,
+                                                              scrollport_size);
         }
 
         return result;
     }
 
 }
+
+/// Returns how far a `position: sticky` layer with the given origin has to move, once it has
+/// scrolled along with its scrolling root, to stay within the given scrollport. All coordinates are
+/// in page pixels.
+fn sticky_position_offset(constraints: &StickyPositionConstraints,
+                          layer_origin: &Point2D<f32>,
+                          scrollport: &Rect<f32>)
+                          -> Point2D<f32> {
+    let rect = constraints.rect.translate(layer_origin);
+    let containing_block_rect = constraints.containing_block_rect.translate(layer_origin);
+    let min_x = scrollport.origin.x + constraints.left.unwrap_or(0.0);
+    let max_x = scrollport.max_x() - constraints.right.unwrap_or(0.0);
+    let min_y = scrollport.origin.y + constraints.top.unwrap_or(0.0);
+    let max_y = scrollport.max_y() - constraints.bottom.unwrap_or(0.0);
+    Point2D(sticky_position_offset_along_axis(rect.origin.x,
+                                              rect.max_x(),
+                                              containing_block_rect.origin.x,
+                                              containing_block_rect.max_x(),
+                                              min_x,
+                                              max_x,
+                                              constraints.left.is_some(),
+                                              constraints.right.is_some()),
+            sticky_position_offset_along_axis(rect.origin.y,
+                                              rect.max_y(),
+                                              containing_block_rect.origin.y,
+                                              containing_block_rect.max_y(),
+                                              min_y,
+                                              max_y,
+                                              constraints.top.is_some(),
+                                              constraints.bottom.is_some()))
+}
+
+/// Computes the sticky offset along one axis: the element is pushed forward to stay after
+/// `min_start` and pulled back to stay before `max_end`, but never out of its containing block.
+/// If both constraints apply and conflict, the start one wins.
+fn sticky_position_offset_along_axis(start: f32,
+                                     end: f32,
+                                     containing_block_start: f32,
+                                     containing_block_end: f32,
+                                     min_start: f32,
+                                     max_end: f32,
+                                     has_start_constraint: bool,
+                                     has_end_constraint: bool)
+                                     -> f32 {
+    let mut offset = 0.0;
+    if has_end_constraint && end > max_end {
+        offset = (max_end - end).max((containing_block_start - start).min(0.0));
+    }
+    if has_start_constraint && start < min_start {
+        let push = (min_start - start).min((containing_block_end - end).max(0.0));
+        if push > 0.0 {
+            offset = push
+        }
+    }
+    offset
+}
+
+#[test]
+fn test_sticky_position_unstuck() {
+    // The element is already after the top of the scrollport, so it stays where it is.
+    assert_eq!(sticky_position_offset_along_axis(100.0, 150.0, 0.0, 1000.0, 50.0, 500.0,
+                                                 true, false), 0.0);
+    // Constraints on sides that are `auto` are ignored.
+    assert_eq!(sticky_position_offset_along_axis(100.0, 150.0, 0.0, 1000.0, 200.0, 120.0,
+                                                 false, false), 0.0);
+}
+
+#[test]
+fn test_sticky_position_stuck() {
+    // Scrolled past the top of the scrollport: pushed down to stay at its top.
+    assert_eq!(sticky_position_offset_along_axis(100.0, 150.0, 0.0, 1000.0, 200.0, 500.0,
+                                                 true, false), 100.0);
+    // Past the bottom of the scrollport: pulled up to stay at its bottom.
+    assert_eq!(sticky_position_offset_along_axis(100.0, 150.0, 0.0, 1000.0, 0.0, 120.0,
+                                                 false, true), -30.0);
+}
+
+#[test]
+fn test_sticky_position_stuck_within_containing_block() {
+    // The element never leaves its containing block, however far it has been scrolled.
+    assert_eq!(sticky_position_offset_along_axis(100.0, 150.0, 0.0, 300.0, 400.0, 500.0,
+                                                 true, false), 150.0);
+    assert_eq!(sticky_position_offset_along_axis(100.0, 150.0, 80.0, 300.0, 0.0, 50.0,
+                                                 false, true), -20.0);
+}
@@ -19,7 +19,8 @@ use layers::platform::surface::{NativeCompositingGraphicsContext, NativeGraphics
 use layers::layers::LayerBufferSet;
 use servo_msg::compositor_msg::{Epoch, LayerId, LayerMetadata, ReadyState};
 use servo_msg::compositor_msg::{RenderListener, RenderState, ScriptListener, ScrollPolicy};
-use servo_msg::constellation_msg::{ConstellationChan, PipelineId};
+use servo_msg::compositor_msg::{Pagination, Scrollport};
+use servo_msg::constellation_msg::{AnimationState, ConstellationChan, PipelineId};
 use servo_util::memory::MemoryProfilerChan;
 use servo_util::time::TimeProfilerChan;
 use std::comm::{channel, Sender, Receiver};
@@ -72,6 +73,13 @@ impl ScriptListener for Box<CompositorProxy+'static+Send> {
         self.send(ScrollFragmentPoint(pipeline_id, layer_id, point));
     }
 
+    fn set_scroll_position(&mut self,
+                           pipeline_id: PipelineId,
+                           layer_id: LayerId,
+                           point: Point2D<f32>) {
+        self.send(SetScrollPosition(pipeline_id, layer_id, point));
+    }
+
     fn close(&mut self) {
         let (chan, port) = channel();
         self.send(Exit(chan));
@@ -90,6 +98,8 @@ pub struct LayerProperties {
     pub rect: Rect<f32>,
     pub background_color: Color,
     pub scroll_policy: ScrollPolicy,
+    pub scrollport: Option<Scrollport>,
+    pub pagination: Option<Pagination>,
 }
 
 impl LayerProperties {
@@ -104,6 +114,8 @@ impl LayerProperties {
                               metadata.position.size.height as f32)),
             background_color: metadata.background_color,
             scroll_policy: metadata.scroll_policy,
+            scrollport: metadata.scrollport,
+            pagination: metadata.pagination,
         }
     }
 }
@@ -178,6 +190,9 @@ pub enum Msg {
     SetLayerOrigin(PipelineId, LayerId, Point2D<f32>),
     /// Scroll a page in a window
     ScrollFragmentPoint(PipelineId, LayerId, Point2D<f32>),
+    /// Scrolls the contents of the given scroll root layer to the given position, if the layer
+    /// exists.
+    SetScrollPosition(PipelineId, LayerId, Point2D<f32>),
     /// Requests that the compositor paint the given layer buffer set for the given page size.
     Paint(PipelineId, Epoch, Vec<(LayerId, Box<LayerBufferSet>)>),
     /// Alerts the compositor to the current status of page loading.
@@ -195,6 +210,12 @@ pub enum Msg {
     /// Indicates that the scrolling timeout with the given starting timestamp has happened and a
     /// composite should happen. (See the `scrolling` module.)
     ScrollTimeout(u64),
+    /// Alerts the compositor that the given pipeline has, or no longer has, animation frame
+    /// callbacks that need ticks.
+    ChangeRunningAnimationsState(PipelineId, AnimationState),
+    /// Indicates that a frame interval has passed, and animating pipelines should be ticked. (See
+    /// the `vsync` module.)
+    VsyncTick,
 }
 
 impl Show for Msg {
@@ -207,6 +228,7 @@ impl Show for Msg {
             CreateOrUpdateDescendantLayer(..) => write!(f, "CreateOrUpdateDescendantLayer"),
             SetLayerOrigin(..) => write!(f, "SetLayerOrigin"),
             ScrollFragmentPoint(..) => write!(f, "ScrollFragmentPoint"),
+            SetScrollPosition(..) => write!(f, "SetScrollPosition"),
             Paint(..) => write!(f, "Paint"),
             ChangeReadyState(..) => write!(f, "ChangeReadyState"),
             ChangeRenderState(..) => write!(f, "ChangeRenderState"),
@@ -215,6 +237,8 @@ impl Show for Msg {
             FrameTreeUpdateMsg(..) => write!(f, "FrameTreeUpdateMsg"),
             LoadComplete => write!(f, "LoadComplete"),
             ScrollTimeout(..) => write!(f, "ScrollTimeout"),
+            ChangeRunningAnimationsState(..) => write!(f, "ChangeRunningAnimationsState"),
+            VsyncTick => write!(f, "VsyncTick"),
         }
     }
 }
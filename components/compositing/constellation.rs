@@ -5,6 +5,7 @@
 use pipeline::{Pipeline, CompositionPipeline};
 
 use compositor_task::{CompositorProxy, FrameTreeUpdateMsg, LoadComplete, ShutdownComplete, SetLayerOrigin, SetIds};
+use compositor_task::ChangeRunningAnimationsState;
 use devtools_traits;
 use devtools_traits::DevtoolsControlChan;
 use geom::rect::{Rect, TypedRect};
@@ -15,16 +16,23 @@ use layers::geometry::DevicePixel;
 use layout_traits::{LayoutControlChan, LayoutTaskFactory, ExitNowMsg};
 use libc;
 use script_traits;
-use script_traits::{ResizeMsg, ResizeInactiveMsg, ExitPipelineMsg, SendEventMsg};
+use script_traits::{ResizeMsg, ResizeInactiveMsg, ExitPipelineMsg, HistoryTraversalMsg};
+use script_traits::SessionHistoryChangedMsg;
+use script_traits::{SendEventMsg, TickAllAnimationsMsg};
 use script_traits::{ScriptControlChan, ScriptTaskFactory};
 use servo_msg::compositor_msg::LayerId;
 use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, FailureMsg, Failure, FrameRectMsg};
-use servo_msg::constellation_msg::{IFrameSandboxState, IFrameUnsandboxed, InitLoadUrlMsg};
+use servo_msg::constellation_msg::{HistoryState, IFrameSandboxState};
+use servo_msg::constellation_msg::{IFrameUnsandboxed, InitLoadUrlMsg, PushHistoryStateMsg};
 use servo_msg::constellation_msg::{LoadCompleteMsg, LoadUrlMsg, LoadData, Msg, NavigateMsg};
+use servo_msg::constellation_msg::{ReplaceHistoryStateMsg, TraverseHistoryMsg};
 use servo_msg::constellation_msg::{NavigationType, PipelineId, RendererReadyMsg, ResizedWindowMsg};
 use servo_msg::constellation_msg::{ScriptLoadedURLInIFrameMsg, SubpageId, WindowSizeData};
 use servo_msg::constellation_msg::{KeyEvent, Key, KeyState, KeyModifiers};
 use servo_msg::constellation_msg::StorageEventMsg;
+use servo_msg::constellation_msg::{AnimationCallbacksPresent, AnimationState};
+use servo_msg::constellation_msg::{ChangeRunningAnimationsStateMsg, NoAnimationCallbacksPresent};
+use servo_msg::constellation_msg::{PostMessageMsg, PostedMessage, TickAnimationMsg};
 use servo_msg::constellation_msg;
 use servo_net::image_cache_task::{ImageCacheTask, ImageCacheTaskClient};
 use servo_net::resource_task::ResourceTask;
@@ -75,6 +83,10 @@ pub struct Constellation<LTF, STF> {
 
     navigation_context: NavigationContext,
 
+    /// The same-document session history entries of each pipeline that has any, in order. The
+    /// first one describes the entry created by loading the document.
+    history_states: HashMap<PipelineId, Vec<HistoryState>>,
+
     /// The next free ID to assign to a pipeline.
     next_pipeline_id: PipelineId,
 
@@ -82,6 +94,10 @@ pub struct Constellation<LTF, STF> {
 
     pending_sizes: HashMap<(PipelineId, SubpageId), TypedRect<PagePx, f32>>,
 
+    /// The pipelines with animation frame callbacks that aren't being displayed. The compositor
+    /// doesn't tick them until they are again.
+    hidden_animating_pipelines: HashSet<PipelineId>,
+
     /// A channel through which messages can be sent to the time profiler.
     pub time_profiler_chan: TimeProfilerChan,
 
@@ -105,6 +121,20 @@ impl FrameTree {
             has_compositor_layer: Cell::new(false),
         }
     }
+
+    /// Returns a copy of this frame tree which shares its pipelines. Replacing a subframe in the
+    /// copy leaves the session history entries that display this tree intact.
+    fn copy(&self) -> Rc<FrameTree> {
+        let children = self.children.borrow().iter().map(|child| {
+            ChildFrameTree::new(child.frame_tree.copy(), child.rect)
+        }).collect();
+        Rc::new(FrameTree {
+            pipeline: self.pipeline.clone(),
+            parent: RefCell::new(self.parent.borrow().clone()),
+            children: RefCell::new(children),
+            has_compositor_layer: Cell::new(self.has_compositor_layer.get()),
+        })
+    }
 }
 
 #[deriving(Clone)]
@@ -241,11 +271,34 @@ struct FrameChange {
     pub navigation_type: NavigationType,
 }
 
-/// Stores the Id's of the pipelines previous and next in the browser's history
+/// An entry of the joint session history: either a document loaded in some frame, or a
+/// same-document entry added by a document with `history.pushState()` or a fragment navigation.
+#[deriving(Clone)]
+struct SessionHistoryEntry {
+    /// The frame tree displayed while this entry is the current one.
+    pub frame_tree: Rc<FrameTree>,
+    /// The pipeline whose session history this entry extends.
+    pub pipeline_id: PipelineId,
+    /// The index of this entry among the same-document entries of that pipeline.
+    pub state_index: uint,
+}
+
+impl SessionHistoryEntry {
+    fn new(frame_tree: Rc<FrameTree>, pipeline_id: PipelineId, state_index: uint)
+           -> SessionHistoryEntry {
+        SessionHistoryEntry {
+            frame_tree: frame_tree,
+            pipeline_id: pipeline_id,
+            state_index: state_index,
+        }
+    }
+}
+
+/// Stores the joint session history entries previous and next in the browser's history
 struct NavigationContext {
-    pub previous: Vec<Rc<FrameTree>>,
-    pub next: Vec<Rc<FrameTree>>,
-    pub current: Option<Rc<FrameTree>>,
+    pub previous: Vec<SessionHistoryEntry>,
+    pub next: Vec<SessionHistoryEntry>,
+    pub current: Option<SessionHistoryEntry>,
 }
 
 impl NavigationContext {
@@ -260,42 +313,57 @@ impl NavigationContext {
     /* Note that the following two methods can fail. They should only be called  *
      * when it is known that there exists either a previous page or a next page. */
 
-    fn back(&mut self) -> Rc<FrameTree> {
+    fn back(&mut self) -> SessionHistoryEntry {
         self.next.push(self.current.take().unwrap());
         let prev = self.previous.pop().unwrap();
         self.current = Some(prev.clone());
         prev
     }
 
-    fn forward(&mut self) -> Rc<FrameTree> {
+    fn forward(&mut self) -> SessionHistoryEntry {
         self.previous.push(self.current.take().unwrap());
         let next = self.next.pop().unwrap();
         self.current = Some(next.clone());
         next
     }
 
-    /// Loads a new set of page frames, returning all evicted frame trees
-    fn load(&mut self, frame_tree: Rc<FrameTree>) -> Vec<Rc<FrameTree>> {
-        debug!("navigating to {}", frame_tree.pipeline.id);
+    /// Adds a new entry after the current one, returning the frame trees of all evicted entries
+    fn load(&mut self, entry: SessionHistoryEntry) -> Vec<Rc<FrameTree>> {
+        debug!("navigating to {}", entry.pipeline_id);
         let evicted = replace(&mut self.next, vec!());
         match self.current.take() {
             Some(current) => self.previous.push(current),
             None => (),
         }
-        self.current = Some(frame_tree);
-        evicted
+        self.current = Some(entry);
+        evicted.into_iter().map(|entry| entry.frame_tree).collect()
+    }
+
+    /// The number of entries in the joint session history.
+    fn len(&self) -> uint {
+        self.previous.len() + self.current.iter().count() + self.next.len()
+    }
+
+    /// Returns the index of the current same-document entry of each pipeline which has added
+    /// entries to the session history, up to and including the current entry.
+    fn state_indices(&self) -> HashMap<PipelineId, uint> {
+        let mut indices = HashMap::new();
+        for entry in self.previous.iter().chain(self.current.iter()) {
+            indices.insert(entry.pipeline_id, entry.state_index);
+        }
+        indices
     }
 
     /// Returns the frame trees whose keys are pipeline_id.
     fn find_all(&mut self, pipeline_id: PipelineId) -> Vec<Rc<FrameTree>> {
-        let from_current = self.current.iter().filter_map(|frame_tree| {
-            frame_tree.find(pipeline_id)
+        let from_current = self.current.iter().filter_map(|entry| {
+            entry.frame_tree.find(pipeline_id)
         });
-        let from_next = self.next.iter().filter_map(|frame_tree| {
-            frame_tree.find(pipeline_id)
+        let from_next = self.next.iter().filter_map(|entry| {
+            entry.frame_tree.find(pipeline_id)
         });
-        let from_prev = self.previous.iter().filter_map(|frame_tree| {
-            frame_tree.find(pipeline_id)
+        let from_prev = self.previous.iter().filter_map(|entry| {
+            entry.frame_tree.find(pipeline_id)
         });
         from_prev.chain(from_current).chain(from_next).collect()
     }
@@ -306,8 +374,8 @@ impl NavigationContext {
         let from_prev = self.previous.iter();
 
         let mut all_contained = from_prev.chain(from_current).chain(from_next);
-        all_contained.any(|frame_tree| {
-            frame_tree.contains(pipeline_id)
+        all_contained.any(|entry| {
+            entry.frame_tree.contains(pipeline_id)
         })
     }
 }
@@ -335,9 +403,11 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
                 storage_task: storage_task,
                 pipelines: HashMap::new(),
                 navigation_context: NavigationContext::new(),
+                history_states: HashMap::new(),
                 next_pipeline_id: PipelineId(0),
                 pending_frames: vec!(),
                 pending_sizes: HashMap::new(),
+                hidden_animating_pipelines: HashSet::new(),
                 time_profiler_chan: time_profiler_chan,
                 window_size: WindowSizeData {
                     visible_viewport: opts::get().initial_window_size.as_f32() * ScaleFactor(1.0),
@@ -394,8 +464,8 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
 
     /// Convenience function for getting the currently active frame tree.
     /// The currently active frame tree should always be the current painter
-    fn current_frame<'a>(&'a self) -> &'a Option<Rc<FrameTree>> {
-        &self.navigation_context.current
+    fn current_frame<'a>(&'a self) -> Option<&'a Rc<FrameTree>> {
+        self.navigation_context.current.as_ref().map(|entry| &entry.frame_tree)
     }
 
     /// Returns both the navigation context and pending frame trees whose keys are pipeline_id.
@@ -452,7 +522,24 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
             // Handle a forward or back request
             NavigateMsg(direction) => {
                 debug!("constellation got navigation message");
-                self.handle_navigate_msg(direction);
+                let delta = match direction {
+                    constellation_msg::Forward => 1,
+                    constellation_msg::Back => -1,
+                };
+                self.handle_traverse_history_msg(delta);
+            }
+            // Traverse the session history by a number of entries, from `history.go()`
+            TraverseHistoryMsg(delta) => {
+                debug!("constellation got traverse history message");
+                self.handle_traverse_history_msg(delta);
+            }
+            PushHistoryStateMsg(pipeline_id, state) => {
+                debug!("constellation got push history state message");
+                self.handle_push_history_state_msg(pipeline_id, state);
+            }
+            ReplaceHistoryStateMsg(pipeline_id, state) => {
+                debug!("constellation got replace history state message");
+                self.handle_replace_history_state_msg(pipeline_id, state);
             }
             // Notification that rendering has finished and is requesting permission to paint.
             RendererReadyMsg(pipeline_id) => {
@@ -471,6 +558,17 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
                 debug!("constellation got strage event message");
                 self.handle_storage_event_msg(url, source_pipeline_id, key, old_value, new_value);
             }
+            ChangeRunningAnimationsStateMsg(pipeline_id, animation_state) => {
+                debug!("constellation got running animations state message");
+                self.handle_change_running_animations_state(pipeline_id, animation_state);
+            }
+            TickAnimationMsg(pipeline_id) => {
+                self.handle_tick_animation(pipeline_id);
+            }
+            PostMessageMsg(pipeline_id, message) => {
+                debug!("constellation got post message message");
+                self.handle_post_message_msg(pipeline_id, message);
+            }
         }
         true
     }
@@ -576,9 +674,9 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
 
         {
             // If the subframe is in the current frame tree, the compositor needs the new size
-            for current_frame in self.navigation_context.current.iter() {
+            for current_entry in self.navigation_context.current.iter() {
                 debug!("Constellation: Sending size for frame in current frame tree.");
-                let source_frame = current_frame.find(pipeline_id);
+                let source_frame = current_entry.frame_tree.find(pipeline_id);
                 for source_frame in source_frame.iter() {
                     let mut children = source_frame.children.borrow_mut();
                     match children.iter_mut().find(|child| subpage_eq(child)) {
@@ -706,7 +804,7 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
     fn handle_load_url_msg(&mut self, source_id: PipelineId, load_data: LoadData) {
         debug!("Constellation: received message to load {:s}", load_data.url.to_string());
         // Make sure no pending page would be overridden.
-        let source_frame = self.current_frame().as_ref().unwrap().find(source_id).expect(
+        let source_frame = self.current_frame().unwrap().find(source_id).expect(
             "Constellation: received a LoadUrlMsg from a pipeline_id associated
             with a pipeline not in the active frame tree. This should be
             impossible.");
@@ -715,7 +813,7 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
             let old_id = frame_change.before.expect("Constellation: Received load msg
                 from pipeline, but there is no currently active page. This should
                 be impossible.");
-            let changing_frame = self.current_frame().as_ref().unwrap().find(old_id).expect("Constellation:
+            let changing_frame = self.current_frame().unwrap().find(old_id).expect("Constellation:
                 Pending change has non-active source pipeline. This should be
                 impossible.");
             if changing_frame.contains(source_id) || source_frame.contains(old_id) {
@@ -740,45 +838,135 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
         self.pipelines.insert(pipeline.id, pipeline);
     }
 
-    fn handle_navigate_msg(&mut self, direction: constellation_msg::NavigationDirection) {
-        debug!("received message to navigate {}", direction);
+    fn handle_traverse_history_msg(&mut self, delta: int) {
+        debug!("received message to traverse the session history by {}", delta);
 
         // TODO(tkuehn): what is the "critical point" beyond which pending frames
         // should not be cleared? Currently, the behavior is that forward/back
         // navigation always has navigation priority, and after that new page loading is
         // first come, first served.
-        let destination_frame = match direction {
-            constellation_msg::Forward => {
-                if self.navigation_context.next.is_empty() {
-                    debug!("no next page to navigate to");
-                    return;
-                } else {
-                    let old = self.current_frame().as_ref().unwrap();
-                    for frame in old.iter() {
-                        frame.pipeline.revoke_paint_permission();
-                    }
-                }
-                self.navigation_context.forward()
+        let available = if delta < 0 {
+            self.navigation_context.previous.len()
+        } else {
+            self.navigation_context.next.len()
+        };
+        if delta == 0 || delta.abs() as uint > available {
+            debug!("no session history entry to navigate to");
+            return;
+        }
+
+        let old_frame = self.current_frame().unwrap().clone();
+        let old_indices = self.navigation_context.state_indices();
+        for _ in range(0, delta.abs()) {
+            if delta < 0 {
+                self.navigation_context.back();
+            } else {
+                self.navigation_context.forward();
             }
-            constellation_msg::Back => {
-                if self.navigation_context.previous.is_empty() {
-                    debug!("no previous page to navigate to");
-                    return;
-                } else {
-                    let old = self.current_frame().as_ref().unwrap();
-                    for frame in old.iter() {
-                        frame.pipeline.revoke_paint_permission();
-                    }
+        }
+        let destination = self.navigation_context.current.clone().unwrap();
+        let destination_frame = destination.frame_tree.clone();
+
+        // Entries of the same frame tree only differ in the state of some of its documents.
+        if &*old_frame as *const FrameTree != &*destination_frame as *const FrameTree {
+            for frame in old_frame.iter() {
+                frame.pipeline.revoke_paint_permission();
+            }
+            for frame in destination_frame.iter() {
+                if !old_frame.contains(frame.pipeline.id) {
+                    frame.pipeline.load();
                 }
-                self.navigation_context.back()
             }
-        };
+            self.grant_paint_permission(destination, constellation_msg::Navigate);
+        }
 
+        // Tell every document whose current same-document entry changed to restore its state.
+        // A reloaded document starts at its first entry.
+        let new_indices = self.navigation_context.state_indices();
         for frame in destination_frame.iter() {
-            frame.pipeline.load();
+            let pipeline = &frame.pipeline;
+            let new_index = new_indices.get(&pipeline.id).map_or(0, |&index| index);
+            let old_index = if old_frame.contains(pipeline.id) {
+                old_indices.get(&pipeline.id).map_or(0, |&index| index)
+            } else {
+                0
+            };
+            if new_index == old_index {
+                continue;
+            }
+            let state = self.history_states.get(&pipeline.id).and_then(|states| {
+                states.as_slice().get(new_index)
+            });
+            for state in state.iter() {
+                let ScriptControlChan(ref chan) = pipeline.script_chan;
+                chan.send(HistoryTraversalMsg(pipeline.id, (*state).clone()));
+            }
+        }
+        self.send_session_history_changed_to_current_frame();
+    }
+
+    /// Tells a pipeline where the current entry of the joint session history is and how many
+    /// entries it has.
+    fn send_session_history_changed(&self, pipeline_id: PipelineId) {
+        let index = self.navigation_context.previous.len();
+        let length = self.navigation_context.len();
+        for pipeline in self.pipelines.get(&pipeline_id).iter() {
+            let ScriptControlChan(ref chan) = pipeline.script_chan;
+            chan.send(SessionHistoryChangedMsg(pipeline_id, index, length));
+        }
+    }
+
+    fn send_session_history_changed_to_current_frame(&self) {
+        for frame_tree in self.current_frame().iter() {
+            for frame in frame_tree.iter() {
+                self.send_session_history_changed(frame.pipeline.id);
+            }
         }
-        self.grant_paint_permission(destination_frame, constellation_msg::Navigate);
+    }
 
+    /// Returns the same-document session history entries of a pipeline, first adding the entry
+    /// of its document if there are none yet.
+    fn history_states_mut<'a>(&'a mut self, pipeline_id: PipelineId)
+                              -> &'a mut Vec<HistoryState> {
+        if !self.history_states.contains_key(&pipeline_id) {
+            let url = self.pipelines.get(&pipeline_id).unwrap().load_data.url.clone();
+            self.history_states.insert(pipeline_id, vec!(HistoryState {
+                url: url,
+                data: None,
+            }));
+        }
+        self.history_states.get_mut(&pipeline_id).unwrap()
+    }
+
+    fn handle_push_history_state_msg(&mut self, pipeline_id: PipelineId, state: HistoryState) {
+        // Only documents in the current frame tree can add entries to the session history.
+        let frame_tree = match self.current_frame() {
+            Some(frame_tree) if frame_tree.contains(pipeline_id) => frame_tree.clone(),
+            _ => return,
+        };
+        let index = self.navigation_context.state_indices().get(&pipeline_id)
+                                                           .map_or(0, |&index| index) + 1;
+        {
+            // The entries after the current one are evicted below.
+            let states = self.history_states_mut(pipeline_id);
+            states.truncate(index);
+            states.push(state);
+        }
+        let evicted = self.navigation_context.load(SessionHistoryEntry::new(frame_tree,
+                                                                            pipeline_id,
+                                                                            index));
+        self.handle_evicted_frames(evicted);
+        self.send_session_history_changed_to_current_frame();
+    }
+
+    fn handle_replace_history_state_msg(&mut self, pipeline_id: PipelineId, state: HistoryState) {
+        if !self.pipeline_is_in_current_frame(pipeline_id) {
+            return;
+        }
+        let index = self.navigation_context.state_indices().get(&pipeline_id)
+                                                           .map_or(0, |&index| index);
+        let states = self.history_states_mut(pipeline_id);
+        states.as_mut_slice()[index] = state;
     }
 
     fn pipeline_is_in_current_frame(&self, pipeline_id: PipelineId) -> bool {
@@ -786,8 +974,72 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
             .any(|current_frame| current_frame.contains(pipeline_id))
     }
 
+    fn handle_change_running_animations_state(&mut self,
+                                              pipeline_id: PipelineId,
+                                              animation_state: AnimationState) {
+        if animation_state == AnimationCallbacksPresent &&
+                !self.pipeline_is_in_current_frame(pipeline_id) {
+            self.hidden_animating_pipelines.insert(pipeline_id);
+            return;
+        }
+        self.hidden_animating_pipelines.remove(&pipeline_id);
+        self.compositor_proxy.send(ChangeRunningAnimationsState(pipeline_id, animation_state));
+    }
+
+    fn handle_tick_animation(&mut self, pipeline_id: PipelineId) {
+        // Pipelines that aren't being displayed don't run their animation frame callbacks. The
+        // compositor stops ticking them, and starts again once they are displayed, since their
+        // callbacks are still waiting.
+        let script_chan = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) if self.pipeline_is_in_current_frame(pipeline_id) => {
+                pipeline.script_chan.clone()
+            }
+            Some(_) => {
+                self.hidden_animating_pipelines.insert(pipeline_id);
+                self.compositor_proxy.send(ChangeRunningAnimationsState(
+                    pipeline_id, NoAnimationCallbacksPresent));
+                return;
+            }
+            None => {
+                self.compositor_proxy.send(ChangeRunningAnimationsState(
+                    pipeline_id, NoAnimationCallbacksPresent));
+                return;
+            }
+        };
+        let ScriptControlChan(ref chan) = script_chan;
+        chan.send(TickAllAnimationsMsg(pipeline_id));
+    }
+
+    /// Has the compositor tick the pipelines with animation frame callbacks that are being
+    /// displayed again.
+    fn resume_animations_of_displayed_pipelines(&mut self) {
+        let pipelines: Vec<PipelineId> = self.hidden_animating_pipelines.iter().map(|&id| id)
+                                                                         .collect();
+        for pipeline_id in pipelines.into_iter() {
+            if !self.pipelines.contains_key(&pipeline_id) {
+                self.hidden_animating_pipelines.remove(&pipeline_id);
+            } else if self.pipeline_is_in_current_frame(pipeline_id) {
+                self.hidden_animating_pipelines.remove(&pipeline_id);
+                self.compositor_proxy.send(ChangeRunningAnimationsState(
+                    pipeline_id, AnimationCallbacksPresent));
+            }
+        }
+    }
+
+    fn handle_post_message_msg(&self, pipeline_id: PipelineId, message: PostedMessage) {
+        // The target may have been closed since the message was posted. The origin check is done
+        // by the script task, against the document the window has when the message arrives.
+        match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => {
+                let ScriptControlChan(ref chan) = pipeline.script_chan;
+                chan.send(script_traits::PostMessageMsg(pipeline_id, message));
+            }
+            None => {}
+        }
+    }
+
     fn handle_key_msg(&self, key: Key, state: KeyState, mods: KeyModifiers) {
-        self.current_frame().as_ref().map(|frame| {
+        self.current_frame().map(|frame| {
             let ScriptControlChan(ref chan) = frame.pipeline.script_chan;
             chan.send(SendEventMsg(frame.pipeline.id, script_traits::KeyEvent(key, state, mods)));
         });
@@ -819,6 +1071,7 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
         // they may come from a page load in a subframe.
         if self.pipeline_is_in_current_frame(pipeline_id) {
             self.create_compositor_layer_for_iframe_if_necessary(pipeline_id);
+            self.send_session_history_changed(pipeline_id);
             return;
         }
 
@@ -833,10 +1086,11 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
                 let frame_change = self.pending_frames.swap_remove(pending_index).unwrap();
                 let to_add = frame_change.after.clone();
 
-                // Create the next frame tree that will be given to the compositor
+                // Create the next frame tree that will be given to the compositor. Subframes are
+                // changed in a copy of the current frame tree, which stays in the session history.
                 let next_frame_tree = if to_add.parent.borrow().is_some() {
                     // NOTE: work around borrowchk issues
-                    self.current_frame().as_ref().unwrap().clone()
+                    self.current_frame().unwrap().copy()
                 } else {
                     to_add.clone()
                 };
@@ -845,7 +1099,7 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
                 match frame_change.before {
                     Some(revoke_id) if self.current_frame().is_some() => {
                         debug!("Constellation: revoking permission from {}", revoke_id);
-                        let current_frame = self.current_frame().as_ref().unwrap();
+                        let current_frame = self.current_frame().unwrap();
 
                         let to_revoke = current_frame.find(revoke_id).expect(
                             "Constellation: pending frame change refers to an old \
@@ -889,7 +1143,8 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
                     }
                 }
 
-                self.grant_paint_permission(next_frame_tree, frame_change.navigation_type);
+                let entry = SessionHistoryEntry::new(next_frame_tree, pipeline_id, 0);
+                self.grant_paint_permission(entry, frame_change.navigation_type);
             },
             None => (),
         }
@@ -905,9 +1160,9 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
             let _ = chan.send_opt(ResizeMsg(pipeline.id, new_size));
             already_seen.insert(pipeline.id);
         }
-        for frame_tree in self.navigation_context.previous.iter()
+        for entry in self.navigation_context.previous.iter()
             .chain(self.navigation_context.next.iter()) {
-            let pipeline = &frame_tree.pipeline;
+            let pipeline = &entry.frame_tree.pipeline;
             if !already_seen.contains(&pipeline.id) {
                 debug!("constellation sending resize message to inactive frame");
                 let ScriptControlChan(ref chan) = pipeline.script_chan;
@@ -938,6 +1193,7 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
         for frame_tree in frame_tree.iter() {
             frame_tree.pipeline.exit();
             self.pipelines.remove(&frame_tree.pipeline.id);
+            self.history_states.remove(&frame_tree.pipeline.id);
         }
     }
 
@@ -953,23 +1209,28 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
         }
     }
 
-    // Grants a frame tree permission to paint; optionally updates navigation to reflect a new page
-    fn grant_paint_permission(&mut self, frame_tree: Rc<FrameTree>, navigation_type: NavigationType) {
+    // Grants the frame tree of a session history entry permission to paint; optionally updates
+    // navigation to reflect a new page
+    fn grant_paint_permission(&mut self, entry: SessionHistoryEntry,
+                              navigation_type: NavigationType) {
         // Give permission to paint to the new frame and all child frames
-        self.set_ids(&frame_tree);
+        self.set_ids(&entry.frame_tree);
 
         // Don't call navigation_context.load() on a Navigate type (or None, as in the case of
         // parsed iframes that finish loading)
         match navigation_type {
             constellation_msg::Load => {
                 debug!("evicting old frames due to load");
-                let evicted = self.navigation_context.load(frame_tree);
+                let evicted = self.navigation_context.load(entry);
                 self.handle_evicted_frames(evicted);
             }
             _ => {
                 debug!("ignoring non-load navigation type");
             }
         }
+
+        self.resume_animations_of_displayed_pipelines();
+        self.send_session_history_changed_to_current_frame();
     }
 
     fn set_ids(&mut self, frame_tree: &Rc<FrameTree>) {
@@ -1009,8 +1270,8 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
 
     fn create_compositor_layer_for_iframe_if_necessary(&mut self, pipeline_id: PipelineId) {
         let current_frame_tree = match self.current_frame() {
-            &Some(ref tree) => tree.clone(),
-            &None => return,
+            Some(tree) => tree.clone(),
+            None => return,
         };
 
         let pair = self.find_child_parent_pair_in_frame_tree(current_frame_tree,
@@ -5,12 +5,15 @@
 use compositor_task::{GetGraphicsMetadata, CreateOrUpdateRootLayer, CreateOrUpdateDescendantLayer};
 use compositor_task::{Exit, ChangeReadyState, LoadComplete, Paint, ScrollFragmentPoint, SetIds};
 use compositor_task::{SetLayerOrigin, ShutdownComplete, ChangeRenderState, RenderMsgDiscarded};
+use compositor_task::{SetScrollPosition, ChangeRunningAnimationsState, VsyncTick};
 use compositor_task::{CompositorEventListener, CompositorReceiver, ScrollTimeout, FrameTreeUpdateMsg};
 use windowing::WindowEvent;
 
 use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
 use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, ResizedWindowMsg, WindowSizeData};
+use servo_msg::constellation_msg::{AnimationCallbacksPresent, NoAnimationCallbacksPresent};
+use servo_msg::constellation_msg::TickAnimationMsg;
 use servo_util::memory::MemoryProfilerChan;
 use servo_util::memory;
 use servo_util::time::TimeProfilerChan;
@@ -96,6 +99,14 @@ impl CompositorEventListener for NullCompositor {
                 response_channel.send(());
             }
 
+            // There is no display to sync with, so tick as soon as the script task asks for it.
+            // The script task asks again after running each batch of callbacks, which makes the
+            // ticks deterministic.
+            ChangeRunningAnimationsState(pipeline_id, AnimationCallbacksPresent) => {
+                let ConstellationChan(ref con_chan) = self.constellation_chan;
+                con_chan.send(TickAnimationMsg(pipeline_id));
+            }
+
             // Explicitly list ignored messages so that when we add a new one,
             // we'll notice and think about whether it needs a response, like
             // SetIds.
@@ -104,7 +115,8 @@ impl CompositorEventListener for NullCompositor {
             CreateOrUpdateDescendantLayer(..) |
             SetLayerOrigin(..) | Paint(..) |
             ChangeReadyState(..) | ChangeRenderState(..) | ScrollFragmentPoint(..) |
-            LoadComplete | RenderMsgDiscarded(..) | ScrollTimeout(..) => ()
+            SetScrollPosition(..) | LoadComplete | RenderMsgDiscarded(..) | ScrollTimeout(..) |
+            ChangeRunningAnimationsState(_, NoAnimationCallbacksPresent) | VsyncTick => ()
         }
         true
     }
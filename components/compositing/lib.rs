@@ -44,6 +44,7 @@ pub mod compositor_task;
 
 mod compositor_layer;
 mod scrolling;
+mod vsync;
 
 mod compositor;
 mod headless;
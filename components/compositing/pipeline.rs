@@ -84,6 +84,7 @@ impl Pipeline {
                                           resource_task.clone(),
                                           storage_task.clone(),
                                           image_cache_task.clone(),
+                                          font_cache_task.clone(),
                                           devtools_chan,
                                           window_size);
                 ScriptControlChan(script_chan)
@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A timer thread that tells the compositor when to tick the pipelines that have animation frame
+//! callbacks waiting.
+
+use compositor_task::{CompositorProxy, VsyncTick};
+
+use native::task::NativeTaskBuilder;
+use std::comm::{Disconnected, Empty};
+use std::io::timer;
+use std::task::TaskBuilder;
+use std::time::duration::Duration;
+use time;
+
+/// The amount of time in nanoseconds between two ticks. We don't have access to the real vertical
+/// sync of the display yet, so we assume a 60Hz refresh rate.
+static FRAME_INTERVAL: i64 = 16_666_667;
+
+pub struct VsyncTimerProxy {
+    sender: Sender<ToVsyncTimerMsg>,
+}
+
+pub struct VsyncTimer {
+    compositor_proxy: Box<CompositorProxy>,
+    receiver: Receiver<ToVsyncTimerMsg>,
+}
+
+enum ToVsyncTimerMsg {
+    ExitMsg,
+    StartMsg,
+    StopMsg,
+}
+
+impl VsyncTimerProxy {
+    pub fn new(compositor_proxy: Box<CompositorProxy+Send>) -> VsyncTimerProxy {
+        let (to_vsync_timer_sender, to_vsync_timer_receiver) = channel();
+        TaskBuilder::new().native().spawn(proc() {
+            let mut vsync_timer = VsyncTimer {
+                compositor_proxy: compositor_proxy,
+                receiver: to_vsync_timer_receiver,
+            };
+            vsync_timer.run();
+        });
+        VsyncTimerProxy {
+            sender: to_vsync_timer_sender,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.sender.send(StartMsg)
+    }
+
+    pub fn stop(&mut self) {
+        self.sender.send(StopMsg)
+    }
+
+    pub fn shutdown(&mut self) {
+        self.sender.send(ExitMsg);
+    }
+}
+
+impl VsyncTimer {
+    pub fn run(&mut self) {
+        loop {
+            // Block until somebody wants ticks.
+            match self.receiver.recv_opt() {
+                Ok(StartMsg) => {}
+                Ok(StopMsg) => continue,
+                Ok(ExitMsg) | Err(_) => return,
+            }
+
+            let mut next_tick = time::precise_time_ns() as i64 + FRAME_INTERVAL;
+            loop {
+                let delta = next_tick - (time::precise_time_ns() as i64);
+                if delta > 0 {
+                    timer::sleep(Duration::nanoseconds(delta));
+                }
+                next_tick += FRAME_INTERVAL;
+
+                match self.receiver.try_recv() {
+                    Ok(StopMsg) => break,
+                    Ok(StartMsg) | Err(Empty) => {}
+                    Ok(ExitMsg) | Err(Disconnected) => return,
+                }
+                self.compositor_proxy.send(VsyncTick);
+            }
+        }
+    }
+}
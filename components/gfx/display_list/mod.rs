@@ -402,6 +402,7 @@ pub enum DisplayItem {
     ImageDisplayItemClass(Box<ImageDisplayItem>),
     BorderDisplayItemClass(Box<BorderDisplayItem>),
     GradientDisplayItemClass(Box<GradientDisplayItem>),
+    RadialGradientDisplayItemClass(Box<RadialGradientDisplayItem>),
     LineDisplayItemClass(Box<LineDisplayItem>),
 
     /// A pseudo-display item that exists only so that queries like `ContentBoxQuery` and
@@ -465,10 +466,15 @@ pub struct TextDisplayItem {
     pub orientation: TextOrientation,
 }
 
+/// How the glyphs of a text display item are oriented.
 #[deriving(Clone, Eq, PartialEq)]
 pub enum TextOrientation {
+    /// The glyphs are not rotated. In vertical text, whose run is then upright, they are stacked
+    /// one below the other.
     Upright,
+    /// The glyphs are rotated a quarter turn counterclockwise, so that the text reads upwards.
     SidewaysLeft,
+    /// The glyphs are rotated a quarter turn clockwise, so that the text reads downwards.
     SidewaysRight,
 }
 
@@ -498,6 +504,37 @@ pub struct GradientDisplayItem {
 
     /// A list of color stops.
     pub stops: Vec<GradientStop>,
+
+    /// True if the gradient repeats beyond the start and end points instead of extending the
+    /// colors of the first and last stops.
+    pub repeating: bool,
+}
+
+/// Paints a radial gradient.
+#[deriving(Clone)]
+pub struct RadialGradientDisplayItem {
+    /// Fields common to all display items.
+    pub base: BaseDisplayItem,
+
+    /// The center of the gradient.
+    pub center: Point2D<Au>,
+
+    /// The horizontal radius at which the first color stop is placed.
+    pub start_radius: Au,
+
+    /// The horizontal radius at which the last color stop is placed.
+    pub end_radius: Au,
+
+    /// The ratio of the vertical radius of the ending shape to its horizontal radius. This is 1.0
+    /// for circles.
+    pub vertical_scale: f32,
+
+    /// A list of color stops.
+    pub stops: Vec<GradientStop>,
+
+    /// True if the gradient repeats beyond the end radius instead of extending the color of the
+    /// last stop.
+    pub repeating: bool,
 }
 
 /// Renders a border.
@@ -621,7 +658,18 @@ impl DisplayItem {
                 render_context.draw_linear_gradient(&gradient.base.bounds,
                                                     &gradient.start_point,
                                                     &gradient.end_point,
-                                                    gradient.stops.as_slice());
+                                                    gradient.stops.as_slice(),
+                                                    gradient.repeating);
+            }
+
+            RadialGradientDisplayItemClass(ref gradient) => {
+                render_context.draw_radial_gradient(&gradient.base.bounds,
+                                                    &gradient.center,
+                                                    gradient.start_radius,
+                                                    gradient.end_radius,
+                                                    gradient.vertical_scale,
+                                                    gradient.stops.as_slice(),
+                                                    gradient.repeating);
             }
 
             LineDisplayItemClass(ref line) => {
@@ -641,6 +689,7 @@ impl DisplayItem {
             ImageDisplayItemClass(ref image_item) => &image_item.base,
             BorderDisplayItemClass(ref border) => &border.base,
             GradientDisplayItemClass(ref gradient) => &gradient.base,
+            RadialGradientDisplayItemClass(ref gradient) => &gradient.base,
             LineDisplayItemClass(ref line) => &line.base,
             PseudoDisplayItemClass(ref base) => &**base,
         }
@@ -653,6 +702,7 @@ impl DisplayItem {
             ImageDisplayItemClass(ref mut image_item) => &mut image_item.base,
             BorderDisplayItemClass(ref mut border) => &mut border.base,
             GradientDisplayItemClass(ref mut gradient) => &mut gradient.base,
+            RadialGradientDisplayItemClass(ref mut gradient) => &mut gradient.base,
             LineDisplayItemClass(ref mut line) => &mut line.base,
             PseudoDisplayItemClass(ref mut base) => &mut **base,
         }
@@ -680,6 +730,7 @@ impl fmt::Show for DisplayItem {
                 ImageDisplayItemClass(_) => "Image",
                 BorderDisplayItemClass(_) => "Border",
                 GradientDisplayItemClass(_) => "Gradient",
+                RadialGradientDisplayItemClass(_) => "RadialGradient",
                 LineDisplayItemClass(_) => "Line",
                 PseudoDisplayItemClass(_) => "Pseudo",
             },
@@ -9,7 +9,7 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use servo_util::cache::{Cache, HashCache};
 use servo_util::smallvec::{SmallVec, SmallVec8};
-use style::computed_values::{font_variant, font_weight};
+use style::computed_values::{font_variant, font_weight, word_break};
 use style::style_structs::Font as FontStyle;
 use sync::Arc;
 
@@ -96,22 +96,26 @@ pub struct Font {
     pub actual_pt_size: Au,
     pub shaper: Option<Shaper>,
     pub shape_cache: HashCache<String, Arc<GlyphStore>>,
+    pub rtl_shape_cache: HashCache<String, Arc<GlyphStore>>,
     pub glyph_advance_cache: HashCache<u32, FractionalPixel>,
 }
 
 impl Font {
-    pub fn shape_text(&mut self, text: &str, is_whitespace: bool) -> Arc<GlyphStore> {
+    /// Shapes the given text. Right-to-left text is shaped in that direction, but its glyphs are
+    /// still stored in logical order.
+    pub fn shape_text(&mut self, text: &str, is_whitespace: bool, rtl: bool) -> Arc<GlyphStore> {
         self.make_shaper();
         let shaper = &self.shaper;
-        match self.shape_cache.find_equiv(text) {
+        let shape_cache = if rtl { &mut self.rtl_shape_cache } else { &mut self.shape_cache };
+        match shape_cache.find_equiv(text) {
             None => {}
             Some(glyphs) => return (*glyphs).clone(),
         }
 
         let mut glyphs = GlyphStore::new(text.char_len() as int, is_whitespace);
-        shaper.as_ref().unwrap().shape_text(text, &mut glyphs);
+        shaper.as_ref().unwrap().shape_text(text, rtl, &mut glyphs);
         let glyphs = Arc::new(glyphs);
-        self.shape_cache.insert(text.to_string(), glyphs.clone());
+        shape_cache.insert(text.to_string(), glyphs.clone());
         glyphs
     }
 
@@ -175,11 +179,14 @@ impl FontGroup {
         }
     }
 
-    pub fn create_textrun(&self, text: String) -> TextRun {
+    /// Creates a text run that uses the first font of this group for all of the given text. Text
+    /// that the first font may lack glyphs for should instead be split up by the font that
+    /// `FontContext::get_layout_font_for_character` returns for each character.
+    pub fn create_textrun(&self, text: String, word_break: word_break::T, bidi_level: u8)
+                          -> TextRun {
         assert!(self.fonts.len() > 0);
 
-        // TODO(Issue #177): Actually fall back through the FontGroup when a font is unsuitable.
-        TextRun::new(&mut *self.fonts.get(0).borrow_mut(), text.clone())
+        TextRun::new(&mut *self.fonts.get(0).borrow_mut(), text.clone(), word_break, bidi_level)
     }
 }
 
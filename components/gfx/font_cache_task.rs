@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use platform::font_list::get_available_families;
+use platform::font_list::get_fallback_font_family_for_character;
 use platform::font_list::get_system_default_family;
 use platform::font_list::get_variations_for_family;
 use platform::font_list::get_last_resort_font_families;
@@ -75,6 +76,7 @@ impl FontFamily {
 pub enum Command {
     GetFontTemplate(String, FontTemplateDescriptor, Sender<Reply>),
     GetLastResortFontTemplate(FontTemplateDescriptor, Sender<Reply>),
+    GetFallbackFontTemplateForCharacter(char, FontTemplateDescriptor, Sender<Reply>),
     AddWebFont(String, Source, Sender<()>),
     Exit(Sender<()>),
 }
@@ -120,6 +122,11 @@ impl FontCache {
                     let font_template = self.get_last_resort_font_template(&descriptor);
                     result.send(GetFontTemplateReply(Some(font_template)));
                 }
+                GetFallbackFontTemplateForCharacter(codepoint, descriptor, result) => {
+                    let maybe_font_template =
+                        self.get_fallback_font_template_for_character(codepoint, &descriptor);
+                    result.send(GetFontTemplateReply(maybe_font_template));
+                }
                 AddWebFont(family_name, src, result) => {
                     let family_name = LowercaseString::new(family_name.as_slice());
                     if !self.web_families.contains_key(&family_name) {
@@ -130,7 +137,8 @@ impl FontCache {
                     match src {
                         UrlSource_(ref url_source) => {
                             let url = &url_source.url;
-                            let maybe_resource = load_whole_resource(&self.resource_task, url.clone());
+                            let maybe_resource =
+                                load_whole_resource(&self.resource_task, url.clone(), None);
                             match maybe_resource {
                                 Ok((_, bytes)) => {
                                     let family = &mut self.web_families[family_name];
@@ -239,6 +247,21 @@ impl FontCache {
 
         panic!("Unable to find any fonts that match (do you have fallback fonts installed?)");
     }
+
+    /// Finds a system font that has a glyph for the given character.
+    fn get_fallback_font_template_for_character(&mut self,
+                                                codepoint: char,
+                                                desc: &FontTemplateDescriptor)
+                                                -> Option<Arc<FontTemplateData>> {
+        match get_fallback_font_family_for_character(codepoint) {
+            Some(family) => {
+                debug!("FontList: Falling back to family={:s} for {}", family, codepoint);
+                let family = LowercaseString::new(family.as_slice());
+                self.find_font_in_local_family(&family, desc)
+            }
+            None => None,
+        }
+    }
 }
 
 /// The public interface to the font cache task, used exclusively by
@@ -309,6 +332,22 @@ impl FontCacheTask {
         }
     }
 
+    pub fn get_fallback_font_template_for_character(&self,
+                                                    codepoint: char,
+                                                    desc: FontTemplateDescriptor)
+                                                    -> Option<Arc<FontTemplateData>> {
+        let (response_chan, response_port) = channel();
+        self.chan.send(GetFallbackFontTemplateForCharacter(codepoint, desc, response_chan));
+
+        let reply = response_port.recv();
+
+        match reply {
+            GetFontTemplateReply(data) => {
+                data
+            }
+        }
+    }
+
     pub fn add_web_font(&self, family: String, src: Source) {
         let (response_chan, response_port) = channel();
         self.chan.send(AddWebFont(family, src, response_chan));
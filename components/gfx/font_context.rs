@@ -17,6 +17,7 @@ use servo_util::smallvec::{SmallVec, SmallVec8};
 use servo_util::geometry::Au;
 use servo_util::arc_ptr_eq;
 
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
 use sync::Arc;
@@ -50,6 +51,15 @@ struct FallbackFontCacheEntry {
     font: Rc<RefCell<Font>>,
 }
 
+/// The result of asking the font cache task for a system font that has a glyph for a character
+/// that no font of a font group has.
+struct SystemFallbackFontCacheEntry {
+    descriptor: FontTemplateDescriptor,
+    pt_size: Au,
+    variant: font_variant::T,
+    font: Option<Rc<RefCell<Font>>>,
+}
+
 /// A cached azure font (per render task) that
 /// can be shared by multiple text runs.
 struct RenderFontCacheEntry {
@@ -70,6 +80,16 @@ pub struct FontContext {
     layout_font_cache: Vec<LayoutFontCacheEntry>,
     fallback_font_cache: Vec<FallbackFontCacheEntry>,
 
+    /// Whether each font has a glyph for each character looked up so far. Fonts are identified by
+    /// address, which is stable because the caches above keep them alive.
+    glyph_coverage_cache: HashMap<(uint, char), bool>,
+
+    /// The system fonts found for characters that no font of a font group has a glyph for.
+    system_fallback_font_cache: HashMap<char, Vec<SystemFallbackFontCacheEntry>>,
+
+    /// The system fonts created so far, shared by all of the characters they were found for.
+    system_fallback_fonts: Vec<Rc<RefCell<Font>>>,
+
     /// Strong reference as the render FontContext is (for now) recycled
     /// per frame. TODO: Make this weak when incremental redraw is done.
     render_font_cache: Vec<RenderFontCacheEntry>,
@@ -86,6 +106,9 @@ impl FontContext {
             font_cache_task: font_cache_task,
             layout_font_cache: vec!(),
             fallback_font_cache: vec!(),
+            glyph_coverage_cache: HashMap::new(),
+            system_fallback_font_cache: HashMap::new(),
+            system_fallback_fonts: vec!(),
             render_font_cache: vec!(),
             last_style: None,
             last_fontgroup: None,
@@ -117,6 +140,7 @@ impl FontContext {
             actual_pt_size: actual_pt_size,
             metrics: metrics,
             shape_cache: HashCache::new(),
+            rtl_shape_cache: HashCache::new(),
             glyph_advance_cache: HashCache::new(),
         }
     }
@@ -227,6 +251,115 @@ impl FontContext {
         font_group
     }
 
+    /// Returns the font to lay out the given character with: the first font of the font group that
+    /// has a glyph for it, or failing that a system font that does. If no font has a glyph for
+    /// the character, the first font of the group is returned.
+    pub fn get_layout_font_for_character(&mut self, font_group: &FontGroup, codepoint: char)
+                                         -> Rc<RefCell<Font>> {
+        for font in font_group.fonts.iter() {
+            if self.font_has_glyph_for_character(font, codepoint) {
+                return font.clone()
+            }
+        }
+
+        let first_font = font_group.fonts.get(0).clone();
+        match self.get_system_fallback_font_for_character(&first_font, codepoint) {
+            Some(font) => font,
+            None => first_font,
+        }
+    }
+
+    /// Returns true if the given font has a glyph for the given character.
+    fn font_has_glyph_for_character(&mut self, font: &Rc<RefCell<Font>>, codepoint: char)
+                                    -> bool {
+        let key = (&**font as *const RefCell<Font> as uint, codepoint);
+        match self.glyph_coverage_cache.get(&key) {
+            Some(&has_glyph) => return has_glyph,
+            None => {}
+        }
+
+        let has_glyph = font.borrow().glyph_index(codepoint).is_some();
+        self.glyph_coverage_cache.insert(key, has_glyph);
+        has_glyph
+    }
+
+    /// Asks the font cache task for a system font that has a glyph for the given character, with
+    /// the same style and size as the given font.
+    fn get_system_fallback_font_for_character(&mut self,
+                                              font: &Rc<RefCell<Font>>,
+                                              codepoint: char)
+                                              -> Option<Rc<RefCell<Font>>> {
+        let (descriptor, pt_size, variant) = {
+            let font = font.borrow();
+            (font.descriptor.clone(), font.requested_pt_size, font.variant)
+        };
+
+        match self.system_fallback_font_cache.get(&codepoint) {
+            Some(entries) => {
+                for entry in entries.iter() {
+                    if entry.descriptor == descriptor && entry.pt_size == pt_size &&
+                            entry.variant == variant {
+                        return entry.font.clone()
+                    }
+                }
+            }
+            None => {}
+        }
+
+        let font_template =
+            self.font_cache_task.get_fallback_font_template_for_character(codepoint,
+                                                                          descriptor.clone());
+        let font = match font_template {
+            Some(font_template) => {
+                Some(self.get_system_fallback_font_from_template(font_template,
+                                                                 descriptor.clone(),
+                                                                 pt_size,
+                                                                 variant))
+            }
+            None => None,
+        };
+
+        let entry = SystemFallbackFontCacheEntry {
+            descriptor: descriptor,
+            pt_size: pt_size,
+            variant: variant,
+            font: font.clone(),
+        };
+        match self.system_fallback_font_cache.get_mut(&codepoint) {
+            Some(entries) => {
+                entries.push(entry);
+                return font
+            }
+            None => {}
+        }
+        self.system_fallback_font_cache.insert(codepoint, vec!(entry));
+        font
+    }
+
+    /// Returns a layout font for the given system font template, reusing the font created for an
+    /// earlier character if there is one.
+    fn get_system_fallback_font_from_template(&mut self,
+                                              font_template: Arc<FontTemplateData>,
+                                              descriptor: FontTemplateDescriptor,
+                                              pt_size: Au,
+                                              variant: font_variant::T)
+                                              -> Rc<RefCell<Font>> {
+        for cached_font_ref in self.system_fallback_fonts.iter() {
+            let cached_font = cached_font_ref.borrow();
+            if cached_font.handle.get_template().identifier == font_template.identifier &&
+                    cached_font.descriptor == descriptor &&
+                    cached_font.requested_pt_size == pt_size &&
+                    cached_font.variant == variant {
+                return (*cached_font_ref).clone()
+            }
+        }
+
+        let layout_font = self.create_layout_font(font_template, descriptor, pt_size, variant);
+        let layout_font = Rc::new(RefCell::new(layout_font));
+        self.system_fallback_fonts.push(layout_font.clone());
+        layout_font
+    }
+
     /// Create a render font for use with azure. May return a cached
     /// reference if already used by this font context.
     pub fn get_render_font_from_template(&mut self,
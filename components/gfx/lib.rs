@@ -50,7 +50,7 @@ extern crate freetype;
 #[cfg(target_os="macos")] extern crate core_graphics;
 #[cfg(target_os="macos")] extern crate core_text;
 
-pub use render_context::RenderContext;
+pub use render_context::{RenderContext, ScaledFontExtensionMethods};
 
 // Private rendering modules
 mod render_context;
@@ -7,8 +7,9 @@
 extern crate freetype;
 extern crate fontconfig;
 
-use fontconfig::fontconfig::{FcChar8, FcResultMatch, FcSetSystem};
+use fontconfig::fontconfig::{FcChar8, FcChar32, FcCharSet, FcResultMatch, FcSetSystem};
 use fontconfig::fontconfig::{
+    FcCharSetAddChar, FcCharSetCreate, FcCharSetDestroy, FcCharSetHasChar,
     FcConfigGetCurrent, FcConfigGetFonts,
     FcConfigSubstitute, FcDefaultSubstitute,
     FcFontMatch,
@@ -17,7 +18,8 @@ use fontconfig::fontconfig::{
     FcMatchPattern,
     FcPatternCreate, FcPatternAddString,
     FcFontSetList, FcObjectSetCreate, FcObjectSetDestroy,
-    FcObjectSetAdd, FcPatternGetInteger
+    FcObjectSetAdd, FcPatternGetInteger,
+    FcPatternAddCharSet, FcPatternGetCharSet
 };
 
 use libc;
@@ -28,6 +30,7 @@ use std::string;
 static FC_FAMILY: &'static [u8] = b"family\0";
 static FC_FILE: &'static [u8] = b"file\0";
 static FC_INDEX: &'static [u8] = b"index\0";
+static FC_CHARSET: &'static [u8] = b"charset\0";
 
 pub fn get_available_families(callback: |String|) {
     unsafe {
@@ -124,6 +127,52 @@ pub fn get_system_default_family(generic_name: &str) -> Option<String> {
     }
 }
 
+/// Returns the family of a system font that has a glyph for the given character, if there is one.
+pub fn get_fallback_font_family_for_character(codepoint: char) -> Option<String> {
+    unsafe {
+        let pattern = FcPatternCreate();
+        assert!(pattern.is_not_null());
+        let char_set = FcCharSetCreate();
+        assert!(char_set.is_not_null());
+        FcCharSetAddChar(char_set, codepoint as FcChar32);
+        let ok = FcPatternAddCharSet(pattern, FC_CHARSET.as_ptr() as *mut i8, char_set);
+        assert!(ok != 0);
+
+        FcConfigSubstitute(ptr::null_mut(), pattern, FcMatchPattern);
+        FcDefaultSubstitute(pattern);
+
+        let mut result = 0;
+        let font_match = FcFontMatch(ptr::null_mut(), pattern, &mut result);
+
+        // The closest match is returned even if no font has the character, so check that it does.
+        let family_name = if result == FcResultMatch {
+            let mut match_char_set: *mut FcCharSet = ptr::null_mut();
+            let mut match_string: *mut FcChar8 = ptr::null_mut();
+            let family_name = if FcPatternGetCharSet(font_match,
+                                                     FC_CHARSET.as_ptr() as *mut i8,
+                                                     0,
+                                                     &mut match_char_set) == FcResultMatch &&
+                    FcCharSetHasChar(match_char_set, codepoint as FcChar32) != 0 &&
+                    FcPatternGetString(font_match,
+                                       FC_FAMILY.as_ptr() as *mut i8,
+                                       0,
+                                       &mut match_string) == FcResultMatch {
+                Some(string::raw::from_buf(match_string as *const i8 as *const u8))
+            } else {
+                None
+            };
+            FcPatternDestroy(font_match);
+            family_name
+        } else {
+            None
+        };
+
+        FcCharSetDestroy(char_set);
+        FcPatternDestroy(pattern);
+        family_name
+    }
+}
+
 #[cfg(target_os="linux")]
 pub fn get_last_resort_font_families() -> Vec<String> {
     vec!(
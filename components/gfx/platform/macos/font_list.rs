@@ -41,6 +41,13 @@ pub fn get_system_default_family(_generic_name: &str) -> Option<String> {
     None
 }
 
+/// Returns the family of a system font that has a glyph for the given character, if there is one.
+///
+/// FIXME: Look this up with `CTFontCreateForString` once it is exposed by our Core Text bindings.
+pub fn get_fallback_font_family_for_character(_codepoint: char) -> Option<String> {
+    None
+}
+
 pub fn get_last_resort_font_families() -> Vec<String> {
     vec!("Arial Unicode MS".to_string(), "Arial".to_string())
 }
@@ -6,8 +6,9 @@
 
 use azure::azure::AzIntSize;
 use azure::azure_hl::{B8G8R8A8, A8, Color, ColorPattern, ColorPatternRef, DrawOptions};
-use azure::azure_hl::{DrawSurfaceOptions, DrawTarget, ExtendClamp, GradientStop, Linear};
-use azure::azure_hl::{LinearGradientPattern, LinearGradientPatternRef, SourceOp, StrokeOptions};
+use azure::azure_hl::{DrawSurfaceOptions, DrawTarget, ExtendClamp, ExtendRepeat, GradientStop};
+use azure::azure_hl::{Linear, LinearGradientPattern, LinearGradientPatternRef};
+use azure::azure_hl::{RadialGradientPattern, RadialGradientPatternRef, SourceOp, StrokeOptions};
 use azure::scaled_font::ScaledFont;
 use azure::{AZ_CAP_BUTT, AzFloat, struct__AzDrawOptions, struct__AzGlyph};
 use azure::{struct__AzGlyphBuffer, struct__AzPoint, AzDrawTargetFillGlyphs};
@@ -616,7 +617,7 @@ impl<'a> RenderContext<'a>  {
             .get_render_font_from_template(&text.text_run.font_template,
                                            text.text_run.actual_pt_size)
             .borrow()
-            .draw_text_into_context(self,
+            .draw_text_into_context(&self.draw_target,
                                     &*text.text_run,
                                     &text.range,
                                     baseline_origin,
@@ -635,10 +636,12 @@ impl<'a> RenderContext<'a>  {
                                 bounds: &Rect<Au>,
                                 start_point: &Point2D<Au>,
                                 end_point: &Point2D<Au>,
-                                stops: &[GradientStop]) {
+                                stops: &[GradientStop],
+                                repeating: bool) {
         self.draw_target.make_current();
 
-        let stops = self.draw_target.create_gradient_stops(stops, ExtendClamp);
+        let extend_mode = if repeating { ExtendRepeat } else { ExtendClamp };
+        let stops = self.draw_target.create_gradient_stops(stops, extend_mode);
         let pattern = LinearGradientPattern::new(&start_point.to_azure_point(),
                                                  &end_point.to_azure_point(),
                                                  stops,
@@ -649,6 +652,41 @@ impl<'a> RenderContext<'a>  {
                                    None);
     }
 
+    /// Draws a radial gradient in the given boundaries. The first stop is placed at
+    /// `start_radius` and the last at `end_radius`, both measured horizontally from `center`;
+    /// `vertical_scale` squashes the circles into ellipses.
+    pub fn draw_radial_gradient(&self,
+                                bounds: &Rect<Au>,
+                                center: &Point2D<Au>,
+                                start_radius: Au,
+                                end_radius: Au,
+                                vertical_scale: f32,
+                                stops: &[GradientStop],
+                                repeating: bool) {
+        self.draw_target.make_current();
+
+        let extend_mode = if repeating { ExtendRepeat } else { ExtendClamp };
+        let stops = self.draw_target.create_gradient_stops(stops, extend_mode);
+
+        // The pattern is circular and centered at the origin; the matrix moves it into place and
+        // scales it vertically.
+        let center = center.to_azure_point();
+        let matrix = Matrix2D::new(1.0, 0.0,
+                                   0.0, vertical_scale as AzFloat,
+                                   center.x, center.y);
+        let origin = Point2D(0.0 as AzFloat, 0.0);
+        let pattern = RadialGradientPattern::new(&origin,
+                                                 &origin,
+                                                 start_radius.to_subpx() as AzFloat,
+                                                 end_radius.to_subpx() as AzFloat,
+                                                 stops,
+                                                 &matrix);
+
+        self.draw_target.fill_rect(&bounds.to_azure_rect(),
+                                   RadialGradientPatternRef(&pattern),
+                                   None);
+    }
+
     pub fn get_or_create_temporary_draw_target(&mut self, opacity: AzFloat) -> DrawTarget {
         if opacity == 1.0 {
             return self.draw_target.clone()
@@ -753,9 +791,11 @@ impl ToRadiiPx for BorderRadii<Au> {
     }
 }
 
-trait ScaledFontExtensionMethods {
+pub trait ScaledFontExtensionMethods {
+    /// Draws the glyphs of the given range of the run onto the draw target, in the given color and
+    /// starting at the given point on the baseline.
     fn draw_text_into_context(&self,
-                              rctx: &RenderContext,
+                              target: &DrawTarget,
                               run: &Box<TextRun>,
                               range: &Range<CharIndex>,
                               baseline_origin: Point2D<Au>,
@@ -765,13 +805,12 @@ trait ScaledFontExtensionMethods {
 
 impl ScaledFontExtensionMethods for ScaledFont {
     fn draw_text_into_context(&self,
-                              rctx: &RenderContext,
+                              target: &DrawTarget,
                               run: &Box<TextRun>,
                               range: &Range<CharIndex>,
                               baseline_origin: Point2D<Au>,
                               color: Color,
                               antialias: bool) {
-        let target = rctx.get_draw_target();
         let pattern = ColorPattern::new(color);
         let azure_pattern = pattern.azure_color_pattern;
         assert!(azure_pattern.is_not_null());
@@ -791,20 +830,43 @@ impl ScaledFontExtensionMethods for ScaledFont {
         let mut azglyphs = vec!();
         azglyphs.reserve(range.length().to_uint());
 
-        for (glyphs, _offset, slice_range) in run.iter_slices_for_range(range) {
-            for (_i, glyph) in glyphs.iter_glyphs_for_char_range(&slice_range) {
-                let glyph_advance = glyph.advance();
-                let glyph_offset = glyph.offset().unwrap_or(Zero::zero());
-                let azglyph = struct__AzGlyph {
-                    mIndex: glyph.id() as uint32_t,
-                    mPosition: struct__AzPoint {
-                        x: (origin.x + glyph_offset.x).to_subpx() as AzFloat,
-                        y: (origin.y + glyph_offset.y).to_subpx() as AzFloat
-                    }
-                };
-                origin = Point2D(origin.x + glyph_advance, origin.y);
-                azglyphs.push(azglyph)
+        // Glyphs are stored in logical order, so right-to-left runs are drawn starting from their
+        // last glyph.
+        let mut glyphs_in_visual_order = vec!();
+        for (glyphs, offset, slice_range) in run.iter_slices_for_range(range) {
+            for (index, glyph) in glyphs.iter_glyphs_for_char_range(&slice_range) {
+                glyphs_in_visual_order.push((offset + index, glyph))
+            }
+        }
+        if run.is_rtl() {
+            glyphs_in_visual_order.reverse()
+        }
+
+        for &(index, glyph) in glyphs_in_visual_order.iter() {
+            let glyph_advance = run.advance_for_glyph(index, glyph);
+            let glyph_offset = glyph.offset().unwrap_or(Zero::zero());
+
+            // Upright glyphs are stacked downwards from the start of the run and centered on the
+            // baseline, which is vertical; each sits on its own horizontal baseline at the ascent
+            // below the top of its em box.
+            let glyph_origin = if run.upright {
+                Point2D(origin.x - glyph.advance() / 2, origin.y + run.ascent())
+            } else {
+                origin
+            };
+            let azglyph = struct__AzGlyph {
+                mIndex: glyph.id() as uint32_t,
+                mPosition: struct__AzPoint {
+                    x: (glyph_origin.x + glyph_offset.x).to_subpx() as AzFloat,
+                    y: (glyph_origin.y + glyph_offset.y).to_subpx() as AzFloat
+                }
+            };
+            origin = if run.upright {
+                Point2D(origin.x, origin.y + glyph_advance)
+            } else {
+                Point2D(origin.x + glyph_advance, origin.y)
             };
+            azglyphs.push(azglyph)
         }
 
         let azglyph_buf_len = azglyphs.len();
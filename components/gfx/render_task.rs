@@ -23,6 +23,7 @@ use layers;
 use native::task::NativeTaskBuilder;
 use servo_msg::compositor_msg::{Epoch, IdleRenderState, LayerId};
 use servo_msg::compositor_msg::{LayerMetadata, RenderListener, RenderingRenderState, ScrollPolicy};
+use servo_msg::compositor_msg::{Pagination, Scrollport};
 use servo_msg::constellation_msg::{ConstellationChan, Failure, FailureMsg, PipelineId};
 use servo_msg::constellation_msg::{RendererReadyMsg};
 use servo_msg::platform::surface::NativeSurfaceAzureMethods;
@@ -47,15 +48,26 @@ pub struct RenderLayer {
     pub background_color: Color,
     /// The scrolling policy of this layer.
     pub scroll_policy: ScrollPolicy,
+    /// The scrollport of this layer, if its contents scroll independently of the rest of the
+    /// page.
+    pub scrollport: Option<Scrollport>,
+    /// The pages of this layer, if it is the root layer of a paginated document.
+    pub pagination: Option<Pagination>,
 }
 
 impl RenderLayer {
     /// Creates a new `RenderLayer`.
-    pub fn new(id: LayerId, background_color: Color, scroll_policy: ScrollPolicy) -> RenderLayer {
+    pub fn new(id: LayerId,
+               background_color: Color,
+               scroll_policy: ScrollPolicy,
+               scrollport: Option<Scrollport>)
+               -> RenderLayer {
         RenderLayer {
             id: id,
             background_color: background_color,
             scroll_policy: scroll_policy,
+            scrollport: scrollport,
+            pagination: None,
         }
     }
 }
@@ -157,6 +169,8 @@ fn initialize_layers<C>(compositor: &mut C,
                                     stacking_context.bounds.size.height.to_nearest_px() as uint)),
                     background_color: render_layer.background_color,
                     scroll_policy: render_layer.scroll_policy,
+                    scrollport: render_layer.scrollport,
+                    pagination: render_layer.pagination,
                 })
             }
         }
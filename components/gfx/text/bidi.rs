@@ -0,0 +1,442 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The Unicode Bidirectional Algorithm (UAX #9), which resolves the embedding levels that
+//! determine the visual order of mixed left-to-right and right-to-left text.
+//!
+//! See http://www.unicode.org/reports/tr9/. Explicit embeddings come from the CSS `direction` and
+//! `unicode-bidi` properties rather than from formatting characters, so the explicit formatting
+//! characters are treated as boundary neutrals.
+
+use std::cmp;
+
+/// The deepest embedding level allowed, per rule BD2.
+pub static MAX_DEPTH: u8 = 61;
+
+/// The bidirectional character types of UAX #9 that we distinguish.
+#[deriving(Clone, PartialEq, Show)]
+pub enum BidiClass {
+    /// Left-to-right.
+    L,
+    /// Right-to-left.
+    R,
+    /// Right-to-left Arabic.
+    AL,
+    /// European number.
+    EN,
+    /// European number separator.
+    ES,
+    /// European number terminator.
+    ET,
+    /// Arabic number.
+    AN,
+    /// Common number separator.
+    CS,
+    /// Nonspacing mark.
+    NSM,
+    /// Boundary neutral.
+    BN,
+    /// Paragraph separator.
+    B,
+    /// Segment separator.
+    S,
+    /// Whitespace.
+    WS,
+    /// Other neutrals.
+    ON,
+}
+
+/// Returns the bidirectional character type of the given character.
+pub fn bidi_class(ch: char) -> BidiClass {
+    match ch {
+        '\n' | '\r' | '\u001C'...'\u001E' | '\u0085' | '\u2029' => B,
+        '\t' | '\u000B' | '\u001F' => S,
+        '\u000C' | ' ' | '\u1680' | '\u2000'...'\u200A' | '\u2028' | '\u205F' | '\u3000' => WS,
+        '\u0000'...'\u0008' | '\u000E'...'\u001B' | '\u007F'...'\u0084' |
+        '\u0086'...'\u009F' | '\u00AD' | '\u200B'...'\u200D' | '\u202A'...'\u202E' |
+        '\u2060'...'\u206F' | '\uFEFF' => BN,
+        '0'...'9' | '\u00B2' | '\u00B3' | '\u00B9' | '\u06F0'...'\u06F9' | '\u2070' |
+        '\u2074'...'\u2079' | '\u2080'...'\u2089' | '\uFF10'...'\uFF19' => EN,
+        '+' | '-' | '\u207A' | '\u207B' | '\u208A' | '\u208B' | '\u2212' | '\uFB29' |
+        '\uFE62' | '\uFE63' | '\uFF0B' | '\uFF0D' => ES,
+        '#'...'%' | '\u00A2'...'\u00A5' | '\u00B0' | '\u00B1' | '\u0609' | '\u060A' |
+        '\u066A' | '\u09F2' | '\u09F3' | '\u0E3F' | '\u2030'...'\u2034' | '\u20A0'...'\u20CF' |
+        '\u212E' | '\u2213' | '\uFE5F' | '\uFE69' | '\uFE6A' | '\uFF03'...'\uFF05' | '\uFFE0' |
+        '\uFFE1' | '\uFFE5' | '\uFFE6' => ET,
+        '\u0600'...'\u0605' | '\u0660'...'\u0669' | '\u066B' | '\u066C' | '\u06DD' => AN,
+        ',' | '.' | '/' | ':' | '\u00A0' | '\u060C' | '\u202F' | '\u2044' | '\uFE50' |
+        '\uFE52' | '\uFE55' | '\uFF0C' | '\uFF0E' | '\uFF0F' | '\uFF1A' => CS,
+        '\u0300'...'\u036F' | '\u0483'...'\u0489' | '\u0591'...'\u05BD' | '\u05BF' |
+        '\u05C1' | '\u05C2' | '\u05C4' | '\u05C5' | '\u05C7' | '\u0610'...'\u061A' |
+        '\u064B'...'\u065F' | '\u0670' | '\u06D6'...'\u06DC' | '\u06DF'...'\u06E4' |
+        '\u06E7' | '\u06E8' | '\u06EA'...'\u06ED' | '\u0711' | '\u0730'...'\u074A' |
+        '\u0900'...'\u0902' | '\u093C' | '\u0941'...'\u0948' | '\u094D' | '\u20D0'...'\u20F0' |
+        '\uFB1E' | '\uFE00'...'\uFE0F' | '\uFE20'...'\uFE2F' => NSM,
+        '\u05BE' | '\u05C0' | '\u05C3' | '\u05C6' | '\u05D0'...'\u05FF' | '\u07C0'...'\u085F' |
+        '\uFB1D'...'\uFB4F' | '\U00010800'...'\U00010FFF' | '\U0001E800'...'\U0001EDFF' => R,
+        '\u0608' | '\u060B' | '\u060D' | '\u061B'...'\u064A' | '\u066D'...'\u066F' |
+        '\u0671'...'\u06D5' | '\u06E5' | '\u06E6' | '\u06EE' | '\u06EF' | '\u06FA'...'\u070D' |
+        '\u0710' | '\u0712'...'\u072F' | '\u074D'...'\u07A5' | '\u07B1' | '\u0860'...'\u08FF' |
+        '\uFB50'...'\uFDFF' | '\uFE70'...'\uFEFE' | '\U0001EE00'...'\U0001EEFF' => AL,
+        '!' | '"' | '&'...'*' | ';'...'@' | '['...'`' | '{'...'~' | '\u00A1' |
+        '\u00A6'...'\u00A9' | '\u00AB' | '\u00AC' | '\u00AE' | '\u00AF' | '\u00B4' |
+        '\u00B6'...'\u00B8' | '\u00BB'...'\u00BF' | '\u00D7' | '\u00F7' | '\u2010'...'\u2027' |
+        '\u2035'...'\u2043' | '\u2045'...'\u205E' | '\u2190'...'\u2211' | '\u2214'...'\u2335' |
+        '\u2500'...'\u27FF' | '\u2900'...'\u2BFF' | '\u3001'...'\u3004' | '\u3008'...'\u3020' |
+        '\uFD3E' | '\uFD3F' | '\uFE10'...'\uFE19' | '\uFE30'...'\uFE4F' | '\uFE51' | '\uFE54' |
+        '\uFE56'...'\uFE5E' | '\uFE60' | '\uFE61' | '\uFE64'...'\uFE68' | '\uFE6B' | '\uFF01' |
+        '\uFF02' | '\uFF06'...'\uFF0A' | '\uFF1B'...'\uFF20' | '\uFF3B'...'\uFF40' |
+        '\uFF5B'...'\uFF65' | '\uFFFC' | '\uFFFD' => ON,
+        _ => L,
+    }
+}
+
+/// Whether the characters of an embedding have their directionality overridden, as by
+/// `unicode-bidi: bidi-override`.
+#[deriving(Clone, PartialEq, Show)]
+pub enum DirectionalOverride {
+    NoOverride,
+    OverrideLeftToRight,
+    OverrideRightToLeft,
+}
+
+/// The explicit embedding level and directional override status of a character, per rules
+/// X1 - X8.
+#[deriving(Clone, PartialEq, Show)]
+pub struct ExplicitEmbedding {
+    pub level: u8,
+    pub directional_override: DirectionalOverride,
+}
+
+impl ExplicitEmbedding {
+    /// Returns the embedding in effect at the start of a paragraph with the given level.
+    pub fn new(paragraph_level: u8) -> ExplicitEmbedding {
+        ExplicitEmbedding {
+            level: paragraph_level,
+            directional_override: NoOverride,
+        }
+    }
+
+    /// Returns the embedding nested within this one, as if by an RLE, LRE, RLO or LRO character
+    /// (rules X2 - X5). Embeddings deeper than `MAX_DEPTH` are ignored.
+    pub fn push(&self, rtl: bool, is_override: bool) -> ExplicitEmbedding {
+        let level = if rtl {
+            (self.level + 1) | 1
+        } else {
+            (self.level + 2) & !1
+        };
+        if level > MAX_DEPTH {
+            return *self
+        }
+        ExplicitEmbedding {
+            level: level,
+            directional_override: match (is_override, rtl) {
+                (false, _) => NoOverride,
+                (true, false) => OverrideLeftToRight,
+                (true, true) => OverrideRightToLeft,
+            },
+        }
+    }
+}
+
+/// Returns true if the given embedding level is right-to-left.
+#[inline]
+pub fn is_rtl_level(level: u8) -> bool {
+    level % 2 == 1
+}
+
+/// Returns the strong type matching the direction of the given embedding level.
+fn class_for_level(level: u8) -> BidiClass {
+    if is_rtl_level(level) {
+        R
+    } else {
+        L
+    }
+}
+
+/// Resolves the embedding level of each character of a paragraph. `explicit_embeddings` holds
+/// the explicit embedding of each character, as established by its ancestors' styles.
+pub fn resolve_levels(text: &str, paragraph_level: u8, explicit_embeddings: &[ExplicitEmbedding])
+                      -> Vec<u8> {
+    let original_classes: Vec<BidiClass> = text.chars().map(bidi_class).collect();
+    assert!(original_classes.len() == explicit_embeddings.len());
+
+    // X6: Overrides replace the types of the characters they apply to.
+    let mut classes: Vec<BidiClass> = original_classes.iter().zip(explicit_embeddings.iter())
+                                                      .map(|(class, embedding)| {
+        match embedding.directional_override {
+            NoOverride => *class,
+            OverrideLeftToRight => L,
+            OverrideRightToLeft => R,
+        }
+    }).collect();
+    let explicit_levels: Vec<u8> = explicit_embeddings.iter().map(|embedding| {
+        embedding.level
+    }).collect();
+    let mut levels = explicit_levels.clone();
+
+    // X10: Resolve each level run separately, bounded by the start-of-sequence and
+    // end-of-sequence types derived from the neighboring levels.
+    let length = explicit_levels.len();
+    let mut start = 0;
+    while start < length {
+        let level = explicit_levels[start];
+        let mut end = start + 1;
+        while end < length && explicit_levels[end] == level {
+            end += 1
+        }
+
+        let previous_level = if start == 0 {
+            paragraph_level
+        } else {
+            explicit_levels[start - 1]
+        };
+        let next_level = if end == length {
+            paragraph_level
+        } else {
+            explicit_levels[end]
+        };
+        let sos = class_for_level(cmp::max(previous_level, level));
+        let eos = class_for_level(cmp::max(next_level, level));
+
+        resolve_weak_types(classes.slice_mut(start, end), sos);
+        resolve_neutral_types(classes.slice_mut(start, end), sos, eos, level);
+        resolve_implicit_levels(classes.slice(start, end), levels.slice_mut(start, end));
+        start = end;
+    }
+
+    // L1: Separators, and any whitespace before them or at the end of the paragraph, are reset
+    // to the paragraph level.
+    let mut reset_from = length;
+    for index in range(0, length).rev() {
+        match original_classes[index] {
+            B | S => {
+                levels[index] = paragraph_level;
+                reset_from = index
+            }
+            WS | BN if reset_from == index + 1 => {
+                levels[index] = paragraph_level;
+                reset_from = index
+            }
+            _ => {}
+        }
+    }
+
+    levels
+}
+
+/// Resolves the weak types of one level run, per rules W1 - W7.
+fn resolve_weak_types(classes: &mut [BidiClass], sos: BidiClass) {
+    // W1: Nonspacing marks take the type of the previous character. Boundary neutrals, which
+    // rule X9 removes, are treated the same way.
+    let mut previous = sos;
+    for class in classes.iter_mut() {
+        match *class {
+            NSM | BN => *class = previous,
+            _ => {}
+        }
+        previous = *class
+    }
+
+    // W2: European numbers after Arabic letters are Arabic numbers. W3: Arabic letters are
+    // right-to-left.
+    let mut last_strong = sos;
+    for class in classes.iter_mut() {
+        match *class {
+            L | R | AL => last_strong = *class,
+            EN if last_strong == AL => *class = AN,
+            _ => {}
+        }
+    }
+    for class in classes.iter_mut() {
+        if *class == AL {
+            *class = R
+        }
+    }
+
+    // W4: A single separator between two numbers of the same type takes their type.
+    for index in range(1, cmp::max(classes.len(), 1) - 1) {
+        let (before, after) = (classes[index - 1], classes[index + 1]);
+        match (before, classes[index], after) {
+            (EN, ES, EN) | (EN, CS, EN) => classes[index] = EN,
+            (AN, CS, AN) => classes[index] = AN,
+            _ => {}
+        }
+    }
+
+    // W5: Terminators adjacent to European numbers become European numbers.
+    let mut index = 0;
+    while index < classes.len() {
+        if classes[index] != ET {
+            index += 1;
+            continue
+        }
+        let mut end = index;
+        while end < classes.len() && classes[end] == ET {
+            end += 1
+        }
+        let touches_number = (index > 0 && classes[index - 1] == EN) ||
+            (end < classes.len() && classes[end] == EN);
+        if touches_number {
+            for class in classes.slice_mut(index, end).iter_mut() {
+                *class = EN
+            }
+        }
+        index = end
+    }
+
+    // W6: Remaining separators and terminators are neutral.
+    for class in classes.iter_mut() {
+        match *class {
+            ES | ET | CS => *class = ON,
+            _ => {}
+        }
+    }
+
+    // W7: European numbers after left-to-right text are left-to-right.
+    let mut last_strong = sos;
+    for class in classes.iter_mut() {
+        match *class {
+            L | R => last_strong = *class,
+            EN if last_strong == L => *class = L,
+            _ => {}
+        }
+    }
+}
+
+/// Resolves the neutral types of one level run, per rules N1 and N2.
+fn resolve_neutral_types(classes: &mut [BidiClass],
+                         sos: BidiClass,
+                         eos: BidiClass,
+                         level: u8) {
+    // Numbers count as right-to-left text for the purposes of rule N1.
+    fn strong_direction(class: BidiClass) -> Option<BidiClass> {
+        match class {
+            L => Some(L),
+            R | EN | AN => Some(R),
+            _ => None,
+        }
+    }
+
+    let mut index = 0;
+    while index < classes.len() {
+        if strong_direction(classes[index]).is_some() {
+            index += 1;
+            continue
+        }
+        let mut end = index;
+        while end < classes.len() && strong_direction(classes[end]).is_none() {
+            end += 1
+        }
+
+        let before = if index == 0 {
+            sos
+        } else {
+            strong_direction(classes[index - 1]).unwrap()
+        };
+        let after = if end == classes.len() {
+            eos
+        } else {
+            strong_direction(classes[end]).unwrap()
+        };
+
+        // N1: Neutrals between text of the same direction take that direction. N2: Otherwise,
+        // they take the embedding direction.
+        let resolved = if before == after {
+            before
+        } else {
+            class_for_level(level)
+        };
+        for class in classes.slice_mut(index, end).iter_mut() {
+            *class = resolved
+        }
+        index = end
+    }
+}
+
+/// Resolves the final levels of one level run from its resolved types, per rules I1 and I2.
+fn resolve_implicit_levels(classes: &[BidiClass], levels: &mut [u8]) {
+    for (class, level) in classes.iter().zip(levels.iter_mut()) {
+        *level = match (is_rtl_level(*level), *class) {
+            (false, R) => *level + 1,
+            (false, AN) | (false, EN) => *level + 2,
+            (true, L) | (true, EN) | (true, AN) => *level + 1,
+            _ => *level,
+        }
+    }
+}
+
+/// Returns the indices of items with the given resolved levels in visual, left-to-right order,
+/// per rule L2: from the highest level down to the lowest odd level, every maximal sequence of
+/// items at that level or higher is reversed.
+pub fn visual_order(levels: &[u8]) -> Vec<uint> {
+    let mut order: Vec<uint> = range(0, levels.len()).collect();
+    let mut visual_levels = levels.to_vec();
+    let (highest_level, lowest_level) = match (levels.iter().max(), levels.iter().min()) {
+        (Some(&highest_level), Some(&lowest_level)) => (highest_level, lowest_level),
+        _ => return order,
+    };
+
+    let lowest_odd_level = lowest_level | 1;
+    let mut level = highest_level;
+    while level >= lowest_odd_level {
+        let mut start = 0;
+        while start < visual_levels.len() {
+            if visual_levels[start] < level {
+                start += 1;
+                continue
+            }
+            let mut end = start;
+            while end < visual_levels.len() && visual_levels[end] >= level {
+                end += 1
+            }
+            order.slice_mut(start, end).reverse();
+            visual_levels.slice_mut(start, end).reverse();
+            start = end
+        }
+        level -= 1
+    }
+    order
+}
+
+#[cfg(test)]
+fn levels_for_paragraph(text: &str, paragraph_level: u8) -> Vec<u8> {
+    let explicit_embeddings = Vec::from_elem(text.char_len(),
+                                             ExplicitEmbedding::new(paragraph_level));
+    resolve_levels(text, paragraph_level, explicit_embeddings.as_slice())
+}
+
+#[test]
+fn test_left_to_right_paragraph() {
+    assert_eq!(levels_for_paragraph("ab 12", 0), vec!(0, 0, 0, 0, 0));
+    assert_eq!(levels_for_paragraph("ab \u05D0\u05D1", 0), vec!(0, 0, 0, 1, 1));
+}
+
+#[test]
+fn test_right_to_left_paragraph() {
+    assert_eq!(levels_for_paragraph("\u05D0 ab \u05D1", 1), vec!(1, 1, 2, 2, 1, 1));
+    // Numbers in right-to-left text are laid out left to right.
+    assert_eq!(levels_for_paragraph("\u05D0 12", 1), vec!(1, 1, 2, 2));
+}
+
+#[test]
+fn test_arabic_numbers() {
+    // W2: European digits after Arabic letters become Arabic numbers.
+    assert_eq!(levels_for_paragraph("\u0627 1", 0), vec!(1, 1, 2));
+}
+
+#[test]
+fn test_directional_override() {
+    let embedding = ExplicitEmbedding::new(0).push(true, true);
+    assert_eq!(embedding.level, 1);
+    let explicit_embeddings = Vec::from_elem(2, embedding);
+    assert_eq!(resolve_levels("ab", 0, explicit_embeddings.as_slice()), vec!(1, 1));
+}
+
+#[test]
+fn test_visual_order() {
+    assert_eq!(visual_order(&[0, 0, 1, 1, 0]), vec!(0, 1, 3, 2, 4));
+    assert_eq!(visual_order(&[1, 2, 2, 1]), vec!(3, 1, 2, 0));
+    assert_eq!(visual_order(&[]), vec!());
+}
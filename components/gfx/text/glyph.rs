@@ -469,6 +469,28 @@ impl<'a> GlyphInfo<'a> {
         }
     }
 
+    /// Returns the advance of this glyph plus any extra spacing that follows it. If this is the
+    /// final glyph of its character, `letter_spacing` is added, and `word_spacing` is added too if
+    /// that character is a space. Measurement and painting both use this so that they agree on
+    /// glyph positions.
+    #[inline]
+    pub fn advance_with_spacing(self, letter_spacing: Au, word_spacing: Au) -> Au {
+        let (store, entry_i, is_final_glyph_of_char) = match self {
+            SimpleGlyphInfo(store, entry_i) => (store, entry_i, true),
+            DetailGlyphInfo(store, entry_i, detail_j) => {
+                let glyph_count = store.entry_buffer[entry_i.to_uint()].glyph_count();
+                (store, entry_i, detail_j + 1 == glyph_count)
+            }
+        };
+        if !is_final_glyph_of_char {
+            self.advance()
+        } else if store.char_is_space(entry_i) {
+            self.advance() + letter_spacing + word_spacing
+        } else {
+            self.advance() + letter_spacing
+        }
+    }
+
     pub fn offset(self) -> Option<Point2D<Au>> {
         match self {
             SimpleGlyphInfo(_, _) => None,
@@ -625,10 +647,23 @@ impl<'a> GlyphStore {
         }
     }
 
+    /// Returns the total advance of the glyphs in the given character range, including letter and
+    /// word spacing; see `GlyphInfo::advance_with_spacing`.
     #[inline]
-    pub fn advance_for_char_range(&self, rang: &Range<CharIndex>) -> Au {
-        self.iter_glyphs_for_char_range(rang)
-            .fold(Au(0), |advance, (_, glyph)| advance + glyph.advance())
+    pub fn advance_for_char_range(&self,
+                                  rang: &Range<CharIndex>,
+                                  letter_spacing: Au,
+                                  word_spacing: Au)
+                                  -> Au {
+        self.iter_glyphs_for_char_range(rang).fold(Au(0), |advance, (_, glyph)| {
+            advance + glyph.advance_with_spacing(letter_spacing, word_spacing)
+        })
+    }
+
+    /// Returns the number of space characters in the given character range. These are the
+    /// expansion opportunities used by `text-align: justify`.
+    pub fn space_count_in_range(&self, rang: &Range<CharIndex>) -> int {
+        rang.each_index().filter(|&i| self.char_is_space(i)).count() as int
     }
 
     // getter methods
@@ -0,0 +1,315 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Line break opportunities, following the Unicode Line Breaking Algorithm (UAX #14).
+//!
+//! See http://www.unicode.org/reports/tr14/.
+
+use style::computed_values::word_break;
+
+/// The line breaking classes of UAX #14 that we distinguish. Classes that we don't distinguish
+/// are resolved to the closest class below by `line_break_class`: complex context dependent
+/// (SA), ambiguous (AI), and surrogate (SG) characters are treated as alphabetic, conditional
+/// Japanese starters (CJ) as nonstarters, and Hangul syllables (H2, H3, JL, JV, JT) as
+/// ideographic.
+#[deriving(Clone, PartialEq, Show)]
+pub enum LineBreakClass {
+    /// Mandatory break.
+    BK,
+    /// Carriage return.
+    CR,
+    /// Line feed.
+    LF,
+    /// Next line.
+    NL,
+    /// Space.
+    SP,
+    /// Zero width space.
+    ZW,
+    /// Word joiner.
+    WJ,
+    /// Non-breaking ("glue").
+    GL,
+    /// Combining mark.
+    CM,
+    /// Open punctuation.
+    OP,
+    /// Close punctuation.
+    CL,
+    /// Close parenthesis.
+    CP,
+    /// Quotation.
+    QU,
+    /// Exclamation or interrogation.
+    EX,
+    /// Infix numeric separator.
+    IS,
+    /// Symbols allowing break after.
+    SY,
+    /// Prefix numeric.
+    PR,
+    /// Postfix numeric.
+    PO,
+    /// Numeric.
+    NU,
+    /// Alphabetic.
+    AL,
+    /// Ideographic.
+    ID,
+    /// Inseparable.
+    IN,
+    /// Hyphen.
+    HY,
+    /// Break after.
+    BA,
+    /// Break before.
+    BB,
+    /// Break opportunity before and after.
+    B2,
+    /// Nonstarter.
+    NS,
+}
+
+/// Returns the line breaking class of the given character.
+pub fn line_break_class(ch: char) -> LineBreakClass {
+    match ch {
+        '\n' => LF,
+        '\r' => CR,
+        '\u000B' | '\u000C' | '\u2028' | '\u2029' => BK,
+        '\u0085' => NL,
+        ' ' => SP,
+        '\t' | '\u00AD' | '|' | '\u05BE' | '\u1680' | '\u2000'...'\u2006' |
+        '\u2008'...'\u200A' | '\u2010' | '\u2012' | '\u2013' | '\u2027' | '\u205F' |
+        '\u3000' => BA,
+        '\u200B' => ZW,
+        '\u2060' | '\uFEFF' => WJ,
+        '\u00A0' | '\u180E' | '\u2007' | '\u2011' | '\u202F' => GL,
+        '\u0300'...'\u036F' | '\u0483'...'\u0489' | '\u0591'...'\u05BD' |
+        '\u0610'...'\u061A' | '\u064B'...'\u065F' | '\u200C'...'\u200F' |
+        '\u20D0'...'\u20FF' | '\u3099' | '\u309A' | '\uFE00'...'\uFE0F' |
+        '\uFE20'...'\uFE2F' => CM,
+        '(' | '[' | '{' | '\u00A1' | '\u00BF' | '\u201A' | '\u201E' | '\u3008' | '\u300A' |
+        '\u300C' | '\u300E' | '\u3010' | '\u3014' | '\u3016' | '\u3018' | '\u301A' |
+        '\u301D' | '\uFF08' | '\uFF3B' | '\uFF5B' => OP,
+        '}' | '\u3001' | '\u3002' | '\u3009' | '\u300B' | '\u300D' | '\u300F' | '\u3011' |
+        '\u3015' | '\u3017' | '\u3019' | '\u301B' | '\u301E' | '\u301F' | '\uFE50' |
+        '\uFE52' | '\uFF0C' | '\uFF0E' | '\uFF5D' => CL,
+        ')' | ']' | '\uFF09' | '\uFF3D' => CP,
+        '"' | '\'' | '\u00AB' | '\u00BB' | '\u2018' | '\u2019' | '\u201B'...'\u201D' |
+        '\u201F' | '\u2039' | '\u203A' => QU,
+        '!' | '?' | '\u061F' | '\uFF01' | '\uFF1F' => EX,
+        ',' | '.' | ':' | ';' | '\u037E' | '\u0589' | '\u060C' | '\u060D' | '\u07F8' |
+        '\u2044' | '\uFE10' | '\uFE13' | '\uFE14' => IS,
+        '/' => SY,
+        '$' | '+' | '\\' | '\u00A3' | '\u00A5' | '\u00B1' | '\u20A0'...'\u20CF' | '\u2116' |
+        '\uFE69' | '\uFF04' | '\uFFE1' | '\uFFE5' | '\uFFE6' => PR,
+        '%' | '\u00A2' | '\u00B0' | '\u2030'...'\u2037' | '\u2103' | '\u2109' | '\uFE6A' |
+        '\uFF05' | '\uFFE0' => PO,
+        '0'...'9' | '\u0660'...'\u0669' | '\u06F0'...'\u06F9' | '\u0966'...'\u096F' => NU,
+        '-' => HY,
+        '\u00B4' | '\u02C8' | '\u02CC' | '\u02DF' => BB,
+        '\u2014' | '\u2E3A' | '\u2E3B' => B2,
+        '\u2024'...'\u2026' | '\u22EF' | '\uFE19' => IN,
+        '\u17D6' | '\u203C' | '\u203D' | '\u2047'...'\u2049' | '\u3005' | '\u301C' |
+        '\u303B' | '\u303C' | '\u309B'...'\u309E' | '\u30A0' | '\u30FB' | '\u30FD' |
+        '\u30FE' | '\uA015' | '\uFE54' | '\uFE55' | '\uFF1A' | '\uFF1B' | '\uFF65' |
+        '\uFF9E' | '\uFF9F' => NS,
+        // Small kana and the prolonged sound mark (CJ), which we treat as nonstarters, as in
+        // strict line breaking.
+        '\u3041' | '\u3043' | '\u3045' | '\u3047' | '\u3049' | '\u3063' | '\u3083' |
+        '\u3085' | '\u3087' | '\u308E' | '\u3095' | '\u3096' | '\u30A1' | '\u30A3' |
+        '\u30A5' | '\u30A7' | '\u30A9' | '\u30C3' | '\u30E3' | '\u30E5' | '\u30E7' |
+        '\u30EE' | '\u30F5' | '\u30F6' | '\u30FC' | '\u31F0'...'\u31FF' |
+        '\uFF67'...'\uFF70' => NS,
+        '\u2E80'...'\u2FFF' | '\u3003'...'\u3007' | '\u3012' | '\u3013' |
+        '\u3020'...'\u303A' | '\u303D'...'\u303F' | '\u3040'...'\u309F' |
+        '\u30A0'...'\u30FF' | '\u3100'...'\u31EF' | '\u3200'...'\u4DBF' |
+        '\u4E00'...'\u9FFF' | '\uA000'...'\uA48F' | '\uAC00'...'\uD7A3' |
+        '\uF900'...'\uFAFF' | '\uFE30'...'\uFE4F' | '\uFF00'...'\uFF60' |
+        '\U0001F000'...'\U0001FAFF' | '\U00020000'...'\U0003FFFD' => ID,
+        _ => AL,
+    }
+}
+
+/// Finds line break opportunities in a stream of characters.
+///
+/// Characters are fed one at a time to `can_break_before`, which returns true if a line may be
+/// broken between the previously-fed character and the new one.
+pub struct LineBreaker {
+    /// The value of the `word-break` property, which adjusts the classes of letters and
+    /// ideographs.
+    word_break: word_break::T,
+    /// The class of the previous character, or `None` at the start of the text.
+    previous: Option<LineBreakClass>,
+    /// The class of the last character that was not a space.
+    before_spaces: Option<LineBreakClass>,
+}
+
+impl LineBreaker {
+    pub fn new(word_break: word_break::T) -> LineBreaker {
+        LineBreaker {
+            word_break: word_break,
+            previous: None,
+            before_spaces: None,
+        }
+    }
+
+    /// Returns true if a line may be broken between the previous character and `ch`. Mandatory
+    /// breaks after newlines are reported as break opportunities too; callers handle forced
+    /// line breaks separately.
+    pub fn can_break_before(&mut self, ch: char) -> bool {
+        let mut class = self.adjust_class_for_word_break(line_break_class(ch));
+        let (previous, before_spaces) = match (self.previous, self.before_spaces) {
+            (Some(previous), Some(before_spaces)) => (previous, before_spaces),
+            _ => {
+                // LB2: Never break at the start of text. LB10: Treat a lone combining mark as
+                // alphabetic.
+                if class == CM {
+                    class = AL
+                }
+                self.previous = Some(class);
+                self.before_spaces = Some(class);
+                return false
+            }
+        };
+
+        if class == CM {
+            match previous {
+                // LB10: Treat a combining mark that follows a space or a break as alphabetic.
+                BK | CR | LF | NL | SP | ZW => class = AL,
+                // LB9: Otherwise, a combining mark takes the class of its base character.
+                _ => return false,
+            }
+        }
+
+        let result = can_break_between(previous, before_spaces, class);
+        self.previous = Some(class);
+        if class != SP {
+            self.before_spaces = Some(class)
+        }
+        result
+    }
+
+    /// CSS Text Level 3 § 5.2: `break-all` treats letters and numbers as ideographs, allowing
+    /// breaks between any of them, while `keep-all` forbids breaks between ideographs as if they
+    /// were letters.
+    fn adjust_class_for_word_break(&self, class: LineBreakClass) -> LineBreakClass {
+        match (self.word_break, class) {
+            (word_break::break_all, AL) | (word_break::break_all, NU) => ID,
+            (word_break::keep_all, ID) => AL,
+            _ => class,
+        }
+    }
+}
+
+/// Returns true if a line may be broken before a character of class `class` that immediately
+/// follows a character of class `previous`. `before_spaces` is the class of the last character
+/// that was not a space, which differs from `previous` if `previous` is a space.
+fn can_break_between(previous: LineBreakClass, before_spaces: LineBreakClass, class: LineBreakClass)
+                     -> bool {
+    match previous {
+        // LB4, LB5: Always break after hard line breaks.
+        BK | LF | NL => return true,
+        CR => return class != LF,
+        _ => {}
+    }
+    match class {
+        // LB6: Do not break before hard line breaks. LB7: Do not break before spaces or zero
+        // width space.
+        BK | CR | LF | NL | SP | ZW => return false,
+        _ => {}
+    }
+    // LB8: Break after zero width space, even if spaces follow it.
+    if before_spaces == ZW {
+        return true
+    }
+    // LB11: Do not break before or after word joiners. LB12: Do not break after glue.
+    if class == WJ || previous == WJ || previous == GL {
+        return false
+    }
+
+    if previous == SP {
+        return match (before_spaces, class) {
+            // LB13: Do not break before closing punctuation, even after spaces.
+            (_, CL) | (_, CP) | (_, EX) | (_, IS) | (_, SY) => false,
+            // LB14 - LB17: Rules that prohibit breaks even with intervening spaces.
+            (OP, _) | (QU, OP) | (CL, NS) | (CP, NS) | (B2, B2) => false,
+            // LB18: Otherwise, break after spaces.
+            _ => true,
+        }
+    }
+
+    match (previous, class) {
+        // LB12a: Do not break before glue, except after spaces and hyphens.
+        (BA, GL) | (HY, GL) => true,
+        (_, GL) => false,
+        // LB13
+        (_, CL) | (_, CP) | (_, EX) | (_, IS) | (_, SY) => false,
+        // LB14 - LB17
+        (OP, _) | (QU, OP) | (CL, NS) | (CP, NS) | (B2, B2) => false,
+        // LB19: Do not break around quotation marks.
+        (_, QU) | (QU, _) => false,
+        // LB21: Do not break before hyphen-like characters and small kana, or after acute
+        // accents.
+        (_, BA) | (_, HY) | (_, NS) | (BB, _) => false,
+        // LB22: Do not break between two ellipses, or between letters, numbers or exclamations
+        // and ellipses.
+        (_, IN) => false,
+        // LB23, LB23a: Do not break between digits and letters, or within prefixed and
+        // postfixed ideographs.
+        (AL, NU) | (NU, AL) | (PR, ID) | (ID, PO) => false,
+        // LB24: Do not break between numeric prefixes or postfixes and letters.
+        (PR, AL) | (PO, AL) | (AL, PR) | (AL, PO) => false,
+        // LB25: Do not break within numbers.
+        (CL, PO) | (CP, PO) | (CL, PR) | (CP, PR) | (NU, PO) | (NU, PR) | (PO, OP) |
+        (PO, NU) | (PR, OP) | (PR, NU) | (HY, NU) | (IS, NU) | (NU, NU) | (SY, NU) => false,
+        // LB28: Do not break between alphabetics. LB29: Do not break between numeric
+        // punctuation and alphabetics.
+        (AL, AL) | (IS, AL) => false,
+        // LB30: Do not break between letters or numbers and parentheses.
+        (AL, OP) | (NU, OP) | (CP, AL) | (CP, NU) => false,
+        // LB31: Break everywhere else.
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+fn break_opportunities(text: &str, word_break: word_break::T) -> Vec<uint> {
+    let mut breaker = LineBreaker::new(word_break);
+    text.chars().enumerate().filter_map(|(i, ch)| {
+        if breaker.can_break_before(ch) { Some(i) } else { None }
+    }).collect()
+}
+
+#[test]
+fn test_break_after_spaces() {
+    assert_eq!(break_opportunities("foo bar  baz", word_break::normal), vec!(4, 9));
+}
+
+#[test]
+fn test_break_after_hyphens() {
+    assert_eq!(break_opportunities("well-known -5", word_break::normal), vec!(5, 11));
+}
+
+#[test]
+fn test_no_break_before_closing_punctuation() {
+    assert_eq!(break_opportunities("(foo) bar, baz !", word_break::normal), vec!(6, 11));
+}
+
+#[test]
+fn test_break_between_ideographs() {
+    // A break between each ideograph, but not before the ideographic full stop.
+    assert_eq!(break_opportunities("\u65E5\u672C\u8A9E\u3002", word_break::normal), vec!(1, 2));
+    assert_eq!(break_opportunities("\u65E5\u672C\u8A9E\u3002", word_break::keep_all), vec!());
+}
+
+#[test]
+fn test_break_all() {
+    assert_eq!(break_opportunities("ab c", word_break::break_all), vec!(1, 3));
+}
+
+#[test]
+fn test_no_break_at_glue() {
+    assert_eq!(break_opportunities("a\u00A0b c", word_break::normal), vec!(4));
+}
@@ -11,13 +11,14 @@ use text::shaping::ShaperMethods;
 use text::util::{float_to_fixed, fixed_to_float};
 
 use geom::Point2D;
-use harfbuzz::{HB_MEMORY_MODE_READONLY, HB_DIRECTION_LTR};
+use harfbuzz::{HB_MEMORY_MODE_READONLY, HB_DIRECTION_LTR, HB_DIRECTION_RTL};
 use harfbuzz::{hb_blob_create, hb_face_create_for_tables};
 use harfbuzz::{hb_blob_t};
 use harfbuzz::{hb_bool_t};
 use harfbuzz::{hb_buffer_add_utf8};
 use harfbuzz::{hb_buffer_destroy};
 use harfbuzz::{hb_buffer_get_glyph_positions};
+use harfbuzz::{hb_buffer_guess_segment_properties, hb_buffer_reverse};
 use harfbuzz::{hb_buffer_set_direction};
 use harfbuzz::{hb_face_destroy};
 use harfbuzz::{hb_face_t, hb_font_t};
@@ -200,10 +201,14 @@ impl Shaper {
 impl ShaperMethods for Shaper {
     /// Calculate the layout metrics associated with the given text when rendered in a specific
     /// font.
-    fn shape_text(&self, text: &str, glyphs: &mut GlyphStore) {
+    fn shape_text(&self, text: &str, rtl: bool, glyphs: &mut GlyphStore) {
         unsafe {
             let hb_buffer: *mut hb_buffer_t = hb_buffer_create();
-            hb_buffer_set_direction(hb_buffer, HB_DIRECTION_LTR);
+            hb_buffer_set_direction(hb_buffer, if rtl {
+                HB_DIRECTION_RTL
+            } else {
+                HB_DIRECTION_LTR
+            });
 
             hb_buffer_add_utf8(hb_buffer,
                                text.as_ptr() as *const c_char,
@@ -211,7 +216,18 @@ impl ShaperMethods for Shaper {
                                0,
                                text.len() as c_int);
 
+            // Take the script (and language) from the text itself, so that scripts such as
+            // Arabic get their contextual forms.
+            hb_buffer_guess_segment_properties(hb_buffer);
+
             hb_shape(self.hb_font, hb_buffer, ptr::null_mut(), 0);
+
+            // HarfBuzz returns right-to-left glyphs in visual order. Put them back in logical
+            // order, which is what `save_glyph_results` and the glyph store expect.
+            if rtl {
+                hb_buffer_reverse(hb_buffer);
+            }
+
             self.save_glyph_results(text, glyphs, hb_buffer);
             hb_buffer_destroy(hb_buffer);
         }
@@ -408,6 +424,9 @@ impl Shaper {
                                           true,
                                           true);
                 glyphs.add_glyph_for_char_index(char_idx, &data);
+                if text.char_at(char_byte_span.begin() as uint) == ' ' {
+                    glyphs.set_char_is_space(char_idx);
+                }
             } else {
                 // collect all glyphs to be assigned to the first character.
                 let mut datas = vec!();
@@ -425,6 +444,9 @@ impl Shaper {
 
                 // now add the detailed glyph entry.
                 glyphs.add_glyphs_for_char_index(char_idx, datas.as_slice());
+                if text.char_at(char_byte_span.begin() as uint) == ' ' {
+                    glyphs.set_char_is_space(char_idx);
+                }
 
                 // set the other chars, who have no glyphs
                 let mut i = covered_byte_span.begin();
@@ -14,6 +14,8 @@ pub use text::shaping::harfbuzz::Shaper;
 pub mod harfbuzz;
 
 pub trait ShaperMethods {
-    fn shape_text(&self, text: &str, glyphs: &mut GlyphStore);
+    /// Shapes the given text into the glyph store. Right-to-left text is shaped in that
+    /// direction, but its glyphs are stored in logical order like any other text.
+    fn shape_text(&self, text: &str, rtl: bool, glyphs: &mut GlyphStore);
 }
 
@@ -6,9 +6,13 @@ use font::{Font, RunMetrics, FontMetrics};
 use servo_util::geometry::Au;
 use servo_util::range::Range;
 use servo_util::vec::{Comparator, FullBinarySearchMethods};
+use std::cmp::min;
 use std::slice::Items;
+use style::computed_values::word_break;
 use sync::Arc;
-use text::glyph::{CharIndex, GlyphStore};
+use text::bidi;
+use text::glyph::{CharIndex, GlyphInfo, GlyphStore};
+use text::line_break::LineBreaker;
 use font::FontHandleMethods;
 use platform::font_template::FontTemplateData;
 
@@ -21,6 +25,20 @@ pub struct TextRun {
     pub font_metrics: FontMetrics,
     /// The glyph runs that make up this text run.
     pub glyphs: Arc<Vec<GlyphRun>>,
+    /// The extra space added after each character, from `letter-spacing`.
+    pub letter_spacing: Au,
+    /// The extra space added after each word separator, from `word-spacing`.
+    pub word_spacing: Au,
+    /// The extra space inserted after each word separator by `text-align: justify`.
+    pub extra_word_spacing: Au,
+    /// The word separators of this run before this character get one more Au of extra space, which
+    /// spreads the remainder of the justification slack over the first expansion opportunities.
+    pub extra_word_spacing_remainder_end: CharIndex,
+    /// The embedding level of this run, as resolved by the Unicode Bidirectional Algorithm. Odd
+    /// levels are right-to-left.
+    pub bidi_level: u8,
+    /// True if the glyphs of this run are set upright in vertical text, one below the other.
+    pub upright: bool,
 }
 
 /// A single series of glyphs within a text run.
@@ -117,62 +135,68 @@ impl<'a> Iterator<Range<CharIndex>> for LineIterator<'a> {
 }
 
 impl<'a> TextRun {
-    pub fn new(font: &mut Font, text: String) -> TextRun {
-        let glyphs = TextRun::break_and_shape(font, text.as_slice());
+    pub fn new(font: &mut Font, text: String, word_break: word_break::T, bidi_level: u8)
+               -> TextRun {
+        let glyphs = TextRun::break_and_shape(font,
+                                              text.as_slice(),
+                                              word_break,
+                                              bidi::is_rtl_level(bidi_level));
         let run = TextRun {
             text: Arc::new(text),
             font_metrics: font.metrics.clone(),
             font_template: font.handle.get_template(),
             actual_pt_size: font.actual_pt_size,
             glyphs: Arc::new(glyphs),
+            letter_spacing: Au(0),
+            word_spacing: Au(0),
+            extra_word_spacing: Au(0),
+            extra_word_spacing_remainder_end: CharIndex(0),
+            bidi_level: bidi_level,
+            upright: false,
         };
         return run;
     }
 
-    pub fn break_and_shape(font: &mut Font, text: &str) -> Vec<GlyphRun> {
-        // TODO(Issue #230): do a better job. See Gecko's LineBreaker.
+    /// Splits the text into glyph runs at each line break opportunity and shapes each of them.
+    /// Whitespace always gets glyph runs of its own, so that it can be trimmed at the ends of
+    /// lines.
+    pub fn break_and_shape(font: &mut Font, text: &str, word_break: word_break::T, rtl: bool)
+                           -> Vec<GlyphRun> {
         let mut glyphs = vec!();
         let (mut byte_i, mut char_i) = (0u, CharIndex(0));
         let mut cur_slice_is_whitespace = false;
         let (mut byte_last_boundary, mut char_last_boundary) = (0, CharIndex(0));
+        let mut line_breaker = LineBreaker::new(word_break);
         while byte_i < text.len() {
             let range = text.char_range_at(byte_i);
             let ch = range.ch;
             let next = range.next;
 
-            // Slices alternate between whitespace and non-whitespace,
-            // representing line break opportunities.
-            let can_break_before = if cur_slice_is_whitespace {
-                match ch {
-                    ' ' | '\t' | '\n' => false,
-                    _ => {
-                        cur_slice_is_whitespace = false;
-                        true
-                    }
-                }
-            } else {
-                match ch {
-                    ' ' | '\t' | '\n' => {
-                        cur_slice_is_whitespace = true;
-                        true
-                    },
-                    _ => false
-                }
+            let is_whitespace = match ch {
+                ' ' | '\t' | '\n' => true,
+                _ => false,
             };
 
+            // Every character is fed to the line breaker, since the break opportunities depend
+            // on the surrounding characters.
+            let is_break_opportunity = line_breaker.can_break_before(ch);
+            let can_break_before = is_whitespace != cur_slice_is_whitespace ||
+                (!is_whitespace && is_break_opportunity);
+
             // Create a glyph store for this slice if it's nonempty.
             if can_break_before && byte_i > byte_last_boundary {
                 let slice = text.slice(byte_last_boundary, byte_i);
                 debug!("creating glyph store for slice {} (ws? {}), {} - {} in run {}",
-                        slice, !cur_slice_is_whitespace, byte_last_boundary, byte_i, text);
+                        slice, cur_slice_is_whitespace, byte_last_boundary, byte_i, text);
                 glyphs.push(GlyphRun {
-                    glyph_store: font.shape_text(slice, !cur_slice_is_whitespace),
+                    glyph_store: font.shape_text(slice, cur_slice_is_whitespace, rtl),
                     range: Range::new(char_last_boundary, char_i - char_last_boundary),
                 });
                 byte_last_boundary = byte_i;
                 char_last_boundary = char_i;
             }
 
+            cur_slice_is_whitespace = is_whitespace;
             byte_i = next;
             char_i = char_i + CharIndex(1);
         }
@@ -183,7 +207,7 @@ impl<'a> TextRun {
             debug!("creating glyph store for final slice {} (ws? {}), {} - {} in run {}",
                 slice, cur_slice_is_whitespace, byte_last_boundary, text.len(), text);
             glyphs.push(GlyphRun {
-                glyph_store: font.shape_text(slice, cur_slice_is_whitespace),
+                glyph_store: font.shape_text(slice, cur_slice_is_whitespace, rtl),
                 range: Range::new(char_last_boundary, char_i - char_last_boundary),
             });
         }
@@ -191,6 +215,11 @@ impl<'a> TextRun {
         glyphs
     }
 
+    /// Returns true if this run is laid out right to left.
+    pub fn is_rtl(&self) -> bool {
+        bidi::is_rtl_level(self.bidi_level)
+    }
+
     pub fn char_len(&self) -> CharIndex {
         match self.glyphs.last() {
             None => CharIndex(0),
@@ -218,25 +247,141 @@ impl<'a> TextRun {
 
     pub fn advance_for_range(&self, range: &Range<CharIndex>) -> Au {
         // TODO(Issue #199): alter advance direction for RTL
-        // TODO(Issue #98): using inter-char and inter-word spacing settings  when measuring text
         self.iter_slices_for_range(range)
-            .fold(Au(0), |advance, (glyphs, _, slice_range)| {
-                advance + glyphs.advance_for_char_range(&slice_range)
+            .fold(Au(0), |advance, (glyphs, offset, slice_range)| {
+                advance + self.advance_for_slice(glyphs, offset, &slice_range)
             })
     }
 
+    /// Returns the advance of the glyphs for the given characters of one of the glyph stores of
+    /// this run, which starts at `offset` in the run, including letter and word spacing.
+    fn advance_for_slice(&self, glyphs: &GlyphStore, offset: CharIndex,
+                         slice_range: &Range<CharIndex>) -> Au {
+        if !self.upright {
+            let advance = glyphs.advance_for_char_range(slice_range,
+                                                        self.letter_spacing,
+                                                        self.total_word_spacing());
+            if self.extra_word_spacing_remainder_end <= offset + slice_range.begin() {
+                return advance
+            }
+            let widened_end = min(slice_range.end(),
+                                  self.extra_word_spacing_remainder_end - offset);
+            let widened_range = Range::new(slice_range.begin(),
+                                           widened_end - slice_range.begin());
+            return advance + Au(glyphs.space_count_in_range(&widened_range) as i32)
+        }
+        glyphs.iter_glyphs_for_char_range(slice_range).fold(Au(0), |advance, (index, glyph)| {
+            advance + self.advance_for_glyph(offset + index, glyph)
+        })
+    }
+
+    /// Returns the advance of the given glyph of the character at `index` in this run plus any
+    /// spacing that follows it. Upright glyphs advance by the height of the em box whatever their
+    /// width, except for the ones that don't advance at all, like combining marks.
+    #[inline]
+    pub fn advance_for_glyph(&self, index: CharIndex, glyph: GlyphInfo) -> Au {
+        let advance = glyph.advance_with_spacing(self.letter_spacing,
+                                                 self.total_word_spacing_at(index));
+        if !self.upright || glyph.advance() == Au(0) {
+            return advance
+        }
+        advance - glyph.advance() + self.font_metrics.ascent + self.font_metrics.descent
+    }
+
+    /// Returns the extra space added after each word separator, from both `word-spacing` and
+    /// justification, leaving out the remainder of the justification slack.
+    #[inline]
+    pub fn total_word_spacing(&self) -> Au {
+        self.word_spacing + self.extra_word_spacing
+    }
+
+    /// Returns the extra space added after the character at `index` in this run if it is a word
+    /// separator, from both `word-spacing` and justification.
+    #[inline]
+    fn total_word_spacing_at(&self, index: CharIndex) -> Au {
+        if index < self.extra_word_spacing_remainder_end {
+            self.total_word_spacing() + Au(1)
+        } else {
+            self.total_word_spacing()
+        }
+    }
+
+    /// Returns the index of the character after the `count`th expansion opportunity in the given
+    /// range, or the end of the range if it has fewer expansion opportunities.
+    pub fn end_of_expansion_opportunities(&self, range: &Range<CharIndex>, count: int)
+                                          -> CharIndex {
+        if count <= 0 {
+            return range.begin()
+        }
+        let mut count = count;
+        for (glyphs, offset, slice_range) in self.iter_slices_for_range(range) {
+            for index in slice_range.each_index() {
+                if glyphs.char_is_space(index) {
+                    count -= 1;
+                    if count == 0 {
+                        return offset + index + CharIndex(1)
+                    }
+                }
+            }
+        }
+        range.end()
+    }
+
+    /// Returns the number of expansion opportunities (word separators) in the given range. These
+    /// are the places where `text-align: justify` may insert extra space.
+    pub fn expansion_opportunities_for_range(&self, range: &Range<CharIndex>) -> int {
+        self.iter_slices_for_range(range).fold(0, |count, (glyphs, _, slice_range)| {
+            count + glyphs.space_count_in_range(&slice_range)
+        })
+    }
+
+    /// Returns the length of the run of whitespace at the end of the given range, if any.
+    pub fn trailing_whitespace_length_for_range(&self, range: &Range<CharIndex>) -> CharIndex {
+        self.iter_slices_for_range(range).fold(CharIndex(0), |length, (glyphs, _, slice_range)| {
+            if glyphs.is_whitespace() {
+                length + slice_range.length()
+            } else {
+                CharIndex(0)
+            }
+        })
+    }
+
     pub fn metrics_for_range(&self, range: &Range<CharIndex>) -> RunMetrics {
         RunMetrics::new(self.advance_for_range(range),
                         self.font_metrics.ascent,
                         self.font_metrics.descent)
     }
 
-    pub fn metrics_for_slice(&self, glyphs: &GlyphStore, slice_range: &Range<CharIndex>) -> RunMetrics {
-        RunMetrics::new(glyphs.advance_for_char_range(slice_range),
+    pub fn metrics_for_slice(&self, glyphs: &GlyphStore, offset: CharIndex,
+                             slice_range: &Range<CharIndex>) -> RunMetrics {
+        RunMetrics::new(self.advance_for_slice(glyphs, offset, slice_range),
                         self.font_metrics.ascent,
                         self.font_metrics.descent)
     }
 
+    /// Returns the number of characters at the start of the given slice that fit within
+    /// `max_advance`. At least one character is always counted, so that emergency line breaking
+    /// always makes progress.
+    pub fn fitting_length_for_slice(&self,
+                                    glyphs: &GlyphStore,
+                                    offset: CharIndex,
+                                    slice_range: &Range<CharIndex>,
+                                    max_advance: Au)
+                                    -> CharIndex {
+        let mut advance = Au(0);
+        let mut length = CharIndex(0);
+        for index in slice_range.each_index() {
+            advance = advance + self.advance_for_slice(glyphs,
+                                                       offset,
+                                                       &Range::new(index, CharIndex(1)));
+            if advance > max_advance && length > CharIndex(0) {
+                break
+            }
+            length = length + CharIndex(1);
+        }
+        length
+    }
+
     pub fn min_width_for_range(&self, range: &Range<CharIndex>) -> Au {
         debug!("iterating outer range {}", range);
         self.iter_slices_for_range(range).fold(Au(0), |max_piece_width, (_, offset, slice_range)| {
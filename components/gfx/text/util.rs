@@ -22,9 +22,12 @@ pub enum CompressionMode {
 // * Issue #114: record skipped and kept chars for mapping original to new text
 //
 // * Untracked: various edge cases for bidi, CJK, etc.
+//
+// Preserved tabs are expanded to spaces up to the next multiple of `tab_size` columns.
 pub fn transform_text(text: &str,
                       mode: CompressionMode,
                       incoming_whitespace: bool,
+                      tab_size: uint,
                       output_text: &mut String,
                       new_line_pos: &mut Vec<CharIndex>)
                       -> bool {
@@ -37,7 +40,16 @@ pub fn transform_text(text: &str,
                 } else {
                     // TODO: record kept char
                     if ch == '\t' {
-                        // TODO: set "has tab" flag
+                        // FIXME: Columns are counted from the start of this text rather than
+                        // from the start of the line.
+                        let column = new_line_index.to_uint();
+                        if tab_size > 0 {
+                            for _ in range(0, tab_size - column % tab_size) {
+                                output_text.push(' ');
+                                new_line_index = new_line_index + CharIndex(1);
+                            }
+                        }
+                        continue
                     } else if ch == '\n' {
                         // Save new-line's position for line-break
                         // This value is relative(not absolute)
@@ -56,7 +68,24 @@ pub fn transform_text(text: &str,
 
         CompressWhitespace | CompressWhitespaceNewline => {
             let mut in_whitespace: bool = incoming_whitespace;
+            let mut new_line_index = CharIndex(0);
+            // Whether the last character output by this call is a collapsed space.
+            let mut ends_with_space = false;
             for ch in text.chars() {
+                if ch == '\n' && mode == CompressWhitespace {
+                    // Preserved newlines remove the collapsible spaces around them.
+                    if ends_with_space {
+                        output_text.pop();
+                        new_line_index = new_line_index - CharIndex(1);
+                    }
+                    new_line_pos.push(new_line_index);
+                    new_line_index = CharIndex(0);
+                    output_text.push(ch);
+                    in_whitespace = true;
+                    ends_with_space = false;
+                    continue
+                }
+
                 // TODO: discard newlines between CJK chars
                 let mut next_in_whitespace: bool = is_in_whitespace(ch, mode);
 
@@ -68,6 +97,8 @@ pub fn transform_text(text: &str,
                     } else {
                         // TODO: record kept char
                         output_text.push(ch);
+                        new_line_index = new_line_index + CharIndex(1);
+                        ends_with_space = false;
                     }
                 } else { /* next_in_whitespace; possibly add a space char */
                     if in_whitespace {
@@ -75,6 +106,8 @@ pub fn transform_text(text: &str,
                     } else {
                         // TODO: record kept char
                         output_text.push(' ');
+                        new_line_index = new_line_index + CharIndex(1);
+                        ends_with_space = true;
                     }
                 }
                 // save whitespace context for next char
@@ -158,7 +191,7 @@ fn test_transform_compress_none() {
     for test in test_strs.iter() {
         let mut new_line_pos = vec!();
         let mut trimmed_str = String::new();
-        transform_text(*test, mode, true, &mut trimmed_str, &mut new_line_pos);
+        transform_text(*test, mode, true, 8, &mut trimmed_str, &mut new_line_pos);
         assert_eq!(trimmed_str.as_slice(), *test)
     }
 }
@@ -191,7 +224,7 @@ fn test_transform_discard_newline() {
     for (test, oracle) in test_strs.iter().zip(oracle_strs.iter()) {
         let mut new_line_pos = vec!();
         let mut trimmed_str = String::new();
-        transform_text(*test, mode, true, &mut trimmed_str, &mut new_line_pos);
+        transform_text(*test, mode, true, 8, &mut trimmed_str, &mut new_line_pos);
         assert_eq!(trimmed_str.as_slice(), *oracle)
     }
 }
@@ -284,7 +317,61 @@ fn test_transform_compress_whitespace_newline_no_incoming() {
     for (test, oracle) in test_strs.iter().zip(oracle_strs.iter()) {
         let mut new_line_pos = vec!();
         let mut trimmed_str = String::new();
-        transform_text(*test, mode, false, &mut trimmed_str, &mut new_line_pos);
+        transform_text(*test, mode, false, 8, &mut trimmed_str, &mut new_line_pos);
+        assert_eq!(trimmed_str.as_slice(), *oracle)
+    }
+}
+
+#[test]
+fn test_transform_compress_whitespace_preserve_newline() {
+    let test_strs = vec!(
+        "  foo bar",
+        "foo  \n  bar",
+        "foo\n\nbar",
+        "foo\tbar \n"
+    );
+
+    let oracle_strs = vec!(
+        " foo bar",
+        "foo\nbar",
+        "foo\n\nbar",
+        "foo bar\n"
+    );
+
+    assert_eq!(test_strs.len(), oracle_strs.len());
+    let mode = CompressWhitespace;
+
+    for (test, oracle) in test_strs.iter().zip(oracle_strs.iter()) {
+        let mut new_line_pos = vec!();
+        let mut trimmed_str = String::new();
+        transform_text(*test, mode, false, 8, &mut trimmed_str, &mut new_line_pos);
+        assert_eq!(trimmed_str.as_slice(), *oracle)
+    }
+}
+
+#[test]
+fn test_transform_expand_tabs() {
+    let test_strs = vec!(
+        "\tfoo",
+        "ab\tc",
+        "abcd\t",
+        "a\n\tb"
+    );
+
+    let oracle_strs = vec!(
+        "    foo",
+        "ab  c",
+        "abcd    ",
+        "a\n    b"
+    );
+
+    assert_eq!(test_strs.len(), oracle_strs.len());
+    let mode = CompressNone;
+
+    for (test, oracle) in test_strs.iter().zip(oracle_strs.iter()) {
+        let mut new_line_pos = vec!();
+        let mut trimmed_str = String::new();
+        transform_text(*test, mode, false, 4, &mut trimmed_str, &mut new_line_pos);
         assert_eq!(trimmed_str.as_slice(), *oracle)
     }
 }
@@ -31,6 +31,7 @@ use construct::FlowConstructor;
 use context::LayoutContext;
 use css::node_style::StyledNode;
 use display_list_builder::{BlockFlowDisplayListBuilding, BlockLevel, FragmentDisplayListBuilding};
+use display_list_builder::{SeparateBorderPaintingMode};
 use floats::{ClearBoth, ClearLeft, ClearRight, FloatKind, FloatLeft, Floats, PlacementInfo};
 use flow::{AbsolutePositionInfo, BaseFlow, BlockFlowClass, FloatIfNecessary, FlowClass, Flow};
 use flow::{ForceNonfloated, ImmutableFlowUtils, MutableFlowUtils, PreorderFlowTraversal};
@@ -38,7 +39,7 @@ use flow::{PostorderFlowTraversal, mut_base};
 use flow::{HAS_LEFT_FLOATED_DESCENDANTS, HAS_RIGHT_FLOATED_DESCENDANTS};
 use flow::{IMPACTED_BY_LEFT_FLOATS, IMPACTED_BY_RIGHT_FLOATS};
 use flow::{LAYERS_NEEDED_FOR_DESCENDANTS, NEEDS_LAYER};
-use flow::{IS_ABSOLUTELY_POSITIONED};
+use flow::{IS_ABSOLUTELY_POSITIONED, IS_FLEX_ITEM};
 use flow::{CLEARS_LEFT, CLEARS_RIGHT};
 use flow;
 use fragment::{Fragment, ImageFragment, InlineBlockFragment, FragmentBoundsIterator};
@@ -47,7 +48,8 @@ use incremental::{REFLOW, REFLOW_OUT_OF_FLOW};
 use layout_debug;
 use model::{Auto, IntrinsicISizes, MarginCollapseInfo, MarginsCollapse, MarginsCollapseThrough};
 use model::{MaybeAuto, NoCollapsibleMargins, Specified, specified, specified_or_none};
-use table::ColumnInlineSize;
+use multicol::is_multicol_container;
+use table::{mod, ColumnInlineSize};
 use wrapper::ThreadSafeLayoutNode;
 
 use geom::Size2D;
@@ -484,6 +486,7 @@ pub enum BlockType {
     AbsoluteNonReplacedType,
     FloatReplacedType,
     FloatNonReplacedType,
+    FlexItemType,
 }
 
 #[deriving(Clone, PartialEq)]
@@ -502,7 +505,8 @@ enum FormattingContextType {
 // Propagates the `layers_needed_for_descendants` flag appropriately from a child. This is called
 // as part of block-size assignment.
 //
-// If any fixed descendants of kids are present, this kid needs a layer.
+// If any fixed descendants of kids are present, this kid needs a layer. So does a kid that itself
+// needs a layer, like a scroll container, even if it is not positioned.
 //
 // FIXME(#2006, pcwalton): This is too layer-happy. Like WebKit, we shouldn't do this unless
 // the positioned descendants are actually on top of the fixed kids.
@@ -517,7 +521,8 @@ fn propagate_layer_flag_from_child(layers_needed_for_descendants: &mut bool, kid
         }
     } else {
         let kid_base = flow::mut_base(kid);
-        if kid_base.flags.contains(LAYERS_NEEDED_FOR_DESCENDANTS) {
+        if kid_base.flags.contains(NEEDS_LAYER) ||
+                kid_base.flags.contains(LAYERS_NEEDED_FOR_DESCENDANTS) {
             *layers_needed_for_descendants = true
         }
     }
@@ -640,6 +645,8 @@ impl BlockFlow {
             } else {
                 AbsoluteNonReplacedType
             }
+        } else if self.base.flags.contains(IS_FLEX_ITEM) && !self.is_replaced_content() {
+            FlexItemType
         } else if self.base.flags.is_float() {
             if self.is_replaced_content() {
                 FloatReplacedType
@@ -685,6 +692,10 @@ impl BlockFlow {
                 let inline_size_computer = BlockNonReplaced;
                 inline_size_computer.compute_used_inline_size(self, ctx, containing_block_inline_size);
             }
+            FlexItemType => {
+                let inline_size_computer = FlexItem;
+                inline_size_computer.compute_used_inline_size(self, ctx, containing_block_inline_size);
+            }
         }
     }
 
@@ -693,6 +704,51 @@ impl BlockFlow {
         &mut self.fragment
     }
 
+    /// Returns true if the contents of this block scroll independently of the rest of the page.
+    pub fn is_scroll_container(&self) -> bool {
+        self.fragment.is_scroll_container()
+    }
+
+    /// Returns the scrollport of this block, which is its padding box, and its scrollable overflow
+    /// area: the padding box, grown to take in the overflow of the in-flow contents and the
+    /// padding after them. Both are relative to the origin of the flow. CSS Overflow § 3.
+    pub fn scrollport_and_scrollable_overflow(&self) -> (LogicalRect<Au>, LogicalRect<Au>) {
+        let writing_mode = self.base.writing_mode;
+        let border = self.fragment.border_width();
+        let padding = self.fragment.border_padding - border;
+        let scrollport = self.fragment.border_box - border;
+        let mut scrollable_overflow = scrollport;
+        for kid in self.base.children.iter() {
+            if flow::base(kid).flags.contains(IS_ABSOLUTELY_POSITIONED) {
+                continue
+            }
+            let kid_overflow = flow::base(kid).overflow;
+            let kid_overflow = LogicalRect::new(writing_mode,
+                                                kid_overflow.start.i,
+                                                kid_overflow.start.b,
+                                                kid_overflow.size.inline + padding.inline_end,
+                                                kid_overflow.size.block + padding.block_end);
+            scrollable_overflow = scrollable_overflow.union(&kid_overflow)
+        }
+        (scrollport, scrollable_overflow)
+    }
+
+    /// Returns how far the contents of this block can be scrolled from their initial position,
+    /// which is nothing unless it is a scroll container.
+    pub fn scroll_range(&self) -> Size2D<Au> {
+        if !self.is_scroll_container() {
+            return Size2D(Au(0), Au(0))
+        }
+        // FIXME(#2795): Get the real container size
+        let container_size = Size2D::zero();
+        let writing_mode = self.base.writing_mode;
+        let (scrollport, scrollable_overflow) = self.scrollport_and_scrollable_overflow();
+        let scrollport = scrollport.to_physical(writing_mode, container_size);
+        let scrollable_overflow = scrollable_overflow.to_physical(writing_mode, container_size);
+        Size2D(Au::max(Au(0), scrollable_overflow.max_x() - scrollport.max_x()),
+               Au::max(Au(0), scrollable_overflow.max_y() - scrollport.max_y()))
+    }
+
     /// Return the static x offset from the appropriate Containing Block for this flow.
     pub fn static_i_offset(&self) -> Au {
         if self.is_fixed() {
@@ -947,7 +1003,7 @@ impl BlockFlow {
                 // function here because the child has already translated floats past its border
                 // box.
                 let kid_base = flow::mut_base(kid);
-                cur_b = cur_b + kid_base.position.size.block;
+                cur_b = cur_b + kid_base.size_in_block_container_writing_mode().block;
 
                 // Handle any (possibly collapsed) block-end margin.
                 let delta =
@@ -1007,6 +1063,12 @@ impl BlockFlow {
                 return
             }
 
+            // Sticky-positioned blocks and scroll containers get layers so that the compositor
+            // can move them when the page or the container scrolls.
+            if self.positioning() == position::sticky || self.is_scroll_container() {
+                self.base.flags.insert(NEEDS_LAYER);
+            }
+
             // Compute any explicitly-specified block size.
             // Can't use `for` because we assign to `candidate_block_size_iterator.candidate_value`.
             let mut candidate_block_size_iterator = CandidateBSizeIterator::new(
@@ -1284,8 +1346,12 @@ impl BlockFlow {
         let fixed_static_i_offset = self.base.fixed_static_i_offset + inline_start_content_edge;
         let flags = self.base.flags.clone();
 
-        // This value is used only for table cells.
-        let mut inline_start_margin_edge = inline_start_content_edge;
+        // Table cells are separated from each other, and from the edges of their row, by the
+        // horizontal border spacing. `border-spacing` is inherited, so our own style has it.
+        let border_spacing = match optional_column_inline_sizes {
+            Some(_) => table::border_spacing(self.fragment.style()).inline,
+            None => Au(0),
+        };
 
         // Remember the inline-sizes of the last left and right floats, if there were any. These
         // are used for estimating the inline-sizes of block formatting contexts. (We estimate that
@@ -1310,9 +1376,15 @@ impl BlockFlow {
             (LPA_Length(length), _) => Some(length),
         };
 
-        for (i, kid) in self.base.child_iter().enumerate() {
+        // Per CSS 2.1 § 16.1, `text-indent` applies only to the first formatted line of this block.
+        let text_indent = specified(self.fragment.style().get_inheritedtext().text_indent,
+                                    content_inline_size);
+        let mut seen_in_flow_child = false;
+
+        for kid in self.base.child_iter() {
             {
                 let kid_base = flow::mut_base(kid);
+                kid_base.block_container_writing_mode = self.base.writing_mode;
                 kid_base.block_container_explicit_block_size = explicit_content_size;
                 kid_base.absolute_static_i_offset = absolute_static_i_offset;
                 kid_base.fixed_static_i_offset = fixed_static_i_offset;
@@ -1376,10 +1448,10 @@ impl BlockFlow {
             match optional_column_inline_sizes {
                 Some(ref column_inline_sizes) => {
                     propagate_column_inline_sizes_to_child(kid,
-                                                           i,
+                                                           inline_start_content_edge,
                                                            content_inline_size,
                                                            *column_inline_sizes,
-                                                           &mut inline_start_margin_edge)
+                                                           border_spacing)
                 }
                 None => {}
             }
@@ -1387,7 +1459,21 @@ impl BlockFlow {
             // Per CSS 2.1 § 16.3.1, text alignment propagates to all children in flow.
             //
             // TODO(#2018, pcwalton): Do this in the cascade instead.
-            flow::mut_base(kid).flags.propagate_text_alignment_from_parent(flags.clone())
+            flow::mut_base(kid).flags.propagate_text_alignment_from_parent(flags.clone());
+
+            // Indent the first line, if it belongs to this child.
+            let kid_is_in_flow = {
+                let kid_base = flow::base(kid);
+                !kid_base.flags.contains(IS_ABSOLUTELY_POSITIONED) && !kid_base.flags.is_float()
+            };
+            if kid.is_inline_flow() {
+                kid.as_inline().first_line_indentation = if seen_in_flow_child {
+                    Au(0)
+                } else {
+                    text_indent
+                }
+            }
+            seen_in_flow_child = seen_in_flow_child || kid_is_in_flow
         }
     }
 
@@ -1395,13 +1481,16 @@ impl BlockFlow {
     /// `FormattingContextType`.
     fn formatting_context_type(&self) -> FormattingContextType {
         let style = self.fragment.style();
-        if style.get_box().float != float::none {
+        if style.get_box().float != float::none || self.base.flags.contains(IS_FLEX_ITEM) ||
+                self.base.is_orthogonal_flow() {
             return OtherFormattingContext
         }
         match style.get_box().display {
             display::table_cell | display::table_caption | display::inline_block => {
                 OtherFormattingContext
             }
+            display::flex | display::inline_flex => BlockFormattingContext,
+            display::block if is_multicol_container(style) => BlockFormattingContext,
             _ if style.get_box().overflow != overflow::visible => BlockFormattingContext,
             _ => NonformattingContext,
         }
@@ -1508,7 +1597,13 @@ impl Flow for BlockFlow {
             let is_absolutely_positioned = flow::base(kid).flags.contains(IS_ABSOLUTELY_POSITIONED);
             let child_base = flow::mut_base(kid);
             let float_kind = child_base.flags.float_kind();
-            if !is_absolutely_positioned && !fixed_width {
+
+            // FIXME: The intrinsic inline-sizes of orthogonal flows are measured along our block
+            // axis. They should contribute their block size after layout instead, per CSS Writing
+            // Modes § 7.3.
+            let is_orthogonal_flow = child_base.writing_mode.is_vertical() !=
+                self.base.writing_mode.is_vertical();
+            if !is_absolutely_positioned && !fixed_width && !is_orthogonal_flow {
                 computation.content_intrinsic_sizes.minimum_inline_size =
                     max(computation.content_intrinsic_sizes.minimum_inline_size,
                         child_base.intrinsic_inline_sizes.minimum_inline_size);
@@ -1580,6 +1675,17 @@ impl Flow for BlockFlow {
             // The root element is never impacted by floats.
             self.base.flags.remove(IMPACTED_BY_LEFT_FLOATS);
             self.base.flags.remove(IMPACTED_BY_RIGHT_FLOATS);
+        } else if self.base.is_orthogonal_flow() {
+            // Our parent measured its sizes along the other axis. Per CSS Writing Modes § 7.3, the
+            // available inline space of an orthogonal flow is the block size of its containing
+            // block if that is definite, and the size of the initial containing block otherwise.
+            let screen_size = LogicalSize::from_physical(self.base.writing_mode,
+                                                         layout_context.shared.screen_size);
+            let block_container_inline_size =
+                self.base.block_container_explicit_block_size.unwrap_or(screen_size.inline);
+            self.base.block_container_explicit_block_size =
+                Some(self.base.block_container_inline_size);
+            self.base.block_container_inline_size = block_container_inline_size;
         }
 
         // Our inline-size was set to the inline-size of the containing block by the flow's parent.
@@ -1631,8 +1737,11 @@ impl Flow for BlockFlow {
             return false
         }
 
+        // FIXME: Orthogonal flows are not moved out of the way of floats, since our floats are
+        // laid out along the other axis.
         let is_formatting_context = self.formatting_context_type() != NonformattingContext;
-        if !self.base.flags.contains(IS_ABSOLUTELY_POSITIONED) && is_formatting_context {
+        if !self.base.flags.contains(IS_ABSOLUTELY_POSITIONED) && is_formatting_context &&
+                !self.base.is_orthogonal_flow() {
             self.assign_inline_position_for_formatting_context();
         }
 
@@ -1649,7 +1758,7 @@ impl Flow for BlockFlow {
             // If this is a formatting context and was *not* impacted by floats, then we must
             // translate the floats past us.
             let writing_mode = self.base.floats.writing_mode;
-            let delta = self.base.position.size.block;
+            let delta = self.base.size_in_block_container_writing_mode().block;
             self.base.floats.translate(LogicalSize::new(writing_mode, Au(0), -delta));
             return true
         }
@@ -1669,10 +1778,19 @@ impl Flow for BlockFlow {
             if !self.base.flags.contains(IS_ABSOLUTELY_POSITIONED) {
                 self.base.position.size.block = self.fragment.border_box.size.block;
             }
-        } else if self.is_root() || self.base.flags.is_float() || self.is_inline_block() {
-            // Root element margins should never be collapsed according to CSS § 8.3.1.
+        } else if self.is_root() || self.base.flags.is_float() || self.is_inline_block() ||
+                self.base.flags.contains(IS_FLEX_ITEM) {
+            // Root element margins should never be collapsed according to CSS § 8.3.1, and neither
+            // should the margins of flex items per CSS Flexbox § 4.2.
             debug!("assign_block_size: assigning block_size for root flow");
             self.assign_block_size_block_base(ctx, MarginsMayNotCollapse);
+        } else if self.base.is_orthogonal_flow() {
+            // Our block-axis margins lie along the inline axis of our parent, so there is nothing
+            // for them to collapse with. Our inline-axis margins are already part of
+            // `position.size`, which our parent moves past.
+            debug!("assign_block_size: assigning block_size for orthogonal flow");
+            self.assign_block_size_block_base(ctx, MarginsMayNotCollapse);
+            self.base.collapsible_margins = NoCollapsibleMargins(Au(0), Au(0));
         } else {
             debug!("assign_block_size: assigning block_size for block");
             self.assign_block_size_block_base(ctx, MarginsMayCollapse);
@@ -1764,22 +1882,39 @@ impl Flow for BlockFlow {
 
         // Process children.
         let writing_mode = self.base.writing_mode;
+        let content_box = self.fragment.content_box();
         for kid in self.base.child_iter() {
             if !flow::base(kid).flags.contains(IS_ABSOLUTELY_POSITIONED) {
+                // The origin of the kid is the physical location of its own start corner, which
+                // differs from ours if its writing mode does.
                 let kid_base = flow::mut_base(kid);
+                let kid_start = LogicalPoint::new(writing_mode,
+                                                  kid_base.position.start.i,
+                                                  kid_base.position.start.b);
+                let kid_rect = LogicalRect::from_point_size(
+                    writing_mode,
+                    kid_start + relative_offset,
+                    kid_base.size_in_block_container_writing_mode());
                 kid_base.stacking_relative_position =
                     origin_for_children +
-                    (kid_base.position.start + relative_offset).to_physical(writing_mode,
-                                                                            container_size);
-            }
-
-            flow::mut_base(kid).absolute_position_info = absolute_position_info_for_children;
-            flow::mut_base(kid).clip_rect = clip_rect
+                    kid_rect.inner_start_corner_to_physical(writing_mode, kid_base.writing_mode);
+            }
+
+            let kid_base = flow::mut_base(kid);
+            kid_base.absolute_position_info = absolute_position_info_for_children;
+            kid_base.absolute_position_info.sticky_containing_block_rect =
+                LogicalRect::new(writing_mode,
+                                 content_box.start.i - kid_base.position.start.i,
+                                 content_box.start.b - kid_base.position.start.b,
+                                 content_box.size.inline,
+                                 content_box.size.block);
+            kid_base.clip_rect = clip_rect
         }
     }
 
     fn mark_as_root(&mut self) {
-        self.flags.insert(IS_ROOT)
+        self.flags.insert(IS_ROOT);
+        self.fragment.is_root = true
     }
 
     /// Return true if store overflow is delayed for this flow.
@@ -1845,8 +1980,12 @@ impl Flow for BlockFlow {
             self.build_display_list_for_floating_block(layout_context)
         } else if self.base.flags.contains(IS_ABSOLUTELY_POSITIONED) {
             self.build_display_list_for_absolutely_positioned_block(layout_context)
+        } else if self.positioning() == position::sticky {
+            self.build_display_list_for_sticky_block(layout_context)
         } else {
-            self.build_display_list_for_block(layout_context, BlockLevel)
+            self.build_display_list_for_block(layout_context,
+                                              BlockLevel,
+                                              SeparateBorderPaintingMode)
         }
 
         if opts::get().validate_display_list_geometry {
@@ -2187,6 +2326,7 @@ pub struct BlockNonReplaced;
 pub struct BlockReplaced;
 pub struct FloatNonReplaced;
 pub struct FloatReplaced;
+pub struct FlexItem;
 
 impl ISizeAndMarginsComputer for AbsoluteNonReplaced {
     /// Solve the horizontal constraint equation for absolute non-replaced elements.
@@ -2555,34 +2695,78 @@ impl ISizeAndMarginsComputer for FloatReplaced {
     }
 }
 
+impl ISizeAndMarginsComputer for FlexItem {
+    /// The containing flex container has already resolved the outer inline-size of this item and
+    /// stored it as the containing block inline-size, so `min-inline-size` and `max-inline-size`
+    /// have already been taken into account.
+    ///
+    /// FIXME: Percentage margins and padding are resolved against the item's own outer
+    /// inline-size instead of against the flex container.
+    fn compute_used_inline_size(&self,
+                                block: &mut BlockFlow,
+                                ctx: &LayoutContext,
+                                parent_flow_inline_size: Au) {
+        let input = self.compute_inline_size_constraint_inputs(block,
+                                                               parent_flow_inline_size,
+                                                               ctx);
+        let solution = self.solve_inline_size_constraints(block, &input);
+        self.set_inline_size_constraint_solutions(block, solution);
+    }
+
+    /// CSS Flexbox § 9.7: the hypothetical main size has been resolved to the available space.
+    /// Auto margins are treated as zero.
+    fn solve_inline_size_constraints(&self, _: &mut BlockFlow, input: &ISizeConstraintInput)
+                                     -> ISizeConstraintSolution {
+        let margin_inline_start = input.inline_start_margin.specified_or_zero();
+        let margin_inline_end = input.inline_end_margin.specified_or_zero();
+        let inline_size = max(Au(0),
+                              input.available_inline_size - margin_inline_start -
+                              margin_inline_end);
+        ISizeConstraintSolution::new(inline_size, margin_inline_start, margin_inline_end)
+    }
+}
+
 fn propagate_column_inline_sizes_to_child(kid: &mut Flow,
-                                          child_index: uint,
+                                          inline_start_content_edge: Au,
                                           content_inline_size: Au,
                                           column_inline_sizes: &[ColumnInlineSize],
-                                          inline_start_margin_edge: &mut Au) {
+                                          border_spacing: Au) {
     // If kid is table_rowgroup or table_row, the column inline-sizes info should be copied from
     // its parent.
     //
     // FIXME(pcwalton): This seems inefficient. Reference count it instead?
-    let inline_size = if kid.is_table() || kid.is_table_rowgroup() || kid.is_table_row() {
+    let (inline_start_margin_edge, inline_size) = if kid.is_table() || kid.is_table_rowgroup() ||
+            kid.is_table_row() {
         *kid.column_inline_sizes() = column_inline_sizes.iter().map(|&x| x).collect();
 
         // ISize of kid flow is our content inline-size.
-        content_inline_size
+        (inline_start_content_edge, content_inline_size)
     } else if kid.is_table_cell() {
-        column_inline_sizes[child_index].minimum_length
+        // A cell starts at the column it was placed in and is as wide as all the columns it spans
+        // put together, along with the border spacing between them.
+        let (column_index, column_span) = {
+            let cell = kid.as_table_cell();
+            (cell.column_index, cell.column_span as uint)
+        };
+        let column_start = min(column_index, column_inline_sizes.len());
+        let column_end = min(column_index + column_span, column_inline_sizes.len());
+        fn sum(columns: &[ColumnInlineSize]) -> Au {
+            columns.iter().fold(Au(0), |sum, column| sum + column.minimum_length)
+        }
+        let spacing_within_cell = if column_end > column_start {
+            border_spacing * ((column_end - column_start - 1) as i32)
+        } else {
+            Au(0)
+        };
+        (inline_start_content_edge + border_spacing * ((column_start + 1) as i32) +
+            sum(column_inline_sizes.slice_to(column_start)),
+         sum(column_inline_sizes.slice(column_start, column_end)) + spacing_within_cell)
     } else {
         // ISize of kid flow is our content inline-size.
-        content_inline_size
+        (inline_start_content_edge, content_inline_size)
     };
 
-    {
-        let kid_base = flow::mut_base(kid);
-        kid_base.position.start.i = *inline_start_margin_edge;
-        kid_base.block_container_inline_size = inline_size;
-    }
-
-    if kid.is_table_cell() {
-        *inline_start_margin_edge = *inline_start_margin_edge + inline_size
-    }
+    let kid_base = flow::mut_base(kid);
+    kid_base.position.start.i = inline_start_margin_edge;
+    kid_base.block_container_inline_size = inline_size;
 }
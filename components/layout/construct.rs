@@ -16,6 +16,7 @@
 use css::node_style::StyledNode;
 use block::BlockFlow;
 use context::LayoutContext;
+use flex::FlexFlow;
 use floats::FloatKind;
 use flow::{Flow, ImmutableFlowUtils, MutableOwnedFlowUtils};
 use flow::{Descendants, AbsDescendants};
@@ -30,6 +31,7 @@ use fragment::{TableColumnFragment, TableColumnFragmentInfo, TableFragment, Tabl
 use fragment::{TableWrapperFragment, UnscannedTextFragment, UnscannedTextFragmentInfo};
 use incremental::{RECONSTRUCT_FLOW, RestyleDamage};
 use inline::InlineFlow;
+use multicol::{MulticolFlow, is_multicol_container};
 use parallel;
 use table_wrapper::TableWrapperFlow;
 use table::TableFlow;
@@ -38,7 +40,7 @@ use table_colgroup::TableColGroupFlow;
 use table_rowgroup::TableRowGroupFlow;
 use table_row::TableRowFlow;
 use table_cell::TableCellFlow;
-use text::TextRunScanner;
+use text::{mod, TextRunScanner};
 use util::{HAS_NEWLY_CONSTRUCTED_FLOW, LayoutDataAccess, OpaqueNodeMethods, LayoutDataWrapper};
 use wrapper::{PostorderNodeMutTraversal, TLayoutNode, ThreadSafeLayoutNode};
 use wrapper::{Before, After, Normal};
@@ -58,7 +60,7 @@ use std::collections::DList;
 use std::mem;
 use std::sync::atomic::Relaxed;
 use style::ComputedValues;
-use style::computed_values::{display, position, float};
+use style::computed_values::{caption_side, display, position, float};
 use sync::Arc;
 use url::Url;
 
@@ -333,8 +335,10 @@ impl<'a> FlowConstructor<'a> {
         // We must scan for runs before computing minimum ascent and descent because scanning
         // for runs might collapse so much whitespace away that only hypothetical fragments
         // remain. In that case the inline flow will compute its ascent and descent to be zero.
+        let paragraph_level = text::bidi_paragraph_level(node.style().writing_mode);
         let fragments = TextRunScanner::new().scan_for_runs(self.layout_context.font_context(),
-                                                            fragments);
+                                                            fragments,
+                                                            paragraph_level);
         let mut inline_flow_ref =
             FlowRef::new(box InlineFlow::from_fragments(fragments, node.style().writing_mode));
 
@@ -552,6 +556,20 @@ impl<'a> FlowConstructor<'a> {
         self.build_flow_for_block(FlowRef::new(flow), node)
     }
 
+    /// Builds a flow for a node with `display: flex`. This yields a `FlexFlow` with possibly
+    /// other flows underneath it, each of which becomes a flex item.
+    fn build_flow_for_flex(&mut self, node: &ThreadSafeLayoutNode) -> ConstructionResult {
+        let flow = box FlexFlow::from_node(self, node) as Box<Flow>;
+        self.build_flow_for_block(FlowRef::new(flow), node)
+    }
+
+    /// Builds a flow for a block container with `column-count` or `column-width` set. This yields
+    /// a `MulticolFlow` with possibly other flows underneath it, which are broken into columns.
+    fn build_flow_for_multicol(&mut self, node: &ThreadSafeLayoutNode) -> ConstructionResult {
+        let flow = box MulticolFlow::from_node(self, node) as Box<Flow>;
+        self.build_flow_for_block(FlowRef::new(flow), node)
+    }
+
     /// Builds the flow for a node with `float: {left|right}`. This yields a float `BlockFlow` with
     /// a `BlockFlow` underneath it.
     fn build_flow_for_floated_block(&mut self, node: &ThreadSafeLayoutNode, float_kind: FloatKind)
@@ -756,21 +774,30 @@ impl<'a> FlowConstructor<'a> {
         }
     }
 
-    /// TableCaptionFlow is populated underneath TableWrapperFlow
+    /// TableCaptionFlow is populated underneath TableWrapperFlow.
+    ///
+    /// Adds the captions whose `caption-side` is `top` to the table wrapper flow, and returns the
+    /// ones whose `caption-side` is `bottom` so that they can be added after the table itself.
     fn place_table_caption_under_table_wrapper(&mut self,
                                                table_wrapper_flow: &mut FlowRef,
-                                               node: &ThreadSafeLayoutNode) {
+                                               node: &ThreadSafeLayoutNode)
+                                               -> Vec<FlowRef> {
+        let mut bottom_captions = vec!();
         for kid in node.children() {
             match kid.swap_out_construction_result() {
                 NoConstructionResult | ConstructionItemConstructionResult(_) => {}
                 FlowConstructionResult(kid_flow, _) => {
                     // Only kid flows with table-caption are matched here.
                     if kid_flow.deref().is_table_caption() {
-                        table_wrapper_flow.add_new_child(kid_flow);
+                        match kid.style().get_inheritedtable().caption_side {
+                            caption_side::top => table_wrapper_flow.add_new_child(kid_flow),
+                            caption_side::bottom => bottom_captions.push(kid_flow),
+                        }
                     }
                 }
             }
         }
+        bottom_captions
     }
 
     /// Generates an anonymous table flow according to CSS 2.1 § 17.2.1, step 2.
@@ -824,13 +851,14 @@ impl<'a> FlowConstructor<'a> {
         // We then populate the TableWrapperFlow with TableCaptionFlow, and attach
         // the TableFlow to the TableWrapperFlow
         let construction_result = self.build_flow_for_block(table_flow, node);
-        self.place_table_caption_under_table_wrapper(&mut wrapper_flow, node);
+        let bottom_captions = self.place_table_caption_under_table_wrapper(&mut wrapper_flow,
+                                                                           node);
 
         let mut abs_descendants = Descendants::new();
         let mut fixed_descendants = Descendants::new();
 
         // NOTE: The order of captions and table are not the same order as in the DOM tree.
-        // All caption blocks are placed before the table flow
+        // Caption blocks are placed before or after the table flow according to `caption-side`.
         match construction_result {
             FlowConstructionResult(table_flow, table_abs_descendants) => {
                 wrapper_flow.add_new_child(table_flow);
@@ -838,6 +866,9 @@ impl<'a> FlowConstructor<'a> {
             }
             _ => {}
         }
+        for caption_flow in bottom_captions.into_iter() {
+            wrapper_flow.add_new_child(caption_flow);
+        }
 
         // The flow is done.
         wrapper_flow.finish();
@@ -1039,6 +1070,21 @@ impl<'a> PostorderNodeMutTraversal for FlowConstructor<'a> {
                 node.set_flow_construction_result(construction_result)
             }
 
+            // Flex containers contribute flex flow construction results.
+            //
+            // FIXME: `inline-flex` should be laid out as an atomic inline, like `inline-block`.
+            // FIXME: Floated flex containers are laid out as floated blocks.
+            (display::flex, float::none, _) | (display::inline_flex, float::none, _) => {
+                node.set_flow_construction_result(self.build_flow_for_flex(node))
+            }
+
+            // Multi-column containers contribute multi-column flow construction results.
+            //
+            // FIXME: Floated multi-column containers are laid out as floated blocks.
+            (display::block, float::none, _) if is_multicol_container(&**node.style()) => {
+                node.set_flow_construction_result(self.build_flow_for_multicol(node))
+            }
+
             // Absolutely positioned elements will have computed value of
             // `float` as 'none' and `display` as per the table.
             // Only match here for block items. If an item is absolutely
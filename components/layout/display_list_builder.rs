@@ -12,13 +12,15 @@
 
 use block::BlockFlow;
 use context::LayoutContext;
-use flow::{mod, Flow, NEEDS_LAYER};
+use flow::{mod, Flow, IS_ABSOLUTELY_POSITIONED, NEEDS_LAYER};
 use fragment::{Fragment, GenericFragment, IframeFragment, IframeFragmentInfo, ImageFragment};
 use fragment::{ImageFragmentInfo, InlineAbsoluteHypotheticalFragment, InlineBlockFragment};
 use fragment::{ScannedTextFragment, ScannedTextFragmentInfo, TableFragment};
 use fragment::{TableCellFragment, TableColumnFragment, TableRowFragment, TableWrapperFragment};
 use fragment::{UnscannedTextFragment};
-use model;
+use model::{mod, Auto, MaybeAuto, Specified};
+use multicol::MulticolFlow;
+use table::CollapsedBorder;
 use util::{OpaqueNodeMethods, ToGfxColor};
 
 use geom::approxeq::ApproxEq;
@@ -27,21 +29,29 @@ use gfx::color;
 use gfx::display_list::{BaseDisplayItem, BorderDisplayItem, BorderDisplayItemClass, DisplayItem};
 use gfx::display_list::{DisplayList, GradientDisplayItem, GradientDisplayItemClass, GradientStop};
 use gfx::display_list::{ImageDisplayItem, ImageDisplayItemClass, LineDisplayItem, BorderRadii};
-use gfx::display_list::{LineDisplayItemClass, PseudoDisplayItemClass, SidewaysLeft, SidewaysRight};
+use gfx::display_list::{LineDisplayItemClass, OpaqueNode, PseudoDisplayItemClass};
+use gfx::display_list::{RadialGradientDisplayItem, RadialGradientDisplayItemClass};
+use gfx::display_list::{SidewaysLeft, SidewaysRight};
 use gfx::display_list::{SolidColorDisplayItem, SolidColorDisplayItemClass, StackingContext};
 use gfx::display_list::{TextDisplayItem, TextDisplayItemClass, Upright};
 use gfx::render_task::RenderLayer;
-use servo_msg::compositor_msg::{FixedPosition, Scrollable};
+use servo_msg::compositor_msg::{FixedPosition, LayerId, Scrollable, Scrollport, StickyPosition};
+use servo_msg::compositor_msg::{StickyPositionConstraints};
 use servo_msg::constellation_msg::{ConstellationChan, FrameRectMsg};
 use servo_net::image::holder::ImageHolder;
-use servo_util::geometry::{mod, Au, ZERO_POINT, ZERO_RECT};
-use servo_util::logical_geometry::{LogicalRect, WritingMode};
+use servo_util::geometry::{mod, Au, MAX_RECT, ZERO_POINT, ZERO_RECT};
+use servo_util::logical_geometry::{LogicalMargin, LogicalRect, WritingMode};
 use servo_util::opts;
 use std::default::Default;
-use style::computed::{AngleAoc, CornerAoc, LP_Length, LP_Percentage, LengthOrPercentage};
-use style::computed::{LinearGradient, LinearGradientImage, UrlImage};
-use style::computed_values::{background_attachment, background_repeat, border_style, overflow};
-use style::computed_values::{visibility};
+use std::f64::consts::SQRT2;
+use style::computed::{AngleAoc, Circle, ClosestCorner, ClosestSide, ColorStop, CornerAoc};
+use style::computed::{Ellipse, ExplicitSize, ExtentSize, FarthestCorner, FarthestSide};
+use style::computed::{LP_Length, LP_Percentage, LengthOrPercentage, LengthOrPercentageOrAuto};
+use style::computed::{LinearGradient};
+use style::computed::{LinearGradientImage, RadialGradient, RadialGradientImage, UrlImage};
+use style::computed_values::{background_attachment, background_clip, background_origin};
+use style::computed_values::{background_repeat, background_size, border_style, overflow};
+use style::computed_values::{border_collapse, visibility};
 use style::{ComputedValues, Bottom, Left, RGBA, Right, Top};
 use style::style_structs::Border;
 use sync::Arc;
@@ -82,8 +92,16 @@ pub trait FragmentDisplayListBuilding {
                                                        absolute_bounds: &Rect<Au>,
                                                        clip_rect: &Rect<Au>);
 
-    /// Adds the display items necessary to paint the background image of this fragment to the
-    /// display list at the appropriate stacking level.
+    /// Returns the border box, padding box, or content box of this fragment, as selected by
+    /// `background-origin`, in absolute coordinates. `absolute_bounds` is the border box.
+    fn compute_background_box(&self,
+                              style: &ComputedValues,
+                              absolute_bounds: &Rect<Au>,
+                              background_box: background_origin::SingleComputedValue)
+                              -> Rect<Au>;
+
+    /// Adds the display items necessary to paint the background image of the given background
+    /// layer of this fragment to the display list at the appropriate stacking level.
     fn build_display_list_for_background_image(&self,
                                                style: &ComputedValues,
                                                display_list: &mut DisplayList,
@@ -91,10 +109,12 @@ pub trait FragmentDisplayListBuilding {
                                                level: StackingLevel,
                                                absolute_bounds: &Rect<Au>,
                                                clip_rect: &Rect<Au>,
-                                               image_url: &Url);
+                                               image_url: &Url,
+                                               layer_index: uint);
 
-    /// Adds the display items necessary to paint the background linear gradient of this fragment
-    /// to the display list at the appropriate stacking level.
+    /// Adds the display items necessary to paint a background linear gradient of this fragment
+    /// to the display list at the appropriate stacking level. `absolute_bounds` is the area that
+    /// the gradient image covers.
     fn build_display_list_for_background_linear_gradient(&self,
                                                          display_list: &mut DisplayList,
                                                          level: StackingLevel,
@@ -103,10 +123,22 @@ pub trait FragmentDisplayListBuilding {
                                                          gradient: &LinearGradient,
                                                          style: &ComputedValues);
 
+    /// Adds the display items necessary to paint a background radial gradient of this fragment
+    /// to the display list at the appropriate stacking level. `absolute_bounds` is the area that
+    /// the gradient image covers.
+    fn build_display_list_for_background_radial_gradient(&self,
+                                                         display_list: &mut DisplayList,
+                                                         level: StackingLevel,
+                                                         absolute_bounds: &Rect<Au>,
+                                                         clip_rect: &Rect<Au>,
+                                                         gradient: &RadialGradient,
+                                                         style: &ComputedValues);
+
     /// Adds the display items necessary to paint the borders of this fragment to a display list if
     /// necessary.
     fn build_display_list_for_borders_if_applicable(&self,
                                                     style: &ComputedValues,
+                                                    border_painting_mode: BorderPaintingMode,
                                                     display_list: &mut DisplayList,
                                                     abs_bounds: &Rect<Au>,
                                                     level: StackingLevel,
@@ -131,12 +163,14 @@ pub trait FragmentDisplayListBuilding {
     /// * `layout_context`: The layout context.
     /// * `dirty`: The dirty rectangle in the coordinate system of the owning flow.
     /// * `flow_origin`: Position of the origin of the owning flow wrt the display list root flow.
+    /// * `border_painting_mode`: How the borders of this fragment are to be painted.
     /// * `clip_rect`: The rectangle to clip the display items to.
     fn build_display_list(&mut self,
                           display_list: &mut DisplayList,
                           layout_context: &LayoutContext,
                           flow_origin: Point2D<Au>,
                           background_and_border_level: BackgroundAndBorderLevel,
+                          border_painting_mode: BorderPaintingMode,
                           clip_rect: &Rect<Au>);
 
     /// Sends the size and position of this iframe fragment to the constellation. This is out of
@@ -171,44 +205,132 @@ impl FragmentDisplayListBuilding for Fragment {
                                                        level: StackingLevel,
                                                        absolute_bounds: &Rect<Au>,
                                                        clip_rect: &Rect<Au>) {
+        let background = style.get_background();
+
+        // The background color is clipped to the background painting area of the final (i.e.
+        // bottommost) background layer.
+        //
         // FIXME: This causes a lot of background colors to be displayed when they are clearly not
         // needed. We could use display list optimization to clean this up, but it still seems
         // inefficient. What we really want is something like "nearest ancestor element that
         // doesn't have a fragment".
-        let background_color = style.resolve_color(style.get_background().background_color);
+        let background_color = style.resolve_color(background.background_color);
         if !background_color.alpha.approx_eq(&0.0) {
+            let final_layer_index = background.background_image.len() - 1;
+            let color_bounds = self.compute_background_box(
+                style,
+                absolute_bounds,
+                clip_to_origin(*background_layer(&background.background_clip,
+                                                 final_layer_index)));
             display_list.push(SolidColorDisplayItemClass(box SolidColorDisplayItem {
-                base: BaseDisplayItem::new(*absolute_bounds, self.node, *clip_rect),
+                base: BaseDisplayItem::new(color_bounds, self.node, *clip_rect),
                 color: background_color.to_gfx_color(),
             }), level);
         }
 
-        // The background image is painted on top of the background color.
-        // Implements background image, per spec:
-        // http://www.w3.org/TR/CSS21/colors.html#background
-        let background = style.get_background();
-        match background.background_image {
-            None => {}
-            Some(LinearGradientImage(ref gradient)) => {
-                self.build_display_list_for_background_linear_gradient(display_list,
-                                                                       level,
-                                                                       absolute_bounds,
-                                                                       clip_rect,
-                                                                       gradient,
-                                                                       style)
-            }
-            Some(UrlImage(ref image_url)) => {
-                self.build_display_list_for_background_image(style,
-                                                             display_list,
-                                                             layout_context,
-                                                             level,
-                                                             absolute_bounds,
-                                                             clip_rect,
-                                                             image_url)
+        // The background images are painted on top of the background color, with the first
+        // layer topmost. Implements background images, per spec:
+        // http://dev.w3.org/csswg/css-backgrounds-3/#layering
+        for layer_index in range(0, background.background_image.len()).rev() {
+            let image = match background.background_image[layer_index] {
+                None => continue,
+                Some(ref image) => image,
+            };
+
+            // Clip to the background painting area.
+            let clip_box = self.compute_background_box(
+                style,
+                absolute_bounds,
+                clip_to_origin(*background_layer(&background.background_clip, layer_index)));
+            let layer_clip_rect = clip_rect.intersection(&clip_box).unwrap_or(ZERO_RECT);
+
+            match *image {
+                UrlImage(ref image_url) => {
+                    self.build_display_list_for_background_image(style,
+                                                                 display_list,
+                                                                 layout_context,
+                                                                 level,
+                                                                 absolute_bounds,
+                                                                 &layer_clip_rect,
+                                                                 image_url,
+                                                                 layer_index)
+                }
+                LinearGradientImage(_) | RadialGradientImage(_) => {
+                    // Gradients have no intrinsic size, so they are sized against the background
+                    // positioning area.
+                    //
+                    // FIXME: Gradients smaller than the positioning area should be tiled according
+                    // to `background-repeat`.
+                    let positioning_area = self.compute_background_box(
+                        style,
+                        absolute_bounds,
+                        *background_layer(&background.background_origin, layer_index));
+                    let size = compute_background_image_size(
+                        background_layer(&background.background_size, layer_index),
+                        &positioning_area.size,
+                        None);
+                    let position = background_layer(&background.background_position,
+                                                    layer_index);
+                    let origin = Point2D(
+                        positioning_area.origin.x +
+                            model::specified(position.horizontal,
+                                             positioning_area.size.width - size.width),
+                        positioning_area.origin.y +
+                            model::specified(position.vertical,
+                                             positioning_area.size.height - size.height));
+                    let gradient_bounds = Rect(origin, size);
+
+                    match *image {
+                        LinearGradientImage(ref gradient) => {
+                            self.build_display_list_for_background_linear_gradient(
+                                display_list,
+                                level,
+                                &gradient_bounds,
+                                &layer_clip_rect,
+                                gradient,
+                                style)
+                        }
+                        RadialGradientImage(ref gradient) => {
+                            self.build_display_list_for_background_radial_gradient(
+                                display_list,
+                                level,
+                                &gradient_bounds,
+                                &layer_clip_rect,
+                                gradient,
+                                style)
+                        }
+                        UrlImage(_) => unreachable!(),
+                    }
+                }
             }
         }
     }
 
+    fn compute_background_box(&self,
+                              style: &ComputedValues,
+                              absolute_bounds: &Rect<Au>,
+                              background_box: background_origin::SingleComputedValue)
+                              -> Rect<Au> {
+        let insets = match background_box {
+            background_origin::border_box => return *absolute_bounds,
+            background_origin::padding_box => style.logical_border_width(),
+            background_origin::content_box => {
+                if style as *const ComputedValues == &*self.style as *const ComputedValues {
+                    self.border_padding
+                } else {
+                    // No containing block is known for the styles of inline ancestors, so their
+                    // percentage padding is resolved against zero, as in `border_padding`.
+                    style.logical_border_width() + model::padding_from_style(style, Au(0))
+                }
+            }
+        };
+        let insets = insets.to_physical(style.writing_mode);
+        Rect(Point2D(absolute_bounds.origin.x + insets.left,
+                     absolute_bounds.origin.y + insets.top),
+             Size2D(Au::max(absolute_bounds.size.width - insets.left - insets.right, Au(0)),
+                    Au::max(absolute_bounds.size.height - insets.top - insets.bottom, Au(0))))
+    }
+
     fn build_display_list_for_background_image(&self,
                                                style: &ComputedValues,
                                                display_list: &mut DisplayList,
@@ -216,7 +338,8 @@ impl FragmentDisplayListBuilding for Fragment {
                                                level: StackingLevel,
                                                absolute_bounds: &Rect<Au>,
                                                clip_rect: &Rect<Au>,
-                                               image_url: &Url) {
+                                               image_url: &Url,
+                                               layer_index: uint) {
         let background = style.get_background();
         let mut holder = ImageHolder::new(image_url.clone(),
                                           layout_context.shared.image_cache.clone());
@@ -232,68 +355,83 @@ impl FragmentDisplayListBuilding for Fragment {
         };
         debug!("(building display list) building background image");
 
-        let image_width = Au::from_px(image.width as int);
-        let image_height = Au::from_px(image.height as int);
-        let mut bounds = *absolute_bounds;
+        // Use background-origin to find the background positioning area.
+        let positioning_area =
+            self.compute_background_box(style,
+                                        absolute_bounds,
+                                        *background_layer(&background.background_origin,
+                                                          layer_index));
+
+        // Use background-size to find the size of each tile.
+        let intrinsic_size = Size2D(Au::from_px(image.width as int),
+                                    Au::from_px(image.height as int));
+        let image_size =
+            compute_background_image_size(background_layer(&background.background_size,
+                                                           layer_index),
+                                          &positioning_area.size,
+                                          Some(intrinsic_size));
+        if image_size.width <= Au(0) || image_size.height <= Au(0) {
+            return
+        }
 
-        // Clip.
-        //
-        // TODO: Check the bounds to see if a clip item is actually required.
-        let clip_rect = clip_rect.intersection(&bounds).unwrap_or(ZERO_RECT);
+        // Tile the whole painting area; the clip rect limits the tiles to it.
+        let mut bounds = *clip_rect;
 
         // Use background-attachment to get the initial virtual origin
-        let (virtual_origin_x, virtual_origin_y) = match background.background_attachment {
-            background_attachment::scroll => {
-                (absolute_bounds.origin.x, absolute_bounds.origin.y)
-            }
-            background_attachment::fixed => {
-                (Au(0), Au(0))
-            }
-        };
+        let (virtual_origin_x, virtual_origin_y) =
+            match *background_layer(&background.background_attachment, layer_index) {
+                background_attachment::scroll => {
+                    (positioning_area.origin.x, positioning_area.origin.y)
+                }
+                background_attachment::fixed => {
+                    (Au(0), Au(0))
+                }
+            };
 
         // Use background-position to get the offset
-        let horizontal_position = model::specified(background.background_position.horizontal,
-                                                   bounds.size.width - image_width);
-        let vertical_position = model::specified(background.background_position.vertical,
-                                                 bounds.size.height - image_height);
+        let position = background_layer(&background.background_position, layer_index);
+        let horizontal_position = model::specified(position.horizontal,
+                                                   positioning_area.size.width - image_size.width);
+        let vertical_position = model::specified(position.vertical,
+                                                 positioning_area.size.height -
+                                                 image_size.height);
 
         let abs_x = virtual_origin_x + horizontal_position;
         let abs_y = virtual_origin_y + vertical_position;
 
         // Adjust origin and size based on background-repeat
-        match background.background_repeat {
+        match *background_layer(&background.background_repeat, layer_index) {
             background_repeat::no_repeat => {
                 bounds.origin.x = abs_x;
                 bounds.origin.y = abs_y;
-                bounds.size.width = image_width;
-                bounds.size.height = image_height;
+                bounds.size.width = image_size.width;
+                bounds.size.height = image_size.height;
             }
             background_repeat::repeat_x => {
                 bounds.origin.y = abs_y;
-                bounds.size.height = image_height;
+                bounds.size.height = image_size.height;
                 ImageFragmentInfo::tile_image(&mut bounds.origin.x, &mut bounds.size.width,
-                                                abs_x, image.width);
+                                                abs_x, image_size.width);
             }
             background_repeat::repeat_y => {
                 bounds.origin.x = abs_x;
-                bounds.size.width = image_width;
+                bounds.size.width = image_size.width;
                 ImageFragmentInfo::tile_image(&mut bounds.origin.y, &mut bounds.size.height,
-                                                abs_y, image.height);
+                                                abs_y, image_size.height);
             }
             background_repeat::repeat => {
                 ImageFragmentInfo::tile_image(&mut bounds.origin.x, &mut bounds.size.width,
-                                                abs_x, image.width);
+                                                abs_x, image_size.width);
                 ImageFragmentInfo::tile_image(&mut bounds.origin.y, &mut bounds.size.height,
-                                                abs_y, image.height);
+                                                abs_y, image_size.height);
             }
         };
 
         // Create the image display item.
         display_list.push(ImageDisplayItemClass(box ImageDisplayItem {
-            base: BaseDisplayItem::new(bounds, self.node, clip_rect),
+            base: BaseDisplayItem::new(bounds, self.node, *clip_rect),
             image: image.clone(),
-            stretch_size: Size2D(Au::from_px(image.width as int),
-                                 Au::from_px(image.height as int)),
+            stretch_size: image_size,
         }), level);
     }
 
@@ -333,109 +471,196 @@ impl FragmentDisplayListBuilding for Fragment {
         let length = Au((delta.x.to_f64().unwrap() * 2.0).hypot(delta.y.to_f64().unwrap() * 2.0)
                         as i32);
 
-        // Determine the position of each stop per CSS-IMAGES § 3.4.
-        //
-        // FIXME(#3908, pcwalton): Make sure later stops can't be behind earlier stops.
-        let (mut stops, mut stop_run) = (Vec::new(), None);
-        for (i, stop) in gradient.stops.iter().enumerate() {
-            let offset = match stop.position {
-                None => {
-                    if stop_run.is_none() {
-                        // Initialize a new stop run.
-                        let start_offset = if i == 0 {
-                            0.0
-                        } else {
-                            // `unwrap()` here should never fail because this is the beginning of
-                            // a stop run, which is always bounded by a length or percentage.
-                            position_to_offset(gradient.stops[i - 1].position.unwrap(), length)
-                        };
-                        let (end_index, end_offset) =
-                            match gradient.stops
-                                          .as_slice()
-                                          .slice_from(i)
-                                          .iter()
-                                          .enumerate()
-                                          .find(|&(_, ref stop)| stop.position.is_some()) {
-                                None => (gradient.stops.len() - 1, 1.0),
-                                Some((end_index, end_stop)) => {
-                                    // `unwrap()` here should never fail because this is the end of
-                                    // a stop run, which is always bounded by a length or
-                                    // percentage.
-                                    (end_index,
-                                     position_to_offset(end_stop.position.unwrap(), length))
-                                }
-                            };
-                        stop_run = Some(StopRun {
-                            start_offset: start_offset,
-                            end_offset: end_offset,
-                            start_index: i,
-                            stop_count: end_index - i,
-                        })
-                    }
+        let mut stops = compute_gradient_stops(gradient.stops.as_slice(), length, style);
 
-                    let stop_run = stop_run.unwrap();
-                    let stop_run_length = stop_run.end_offset - stop_run.start_offset;
-                    if stop_run.stop_count == 0 {
-                        stop_run.end_offset
-                    } else {
-                        stop_run.start_offset +
-                            stop_run_length * (i - stop_run.start_index) as f32 /
-                                (stop_run.stop_count as f32)
-                    }
+        let center = Point2D(absolute_bounds.origin.x + absolute_bounds.size.width / 2,
+                             absolute_bounds.origin.y + absolute_bounds.size.height / 2);
+        let (mut start_point, mut end_point) = (center - delta, center + delta);
+
+        // A repeating gradient repeats the span between its first and last stops, so move the
+        // ends of the gradient line onto those stops.
+        if gradient.repeating {
+            match normalize_repeating_gradient_stops(&mut stops) {
+                None => {
+                    push_solid_color_for_degenerate_gradient(display_list,
+                                                             level,
+                                                             absolute_bounds,
+                                                             &clip_rect,
+                                                             self.node,
+                                                             stops.as_slice());
+                    return
                 }
-                Some(position) => {
-                    stop_run = None;
-                    position_to_offset(position, length)
+                Some((first_offset, last_offset)) => {
+                    let (start, end) = (start_point, end_point);
+                    start_point = point_along_line(&start, &end, first_offset);
+                    end_point = point_along_line(&start, &end, last_offset);
                 }
-            };
-            stops.push(GradientStop {
-                offset: offset,
-                color: style.resolve_color(stop.color).to_gfx_color()
-            })
+            }
         }
 
-        let center = Point2D(absolute_bounds.origin.x + absolute_bounds.size.width / 2,
-                             absolute_bounds.origin.y + absolute_bounds.size.height / 2);
-
         let gradient_display_item = GradientDisplayItemClass(box GradientDisplayItem {
             base: BaseDisplayItem::new(*absolute_bounds, self.node, clip_rect),
-            start_point: center - delta,
-            end_point: center + delta,
+            start_point: start_point,
+            end_point: end_point,
             stops: stops,
+            repeating: gradient.repeating,
         });
 
         display_list.push(gradient_display_item, level)
     }
 
+    fn build_display_list_for_background_radial_gradient(&self,
+                                                         display_list: &mut DisplayList,
+                                                         level: StackingLevel,
+                                                         absolute_bounds: &Rect<Au>,
+                                                         clip_rect: &Rect<Au>,
+                                                         gradient: &RadialGradient,
+                                                         style: &ComputedValues) {
+        let clip_rect = clip_rect.intersection(absolute_bounds).unwrap_or(ZERO_RECT);
+
+        let size = absolute_bounds.size;
+        let center = Point2D(model::specified(gradient.center.horizontal, size.width),
+                             model::specified(gradient.center.vertical, size.height));
+
+        // Determine the size of the ending shape per CSS-IMAGES § 3.2.1.
+        let (width, height) = (size.width.to_f64().unwrap(), size.height.to_f64().unwrap());
+        let (center_x, center_y) = (center.x.to_f64().unwrap(), center.y.to_f64().unwrap());
+        let (left, right) = (center_x.abs(), (width - center_x).abs());
+        let (top, bottom) = (center_y.abs(), (height - center_y).abs());
+        let (closest_x, farthest_x) = (left.min(right), left.max(right));
+        let (closest_y, farthest_y) = (top.min(bottom), top.max(bottom));
+        let (radius_x, radius_y) = match (gradient.shape, &gradient.size) {
+            (Circle, &ExtentSize(ClosestSide)) => {
+                let radius = closest_x.min(closest_y);
+                (radius, radius)
+            }
+            (Circle, &ExtentSize(FarthestSide)) => {
+                let radius = farthest_x.max(farthest_y);
+                (radius, radius)
+            }
+            (Circle, &ExtentSize(ClosestCorner)) => {
+                let radius = closest_x.hypot(closest_y);
+                (radius, radius)
+            }
+            (Circle, &ExtentSize(FarthestCorner)) => {
+                let radius = farthest_x.hypot(farthest_y);
+                (radius, radius)
+            }
+            (Ellipse, &ExtentSize(ClosestSide)) => (closest_x, closest_y),
+            (Ellipse, &ExtentSize(FarthestSide)) => (farthest_x, farthest_y),
+            // The corner sizes keep the aspect ratio of the corresponding side sizes.
+            (Ellipse, &ExtentSize(ClosestCorner)) => {
+                (closest_x * SQRT2, closest_y * SQRT2)
+            }
+            (Ellipse, &ExtentSize(FarthestCorner)) => {
+                (farthest_x * SQRT2, farthest_y * SQRT2)
+            }
+            (_, &ExplicitSize(horizontal, vertical)) => {
+                (model::specified(horizontal, size.width).to_f64().unwrap(),
+                 model::specified(vertical, size.height).to_f64().unwrap())
+            }
+        };
+
+        // The gradient ray runs horizontally from the center to the ending shape.
+        let length = Au(radius_x as i32);
+        let mut stops = compute_gradient_stops(gradient.stops.as_slice(), length, style);
+        if length <= Au(0) || radius_y <= 0.0 {
+            push_solid_color_for_degenerate_gradient(display_list,
+                                                     level,
+                                                     absolute_bounds,
+                                                     &clip_rect,
+                                                     self.node,
+                                                     stops.as_slice());
+            return
+        }
+
+        let (mut start_radius, mut end_radius) = (Au(0), length);
+        if gradient.repeating {
+            match normalize_repeating_gradient_stops(&mut stops) {
+                None => {
+                    push_solid_color_for_degenerate_gradient(display_list,
+                                                             level,
+                                                             absolute_bounds,
+                                                             &clip_rect,
+                                                             self.node,
+                                                             stops.as_slice());
+                    return
+                }
+                Some((first_offset, last_offset)) => {
+                    start_radius = length.scale_by(first_offset as f64);
+                    end_radius = length.scale_by(last_offset as f64);
+                }
+            }
+        }
+
+        display_list.push(RadialGradientDisplayItemClass(box RadialGradientDisplayItem {
+            base: BaseDisplayItem::new(*absolute_bounds, self.node, clip_rect),
+            center: absolute_bounds.origin + center,
+            start_radius: start_radius,
+            end_radius: end_radius,
+            vertical_scale: (radius_y / radius_x) as f32,
+            stops: stops,
+            repeating: gradient.repeating,
+        }), level)
+    }
+
     fn build_display_list_for_borders_if_applicable(&self,
                                                     style: &ComputedValues,
+                                                    border_painting_mode: BorderPaintingMode,
                                                     display_list: &mut DisplayList,
                                                     abs_bounds: &Rect<Au>,
                                                     level: StackingLevel,
                                                     clip_rect: &Rect<Au>) {
-        let border = style.logical_border_width();
-        if border.is_zero() {
-            return
-        }
+        let (border_widths, colors, styles, radius) = match border_painting_mode {
+            HiddenBorderPaintingMode => return,
+            SeparateBorderPaintingMode => {
+                let border = style.logical_border_width();
+                if border.is_zero() {
+                    return
+                }
 
-        let top_color = style.resolve_color(style.get_border().border_top_color);
-        let right_color = style.resolve_color(style.get_border().border_right_color);
-        let bottom_color = style.resolve_color(style.get_border().border_bottom_color);
-        let left_color = style.resolve_color(style.get_border().border_left_color);
+                let border_style = style.get_border();
+                (border.to_physical(style.writing_mode),
+                 SideOffsets2D::new(style.resolve_color(border_style.border_top_color),
+                                    style.resolve_color(border_style.border_right_color),
+                                    style.resolve_color(border_style.border_bottom_color),
+                                    style.resolve_color(border_style.border_left_color)),
+                 SideOffsets2D::new(border_style.border_top_style,
+                                    border_style.border_right_style,
+                                    border_style.border_bottom_style,
+                                    border_style.border_left_style),
+                 build_border_radius(abs_bounds, border_style))
+            }
+            CollapseBorderPaintingMode(collapsed_borders) => {
+                // Per CSS 2.1 § 17.6.2, `border-radius` does not apply in the collapsing border
+                // model.
+                let collapsed_borders = collapsed_borders.to_physical(style.writing_mode);
+                (SideOffsets2D::new(collapsed_borders.top.width,
+                                    collapsed_borders.right.width,
+                                    collapsed_borders.bottom.width,
+                                    collapsed_borders.left.width),
+                 SideOffsets2D::new(collapsed_borders.top.color,
+                                    collapsed_borders.right.color,
+                                    collapsed_borders.bottom.color,
+                                    collapsed_borders.left.color),
+                 SideOffsets2D::new(collapsed_borders.top.style,
+                                    collapsed_borders.right.style,
+                                    collapsed_borders.bottom.style,
+                                    collapsed_borders.left.style),
+                 Default::default())
+            }
+        };
 
         // Append the border to the display list.
         display_list.push(BorderDisplayItemClass(box BorderDisplayItem {
             base: BaseDisplayItem::new(*abs_bounds, self.node, *clip_rect),
-            border_widths: border.to_physical(style.writing_mode),
-            color: SideOffsets2D::new(top_color.to_gfx_color(),
-                                      right_color.to_gfx_color(),
-                                      bottom_color.to_gfx_color(),
-                                      left_color.to_gfx_color()),
-            style: SideOffsets2D::new(style.get_border().border_top_style,
-                                      style.get_border().border_right_style,
-                                      style.get_border().border_bottom_style,
-                                      style.get_border().border_left_style),
-            radius: build_border_radius(abs_bounds, style.get_border()),
+            border_widths: border_widths,
+            color: SideOffsets2D::new(colors.top.to_gfx_color(),
+                                      colors.right.to_gfx_color(),
+                                      colors.bottom.to_gfx_color(),
+                                      colors.left.to_gfx_color()),
+            style: styles,
+            radius: radius,
         }), level);
     }
 
@@ -504,6 +729,7 @@ impl FragmentDisplayListBuilding for Fragment {
                           layout_context: &LayoutContext,
                           flow_origin: Point2D<Au>,
                           background_and_border_level: BackgroundAndBorderLevel,
+                          border_painting_mode: BorderPaintingMode,
                           clip_rect: &Rect<Au>) {
         // Compute the fragment position relative to the parent stacking context. If the fragment
         // itself establishes a stacking context, then the origin of its position will be (0, 0)
@@ -592,6 +818,7 @@ impl FragmentDisplayListBuilding for Fragment {
                     for style in inline_context.styles.iter().rev() {
                         self.build_display_list_for_borders_if_applicable(
                             &**style,
+                            SeparateBorderPaintingMode,
                             display_list,
                             &absolute_fragment_bounds,
                             level,
@@ -602,9 +829,14 @@ impl FragmentDisplayListBuilding for Fragment {
             }
             match self.specific {
                 ScannedTextFragment(_) => {},
+                TableWrapperFragment if self.style.get_inheritedtable().border_collapse ==
+                        border_collapse::collapse => {
+                    // In the collapsing border model, the cells paint the borders of the table.
+                }
                 _ => {
                     self.build_display_list_for_borders_if_applicable(
                         &*self.style,
+                        border_painting_mode,
                         display_list,
                         &absolute_fragment_bounds,
                         level,
@@ -622,22 +854,36 @@ impl FragmentDisplayListBuilding for Fragment {
             TableColumnFragment(_) => panic!("Shouldn't see table column fragments here."),
             ScannedTextFragment(ref text_fragment) => {
                 // Create the text display item.
-                let orientation = if self.style.writing_mode.is_vertical() {
-                    if self.style.writing_mode.is_sideways_left() {
-                        SidewaysLeft
-                    } else {
-                        SidewaysRight
-                    }
-                } else {
+                let writing_mode = self.style.writing_mode;
+                let orientation = if !writing_mode.is_vertical() || writing_mode.is_upright() {
                     Upright
+                } else if writing_mode.is_sideways_left() {
+                    SidewaysLeft
+                } else {
+                    SidewaysRight
                 };
 
+                // Upright vertical text is centered on its baseline. Otherwise the baseline is the
+                // ascent away from the line-over side, which in vertical writing modes can be the
+                // block-end side.
                 let metrics = &text_fragment.run.font_metrics;
+                let line_over_is_block_start = writing_mode.is_line_over_block_start();
+                let baseline_offset = if writing_mode.is_vertical() && writing_mode.is_upright() {
+                    content_box.size.block / 2
+                } else if line_over_is_block_start {
+                    metrics.ascent
+                } else {
+                    content_box.size.block - metrics.ascent
+                };
                 let baseline_origin = {
                     let mut content_box_start = content_box.start;
-                    content_box_start.b = content_box_start.b + metrics.ascent;
-                    content_box_start.to_physical(self.style.writing_mode, container_size)
-                        + flow_origin
+                    content_box_start.b = content_box_start.b + baseline_offset;
+                    // Glyphs are always drawn left to right from the baseline origin, which
+                    // therefore sits at the inline-end edge of right-to-left text.
+                    if !writing_mode.is_vertical() && !writing_mode.is_bidi_ltr() {
+                        content_box_start.i = content_box_start.i + content_box.size.inline
+                    }
+                    content_box_start.to_physical(writing_mode, container_size) + flow_origin
                 };
 
                 display_list.content.push_back(TextDisplayItemClass(box TextDisplayItem {
@@ -665,27 +911,30 @@ impl FragmentDisplayListBuilding for Fragment {
                         }
                     };
 
-                    let text_decorations =
-                        self.style().get_inheritedtext()._servo_text_decorations_in_effect;
-                    line(text_decorations.underline, || {
+                    // Returns the rectangle of a line of the given thickness drawn the given
+                    // distance from the baseline on the line-over side.
+                    let line_over_baseline = |distance: Au, thickness: Au| {
                         let mut rect = content_box.clone();
-                        rect.start.b = rect.start.b + metrics.ascent - metrics.underline_offset;
-                        rect.size.block = metrics.underline_size;
-                        rect
-                    });
-
-                    line(text_decorations.overline, || {
-                        let mut rect = content_box.clone();
-                        rect.size.block = metrics.underline_size;
+                        rect.start.b = if line_over_is_block_start {
+                            rect.start.b + baseline_offset - distance
+                        } else {
+                            rect.start.b + baseline_offset + distance - thickness
+                        };
+                        rect.size.block = thickness;
                         rect
-                    });
+                    };
+                    let underline_rect = line_over_baseline(metrics.underline_offset,
+                                                            metrics.underline_size);
+                    let overline_rect = line_over_baseline(metrics.ascent,
+                                                           metrics.underline_size);
+                    let line_through_rect = line_over_baseline(metrics.strikeout_offset,
+                                                               metrics.strikeout_size);
 
-                    line(text_decorations.line_through, || {
-                        let mut rect = content_box.clone();
-                        rect.start.b = rect.start.b + metrics.ascent - metrics.strikeout_offset;
-                        rect.size.block = metrics.strikeout_size;
-                        rect
-                    });
+                    let text_decorations =
+                        self.style().get_inheritedtext()._servo_text_decorations_in_effect;
+                    line(text_decorations.underline, || underline_rect);
+                    line(text_decorations.overline, || overline_rect);
+                    line(text_decorations.line_through, || line_through_rect);
                 }
 
                 if opts::get().show_debug_fragment_borders {
@@ -784,7 +1033,11 @@ impl FragmentDisplayListBuilding for Fragment {
             _ => {}
         }
 
-        // Only clip if `overflow` tells us to.
+        // Only clip if `overflow` tells us to. The contents of scroll containers are clipped by
+        // the compositor instead, since they have a layer of their own.
+        if self.is_scroll_container() {
+            return MAX_RECT
+        }
         match self.style.get_box().overflow {
             overflow::hidden | overflow::auto | overflow::scroll => {}
             _ => return current_clip_rect,
@@ -803,24 +1056,30 @@ pub trait BlockFlowDisplayListBuilding {
     fn build_display_list_for_block_base(&mut self,
                                          display_list: &mut DisplayList,
                                          layout_context: &LayoutContext,
-                                         background_border_level: BackgroundAndBorderLevel);
+                                         background_border_level: BackgroundAndBorderLevel,
+                                         border_painting_mode: BorderPaintingMode);
     fn build_display_list_for_block(&mut self,
                                     layout_context: &LayoutContext,
-                                    background_border_level: BackgroundAndBorderLevel);
+                                    background_border_level: BackgroundAndBorderLevel,
+                                    border_painting_mode: BorderPaintingMode);
     fn build_display_list_for_absolutely_positioned_block(&mut self,
                                                           layout_context: &LayoutContext);
     fn build_display_list_for_floating_block(&mut self, layout_context: &LayoutContext);
+    fn build_display_list_for_sticky_block(&mut self, layout_context: &LayoutContext);
     fn create_stacking_context(&self,
                                display_list: Box<DisplayList>,
                                layer: Option<Arc<RenderLayer>>)
                                -> Arc<StackingContext>;
+    fn create_scroll_container_contents_stacking_context(&self, display_list: Box<DisplayList>)
+                                                         -> Arc<StackingContext>;
 }
 
 impl BlockFlowDisplayListBuilding for BlockFlow {
     fn build_display_list_for_block_base(&mut self,
                                          display_list: &mut DisplayList,
                                          layout_context: &LayoutContext,
-                                         background_border_level: BackgroundAndBorderLevel) {
+                                         background_border_level: BackgroundAndBorderLevel,
+                                         border_painting_mode: BorderPaintingMode) {
         // Add the box that starts the block context.
         let stacking_relative_fragment_origin =
             self.base.stacking_relative_position_of_child_fragment(&self.fragment);
@@ -828,20 +1087,35 @@ impl BlockFlowDisplayListBuilding for BlockFlow {
                                          layout_context,
                                          stacking_relative_fragment_origin,
                                          background_border_level,
+                                         border_painting_mode,
                                          &self.base.clip_rect);
 
+        if !self.is_scroll_container() {
+            for kid in self.base.children.iter_mut() {
+                flow::mut_base(kid).display_list_building_result.add_to(display_list);
+            }
+            return
+        }
+
+        // The contents of a scroll container go in a layer of their own, which the compositor
+        // clips to the scrollport and scrolls.
+        let mut contents_display_list = box DisplayList::new();
         for kid in self.base.children.iter_mut() {
-            flow::mut_base(kid).display_list_building_result.add_to(display_list);
+            flow::mut_base(kid).display_list_building_result.add_to(&mut *contents_display_list);
         }
+        display_list.children.push_back(
+            self.create_scroll_container_contents_stacking_context(contents_display_list))
     }
 
     fn build_display_list_for_block(&mut self,
                                     layout_context: &LayoutContext,
-                                    background_border_level: BackgroundAndBorderLevel) {
+                                    background_border_level: BackgroundAndBorderLevel,
+                                    border_painting_mode: BorderPaintingMode) {
         let mut display_list = box DisplayList::new();
         self.build_display_list_for_block_base(&mut *display_list,
                                                layout_context,
-                                               background_border_level);
+                                               background_border_level,
+                                               border_painting_mode);
 
         self.base.display_list_building_result = if self.fragment.establishes_stacking_context() {
             StackingContextResult(self.create_stacking_context(display_list, None))
@@ -855,7 +1129,8 @@ impl BlockFlowDisplayListBuilding for BlockFlow {
         let mut display_list = box DisplayList::new();
         self.build_display_list_for_block_base(&mut *display_list,
                                                layout_context,
-                                               RootOfStackingContextLevel);
+                                               RootOfStackingContextLevel,
+                                               SeparateBorderPaintingMode);
 
         if !self.base.absolute_position_info.layers_needed_for_positioned_flows &&
                 !self.base.flags.contains(NEEDS_LAYER) {
@@ -877,7 +1152,8 @@ impl BlockFlowDisplayListBuilding for BlockFlow {
             self.create_stacking_context(display_list,
                                          Some(Arc::new(RenderLayer::new(self.layer_id(0),
                                                                         transparent,
-                                                                        scroll_policy))));
+                                                                        scroll_policy,
+                                                                        None))));
         self.base.display_list_building_result = StackingContextResult(stacking_context)
     }
 
@@ -885,7 +1161,8 @@ impl BlockFlowDisplayListBuilding for BlockFlow {
         let mut display_list = box DisplayList::new();
         self.build_display_list_for_block_base(&mut *display_list,
                                                layout_context,
-                                               RootOfStackingContextLevel);
+                                               RootOfStackingContextLevel,
+                                               SeparateBorderPaintingMode);
         display_list.form_float_pseudo_stacking_context();
 
         self.base.display_list_building_result = if self.fragment.establishes_stacking_context() {
@@ -895,6 +1172,49 @@ impl BlockFlowDisplayListBuilding for BlockFlow {
         }
     }
 
+    fn build_display_list_for_sticky_block(&mut self, layout_context: &LayoutContext) {
+        let mut display_list = box DisplayList::new();
+        self.build_display_list_for_block_base(&mut *display_list,
+                                               layout_context,
+                                               RootOfStackingContextLevel,
+                                               SeparateBorderPaintingMode);
+
+        // The block is laid out where it would be if it were relatively positioned. It goes in
+        // its own layer, and the compositor works out the sticky offset from these constraints
+        // whenever the page scrolls, without a reflow.
+        //
+        // FIXME: Layers belonging to positioned descendants of the block don't move with it.
+        // FIXME: A new sticky layer is not moved into place until the next scroll.
+        // FIXME(#2795): Get the real container size
+        let container_size = Size2D::zero();
+        let writing_mode = self.base.writing_mode;
+        let rect = self.fragment.border_box.to_physical(writing_mode, container_size);
+        let containing_block_rect = self.base
+                                        .absolute_position_info
+                                        .sticky_containing_block_rect
+                                        .to_physical(writing_mode, container_size);
+
+        // FIXME: Percentage insets should be resolved against the scrollport.
+        let offsets = self.fragment.style().get_positionoffsets();
+        let constraints = StickyPositionConstraints {
+            rect: rect_to_frac_px(&rect),
+            containing_block_rect: rect_to_frac_px(&containing_block_rect),
+            top: sticky_inset(offsets.top, containing_block_rect.size.height),
+            right: sticky_inset(offsets.right, containing_block_rect.size.width),
+            bottom: sticky_inset(offsets.bottom, containing_block_rect.size.height),
+            left: sticky_inset(offsets.left, containing_block_rect.size.width),
+        };
+
+        let transparent = color::rgba(1.0, 1.0, 1.0, 0.0);
+        let layer = RenderLayer::new(self.layer_id(0),
+                                     transparent,
+                                     StickyPosition(constraints),
+                                     None);
+        let stacking_context = self.create_stacking_context(display_list,
+                                                            Some(Arc::new(layer)));
+        self.base.display_list_building_result = StackingContextResult(stacking_context)
+    }
+
     fn create_stacking_context(&self,
                                display_list: Box<DisplayList>,
                                layer: Option<Arc<RenderLayer>>)
@@ -905,6 +1225,135 @@ impl BlockFlowDisplayListBuilding for BlockFlow {
         let opacity = self.fragment.style().get_effects().opacity as f32;
         Arc::new(StackingContext::new(display_list, bounds, z_index, opacity, layer))
     }
+
+    fn create_scroll_container_contents_stacking_context(&self, display_list: Box<DisplayList>)
+                                                         -> Arc<StackingContext> {
+        // Scroll containers establish stacking contexts, so the contents share the origin of
+        // ours.
+        //
+        // FIXME: Layers of positioned descendants don't scroll with the contents.
+        // FIXME(#2795): Get the real container size
+        let container_size = Size2D::zero();
+        let writing_mode = self.base.writing_mode;
+        let (padding_box, scrollable_overflow) = self.scrollport_and_scrollable_overflow();
+        let scrollable_overflow = scrollable_overflow.to_physical(writing_mode, container_size);
+        let bounds = Rect(ZERO_POINT,
+                          Size2D(scrollable_overflow.max_x(), scrollable_overflow.max_y()));
+        let scrollport = Scrollport {
+            id: LayerId::for_scroll_root(self.fragment.node.id() as uint),
+            rect: rect_to_frac_px(&padding_box.to_physical(writing_mode, container_size)),
+        };
+
+        let transparent = color::rgba(1.0, 1.0, 1.0, 0.0);
+        let layer = RenderLayer::new(self.layer_id(1), transparent, Scrollable, Some(scrollport));
+        Arc::new(StackingContext::new(display_list, bounds, 0, 1.0, Some(Arc::new(layer))))
+    }
+}
+
+pub trait MulticolFlowDisplayListBuilding {
+    fn build_display_list_for_multicol(&mut self, layout_context: &LayoutContext);
+    fn build_display_list_for_column_rules(&self,
+                                           display_list: &mut DisplayList,
+                                           flow_origin: Point2D<Au>,
+                                           level: StackingLevel);
+}
+
+impl MulticolFlowDisplayListBuilding for MulticolFlow {
+    fn build_display_list_for_multicol(&mut self, layout_context: &LayoutContext) {
+        // FIXME: Floated and absolutely-positioned multi-column containers don't paint their
+        // column rules.
+        if self.block_flow.base.flags.is_float() ||
+                self.block_flow.base.flags.contains(IS_ABSOLUTELY_POSITIONED) {
+            return self.block_flow.build_display_list(layout_context)
+        }
+
+        // This is `build_display_list_for_block_base`, except that the column rules are painted
+        // between our own background and borders and those of our children. CSS Multi-column §
+        // 4.5.
+        let mut display_list = box DisplayList::new();
+        let stacking_relative_fragment_origin =
+            self.block_flow.base.stacking_relative_position_of_child_fragment(
+                &self.block_flow.fragment);
+        self.block_flow.fragment.build_display_list(&mut *display_list,
+                                                    layout_context,
+                                                    stacking_relative_fragment_origin,
+                                                    BlockLevel,
+                                                    SeparateBorderPaintingMode,
+                                                    &self.block_flow.base.clip_rect);
+        self.build_display_list_for_column_rules(&mut *display_list,
+                                                 stacking_relative_fragment_origin,
+                                                 BlockBackgroundsAndBordersStackingLevel);
+        for kid in self.block_flow.base.children.iter_mut() {
+            flow::mut_base(kid).display_list_building_result.add_to(&mut *display_list);
+        }
+
+        self.block_flow.base.display_list_building_result =
+            if self.block_flow.fragment.establishes_stacking_context() {
+                StackingContextResult(self.block_flow.create_stacking_context(display_list, None))
+            } else {
+                DisplayListResult(display_list)
+            };
+
+        if opts::get().validate_display_list_geometry {
+            self.block_flow.base.validate_display_list_geometry();
+        }
+    }
+
+    fn build_display_list_for_column_rules(&self,
+                                           display_list: &mut DisplayList,
+                                           flow_origin: Point2D<Au>,
+                                           level: StackingLevel) {
+        let fragment = &self.block_flow.fragment;
+        let style = fragment.style();
+        let column_style = style.get_column();
+        let rule_inline_size = column_style.column_rule_width;
+        match column_style.column_rule_style {
+            border_style::none | border_style::hidden => return,
+            _ => {}
+        }
+        if rule_inline_size == Au(0) || self.filled_column_count < 2 ||
+                style.get_inheritedbox().visibility != visibility::visible {
+            return
+        }
+
+        let stacking_relative_flow_origin = if fragment.establishes_stacking_context() {
+            ZERO_POINT
+        } else {
+            flow_origin
+        };
+        let color = style.resolve_color(column_style.column_rule_color).to_gfx_color();
+        let writing_mode = style.writing_mode;
+
+        // Each rule is centered in the gap between two columns that have content.
+        for column_index in range(1, self.filled_column_count) {
+            let inline_position = fragment.border_box.start.i +
+                fragment.border_padding.inline_start +
+                (self.column_inline_size + self.column_gap) * (column_index as i32) -
+                (self.column_gap + rule_inline_size) / 2;
+            let logical_bounds =
+                LogicalRect::new(writing_mode,
+                                 inline_position,
+                                 fragment.border_box.start.b + fragment.border_padding.block_start,
+                                 rule_inline_size,
+                                 self.column_block_size);
+
+            // FIXME(#2795): Get the real container size
+            let physical_bounds = logical_bounds.to_physical(writing_mode, Size2D::zero());
+            let bounds = Rect(physical_bounds.origin + stacking_relative_flow_origin,
+                              physical_bounds.size);
+
+            // A rule is painted as the inline-start border of a box as wide as the rule.
+            let border_widths =
+                LogicalMargin::new(writing_mode, Au(0), Au(0), Au(0), rule_inline_size);
+            display_list.push(BorderDisplayItemClass(box BorderDisplayItem {
+                base: BaseDisplayItem::new(bounds, fragment.node, self.block_flow.base.clip_rect),
+                border_widths: border_widths.to_physical(writing_mode),
+                color: SideOffsets2D::new_all_same(color),
+                style: SideOffsets2D::new_all_same(column_style.column_rule_style),
+                radius: Default::default(),
+            }), level);
+        }
+    }
 }
 
 // A helper data structure for gradients.
@@ -930,6 +1379,200 @@ fn position_to_offset(position: LengthOrPercentage, Au(total_length): Au) -> f32
     }
 }
 
+/// Converts a rectangle in app units to a rectangle in fractional pixels.
+fn rect_to_frac_px(rect: &Rect<Au>) -> Rect<f32> {
+    Rect(Point2D(geometry::to_frac_px(rect.origin.x) as f32,
+                 geometry::to_frac_px(rect.origin.y) as f32),
+         Size2D(geometry::to_frac_px(rect.size.width) as f32,
+                geometry::to_frac_px(rect.size.height) as f32))
+}
+
+/// Returns the inset, in fractional pixels, that a `position: sticky` element keeps from the edge
+/// of the scrollport on one side, or `None` if the inset on that side is `auto`.
+fn sticky_inset(inset: LengthOrPercentageOrAuto, containing_length: Au) -> Option<f32> {
+    match MaybeAuto::from_style(inset, containing_length) {
+        Auto => None,
+        Specified(length) => Some(geometry::to_frac_px(length) as f32),
+    }
+}
+
+/// Returns the value of a background property for the given background layer. Lists that are
+/// shorter than `background-image` are repeated as necessary.
+fn background_layer<'a, T>(values: &'a Vec<T>, layer_index: uint) -> &'a T {
+    &values[layer_index % values.len()]
+}
+
+/// Converts a `background-clip` value to the equivalent `background-origin` value so that both
+/// can be resolved with `compute_background_box()`.
+fn clip_to_origin(clip: background_clip::SingleComputedValue)
+                  -> background_origin::SingleComputedValue {
+    match clip {
+        background_clip::border_box => background_origin::border_box,
+        background_clip::padding_box => background_origin::padding_box,
+        background_clip::content_box => background_origin::content_box,
+    }
+}
+
+/// Computes the size of a background image per CSS-BACKGROUNDS § 3.9. Images without an intrinsic
+/// size, such as gradients, fill the positioning area unless sized explicitly.
+fn compute_background_image_size(size: &background_size::SingleComputedValue,
+                                 positioning_area: &Size2D<Au>,
+                                 intrinsic_size: Option<Size2D<Au>>)
+                                 -> Size2D<Au> {
+    let area_width = positioning_area.width.to_f64().unwrap();
+    let area_height = positioning_area.height.to_f64().unwrap();
+    match (size, intrinsic_size) {
+        (&background_size::Cover, Some(intrinsic_size)) |
+        (&background_size::Contain, Some(intrinsic_size)) => {
+            let intrinsic_width = intrinsic_size.width.to_f64().unwrap();
+            let intrinsic_height = intrinsic_size.height.to_f64().unwrap();
+            if intrinsic_width == 0.0 || intrinsic_height == 0.0 {
+                return *positioning_area
+            }
+            let (horizontal_scale, vertical_scale) = (area_width / intrinsic_width,
+                                                      area_height / intrinsic_height);
+            let scale = match *size {
+                background_size::Cover => horizontal_scale.max(vertical_scale),
+                _ => horizontal_scale.min(vertical_scale),
+            };
+            Size2D(intrinsic_size.width.scale_by(scale), intrinsic_size.height.scale_by(scale))
+        }
+        (&background_size::Cover, None) | (&background_size::Contain, None) => *positioning_area,
+        (&background_size::Explicit(width, height), _) => {
+            let width = MaybeAuto::from_style(width, positioning_area.width);
+            let height = MaybeAuto::from_style(height, positioning_area.height);
+            match (width, height, intrinsic_size) {
+                (Specified(width), Specified(height), _) => Size2D(width, height),
+                (Auto, Auto, Some(intrinsic_size)) => intrinsic_size,
+                (Specified(width), Auto, Some(intrinsic_size)) if
+                        intrinsic_size.width > Au(0) => {
+                    let scale = width.to_f64().unwrap() /
+                        intrinsic_size.width.to_f64().unwrap();
+                    Size2D(width, intrinsic_size.height.scale_by(scale))
+                }
+                (Auto, Specified(height), Some(intrinsic_size)) if
+                        intrinsic_size.height > Au(0) => {
+                    let scale = height.to_f64().unwrap() /
+                        intrinsic_size.height.to_f64().unwrap();
+                    Size2D(intrinsic_size.width.scale_by(scale), height)
+                }
+                (width, height, _) => {
+                    Size2D(width.specified_or_default(positioning_area.width),
+                           height.specified_or_default(positioning_area.height))
+                }
+            }
+        }
+    }
+}
+
+/// Determines the offset of each color stop along a gradient line of the given length per
+/// CSS-IMAGES § 3.4.
+fn compute_gradient_stops(color_stops: &[ColorStop], length: Au, style: &ComputedValues)
+                          -> Vec<GradientStop> {
+    let (mut stops, mut stop_run) = (Vec::new(), None);
+    let mut previous_offset = 0.0;
+    for (i, stop) in color_stops.iter().enumerate() {
+        let offset = match stop.position {
+            None => {
+                if stop_run.is_none() {
+                    // Initialize a new stop run.
+                    let start_offset = if i == 0 {
+                        0.0
+                    } else {
+                        // `unwrap()` here should never fail because this is the beginning of
+                        // a stop run, which is always bounded by a length or percentage.
+                        position_to_offset(color_stops[i - 1].position.unwrap(), length)
+                    };
+                    let (end_index, end_offset) =
+                        match color_stops.slice_from(i)
+                                         .iter()
+                                         .enumerate()
+                                         .find(|&(_, ref stop)| stop.position.is_some()) {
+                            None => (color_stops.len() - 1, 1.0),
+                            Some((end_index, end_stop)) => {
+                                // `unwrap()` here should never fail because this is the end of
+                                // a stop run, which is always bounded by a length or
+                                // percentage.
+                                (end_index,
+                                 position_to_offset(end_stop.position.unwrap(), length))
+                            }
+                        };
+                    stop_run = Some(StopRun {
+                        start_offset: start_offset,
+                        end_offset: end_offset,
+                        start_index: i,
+                        stop_count: end_index - i,
+                    })
+                }
+
+                let stop_run = stop_run.unwrap();
+                let stop_run_length = stop_run.end_offset - stop_run.start_offset;
+                if stop_run.stop_count == 0 {
+                    stop_run.end_offset
+                } else {
+                    stop_run.start_offset +
+                        stop_run_length * (i - stop_run.start_index) as f32 /
+                            (stop_run.stop_count as f32)
+                }
+            }
+            Some(position) => {
+                stop_run = None;
+                position_to_offset(position, length)
+            }
+        };
+
+        // Later stops can't be behind earlier stops.
+        let offset = if i > 0 && offset < previous_offset {
+            previous_offset
+        } else {
+            offset
+        };
+        previous_offset = offset;
+
+        stops.push(GradientStop {
+            offset: offset,
+            color: style.resolve_color(stop.color).to_gfx_color()
+        })
+    }
+    stops
+}
+
+/// Rescales the stops of a repeating gradient so that the first stop is at offset 0 and the last
+/// is at offset 1, and returns the original offsets of the first and last stops. Returns `None`
+/// if all the stops are at the same position, in which case the gradient can't repeat.
+fn normalize_repeating_gradient_stops(stops: &mut Vec<GradientStop>) -> Option<(f32, f32)> {
+    let first_offset = stops[0].offset;
+    let last_offset = stops[stops.len() - 1].offset;
+    let span = last_offset - first_offset;
+    if span.approx_eq(&0.0) {
+        return None
+    }
+    for stop in stops.iter_mut() {
+        stop.offset = (stop.offset - first_offset) / span;
+    }
+    Some((first_offset, last_offset))
+}
+
+/// Returns the point at the given fraction of the way from `start` to `end`.
+fn point_along_line(start: &Point2D<Au>, end: &Point2D<Au>, fraction: f32) -> Point2D<Au> {
+    Point2D(start.x + (end.x - start.x).scale_by(fraction as f64),
+            start.y + (end.y - start.y).scale_by(fraction as f64))
+}
+
+/// Paints a gradient whose ending shape or repeating span has no size with the color of its last
+/// stop, per CSS-IMAGES § 3.
+fn push_solid_color_for_degenerate_gradient(display_list: &mut DisplayList,
+                                            level: StackingLevel,
+                                            bounds: &Rect<Au>,
+                                            clip_rect: &Rect<Au>,
+                                            node: OpaqueNode,
+                                            stops: &[GradientStop]) {
+    display_list.push(SolidColorDisplayItemClass(box SolidColorDisplayItem {
+        base: BaseDisplayItem::new(*bounds, node, *clip_rect),
+        color: stops[stops.len() - 1].color,
+    }), level)
+}
+
 /// "Steps" as defined by CSS 2.1 § E.2.
 #[deriving(Clone, PartialEq, Show)]
 pub enum StackingLevel {
@@ -959,6 +1602,16 @@ pub enum BackgroundAndBorderLevel {
     ContentLevel,
 }
 
+/// How a fragment's borders are to be painted.
+pub enum BorderPaintingMode<'a> {
+    /// Paint the borders specified by the fragment's style, as in the separated borders model.
+    SeparateBorderPaintingMode,
+    /// Paint the given collapsed borders instead, per CSS 2.1 § 17.6.2.
+    CollapseBorderPaintingMode(&'a LogicalMargin<CollapsedBorder>),
+    /// Paint no borders, because some other fragment paints them.
+    HiddenBorderPaintingMode,
+}
+
 trait StackingContextConstruction {
     /// Adds the given display item at the specified level to this display list.
     fn push(&mut self, display_item: DisplayItem, level: StackingLevel);
@@ -0,0 +1,938 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Layout for elements with `display: flex`.
+//!
+//! "CSS Flexbox" refers to the W3C "CSS Flexible Box Layout Module Level 1" specification
+//! available here:
+//!
+//!   http://dev.w3.org/csswg/css-flexbox/
+
+#![deny(unsafe_blocks)]
+
+use block::{BlockFlow, MarginsMayNotCollapse};
+use construct::FlowConstructor;
+use context::LayoutContext;
+use flow::{FlexFlowClass, FlowClass, Flow, ImmutableFlowUtils};
+use flow::{IS_ABSOLUTELY_POSITIONED, IS_FLEX_ITEM};
+use flow;
+use fragment::FragmentBoundsIterator;
+use incremental::{REFLOW, REFLOW_OUT_OF_FLOW};
+use layout_debug;
+use model::{Auto, MaybeAuto, Specified, specified, specified_or_none};
+use wrapper::ThreadSafeLayoutNode;
+
+use servo_util::geometry::{Au, MAX_AU};
+use std::cmp::{max, min};
+use std::fmt;
+use style::{ComputedValues, CSSFloat};
+use style::computed_values::{LengthOrPercentageOrAuto, LPA_Auto, LPA_Percentage, LPN_Percentage};
+use style::computed_values::{align_content, align_items, align_self, box_sizing, flex_direction};
+use style::computed_values::{flex_wrap, justify_content, overflow};
+use sync::Arc;
+
+/// Information about a single in-flow child of a flex container.
+///
+/// All sizes here are outer sizes along the main axis; that is, they include the margins, borders,
+/// and padding of the item.
+struct FlexItem {
+    /// The index of this item's flow among the children of the flex container.
+    index: uint,
+    /// The value of the `order` property of this item.
+    order: i32,
+    /// The flex base size of this item. CSS Flexbox § 9.2.
+    base_size: Au,
+    /// The minimum main size of this item, including the automatic minimum size.
+    min_size: Au,
+    /// The maximum main size of this item.
+    max_size: Au,
+    /// The flex grow factor of this item.
+    flex_grow: CSSFloat,
+    /// The flex shrink factor of this item.
+    flex_shrink: CSSFloat,
+    /// Whether the main size of this item has been frozen while resolving flexible lengths.
+    frozen: bool,
+    /// The used main size of this item.
+    main_size: Au,
+    /// The offset of the main-start margin edge of this item from the main-start content edge of
+    /// its line.
+    main_position: Au,
+    /// The outer inline-size of this item.
+    inline_size: Au,
+    /// The offset of the inline-start margin edge of this item from the inline-start content edge
+    /// of the flex container.
+    inline_position: Au,
+}
+
+impl FlexItem {
+    fn new(index: uint, style: Option<&ComputedValues>) -> FlexItem {
+        let (order, flex_grow, flex_shrink) = match style {
+            Some(style) => {
+                let flex = style.get_flex();
+                (flex.order, flex.flex_grow, flex.flex_shrink)
+            }
+            None => (0, 0.0, 1.0),
+        };
+        FlexItem {
+            index: index,
+            order: order,
+            base_size: Au(0),
+            min_size: Au(0),
+            max_size: MAX_AU,
+            flex_grow: flex_grow,
+            flex_shrink: flex_shrink,
+            frozen: false,
+            main_size: Au(0),
+            main_position: Au(0),
+            inline_size: Au(0),
+            inline_position: Au(0),
+        }
+    }
+
+    /// Clamps the given outer main size by the minimum and maximum main sizes of this item. The
+    /// minimum wins if the two conflict.
+    fn clamp(&self, size: Au) -> Au {
+        max(self.min_size, min(self.max_size, size))
+    }
+
+    /// Computes the flex base size and the minimum and maximum main sizes of this item along the
+    /// inline axis. CSS Flexbox § 9.2.
+    fn compute_inline_sizes(&mut self,
+                            kid: &mut Flow,
+                            style: Option<&ComputedValues>,
+                            container_inline_size: Au) {
+        let intrinsic_inline_sizes = flow::base(kid).intrinsic_inline_sizes;
+        let style = match style {
+            Some(style) => style,
+            None => {
+                self.base_size = intrinsic_inline_sizes.preferred_inline_size;
+                self.min_size = intrinsic_inline_sizes.minimum_inline_size;
+                return
+            }
+        };
+
+        let margin = style.logical_margin();
+        let padding = style.logical_padding();
+        let margins =
+            MaybeAuto::from_style(margin.inline_start, container_inline_size).specified_or_zero() +
+            MaybeAuto::from_style(margin.inline_end, container_inline_size).specified_or_zero();
+        let surrounding_size = match style.get_box().box_sizing {
+            box_sizing::content_box => {
+                margins + specified(padding.inline_start, container_inline_size) +
+                    specified(padding.inline_end, container_inline_size) +
+                    style.logical_border_width().inline_start_end()
+            }
+            box_sizing::border_box => margins,
+        };
+
+        // A `flex-basis` of `auto` means that the main size property is used instead.
+        let inline_size = MaybeAuto::from_style(style.content_inline_size(),
+                                                container_inline_size);
+        let basis = match style.get_flex().flex_basis {
+            LPA_Auto => inline_size,
+            basis => MaybeAuto::from_style(basis, container_inline_size),
+        };
+        self.base_size = match basis {
+            Specified(size) => size + surrounding_size,
+            Auto => intrinsic_inline_sizes.preferred_inline_size,
+        };
+
+        // CSS Flexbox § 4.5: items that don't scroll can't shrink below their content.
+        let automatic_min_size = if style.get_box().overflow != overflow::visible {
+            Au(0)
+        } else {
+            match inline_size {
+                Specified(size) => {
+                    min(size + surrounding_size, intrinsic_inline_sizes.minimum_inline_size)
+                }
+                Auto => intrinsic_inline_sizes.minimum_inline_size,
+            }
+        };
+        self.min_size = max(automatic_min_size,
+                            specified(style.min_inline_size(), container_inline_size) +
+                                surrounding_size);
+        self.max_size = match specified_or_none(style.max_inline_size(), container_inline_size) {
+            Some(size) => size + surrounding_size,
+            None => MAX_AU,
+        };
+    }
+
+    /// Computes the flex base size and the minimum and maximum main sizes of this item along the
+    /// block axis. The block-size of the item must already have been assigned.
+    fn compute_block_sizes(&mut self,
+                           kid: &mut Flow,
+                           style: Option<&ComputedValues>,
+                           container_block_size: Option<Au>) {
+        let (margins, border_padding) = block_direction_margins_and_border_padding(kid);
+        let outer_block_size = flow::base(kid).position.size.block + margins;
+        let style = match style {
+            Some(style) => style,
+            None => {
+                self.base_size = outer_block_size;
+                self.min_size = outer_block_size;
+                return
+            }
+        };
+
+        let surrounding_size = match style.get_box().box_sizing {
+            box_sizing::content_box => margins + border_padding,
+            box_sizing::border_box => margins,
+        };
+
+        // If `flex-basis` is `auto`, the item has already been laid out with its main size.
+        self.base_size = match block_size_from_style(style.get_flex().flex_basis,
+                                                     container_block_size) {
+            Specified(size) => size + surrounding_size,
+            Auto => outer_block_size,
+        };
+
+        // CSS Flexbox § 4.5: items that don't scroll can't shrink below their content.
+        let automatic_min_size = match (style.get_box().overflow, style.content_block_size()) {
+            (overflow::visible, LPA_Auto) => outer_block_size,
+            _ => Au(0),
+        };
+        let min_size = specified(style.min_block_size(), container_block_size.unwrap_or(Au(0)));
+        self.min_size = max(automatic_min_size, min_size + surrounding_size);
+        self.max_size = match max_block_size_from_style(style, container_block_size) {
+            Some(size) => size + surrounding_size,
+            None => MAX_AU,
+        };
+    }
+}
+
+/// A line of flex items. CSS Flexbox § 6.
+struct FlexLine {
+    /// The index of the first item in this line.
+    start: uint,
+    /// The index one past the last item in this line.
+    end: uint,
+    /// The cross size of this line.
+    cross_size: Au,
+}
+
+/// A flex container.
+pub struct FlexFlow {
+    /// Data common to all block flows.
+    pub block_flow: BlockFlow,
+
+    /// The in-flow children of this flex container, in `order`-modified document order.
+    items: Vec<FlexItem>,
+
+    /// The lines that the items have been broken into.
+    lines: Vec<FlexLine>,
+}
+
+impl FlexFlow {
+    pub fn from_node(constructor: &mut FlowConstructor,
+                     node: &ThreadSafeLayoutNode)
+                     -> FlexFlow {
+        FlexFlow {
+            block_flow: BlockFlow::from_node(constructor, node),
+            items: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Returns true if the main axis of this flex container is the inline axis.
+    fn is_row(&self) -> bool {
+        match self.block_flow.fragment.style().get_flex().flex_direction {
+            flex_direction::row | flex_direction::row_reverse => true,
+            flex_direction::column | flex_direction::column_reverse => false,
+        }
+    }
+
+    /// Returns true if the main-start and main-end directions are swapped.
+    fn is_main_axis_reversed(&self) -> bool {
+        match self.block_flow.fragment.style().get_flex().flex_direction {
+            flex_direction::row_reverse | flex_direction::column_reverse => true,
+            flex_direction::row | flex_direction::column => false,
+        }
+    }
+
+    /// Returns the block-size of the content box of this flex container if it is definite.
+    fn explicit_content_block_size(&self) -> Option<Au> {
+        let fragment = &self.block_flow.fragment;
+        let block_size = block_size_from_style(fragment.style().content_block_size(),
+                                               self.block_flow
+                                                   .base
+                                                   .block_container_explicit_block_size);
+        match (block_size, fragment.style().get_box().box_sizing) {
+            (Specified(size), box_sizing::content_box) => Some(size),
+            (Specified(size), box_sizing::border_box) => {
+                Some(max(Au(0), size - fragment.border_padding.block_start_end()))
+            }
+            (Auto, _) => None,
+        }
+    }
+
+    /// Clamps a content block-size of this flex container by `min-block-size` and
+    /// `max-block-size`.
+    fn clamp_content_block_size(&self, block_size: Au) -> Au {
+        let style = self.block_flow.fragment.style();
+        let container_block_size = self.block_flow.base.block_container_explicit_block_size;
+        let block_size = match max_block_size_from_style(style, container_block_size) {
+            Some(max_block_size) => min(block_size, max_block_size),
+            None => block_size,
+        };
+        max(block_size, specified(style.min_block_size(), container_block_size.unwrap_or(Au(0))))
+    }
+
+    /// Gathers the in-flow children of this flex container and sorts them by `order`. CSS Flexbox
+    /// § 5.4.
+    fn collect_items(&mut self) {
+        self.items.clear();
+        for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+            if flow::base(kid).flags.contains(IS_ABSOLUTELY_POSITIONED) {
+                continue
+            }
+            let style = item_style(kid);
+            self.items.push(FlexItem::new(index, style.as_ref().map(|style| &**style)))
+        }
+
+        // This sort is stable, so items with the same `order` stay in document order.
+        self.items.sort_by(|a, b| a.order.cmp(&b.order));
+    }
+
+    /// Collects items into flex lines, breaking whenever the next item would overflow the
+    /// available main size, if wrapping is enabled. CSS Flexbox § 9.3.
+    fn collect_lines(&mut self, available_main_size: Au, wrap: bool) {
+        self.lines.clear();
+        let mut start = 0;
+        let mut line_size = Au(0);
+        for (index, item) in self.items.iter().enumerate() {
+            let item_size = item.clamp(item.base_size);
+            if wrap && index > start && line_size + item_size > available_main_size {
+                self.lines.push(FlexLine {
+                    start: start,
+                    end: index,
+                    cross_size: Au(0),
+                });
+                start = index;
+                line_size = Au(0);
+            }
+            line_size = line_size + item_size;
+        }
+        if start < self.items.len() {
+            self.lines.push(FlexLine {
+                start: start,
+                end: self.items.len(),
+                cross_size: Au(0),
+            });
+        }
+    }
+
+    /// Resolves the main sizes of all items and positions them along the main axis within their
+    /// lines.
+    fn lay_out_lines_along_main_axis(&mut self, available_main_size: Au) {
+        let justify_content = self.block_flow.fragment.style().get_flex().justify_content;
+        for line in self.lines.iter() {
+            let items = self.items.slice_mut(line.start, line.end);
+            resolve_flexible_lengths(items, available_main_size);
+
+            let used_size = items.iter().fold(Au(0), |size, item| size + item.main_size);
+            let distribution = match justify_content {
+                justify_content::flex_start => DistributeFromStart,
+                justify_content::flex_end => DistributeFromEnd,
+                justify_content::center => DistributeFromCenter,
+                justify_content::space_between => DistributeSpaceBetween,
+                justify_content::space_around => DistributeSpaceAround,
+            };
+            let (mut position, gap) = distribute_free_space(available_main_size - used_size,
+                                                            items.len(),
+                                                            distribution);
+            for item in items.iter_mut() {
+                item.main_position = position;
+                position = position + item.main_size + gap;
+            }
+        }
+    }
+
+    /// Assigns inline-sizes to the items of a flex container whose main axis is the inline axis.
+    fn assign_inline_sizes_to_row_items(&mut self, content_inline_size: Au) {
+        {
+            let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+            let kids = kids.as_mut_slice();
+            for item in self.items.iter_mut() {
+                let kid = &mut *kids[item.index];
+                let style = item_style(kid);
+                item.compute_inline_sizes(kid,
+                                          style.as_ref().map(|style| &**style),
+                                          content_inline_size);
+            }
+        }
+
+        let wrap = self.block_flow.fragment.style().get_flex().flex_wrap != flex_wrap::nowrap;
+        self.collect_lines(content_inline_size, wrap);
+        self.lay_out_lines_along_main_axis(content_inline_size);
+
+        let reversed = self.is_main_axis_reversed();
+        for item in self.items.iter_mut() {
+            item.inline_size = item.main_size;
+            item.inline_position = if reversed {
+                content_inline_size - item.main_position - item.main_size
+            } else {
+                item.main_position
+            };
+        }
+    }
+
+    /// Assigns inline-sizes to the items of a flex container whose main axis is the block axis.
+    /// The inline axis is then the cross axis, so this performs cross-axis alignment.
+    ///
+    /// FIXME: Column flex containers are always single-line.
+    fn assign_inline_sizes_to_column_items(&mut self, content_inline_size: Au) {
+        let align_items = self.block_flow.fragment.style().get_flex().align_items;
+        let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+        let kids = kids.as_mut_slice();
+        for item in self.items.iter_mut() {
+            let kid = &mut *kids[item.index];
+            let style = item_style(kid);
+            let intrinsic_inline_sizes = flow::base(kid).intrinsic_inline_sizes;
+
+            // Compute the cross size the way it would be computed for a row item, then either
+            // stretch it or shrink it to fit.
+            item.compute_inline_sizes(kid,
+                                      style.as_ref().map(|style| &**style),
+                                      content_inline_size);
+            let (alignment, has_auto_inline_size) = match style {
+                Some(ref style) => {
+                    (used_alignment(style.get_flex().align_self, align_items),
+                     style.content_inline_size() == LPA_Auto)
+                }
+                None => (align_items::stretch, true),
+            };
+            let cross_size = match (alignment, has_auto_inline_size) {
+                (align_items::stretch, true) => content_inline_size,
+                (_, true) => {
+                    max(intrinsic_inline_sizes.minimum_inline_size,
+                        min(intrinsic_inline_sizes.preferred_inline_size, content_inline_size))
+                }
+                (_, false) => item.base_size,
+            };
+            item.inline_size = item.clamp(cross_size);
+            item.inline_position = match alignment {
+                align_items::flex_end => content_inline_size - item.inline_size,
+                align_items::center => (content_inline_size - item.inline_size) / 2,
+                align_items::stretch | align_items::flex_start | align_items::baseline => Au(0),
+            };
+        }
+    }
+
+    /// Lays out the items of a flex container whose main axis is the inline axis along the block
+    /// axis, which is the cross axis. Returns the content block-size of the container.
+    fn assign_block_sizes_to_row_items(&mut self, block_start_content_edge: Au) -> Au {
+        let style = self.block_flow.fragment.style.clone();
+        let flex_style = style.get_flex();
+
+        // Determine the cross size of each line. CSS Flexbox § 9.4.
+        //
+        // FIXME: `align-self: baseline` is treated as `flex-start`.
+        {
+            let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+            let kids = kids.as_mut_slice();
+            for line in self.lines.iter_mut() {
+                line.cross_size = Au(0);
+                for item in self.items.slice(line.start, line.end).iter() {
+                    let kid = &mut *kids[item.index];
+                    let (margins, _) = block_direction_margins_and_border_padding(kid);
+                    line.cross_size = max(line.cross_size,
+                                          flow::base(kid).position.size.block + margins);
+                }
+            }
+        }
+        let lines_size = self.lines.iter().fold(Au(0), |size, line| size + line.cross_size);
+        let content_block_size = match self.explicit_content_block_size() {
+            Some(block_size) => block_size,
+            None => self.clamp_content_block_size(lines_size),
+        };
+
+        // Distribute the remaining cross space among the lines. CSS Flexbox § 9.4 and § 8.4.
+        let single_line = flex_style.flex_wrap == flex_wrap::nowrap;
+        let free_space = content_block_size - lines_size;
+        if single_line {
+            self.lines.as_mut_slice()[0].cross_size = content_block_size
+        } else if flex_style.align_content == align_content::stretch && free_space > Au(0) {
+            let extra_size = free_space / (self.lines.len() as i32);
+            for line in self.lines.iter_mut() {
+                line.cross_size = line.cross_size + extra_size
+            }
+        }
+        let distribution = match flex_style.align_content {
+            _ if single_line => DistributeFromStart,
+            align_content::stretch | align_content::flex_start => DistributeFromStart,
+            align_content::flex_end => DistributeFromEnd,
+            align_content::center => DistributeFromCenter,
+            align_content::space_between => DistributeSpaceBetween,
+            align_content::space_around => DistributeSpaceAround,
+        };
+        let lines_size = self.lines.iter().fold(Au(0), |size, line| size + line.cross_size);
+        let (mut line_position, gap) = distribute_free_space(content_block_size - lines_size,
+                                                             self.lines.len(),
+                                                             distribution);
+
+        // Align the items within their lines. CSS Flexbox § 8.3.
+        //
+        // FIXME: Auto margins are treated as zero.
+        let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+        let kids = kids.as_mut_slice();
+        let wrap_reverse = flex_style.flex_wrap == flex_wrap::wrap_reverse;
+        for line in self.lines.iter() {
+            for item in self.items.slice(line.start, line.end).iter() {
+                let kid = &mut *kids[item.index];
+                let (margin_block_start, margin_block_end) = block_direction_margins(kid);
+                let (alignment, has_auto_block_size) = match item_style(kid) {
+                    Some(ref style) => {
+                        (used_alignment(style.get_flex().align_self, flex_style.align_items),
+                         style.content_block_size() == LPA_Auto)
+                    }
+                    None => (align_items::stretch, true),
+                };
+                if alignment == align_items::stretch && has_auto_block_size {
+                    let block_size = max(Au(0), line.cross_size - margin_block_start -
+                                                    margin_block_end);
+                    set_item_block_size(kid, block_size);
+                }
+
+                let outer_block_size = flow::base(kid).position.size.block + margin_block_start +
+                    margin_block_end;
+                let offset = match alignment {
+                    align_items::flex_end => line.cross_size - outer_block_size,
+                    align_items::center => (line.cross_size - outer_block_size) / 2,
+                    align_items::stretch | align_items::flex_start | align_items::baseline => {
+                        Au(0)
+                    }
+                };
+
+                // With `wrap-reverse`, the cross-start and cross-end directions are swapped.
+                let position = if wrap_reverse {
+                    content_block_size - line_position - offset - outer_block_size
+                } else {
+                    line_position + offset
+                };
+                flow::mut_base(kid).position.start.b = block_start_content_edge + position +
+                    margin_block_start;
+            }
+            line_position = line_position + line.cross_size + gap;
+        }
+
+        content_block_size
+    }
+
+    /// Lays out the items of a flex container whose main axis is the block axis along that axis.
+    /// Returns the content block-size of the container.
+    fn assign_block_sizes_to_column_items(&mut self, block_start_content_edge: Au) -> Au {
+        let explicit_content_block_size = self.explicit_content_block_size();
+        {
+            let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+            let kids = kids.as_mut_slice();
+            for item in self.items.iter_mut() {
+                let kid = &mut *kids[item.index];
+                let style = item_style(kid);
+                item.compute_block_sizes(kid,
+                                         style.as_ref().map(|style| &**style),
+                                         explicit_content_block_size);
+            }
+        }
+
+        // Without a definite block-size, the container is sized to fit its items, so no space
+        // is left to distribute.
+        let content_block_size = match explicit_content_block_size {
+            Some(block_size) => block_size,
+            None => {
+                let hypothetical_size = self.items.iter().fold(Au(0), |size, item| {
+                    size + item.clamp(item.base_size)
+                });
+                self.clamp_content_block_size(hypothetical_size)
+            }
+        };
+        self.collect_lines(content_block_size, false);
+        self.lay_out_lines_along_main_axis(content_block_size);
+
+        let reversed = self.is_main_axis_reversed();
+        let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+        let kids = kids.as_mut_slice();
+        for item in self.items.iter() {
+            let kid = &mut *kids[item.index];
+            let (margin_block_start, margin_block_end) = block_direction_margins(kid);
+            set_item_block_size(kid, max(Au(0), item.main_size - margin_block_start -
+                                                    margin_block_end));
+            let position = if reversed {
+                content_block_size - item.main_position - item.main_size
+            } else {
+                item.main_position
+            };
+            flow::mut_base(kid).position.start.b = block_start_content_edge + position +
+                margin_block_start;
+        }
+
+        content_block_size
+    }
+}
+
+impl Flow for FlexFlow {
+    fn class(&self) -> FlowClass {
+        FlexFlowClass
+    }
+
+    fn as_flex<'a>(&'a mut self) -> &'a mut FlexFlow {
+        self
+    }
+
+    fn as_block<'a>(&'a mut self) -> &'a mut BlockFlow {
+        &mut self.block_flow
+    }
+
+    fn as_immutable_block<'a>(&'a self) -> &'a BlockFlow {
+        &self.block_flow
+    }
+
+    /// The intrinsic inline-sizes of a row flex container are the sums of those of its items,
+    /// except that the minimum inline-size of a multi-line container is that of its widest item.
+    fn bubble_inline_sizes(&mut self) {
+        let _scope = layout_debug_scope!("flex::bubble_inline_sizes {:x}",
+                                         self.block_flow.base.debug_id());
+
+        // This takes care of floated descendants and of column flex containers, whose intrinsic
+        // inline-sizes are computed the same way as those of blocks.
+        self.block_flow.bubble_inline_sizes();
+
+        let style = self.block_flow.fragment.style.clone();
+        if !self.is_row() || style.content_inline_size() != LPA_Auto {
+            return
+        }
+
+        let wrap = style.get_flex().flex_wrap != flex_wrap::nowrap;
+        let mut computation = self.block_flow.fragment.compute_intrinsic_inline_sizes();
+        for kid in self.block_flow.base.child_iter() {
+            let kid_base = flow::base(kid);
+            if kid_base.flags.contains(IS_ABSOLUTELY_POSITIONED) {
+                continue
+            }
+            if wrap {
+                computation.union_inline(&kid_base.intrinsic_inline_sizes)
+            } else {
+                let sizes = &mut computation.content_intrinsic_sizes;
+                sizes.minimum_inline_size = sizes.minimum_inline_size +
+                    kid_base.intrinsic_inline_sizes.minimum_inline_size;
+                sizes.preferred_inline_size = sizes.preferred_inline_size +
+                    kid_base.intrinsic_inline_sizes.preferred_inline_size;
+            }
+        }
+        self.block_flow.base.intrinsic_inline_sizes = computation.finish();
+    }
+
+    fn assign_inline_sizes(&mut self, layout_context: &LayoutContext) {
+        let _scope = layout_debug_scope!("flex::assign_inline_sizes {:x}",
+                                         self.block_flow.base.debug_id());
+
+        if !self.block_flow.base.restyle_damage.intersects(REFLOW_OUT_OF_FLOW | REFLOW) {
+            return
+        }
+
+        // Compute our own inline-size and hand the children the information that blocks hand to
+        // their children. The inline-sizes and positions of flex items are overridden below.
+        self.block_flow.assign_inline_sizes(layout_context);
+
+        let inline_start_content_edge = self.block_flow.fragment.border_box.start.i +
+            self.block_flow.fragment.border_padding.inline_start;
+        let content_inline_size = self.block_flow.fragment.border_box.size.inline -
+            self.block_flow.fragment.border_padding.inline_start_end();
+
+        self.collect_items();
+        if self.is_row() {
+            self.assign_inline_sizes_to_row_items(content_inline_size)
+        } else {
+            self.assign_inline_sizes_to_column_items(content_inline_size)
+        }
+
+        // Each flex item receives its outer inline-size as its containing block inline-size; see
+        // the `FlexItem` inline-size computer in `block`.
+        let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+        let kids = kids.as_mut_slice();
+        for item in self.items.iter() {
+            let kid_base = flow::mut_base(&mut *kids[item.index]);
+            kid_base.block_container_inline_size = item.inline_size;
+            kid_base.position.start.i = inline_start_content_edge + item.inline_position;
+            kid_base.flags.insert(IS_FLEX_ITEM);
+        }
+    }
+
+    fn assign_block_size<'a>(&mut self, layout_context: &'a LayoutContext<'a>) {
+        let _scope = layout_debug_scope!("flex::assign_block_size {:x}",
+                                         self.block_flow.base.debug_id());
+
+        let needs_reflow = self.block_flow.base.restyle_damage.contains(REFLOW);
+
+        // Lay the children out as if this were a block formatting context first. This takes care
+        // of absolutely-positioned and in-order descendants; the in-flow children are then moved
+        // to their flex positions.
+        //
+        // FIXME: Absolutely-positioned descendants see the block-size of this flow from before
+        // the flex items were moved.
+        self.block_flow.assign_block_size_block_base(layout_context, MarginsMayNotCollapse);
+        if !needs_reflow || self.items.is_empty() {
+            return
+        }
+
+        let block_start_content_edge = self.block_flow.fragment.border_padding.block_start;
+        let content_block_size = if self.is_row() {
+            self.assign_block_sizes_to_row_items(block_start_content_edge)
+        } else {
+            self.assign_block_sizes_to_column_items(block_start_content_edge)
+        };
+
+        // Laying out the block formatting context may have moved the children in the inline
+        // direction, so put them back.
+        let inline_start_content_edge = self.block_flow.fragment.border_box.start.i +
+            self.block_flow.fragment.border_padding.inline_start;
+        {
+            let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+            let kids = kids.as_mut_slice();
+            for item in self.items.iter() {
+                flow::mut_base(&mut *kids[item.index]).position.start.i =
+                    inline_start_content_edge + item.inline_position
+            }
+        }
+
+        if self.block_flow.base.flags.contains(IS_ABSOLUTELY_POSITIONED) {
+            // Like blocks, store the content block-size for use in calculating the absolute
+            // flow's dimensions later.
+            self.block_flow.fragment.border_box.size.block = content_block_size;
+            return
+        }
+
+        let block_size = content_block_size +
+            self.block_flow.fragment.border_padding.block_start_end();
+        self.block_flow.fragment.border_box.size.block = block_size;
+        self.block_flow.base.position.size.block = block_size;
+    }
+
+    fn assign_block_size_for_inorder_child_if_necessary<'a>(&mut self,
+                                                            layout_context: &'a LayoutContext<'a>)
+                                                            -> bool {
+        // Flex containers are block formatting contexts, so this translates floats past us.
+        self.block_flow.assign_block_size_for_inorder_child_if_necessary(layout_context)
+    }
+
+    fn compute_absolute_position(&mut self) {
+        self.block_flow.compute_absolute_position()
+    }
+
+    fn update_late_computed_inline_position_if_necessary(&mut self, inline_position: Au) {
+        self.block_flow.update_late_computed_inline_position_if_necessary(inline_position)
+    }
+
+    fn update_late_computed_block_position_if_necessary(&mut self, block_position: Au) {
+        self.block_flow.update_late_computed_block_position_if_necessary(block_position)
+    }
+
+    fn build_display_list(&mut self, layout_context: &LayoutContext) {
+        self.block_flow.build_display_list(layout_context)
+    }
+
+    fn repair_style(&mut self, new_style: &Arc<ComputedValues>) {
+        self.block_flow.repair_style(new_style)
+    }
+
+    fn iterate_through_fragment_bounds(&self, iterator: &mut FragmentBoundsIterator) {
+        self.block_flow.iterate_through_fragment_bounds(iterator);
+    }
+}
+
+impl fmt::Show for FlexFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FlexFlow: {}", self.block_flow)
+    }
+}
+
+/// How free space is distributed along an axis by `justify-content` and `align-content`.
+enum FreeSpaceDistribution {
+    DistributeFromStart,
+    DistributeFromEnd,
+    DistributeFromCenter,
+    DistributeSpaceBetween,
+    DistributeSpaceAround,
+}
+
+/// Returns the offset of the first of `count` boxes and the gap between consecutive boxes when
+/// `free_space` is distributed among them. CSS Flexbox § 8.2.
+fn distribute_free_space(free_space: Au, count: uint, distribution: FreeSpaceDistribution)
+                         -> (Au, Au) {
+    let count = count as i32;
+    match distribution {
+        DistributeFromStart => (Au(0), Au(0)),
+        DistributeFromEnd => (free_space, Au(0)),
+        DistributeFromCenter => (free_space / 2, Au(0)),
+        DistributeSpaceBetween if free_space > Au(0) && count > 1 => {
+            (Au(0), free_space / (count - 1))
+        }
+        DistributeSpaceBetween => (Au(0), Au(0)),
+        DistributeSpaceAround if free_space > Au(0) && count > 0 => {
+            (free_space / (count * 2), free_space / count)
+        }
+        DistributeSpaceAround => (free_space / 2, Au(0)),
+    }
+}
+
+/// Resolves the main sizes of the items on a flex line. CSS Flexbox § 9.7.
+fn resolve_flexible_lengths(items: &mut [FlexItem], available_size: Au) {
+    let hypothetical_size = items.iter().fold(Au(0), |size, item| {
+        size + item.clamp(item.base_size)
+    });
+    let growing = hypothetical_size < available_size;
+
+    // Size inflexible items.
+    for item in items.iter_mut() {
+        let item_hypothetical_size = item.clamp(item.base_size);
+        let flex_factor = if growing {
+            item.flex_grow
+        } else {
+            item.flex_shrink
+        };
+        item.frozen = flex_factor == 0.0 ||
+            (growing && item.base_size > item_hypothetical_size) ||
+            (!growing && item.base_size < item_hypothetical_size);
+        item.main_size = item_hypothetical_size;
+    }
+
+    let initial_free_space = available_size - used_main_size(items);
+    while !items.iter().all(|item| item.frozen) {
+        let mut flex_factor_sum = 0.0;
+        let mut scaled_flex_shrink_factor_sum = 0.0;
+        for item in items.iter().filter(|item| !item.frozen) {
+            if growing {
+                flex_factor_sum += item.flex_grow
+            } else {
+                flex_factor_sum += item.flex_shrink;
+                scaled_flex_shrink_factor_sum += item.flex_shrink * item.base_size.to_subpx()
+            }
+        }
+
+        // If the flex factors sum to less than one, only that fraction of the free space is
+        // distributed.
+        let mut free_space = available_size - used_main_size(items);
+        if flex_factor_sum < 1.0 {
+            let scaled_initial_free_space = initial_free_space.scale_by(flex_factor_sum);
+            if abs(scaled_initial_free_space) < abs(free_space) {
+                free_space = scaled_initial_free_space
+            }
+        }
+
+        // Distribute the free space in proportion to the flex factors.
+        for item in items.iter_mut().filter(|item| !item.frozen) {
+            let ratio = if growing {
+                item.flex_grow / flex_factor_sum
+            } else if scaled_flex_shrink_factor_sum > 0.0 {
+                item.flex_shrink * item.base_size.to_subpx() / scaled_flex_shrink_factor_sum
+            } else {
+                0.0
+            };
+            item.main_size = item.base_size + free_space.scale_by(ratio)
+        }
+
+        // Fix min/max violations, freezing the items that violated in the same direction as
+        // the total violation.
+        let total_violation = items.iter().filter(|item| !item.frozen).fold(Au(0), |sum, item| {
+            sum + item.clamp(item.main_size) - item.main_size
+        });
+        for item in items.iter_mut().filter(|item| !item.frozen) {
+            let clamped_size = item.clamp(item.main_size);
+            let violation = clamped_size - item.main_size;
+            item.frozen = total_violation == Au(0) ||
+                (total_violation > Au(0) && violation > Au(0)) ||
+                (total_violation < Au(0) && violation < Au(0));
+            item.main_size = clamped_size
+        }
+    }
+}
+
+/// Returns the space taken up by the given items along the main axis, counting the base sizes of
+/// items that are not yet frozen.
+fn used_main_size(items: &[FlexItem]) -> Au {
+    items.iter().fold(Au(0), |size, item| {
+        size + if item.frozen {
+            item.main_size
+        } else {
+            item.base_size
+        }
+    })
+}
+
+fn abs(size: Au) -> Au {
+    max(size, -size)
+}
+
+/// Returns the used value of `align-self` for an item. CSS Flexbox § 8.3.
+fn used_alignment(align_self: align_self::T, align_items: align_items::T) -> align_items::T {
+    match align_self {
+        align_self::auto => align_items,
+        align_self::stretch => align_items::stretch,
+        align_self::flex_start => align_items::flex_start,
+        align_self::flex_end => align_items::flex_end,
+        align_self::center => align_items::center,
+        align_self::baseline => align_items::baseline,
+    }
+}
+
+/// Returns the style of the given item, or `None` if the item is an anonymous inline flow.
+fn item_style(kid: &mut Flow) -> Option<Arc<ComputedValues>> {
+    if kid.is_inline_flow() {
+        None
+    } else {
+        Some(kid.as_block().fragment.style.clone())
+    }
+}
+
+/// Returns the block-start and block-end margins of the given item.
+fn block_direction_margins(kid: &mut Flow) -> (Au, Au) {
+    if kid.is_inline_flow() {
+        (Au(0), Au(0))
+    } else {
+        let margin = kid.as_block().fragment.margin;
+        (margin.block_start, margin.block_end)
+    }
+}
+
+/// Returns the sums of the block-direction margins and of the block-direction borders and padding
+/// of the given item.
+fn block_direction_margins_and_border_padding(kid: &mut Flow) -> (Au, Au) {
+    if kid.is_inline_flow() {
+        (Au(0), Au(0))
+    } else {
+        let fragment = &kid.as_block().fragment;
+        (fragment.margin.block_start_end(), fragment.border_padding.block_start_end())
+    }
+}
+
+/// Sets the border-box block-size of the given item.
+fn set_item_block_size(kid: &mut Flow, block_size: Au) {
+    if !kid.is_inline_flow() {
+        kid.as_block().fragment.border_box.size.block = block_size
+    }
+    flow::mut_base(kid).position.size.block = block_size
+}
+
+/// Resolves a block-size from style. Percentages are treated as `auto` unless the containing
+/// block has a definite block-size, per CSS 2.1 § 10.5.
+fn block_size_from_style(length: LengthOrPercentageOrAuto, container_block_size: Option<Au>)
+                         -> MaybeAuto {
+    match (length, container_block_size) {
+        (LPA_Percentage(_), None) => Auto,
+        (length, container_block_size) => {
+            MaybeAuto::from_style(length, container_block_size.unwrap_or(Au(0)))
+        }
+    }
+}
+
+/// Resolves `max-block-size` from style. Percentages are treated as `none` unless the containing
+/// block has a definite block-size.
+fn max_block_size_from_style(style: &ComputedValues, container_block_size: Option<Au>)
+                             -> Option<Au> {
+    match (style.max_block_size(), container_block_size) {
+        (LPN_Percentage(_), None) => None,
+        (max_block_size, container_block_size) => {
+            specified_or_none(max_block_size, container_block_size.unwrap_or(Au(0)))
+        }
+    }
+}
@@ -24,12 +24,19 @@
 ///   fragments/flows that are subject to inline layout and line breaking and structs to represent
 ///   line breaks and mapping to CSS boxes, for the purpose of handling `getClientRects()` and
 ///   similar methods.
+///
+/// * `FlexFlow`: A flow that establishes a flex formatting context. It wraps a `BlockFlow` and
+///   lays its child flows out along a main axis according to the CSS Flexbox rules.
+///
+/// * `MulticolFlow`: A flow that establishes a multi-column formatting context. It wraps a
+///   `BlockFlow` and breaks the content of its child flows up into columns.
 
 use css::node_style::StyledNode;
 use block::BlockFlow;
 use context::LayoutContext;
 use display_list_builder::{DisplayListBuildingResult, DisplayListResult};
 use display_list_builder::{NoDisplayListBuildingResult, StackingContextResult};
+use flex::FlexFlow;
 use floats::Floats;
 use flow_list::{FlowList, FlowListIterator, MutFlowListIterator};
 use flow_ref::FlowRef;
@@ -37,6 +44,7 @@ use fragment::{Fragment, FragmentBoundsIterator, TableRowFragment, TableCellFrag
 use incremental::{RECONSTRUCT_FLOW, REFLOW, REFLOW_OUT_OF_FLOW, RestyleDamage};
 use inline::InlineFlow;
 use model::{CollapsibleMargins, IntrinsicISizes, MarginCollapseInfo};
+use multicol::MulticolFlow;
 use parallel::FlowParallelInfo;
 use table::{ColumnInlineSize, TableFlow};
 use table_caption::TableCaptionFlow;
@@ -52,7 +60,7 @@ use serialize::{Encoder, Encodable};
 use servo_msg::compositor_msg::LayerId;
 use servo_util::geometry::Au;
 use servo_util::logical_geometry::WritingMode;
-use servo_util::logical_geometry::{LogicalRect, LogicalSize};
+use servo_util::logical_geometry::{LogicalPoint, LogicalRect, LogicalSize};
 use std::mem;
 use std::num::Zero;
 use std::fmt;
@@ -88,6 +96,16 @@ pub trait Flow: fmt::Show + ToString + Sync {
         panic!("called as_block() on a non-block flow")
     }
 
+    /// If this is a flex flow, returns the underlying object. Fails otherwise.
+    fn as_flex<'a>(&'a mut self) -> &'a mut FlexFlow {
+        panic!("called as_flex() on a non-flex flow")
+    }
+
+    /// If this is a multi-column flow, returns the underlying object. Fails otherwise.
+    fn as_multicol<'a>(&'a mut self) -> &'a mut MulticolFlow {
+        panic!("called as_multicol() on a non-multicol flow")
+    }
+
     /// If this is an inline flow, returns the underlying object, borrowed immutably. Fails
     /// otherwise.
     fn as_immutable_inline<'a>(&'a self) -> &'a InlineFlow {
@@ -259,7 +277,9 @@ pub trait Flow: fmt::Show + ToString + Sync {
     }
 
     fn is_relatively_positioned(&self) -> bool {
-        self.positioning() == position::relative
+        // Sticky positioning is laid out as relative positioning with no offset; the compositor
+        // applies the sticky offset.
+        self.positioning() == position::relative || self.positioning() == position::sticky
     }
 
     /// Return true if this is the root of an absolute flow tree.
@@ -428,6 +448,8 @@ pub enum FlowClass {
     TableRowFlowClass,
     TableCaptionFlowClass,
     TableCellFlowClass,
+    FlexFlowClass,
+    MulticolFlowClass,
 }
 
 /// A top-down traversal.
@@ -501,6 +523,9 @@ bitflags! {
 
                  NB: If you update this, update `TEXT_ALIGN_SHIFT` below."]
         const TEXT_ALIGN = 0b0111_1000_0000_0000,
+        #[doc = "Whether this flow is an item of a flex container. Flex items have their inline"]
+        #[doc = "sizes and positions determined by the containing `FlexFlow`."]
+        const IS_FLEX_ITEM = 0b1000_0000_0000_0000,
     }
 }
 
@@ -673,6 +698,10 @@ pub struct AbsolutePositionInfo {
     /// and this flow is not itself absolutely-positioned, then this is (0, 0).
     pub stacking_relative_position_of_absolute_containing_block: Point2D<Au>,
 
+    /// The content box of the parent flow relative to this flow's origin. A sticky-positioned
+    /// flow is never moved out of this rectangle.
+    pub sticky_containing_block_rect: LogicalRect<Au>,
+
     /// Whether the absolute containing block forces positioned descendants to be layerized.
     ///
     /// FIXME(pcwalton): Move into `FlowFlags`.
@@ -686,6 +715,7 @@ impl AbsolutePositionInfo {
         AbsolutePositionInfo {
             relative_containing_block_size: LogicalSize::zero(writing_mode),
             stacking_relative_position_of_absolute_containing_block: Zero::zero(),
+            sticky_containing_block_rect: LogicalRect::zero(writing_mode),
             layers_needed_for_positioned_flows: false,
         }
     }
@@ -775,6 +805,11 @@ pub struct BaseFlow {
     /// The writing mode for this flow.
     pub writing_mode: WritingMode,
 
+    /// The writing mode of the block container of this flow. `position.start` and `overflow` are
+    /// expressed in this writing mode. If it is not parallel to `writing_mode`, this flow is an
+    /// orthogonal flow per CSS Writing Modes § 7.3.
+    pub block_container_writing_mode: WritingMode,
+
     /// Various flags for flows, tightly packed to save space.
     pub flags: FlowFlags,
 }
@@ -913,9 +948,45 @@ impl BaseFlow {
             clip_rect: Rect(Zero::zero(), Size2D(Au(0), Au(0))),
             flags: flags,
             writing_mode: writing_mode,
+            block_container_writing_mode: writing_mode,
         }
     }
 
+    /// Returns true if the inline axis of this flow is perpendicular to that of its block
+    /// container. See CSS Writing Modes § 7.3.
+    #[inline]
+    pub fn is_orthogonal_flow(&self) -> bool {
+        self.writing_mode.is_vertical() != self.block_container_writing_mode.is_vertical()
+    }
+
+    /// Returns the size of this flow (see `position`) in the writing mode of its block container.
+    #[inline]
+    pub fn size_in_block_container_writing_mode(&self) -> LogicalSize<Au> {
+        self.position.size.convert(self.writing_mode, self.block_container_writing_mode)
+    }
+
+    /// Converts a rectangle relative to the start corner of this flow, in this flow's writing
+    /// mode, to one relative to the start corner of the block container, in its writing mode.
+    pub fn rect_to_block_container_writing_mode(&self, rect: &LogicalRect<Au>)
+                                                -> LogicalRect<Au> {
+        let (mode, container_mode) = (self.writing_mode, self.block_container_writing_mode);
+        let start = LogicalPoint::new(container_mode, self.position.start.i,
+                                      self.position.start.b);
+        if mode == container_mode {
+            return rect.translate(&start)
+        }
+
+        // FIXME(#2795): Get the real container size.
+        let border_box = LogicalRect::from_point_size(container_mode,
+                                                      start,
+                                                      self.size_in_block_container_writing_mode());
+        let origin = border_box.inner_start_corner_to_physical(container_mode, mode);
+        let physical_rect = rect.to_physical(mode, Size2D::zero());
+        LogicalRect::from_physical(container_mode,
+                                   Rect(physical_rect.origin + origin, physical_rect.size),
+                                   Size2D::zero())
+    }
+
     pub fn child_iter<'a>(&'a mut self) -> MutFlowListIterator<'a> {
         self.children.iter_mut()
     }
@@ -1176,8 +1247,12 @@ impl<'a> MutableFlowUtils for &'a mut Flow + 'a {
     /// already been set.
     /// Assumption: Absolute descendants have had their overflow calculated.
     fn store_overflow(self, _: &LayoutContext) {
-        let my_position = mut_base(self).position;
-        let mut overflow = my_position;
+        // Compute the overflow relative to our own start corner, in our own writing mode, and
+        // then move it into the frame of our block container.
+        let writing_mode = base(self).writing_mode;
+        let mut overflow = LogicalRect::from_point_size(writing_mode,
+                                                        LogicalPoint::zero(writing_mode),
+                                                        base(self).position.size);
 
         if self.is_block_container() {
             for kid in child_iter(self) {
@@ -1186,18 +1261,15 @@ impl<'a> MutableFlowUtils for &'a mut Flow + 'a {
                     // their CB, they will show up in `abs_descendants`.
                     continue;
                 }
-                let mut kid_overflow = base(kid).overflow;
-                kid_overflow = kid_overflow.translate(&my_position.start);
-                overflow = overflow.union(&kid_overflow)
+                overflow = overflow.union(&base(kid).overflow)
             }
 
             // FIXME(#2004, pcwalton): This is wrong for `position: fixed`.
             for descendant_link in mut_base(self).abs_descendants.iter() {
-                let mut kid_overflow = base(descendant_link).overflow;
-                kid_overflow = kid_overflow.translate(&my_position.start);
-                overflow = overflow.union(&kid_overflow)
+                overflow = overflow.union(&base(descendant_link).overflow)
             }
         }
+        let overflow = base(self).rect_to_block_container_writing_mode(&overflow);
         mut_base(self).overflow = overflow;
     }
 
@@ -24,6 +24,7 @@ use wrapper::{TLayoutNode, ThreadSafeLayoutNode};
 
 use geom::{Point2D, Rect, Size2D};
 use gfx::display_list::OpaqueNode;
+use gfx::text::bidi::ExplicitEmbedding;
 use gfx::text::glyph::CharIndex;
 use gfx::text::text_run::TextRun;
 use script_traits::UntrustedNodeAddress;
@@ -32,7 +33,6 @@ use servo_msg::constellation_msg::{PipelineId, SubpageId};
 use servo_net::image::holder::ImageHolder;
 use servo_net::local_image_cache::LocalImageCache;
 use servo_util::geometry::Au;
-use servo_util::geometry;
 use servo_util::logical_geometry::{LogicalRect, LogicalSize, LogicalMargin};
 use servo_util::range::*;
 use servo_util::smallvec::SmallVec;
@@ -44,8 +44,9 @@ use string_cache::Atom;
 use style::{ComputedValues, TElement, TNode, cascade_anonymous};
 use style::computed_values::{LengthOrPercentage, LengthOrPercentageOrAuto};
 use style::computed_values::{LengthOrPercentageOrNone};
-use style::computed_values::{LPA_Auto, clear, position, text_align, text_decoration};
-use style::computed_values::{vertical_align, white_space};
+use style::computed_values::{LPA_Auto, border_collapse, clear, overflow, overflow_wrap};
+use style::computed_values::{position, text_align, text_decoration, unicode_bidi, vertical_align};
+use style::computed_values::{white_space};
 use sync::{Arc, Mutex};
 use url::Url;
 
@@ -107,6 +108,9 @@ pub struct Fragment {
 
     /// How damaged this fragment is since last reflow.
     pub restyle_damage: RestyleDamage,
+
+    /// True if this is the fragment of the root flow, whose overflow the viewport scrolls.
+    pub is_root: bool,
 }
 
 impl<E, S: Encoder<E>> Encodable<S, E> for Fragment {
@@ -318,13 +322,13 @@ impl ImageFragmentInfo {
         })
     }
 
-    /// Tile an image
+    /// Tile an image whose tiles are `tile_size` long.
     pub fn tile_image(position: &mut Au, size: &mut Au,
-                        virtual_position: Au, image_size: u32) {
-        let image_size = image_size as int;
-        let delta_pixels = geometry::to_px(virtual_position - *position);
-        let tile_count = (delta_pixels + image_size - 1) / image_size;
-        let offset = Au::from_px(image_size * tile_count);
+                        virtual_position: Au, tile_size: Au) {
+        let Au(delta) = virtual_position - *position;
+        let Au(tile_length) = tile_size;
+        let tile_count = (delta + tile_length - 1) / tile_length;
+        let offset = tile_size * tile_count;
         let new_position = virtual_position - offset;
         *size = *position - new_position + *size;
         *position = new_position;
@@ -487,6 +491,7 @@ impl Fragment {
             specific: constructor.build_specific_fragment_info_for_node(node),
             inline_context: None,
             debug_id: layout_debug::generate_unique_debug_id(),
+            is_root: false,
         }
     }
 
@@ -505,6 +510,7 @@ impl Fragment {
             specific: specific,
             inline_context: None,
             debug_id: layout_debug::generate_unique_debug_id(),
+            is_root: false,
         }
     }
 
@@ -534,6 +540,7 @@ impl Fragment {
             specific: specific,
             inline_context: None,
             debug_id: layout_debug::generate_unique_debug_id(),
+            is_root: false,
         }
     }
 
@@ -554,6 +561,7 @@ impl Fragment {
             specific: specific,
             inline_context: None,
             debug_id: layout_debug::generate_unique_debug_id(),
+            is_root: false,
         }
     }
 
@@ -588,6 +596,30 @@ impl Fragment {
         }
     }
 
+    /// Removes any extra word spacing that `text-align: justify` added to the text run of this
+    /// fragment during a previous layout, restoring the fragment's natural inline-size.
+    pub fn reset_text_justification(&mut self) {
+        match self.specific {
+            ScannedTextFragment(ref mut info) => {
+                if info.run.extra_word_spacing == Au(0) &&
+                        info.run.extra_word_spacing_remainder_end == CharIndex(0) {
+                    return
+                }
+                let justified_inline_size = info.run.advance_for_range(&info.range);
+                {
+                    let run = info.run.make_unique();
+                    run.extra_word_spacing = Au(0);
+                    run.extra_word_spacing_remainder_end = CharIndex(0);
+                }
+                let natural_inline_size = info.run.advance_for_range(&info.range);
+                self.border_box.size.inline = self.border_box.size.inline -
+                    (justified_inline_size - natural_inline_size);
+                info.content_size.inline = self.border_box.size.inline;
+            }
+            _ => {}
+        }
+    }
+
     /// Returns a debug ID of this fragment. This ID should not be considered stable across
     /// multiple layouts or fragment manipulations.
     pub fn debug_id(&self) -> u16 {
@@ -614,6 +646,7 @@ impl Fragment {
             specific: ScannedTextFragment(info),
             inline_context: self.inline_context.clone(),
             debug_id: self.debug_id,
+            is_root: self.is_root,
         }
     }
 
@@ -638,6 +671,18 @@ impl Fragment {
             GenericFragment | IframeFragment(_) | ImageFragment(_) | InlineBlockFragment(_) => {
                 QuantitiesIncludedInIntrinsicInlineSizes::all()
             }
+            // In the collapsing border model, the cells contain the borders of the table and its
+            // rows, and the table has no padding.
+            TableFragment | TableRowFragment
+                    if self.style.get_inheritedtable().border_collapse ==
+                        border_collapse::collapse => {
+                INTRINSIC_INLINE_SIZE_INCLUDES_SPECIFIED
+            }
+            TableWrapperFragment
+                    if self.style.get_inheritedtable().border_collapse ==
+                        border_collapse::collapse => {
+                INTRINSIC_INLINE_SIZE_INCLUDES_MARGINS | INTRINSIC_INLINE_SIZE_INCLUDES_SPECIFIED
+            }
             TableFragment | TableCellFragment => {
                 INTRINSIC_INLINE_SIZE_INCLUDES_PADDING |
                     INTRINSIC_INLINE_SIZE_INCLUDES_BORDER |
@@ -797,6 +842,18 @@ impl Fragment {
     /// block inline-size and the style. After this call, the `border_padding` field will be
     /// correct.
     pub fn compute_border_and_padding(&mut self, containing_block_inline_size: Au) {
+        // In the collapsing border model, the cells paint the borders of the table and its rows
+        // and row groups, and the table has no padding. See CSS 2.1 § 17.6.2.
+        match self.specific {
+            TableFragment | TableRowFragment | TableWrapperFragment
+                    if self.style.get_inheritedtable().border_collapse ==
+                        border_collapse::collapse => {
+                self.border_padding = LogicalMargin::zero(self.style.writing_mode);
+                return
+            }
+            _ => {}
+        }
+
         // Compute border.
         let border = self.border_width();
 
@@ -844,6 +901,9 @@ impl Fragment {
         }
 
         // Go over the ancestor fragments and add all relative offsets (if any).
+        //
+        // FIXME: Inline `position: sticky` elements are never moved, since only blocks get the
+        // layers that the compositor moves.
         let mut rel_pos = if self.style().get_box().position == position::relative {
             from_style(self.style(), containing_block_size)
         } else {
@@ -907,6 +967,38 @@ impl Fragment {
         self.style().get_text().text_decoration
     }
 
+    /// Returns the explicit bidi embedding of this fragment within a paragraph of the given
+    /// level, as established by the `unicode-bidi` and `direction` properties of the inline
+    /// elements enclosing it. See CSS 2.1 § 9.10.
+    pub fn explicit_bidi_embedding(&self, paragraph_level: u8) -> ExplicitEmbedding {
+        let mut embedding = ExplicitEmbedding::new(paragraph_level);
+        let inline_context = match self.inline_context {
+            None => return embedding,
+            Some(ref inline_context) => inline_context,
+        };
+
+        // The styles of the inline context are ordered from the innermost element outward.
+        for style in inline_context.styles.iter().rev() {
+            let rtl = !style.writing_mode.is_bidi_ltr();
+            embedding = match style.get_text().unicode_bidi {
+                unicode_bidi::normal => continue,
+                unicode_bidi::embed => embedding.push(rtl, false),
+                unicode_bidi::bidi_override => embedding.push(rtl, true),
+            }
+        }
+        embedding
+    }
+
+    /// Returns the resolved bidi embedding level of this fragment within a paragraph of the given
+    /// level. Text fragments were given their levels by the Unicode Bidirectional Algorithm when
+    /// their runs were built; other fragments take their explicit embedding level.
+    pub fn bidi_level(&self, paragraph_level: u8) -> u8 {
+        match self.specific {
+            ScannedTextFragment(ref info) => info.run.bidi_level,
+            _ => self.explicit_bidi_embedding(paragraph_level).level,
+        }
+    }
+
     /// Returns the inline-start offset from margin edge to content edge.
     ///
     /// FIXME(#2262, pcwalton): I think this method is pretty bogus, because it won't work for
@@ -1140,14 +1232,17 @@ impl Fragment {
                            slice_range,
                            remaining_inline_size);
 
-                    let metrics = text_fragment_info.run.metrics_for_slice(glyphs, &slice_range);
+                    let metrics = text_fragment_info.run.metrics_for_slice(glyphs,
+                                                                           offset,
+                                                                           &slice_range);
                     let advance = metrics.advance_width;
 
                     let should_continue;
                     if advance <= remaining_inline_size || glyphs.is_whitespace() {
                         should_continue = true;
 
-                        if starts_line && pieces_processed_count == 0 && glyphs.is_whitespace() {
+                        if starts_line && pieces_processed_count == 0 && glyphs.is_whitespace() &&
+                                self.white_space() != white_space::pre_wrap {
                             debug!("split_to_inline_size: case=skipping leading trimmable whitespace");
                             inline_start_range.shift_by(slice_range.length());
                         } else {
@@ -1158,7 +1253,24 @@ impl Fragment {
                     } else {
                         // The advance is more than the remaining inline-size.
                         should_continue = false;
-                        let slice_begin = offset + slice_range.begin();
+                        let mut slice_begin = offset + slice_range.begin();
+
+                        // CSS Text Level 3 § 6.2: If nothing precedes this slice on the line,
+                        // there is no other break opportunity, so `overflow-wrap: break-word`
+                        // allows breaking the word at an arbitrary point.
+                        if starts_line && inline_start_range.length() == CharIndex(0) &&
+                                self.style().get_inheritedtext().overflow_wrap ==
+                                    overflow_wrap::break_word {
+                            let fitting_length = text_fragment_info.run.fitting_length_for_slice(
+                                glyphs,
+                                offset,
+                                &slice_range,
+                                remaining_inline_size);
+                            debug!("split_to_inline_size: case=emergency break after {} chars",
+                                   fitting_length);
+                            inline_start_range.extend_by(fitting_length);
+                            slice_begin = slice_begin + fitting_length;
+                        }
 
                         if slice_begin < text_fragment_info.range.end() {
                             // There are still some things inline-start over at the end of the line. Create
@@ -1201,7 +1313,7 @@ impl Fragment {
     /// whitespace that should be stripped.
     pub fn is_ignorable_whitespace(&self) -> bool {
         match self.white_space() {
-            white_space::pre => return false,
+            white_space::pre | white_space::pre_wrap | white_space::pre_line => return false,
             white_space::normal | white_space::nowrap => {}
         }
         match self.specific {
@@ -1430,9 +1542,15 @@ impl Fragment {
         match (&self.specific, &other.specific) {
             (&UnscannedTextFragment(_), &UnscannedTextFragment(_)) => {
                 // FIXME: Should probably use a whitelist of styles that can safely differ (#3165)
+                let (inherited_text, other_inherited_text) =
+                    (self.style().get_inheritedtext(), other.style().get_inheritedtext());
                 self.style().get_font() == other.style().get_font() &&
                     self.text_decoration() == other.text_decoration() &&
-                    self.white_space() == other.white_space()
+                    self.white_space() == other.white_space() &&
+                    inherited_text.text_transform == other_inherited_text.text_transform &&
+                    inherited_text.letter_spacing == other_inherited_text.letter_spacing &&
+                    inherited_text.word_spacing == other_inherited_text.word_spacing &&
+                    inherited_text.word_break == other_inherited_text.word_break
             }
             _ => false,
         }
@@ -1492,9 +1610,26 @@ impl Fragment {
             .translate(stacking_relative_flow_origin)
     }
 
+    /// Returns true if the contents of this fragment can be scrolled independently of the rest of
+    /// the page, in which case they are painted into a layer of their own. The root never is,
+    /// since its overflow is what the viewport scrolls.
+    pub fn is_scroll_container(&self) -> bool {
+        if self.is_root {
+            return false
+        }
+        match self.specific {
+            GenericFragment => {}
+            _ => return false,
+        }
+        match self.style().get_box().overflow {
+            overflow::scroll | overflow::auto => true,
+            overflow::visible | overflow::hidden => false,
+        }
+    }
+
     /// Returns true if this fragment establishes a new stacking context and false otherwise.
     pub fn establishes_stacking_context(&self) -> bool {
-        if self.style().get_effects().opacity != 1.0 {
+        if self.style().get_effects().opacity != 1.0 || self.is_scroll_container() {
             return true
         }
         match self.style().get_box().position {
@@ -1503,6 +1638,11 @@ impl Fragment {
                 // `z-index` is not `auto`. But this matches what we did before.
                 true
             }
+            position::sticky => {
+                // Sticky-positioned elements are painted into their own layer, which the
+                // compositor moves as the page scrolls.
+                true
+            }
             position::relative | position::static_ => {
                 // FIXME(pcwalton): `position: relative` establishes a new stacking context if
                 // `z-index` is not `auto`. But this matches what we did before.
@@ -0,0 +1,566 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Breaking laid-out content across a series of fragmentainers (columns or pages).
+//!
+//! Content is first laid out as one continuous strip, as if the fragmentainer were infinitely
+//! tall. The passes here then walk the laid-out flow tree: `insert_breaks` pushes every line box
+//! and every monolithic block that would straddle a fragmentainer boundary down to the start of
+//! the next fragmentainer, growing its ancestors to match, and `distribute` moves the content of
+//! each fragmentainer from its place in the strip to the place the caller gives it.
+//!
+//! When the fragmentainers are pages, the first page is a right page and pages alternate sides
+//! from there, as with a left-to-right page progression.
+//!
+//! "CSS Fragmentation" refers to the W3C "CSS Fragmentation Module Level 3" specification
+//! available here:
+//!
+//!   http://dev.w3.org/csswg/css-break/
+
+#![deny(unsafe_blocks)]
+
+use block::BlockFlow;
+use flow::{BlockFlowClass, Flow, InlineFlowClass, IS_ABSOLUTELY_POSITIONED};
+use flow::TableColGroupFlowClass;
+use flow;
+use inline::InlineFlow;
+
+use servo_util::geometry::Au;
+use servo_util::logical_geometry::{LogicalPoint, LogicalSize, WritingMode};
+use std::cmp::{max, min};
+use style::computed_values::{page_break_after, page_break_before, page_break_inside};
+
+#[deriving(PartialEq)]
+enum BreakInsertionMode {
+    /// Only compute how much the content would grow; leave the flow tree untouched.
+    MeasureBreaks,
+    /// Move content past the breaks and grow its ancestors.
+    InsertBreaks,
+}
+
+/// A break that the `page-break-before` or `page-break-after` property forces.
+#[deriving(PartialEq)]
+enum ForcedBreak {
+    NoForcedBreak,
+    /// `always`: a break to the next page.
+    PageBreak,
+    /// `left`: one or two breaks, so that the next page is a left page.
+    LeftPageBreak,
+    /// `right`: one or two breaks, so that the next page is a right page.
+    RightPageBreak,
+}
+
+/// What decides where a child of a fragmented block may be broken around. Only the
+/// `page-break-*` properties are honored, so outside of pages nothing is forced or avoided.
+struct ChildBreakInfo {
+    /// Whether the child is neither floated nor absolutely positioned.
+    is_in_flow: bool,
+    forced_break_before: ForcedBreak,
+    forced_break_after: ForcedBreak,
+    avoid_break_before: bool,
+    avoid_break_after: bool,
+    avoid_break_inside: bool,
+    /// The offset of the child from the start of its parent, before any break is inserted.
+    block_start: Au,
+    /// The block size of the start of the child that no break can separate from whatever
+    /// precedes it: its first lines, as many as `orphans` requires, or all of it if it is
+    /// monolithic.
+    leading_block_size: Au,
+}
+
+impl ChildBreakInfo {
+    fn new(flow: &mut Flow, is_paginated: bool, orphans: uint) -> ChildBreakInfo {
+        let is_in_flow = !flow::base(flow).flags.contains(IS_ABSOLUTELY_POSITIONED) &&
+            !flow::base(flow).flags.is_float();
+        let (before, after, inside) = match page_break_properties(flow) {
+            Some(properties) if is_paginated => properties,
+            _ => (page_break_before::auto, page_break_after::auto, page_break_inside::auto),
+        };
+        let forced_break_before = match before {
+            page_break_before::always => PageBreak,
+            page_break_before::left => LeftPageBreak,
+            page_break_before::right => RightPageBreak,
+            page_break_before::auto | page_break_before::avoid => NoForcedBreak,
+        };
+        let forced_break_after = match after {
+            page_break_after::always => PageBreak,
+            page_break_after::left => LeftPageBreak,
+            page_break_after::right => RightPageBreak,
+            page_break_after::auto | page_break_after::avoid => NoForcedBreak,
+        };
+        let avoid_break_inside = inside == page_break_inside::avoid;
+        let leading_block_size = if avoid_break_inside {
+            flow::base(flow).position.size.block
+        } else {
+            leading_block_size(flow, orphans)
+        };
+        ChildBreakInfo {
+            is_in_flow: is_in_flow,
+            forced_break_before: forced_break_before,
+            forced_break_after: forced_break_after,
+            avoid_break_before: before == page_break_before::avoid,
+            avoid_break_after: after == page_break_after::avoid,
+            avoid_break_inside: avoid_break_inside,
+            block_start: flow::base(flow).position.start.b,
+            leading_block_size: leading_block_size,
+        }
+    }
+}
+
+/// A series of fragmentainers of equal block size. CSS Fragmentation § 1.
+pub struct FragmentationContext {
+    /// The block size of each fragmentainer.
+    pub fragmentainer_block_size: Au,
+    /// Whether the fragmentainers are pages, in which case the `page-break-*`, `orphans` and
+    /// `widows` properties apply.
+    pub is_paginated: bool,
+}
+
+impl FragmentationContext {
+    pub fn new(fragmentainer_block_size: Au) -> FragmentationContext {
+        FragmentationContext {
+            fragmentainer_block_size: fragmentainer_block_size,
+            is_paginated: false,
+        }
+    }
+
+    pub fn new_for_pages(page_block_size: Au) -> FragmentationContext {
+        FragmentationContext {
+            fragmentainer_block_size: page_block_size,
+            is_paginated: true,
+        }
+    }
+
+    /// Returns the index of the fragmentainer that contains the given offset from the start of
+    /// the fragmented flow.
+    pub fn fragmentainer_index(&self, block_offset: Au) -> uint {
+        let (Au(block_offset), Au(fragmentainer_block_size)) =
+            (block_offset, self.fragmentainer_block_size);
+        if block_offset <= 0 || fragmentainer_block_size <= 0 {
+            return 0
+        }
+        (block_offset / fragmentainer_block_size) as uint
+    }
+
+    /// Returns how far content occupying the given range must be pushed so that it does not
+    /// straddle a fragmentainer boundary. Content that does not fit in a fragmentainer at all is
+    /// only pushed if that lets it start at the top of one.
+    fn break_offset(&self, block_start: Au, block_size: Au) -> Au {
+        if self.fragmentainer_block_size <= Au(0) {
+            return Au(0)
+        }
+        let index = self.fragmentainer_index(block_start);
+        let fragmentainer_start = self.fragmentainer_block_size * (index as i32);
+        let fragmentainer_end = fragmentainer_start + self.fragmentainer_block_size;
+        if block_start + block_size <= fragmentainer_end || block_start == fragmentainer_start {
+            Au(0)
+        } else {
+            fragmentainer_end - block_start
+        }
+    }
+
+    /// Returns how far content that starts at the given offset must be pushed to start the next
+    /// fragmentainer, or zero if it already starts one. A break to a page of a given side leaves
+    /// a blank page if the next page is on the other side. CSS Fragmentation § 3.1, CSS Paged
+    /// Media § 4.2.
+    fn forced_break_offset(&self, block_start: Au, forced_break: ForcedBreak) -> Au {
+        if self.fragmentainer_block_size <= Au(0) || forced_break == NoForcedBreak {
+            return Au(0)
+        }
+        let index = self.fragmentainer_index(block_start);
+        let fragmentainer_start = self.fragmentainer_block_size * (index as i32);
+        let (push, index) = if block_start <= fragmentainer_start {
+            (Au(0), index)
+        } else {
+            (fragmentainer_start + self.fragmentainer_block_size - block_start, index + 1)
+        };
+        let is_right_page = index % 2 == 0;
+        match forced_break {
+            LeftPageBreak if is_right_page => push + self.fragmentainer_block_size,
+            RightPageBreak if !is_right_page => push + self.fragmentainer_block_size,
+            _ => push,
+        }
+    }
+
+    /// Returns how much the content of the given block would grow if breaks were inserted into
+    /// it, without changing anything.
+    pub fn measure_breaks(&self, block: &mut BlockFlow) -> Au {
+        let block_offset = -block.fragment.border_padding.block_start;
+        self.insert_breaks_in_children(block, block_offset, MeasureBreaks)
+    }
+
+    /// Pushes the in-flow content of the given block past the fragmentainer boundaries and
+    /// returns how much it grew. The fragmented flow starts at the content edge of the block,
+    /// whose own size is left for the caller to set.
+    pub fn insert_breaks(&self, block: &mut BlockFlow) -> Au {
+        let block_offset = -block.fragment.border_padding.block_start;
+        self.insert_breaks_in_children(block, block_offset, InsertBreaks)
+    }
+
+    /// Moves the content of the fragmentainer at index `k` of the given block, once breaks have
+    /// been inserted, from `k` fragmentainers down the strip to `fragmentainer_offsets[k]` from
+    /// the first fragmentainer. Line boxes and monolithic blocks move; blocks that span several
+    /// fragmentainers stay put and have their children moved instead.
+    ///
+    /// FIXME: The backgrounds and borders of blocks that span several fragmentainers are still
+    /// painted as one box down the strip.
+    pub fn distribute(&self, block: &mut BlockFlow, fragmentainer_offsets: &[LogicalSize<Au>]) {
+        let block_offset = -block.fragment.border_padding.block_start;
+        self.distribute_children(block, block_offset, fragmentainer_offsets)
+    }
+
+    fn insert_breaks_in_children(&self,
+                                 block: &mut BlockFlow,
+                                 block_offset: Au,
+                                 mode: BreakInsertionMode)
+                                 -> Au {
+        let (orphans, widows) = if self.is_paginated {
+            let inherited_text = block.fragment.style().get_inheritedtext();
+            (inherited_text.orphans as uint, inherited_text.widows as uint)
+        } else {
+            (1, 1)
+        };
+
+        let is_paginated = self.is_paginated;
+        let kids: Vec<ChildBreakInfo> = block.base.child_iter().map(|kid| {
+            ChildBreakInfo::new(kid, is_paginated, orphans)
+        }).collect();
+
+        let mut shift = Au(0);
+        let mut forced_break_after_previous_kid = NoForcedBreak;
+        for (kid_index, kid) in block.base.child_iter().enumerate() {
+            if flow::base(kid).flags.contains(IS_ABSOLUTELY_POSITIONED) {
+                continue
+            }
+            let info = &kids[kid_index];
+
+            // Floats are broken like any other block, but the content after them does not move
+            // along.
+            if flow::base(kid).flags.is_float() {
+                let float_block_start = block_offset + info.block_start + shift;
+                let push = if is_breakable(kid) && !info.avoid_break_inside {
+                    self.insert_breaks_in_flow(kid, float_block_start, mode, orphans, widows);
+                    Au(0)
+                } else {
+                    self.break_offset(float_block_start, flow::base(kid).position.size.block)
+                };
+                if mode == InsertBreaks {
+                    let kid_base = flow::mut_base(kid);
+                    kid_base.position.start.b = kid_base.position.start.b + shift + push;
+                }
+                continue
+            }
+
+            // A side given before the child wins over one given after the previous child.
+            let forced_break = match info.forced_break_before {
+                NoForcedBreak => forced_break_after_previous_kid,
+                PageBreak if forced_break_after_previous_kid != NoForcedBreak => {
+                    forced_break_after_previous_kid
+                }
+                forced_break => forced_break,
+            };
+            let mut kid_block_start = block_offset + info.block_start + shift;
+            let forced_push = self.forced_break_offset(kid_block_start, forced_break);
+            kid_block_start = kid_block_start + forced_push;
+            forced_break_after_previous_kid = info.forced_break_after;
+
+            // To avoid a break between this child and the next one, the two are kept together
+            // when the whole of this one and the start of the next fit in a fragmentainer.
+            let next_kid = kids.slice_from(kid_index + 1).iter().find(|next| next.is_in_flow);
+            let chained_block_size = match next_kid {
+                Some(next) if (info.avoid_break_after || next.avoid_break_before) &&
+                              info.forced_break_after == NoForcedBreak &&
+                              next.forced_break_before == NoForcedBreak => {
+                    let block_size = next.block_start - info.block_start +
+                        next.leading_block_size;
+                    if block_size <= self.fragmentainer_block_size {
+                        Some(block_size)
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+
+            let (push, growth) = match chained_block_size {
+                Some(block_size) => (self.break_offset(kid_block_start, block_size), Au(0)),
+                None if is_breakable(kid) && !info.avoid_break_inside => {
+                    let growth =
+                        self.insert_breaks_in_flow(kid, kid_block_start, mode, orphans, widows);
+                    (Au(0), growth)
+                }
+                None => {
+                    (self.break_offset(kid_block_start, flow::base(kid).position.size.block),
+                     Au(0))
+                }
+            };
+            let push = forced_push + push;
+
+            if mode == InsertBreaks {
+                let kid_base = flow::mut_base(kid);
+                kid_base.position.start.b = kid_base.position.start.b + shift + push;
+            }
+            shift = shift + push + growth
+        }
+        shift
+    }
+
+    fn insert_breaks_in_flow(&self,
+                             flow: &mut Flow,
+                             block_offset: Au,
+                             mode: BreakInsertionMode,
+                             orphans: uint,
+                             widows: uint)
+                             -> Au {
+        match flow.class() {
+            InlineFlowClass => {
+                let pushes = self.line_pushes(flow.as_inline(), block_offset, orphans, widows);
+                self.insert_breaks_in_lines(flow.as_inline(), pushes.as_slice(), mode)
+            }
+            BlockFlowClass => {
+                let block = flow.as_block();
+                let growth = self.insert_breaks_in_children(block, block_offset, mode);
+                if mode == InsertBreaks {
+                    block.base.position.size.block = block.base.position.size.block + growth;
+                    block.fragment.border_box.size.block =
+                        block.fragment.border_box.size.block + growth;
+                }
+                growth
+            }
+            _ => Au(0),
+        }
+    }
+
+    /// Returns how far each line of the given inline flow must be pushed, on top of the lines
+    /// before it, so that no line straddles a fragmentainer boundary. A break that would leave
+    /// fewer than `orphans` lines of the paragraph before it or fewer than `widows` lines after it
+    /// is moved earlier, as far as the start of the paragraph. CSS Fragmentation § 3.3.
+    fn line_pushes(&self, inline: &InlineFlow, block_offset: Au, orphans: uint, widows: uint)
+                   -> Vec<Au> {
+        let line_count = inline.lines.len();
+        let mut pushes = Vec::from_elem(line_count, Au(0));
+        let mut shift = Au(0);
+
+        // The first line of the paragraph in the current fragmentainer.
+        let mut first_line_index = 0;
+        let mut line_index = 0;
+        while line_index < line_count {
+            let line = &inline.lines[line_index];
+            let line_block_start = block_offset + line.bounds.start.b + shift;
+            let push = self.break_offset(line_block_start, line.bounds.size.block);
+
+            // A line that starts exactly at the start of a fragmentainer follows a break as well.
+            let fragmentainer_start = self.fragmentainer_block_size *
+                (self.fragmentainer_index(line_block_start) as i32);
+            let starts_fragmentainer = line_index > first_line_index &&
+                line_block_start > Au(0) && line_block_start == fragmentainer_start;
+            if push == Au(0) && !starts_fragmentainer {
+                line_index += 1;
+                continue
+            }
+
+            let mut break_index = line_index;
+            if line_count - break_index < widows && line_count >= widows &&
+                    line_count - widows > first_line_index {
+                break_index = line_count - widows
+            }
+            if first_line_index == 0 && break_index < orphans {
+                break_index = 0
+            }
+
+            // The lines between the two candidate breaks have not been pushed, so the shift so
+            // far applies to them as well.
+            let forced_push = if break_index < line_index {
+                let line = &inline.lines[break_index];
+                self.forced_break_offset(block_offset + line.bounds.start.b + shift, PageBreak)
+            } else {
+                Au(0)
+            };
+            if forced_push > Au(0) {
+                pushes[break_index] = forced_push;
+                shift = shift + forced_push;
+                first_line_index = break_index;
+                line_index = break_index + 1;
+            } else {
+                pushes[line_index] = push;
+                shift = shift + push;
+                first_line_index = line_index;
+                line_index += 1;
+            }
+        }
+        pushes
+    }
+
+    fn insert_breaks_in_lines(&self,
+                              inline: &mut InlineFlow,
+                              pushes: &[Au],
+                              mode: BreakInsertionMode)
+                              -> Au {
+        let mut shift = Au(0);
+        for (line, push) in inline.lines.iter_mut().zip(pushes.iter()) {
+            shift = shift + *push;
+            if mode == MeasureBreaks || shift == Au(0) {
+                continue
+            }
+
+            line.bounds.start.b = line.bounds.start.b + shift;
+            for fragment_index in range(line.range.begin(), line.range.end()) {
+                let fragment = &mut inline.fragments.fragments[fragment_index.to_uint()];
+                fragment.border_box.start.b = fragment.border_box.start.b + shift;
+            }
+        }
+
+        if mode == InsertBreaks {
+            inline.base.position.size.block = inline.base.position.size.block + shift
+        }
+        shift
+    }
+
+    /// Returns the offset by which content that starts at the given offset in the strip moves.
+    /// Fragmentainers past the end of `fragmentainer_offsets` keep their place in the strip.
+    fn distribution_offset(&self,
+                           writing_mode: WritingMode,
+                           block_offset: Au,
+                           fragmentainer_offsets: &[LogicalSize<Au>])
+                           -> LogicalSize<Au> {
+        let index = self.fragmentainer_index(block_offset);
+        if index >= fragmentainer_offsets.len() {
+            return LogicalSize::zero(writing_mode)
+        }
+        let strip_offset = self.fragmentainer_block_size * (index as i32);
+        LogicalSize::new(writing_mode,
+                         fragmentainer_offsets[index].inline,
+                         fragmentainer_offsets[index].block - strip_offset)
+    }
+
+    fn distribute_children(&self,
+                           block: &mut BlockFlow,
+                           block_offset: Au,
+                           fragmentainer_offsets: &[LogicalSize<Au>]) {
+        let writing_mode = block.base.writing_mode;
+        for kid in block.base.child_iter() {
+            if flow::base(kid).flags.contains(IS_ABSOLUTELY_POSITIONED) {
+                continue
+            }
+
+            let kid_block_start = block_offset + flow::base(kid).position.start.b;
+            let kid_block_end = kid_block_start + flow::base(kid).position.size.block;
+            let spans_fragmentainers = kid_block_end > kid_block_start &&
+                self.fragmentainer_index(kid_block_start) !=
+                self.fragmentainer_index(kid_block_end - Au(1));
+
+            if spans_fragmentainers && is_breakable(kid) {
+                match kid.class() {
+                    InlineFlowClass => {
+                        self.distribute_lines(kid.as_inline(),
+                                              kid_block_start,
+                                              fragmentainer_offsets)
+                    }
+                    _ => {
+                        let kid_block = kid.as_block();
+                        self.distribute_children(kid_block,
+                                                 kid_block_start,
+                                                 fragmentainer_offsets);
+                        let position = kid_block.base.position;
+                        let mut overflow = kid_block.base.overflow.union(&position);
+                        for grandkid in kid_block.base.child_iter() {
+                            let grandkid_overflow = flow::base(grandkid).overflow;
+                            overflow = overflow.union(&grandkid_overflow.translate(&position.start))
+                        }
+                        kid_block.base.overflow = overflow
+                    }
+                }
+                continue
+            }
+
+            let offset = self.distribution_offset(writing_mode,
+                                                  kid_block_start,
+                                                  fragmentainer_offsets);
+            let kid_base = flow::mut_base(kid);
+            kid_base.position.start = kid_base.position.start + offset;
+            kid_base.overflow =
+                kid_base.overflow.translate(&LogicalPoint::new(writing_mode,
+                                                               offset.inline,
+                                                               offset.block));
+        }
+    }
+
+    fn distribute_lines(&self,
+                        inline: &mut InlineFlow,
+                        block_offset: Au,
+                        fragmentainer_offsets: &[LogicalSize<Au>]) {
+        let writing_mode = inline.base.writing_mode;
+        let position = inline.base.position;
+        let mut overflow = inline.base.overflow;
+        for line in inline.lines.iter_mut() {
+            let offset = self.distribution_offset(writing_mode,
+                                                  block_offset + line.bounds.start.b,
+                                                  fragmentainer_offsets);
+            line.bounds.start = line.bounds.start + offset;
+            for fragment_index in range(line.range.begin(), line.range.end()) {
+                let fragment = &mut inline.fragments.fragments[fragment_index.to_uint()];
+                fragment.border_box.start = fragment.border_box.start + offset;
+            }
+            overflow = overflow.union(&line.bounds.translate(&position.start));
+        }
+        inline.base.overflow = overflow
+    }
+}
+
+/// Returns the values of the `page-break-before`, `page-break-after` and `page-break-inside`
+/// properties of the given flow, if it is block-level.
+fn page_break_properties(flow: &mut Flow)
+                         -> Option<(page_break_before::T,
+                                    page_break_after::T,
+                                    page_break_inside::T)> {
+    match flow.class() {
+        InlineFlowClass | TableColGroupFlowClass => None,
+        _ => {
+            let style = flow.as_block().fragment.style();
+            Some((style.get_box().page_break_before,
+                  style.get_box().page_break_after,
+                  style.get_box().page_break_inside))
+        }
+    }
+}
+
+/// Returns true if breaks may be inserted inside the given flow. Everything else is monolithic
+/// and moves as a whole. CSS Fragmentation § 4.1.
+fn is_breakable(flow: &mut Flow) -> bool {
+    match flow.class() {
+        InlineFlowClass => true,
+        BlockFlowClass => !flow::base(flow).children.is_empty(),
+        _ => false,
+    }
+}
+
+/// Returns the block size of the start of the given flow that a break cannot split: its first
+/// `orphans` lines, or all of it if it is monolithic.
+fn leading_block_size(flow: &mut Flow, orphans: uint) -> Au {
+    if !is_breakable(flow) {
+        return flow::base(flow).position.size.block
+    }
+    match flow.class() {
+        InlineFlowClass => {
+            let inline = flow.as_inline();
+            let line_count = min(max(orphans, 1), inline.lines.len());
+            if line_count == 0 {
+                return Au(0)
+            }
+            let last_line = &inline.lines[line_count - 1];
+            last_line.bounds.start.b + last_line.bounds.size.block
+        }
+        _ => {
+            let block = flow.as_block();
+            for kid in block.base.child_iter() {
+                let kid_flags = flow::base(kid).flags;
+                if kid_flags.contains(IS_ABSOLUTELY_POSITIONED) || kid_flags.is_float() {
+                    continue
+                }
+                return flow::base(kid).position.start.b + leading_block_size(kid, orphans)
+            }
+            block.base.position.size.block
+        }
+    }
+}
@@ -144,8 +144,14 @@ pub fn compute_damage(old: &Option<Arc<ComputedValues>>, new: &ComputedValues) -
     add_if_not_equal!(old, new, damage,
                       [ REPAINT ], [
         get_color.color, get_background.background_color,
+        get_background.background_image, get_background.background_position,
+        get_background.background_repeat, get_background.background_attachment,
+        get_background.background_size, get_background.background_origin,
+        get_background.background_clip, get_inheritedtable.empty_cells,
         get_border.border_top_color, get_border.border_right_color,
-        get_border.border_bottom_color, get_border.border_left_color
+        get_border.border_bottom_color, get_border.border_left_color,
+        get_column.column_rule_color, get_column.column_rule_style,
+        get_column.column_rule_width
     ]);
 
     add_if_not_equal!(old, new, damage,
@@ -164,12 +170,39 @@ pub fn compute_damage(old: &Option<Arc<ComputedValues>>, new: &ComputedValues) -
         get_padding.padding_bottom, get_padding.padding_left,
         get_box.width, get_box.height,
         get_font.font_family, get_font.font_size, get_font.font_style, get_font.font_weight,
-        get_inheritedtext.text_align, get_text.text_decoration, get_inheritedbox.line_height
+        get_inheritedtext.text_align, get_inheritedtext.text_indent, get_text.text_decoration,
+        get_inheritedbox.line_height, get_inheritedtable.border_collapse,
+        get_inheritedtable.border_spacing, get_inheritedtext.overflow_wrap,
+        get_flex.flex_direction, get_flex.flex_wrap, get_flex.order,
+        get_flex.flex_grow, get_flex.flex_shrink, get_flex.flex_basis,
+        get_flex.justify_content, get_flex.align_items, get_flex.align_self,
+        get_flex.align_content, get_column.column_gap,
+        get_box.page_break_before, get_box.page_break_after, get_box.page_break_inside,
+        get_inheritedtext.orphans, get_inheritedtext.widows
     ]);
 
+    // Text runs are created during flow construction, so properties that affect shaping require
+    // the flow to be rebuilt.
     add_if_not_equal!(old, new, damage,
-                      [ REPAINT, BUBBLE_ISIZES, REFLOW_OUT_OF_FLOW, REFLOW, RECONSTRUCT_FLOW ],
-                      [ get_box.float, get_box.display, get_box.position ]);
+                      [ REPAINT, BUBBLE_ISIZES, REFLOW_OUT_OF_FLOW, REFLOW, RECONSTRUCT_FLOW ], [
+        get_box.float, get_box.display, get_box.position,
+        get_inheritedtext.text_transform, get_inheritedtext.letter_spacing,
+        get_inheritedtext.word_spacing, get_inheritedtext.word_break,
+        get_inheritedtext.white_space, get_inheritedtext.tab_size,
+        get_inheritedbox.direction, get_text.unicode_bidi
+    ]);
+
+    // Whether an element is a multi-column container is decided during flow construction.
+    add_if_not_equal!(old, new, damage,
+                      [ REPAINT, BUBBLE_ISIZES, REFLOW_OUT_OF_FLOW, REFLOW, RECONSTRUCT_FLOW ], [
+        get_column.column_width, get_column.column_count
+    ]);
+
+    // Table captions are placed before or after the table during flow construction.
+    add_if_not_equal!(old, new, damage,
+                      [ REPAINT, BUBBLE_ISIZES, REFLOW_OUT_OF_FLOW, REFLOW, RECONSTRUCT_FLOW ], [
+        get_inheritedtable.caption_side
+    ]);
 
     // FIXME: test somehow that we checked every CSS property
 
@@ -7,6 +7,7 @@
 use css::node_style::StyledNode;
 use context::LayoutContext;
 use display_list_builder::{ContentLevel, DisplayListResult, FragmentDisplayListBuilding};
+use display_list_builder::{SeparateBorderPaintingMode};
 use floats::{FloatLeft, Floats, PlacementInfo};
 use flow::{BaseFlow, FlowClass, Flow, ForceNonfloated, InlineFlowClass, MutableFlowUtils};
 use flow::{IS_ABSOLUTELY_POSITIONED};
@@ -24,6 +25,7 @@ use geom::Size2D;
 use gfx::display_list::DisplayList;
 use gfx::font::FontMetrics;
 use gfx::font_context::FontContext;
+use gfx::text::bidi;
 use gfx::text::glyph::CharIndex;
 use servo_util::geometry::Au;
 use servo_util::logical_geometry::{LogicalRect, LogicalSize, WritingMode};
@@ -150,6 +152,10 @@ pub struct Line {
     /// FFF float
     /// ~~~
     pub green_zone: LogicalSize<Au>,
+
+    /// True if this line was ended by a forced line break, such as a preserved newline or a
+    /// `<br>`, rather than by running out of room. Such lines are not justified.
+    pub ends_with_forced_break: bool,
 }
 
 int_range_index! {
@@ -185,7 +191,8 @@ impl LineBreaker {
             pending_line: Line {
                 range: Range::empty(),
                 bounds: LogicalRect::zero(float_context.writing_mode),
-                green_zone: LogicalSize::zero(float_context.writing_mode)
+                green_zone: LogicalSize::zero(float_context.writing_mode),
+                ends_with_forced_break: false,
             },
             floats: float_context,
             lines: Vec::new(),
@@ -212,7 +219,8 @@ impl LineBreaker {
                                                     self.cur_b,
                                                     Au(0),
                                                     Au(0));
-        self.pending_line.green_zone = LogicalSize::zero(self.floats.writing_mode)
+        self.pending_line.green_zone = LogicalSize::zero(self.floats.writing_mode);
+        self.pending_line.ends_with_forced_break = false
     }
 
     pub fn scan_for_lines(&mut self, flow: &mut InlineFlow, layout_context: &LayoutContext) {
@@ -246,10 +254,17 @@ impl LineBreaker {
                     white_space::normal => {
                         self.try_append_to_line(cur_fragment, flow, layout_context, WrapNormally)
                     }
-                    white_space::pre => self.try_append_to_line_by_new_line(cur_fragment),
+                    white_space::pre => {
+                        self.try_append_to_line_by_new_line(cur_fragment, flow)
+                    }
                     white_space::nowrap => {
                         self.try_append_to_line(cur_fragment, flow, layout_context, NoWrap)
                     }
+                    white_space::pre_wrap | white_space::pre_line => {
+                        self.try_append_to_line_by_new_line_or_wrapping(cur_fragment,
+                                                                        flow,
+                                                                        layout_context)
+                    }
                 };
 
                 if !fragment_was_appended {
@@ -381,6 +396,7 @@ impl LineBreaker {
 
             self.pending_line.bounds.start = next_line.start;
             self.pending_line.green_zone = next_green_zone;
+            self.indent_line_if_necessary(flow);
 
             assert!(!line_is_empty, "Non-terminating line breaking");
             self.work_list.push_front(in_fragment);
@@ -392,7 +408,23 @@ impl LineBreaker {
         false
     }
 
-    fn try_append_to_line_by_new_line(&mut self, in_fragment: Fragment) -> bool {
+    /// Indents the pending line by `text-indent` if it is the first line of the block.
+    fn indent_line_if_necessary(&mut self, flow: &InlineFlow) {
+        if !self.lines.is_empty() {
+            return
+        }
+        self.pending_line.bounds.start.i = self.pending_line.bounds.start.i +
+            flow.first_line_indentation;
+        self.pending_line.green_zone.inline = self.pending_line.green_zone.inline -
+            flow.first_line_indentation;
+    }
+
+    fn try_append_to_line_by_new_line(&mut self, in_fragment: Fragment, flow: &InlineFlow)
+                                      -> bool {
+        if self.pending_line.range.length() == num::zero() {
+            self.indent_line_if_necessary(flow);
+        }
+
         let no_newline_positions = match in_fragment.newline_positions() {
             None => true,
             Some(ref positions) => positions.is_empty(),
@@ -438,6 +470,92 @@ impl LineBreaker {
             self.work_list.push_front(inline_end);
         }
 
+        self.pending_line.ends_with_forced_break = true;
+        false
+    }
+
+    /// Tries to append the given fragment to the line for `white-space: pre-wrap` and `pre-line`,
+    /// which break lines at preserved newlines but also wrap. Returns false if and only if we
+    /// should break the line.
+    fn try_append_to_line_by_new_line_or_wrapping(&mut self,
+                                                  in_fragment: Fragment,
+                                                  flow: &InlineFlow,
+                                                  layout_context: &LayoutContext)
+                                                  -> bool {
+        let has_newline_positions = match in_fragment.newline_positions() {
+            None => false,
+            Some(ref positions) => !positions.is_empty(),
+        };
+        if !has_newline_positions {
+            return self.try_append_to_line(in_fragment, flow, layout_context, WrapNormally)
+        }
+
+        // The line is indented only once we know which way the fragment is appended, since
+        // `try_append_to_line_by_new_line` indents it too.
+        let line_is_empty = self.pending_line.range.length() == num::zero();
+        let mut available_inline_size = self.pending_line.green_zone.inline -
+            self.pending_line.bounds.size.inline;
+        if line_is_empty {
+            let (line_bounds, _) = self.initial_line_placement(&in_fragment, self.cur_b, flow);
+            self.pending_line.bounds.start = line_bounds.start;
+            self.pending_line.green_zone = line_bounds.size;
+            available_inline_size = line_bounds.size.inline;
+            if self.lines.is_empty() {
+                available_inline_size = available_inline_size - flow.first_line_indentation
+            }
+        }
+
+        // If the text up to the first newline fits, the line ends at that newline.
+        let (inline_start, _, _) =
+            in_fragment.find_split_info_by_new_line()
+                       .expect("LineBreaker: This split case makes no sense!");
+        if inline_start.inline_size <= available_inline_size {
+            return self.try_append_to_line_by_new_line(in_fragment, flow)
+        }
+
+        // Otherwise, wrap the text before the newline, and carry the newline positions over to
+        // whatever remains.
+        debug!("LineBreaker: Text before the new-line character doesn't fit, so wrapping.");
+        let split = in_fragment.find_split_info_for_inline_size(CharIndex(0),
+                                                                available_inline_size,
+                                                                line_is_empty);
+        let fragment_start = match in_fragment.specific {
+            ScannedTextFragment(ref info) => info.range.begin(),
+            _ => panic!("LineBreaker: Only scanned text fragments have newlines!"),
+        };
+        let first_new_line_position = in_fragment.newline_positions().unwrap()[0];
+        let (inline_start, inline_end, run) = match split {
+            Some((inline_start, Some(inline_end), run))
+                    if (inline_start.is_some() || !line_is_empty) &&
+                        inline_end.range.begin() <= fragment_start + first_new_line_position => {
+                (inline_start, inline_end, run)
+            }
+            _ => return self.try_append_to_line_by_new_line(in_fragment, flow),
+        };
+        if line_is_empty {
+            self.indent_line_if_necessary(flow);
+        }
+
+        let writing_mode = self.floats.writing_mode;
+        let split_fragment = |split: SplitInfo, new_line_positions: Vec<CharIndex>| {
+            let info = box ScannedTextFragmentInfo::new(run.clone(),
+                                                        split.range,
+                                                        new_line_positions,
+                                                        in_fragment.border_box.size);
+            let size = LogicalSize::new(writing_mode,
+                                        split.inline_size,
+                                        in_fragment.border_box.size.block);
+            in_fragment.transform(size, info)
+        };
+
+        let mut new_line_positions = (*in_fragment.newline_positions().unwrap()).clone();
+        new_line_positions[0] = fragment_start + first_new_line_position -
+            inline_end.range.begin();
+        match inline_start {
+            Some(inline_start) => self.push_fragment_to_line(split_fragment(inline_start, vec![])),
+            None => {}
+        }
+        self.work_list.push_front(split_fragment(inline_end, new_line_positions));
         false
     }
 
@@ -456,6 +574,7 @@ impl LineBreaker {
             let (line_bounds, _) = self.initial_line_placement(&in_fragment, self.cur_b, flow);
             self.pending_line.bounds.start = line_bounds.start;
             self.pending_line.green_zone = line_bounds.size;
+            self.indent_line_if_necessary(flow);
         }
 
         debug!("LineBreaker: Trying to append fragment to line {:u} (fragment size: {}, green \
@@ -645,6 +764,7 @@ impl InlineFragments {
             let right: Fragment =
                 match work.pop_front() {
                     None => {
+                        left.reset_text_justification();
                         out.push(left);
                         break;
                     }
@@ -652,12 +772,15 @@ impl InlineFragments {
                 };
 
             left.restore_new_line_pos();
+            left.reset_text_justification();
 
+            // Justification may have given each line its own copy of the text run, so compare
+            // the underlying text rather than the runs themselves.
             let right_is_from_same_fragment =
                 match (&mut left.specific, &right.specific) {
                     (&ScannedTextFragment(ref mut left_info),
                      &ScannedTextFragment(ref right_info)) => {
-                        if arc_ptr_eq(&left_info.run, &right_info.run)
+                        if arc_ptr_eq(&left_info.run.text, &right_info.run.text)
                         && left_info.range.end() + CharIndex(1) == right_info.range.begin() {
                             left_info.range.extend_by(right_info.range.length() + CharIndex(1));
                             true
@@ -701,6 +824,10 @@ pub struct InlineFlow {
     /// The minimum depth below the baseline for each line, as specified by the line block-size and
     /// font style.
     pub minimum_depth_below_baseline: Au,
+
+    /// The amount by which the first line is indented, per the `text-indent` property of the
+    /// containing block. This is zero unless this flow holds the first line of that block.
+    pub first_line_indentation: Au,
 }
 
 impl InlineFlow {
@@ -711,6 +838,7 @@ impl InlineFlow {
             lines: Vec::new(),
             minimum_block_size_above_baseline: Au(0),
             minimum_depth_below_baseline: Au(0),
+            first_line_indentation: Au(0),
         }
     }
 
@@ -788,26 +916,42 @@ impl InlineFlow {
     }
 
     /// Sets fragment positions in the inline direction based on alignment for one line.
+    ///
+    /// `is_last_line` is true if this is the last line of the flow. Neither it nor a line ended by
+    /// a forced break is justified.
     fn set_inline_fragment_positions(fragments: &mut InlineFragments,
-                                     line: &Line,
-                                     line_align: text_align::T) {
+                                     line: &mut Line,
+                                     line_align: text_align::T,
+                                     is_last_line: bool,
+                                     writing_mode: WritingMode) {
         // Figure out how much inline-size we have.
         let slack_inline_size = max(Au(0), line.green_zone.inline - line.bounds.size.inline);
 
         // Set the fragment inline positions based on that alignment.
         let mut offset = line.bounds.start.i;
         offset = offset + match line_align {
-            // So sorry, but justified text is more complicated than shuffling line
-            // coordinates.
-            //
-            // TODO(burg, issue #213): Implement `text-align: justify`.
+            text_align::justify if !is_last_line && !line.ends_with_forced_break => {
+                InlineFlow::justify_inline_fragments(fragments, line, slack_inline_size);
+                Au(0)
+            }
             text_align::left | text_align::justify => Au(0),
             text_align::center => slack_inline_size.scale_by(0.5),
             text_align::right => slack_inline_size,
         };
 
-        for fragment_index in range(line.range.begin(), line.range.end()) {
-            let fragment = fragments.get_mut(fragment_index.to_uint());
+        // Lay the fragments out in visual order, per the Unicode Bidirectional Algorithm. Inline
+        // offsets grow leftward in right-to-left paragraphs, so the order is reversed there.
+        let paragraph_level = text::bidi_paragraph_level(writing_mode);
+        let bidi_levels: Vec<u8> = range(line.range.begin(), line.range.end()).map(|index| {
+            fragments.get(index.to_uint()).bidi_level(paragraph_level)
+        }).collect();
+        let mut visual_order = bidi::visual_order(bidi_levels.as_slice());
+        if bidi::is_rtl_level(paragraph_level) {
+            visual_order.reverse()
+        }
+
+        for &line_index in visual_order.iter() {
+            let fragment = fragments.get_mut(line.range.begin().to_uint() + line_index);
             let size = fragment.border_box.size;
             fragment.border_box = LogicalRect::new(fragment.style.writing_mode,
                                                    offset,
@@ -819,13 +963,92 @@ impl InlineFlow {
         }
     }
 
+    /// Justifies one line by distributing `slack_inline_size` across the expansion opportunities
+    /// (word separators) of the text fragments on it, per CSS Text Level 3 § 7.3. Whitespace at
+    /// the end of the line hangs, so it is neither expanded nor counted as taking up room.
+    fn justify_inline_fragments(fragments: &mut InlineFragments,
+                                line: &mut Line,
+                                slack_inline_size: Au) {
+        let last_fragment_index = line.range.end() - FragmentIndex(1);
+
+        let mut expansion_opportunities = 0;
+        let mut hanging_inline_size = Au(0);
+        for fragment_index in range(line.range.begin(), line.range.end()) {
+            let fragment = fragments.get(fragment_index.to_uint());
+            let scanned_text_fragment_info = match fragment.specific {
+                ScannedTextFragment(ref info) => info,
+                _ => continue,
+            };
+
+            let run = &scanned_text_fragment_info.run;
+            let mut text_range = scanned_text_fragment_info.range;
+            if fragment_index == last_fragment_index {
+                let trailing_whitespace_length =
+                    run.trailing_whitespace_length_for_range(&text_range);
+                text_range.extend_by(-trailing_whitespace_length);
+                hanging_inline_size =
+                    run.advance_for_range(&Range::new(text_range.end(),
+                                                      trailing_whitespace_length))
+            }
+            expansion_opportunities += run.expansion_opportunities_for_range(&text_range);
+        }
+
+        if expansion_opportunities == 0 {
+            return
+        }
+
+        // The remainder of the division is spread over the first expansion opportunities of the
+        // line, one Au each, so that the line fills its box exactly.
+        let justification_inline_size = slack_inline_size + hanging_inline_size;
+        let extra_word_spacing = justification_inline_size / (expansion_opportunities as i32);
+        let Au(remainder) = justification_inline_size % (expansion_opportunities as i32);
+        let mut remaining_widened_opportunities = remainder as int;
+
+        // Give each text fragment its own copy of its run so that lines of the same text can be
+        // justified independently, and grow the fragment to match.
+        for fragment_index in range(line.range.begin(), line.range.end()) {
+            let fragment = fragments.get_mut(fragment_index.to_uint());
+            let scanned_text_fragment_info = match fragment.specific {
+                ScannedTextFragment(ref mut info) => info,
+                _ => continue,
+            };
+
+            let text_range = scanned_text_fragment_info.range;
+            let natural_inline_size =
+                scanned_text_fragment_info.run.advance_for_range(&text_range);
+            let remainder_end = scanned_text_fragment_info.run.end_of_expansion_opportunities(
+                &text_range,
+                remaining_widened_opportunities);
+            remaining_widened_opportunities -=
+                scanned_text_fragment_info.run.expansion_opportunities_for_range(
+                    &Range::new(text_range.begin(), remainder_end - text_range.begin()));
+            {
+                let run = scanned_text_fragment_info.run.make_unique();
+                run.extra_word_spacing = extra_word_spacing;
+                run.extra_word_spacing_remainder_end = remainder_end;
+            }
+            let justified_inline_size =
+                scanned_text_fragment_info.run.advance_for_range(&text_range);
+
+            let extra_inline_size = justified_inline_size - natural_inline_size;
+            fragment.border_box.size.inline = fragment.border_box.size.inline + extra_inline_size;
+            scanned_text_fragment_info.content_size.inline = fragment.border_box.size.inline;
+            line.bounds.size.inline = line.bounds.size.inline + extra_inline_size;
+        }
+    }
+
     /// Sets final fragment positions in the block direction for one line. Assumes that the
     /// fragment positions were initially set to the distance from the baseline first.
+    ///
+    /// In vertical writing modes whose glyphs are rotated to face the block-end side (see
+    /// `WritingMode::is_line_over_block_start`), the line is mirrored in the block direction.
     fn set_block_fragment_positions(fragments: &mut InlineFragments,
                                     line: &Line,
+                                    writing_mode: WritingMode,
                                     line_distance_from_flow_block_start: Au,
                                     baseline_distance_from_block_start: Au,
                                     largest_depth_below_baseline: Au) {
+        let line_block_size = baseline_distance_from_block_start + largest_depth_below_baseline;
         for fragment_index in range(line.range.begin(), line.range.end()) {
             let fragment = fragments.get_mut(fragment_index.to_uint());
             match fragment.vertical_align() {
@@ -843,6 +1066,12 @@ impl InlineFlow {
                         line_distance_from_flow_block_start + baseline_distance_from_block_start
                 }
             }
+            if !writing_mode.is_line_over_block_start() {
+                let offset_in_line = fragment.border_box.start.b -
+                    line_distance_from_flow_block_start;
+                fragment.border_box.start.b = line_distance_from_flow_block_start +
+                    line_block_size - offset_in_line - fragment.border_box.size.block
+            }
             fragment.update_late_computed_block_position_if_necessary();
         }
     }
@@ -1010,9 +1239,15 @@ impl Flow for InlineFlow {
 
         // Now, go through each line and lay out the fragments inside.
         let mut line_distance_from_flow_block_start = Au(0);
-        for line in self.lines.iter_mut() {
+        let line_count = self.lines.len();
+        let writing_mode = self.base.writing_mode;
+        for (line_index, line) in self.lines.iter_mut().enumerate() {
             // Lay out fragments in the inline direction.
-            InlineFlow::set_inline_fragment_positions(&mut self.fragments, line, text_align);
+            InlineFlow::set_inline_fragment_positions(&mut self.fragments,
+                                                      line,
+                                                      text_align,
+                                                      line_index == line_count - 1,
+                                                      writing_mode);
 
             // Set the block-start position of the current line.
             // `line_height_offset` is updated at the end of the previous loop.
@@ -1103,6 +1338,7 @@ impl Flow for InlineFlow {
             // `fragment.border_box.start.b` was set to the distance from the baseline above.
             InlineFlow::set_block_fragment_positions(&mut self.fragments,
                                                      line,
+                                                     writing_mode,
                                                      line_distance_from_flow_block_start,
                                                      baseline_distance_from_block_start,
                                                      largest_depth_below_baseline);
@@ -1198,6 +1434,7 @@ impl Flow for InlineFlow {
                                         layout_context,
                                         fragment_origin,
                                         ContentLevel,
+                                        SeparateBorderPaintingMode,
                                         &self.base.clip_rect);
             match fragment.specific {
                 InlineBlockFragment(ref mut block_flow) => {
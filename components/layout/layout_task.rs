@@ -10,7 +10,8 @@ use construct::FlowConstructionResult;
 use context::SharedLayoutContext;
 use flow::{mod, Flow, ImmutableFlowUtils, MutableFlowUtils, MutableOwnedFlowUtils};
 use flow_ref::FlowRef;
-use fragment::{Fragment, FragmentBoundsIterator};
+use fragmentation::FragmentationContext;
+use fragment::{Fragment, FragmentBoundsIterator, ScannedTextFragment};
 use incremental::{LayoutDamageComputation, REFLOW, REFLOW_ENTIRE_DOCUMENT, REPAINT};
 use layout_debug;
 use parallel::UnsafeFlow;
@@ -23,12 +24,15 @@ use encoding::EncodingRef;
 use encoding::all::UTF_8;
 use geom::point::Point2D;
 use geom::rect::Rect;
+use geom::side_offsets::SideOffsets2D;
 use geom::size::Size2D;
 use geom::scale_factor::ScaleFactor;
 use gfx::color;
 use gfx::display_list::{DisplayList, OpaqueNode, StackingContext};
 use gfx::font_cache_task::FontCacheTask;
 use gfx::render_task::{mod, RenderInitMsg, RenderChan, RenderLayer};
+use gfx::text::glyph::CharIndex;
+use gfx::text::text_run::TextRun;
 use layout_traits;
 use layout_traits::{LayoutControlMsg, LayoutTaskFactory};
 use log;
@@ -40,17 +44,19 @@ use script::layout_interface::{ContentBoxesQuery, ContentBoxQuery, ExitNowMsg, G
 use script::layout_interface::{HitTestResponse, LayoutChan, LayoutRPC, LoadStylesheetMsg};
 use script::layout_interface::{MouseOverResponse, Msg, NoQuery, PrepareToExitMsg};
 use script::layout_interface::{ReapLayoutDataMsg, Reflow, ReflowForDisplay, ReflowMsg};
-use script::layout_interface::{ScriptLayoutChan, TrustedNodeAddress};
+use script::layout_interface::{ScriptLayoutChan, ScrollRangeQuery, ScrollRangeResponse};
+use script::layout_interface::{TextBoxesQuery, TrustedNodeAddress};
 use script_traits::{SendEventMsg, ReflowEvent, ReflowCompleteMsg, OpaqueScriptLayoutChannel};
 use script_traits::{ScriptControlChan, UntrustedNodeAddress};
-use servo_msg::compositor_msg::Scrollable;
+use servo_msg::compositor_msg::{Pagination, Scrollable};
 use servo_msg::constellation_msg::{ConstellationChan, PipelineId, Failure, FailureMsg};
 use servo_net::image_cache_task::{ImageCacheTask, ImageResponseMsg};
 use servo_net::local_image_cache::{ImageResponder, LocalImageCache};
 use servo_net::resource_task::{ResourceTask, load_bytes_iter};
 use servo_util::geometry::Au;
-use servo_util::logical_geometry::LogicalPoint;
+use servo_util::logical_geometry::{LogicalPoint, LogicalSize};
 use servo_util::opts;
+use servo_util::range::Range;
 use servo_util::smallvec::{SmallVec, SmallVec1, VecLike};
 use servo_util::task::spawn_named_with_send_on_failure;
 use servo_util::task_state;
@@ -58,11 +64,12 @@ use servo_util::time::{TimeProfilerChan, profile};
 use servo_util::time;
 use servo_util::workqueue::WorkQueue;
 use std::cell::Cell;
+use std::cmp::{max, min};
 use std::comm::{channel, Sender, Receiver, Select};
 use std::mem;
 use std::ptr;
 use style::{AuthorOrigin, Stylesheet, Stylist, TNode, iter_font_face_rules};
-use style::{Device, Screen};
+use style::{Device, Print, Screen};
 use sync::{Arc, Mutex, MutexGuard};
 use url::Url;
 
@@ -96,6 +103,67 @@ pub struct LayoutTaskData {
 
     /// A queued response for the content boxes of a node.
     pub content_boxes_response: Vec<Rect<Au>>,
+
+    /// A queued response for how far the contents of a node can be scrolled.
+    pub scroll_range_response: Size2D<Au>,
+}
+
+/// The pages that the document is laid out in, when layout is paginated. CSS Paged Media § 3.
+///
+/// FIXME: Every page has the page box size and the page area size of the first page. The margins
+/// of the pages after it only move their page area within the page box.
+struct PageLayout {
+    /// The size of each page box.
+    page_box_size: Size2D<Au>,
+
+    /// The margins of the first page box, around the page area that holds the content.
+    margin: SideOffsets2D<Au>,
+
+    /// The margins of the left page boxes.
+    left_margin: SideOffsets2D<Au>,
+
+    /// The margins of the right page boxes after the first.
+    right_margin: SideOffsets2D<Au>,
+
+    /// The number of pages, once the document has been broken into pages.
+    page_count: uint,
+}
+
+impl PageLayout {
+    /// Returns the page layout given by the `@page` rules of the stylist, or `None` if layout is
+    /// not paginated.
+    fn new(stylist: &Stylist) -> Option<PageLayout> {
+        opts::get().page_size.map(|page_size| {
+            let medium_size = Size2D(Au::from_page_px(page_size.width),
+                                     Au::from_page_px(page_size.height));
+            let first_page_style = stylist.page_style(0);
+            PageLayout {
+                page_box_size: first_page_style.page_box_size(medium_size),
+                margin: first_page_style.margin,
+                left_margin: stylist.page_style(1).margin,
+                right_margin: stylist.page_style(2).margin,
+                page_count: 0,
+            }
+        })
+    }
+
+    /// Returns the size of the page area, which layout uses as the viewport.
+    fn page_area_size(&self) -> Size2D<Au> {
+        let margin = self.margin;
+        Size2D(Au::max(Au(0), self.page_box_size.width - margin.left - margin.right),
+               Au::max(Au(0), self.page_box_size.height - margin.top - margin.bottom))
+    }
+
+    /// Returns the position of the page area of the page at the given index, with the page boxes
+    /// stacked down the canvas. The first page is a right page.
+    fn page_area_origin(&self, page_index: uint) -> Point2D<Au> {
+        let margin = match page_index {
+            0 => self.margin,
+            _ if page_index % 2 == 1 => self.left_margin,
+            _ => self.right_margin,
+        };
+        Point2D(margin.left, self.page_box_size.height * (page_index as i32) + margin.top)
+    }
 }
 
 /// Information needed by the layout task.
@@ -249,7 +317,11 @@ impl LayoutTask {
         let local_image_cache =
             Arc::new(Mutex::new(LocalImageCache::new(image_cache_task.clone())));
         let screen_size = Size2D(Au(0), Au(0));
-        let device = Device::new(Screen, opts::get().initial_window_size.as_f32() * ScaleFactor(1.0));
+        let device = match opts::get().page_size {
+            Some(page_size) => Device::new(Print, page_size * ScaleFactor(1.0)),
+            None => Device::new(Screen,
+                                opts::get().initial_window_size.as_f32() * ScaleFactor(1.0)),
+        };
         let parallel_traversal = if opts::get().layout_threads != 1 {
             Some(WorkQueue::new("LayoutWorker", task_state::LAYOUT,
                                 opts::get().layout_threads, ptr::null()))
@@ -281,6 +353,7 @@ impl LayoutTask {
                     generation: 0,
                     content_box_response: Rect::zero(),
                     content_boxes_response: Vec::new(),
+                    scroll_range_response: Size2D::zero(),
               })),
         }
     }
@@ -571,6 +644,43 @@ impl LayoutTask {
         }
     }
 
+    /// Breaks the laid-out document into pages along its block axis, and moves the content of
+    /// each page to its page area, with the page boxes stacked down the canvas. Returns the number
+    /// of pages. CSS Paged Media § 3.
+    fn paginate(layout_root: &mut FlowRef, page_layout: &PageLayout) -> uint {
+        let _scope = layout_debug_scope!("paginate");
+        let root = layout_root.deref_mut().as_block();
+        let writing_mode = root.base.writing_mode;
+        let page_area_block_size =
+            LogicalSize::from_physical(writing_mode, page_layout.page_area_size()).block;
+        let fragmentation_context = FragmentationContext::new_for_pages(page_area_block_size);
+        let growth = fragmentation_context.insert_breaks(root);
+        root.base.position.size.block = root.base.position.size.block + growth;
+        root.fragment.border_box.size.block = root.fragment.border_box.size.block + growth;
+
+        let page_count =
+            fragmentation_context.fragmentainer_index(root.base.position.size.block - Au(1)) + 1;
+
+        // The root is positioned at the page area of the first page, so each page moves by the
+        // physical offset of its page area from that one, in the writing mode of the root.
+        let container_size = Size2D(Au(0), Au(0));
+        let first_page_area_origin =
+            LogicalPoint::from_physical(writing_mode,
+                                        page_layout.page_area_origin(0),
+                                        container_size);
+        let page_offsets: Vec<LogicalSize<Au>> = range(0, page_count).map(|page_index| {
+            let page_area_origin =
+                LogicalPoint::from_physical(writing_mode,
+                                            page_layout.page_area_origin(page_index),
+                                            container_size);
+            LogicalSize::new(writing_mode,
+                             page_area_origin.i - first_page_area_origin.i,
+                             page_area_origin.b - first_page_area_origin.b)
+        }).collect();
+        fragmentation_context.distribute(root, page_offsets.as_slice());
+        page_count
+    }
+
     /// Verifies that every node was either marked as a leaf or as a nonleaf in the flow tree.
     /// This is only on in debug builds.
     #[inline(never)]
@@ -604,11 +714,34 @@ impl LayoutTask {
         rw_data.content_boxes_response = iterator.rects;
     }
 
+    fn process_text_boxes_request<'a>(&'a self,
+                                      requested_node: TrustedNodeAddress,
+                                      start: uint,
+                                      end: uint,
+                                      layout_root: &mut FlowRef,
+                                      rw_data: &mut RWGuard<'a>) {
+        let requested_node: OpaqueNode = OpaqueNodeMethods::from_script_node(requested_node);
+        let mut iterator = TextFragmentBoundsIterator::new(requested_node);
+        sequential::iterate_through_flow_tree_fragment_bounds(layout_root, &mut iterator);
+        rw_data.content_boxes_response =
+            iterator.rects_for_range(CharIndex(start as int), CharIndex(end as int));
+    }
+
+    fn process_scroll_range_request<'a>(&'a self,
+                                        requested_node: TrustedNodeAddress,
+                                        layout_root: &mut FlowRef,
+                                        rw_data: &mut RWGuard<'a>) {
+        let requested_node: OpaqueNode = OpaqueNodeMethods::from_script_node(requested_node);
+        rw_data.scroll_range_response =
+            sequential::scroll_range_of_node(layout_root, requested_node);
+    }
+
     fn build_display_list_for_reflow<'a>(&'a self,
                                          data: &Reflow,
                                          node: &mut LayoutNode,
                                          layout_root: &mut FlowRef,
                                          shared_layout_ctx: &mut SharedLayoutContext,
+                                         page_layout: &Option<PageLayout>,
                                          rw_data: &mut RWGuard<'a>) {
         let writing_mode = flow::base(layout_root.deref()).writing_mode;
         profile(time::LayoutDispListBuildCategory,
@@ -619,8 +752,16 @@ impl LayoutTask {
                 flow::base(layout_root.deref()).position.to_physical(writing_mode,
                                                                      rw_data.screen_size);
             flow::mut_base(layout_root.deref_mut()).stacking_relative_position =
-                LogicalPoint::zero(writing_mode).to_physical(writing_mode,
-                                                             rw_data.screen_size);
+                match *page_layout {
+                    // The content of the first page starts at the corner of its page area.
+                    Some(ref page_layout) => {
+                        Point2D(page_layout.margin.left, page_layout.margin.top)
+                    }
+                    None => {
+                        LogicalPoint::zero(writing_mode).to_physical(writing_mode,
+                                                                     rw_data.screen_size)
+                    }
+                };
 
             flow::mut_base(layout_root.deref_mut()).clip_rect = data.page_clip_rect;
 
@@ -665,16 +806,32 @@ impl LayoutTask {
                 }
             }
 
-            let root_size = {
-                let root_flow = flow::base(layout_root.deref());
-                root_flow.position.size.to_physical(root_flow.writing_mode)
+            let root_size = match *page_layout {
+                // The pages are stacked one above the other.
+                Some(ref page_layout) => {
+                    Size2D(page_layout.page_box_size.width,
+                           page_layout.page_box_size.height * (page_layout.page_count as i32))
+                }
+                None => {
+                    let root_flow = flow::base(layout_root.deref());
+                    root_flow.position.size.to_physical(root_flow.writing_mode)
+                }
             };
             let mut display_list = box DisplayList::new();
             flow::mut_base(layout_root.deref_mut()).display_list_building_result
                                                    .add_to(&mut *display_list);
-            let render_layer = Arc::new(RenderLayer::new(layout_root.layer_id(0),
-                                                         color,
-                                                         Scrollable));
+            let mut render_layer = RenderLayer::new(layout_root.layer_id(0),
+                                                    color,
+                                                    Scrollable,
+                                                    None);
+            render_layer.pagination = page_layout.as_ref().map(|page_layout| {
+                Pagination {
+                    page_size: Size2D(page_layout.page_box_size.width.to_subpx() as f32,
+                                      page_layout.page_box_size.height.to_subpx() as f32),
+                    page_count: page_layout.page_count,
+                }
+            });
+            let render_layer = Arc::new(render_layer);
             let origin = Rect(Point2D(Au(0), Au(0)), root_size);
             let stacking_context = Arc::new(StackingContext::new(display_list,
                                                                  origin,
@@ -722,9 +879,22 @@ impl LayoutTask {
         // http://www.w3.org/TR/css-device-adapt/#actual-viewport
         let viewport_size = data.window_size.initial_viewport;
 
+        // When layout is paginated, the viewport is the page area instead. `@page` rules may come
+        // from any stylesheet, so the stylist has to be brought up to date before computing it;
+        // the print device never changes, so it never needs updating again below.
+        let mut needs_dirtying = false;
+        let mut page_layout = None;
+        if opts::get().page_size.is_some() {
+            needs_dirtying = rw_data.stylist.update();
+            page_layout = PageLayout::new(&*rw_data.stylist);
+        }
+
         let old_screen_size = rw_data.screen_size;
-        let current_screen_size = Size2D(Au::from_frac32_px(viewport_size.width.get()),
-                                         Au::from_frac32_px(viewport_size.height.get()));
+        let current_screen_size = match page_layout {
+            Some(ref page_layout) => page_layout.page_area_size(),
+            None => Size2D(Au::from_frac32_px(viewport_size.width.get()),
+                           Au::from_frac32_px(viewport_size.height.get())),
+        };
         rw_data.screen_size = current_screen_size;
 
         // Create a layout context for use throughout the following passes.
@@ -735,12 +905,12 @@ impl LayoutTask {
         // Handle conditions where the entire flow tree is invalid.
         let screen_size_changed = current_screen_size != old_screen_size;
 
-        if screen_size_changed {
+        if screen_size_changed && page_layout.is_none() {
             let device = Device::new(Screen, data.window_size.initial_viewport);
             rw_data.stylist.set_device(device);
         }
 
-        let needs_dirtying = rw_data.stylist.update();
+        needs_dirtying = rw_data.stylist.update() || needs_dirtying;
 
         // If the entire flow tree is invalid, then it will be reflowed anyhow.
         let needs_reflow = screen_size_changed && !needs_dirtying;
@@ -780,7 +950,9 @@ impl LayoutTask {
                 Some((&data.url, data.iframe, self.first_reflow.get())),
                 self.time_profiler_chan.clone(),
                 || {
-            if opts::get().nonincremental_layout ||
+            // Page breaks are inserted into the laid-out flow tree, so paginated layout always
+            // starts again from scratch.
+            if opts::get().nonincremental_layout || page_layout.is_some() ||
                     layout_root.deref_mut().compute_layout_damage().contains(REFLOW_ENTIRE_DOCUMENT) {
                 layout_root.deref_mut().reflow_entire_document()
             }
@@ -817,12 +989,20 @@ impl LayoutTask {
             }
         });
 
+        match page_layout {
+            Some(ref mut page_layout) => {
+                page_layout.page_count = LayoutTask::paginate(&mut layout_root, page_layout)
+            }
+            None => {}
+        }
+
         // Build the display list if necessary, and send it to the renderer.
         if data.goal == ReflowForDisplay {
             self.build_display_list_for_reflow(data,
                                                node,
                                                &mut layout_root,
                                                &mut shared_layout_ctx,
+                                               &page_layout,
                                                &mut rw_data);
         }
 
@@ -831,6 +1011,10 @@ impl LayoutTask {
                 self.process_content_box_request(node, &mut layout_root, &mut rw_data),
             ContentBoxesQuery(node) =>
                 self.process_content_boxes_request(node, &mut layout_root, &mut rw_data),
+            TextBoxesQuery(node, start, end) =>
+                self.process_text_boxes_request(node, start, end, &mut layout_root, &mut rw_data),
+            ScrollRangeQuery(node) =>
+                self.process_scroll_range_request(node, &mut layout_root, &mut rw_data),
             NoQuery => {},
         }
 
@@ -917,6 +1101,13 @@ impl LayoutRPC for LayoutRPCImpl {
         ContentBoxesResponse(rw_data.content_boxes_response.clone())
     }
 
+    /// Requests how far the contents of a scroll container can be scrolled.
+    fn scroll_range(&self) -> ScrollRangeResponse {
+        let &LayoutRPCImpl(ref rw_data) = self;
+        let rw_data = rw_data.lock();
+        ScrollRangeResponse(rw_data.scroll_range_response)
+    }
+
     /// Requests the node containing the point of interest.
     fn hit_test(&self, _: TrustedNodeAddress, point: Point2D<f32>) -> Result<HitTestResponse, ()> {
         let point = Point2D(Au::from_frac_px(point.x as f64), Au::from_frac_px(point.y as f64));
@@ -1017,3 +1208,71 @@ impl FragmentBoundsIterator for CollectingFragmentBoundsIterator {
         self.node_address == fragment.node
     }
 }
+
+/// Collects the text fragments of a node so that the boxes of a range of its characters can be
+/// computed afterwards.
+struct TextFragmentBoundsIterator {
+    node_address: OpaqueNode,
+    fragments: Vec<(Arc<Box<TextRun>>, Range<CharIndex>, bool, Rect<Au>)>,
+}
+
+impl TextFragmentBoundsIterator {
+    fn new(node_address: OpaqueNode) -> TextFragmentBoundsIterator {
+        TextFragmentBoundsIterator {
+            node_address: node_address,
+            fragments: Vec::new(),
+        }
+    }
+
+    /// Returns one box per fragment overlapping the characters `start..end` of the node.
+    ///
+    /// The fragments of a node may share their text run with the neighbouring nodes, so the
+    /// offsets are made relative to the first character of the node in the run.
+    ///
+    /// FIXME: This assumes that white-space collapsing left the text of the node untouched.
+    fn rects_for_range(&self, start: CharIndex, end: CharIndex) -> Vec<Rect<Au>> {
+        let base = match self.fragments.iter().map(|&(_, ref range, _, _)| range.begin()).min() {
+            Some(base) => base,
+            None => return vec!(),
+        };
+        let (start, end) = (base + start, base + end);
+
+        let mut rects = vec!();
+        for &(ref run, ref range, is_vertical, ref bounds) in self.fragments.iter() {
+            let begin = max(range.begin(), start);
+            let finish = min(range.end(), end);
+            if begin > finish || (begin == finish && start != end) {
+                continue
+            }
+
+            let leading = run.advance_for_range(&Range::new(range.begin(), begin - range.begin()));
+            let advance = run.advance_for_range(&Range::new(begin, finish - begin));
+            rects.push(if is_vertical {
+                Rect(Point2D(bounds.origin.x, bounds.origin.y + leading),
+                     Size2D(bounds.size.width, advance))
+            } else {
+                Rect(Point2D(bounds.origin.x + leading, bounds.origin.y),
+                     Size2D(advance, bounds.size.height))
+            });
+        }
+        rects
+    }
+}
+
+impl FragmentBoundsIterator for TextFragmentBoundsIterator {
+    fn process(&mut self, fragment: &Fragment, bounds: Rect<Au>) {
+        match fragment.specific {
+            ScannedTextFragment(ref info) => {
+                self.fragments.push((info.run.clone(),
+                                     info.range,
+                                     fragment.style.writing_mode.is_vertical(),
+                                     bounds))
+            }
+            _ => {}
+        }
+    }
+
+    fn should_process(&mut self, fragment: &Fragment) -> bool {
+        self.node_address == fragment.node
+    }
+}
@@ -45,14 +45,17 @@ pub mod block;
 pub mod construct;
 pub mod context;
 pub mod display_list_builder;
+pub mod flex;
 pub mod floats;
 pub mod flow;
 pub mod flow_list;
 pub mod flow_ref;
 pub mod fragment;
+pub mod fragmentation;
 pub mod layout_task;
 pub mod inline;
 pub mod model;
+pub mod multicol;
 pub mod parallel;
 pub mod sequential;
 pub mod table_wrapper;
@@ -0,0 +1,298 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Layout for multi-column containers: block containers with a `column-count` or `column-width`
+//! other than `auto`.
+//!
+//! "CSS Multi-column" refers to the W3C "CSS Multi-column Layout Module Level 1" specification
+//! available here:
+//!
+//!   http://dev.w3.org/csswg/css-multicol/
+
+#![deny(unsafe_blocks)]
+
+use block::{BlockFlow, MarginsMayNotCollapse};
+use construct::FlowConstructor;
+use context::LayoutContext;
+use display_list_builder::MulticolFlowDisplayListBuilding;
+use flow::{Flow, FlowClass, MulticolFlowClass, IS_ABSOLUTELY_POSITIONED};
+use flow;
+use fragment::FragmentBoundsIterator;
+use fragmentation::FragmentationContext;
+use incremental::{LayoutDamageComputation, REFLOW, REFLOW_OUT_OF_FLOW};
+use layout_debug;
+use wrapper::ThreadSafeLayoutNode;
+
+use servo_util::geometry::Au;
+use servo_util::logical_geometry::LogicalSize;
+use std::cmp::{max, min};
+use std::fmt;
+use style::ComputedValues;
+use style::computed_values::{LPA_Auto, column_fill};
+use sync::Arc;
+
+/// The number of times the column block-size is raised while balancing before giving up and
+/// letting the content overflow into extra columns.
+static MAX_BALANCING_ITERATIONS: uint = 32;
+
+/// Returns true if an element with the given style is a multi-column container. CSS Multi-column
+/// § 2.
+pub fn is_multicol_container(style: &ComputedValues) -> bool {
+    let column_style = style.get_column();
+    column_style.column_count.is_some() || column_style.column_width.is_some()
+}
+
+/// A multi-column container.
+pub struct MulticolFlow {
+    /// Data common to all block flows.
+    pub block_flow: BlockFlow,
+
+    /// The used number of columns.
+    pub column_count: uint,
+
+    /// The number of columns that the content actually occupies. This may be more than
+    /// `column_count` if the container's block-size is too small to fit the content.
+    pub filled_column_count: uint,
+
+    /// The used inline-size of each column.
+    pub column_inline_size: Au,
+
+    /// The used gap between columns.
+    pub column_gap: Au,
+
+    /// The block-size of each column.
+    pub column_block_size: Au,
+}
+
+impl MulticolFlow {
+    pub fn from_node(constructor: &mut FlowConstructor,
+                     node: &ThreadSafeLayoutNode)
+                     -> MulticolFlow {
+        MulticolFlow {
+            block_flow: BlockFlow::from_node(constructor, node),
+            column_count: 1,
+            filled_column_count: 0,
+            column_inline_size: Au(0),
+            column_gap: Au(0),
+            column_block_size: Au(0),
+        }
+    }
+
+    /// Returns the block-size of the content of this container laid out as a single column.
+    fn unfragmented_content_block_size(&mut self) -> Au {
+        let block_start_content_edge = self.block_flow.fragment.border_padding.block_start;
+        let mut block_end = block_start_content_edge;
+        for kid in self.block_flow.base.child_iter() {
+            let kid_base = flow::base(kid);
+            if kid_base.flags.contains(IS_ABSOLUTELY_POSITIONED) {
+                continue
+            }
+            block_end = max(block_end, kid_base.position.start.b + kid_base.position.size.block)
+        }
+        block_end - block_start_content_edge
+    }
+
+    /// Returns the smallest column block-size at which the content fits in the used number of
+    /// columns, or as close to it as balancing gets. CSS Multi-column § 7.1.
+    fn balanced_column_block_size(&mut self, content_block_size: Au) -> Au {
+        let column_count = self.column_count as i32;
+        let mut column_block_size = (content_block_size + Au(column_count - 1)) / column_count;
+        for _ in range(0, MAX_BALANCING_ITERATIONS) {
+            // Breaks push content down, so the columns have to be taller than an even split.
+            let growth = FragmentationContext::new(column_block_size)
+                .measure_breaks(&mut self.block_flow);
+            let excess = content_block_size + growth - column_block_size * column_count;
+            if excess <= Au(0) {
+                break
+            }
+            column_block_size = column_block_size + max(excess / column_count, Au::from_px(1))
+        }
+        column_block_size
+    }
+}
+
+/// Returns the used number of columns and the used column inline-size for the given content
+/// inline-size. CSS Multi-column § 3.4.
+fn used_column_count_and_inline_size(column_count: Option<u32>,
+                                     column_width: Option<Au>,
+                                     column_gap: Au,
+                                     available_inline_size: Au)
+                                     -> (uint, Au) {
+    let Au(available) = available_inline_size;
+    let Au(gap) = column_gap;
+    let column_count = match (column_count, column_width) {
+        (Some(count), None) => count as i32,
+        (count, Some(Au(width))) => {
+            let fitting_count = max(1, (available + gap) / max(1, width + gap));
+            match count {
+                Some(count) => max(1, min(count as i32, fitting_count)),
+                None => fitting_count,
+            }
+        }
+        (None, None) => 1,
+    };
+    let column_inline_size =
+        max(Au(0), (available_inline_size - column_gap * (column_count - 1)) / column_count);
+    (column_count as uint, column_inline_size)
+}
+
+impl Flow for MulticolFlow {
+    fn class(&self) -> FlowClass {
+        MulticolFlowClass
+    }
+
+    fn as_multicol<'a>(&'a mut self) -> &'a mut MulticolFlow {
+        self
+    }
+
+    fn as_block<'a>(&'a mut self) -> &'a mut BlockFlow {
+        &mut self.block_flow
+    }
+
+    fn as_immutable_block<'a>(&'a self) -> &'a BlockFlow {
+        &self.block_flow
+    }
+
+    fn bubble_inline_sizes(&mut self) {
+        self.block_flow.bubble_inline_sizes()
+    }
+
+    fn assign_inline_sizes(&mut self, layout_context: &LayoutContext) {
+        let _scope = layout_debug_scope!("multicol::assign_inline_sizes {:x}",
+                                         self.block_flow.base.debug_id());
+
+        if !self.block_flow.base.restyle_damage.intersects(REFLOW_OUT_OF_FLOW | REFLOW) {
+            return
+        }
+
+        self.block_flow.assign_inline_sizes(layout_context);
+
+        let content_inline_size = self.block_flow.fragment.border_box.size.inline -
+            self.block_flow.fragment.border_padding.inline_start_end();
+        let style = self.block_flow.fragment.style.clone();
+        let column_style = style.get_column();
+        self.column_gap = column_style.column_gap;
+        let (column_count, column_inline_size) =
+            used_column_count_and_inline_size(column_style.column_count,
+                                              column_style.column_width,
+                                              self.column_gap,
+                                              content_inline_size);
+        self.column_count = column_count;
+        self.column_inline_size = column_inline_size;
+
+        // The in-flow children are laid out in a single column, which fragmentation then breaks
+        // up. That moves them around, so they have to be laid out afresh every time we are.
+        for kid in self.block_flow.base.child_iter() {
+            if flow::base(kid).flags.contains(IS_ABSOLUTELY_POSITIONED) {
+                continue
+            }
+            flow::mut_base(kid).block_container_inline_size = column_inline_size;
+            kid.reflow_entire_document()
+        }
+    }
+
+    fn assign_block_size<'a>(&mut self, layout_context: &'a LayoutContext<'a>) {
+        let _scope = layout_debug_scope!("multicol::assign_block_size {:x}",
+                                         self.block_flow.base.debug_id());
+
+        let needs_reflow = self.block_flow.base.restyle_damage.contains(REFLOW);
+        self.block_flow.assign_block_size_block_base(layout_context, MarginsMayNotCollapse);
+        if !needs_reflow {
+            return
+        }
+
+        let content_block_size = self.unfragmented_content_block_size();
+        if content_block_size <= Au(0) {
+            self.filled_column_count = 0;
+            self.column_block_size = Au(0);
+            return
+        }
+
+        // Balance the columns, but never make them taller than an explicit block-size; if the
+        // content doesn't fit, it overflows into extra columns in the inline direction. With
+        // `column-fill: auto`, an explicit block-size is instead filled one column after another.
+        // CSS Multi-column § 7.1.
+        let is_absolutely_positioned =
+            self.block_flow.base.flags.contains(IS_ABSOLUTELY_POSITIONED);
+        let border_padding = self.block_flow.fragment.border_padding;
+        let has_auto_block_size = is_absolutely_positioned ||
+            self.block_flow.fragment.style().content_block_size() == LPA_Auto;
+        let column_block_size = if has_auto_block_size {
+            self.balanced_column_block_size(content_block_size)
+        } else {
+            let block_size = max(Au(0), self.block_flow.fragment.border_box.size.block -
+                                        border_padding.block_start_end());
+            let column_fill = self.block_flow.fragment.style().get_column().column_fill;
+            match column_fill {
+                column_fill::auto => block_size,
+                column_fill::balance => {
+                    min(self.balanced_column_block_size(content_block_size), block_size)
+                }
+            }
+        };
+
+        let fragmentation_context = FragmentationContext::new(column_block_size);
+        let growth = fragmentation_context.insert_breaks(&mut self.block_flow);
+        self.filled_column_count =
+            fragmentation_context.fragmentainer_index(content_block_size + growth - Au(1)) + 1;
+        let writing_mode = self.block_flow.base.writing_mode;
+        let column_pitch = self.column_inline_size + self.column_gap;
+        let column_offsets: Vec<LogicalSize<Au>> = range(0, self.filled_column_count).map(|k| {
+            LogicalSize::new(writing_mode, column_pitch * (k as i32), Au(0))
+        }).collect();
+        fragmentation_context.distribute(&mut self.block_flow, column_offsets.as_slice());
+        self.column_block_size = column_block_size;
+
+        if is_absolutely_positioned {
+            // Like blocks, store the content block-size for use in calculating the absolute
+            // flow's dimensions later.
+            self.block_flow.fragment.border_box.size.block = column_block_size;
+            return
+        }
+
+        if has_auto_block_size {
+            let block_size = column_block_size + border_padding.block_start_end();
+            self.block_flow.fragment.border_box.size.block = block_size;
+            self.block_flow.base.position.size.block = block_size;
+        }
+    }
+
+    fn assign_block_size_for_inorder_child_if_necessary<'a>(&mut self,
+                                                            layout_context: &'a LayoutContext<'a>)
+                                                            -> bool {
+        // Multi-column containers are block formatting contexts, so this translates floats past
+        // us.
+        self.block_flow.assign_block_size_for_inorder_child_if_necessary(layout_context)
+    }
+
+    fn compute_absolute_position(&mut self) {
+        self.block_flow.compute_absolute_position()
+    }
+
+    fn update_late_computed_inline_position_if_necessary(&mut self, inline_position: Au) {
+        self.block_flow.update_late_computed_inline_position_if_necessary(inline_position)
+    }
+
+    fn update_late_computed_block_position_if_necessary(&mut self, block_position: Au) {
+        self.block_flow.update_late_computed_block_position_if_necessary(block_position)
+    }
+
+    fn build_display_list(&mut self, layout_context: &LayoutContext) {
+        self.build_display_list_for_multicol(layout_context)
+    }
+
+    fn repair_style(&mut self, new_style: &Arc<ComputedValues>) {
+        self.block_flow.repair_style(new_style)
+    }
+
+    fn iterate_through_fragment_bounds(&self, iterator: &mut FragmentBoundsIterator) {
+        self.block_flow.iterate_through_fragment_bounds(iterator);
+    }
+}
+
+impl fmt::Show for MulticolFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MulticolFlow: {}", self.block_flow)
+    }
+}
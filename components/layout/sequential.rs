@@ -5,10 +5,14 @@
 //! Implements sequential traversals over the DOM and flow trees.
 
 use context::{LayoutContext, SharedLayoutContext};
-use flow::{Flow, MutableFlowUtils, PreorderFlowTraversal, PostorderFlowTraversal};
+use flow::{BlockFlowClass, FlexFlowClass, Flow, MulticolFlowClass, MutableFlowUtils};
+use flow::{PreorderFlowTraversal, PostorderFlowTraversal};
 use flow;
 use flow_ref::FlowRef;
 use fragment::FragmentBoundsIterator;
+use geom::Size2D;
+use gfx::display_list::OpaqueNode;
+use servo_util::geometry::Au;
 use servo_util::opts;
 use traversal::{BubbleISizes, RecalcStyleForNode, ConstructFlows};
 use traversal::{AssignBSizesAndStoreOverflow, AssignISizes};
@@ -106,3 +110,29 @@ pub fn iterate_through_flow_tree_fragment_bounds(root: &mut FlowRef,
 
     doit(root.deref_mut(), iterator);
 }
+
+/// Returns how far the contents of the block of the given node can be scrolled, or nothing if it
+/// has no block or is not a scroll container.
+pub fn scroll_range_of_node(root: &mut FlowRef, node: OpaqueNode) -> Size2D<Au> {
+    fn doit(flow: &mut Flow, node: OpaqueNode) -> Option<Size2D<Au>> {
+        match flow.class() {
+            BlockFlowClass | FlexFlowClass | MulticolFlowClass => {
+                let block = flow.as_block();
+                if block.fragment.node == node && block.is_scroll_container() {
+                    return Some(block.scroll_range())
+                }
+            }
+            _ => {}
+        }
+
+        for kid in flow::mut_base(flow).child_iter() {
+            match doit(kid, node) {
+                Some(range) => return Some(range),
+                None => {}
+            }
+        }
+        None
+    }
+
+    doit(root.deref_mut(), node).unwrap_or(Size2D(Au(0), Au(0)))
+}
@@ -10,21 +10,29 @@ use block::{BlockFlow, MarginsMayNotCollapse, ISizeAndMarginsComputer};
 use block::{ISizeConstraintInput, ISizeConstraintSolution};
 use construct::FlowConstructor;
 use context::LayoutContext;
+use display_list_builder::{BlockFlowDisplayListBuilding, BlockLevel, HiddenBorderPaintingMode};
 use floats::FloatKind;
 use flow::{Flow, FlowClass, IMPACTED_BY_LEFT_FLOATS, IMPACTED_BY_RIGHT_FLOATS, ImmutableFlowUtils};
-use flow::{TableFlowClass};
-use fragment::{Fragment, FragmentBoundsIterator};
+use flow::{mod, BaseFlow, TableFlowClass};
+use fragment::{Fragment, FragmentBoundsIterator, TableColumnFragment};
 use layout_debug;
 use model::{IntrinsicISizes, IntrinsicISizesContribution};
+use table_cell::TableCellFlow;
+use table_row::{CellIntrinsicInlineSize, TableRowFlow};
+use table_rowgroup::assign_block_sizes_to_rows;
 use table_wrapper::{TableLayout, FixedLayout, AutoLayout};
 use wrapper::ThreadSafeLayoutNode;
 
+use geom::{SideOffsets2D, Size2D};
+use serialize::{Encodable, Encoder};
 use servo_util::geometry::Au;
-use servo_util::logical_geometry::LogicalRect;
-use std::cmp::max;
+use servo_util::logical_geometry::{LogicalMargin, LogicalRect, LogicalSize, WritingMode};
+use std::cmp::{max, min};
 use std::fmt;
-use style::{ComputedValues, CSSFloat};
-use style::computed_values::{LPA_Auto, LPA_Length, LPA_Percentage, table_layout};
+use std::u32;
+use style::{ComputedValues, CSSFloat, RGBA};
+use style::computed_values::{LPA_Auto, LPA_Length, LPA_Percentage, border_collapse};
+use style::computed_values::{border_spacing, border_style, table_layout};
 use sync::Arc;
 
 /// A table flow corresponded to the table's internal table fragment under a table wrapper flow.
@@ -103,12 +111,7 @@ impl TableFlow {
         let mut total_inline_sizes = IntrinsicISizes::new();
         for (parent_sizes, child_sizes) in parent_inline_sizes.iter_mut()
                                                               .zip(child_inline_sizes.iter()) {
-            *parent_sizes = ColumnInlineSize {
-                minimum_length: max(parent_sizes.minimum_length, child_sizes.minimum_length),
-                percentage: parent_sizes.greatest_percentage(child_sizes),
-                preferred: max(parent_sizes.preferred, child_sizes.preferred),
-                constrained: parent_sizes.constrained || child_sizes.constrained
-            };
+            *parent_sizes = parent_sizes.union(child_sizes);
 
             total_inline_sizes.minimum_inline_size = total_inline_sizes.minimum_inline_size +
                 parent_sizes.minimum_length;
@@ -126,7 +129,50 @@ impl TableFlow {
     /// methods
     #[inline(always)]
     fn assign_block_size_table_base<'a>(&mut self, layout_context: &'a LayoutContext<'a>) {
+        // Rows that are direct children of the table are sized like the rows of a row group, so
+        // that cells can span several of them.
+        let spacing = border_spacing(self.block_flow.fragment.style()).block;
+        for &(first_row_index, row_count) in runs_of_direct_rows(&mut self.block_flow.base).iter() {
+            assign_block_sizes_to_rows(&mut self.block_flow.base,
+                                       first_row_index,
+                                       row_count,
+                                       spacing,
+                                       layout_context);
+        }
+
         self.block_flow.assign_block_size_block_base(layout_context, MarginsMayNotCollapse);
+
+        // In the separated borders model, move the rows apart and grow the table to make room for
+        // the spacing around and between them.
+        if spacing == Au(0) {
+            return
+        }
+        let mut offset = Au(0);
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_table_rowgroup() || kid.is_table_row() {
+                offset = offset + spacing;
+                let kid_base = flow::mut_base(kid);
+                kid_base.position.start.b = kid_base.position.start.b + offset;
+            }
+        }
+        if offset == Au(0) {
+            return
+        }
+        offset = offset + spacing;
+        self.block_flow.fragment.border_box.size.block =
+            self.block_flow.fragment.border_box.size.block + offset;
+        self.block_flow.base.position.size.block =
+            self.block_flow.base.position.size.block + offset;
+    }
+
+    /// Returns the total inline-size of the spacing around and between the columns of this table
+    /// in the separated borders model.
+    pub fn total_inline_border_spacing(&self) -> Au {
+        let column_count = self.column_inline_sizes.len() as i32;
+        if column_count == 0 {
+            return Au(0)
+        }
+        border_spacing(self.block_flow.fragment.style()).inline * (column_count + 1)
     }
 }
 
@@ -159,6 +205,20 @@ impl Flow for TableFlow {
         let _scope = layout_debug_scope!("table::bubble_inline_sizes {:x}",
                                          self.block_flow.base.debug_id());
 
+        // Rows that are direct children of the table have not had their cells placed into columns
+        // yet. Each run of consecutive ones shares a grid, as if it were a row group, so that
+        // `rowspan` works across them.
+        for &(first_row_index, row_count) in runs_of_direct_rows(&mut self.block_flow.base).iter() {
+            let mut grid = TableGrid::new();
+            for kid in self.block_flow.base.child_iter().skip(first_row_index).take(row_count) {
+                grid.add_row(kid.as_table_row());
+            }
+            let (column_inline_sizes, _) = grid.finish();
+            for kid in self.block_flow.base.child_iter().skip(first_row_index).take(row_count) {
+                *kid.column_inline_sizes() = column_inline_sizes.clone()
+            }
+        }
+
         let mut computation = IntrinsicISizesContribution::new();
         let mut did_first_row = false;
         for kid in self.block_flow.base.child_iter() {
@@ -224,7 +284,14 @@ impl Flow for TableFlow {
             }
         }
 
-        self.block_flow.base.intrinsic_inline_sizes = computation.finish()
+        // Add the spacing around and between the columns in the separated borders model.
+        let mut intrinsic_inline_sizes = computation.finish();
+        let spacing = self.total_inline_border_spacing();
+        intrinsic_inline_sizes.minimum_inline_size =
+            intrinsic_inline_sizes.minimum_inline_size + spacing;
+        intrinsic_inline_sizes.preferred_inline_size =
+            intrinsic_inline_sizes.preferred_inline_size + spacing;
+        self.block_flow.base.intrinsic_inline_sizes = intrinsic_inline_sizes
     }
 
     /// Recursively (top-down) determines the actual inline-size of child contexts and fragments.
@@ -248,6 +315,14 @@ impl Flow for TableFlow {
             }
         }
 
+        // Resolve border conflicts first, since they determine the borders of the cells.
+        match self.block_flow.fragment.style().get_inheritedtable().border_collapse {
+            border_collapse::separate => {
+                for_each_table_cell(self, |cell| cell.collapsed_borders = None)
+            }
+            border_collapse::collapse => collapse_borders(self),
+        }
+
         let inline_size_computer = InternalTable;
 
         inline_size_computer.compute_used_inline_size(&mut self.block_flow,
@@ -258,21 +333,22 @@ impl Flow for TableFlow {
         let padding_and_borders = self.block_flow.fragment.border_padding.inline_start_end();
         let content_inline_size =
             self.block_flow.fragment.border_box.size.inline - padding_and_borders;
+        let columns_inline_size = content_inline_size - self.total_inline_border_spacing();
 
         match self.table_layout {
             FixedLayout => {
                 // In fixed table layout, we distribute extra space among the unspecified columns
                 // if there are any, or among all the columns if all are specified.
-                if total_column_inline_size < content_inline_size &&
+                if total_column_inline_size < columns_inline_size &&
                         num_unspecified_inline_sizes == 0 {
-                    let ratio = content_inline_size.to_subpx() / total_column_inline_size.to_subpx();
+                    let ratio = columns_inline_size.to_subpx() / total_column_inline_size.to_subpx();
                     for column_inline_size in self.column_inline_sizes.iter_mut() {
                         column_inline_size.minimum_length = column_inline_size.minimum_length.scale_by(ratio);
                         column_inline_size.percentage = 0.0;
                     }
                 } else if num_unspecified_inline_sizes != 0 {
                     let extra_column_inline_size =
-                        (content_inline_size - total_column_inline_size) /
+                        (columns_inline_size - total_column_inline_size) /
                         num_unspecified_inline_sizes;
                     for column_inline_size in self.column_inline_sizes.iter_mut() {
                         if column_inline_size.minimum_length == Au(0) &&
@@ -319,7 +395,15 @@ impl Flow for TableFlow {
     }
 
     fn build_display_list(&mut self, layout_context: &LayoutContext) {
-        self.block_flow.build_display_list(layout_context);
+        // In the collapsing border model, the cells paint the borders of the table.
+        match self.block_flow.fragment.style().get_inheritedtable().border_collapse {
+            border_collapse::separate => self.block_flow.build_display_list(layout_context),
+            border_collapse::collapse => {
+                self.block_flow.build_display_list_for_block(layout_context,
+                                                             BlockLevel,
+                                                             HiddenBorderPaintingMode)
+            }
+        }
     }
 
     fn repair_style(&mut self, new_style: &Arc<ComputedValues>) {
@@ -404,4 +488,711 @@ impl ColumnInlineSize {
             other.percentage
         }
     }
+
+    /// Returns the column inline size that satisfies the constraints of both `self` and `other`.
+    pub fn union(&self, other: &ColumnInlineSize) -> ColumnInlineSize {
+        ColumnInlineSize {
+            minimum_length: max(self.minimum_length, other.minimum_length),
+            percentage: self.greatest_percentage(other),
+            preferred: max(self.preferred, other.preferred),
+            constrained: self.constrained || other.constrained,
+        }
+    }
+
+    /// Returns a column inline size with no constraints at all, used for columns that no cell
+    /// starting in them has contributed to yet.
+    fn empty() -> ColumnInlineSize {
+        ColumnInlineSize {
+            preferred: Au(0),
+            minimum_length: Au(0),
+            percentage: 0.0,
+            constrained: false,
+        }
+    }
+}
+
+/// Returns the index of the first row and the number of rows of each run of consecutive rows
+/// among the children of the given table. Such runs are laid out as if they were wrapped in an
+/// anonymous row group, per CSS 2.1 § 17.2.1.
+fn runs_of_direct_rows(table: &mut BaseFlow) -> Vec<(uint, uint)> {
+    let mut runs: Vec<(uint, uint)> = Vec::new();
+    let mut previous_kid_is_row = false;
+    for (kid_index, kid) in table.child_iter().enumerate() {
+        let kid_is_row = kid.is_table_row();
+        if kid_is_row {
+            if previous_kid_is_row {
+                let (first_row_index, row_count) = runs.pop().unwrap();
+                runs.push((first_row_index, row_count + 1))
+            } else {
+                runs.push((kid_index, 1))
+            }
+        }
+        previous_kid_is_row = kid_is_row
+    }
+    runs
+}
+
+/// Places the cells of consecutive rows into the slots of the table grid, taking cells that span
+/// several columns or rows into account, and accumulates the intrinsic inline sizes of the
+/// resulting columns. See HTML5 § 4.9.12.1, "Forming a table".
+pub struct TableGrid {
+    /// The intrinsic inline sizes of each column discovered so far.
+    column_inline_sizes: Vec<ColumnInlineSize>,
+    /// For each column, the number of rows (including the current one) that are still occupied by
+    /// a cell spanning down from a previous row.
+    occupied_row_counts: Vec<u32>,
+    /// Cells that span more than one column, along with the index of the first column that each
+    /// occupies. Their inline sizes are distributed once all single-column cells have been seen.
+    spanning_cells: Vec<(uint, CellIntrinsicInlineSize)>,
+}
+
+impl TableGrid {
+    pub fn new() -> TableGrid {
+        TableGrid {
+            column_inline_sizes: Vec::new(),
+            occupied_row_counts: Vec::new(),
+            spanning_cells: Vec::new(),
+        }
+    }
+
+    /// Places the cells of the given row into the next row of the grid, records the column that
+    /// each cell starts in, and merges the cells' inline sizes into the columns they occupy.
+    pub fn add_row(&mut self, row: &mut TableRowFlow) {
+        let TableRowFlow {
+            ref mut block_flow,
+            ref cell_intrinsic_inline_sizes,
+            ..
+        } = *row;
+
+        let mut column_index = 0;
+        for (kid, cell) in block_flow.base.child_iter().zip(cell_intrinsic_inline_sizes.iter()) {
+            // Skip the slots taken by cells spanning down from previous rows.
+            while column_index < self.occupied_row_counts.len() &&
+                    self.occupied_row_counts[column_index] > 0 {
+                column_index += 1
+            }
+            kid.as_table_cell().column_index = column_index;
+
+            let column_span = cell.column_span as uint;
+            while self.column_inline_sizes.len() < column_index + column_span {
+                self.column_inline_sizes.push(ColumnInlineSize::empty());
+                self.occupied_row_counts.push(0);
+            }
+
+            // A row span of zero means that the cell spans all remaining rows.
+            let occupied_row_count = match cell.row_span {
+                0 => u32::MAX,
+                row_span => row_span,
+            };
+            for occupied_rows in self.occupied_row_counts
+                                     .slice_mut(column_index, column_index + column_span)
+                                     .iter_mut() {
+                *occupied_rows = occupied_row_count
+            }
+
+            if column_span == 1 {
+                let column_inline_size = self.column_inline_sizes.get_mut(column_index);
+                *column_inline_size = column_inline_size.union(&cell.column_size)
+            } else {
+                self.spanning_cells.push((column_index, *cell))
+            }
+            column_index += column_span
+        }
+
+        // Move on to the next row.
+        for occupied_rows in self.occupied_row_counts.iter_mut() {
+            if *occupied_rows > 0 {
+                *occupied_rows -= 1
+            }
+        }
+    }
+
+    /// Distributes the inline sizes of cells spanning several columns among those columns and
+    /// returns the final column inline sizes, along with the intrinsic inline sizes of the grid
+    /// as a whole.
+    pub fn finish(mut self) -> (Vec<ColumnInlineSize>, IntrinsicISizes) {
+        // Handle narrower spans first, so that the distribution of wider ones can take them into
+        // account.
+        self.spanning_cells.sort_by(|&(_, ref a), &(_, ref b)| a.column_span.cmp(&b.column_span));
+        for &(column_index, ref cell) in self.spanning_cells.iter() {
+            let column_end = column_index + cell.column_span as uint;
+            distribute_spanning_cell_inline_size(
+                self.column_inline_sizes.slice_mut(column_index, column_end),
+                &cell.column_size)
+        }
+
+        let mut intrinsic_inline_sizes = IntrinsicISizes::new();
+        for column_inline_size in self.column_inline_sizes.iter() {
+            intrinsic_inline_sizes.minimum_inline_size =
+                intrinsic_inline_sizes.minimum_inline_size + column_inline_size.minimum_length;
+            intrinsic_inline_sizes.preferred_inline_size =
+                intrinsic_inline_sizes.preferred_inline_size + column_inline_size.preferred;
+        }
+        (self.column_inline_sizes, intrinsic_inline_sizes)
+    }
+}
+
+/// Grows the given columns, which are all spanned by a single cell, evenly until together they
+/// satisfy the minimum, preferred, and percentage inline sizes of that cell. Any rounding error is
+/// given to the last column.
+fn distribute_spanning_cell_inline_size(columns: &mut [ColumnInlineSize],
+                                        cell_inline_size: &ColumnInlineSize) {
+    let column_count = columns.len() as i32;
+    let (mut minimum_length, mut preferred, mut percentage) = (Au(0), Au(0), 0.0);
+    for column in columns.iter() {
+        minimum_length = minimum_length + column.minimum_length;
+        preferred = preferred + column.preferred;
+        percentage += column.percentage;
+    }
+
+    let extra_minimum_length = max(cell_inline_size.minimum_length - minimum_length, Au(0));
+    let extra_preferred = max(cell_inline_size.preferred - preferred, Au(0));
+    let extra_percentage = if cell_inline_size.percentage > percentage {
+        cell_inline_size.percentage - percentage
+    } else {
+        0.0
+    };
+
+    let last_column_index = columns.len() - 1;
+    for (i, column) in columns.iter_mut().enumerate() {
+        let (minimum_length_share, preferred_share) = if i == last_column_index {
+            (extra_minimum_length - (extra_minimum_length / column_count) * (column_count - 1),
+             extra_preferred - (extra_preferred / column_count) * (column_count - 1))
+        } else {
+            (extra_minimum_length / column_count, extra_preferred / column_count)
+        };
+        column.minimum_length = column.minimum_length + minimum_length_share;
+        column.preferred = max(column.preferred + preferred_share, column.minimum_length);
+        column.percentage += extra_percentage / (column_count as CSSFloat);
+        column.constrained = column.constrained || cell_inline_size.constrained;
+    }
+}
+
+/// Returns the spacing that the given table style puts between adjacent cells, and between the
+/// outermost cells and the edges of the table, per CSS 2.1 § 17.6.1. In the collapsing border
+/// model there is no such spacing.
+///
+/// `border-spacing` is specified physically; the result is mapped into the writing mode of the
+/// table, so that in vertical tables the vertical spacing separates columns along the inline axis.
+pub fn border_spacing(style: &ComputedValues) -> LogicalSize<Au> {
+    let inherited_table = style.get_inheritedtable();
+    match inherited_table.border_collapse {
+        border_collapse::separate => {
+            let border_spacing::T { horizontal, vertical } = inherited_table.border_spacing;
+            LogicalSize::from_physical(style.writing_mode, Size2D(horizontal, vertical))
+        }
+        border_collapse::collapse => LogicalSize::zero(style.writing_mode),
+    }
+}
+
+/// The table part that a border in the collapsing border model came from. When two borders of
+/// the same width and style conflict, the one from the later variant here wins, per CSS 2.1 §
+/// 17.6.2.1.
+#[deriving(Clone, PartialEq, Show)]
+pub enum CollapsedBorderProvenance {
+    FromTable,
+    FromTableColumnGroup,
+    FromTableColumn,
+    FromTableRowGroup,
+    FromTableRow,
+    FromTableCell,
+}
+
+/// A border in the collapsing border model, together with the table part it came from, as used
+/// for border conflict resolution.
+#[deriving(Clone)]
+pub struct CollapsedBorder {
+    /// The style of the border.
+    pub style: border_style::T,
+    /// The width of the border. For a border that a cell shares with one of its neighbors, this
+    /// is only the portion of the border that lies in the cell.
+    pub width: Au,
+    /// The color of the border.
+    pub color: RGBA,
+    /// The table part that the border came from.
+    pub provenance: CollapsedBorderProvenance,
+}
+
+impl<E, S: Encoder<E>> Encodable<S, E> for CollapsedBorder {
+    fn encode(&self, e: &mut S) -> Result<(), E> {
+        e.emit_struct("collapsed_border", 0, |e| {
+            try!(e.emit_struct_field("style", 0, |e| format!("{}", self.style).encode(e)))
+            try!(e.emit_struct_field("width", 1, |e| self.width.encode(e)))
+            e.emit_struct_field("color", 2, |e| format!("{}", self.color).encode(e))
+        })
+    }
+}
+
+impl CollapsedBorder {
+    /// Returns the four borders that the given style specifies for a table part, as candidates for
+    /// conflict resolution.
+    pub fn from_style(style: &ComputedValues, provenance: CollapsedBorderProvenance)
+                      -> LogicalMargin<CollapsedBorder> {
+        let border = style.get_border();
+        LogicalMargin::from_physical(style.writing_mode, SideOffsets2D::new(
+            CollapsedBorder {
+                style: border.border_top_style,
+                width: border.border_top_width,
+                color: style.resolve_color(border.border_top_color),
+                provenance: provenance,
+            },
+            CollapsedBorder {
+                style: border.border_right_style,
+                width: border.border_right_width,
+                color: style.resolve_color(border.border_right_color),
+                provenance: provenance,
+            },
+            CollapsedBorder {
+                style: border.border_bottom_style,
+                width: border.border_bottom_width,
+                color: style.resolve_color(border.border_bottom_color),
+                provenance: provenance,
+            },
+            CollapsedBorder {
+                style: border.border_left_style,
+                width: border.border_left_width,
+                color: style.resolve_color(border.border_left_color),
+                provenance: provenance,
+            }))
+    }
+
+    /// Returns a border of style `none`, used for table parts that are absent, such as the row
+    /// group of a row that is a direct child of its table, or the column of an empty column group.
+    fn none(provenance: CollapsedBorderProvenance) -> CollapsedBorder {
+        CollapsedBorder {
+            style: border_style::none,
+            width: Au(0),
+            color: RGBA {
+                red: 0.0,
+                green: 0.0,
+                blue: 0.0,
+                alpha: 0.0,
+            },
+            provenance: provenance,
+        }
+    }
+
+    /// Replaces this border with `other` if `other` wins the conflict between them, per the rules
+    /// in CSS 2.1 § 17.6.2.1. When the two are equally specific, this border is kept, so callers
+    /// must combine borders starting with the one furthest toward the top and the left.
+    pub fn combine(&mut self, other: &CollapsedBorder) {
+        match (self.style, other.style) {
+            (border_style::hidden, _) => {}
+            (_, border_style::hidden) => *self = *other,
+            (_, border_style::none) => {}
+            (border_style::none, _) => *self = *other,
+            _ if self.width > other.width => {}
+            _ if self.width < other.width => *self = *other,
+            (this_style, other_style) if style_priority(this_style) >
+                    style_priority(other_style) => {}
+            (this_style, other_style) if style_priority(this_style) <
+                    style_priority(other_style) => *self = *other,
+            _ if (self.provenance as int) >= (other.provenance as int) => {}
+            _ => *self = *other,
+        }
+    }
+
+    /// Returns the portion of this border that lies in a cell on the given side of it. Borders
+    /// between two cells are centered on the grid line between them; borders on the edge of the
+    /// table lie entirely within the cell.
+    fn portion(&self, interior: bool, start_side: bool) -> CollapsedBorder {
+        let mut portion = *self;
+        if interior {
+            let half_width = self.width / 2;
+            portion.width = if start_side {
+                half_width
+            } else {
+                self.width - half_width
+            }
+        }
+        portion
+    }
+}
+
+/// Returns the priority of the given border style when resolving conflicts between borders of the
+/// same width, per CSS 2.1 § 17.6.2.1.
+fn style_priority(style: border_style::T) -> int {
+    match style {
+        border_style::double => 8,
+        border_style::solid => 7,
+        border_style::dashed => 6,
+        border_style::dotted => 5,
+        border_style::ridge => 4,
+        border_style::outset => 3,
+        border_style::groove => 2,
+        border_style::inset => 1,
+        border_style::none | border_style::hidden => 0,
+    }
+}
+
+/// A row of a table in the collapsing border model, as seen by border conflict resolution.
+struct CollapsingRow {
+    /// The borders of the row itself.
+    border: LogicalMargin<CollapsedBorder>,
+    /// The borders of the row group that the row belongs to.
+    group_border: LogicalMargin<CollapsedBorder>,
+    /// Whether this is the first row of its row group.
+    first_in_group: bool,
+    /// Whether this is the last row of its row group.
+    last_in_group: bool,
+    /// The cells that start in this row.
+    cells: Vec<CollapsingCell>,
+}
+
+impl CollapsingRow {
+    fn new(row: &mut Flow,
+           group_border: LogicalMargin<CollapsedBorder>,
+           row_index_in_group: uint,
+           row_count_in_group: uint)
+           -> CollapsingRow {
+        let border = CollapsedBorder::from_style(row.as_block().fragment.style(), FromTableRow);
+        let remaining_row_count = row_count_in_group - row_index_in_group;
+        let mut cells = Vec::new();
+        for kid in flow::mut_base(row).child_iter() {
+            let cell = kid.as_table_cell();
+            cells.push(CollapsingCell {
+                column_index: cell.column_index,
+                column_span: cell.column_span as uint,
+                row_span: match cell.row_span {
+                    0 => remaining_row_count,
+                    row_span => min(row_span as uint, remaining_row_count),
+                },
+                border: CollapsedBorder::from_style(cell.block_flow.fragment.style(),
+                                                    FromTableCell),
+                writing_mode: cell.block_flow.base.writing_mode,
+            })
+        }
+        CollapsingRow {
+            border: border,
+            group_border: group_border,
+            first_in_group: row_index_in_group == 0,
+            last_in_group: row_index_in_group + 1 == row_count_in_group,
+            cells: cells,
+        }
+    }
+}
+
+/// A column of a table in the collapsing border model, as seen by border conflict resolution.
+struct CollapsingColumn {
+    /// The borders of the column itself.
+    border: LogicalMargin<CollapsedBorder>,
+    /// The borders of the column group that the column belongs to.
+    group_border: LogicalMargin<CollapsedBorder>,
+    /// Whether this is the first column of its column group.
+    first_in_group: bool,
+    /// Whether this is the last column of its column group.
+    last_in_group: bool,
+}
+
+impl CollapsingColumn {
+    /// Adds the border of the block-start or block-end side of this column, and of its column
+    /// group, to `border`. The column only has borders on these sides at the edges of the table.
+    fn combine_block_side(&self, border: &mut CollapsedBorder, block_start: bool) {
+        if block_start {
+            border.combine(&self.border.block_start);
+            border.combine(&self.group_border.block_start);
+        } else {
+            border.combine(&self.border.block_end);
+            border.combine(&self.group_border.block_end);
+        }
+    }
+
+    /// Adds the border of the inline-start side of this column, and of its column group if the
+    /// column is the first of the group, to `border`.
+    fn combine_inline_start(&self, border: &mut CollapsedBorder) {
+        border.combine(&self.border.inline_start);
+        if self.first_in_group {
+            border.combine(&self.group_border.inline_start);
+        }
+    }
+
+    /// Adds the border of the inline-end side of this column, and of its column group if the
+    /// column is the last of the group, to `border`.
+    fn combine_inline_end(&self, border: &mut CollapsedBorder) {
+        border.combine(&self.border.inline_end);
+        if self.last_in_group {
+            border.combine(&self.group_border.inline_end);
+        }
+    }
+}
+
+/// Gathers the borders of the columns and column groups of the given table. Columns that no
+/// column element describes are absent from the result.
+fn collapsing_columns(table: &mut TableFlow) -> Vec<CollapsingColumn> {
+    let mut columns = Vec::new();
+    for kid in table.block_flow.base.child_iter() {
+        if !kid.is_table_colgroup() {
+            continue
+        }
+        let colgroup = kid.as_table_colgroup();
+        let group_fragment = colgroup.fragment.as_ref().unwrap();
+        let group_border = CollapsedBorder::from_style(group_fragment.style(),
+                                                       FromTableColumnGroup);
+        let first_column_index = columns.len();
+        for col in colgroup.cols.iter() {
+            // A column group without column elements gets a column made from the group itself,
+            // which must not contribute the borders of the group a second time.
+            let border = if col.node == group_fragment.node {
+                LogicalMargin::new_all_same(col.style().writing_mode,
+                                            CollapsedBorder::none(FromTableColumn))
+            } else {
+                CollapsedBorder::from_style(col.style(), FromTableColumn)
+            };
+            let span = match col.specific {
+                TableColumnFragment(ref info) => max(info.span, 1),
+                _ => 1,
+            };
+            for _ in range(0, span) {
+                columns.push(CollapsingColumn {
+                    border: border,
+                    group_border: group_border,
+                    first_in_group: columns.len() == first_column_index,
+                    last_in_group: false,
+                })
+            }
+        }
+        if columns.len() > first_column_index {
+            let last_column_index = columns.len() - 1;
+            columns.get_mut(last_column_index).last_in_group = true
+        }
+    }
+    columns
+}
+
+/// A cell of a table in the collapsing border model, as seen by border conflict resolution.
+struct CollapsingCell {
+    /// The index of the first column that the cell occupies.
+    column_index: uint,
+    /// The number of columns that the cell spans.
+    column_span: uint,
+    /// The number of rows that the cell spans, clipped to the end of its row group.
+    row_span: uint,
+    /// The borders of the cell itself.
+    border: LogicalMargin<CollapsedBorder>,
+    /// The writing mode of the cell.
+    writing_mode: WritingMode,
+}
+
+/// Returns the borders of the cell occupying the given slot of the table grid, if there is one.
+fn border_of_cell_in_slot(rows: &[CollapsingRow],
+                          slots: &[Vec<Option<(uint, uint)>>],
+                          row_index: uint,
+                          column_index: uint)
+                          -> Option<LogicalMargin<CollapsedBorder>> {
+    slots[row_index][column_index].map(|(row_index, cell_index)| {
+        rows[row_index].cells[cell_index].border
+    })
+}
+
+/// Resolves the conflicts between the borders of the given table and of its row groups, rows and
+/// cells in the collapsing border model, and gives each cell the portions of the resulting borders
+/// that it is responsible for painting. See CSS 2.1 § 17.6.2.
+fn collapse_borders(table: &mut TableFlow) {
+    let table_border = CollapsedBorder::from_style(table.block_flow.fragment.style(), FromTable);
+    let writing_mode = table.block_flow.base.writing_mode;
+    let columns = collapsing_columns(table);
+
+    // Gather the borders of every table part, row by row. Each run of rows that are direct children
+    // of the table forms an anonymous row group without borders.
+    let runs = runs_of_direct_rows(&mut table.block_flow.base);
+    let mut rows = Vec::new();
+    for (kid_index, kid) in table.block_flow.base.child_iter().enumerate() {
+        if kid.is_table_rowgroup() {
+            let group_border = CollapsedBorder::from_style(kid.as_block().fragment.style(),
+                                                           FromTableRowGroup);
+            let row_count = flow::base(kid).children.len();
+            for (row_index, row) in flow::mut_base(kid).child_iter().enumerate() {
+                rows.push(CollapsingRow::new(row, group_border, row_index, row_count))
+            }
+        } else if kid.is_table_row() {
+            let group_border =
+                LogicalMargin::new_all_same(writing_mode, CollapsedBorder::none(FromTableRowGroup));
+            let &(first_row_index, row_count) = runs.iter().find(|&&(first_row_index, row_count)| {
+                kid_index >= first_row_index && kid_index < first_row_index + row_count
+            }).unwrap();
+            rows.push(CollapsingRow::new(kid, group_border, kid_index - first_row_index, row_count))
+        }
+    }
+
+    // Place the cells into the slots of the table grid.
+    let mut column_count = table.column_inline_sizes.len();
+    for row in rows.iter() {
+        for cell in row.cells.iter() {
+            column_count = max(column_count, cell.column_index + cell.column_span)
+        }
+    }
+    let mut slots = Vec::from_fn(rows.len(), |_| Vec::from_elem(column_count, None));
+    for (row_index, row) in rows.iter().enumerate() {
+        for (cell_index, cell) in row.cells.iter().enumerate() {
+            for slot_row in slots.slice_mut(row_index, row_index + cell.row_span).iter_mut() {
+                for slot in slot_row.slice_mut(cell.column_index,
+                                               cell.column_index + cell.column_span)
+                                    .iter_mut() {
+                    *slot = Some((row_index, cell_index))
+                }
+            }
+        }
+    }
+
+    // Resolve each side of each cell against the borders it touches. Candidates are combined
+    // starting with the ones furthest toward the top and the left, so that a border shared by two
+    // cells resolves to the same winner for both of them.
+    let mut collapsed_borders = Vec::new();
+    for (row_index, row) in rows.iter().enumerate() {
+        for cell in row.cells.iter() {
+            let last_row_index = row_index + cell.row_span - 1;
+            let last_column_index = cell.column_index + cell.column_span - 1;
+            let last_row = &rows[last_row_index];
+
+            let mut block_start = if row_index == 0 {
+                let mut block_start = table_border.block_start;
+                for column in columns.iter().skip(cell.column_index).take(cell.column_span) {
+                    column.combine_block_side(&mut block_start, true)
+                }
+                block_start
+            } else {
+                let previous_row = &rows[row_index - 1];
+                let mut block_start = previous_row.border.block_end;
+                if row.first_in_group {
+                    block_start.combine(&previous_row.group_border.block_end);
+                }
+                for column_index in range(cell.column_index, last_column_index + 1) {
+                    match border_of_cell_in_slot(rows.as_slice(),
+                                                 slots.as_slice(),
+                                                 row_index - 1,
+                                                 column_index) {
+                        Some(border) => block_start.combine(&border.block_end),
+                        None => {}
+                    }
+                }
+                block_start
+            };
+            block_start.combine(&cell.border.block_start);
+            block_start.combine(&row.border.block_start);
+            if row.first_in_group {
+                block_start.combine(&row.group_border.block_start);
+            }
+
+            let mut block_end = cell.border.block_end;
+            block_end.combine(&last_row.border.block_end);
+            if last_row.last_in_group {
+                block_end.combine(&last_row.group_border.block_end);
+            }
+            if last_row_index + 1 == rows.len() {
+                for column in columns.iter().skip(cell.column_index).take(cell.column_span) {
+                    column.combine_block_side(&mut block_end, false)
+                }
+                block_end.combine(&table_border.block_end);
+            } else {
+                let next_row = &rows[last_row_index + 1];
+                for column_index in range(cell.column_index, last_column_index + 1) {
+                    match border_of_cell_in_slot(rows.as_slice(),
+                                                 slots.as_slice(),
+                                                 last_row_index + 1,
+                                                 column_index) {
+                        Some(border) => block_end.combine(&border.block_start),
+                        None => {}
+                    }
+                }
+                block_end.combine(&next_row.border.block_start);
+                if next_row.first_in_group {
+                    block_end.combine(&next_row.group_border.block_start);
+                }
+            }
+
+            let mut inline_start = cell.border.inline_start;
+            let mut inline_end = cell.border.inline_end;
+            for spanned_row in rows.slice(row_index, last_row_index + 1).iter() {
+                if cell.column_index == 0 {
+                    inline_start.combine(&spanned_row.border.inline_start);
+                    inline_start.combine(&spanned_row.group_border.inline_start);
+                    inline_start.combine(&table_border.inline_start);
+                }
+                if last_column_index + 1 == column_count {
+                    inline_end.combine(&spanned_row.border.inline_end);
+                    inline_end.combine(&spanned_row.group_border.inline_end);
+                    inline_end.combine(&table_border.inline_end);
+                }
+            }
+            for spanned_row_index in range(row_index, last_row_index + 1) {
+                if cell.column_index != 0 {
+                    match border_of_cell_in_slot(rows.as_slice(),
+                                                 slots.as_slice(),
+                                                 spanned_row_index,
+                                                 cell.column_index - 1) {
+                        Some(border) => {
+                            let mut shared_border = border.inline_end;
+                            shared_border.combine(&inline_start);
+                            inline_start = shared_border
+                        }
+                        None => {}
+                    }
+                }
+                if last_column_index + 1 != column_count {
+                    match border_of_cell_in_slot(rows.as_slice(),
+                                                 slots.as_slice(),
+                                                 spanned_row_index,
+                                                 last_column_index + 1) {
+                        Some(border) => inline_end.combine(&border.inline_start),
+                        None => {}
+                    }
+                }
+            }
+
+            // The borders of the columns and column groups on either side of the edges of the
+            // cell. As with cells, those of the column before an edge are combined first.
+            match columns.as_slice().get(cell.column_index) {
+                Some(column) => column.combine_inline_start(&mut inline_start),
+                None => {}
+            }
+            if cell.column_index != 0 {
+                match columns.as_slice().get(cell.column_index - 1) {
+                    Some(previous_column) => {
+                        let mut shared_border = CollapsedBorder::none(FromTableColumn);
+                        previous_column.combine_inline_end(&mut shared_border);
+                        shared_border.combine(&inline_start);
+                        inline_start = shared_border
+                    }
+                    None => {}
+                }
+            }
+            match columns.as_slice().get(last_column_index) {
+                Some(column) => column.combine_inline_end(&mut inline_end),
+                None => {}
+            }
+            match columns.as_slice().get(last_column_index + 1) {
+                Some(next_column) => next_column.combine_inline_start(&mut inline_end),
+                None => {}
+            }
+
+            collapsed_borders.push(LogicalMargin::new(
+                cell.writing_mode,
+                block_start.portion(row_index != 0, true),
+                inline_end.portion(last_column_index + 1 != column_count, false),
+                block_end.portion(last_row_index + 1 != rows.len(), false),
+                inline_start.portion(cell.column_index != 0, true)))
+        }
+    }
+
+    // Hand the resolved borders to the cells, visiting them in the same order as above.
+    let mut collapsed_borders = collapsed_borders.into_iter();
+    for_each_table_cell(table, |cell| cell.collapsed_borders = collapsed_borders.next());
+}
+
+/// Calls the given function on each cell of the given table, row group by row group and row by
+/// row.
+fn for_each_table_cell(table: &mut TableFlow, callback: |&mut TableCellFlow|) {
+    for kid in table.block_flow.base.child_iter() {
+        if kid.is_table_rowgroup() {
+            for row in flow::mut_base(kid).child_iter() {
+                for cell in flow::mut_base(row).child_iter() {
+                    callback(cell.as_table_cell())
+                }
+            }
+        } else if kid.is_table_row() {
+            for cell in flow::mut_base(kid).child_iter() {
+                callback(cell.as_table_cell())
+            }
+        }
+    }
 }
@@ -8,30 +8,72 @@
 
 use block::{BlockFlow, MarginsMayNotCollapse, ISizeAndMarginsComputer};
 use context::LayoutContext;
-use flow::{TableCellFlowClass, FlowClass, Flow};
+use display_list_builder::{BlockFlowDisplayListBuilding, BlockLevel};
+use display_list_builder::{CollapseBorderPaintingMode, NoDisplayListBuildingResult};
+use flow::{TableCellFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use fragment::{Fragment, FragmentBoundsIterator};
-use model::{MaybeAuto};
+use model::{mod, MaybeAuto};
 use layout_debug;
-use table::InternalTable;
+use table::{CollapsedBorder, InternalTable};
 use wrapper::ThreadSafeLayoutNode;
 
 use servo_util::geometry::Au;
+use servo_util::logical_geometry::LogicalMargin;
+use std::cmp::{max, min};
 use std::fmt;
-use style::ComputedValues;
+use style::{ColSpanIntegerAttribute, ComputedValues, RowSpanIntegerAttribute};
+use style::computed_values::empty_cells;
 use sync::Arc;
 
+/// The largest `colspan` honored, per HTML5 § 4.9.11.
+static MAX_COLUMN_SPAN: u32 = 1000;
+
+/// The largest `rowspan` honored, per HTML5 § 4.9.11.
+static MAX_ROW_SPAN: u32 = 65534;
+
 /// A table formatting context.
 #[deriving(Encodable)]
 pub struct TableCellFlow {
     /// Data common to all flows.
     pub block_flow: BlockFlow,
+
+    /// The number of columns this cell spans (`<td colspan>`). Always at least 1.
+    pub column_span: u32,
+
+    /// The number of rows this cell spans (`<td rowspan>`). Zero means that the cell spans all
+    /// remaining rows in its row group, per HTML5 § 4.9.11.
+    pub row_span: u32,
+
+    /// The index of the first column that this cell occupies. This is computed by the parent row
+    /// group or table during inline-size bubbling, once the slots taken by cells spanning down
+    /// from previous rows are known.
+    pub column_index: uint,
+
+    /// The portions of the resolved borders around this cell that it paints, in the collapsing
+    /// border model. These are computed by the parent table. `None` in the separated borders
+    /// model.
+    pub collapsed_borders: Option<LogicalMargin<CollapsedBorder>>,
 }
 
 impl TableCellFlow {
     pub fn from_node_and_fragment(node: &ThreadSafeLayoutNode, fragment: Fragment)
                                   -> TableCellFlow {
+        // Per HTML5 § 4.9.11, a `colspan` of zero is treated as 1 and values are clamped to 1000;
+        // `rowspan` values are clamped to 65534.
+        let column_span = match node.get_table_cell_span(ColSpanIntegerAttribute) {
+            None | Some(0) => 1,
+            Some(column_span) => min(column_span, MAX_COLUMN_SPAN),
+        };
+        let row_span = match node.get_table_cell_span(RowSpanIntegerAttribute) {
+            None => 1,
+            Some(row_span) => min(row_span, MAX_ROW_SPAN),
+        };
         TableCellFlow {
-            block_flow: BlockFlow::from_node_and_fragment(node, fragment)
+            block_flow: BlockFlow::from_node_and_fragment(node, fragment),
+            column_span: column_span,
+            row_span: row_span,
+            column_index: 0,
+            collapsed_borders: None,
         }
     }
 
@@ -43,6 +85,33 @@ impl TableCellFlow {
         &mut self.block_flow.fragment
     }
 
+    /// Returns the block-size that this cell needs: the larger of its specified block-size and
+    /// the block-size of its contents, including borders and padding.
+    pub fn natural_block_size(&self) -> Au {
+        // TODO: Percentage block-size
+        let specified_block_size =
+            MaybeAuto::from_style(self.block_flow.fragment.style().content_block_size(),
+                                  Au(0)).specified_or_zero();
+        max(specified_block_size + self.block_flow.fragment.border_padding.block_start_end(),
+            self.block_flow.base.position.size.block)
+    }
+
+    /// Stretches this cell to the given block-size, which is the block-size of the row or rows
+    /// that it spans.
+    pub fn set_block_size(&mut self, block_size: Au) {
+        let mut position = self.block_flow.fragment.border_box;
+        position.size.block = block_size;
+        self.block_flow.fragment.border_box = position;
+        self.block_flow.base.position.size.block = block_size;
+    }
+
+    /// Returns true if this cell has no content, in the sense used by the `empty-cells` property.
+    fn is_empty(&self) -> bool {
+        self.block_flow.base.children.iter().all(|kid| {
+            kid.is_inline_flow() && kid.as_immutable_inline().fragments.is_empty()
+        })
+    }
+
     /// Assign block-size for table-cell flow.
     ///
     /// TODO(#2015, pcwalton): This doesn't handle floats right.
@@ -112,6 +181,22 @@ impl Flow for TableCellFlow {
                                                       ctx,
                                                       containing_block_inline_size);
 
+        // In the collapsing border model, this cell's borders are the ones resolved by the table.
+        match self.collapsed_borders {
+            None => {}
+            Some(ref collapsed_borders) => {
+                let fragment = &mut self.block_flow.fragment;
+                let padding = model::padding_from_style(fragment.style(),
+                                                        containing_block_inline_size);
+                fragment.border_padding = LogicalMargin::new(
+                    fragment.style().writing_mode,
+                    collapsed_borders.block_start.width + padding.block_start,
+                    collapsed_borders.inline_end.width + padding.inline_end,
+                    collapsed_borders.block_end.width + padding.block_end,
+                    collapsed_borders.inline_start.width + padding.inline_start)
+            }
+        }
+
         let inline_start_content_edge =
             self.block_flow.fragment.border_box.start.i +
             self.block_flow.fragment.border_padding.inline_start;
@@ -142,7 +227,25 @@ impl Flow for TableCellFlow {
     }
 
     fn build_display_list(&mut self, layout_context: &LayoutContext) {
-        self.block_flow.build_display_list(layout_context)
+        match self.collapsed_borders {
+            Some(ref collapsed_borders) => {
+                self.block_flow.build_display_list_for_block(
+                    layout_context,
+                    BlockLevel,
+                    CollapseBorderPaintingMode(collapsed_borders))
+            }
+            None => {
+                // Per CSS 2.1 § 17.6.1.1, `empty-cells: hide` suppresses the borders and
+                // background of empty cells in the separated borders model.
+                if self.block_flow.fragment.style().get_inheritedtable().empty_cells ==
+                        empty_cells::hide && self.is_empty() {
+                    self.block_flow.base.display_list_building_result =
+                        NoDisplayListBuildingResult;
+                    return
+                }
+                self.block_flow.build_display_list(layout_context)
+            }
+        }
     }
 
     fn repair_style(&mut self, new_style: &Arc<ComputedValues>) {
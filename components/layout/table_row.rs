@@ -10,6 +10,7 @@ use block::BlockFlow;
 use block::ISizeAndMarginsComputer;
 use construct::FlowConstructor;
 use context::LayoutContext;
+use display_list_builder::{BlockFlowDisplayListBuilding, BlockLevel, HiddenBorderPaintingMode};
 use flow::{TableRowFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use flow;
 use fragment::{Fragment, FragmentBoundsIterator};
@@ -22,7 +23,7 @@ use servo_util::geometry::Au;
 use std::cmp::max;
 use std::fmt;
 use style::ComputedValues;
-use style::computed_values::{LPA_Auto, LPA_Length, LPA_Percentage};
+use style::computed_values::{LPA_Auto, LPA_Length, LPA_Percentage, border_collapse};
 use sync::Arc;
 
 /// A single row of a table.
@@ -30,16 +31,31 @@ use sync::Arc;
 pub struct TableRowFlow {
     pub block_flow: BlockFlow,
 
+    /// Information about the intrinsic inline-sizes of each cell in this row.
+    pub cell_intrinsic_inline_sizes: Vec<CellIntrinsicInlineSize>,
+
     /// Information about the inline-sizes of each column.
     pub column_inline_sizes: Vec<ColumnInlineSize>,
 }
 
+/// Information about the column inline size and span for each cell.
+#[deriving(Clone, Encodable)]
+pub struct CellIntrinsicInlineSize {
+    /// Inline sizes that this cell contributes to the column or columns it spans.
+    pub column_size: ColumnInlineSize,
+    /// The number of columns that this cell spans.
+    pub column_span: u32,
+    /// The number of rows that this cell spans, or zero if it spans the rest of its row group.
+    pub row_span: u32,
+}
+
 impl TableRowFlow {
     pub fn from_node_and_fragment(node: &ThreadSafeLayoutNode,
                                   fragment: Fragment)
                                   -> TableRowFlow {
         TableRowFlow {
             block_flow: BlockFlow::from_node_and_fragment(node, fragment),
+            cell_intrinsic_inline_sizes: Vec::new(),
             column_inline_sizes: Vec::new()
         }
     }
@@ -49,6 +65,7 @@ impl TableRowFlow {
                      -> TableRowFlow {
         TableRowFlow {
             block_flow: BlockFlow::from_node(constructor, node),
+            cell_intrinsic_inline_sizes: Vec::new(),
             column_inline_sizes: Vec::new()
         }
     }
@@ -76,7 +93,7 @@ impl TableRowFlow {
         let /* mut */ cur_y = block_start_offset;
 
         // Per CSS 2.1 § 17.5.3, find max_y = max(computed `block-size`, minimum block-size of all
-        // cells).
+        // cells). Cells that span several rows are accounted for by the parent row group instead.
         let mut max_y = Au(0);
         for kid in self.block_flow.base.child_iter() {
             kid.place_float_if_applicable(layout_context);
@@ -85,18 +102,13 @@ impl TableRowFlow {
             }
 
             {
-                let child_fragment = kid.as_table_cell().fragment();
-                // TODO: Percentage block-size
-                let child_specified_block_size =
-                    MaybeAuto::from_style(child_fragment.style().content_block_size(),
-                                          Au::new(0)).specified_or_zero();
-                max_y = max(max_y,
-                            child_specified_block_size +
-                                child_fragment.border_padding.block_start_end());
+                let child_table_cell = kid.as_table_cell();
+                if child_table_cell.row_span == 1 {
+                    max_y = max(max_y, child_table_cell.natural_block_size());
+                }
             }
             let child_node = flow::mut_base(kid);
             child_node.position.start.b = cur_y;
-            max_y = max(max_y, child_node.position.size.block);
         }
 
         let mut block_size = max_y;
@@ -111,24 +123,24 @@ impl TableRowFlow {
         };
         // cur_y = cur_y + block-size;
 
-        // Assign the block-size of own fragment
-        //
         // FIXME(pcwalton): Take `cur_y` into account.
+        self.set_block_size(block_size)
+    }
+
+    /// Assigns the block-size of own fragment, and of the fragments of the cells that do not span
+    /// several rows, which is the same value. Cells spanning several rows are sized by the parent
+    /// row group once the block-sizes of all rows are known.
+    pub fn set_block_size(&mut self, block_size: Au) {
         let mut position = self.block_flow.fragment.border_box;
         position.size.block = block_size;
         self.block_flow.fragment.border_box = position;
         self.block_flow.base.position.size.block = block_size;
 
-        // Assign the block-size of kid fragments, which is the same value as own block-size.
         for kid in self.block_flow.base.child_iter() {
-            {
-                let kid_fragment = kid.as_table_cell().mut_fragment();
-                let mut position = kid_fragment.border_box;
-                position.size.block = block_size;
-                kid_fragment.border_box = position;
+            let child_table_cell = kid.as_table_cell();
+            if child_table_cell.row_span == 1 {
+                child_table_cell.set_block_size(block_size)
             }
-            let child_node = flow::mut_base(kid);
-            child_node.position.size.block = block_size;
         }
     }
 }
@@ -165,17 +177,22 @@ impl Flow for TableRowFlow {
         let _scope = layout_debug_scope!("table_row::bubble_inline_sizes {:x}",
                                          self.block_flow.base.debug_id());
 
-        // Bubble up the specified inline-sizes from child table cells.
+        // Bubble up the specified inline-sizes from child table cells. The columns that each cell
+        // occupies are not known until the parent row group or table has seen the cells that
+        // span down from previous rows, so only the per-cell information is recorded here.
         let (mut min_inline_size, mut pref_inline_size) = (Au(0), Au(0));
+        self.cell_intrinsic_inline_sizes.clear();
         for kid in self.block_flow.base.child_iter() {
             assert!(kid.is_table_cell());
 
             // Collect the specified column inline-size of the cell. This is used in both fixed and
             // automatic table layout calculation.
-            let child_specified_inline_size = kid.as_table_cell()
-                                                 .fragment()
-                                                 .style()
-                                                 .content_inline_size();
+            let (child_specified_inline_size, child_column_span, child_row_span) = {
+                let child_table_cell = kid.as_table_cell();
+                (child_table_cell.fragment().style().content_inline_size(),
+                 child_table_cell.column_span,
+                 child_table_cell.row_span)
+            };
 
             // Collect minimum and preferred inline-sizes of the cell for automatic table layout
             // calculation.
@@ -199,7 +216,11 @@ impl Flow for TableRowFlow {
             };
             min_inline_size = min_inline_size + child_column_inline_size.minimum_length;
             pref_inline_size = pref_inline_size + child_column_inline_size.preferred;
-            self.column_inline_sizes.push(child_column_inline_size);
+            self.cell_intrinsic_inline_sizes.push(CellIntrinsicInlineSize {
+                column_size: child_column_inline_size,
+                column_span: child_column_span,
+                row_span: child_row_span,
+            });
         }
         self.block_flow.base.intrinsic_inline_sizes.minimum_inline_size = min_inline_size;
         self.block_flow.base.intrinsic_inline_sizes.preferred_inline_size = max(min_inline_size,
@@ -248,7 +269,15 @@ impl Flow for TableRowFlow {
     }
 
     fn build_display_list(&mut self, layout_context: &LayoutContext) {
-        self.block_flow.build_display_list(layout_context)
+        // In the collapsing border model, the cells paint the borders of the row.
+        match self.block_flow.fragment.style().get_inheritedtable().border_collapse {
+            border_collapse::separate => self.block_flow.build_display_list(layout_context),
+            border_collapse::collapse => {
+                self.block_flow.build_display_list_for_block(layout_context,
+                                                             BlockLevel,
+                                                             HiddenBorderPaintingMode)
+            }
+        }
     }
 
     fn repair_style(&mut self, new_style: &Arc<ComputedValues>) {
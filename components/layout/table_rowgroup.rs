@@ -10,17 +10,19 @@ use block::BlockFlow;
 use block::ISizeAndMarginsComputer;
 use construct::FlowConstructor;
 use context::LayoutContext;
-use flow::{TableRowGroupFlowClass, FlowClass, Flow, ImmutableFlowUtils};
+use display_list_builder::{BlockFlowDisplayListBuilding, BlockLevel, HiddenBorderPaintingMode};
+use flow::{BaseFlow, TableRowGroupFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use flow;
 use fragment::{Fragment, FragmentBoundsIterator};
 use layout_debug;
-use model::IntrinsicISizesContribution;
-use table::{ColumnInlineSize, InternalTable, TableFlow};
+use table::{mod, ColumnInlineSize, InternalTable, TableGrid};
 use wrapper::ThreadSafeLayoutNode;
 
 use servo_util::geometry::Au;
+use std::cmp::min;
 use std::fmt;
 use style::ComputedValues;
+use style::computed_values::border_collapse;
 use sync::Arc;
 
 /// A table formatting context.
@@ -70,18 +72,24 @@ impl TableRowGroupFlow {
     #[inline(always)]
     fn assign_block_size_table_rowgroup_base<'a>(&mut self, layout_context: &'a LayoutContext<'a>) {
         let (block_start_offset, _, _) = self.initialize_offsets();
+        let border_spacing = table::border_spacing(self.block_flow.fragment.style()).block;
 
-        let mut cur_y = block_start_offset;
+        let row_count = self.block_flow.base.children.len();
+        let row_block_sizes = assign_block_sizes_to_rows(&mut self.block_flow.base,
+                                                         0,
+                                                         row_count,
+                                                         border_spacing,
+                                                         layout_context);
 
-        for kid in self.block_flow.base.child_iter() {
-            kid.place_float_if_applicable(layout_context);
-            if !flow::base(kid).flags.is_float() {
-                kid.assign_block_size_for_inorder_child_if_necessary(layout_context);
+        let mut cur_y = block_start_offset;
+        for (row_index, kid) in self.block_flow.base.child_iter().enumerate() {
+            // Rows are separated by the vertical border spacing.
+            if row_index != 0 {
+                cur_y = cur_y + border_spacing
             }
-
             let child_node = flow::mut_base(kid);
             child_node.position.start.b = cur_y;
-            cur_y = cur_y + child_node.position.size.block;
+            cur_y = cur_y + row_block_sizes[row_index];
         }
 
         let block_size = cur_y - block_start_offset;
@@ -93,6 +101,100 @@ impl TableRowGroupFlow {
     }
 }
 
+/// Assigns block-sizes to the `row_count` consecutive rows among the children of `parent` that
+/// start with the child at `first_row_index`, which form a row group, and returns them. The rows
+/// are grown so that the cells spanning several of them fit, and those cells are stretched over
+/// the rows they span. A row span of zero means that the cell spans all remaining rows of the
+/// group, and no cell may span past the end of the group.
+pub fn assign_block_sizes_to_rows<'a>(parent: &mut BaseFlow,
+                                      first_row_index: uint,
+                                      row_count: uint,
+                                      border_spacing: Au,
+                                      layout_context: &'a LayoutContext<'a>)
+                                      -> Vec<Au> {
+    let mut row_block_sizes = Vec::new();
+    for kid in parent.child_iter().skip(first_row_index).take(row_count) {
+        kid.place_float_if_applicable(layout_context);
+        if !flow::base(kid).flags.is_float() {
+            kid.assign_block_size_for_inorder_child_if_necessary(layout_context);
+        }
+        row_block_sizes.push(flow::base(kid).position.size.block);
+    }
+
+    // Find the cells that span several rows.
+    let mut spanning_cells = Vec::new();
+    for (row_index, kid) in parent.child_iter().skip(first_row_index).take(row_count).enumerate() {
+        for (cell_index, cell) in flow::mut_base(kid).child_iter().enumerate() {
+            let cell = cell.as_table_cell();
+            let row_span = match cell.row_span {
+                1 => continue,
+                0 => row_count - row_index,
+                row_span => min(row_span as uint, row_count - row_index),
+            };
+            spanning_cells.push(RowSpanningCell {
+                row_index: row_index,
+                cell_index: cell_index,
+                row_span: row_span,
+                block_size: cell.natural_block_size(),
+            })
+        }
+    }
+
+    // Per CSS 2.1 § 17.5.3, the rows that a cell spans must together be tall enough to hold it. If
+    // they are not, grow the last row that it spans.
+    for spanning_cell in spanning_cells.iter() {
+        let spanned_block_size = spanning_cell.spanned_block_size(row_block_sizes.as_slice(),
+                                                                  border_spacing);
+        if spanning_cell.block_size > spanned_block_size {
+            let last_row_block_size =
+                row_block_sizes.get_mut(spanning_cell.row_index + spanning_cell.row_span - 1);
+            *last_row_block_size = *last_row_block_size + spanning_cell.block_size -
+                spanned_block_size
+        }
+    }
+
+    for (row_index, kid) in parent.child_iter().skip(first_row_index).take(row_count).enumerate() {
+        let row_block_size = row_block_sizes[row_index];
+        if row_block_size != flow::base(kid).position.size.block {
+            kid.as_table_row().set_block_size(row_block_size)
+        }
+    }
+
+    // Stretch each spanning cell over all of the rows it spans.
+    for spanning_cell in spanning_cells.iter() {
+        let spanned_block_size = spanning_cell.spanned_block_size(row_block_sizes.as_slice(),
+                                                                  border_spacing);
+        let row = parent.child_iter().nth(first_row_index + spanning_cell.row_index).unwrap();
+        let cell = flow::mut_base(row).child_iter().nth(spanning_cell.cell_index).unwrap();
+        cell.as_table_cell().set_block_size(spanned_block_size)
+    }
+
+    row_block_sizes
+}
+
+/// A table cell that spans several rows of a row group.
+struct RowSpanningCell {
+    /// The index of the row that the cell belongs to.
+    row_index: uint,
+    /// The index of the cell within its row.
+    cell_index: uint,
+    /// The number of rows that the cell spans, clipped to the end of the row group.
+    row_span: uint,
+    /// The block-size that the cell needs.
+    block_size: Au,
+}
+
+impl RowSpanningCell {
+    /// Returns the total block-size of the rows that this cell spans, including the border
+    /// spacing between them.
+    fn spanned_block_size(&self, row_block_sizes: &[Au], border_spacing: Au) -> Au {
+        row_block_sizes.slice(self.row_index, self.row_index + self.row_span)
+                       .iter()
+                       .fold(Au(0), |sum, &block_size| sum + block_size) +
+            border_spacing * ((self.row_span - 1) as i32)
+    }
+}
+
 impl Flow for TableRowGroupFlow {
     fn class(&self) -> FlowClass {
         TableRowGroupFlowClass
@@ -127,41 +229,17 @@ impl Flow for TableRowGroupFlow {
         let _scope = layout_debug_scope!("table_rowgroup::bubble_inline_sizes {:x}",
                                          self.block_flow.base.debug_id());
 
-        let mut computation = IntrinsicISizesContribution::new();
+        // Place the cells of each row into the grid, taking row and column spans into account, and
+        // calculate minimum and preferred inline sizes for automatic table layout.
+        let mut grid = TableGrid::new();
         for kid in self.block_flow.base.child_iter() {
             assert!(kid.is_table_row());
-
-            // Calculate minimum and preferred inline sizes for automatic table layout.
-            if self.column_inline_sizes.is_empty() {
-                // We're the first row.
-                debug_assert!(self.column_inline_sizes.is_empty());
-                self.column_inline_sizes = kid.column_inline_sizes().clone();
-            } else {
-                let mut child_intrinsic_sizes =
-                    TableFlow::update_column_inline_sizes(&mut self.column_inline_sizes,
-                                                          kid.column_inline_sizes());
-
-                // update the number of column inline-sizes from table-rows.
-                let column_count = self.column_inline_sizes.len();
-                let child_column_count = kid.column_inline_sizes().len();
-                for i in range(column_count, child_column_count) {
-                    let this_column_inline_size = (*kid.column_inline_sizes())[i];
-
-                    // FIXME(pcwalton): Ignoring the percentage here seems dubious.
-                    child_intrinsic_sizes.minimum_inline_size =
-                        child_intrinsic_sizes.minimum_inline_size +
-                        this_column_inline_size.minimum_length;
-                    child_intrinsic_sizes.preferred_inline_size =
-                        child_intrinsic_sizes.preferred_inline_size +
-                        this_column_inline_size.preferred;
-                    self.column_inline_sizes.push(this_column_inline_size);
-                }
-
-                computation.union_block(&child_intrinsic_sizes)
-            }
+            grid.add_row(kid.as_table_row());
         }
 
-        self.block_flow.base.intrinsic_inline_sizes = computation.finish()
+        let (column_inline_sizes, intrinsic_inline_sizes) = grid.finish();
+        self.column_inline_sizes = column_inline_sizes;
+        self.block_flow.base.intrinsic_inline_sizes = intrinsic_inline_sizes
     }
 
     /// Recursively (top-down) determines the actual inline-size of child contexts and fragments.
@@ -208,7 +286,15 @@ impl Flow for TableRowGroupFlow {
 
     fn build_display_list(&mut self, layout_context: &LayoutContext) {
         debug!("build_display_list_table_rowgroup: same process as block flow");
-        self.block_flow.build_display_list(layout_context)
+        // In the collapsing border model, the cells paint the borders of the row group.
+        match self.block_flow.fragment.style().get_inheritedtable().border_collapse {
+            border_collapse::separate => self.block_flow.build_display_list(layout_context),
+            border_collapse::collapse => {
+                self.block_flow.build_display_list_for_block(layout_context,
+                                                             BlockLevel,
+                                                             HiddenBorderPaintingMode)
+            }
+        }
     }
 
     fn repair_style(&mut self, new_style: &Arc<ComputedValues>) {
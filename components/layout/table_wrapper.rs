@@ -121,13 +121,17 @@ impl TableWrapperFlow {
         // tables are separated into table flows and table wrapper flows.
         let available_inline_size = self.block_flow.fragment.border_box.size.inline;
         let mut table_border_padding = Au(0);
+        let mut table_border_spacing = Au(0);
         for kid in self.block_flow.base.child_iter() {
             if kid.is_table() {
-                let kid_block = kid.as_block();
-                kid_block.fragment.compute_border_and_padding(available_inline_size);
-                kid_block.fragment.compute_block_direction_margins(available_inline_size);
-                kid_block.fragment.compute_inline_direction_margins(available_inline_size);
-                table_border_padding = kid_block.fragment.border_padding.inline_start_end();
+                {
+                    let kid_block = kid.as_block();
+                    kid_block.fragment.compute_border_and_padding(available_inline_size);
+                    kid_block.fragment.compute_block_direction_margins(available_inline_size);
+                    kid_block.fragment.compute_inline_direction_margins(available_inline_size);
+                    table_border_padding = kid_block.fragment.border_padding.inline_start_end();
+                }
+                table_border_spacing = kid.as_table().total_inline_border_spacing();
                 break
             }
         }
@@ -135,8 +139,11 @@ impl TableWrapperFlow {
         // FIXME(pcwalton, spec): INTRINSIC § 8 does not properly define how to compute this, but
         // says "the basic idea is the same as the shrink-to-fit width that CSS2.1 defines". So we
         // just use the shrink-to-fit inline size.
+        // The columns share whatever is left over after the border spacing around and between
+        // them.
         let available_inline_size =
-            self.block_flow.get_shrink_to_fit_inline_size(available_inline_size);
+            self.block_flow.get_shrink_to_fit_inline_size(available_inline_size) -
+            table_border_spacing;
 
         // Compute all the guesses for the column sizes, and sum them.
         let mut total_guess = AutoLayoutCandidateGuess::new();
@@ -182,9 +189,10 @@ impl TableWrapperFlow {
         }
 
         self.block_flow.fragment.border_box.size.inline = total_used_inline_size +
-            table_border_padding;
+            table_border_spacing + table_border_padding;
         self.block_flow.base.position.size.inline = total_used_inline_size +
-            table_border_padding + self.block_flow.fragment.margin.inline_start_end();
+            table_border_spacing + table_border_padding +
+            self.block_flow.fragment.margin.inline_start_end();
     }
 
     fn compute_used_inline_size(&mut self,
@@ -7,22 +7,26 @@
 #![deny(unsafe_blocks)]
 
 use fragment::{Fragment, ScannedTextFragmentInfo, UnscannedTextFragment};
+use fragment::UnscannedTextFragmentInfo;
 use inline::InlineFragments;
 
-use gfx::font::{FontMetrics,RunMetrics};
+use gfx::font::{Font, FontGroup, FontMetrics, RunMetrics};
 use gfx::font_context::FontContext;
+use gfx::text::bidi;
 use gfx::text::glyph::CharIndex;
 use gfx::text::text_run::TextRun;
-use gfx::text::util::{mod, CompressWhitespaceNewline, CompressNone};
+use gfx::text::util::{mod, CompressWhitespace, CompressWhitespaceNewline, CompressNone};
 use servo_util::dlist;
 use servo_util::geometry::Au;
 use servo_util::logical_geometry::{LogicalSize, WritingMode};
 use servo_util::range::Range;
 use servo_util::smallvec::{SmallVec, SmallVec1};
+use std::cell::RefCell;
 use std::collections::DList;
 use std::mem;
+use std::rc::Rc;
 use style::ComputedValues;
-use style::computed_values::{line_height, text_orientation, white_space};
+use style::computed_values::{line_height, text_orientation, text_transform, white_space};
 use style::style_structs::Font as FontStyle;
 use sync::Arc;
 
@@ -38,29 +42,40 @@ impl TextRunScanner {
         }
     }
 
-    pub fn scan_for_runs(&mut self, font_context: &mut FontContext, mut fragments: DList<Fragment>)
+    pub fn scan_for_runs(&mut self,
+                         font_context: &mut FontContext,
+                         fragments: DList<Fragment>,
+                         paragraph_level: u8)
                          -> InlineFragments {
         debug!("TextRunScanner: scanning {:u} fragments for text runs...", fragments.len());
 
+        // Bidi levels depend on the text of the whole paragraph, so resolve them before building
+        // any runs.
+        let (mut fragments, mut bidi_levels) = split_fragments_by_bidi_level(fragments,
+                                                                             paragraph_level);
+
         // FIXME(pcwalton): We want to be sure not to allocate multiple times, since this is a
         // performance-critical spot, but this may overestimate and allocate too much memory.
         let mut new_fragments = Vec::with_capacity(fragments.len());
         let mut last_whitespace = true;
         while !fragments.is_empty() {
-            // Create a clump.
+            // Create a clump. Text of different bidi levels is shaped in different directions, so
+            // it never shares a clump.
             self.clump.append(dlist::split(&mut fragments));
-            while !fragments.is_empty() && self.clump
-                                               .back()
-                                               .unwrap()
-                                               .can_merge_with_fragment(fragments.front()
-                                                                                 .unwrap()) {
+            let bidi_level = bidi_levels.pop_front().unwrap();
+            while !fragments.is_empty() &&
+                    *bidi_levels.front().unwrap() == bidi_level &&
+                    self.clump.back().unwrap().can_merge_with_fragment(fragments.front()
+                                                                                .unwrap()) {
                 self.clump.append(dlist::split(&mut fragments));
+                bidi_levels.pop_front();
             }
 
             // Flush that clump to the list of fragments we're building up.
             last_whitespace = self.flush_clump_to_list(font_context,
                                                        &mut new_fragments,
-                                                       last_whitespace);
+                                                       last_whitespace,
+                                                       bidi_level);
         }
 
         debug!("TextRunScanner: complete.");
@@ -78,7 +93,8 @@ impl TextRunScanner {
     fn flush_clump_to_list(&mut self,
                            font_context: &mut FontContext,
                            out_fragments: &mut Vec<Fragment>,
-                           mut last_whitespace: bool)
+                           mut last_whitespace: bool,
+                           bidi_level: u8)
                            -> bool {
         debug!("TextRunScanner: flushing {} fragments in range", self.clump.len());
 
@@ -93,29 +109,40 @@ impl TextRunScanner {
             }
         }
 
-        // TODO(#177): Text run creation must account for the renderability of text by font group
-        // fonts. This is probably achieved by creating the font group above and then letting
-        // `FontGroup` decide which `Font` to stick into the text run.
-        //
         // Concatenate all of the transformed strings together, saving the new character indices.
         let mut new_ranges: SmallVec1<Range<CharIndex>> = SmallVec1::new();
         let mut new_line_positions: SmallVec1<NewLinePositions> = SmallVec1::new();
         let mut char_total = CharIndex(0);
-        let run = {
+        let runs = {
             let fontgroup;
             let compression;
+            let text_transform;
+            let letter_spacing;
+            let word_spacing;
+            let word_break;
+            let tab_size;
+            let writing_mode;
             {
                 let in_fragment = self.clump.front().unwrap();
                 let font_style = in_fragment.style().get_font_arc();
+                let inherited_text_style = in_fragment.style().get_inheritedtext();
                 fontgroup = font_context.get_layout_font_group_for_style(font_style);
                 compression = match in_fragment.white_space() {
                     white_space::normal | white_space::nowrap => CompressWhitespaceNewline,
-                    white_space::pre => CompressNone,
-                }
+                    white_space::pre | white_space::pre_wrap => CompressNone,
+                    white_space::pre_line => CompressWhitespace,
+                };
+                text_transform = inherited_text_style.text_transform;
+                letter_spacing = inherited_text_style.letter_spacing;
+                word_spacing = inherited_text_style.word_spacing;
+                word_break = inherited_text_style.word_break;
+                tab_size = inherited_text_style.tab_size as uint;
+                writing_mode = in_fragment.style().writing_mode;
             }
 
             // First, transform/compress text of all the nodes.
             let mut run_text = String::new();
+            let incoming_whitespace = last_whitespace;
             for in_fragment in self.clump.iter() {
                 let in_fragment = match in_fragment.specific {
                     UnscannedTextFragment(ref text_fragment_info) => &text_fragment_info.text,
@@ -127,6 +154,7 @@ impl TextRunScanner {
                 last_whitespace = util::transform_text(in_fragment.as_slice(),
                                                        compression,
                                                        last_whitespace,
+                                                       tab_size,
                                                        &mut run_text,
                                                        &mut new_line_pos);
                 new_line_positions.push(NewLinePositions(new_line_pos));
@@ -144,10 +172,33 @@ impl TextRunScanner {
                 self.clump = DList::new();
                 return last_whitespace
             }
-            Arc::new(box TextRun::new(&mut *fontgroup.fonts.get(0).borrow_mut(), run_text))
+
+            // Case transforms don't change the number of characters, so the ranges and newline
+            // positions computed above remain valid.
+            let run_text = apply_text_transform(run_text, text_transform, incoming_whitespace);
+
+            // Create one run for each stretch of text that a single font has glyphs for.
+            let mut runs = Vec::new();
+            for font_run in split_text_by_font(font_context, &*fontgroup, run_text).into_iter() {
+                let FontRun {
+                    font,
+                    range,
+                    text,
+                } = font_run;
+                let mut run = box TextRun::new(&mut *font.borrow_mut(),
+                                             text,
+                                             word_break,
+                                             bidi_level);
+                run.letter_spacing = letter_spacing.unwrap_or(Au(0));
+                run.word_spacing = word_spacing.unwrap_or(Au(0));
+                run.upright = writing_mode.is_vertical() && writing_mode.is_upright();
+                runs.push((range, Arc::new(run)))
+            }
+            runs
         };
 
-        // Make new fragments with the run and adjusted text indices.
+        // Make new fragments with the runs and adjusted text indices. A fragment whose text spans
+        // several runs is split into one fragment per run.
         debug!("TextRunScanner: pushing {} fragment(s)", self.clump.len());
         for (logical_offset, old_fragment) in
                 mem::replace(&mut self.clump, DList::new()).into_iter().enumerate() {
@@ -162,16 +213,34 @@ impl TextRunScanner {
             let text_size = old_fragment.border_box.size;
             let &NewLinePositions(ref mut new_line_positions) =
                 new_line_positions.get_mut(logical_offset);
-            let new_text_fragment_info =
-                box ScannedTextFragmentInfo::new(run.clone(),
-                                                 range,
-                                                 mem::replace(new_line_positions, Vec::new()),
-                                                 text_size);
-            let new_metrics = new_text_fragment_info.run.metrics_for_range(&range);
-            let bounding_box_size = bounding_box_for_run_metrics(&new_metrics,
-                                                                 old_fragment.style.writing_mode);
-            let new_fragment = old_fragment.transform(bounding_box_size, new_text_fragment_info);
-            out_fragments.push(new_fragment)
+            for &(ref run_range, ref run) in runs.iter() {
+                let piece_range = range.intersect(run_range);
+                if piece_range.is_empty() {
+                    continue
+                }
+                let piece_range = Range::new(piece_range.begin() - run_range.begin(),
+                                             piece_range.length());
+
+                // The newline positions are relative to the start of the fragment, so they have
+                // to be recomputed for each piece of a fragment that is split.
+                let piece_new_line_positions = if piece_range.length() == range.length() {
+                    mem::replace(new_line_positions, Vec::new())
+                } else {
+                    new_line_positions_in_range(run.text.as_slice(), &piece_range)
+                };
+
+                let new_text_fragment_info =
+                    box ScannedTextFragmentInfo::new(run.clone(),
+                                                     piece_range,
+                                                     piece_new_line_positions,
+                                                     text_size);
+                let new_metrics = new_text_fragment_info.run.metrics_for_range(&piece_range);
+                let bounding_box_size =
+                    bounding_box_for_run_metrics(&new_metrics, old_fragment.style.writing_mode);
+                let new_fragment = old_fragment.transform(bounding_box_size,
+                                                          new_text_fragment_info);
+                out_fragments.push(new_fragment)
+            }
         }
 
         last_whitespace
@@ -180,6 +249,194 @@ impl TextRunScanner {
 
 struct NewLinePositions(Vec<CharIndex>);
 
+/// Returns the bidi level of the paragraphs in a block with the given writing mode: 1 if the block
+/// is right-to-left, and 0 otherwise.
+pub fn bidi_paragraph_level(writing_mode: WritingMode) -> u8 {
+    if writing_mode.is_bidi_ltr() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Resolves the bidi levels of the paragraph formed by the given fragments with the Unicode
+/// Bidirectional Algorithm, and splits unscanned text fragments wherever the level changes.
+/// Returns the resulting fragments along with the level of each. Fragments other than text take
+/// part in the paragraph as neutral object replacement characters.
+fn split_fragments_by_bidi_level(fragments: DList<Fragment>, paragraph_level: u8)
+                                 -> (DList<Fragment>, DList<u8>) {
+    let mut text = String::new();
+    let mut explicit_embeddings = Vec::new();
+    for fragment in fragments.iter() {
+        let embedding = fragment.explicit_bidi_embedding(paragraph_level);
+        match fragment.specific {
+            UnscannedTextFragment(ref text_fragment_info) => {
+                text.push_str(text_fragment_info.text.as_slice());
+                for _ in text_fragment_info.text.as_slice().chars() {
+                    explicit_embeddings.push(embedding)
+                }
+            }
+            _ => {
+                text.push('\uFFFC');
+                explicit_embeddings.push(embedding)
+            }
+        }
+    }
+    let levels = bidi::resolve_levels(text.as_slice(),
+                                      paragraph_level,
+                                      explicit_embeddings.as_slice());
+
+    let mut new_fragments = DList::new();
+    let mut new_levels = DList::new();
+    let mut level_index = 0;
+    for fragment in fragments.into_iter() {
+        let text = match fragment.specific {
+            UnscannedTextFragment(ref text_fragment_info) => {
+                Some((*text_fragment_info.text).clone())
+            }
+            _ => None,
+        };
+        let text = match text {
+            Some(text) => text,
+            None => {
+                new_fragments.push_back(fragment);
+                new_levels.push_back(levels[level_index]);
+                level_index += 1;
+                continue
+            }
+        };
+
+        let char_count = text.as_slice().char_len();
+        let fragment_levels = levels.slice(level_index, level_index + char_count);
+        level_index += char_count;
+        let first_level = match fragment_levels.head() {
+            Some(&level) => level,
+            None => paragraph_level,
+        };
+        if fragment_levels.iter().all(|&level| level == first_level) {
+            new_fragments.push_back(fragment);
+            new_levels.push_back(first_level);
+            continue
+        }
+
+        let mut piece = String::new();
+        let mut piece_level = first_level;
+        for (ch, &level) in text.as_slice().chars().zip(fragment_levels.iter()) {
+            if level != piece_level {
+                let piece_text = mem::replace(&mut piece, String::new());
+                new_fragments.push_back(unscanned_text_fragment_with_text(&fragment, piece_text));
+                new_levels.push_back(piece_level);
+                piece_level = level
+            }
+            piece.push(ch)
+        }
+        new_fragments.push_back(unscanned_text_fragment_with_text(&fragment, piece));
+        new_levels.push_back(piece_level);
+    }
+    (new_fragments, new_levels)
+}
+
+/// Returns a copy of the given unscanned text fragment with the given text.
+fn unscanned_text_fragment_with_text(fragment: &Fragment, text: String) -> Fragment {
+    let mut new_fragment = fragment.clone();
+    new_fragment.specific = UnscannedTextFragment(UnscannedTextFragmentInfo::from_text(text));
+    new_fragment
+}
+
+/// A stretch of text that a single font has glyphs for.
+struct FontRun {
+    font: Rc<RefCell<Font>>,
+    /// The characters of the whole text that this run covers.
+    range: Range<CharIndex>,
+    text: String,
+}
+
+/// Splits the given text into runs that can each be rendered with a single font, choosing for each
+/// character the first font of the font group that has a glyph for it, or else a system font that
+/// does. Whitespace stays in the run of the preceding character, so that it does not break runs
+/// up needlessly.
+fn split_text_by_font(font_context: &mut FontContext, font_group: &FontGroup, text: String)
+                      -> Vec<FontRun> {
+    let mut font_runs: Vec<FontRun> = Vec::new();
+    for (char_index, codepoint) in text.as_slice().chars().enumerate() {
+        let char_index = CharIndex(char_index as int);
+        let font = if codepoint.is_whitespace() && !font_runs.is_empty() {
+            None
+        } else {
+            Some(font_context.get_layout_font_for_character(font_group, codepoint))
+        };
+
+        let extends_last_run = match (font_runs.last(), &font) {
+            (Some(_), &None) => true,
+            (Some(last_run), &Some(ref font)) => {
+                &*last_run.font as *const RefCell<Font> == &**font as *const RefCell<Font>
+            }
+            (None, _) => false,
+        };
+        if extends_last_run {
+            let last_run = font_runs.last_mut().unwrap();
+            last_run.range.extend_by(CharIndex(1));
+            last_run.text.push(codepoint);
+            continue
+        }
+
+        font_runs.push(FontRun {
+            font: font.unwrap(),
+            range: Range::new(char_index, CharIndex(1)),
+            text: String::from_char(1, codepoint),
+        })
+    }
+    font_runs
+}
+
+/// Returns the positions of the newlines in the given range of the given text, each relative to
+/// the character after the previous newline, in the form that `transform_text` records them.
+fn new_line_positions_in_range(text: &str, range: &Range<CharIndex>) -> Vec<CharIndex> {
+    let mut new_line_positions = Vec::new();
+    let mut new_line_index = CharIndex(0);
+    for codepoint in text.chars().skip(range.begin().to_uint()).take(range.length().to_uint()) {
+        if codepoint == '\n' {
+            new_line_positions.push(new_line_index);
+            new_line_index = CharIndex(0);
+        } else {
+            new_line_index = new_line_index + CharIndex(1);
+        }
+    }
+    new_line_positions
+}
+
+/// Applies the case mapping specified by `text-transform` to the given text.
+/// `incoming_whitespace` is true if the text preceding this text ended in whitespace, which tells
+/// `capitalize` whether the first character starts a word.
+///
+/// FIXME: Mappings that change the length of the string, like German `ß` to `SS`, are not
+/// supported, because the character indices computed during whitespace compression must remain
+/// valid.
+fn apply_text_transform(text: String, text_transform: text_transform::T, incoming_whitespace: bool)
+                        -> String {
+    match text_transform {
+        text_transform::none => text,
+        text_transform::uppercase => {
+            text.as_slice().chars().map(|ch| ch.to_uppercase()).collect()
+        }
+        text_transform::lowercase => {
+            text.as_slice().chars().map(|ch| ch.to_lowercase()).collect()
+        }
+        text_transform::capitalize => {
+            let mut at_word_start = incoming_whitespace;
+            text.as_slice().chars().map(|ch| {
+                let result = if at_word_start {
+                    ch.to_uppercase()
+                } else {
+                    ch
+                };
+                at_word_start = ch.is_whitespace();
+                result
+            }).collect()
+        }
+    }
+}
+
 #[inline]
 fn bounding_box_for_run_metrics(metrics: &RunMetrics, writing_mode: WritingMode)
                                 -> LogicalSize<Au> {
@@ -192,12 +449,14 @@ fn bounding_box_for_run_metrics(metrics: &RunMetrics, writing_mode: WritingMode)
         Some(text_orientation::sideways_right) |
         Some(text_orientation::sideways_left) |
         Some(text_orientation::sideways) |
+        Some(text_orientation::mixed) |
+        Some(text_orientation::upright) |
         None => {}
     }
 
-    // In vertical sideways or horizontal upgright text,
-    // the "width" of text metrics is always inline
-    // This will need to be updated when other text orientations are supported.
+    // In vertical sideways or horizontal upright text, the "width" of text metrics is always
+    // inline. Upright vertical runs already measure their advances along the block axis of the
+    // font (see `TextRun::advance_for_glyph`), and the em box is square, so the same holds there.
     LogicalSize::new(
         writing_mode,
         metrics.bounding_box.size.width,
@@ -39,7 +39,7 @@ use util::{PrivateLayoutData};
 use gfx::display_list::OpaqueNode;
 use script::dom::bindings::codegen::InheritTypes::{ElementCast, HTMLIFrameElementCast};
 use script::dom::bindings::codegen::InheritTypes::{HTMLImageElementCast, HTMLInputElementCast};
-use script::dom::bindings::codegen::InheritTypes::{NodeCast, TextCast};
+use script::dom::bindings::codegen::InheritTypes::{HTMLTableCellElementCast, NodeCast, TextCast};
 use script::dom::bindings::js::JS;
 use script::dom::element::{Element, HTMLAreaElementTypeId, HTMLAnchorElementTypeId};
 use script::dom::element::{HTMLLinkElementTypeId, LayoutElementHelpers, RawLayoutElementHelpers};
@@ -903,6 +903,25 @@ impl<'ln> ThreadSafeLayoutNode<'ln> {
         }
     }
 
+    /// If this is a table cell element, returns the value of its `colspan` or `rowspan` attribute
+    /// (as selected by `span_attribute`), if present. Anonymous table cells and pseudo-elements
+    /// never have spans.
+    pub fn get_table_cell_span(&self, span_attribute: IntegerAttribute) -> Option<u32> {
+        if self.pseudo != Normal {
+            return None
+        }
+        unsafe {
+            if HTMLTableCellElementCast::to_js(self.get_jsmanaged()).is_none() {
+                return None
+            }
+            let element = match ElementCast::to_js(self.get_jsmanaged()) {
+                Some(element) => element.unsafe_get(),
+                None => return None,
+            };
+            (*element).get_integer_attribute_for_layout(span_attribute).map(|span| span as u32)
+        }
+    }
+
     /// Get the description of how to account for recent style changes.
     /// This is a simple bitfield and fine to copy by value.
     pub fn restyle_damage(self) -> RestyleDamage {
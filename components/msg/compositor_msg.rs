@@ -5,10 +5,12 @@
 use azure::azure_hl::Color;
 use geom::point::Point2D;
 use geom::rect::Rect;
+use geom::size::Size2D;
 use layers::platform::surface::NativeGraphicsMetadata;
 use layers::layers::LayerBufferSet;
 use std::fmt::{Formatter, Show};
 use std::fmt;
+use std::uint;
 
 use constellation_msg::PipelineId;
 
@@ -52,11 +54,32 @@ impl Show for LayerId {
     }
 }
 
+/// The second half of the ID of every scroll root layer. See `LayerId::for_scroll_root`.
+static SCROLL_ROOT_LAYER_INDEX: uint = uint::MAX;
+
 impl LayerId {
     /// FIXME(#2011, pcwalton): This is unfortunate. Maybe remove this in the future.
     pub fn null() -> LayerId {
         LayerId(0, 0)
     }
+
+    /// Returns the ID of the layer that the compositor creates to clip and scroll the contents of
+    /// the element with the given DOM node address. This lets script refer to the scroll root of
+    /// an element without asking layout.
+    pub fn for_scroll_root(node_address: uint) -> LayerId {
+        LayerId(node_address, SCROLL_ROOT_LAYER_INDEX)
+    }
+
+    /// If this is the ID of the scroll root of an element, returns the address of the DOM node of
+    /// that element.
+    pub fn scroll_root_node_address(&self) -> Option<uint> {
+        let LayerId(node_address, index) = *self;
+        if index == SCROLL_ROOT_LAYER_INDEX {
+            Some(node_address)
+        } else {
+            None
+        }
+    }
 }
 
 /// The scrolling policy of a layer.
@@ -66,6 +89,48 @@ pub enum ScrollPolicy {
     Scrollable,
     /// These layers do not scroll when the parent receives a scrolling message.
     FixedPosition,
+    /// These layers scroll with the parent, but are then moved to stay within the scrollport as
+    /// far as the given constraints allow.
+    StickyPosition(StickyPositionConstraints),
+}
+
+/// What the compositor needs to know to position a layer for a `position: sticky` element. All
+/// rectangles are in pixels, relative to the origin of the layer.
+#[deriving(Clone, PartialEq)]
+pub struct StickyPositionConstraints {
+    /// The border box of the sticky element.
+    pub rect: Rect<f32>,
+    /// The content box of the containing block of the element, which the element never leaves.
+    pub containing_block_rect: Rect<f32>,
+    /// The distance the element keeps from the top edge of the scrollport, if any.
+    pub top: Option<f32>,
+    /// The distance the element keeps from the right edge of the scrollport, if any.
+    pub right: Option<f32>,
+    /// The distance the element keeps from the bottom edge of the scrollport, if any.
+    pub bottom: Option<f32>,
+    /// The distance the element keeps from the left edge of the scrollport, if any.
+    pub left: Option<f32>,
+}
+
+/// The scrollport of a layer whose contents scroll independently of the rest of the page, as they
+/// do for elements with `overflow: scroll` or `overflow: auto`. The compositor puts such a layer
+/// inside a scroll root layer that clips it to the scrollport and handles scroll events over it.
+#[deriving(Clone, PartialEq)]
+pub struct Scrollport {
+    /// The ID of the scroll root layer.
+    pub id: LayerId,
+    /// The scrollport, in pixels relative to the origin of the layer.
+    pub rect: Rect<f32>,
+}
+
+/// How the contents of a layer are divided into pages in paginated layout. Page `k` starts `k`
+/// page heights below the top of the layer.
+#[deriving(Clone, PartialEq)]
+pub struct Pagination {
+    /// The size of each page box, in pixels.
+    pub page_size: Size2D<f32>,
+    /// The number of pages.
+    pub page_count: uint,
 }
 
 /// All layer-specific information that the painting task sends to the compositor other than the
@@ -79,6 +144,10 @@ pub struct LayerMetadata {
     pub background_color: Color,
     /// The scrolling policy of this layer.
     pub scroll_policy: ScrollPolicy,
+    /// The scrollport of this layer, if its contents scroll independently.
+    pub scrollport: Option<Scrollport>,
+    /// The pages of this layer, if it is the root layer of a paginated document.
+    pub pagination: Option<Pagination>,
 }
 
 /// The interface used by the renderer to acquire draw targets for each render frame and
@@ -111,6 +180,10 @@ pub trait ScriptListener {
                              pipeline_id: PipelineId,
                              layer_id: LayerId,
                              point: Point2D<f32>);
+    fn set_scroll_position(&mut self,
+                           pipeline_id: PipelineId,
+                           layer_id: LayerId,
+                           point: Point2D<f32>);
     fn close(&mut self);
     fn dup(&mut self) -> Box<ScriptListener+'static>;
 }
@@ -13,6 +13,7 @@ use http::method::{Method, Get};
 use layers::geometry::DevicePixel;
 use servo_util::geometry::{PagePx, ViewportPx};
 use servo_util::str::DOMString;
+use std::any::Any;
 use std::comm::{channel, Sender, Receiver};
 use url::Url;
 
@@ -207,6 +208,50 @@ pub enum Msg {
     ResizedWindowMsg(WindowSizeData),
     KeyEvent(Key, KeyState, KeyModifiers),
     StorageEventMsg(Url, PipelineId, Option<DOMString>, Option<DOMString>, Option<DOMString>),
+    /// Moves through the joint session history by the given number of entries.
+    TraverseHistoryMsg(int),
+    /// Adds a same-document entry for the given pipeline after the current entry.
+    PushHistoryStateMsg(PipelineId, HistoryState),
+    /// Replaces the current same-document entry of the given pipeline.
+    ReplaceHistoryStateMsg(PipelineId, HistoryState),
+    /// Notifies the compositor whether the given pipeline has animation frame callbacks waiting
+    /// for a tick.
+    ChangeRunningAnimationsStateMsg(PipelineId, AnimationState),
+    /// A tick from the compositor, for the animation frame callbacks of the given pipeline.
+    TickAnimationMsg(PipelineId),
+    /// Posts a message to the window of the given pipeline, from `window.postMessage()`.
+    PostMessageMsg(PipelineId, PostedMessage),
+}
+
+/// Whether a pipeline needs ticks to run its animation frame callbacks.
+#[deriving(Clone, PartialEq, Show)]
+pub enum AnimationState {
+    AnimationCallbacksPresent,
+    NoAnimationCallbacksPresent,
+}
+
+/// The state of a same-document session history entry, as given to `history.pushState()` and
+/// `history.replaceState()`.
+#[deriving(Clone)]
+pub struct HistoryState {
+    /// The URL of the document for this entry.
+    pub url: Url,
+    /// The state object of this entry, as written by `JS_WriteStructuredClone`.
+    pub data: Option<Vec<u64>>,
+}
+
+/// A message posted to a window with `window.postMessage()`.
+pub struct PostedMessage {
+    /// The pipeline of the window that posted the message.
+    pub source: PipelineId,
+    /// The serialized origin of the document that posted the message.
+    pub origin: DOMString,
+    /// The origin the target document must have for the message to be delivered, if any.
+    pub target_origin: Option<Url>,
+    /// The message, as written by `JS_WriteStructuredClone`.
+    pub data: Vec<u64>,
+    /// The ports transferred with the message, which only the script task knows the type of.
+    pub ports: Box<Any+Send>,
 }
 
 /// Similar to net::resource_task::LoadData
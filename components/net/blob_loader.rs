@@ -0,0 +1,124 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use resource_task::{Done, Payload, Metadata, LoadData, TargetedLoadResponse, start_sending, ResponseSenders};
+
+use http::headers::test_utils::from_stream_with_str;
+use http::headers::content_type::MediaType;
+use std::collections::HashMap;
+use url::Url;
+
+/// The contents and content type of each blob registered through `URL.createObjectURL`,
+/// keyed by its URL.
+pub type BlobURLStore = HashMap<String, (Vec<u8>, String)>;
+
+/// Returns the key of a `blob:` URL in the store; the fragment is not part of it.
+/// http://dev.w3.org/2006/webapi/FileAPI/#dereferencing-blob-urls
+pub fn store_key(url: &Url) -> String {
+    let mut url = url.clone();
+    url.fragment = None;
+    url.serialize()
+}
+
+/// Returns the serialized origin of the given URL. The origin of a `blob:` URL is the one it was
+/// created in, which is the part of it before the last `/`.
+fn serialize_origin(url: &Url) -> String {
+    if url.scheme.as_slice() == "blob" {
+        let data = url.non_relative_scheme_data().unwrap_or("");
+        return data.slice_to(data.rfind('/').unwrap_or(0)).to_string();
+    }
+    let mut origin = url.scheme.clone();
+    origin.push_str("://");
+    url.serialize_host().map(|ref host| origin.push_str(host.as_slice()));
+    url.port().map(|port| origin.push_str(format!(":{}", port).as_slice()));
+    origin
+}
+
+pub fn factory(load_data: LoadData, start_chan: Sender<TargetedLoadResponse>,
+               store: &BlobURLStore) {
+    let url = load_data.url;
+    assert!("blob" == url.scheme.as_slice());
+
+    let senders = ResponseSenders {
+        immediate_consumer: start_chan,
+        eventual_consumer: load_data.consumer,
+    };
+
+    // FIXME: loads that are not made for a document or worker, such as those of the image
+    // cache, carry no origin and are not checked.
+    let same_origin = load_data.origin.as_ref().map_or(true, |origin| {
+        serialize_origin(origin) == serialize_origin(&url)
+    });
+    if !same_origin {
+        start_sending(senders, Metadata::default(url))
+            .send(Done(Err("cross-origin blob url".to_string())));
+        return;
+    }
+
+    match store.get(&store_key(&url)) {
+        Some(&(ref bytes, ref content_type)) => {
+            let mut metadata = Metadata::default(url);
+            if !content_type.is_empty() {
+                let content_type: Option<MediaType> =
+                    from_stream_with_str(content_type.as_slice());
+                metadata.set_content_type(&content_type);
+            }
+            let progress_chan = start_sending(senders, metadata);
+            progress_chan.send(Payload(bytes.clone()));
+            progress_chan.send(Done(Ok(())));
+        }
+        None => {
+            start_sending(senders, Metadata::default(url))
+                .send(Done(Err("unknown blob url".to_string())));
+        }
+    }
+}
+
+#[test]
+fn test_register_and_revoke() {
+    use resource_task::{new_resource_task, Load, RegisterBlobURL, RevokeBlobURL, Exit};
+    use std::comm::channel;
+
+    let resource_task = new_resource_task(None);
+    let url = Url::parse("blob:http://example.com/1234").unwrap();
+    resource_task.send(RegisterBlobURL(url.clone(), vec!(1, 2, 3), "text/plain".to_string()));
+
+    let (start_chan, start) = channel();
+    let with_fragment = Url::parse("blob:http://example.com/1234#frag").unwrap();
+    resource_task.send(Load(LoadData::new(with_fragment, start_chan)));
+    let response = start.recv();
+    assert_eq!(response.metadata.content_type, Some(("text".to_string(), "plain".to_string())));
+    assert_eq!(response.progress_port.recv(), Payload(vec!(1, 2, 3)));
+    assert_eq!(response.progress_port.recv(), Done(Ok(())));
+
+    // Other origins can't load it.
+    let (start_chan, start) = channel();
+    let mut load_data = LoadData::new(url.clone(), start_chan);
+    load_data.origin = Some(Url::parse("http://example.org/page.html").unwrap());
+    resource_task.send(Load(load_data));
+    let response = start.recv();
+    match response.progress_port.recv() {
+        Done(result) => assert!(result.is_err()),
+        _ => panic!("expected a failed load"),
+    }
+
+    // The origin that created it can.
+    let (start_chan, start) = channel();
+    let mut load_data = LoadData::new(url.clone(), start_chan);
+    load_data.origin = Some(Url::parse("http://example.com/page.html").unwrap());
+    resource_task.send(Load(load_data));
+    let response = start.recv();
+    assert_eq!(response.progress_port.recv(), Payload(vec!(1, 2, 3)));
+    assert_eq!(response.progress_port.recv(), Done(Ok(())));
+
+    resource_task.send(RevokeBlobURL(url.clone()));
+    let (start_chan, start) = channel();
+    resource_task.send(Load(LoadData::new(url, start_chan)));
+    let response = start.recv();
+    match response.progress_port.recv() {
+        Done(result) => assert!(result.is_err()),
+        _ => panic!("expected a failed load"),
+    }
+    resource_task.send(Exit);
+}
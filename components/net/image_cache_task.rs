@@ -568,6 +568,7 @@ mod tests {
                             Url::parse("file:///fake").unwrap()));
                         on_load.invoke(chan);
                     }
+                    resource_task::RegisterBlobURL(..) | resource_task::RevokeBlobURL(..) => (),
                     resource_task::Exit => break
                 }
             }
@@ -726,6 +727,7 @@ mod tests {
                         chan.send(resource_task::Done(Ok(())));
                         image_bin_sent_chan.send(());
                     }
+                    resource_task::RegisterBlobURL(..) | resource_task::RevokeBlobURL(..) => (),
                     resource_task::Exit => {
                         resource_task_exited_chan.send(());
                         break
@@ -778,6 +780,7 @@ mod tests {
                         chan.send(resource_task::Done(Err("".to_string())));
                         image_bin_sent_chan.send(());
                     }
+                    resource_task::RegisterBlobURL(..) | resource_task::RevokeBlobURL(..) => (),
                     resource_task::Exit => {
                         resource_task_exited_chan.send(());
                         break
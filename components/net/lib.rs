@@ -10,6 +10,8 @@
 extern crate collections;
 extern crate geom;
 extern crate http;
+extern crate libc;
+extern crate openssl;
 extern crate png;
 #[phase(plugin, link)]
 extern crate log;
@@ -31,6 +33,7 @@ pub mod image {
 }
 
 pub mod about_loader;
+pub mod blob_loader;
 pub mod file_loader;
 pub mod http_loader;
 pub mod data_loader;
@@ -38,6 +41,7 @@ pub mod image_cache_task;
 pub mod local_image_cache;
 pub mod resource_task;
 pub mod storage_task;
+pub mod websocket;
 mod sniffer_task;
 
 /// An implementation of the [Fetch spec](http://fetch.spec.whatwg.org/)
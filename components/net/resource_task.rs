@@ -5,12 +5,15 @@
 //! A task that takes a URL and streams back the binary data.
 
 use about_loader;
+use blob_loader;
+use blob_loader::BlobURLStore;
 use data_loader;
 use file_loader;
 use http_loader;
 use sniffer_task;
 use sniffer_task::SnifferTask;
 
+use std::collections::HashMap;
 use std::comm::{channel, Receiver, Sender};
 use http::headers::content_type::MediaType;
 use http::headers::response::HeaderCollection as ResponseHeaderCollection;
@@ -26,6 +29,10 @@ use servo_util::task::spawn_named;
 pub enum ControlMsg {
     /// Request the data associated with a particular URL
     Load(LoadData),
+    /// Make the given contents and content type available at a `blob:` URL
+    RegisterBlobURL(Url, Vec<u8>, String),
+    /// Stop serving the contents registered at a `blob:` URL
+    RevokeBlobURL(Url),
     Exit
 }
 
@@ -36,6 +43,9 @@ pub struct LoadData {
     pub headers: RequestHeaderCollection,
     pub data: Option<Vec<u8>>,
     pub cors: Option<ResourceCORSData>,
+    /// The URL of the document or worker the load is made for, if any. `blob:` URLs may only
+    /// be loaded for the origin that created them.
+    pub origin: Option<Url>,
     pub consumer: Sender<LoadResponse>,
 }
 
@@ -47,6 +57,7 @@ impl LoadData {
             headers: RequestHeaderCollection::new(),
             data: None,
             cors: None,
+            origin: None,
             consumer: consumer,
         }
     }
@@ -161,11 +172,14 @@ pub fn start_sending_opt(senders: ResponseSenders, metadata: Metadata) -> Result
     }
 }
 
-/// Convenience function for synchronously loading a whole resource.
-pub fn load_whole_resource(resource_task: &ResourceTask, url: Url)
+/// Convenience function for synchronously loading a whole resource, for the document or worker
+/// at `origin` if there is one.
+pub fn load_whole_resource(resource_task: &ResourceTask, url: Url, origin: Option<Url>)
         -> Result<(Metadata, Vec<u8>), String> {
     let (start_chan, start_port) = channel();
-    resource_task.send(Load(LoadData::new(url, start_chan)));
+    let mut load_data = LoadData::new(url, start_chan);
+    load_data.origin = origin;
+    resource_task.send(Load(load_data));
     let response = start_port.recv();
 
     let mut buf = vec!();
@@ -186,7 +200,8 @@ pub fn new_resource_task(user_agent: Option<String>) -> ResourceTask {
     let (setup_chan, setup_port) = channel();
     let sniffer_task = sniffer_task::new_sniffer_task();
     spawn_named("ResourceManager", proc() {
-        ResourceManager::new(setup_port, user_agent, sniffer_task).start();
+        let mut resource_manager = ResourceManager::new(setup_port, user_agent, sniffer_task);
+        resource_manager.start();
     });
     setup_chan
 }
@@ -195,6 +210,7 @@ struct ResourceManager {
     from_client: Receiver<ControlMsg>,
     user_agent: Option<String>,
     sniffer_task: SnifferTask,
+    blob_urls: BlobURLStore,
 }
 
 impl ResourceManager {
@@ -203,18 +219,25 @@ impl ResourceManager {
             from_client: from_client,
             user_agent: user_agent,
             sniffer_task: sniffer_task,
+            blob_urls: HashMap::new(),
         }
     }
 }
 
 
 impl ResourceManager {
-    fn start(&self) {
+    fn start(&mut self) {
         loop {
             match self.from_client.recv() {
               Load(load_data) => {
                 self.load(load_data)
               }
+              RegisterBlobURL(url, bytes, content_type) => {
+                self.blob_urls.insert(blob_loader::store_key(&url), (bytes, content_type));
+              }
+              RevokeBlobURL(url) => {
+                self.blob_urls.remove(&blob_loader::store_key(&url));
+              }
               Exit => {
                 break
               }
@@ -230,11 +253,16 @@ impl ResourceManager {
             eventual_consumer: load_data.consumer.clone(),
         };
 
-        let loader = match load_data.url.scheme.as_slice() {
+        let scheme = load_data.url.scheme.clone();
+        let loader = match scheme.as_slice() {
             "file" => file_loader::factory,
             "http" | "https" => http_loader::factory,
             "data" => data_loader::factory,
             "about" => about_loader::factory,
+            "blob" => {
+                blob_loader::factory(load_data, self.sniffer_task.clone(), &self.blob_urls);
+                return
+            }
             _ => {
                 debug!("resource_task: no loader for scheme {:s}", load_data.url.scheme);
                 start_sending(senders, Metadata::default(load_data.url))
@@ -0,0 +1,1175 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A WebSocket client, as specified by [RFC 6455](http://tools.ietf.org/html/rfc6455).
+//!
+//! Each connection runs on its own task, which owns the network stream and writes the messages
+//! queued by its owner. A second task blocks on reads from the socket and hands the data it reads
+//! over to the first one, which decrypts it when the stream is wrapped in TLS, so that a single
+//! TLS session can be used for both directions.
+
+use libc::{c_int, c_long, c_void};
+use openssl::nid;
+use openssl::crypto::hash::{hash, SHA1};
+use openssl::ssl::{Ssl, SslContext, SslStream, SslVerifyPeer, Sslv23};
+use serialize::base64::{ToBase64, STANDARD};
+use servo_util::task::spawn_named;
+use std::ascii::AsciiExt;
+use std::cmp::min;
+use std::comm::Select;
+use std::io::{IoError, IoResult, ResourceUnavailable, TcpStream};
+use std::io::fs::PathExtensions;
+use std::io::timer::Timer;
+use std::ptr::null_mut;
+use std::rand::{Rng, task_rng};
+use std::slice::bytes::copy_memory;
+use std::slice::raw::buf_as_slice;
+use std::time::Duration;
+use url::Url;
+
+#[cfg(test)]
+use std::io::{Acceptor, Listener, TcpListener};
+
+/// The status code for a normal closure.
+pub const NORMAL_CLOSURE: u16 = 1000;
+/// The status code sent when the other endpoint violated the protocol.
+pub const PROTOCOL_ERROR: u16 = 1002;
+/// The status code reported when a close frame carried no status code. Never sent.
+pub const NO_STATUS_RECEIVED: u16 = 1005;
+/// The status code reported when the connection closed without a close frame. Never sent.
+pub const ABNORMAL_CLOSURE: u16 = 1006;
+/// The status code sent when a text message was not valid UTF-8.
+pub const INVALID_FRAME_PAYLOAD_DATA: u16 = 1007;
+/// The status code sent when a message was too big to process.
+pub const MESSAGE_TOO_BIG: u16 = 1009;
+
+const CONTINUATION_OPCODE: u8 = 0x0;
+const TEXT_OPCODE: u8 = 0x1;
+const BINARY_OPCODE: u8 = 0x2;
+const CLOSE_OPCODE: u8 = 0x8;
+const PING_OPCODE: u8 = 0x9;
+const PONG_OPCODE: u8 = 0xA;
+
+/// The GUID that the server appends to the client's key to compute `Sec-WebSocket-Accept`.
+const ACCEPT_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest response to the opening handshake that we accept.
+const MAX_HANDSHAKE_SIZE: uint = 16 * 1024;
+/// The largest message, possibly made of several fragments, that we accept.
+const MAX_MESSAGE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// How long the opening handshake may take, in milliseconds.
+const HANDSHAKE_TIMEOUT: u64 = 30 * 1000;
+/// How long we wait for the server to answer our close frame, in milliseconds.
+const CLOSE_TIMEOUT: i64 = 5 * 1000;
+
+/// Where the bundle of trusted CA certificates lives on common systems.
+static CA_BUNDLE_PATHS: [&'static str, ..3] = [
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/cert.pem",
+];
+
+/// Events sent by a connection to its owner. `ClosedEvent` is always the last one.
+#[deriving(PartialEq, Show)]
+pub enum WebSocketEvent {
+    /// The opening handshake completed, with the subprotocol selected by the server, if any.
+    OpenedEvent(Option<String>),
+    /// A complete text message was received.
+    TextMessageEvent(String),
+    /// A complete binary message was received.
+    BinaryMessageEvent(Vec<u8>),
+    /// The given number of bytes of application data were written to the network.
+    BytesSentEvent(uint),
+    /// The connection failed, either during the opening handshake or because the server
+    /// violated the protocol.
+    FailedEvent,
+    /// The connection closed. Carries whether the closing handshake completed, and the status
+    /// code and reason sent by the server.
+    ClosedEvent(bool, u16, String),
+}
+
+/// Messages sent by the owner of a connection.
+pub enum WebSocketControlMsg {
+    /// Sends a text message.
+    SendTextMsg(String),
+    /// Sends a binary message.
+    SendBinaryMsg(Vec<u8>),
+    /// Starts the closing handshake, with an optional status code and a reason.
+    CloseMsg(Option<u16>, String),
+}
+
+/// Connects to the given `ws://` or `wss://` URL on a new task, asking for the given
+/// subprotocol, if any. Returns the channel used to control the connection and the channel on
+/// which its events are delivered.
+pub fn connect(url: Url, protocol: Option<String>, origin: String)
+               -> (Sender<WebSocketControlMsg>, Receiver<WebSocketEvent>) {
+    let (control_sender, control_receiver) = channel();
+    let (event_sender, event_receiver) = channel();
+    spawn_named("WebSocket", proc() {
+        let (stream, leftover, protocol) = match open(&url, &protocol, origin.as_slice()) {
+            Ok(result) => result,
+            Err(message) => {
+                debug!("websocket: failed to connect to {}: {}", url, message);
+                event_sender.send(FailedEvent);
+                event_sender.send(ClosedEvent(false, ABNORMAL_CLOSURE, String::new()));
+                return
+            }
+        };
+        event_sender.send(OpenedEvent(protocol));
+
+        let mut connection = Connection {
+            stream: stream,
+            buffer: leftover,
+            message: None,
+            closing: false,
+            events: event_sender,
+        };
+        connection.run(control_receiver);
+    });
+    (control_sender, event_receiver)
+}
+
+/// The transport under a TLS stream. During the opening handshake reads block on the socket;
+/// afterwards the socket belongs to the reader task, and reads only return the data that was
+/// handed over by `Stream::decode`.
+struct TlsTransport {
+    socket: TcpStream,
+    incoming: Vec<u8>,
+    handshaking: bool,
+}
+
+impl Reader for TlsTransport {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        if self.handshaking {
+            return self.socket.read(buf)
+        }
+        if self.incoming.is_empty() {
+            return Err(IoError {
+                kind: ResourceUnavailable,
+                desc: "no data was read from the socket yet",
+                detail: None,
+            })
+        }
+        let length = min(buf.len(), self.incoming.len());
+        copy_memory(buf, self.incoming.slice_to(length));
+        self.incoming = self.incoming.slice_from(length).to_vec();
+        Ok(length)
+    }
+}
+
+impl Writer for TlsTransport {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.socket.write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.socket.flush()
+    }
+}
+
+/// The network stream of a connection.
+enum Stream {
+    PlainStream(TcpStream),
+    TlsStream(SslStream<TlsTransport>),
+}
+
+impl Stream {
+    fn socket<'a>(&'a mut self) -> &'a mut TcpStream {
+        match *self {
+            PlainStream(ref mut stream) => stream,
+            TlsStream(ref mut stream) => &mut stream.get_mut().socket,
+        }
+    }
+
+    /// Ends the opening handshake, and starts the task that reads the socket. Everything it
+    /// reads is sent to the returned receiver, which is disconnected once the socket closes.
+    fn start_reader(&mut self) -> Receiver<Vec<u8>> {
+        match *self {
+            TlsStream(ref mut stream) => stream.get_mut().handshaking = false,
+            PlainStream(_) => {}
+        }
+        let socket = self.socket();
+        socket.set_read_timeout(None);
+        let mut reader = socket.clone();
+        let (sender, receiver) = channel();
+        spawn_named("WebSocket reader", proc() {
+            let mut buf = [0u8, ..4096];
+            loop {
+                match reader.read(buf.as_mut_slice()) {
+                    Ok(length) => {
+                        if sender.send_opt(buf.slice_to(length).to_vec()).is_err() {
+                            return
+                        }
+                    }
+                    Err(_) => return,
+                }
+            }
+        });
+        receiver
+    }
+
+    /// Turns data read off the socket into application data, which is only different from it
+    /// when the stream is wrapped in TLS.
+    fn decode(&mut self, data: Vec<u8>) -> Vec<u8> {
+        match *self {
+            PlainStream(_) => data,
+            TlsStream(ref mut stream) => {
+                stream.get_mut().incoming.push_all(data.as_slice());
+                let mut decoded = vec!();
+                let mut buf = [0u8, ..4096];
+                loop {
+                    match stream.read(buf.as_mut_slice()) {
+                        Ok(length) => decoded.push_all(buf.slice_to(length)),
+                        Err(_) => return decoded,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Reader for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        match *self {
+            PlainStream(ref mut stream) => stream.read(buf),
+            TlsStream(ref mut stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Writer for Stream {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        match *self {
+            PlainStream(ref mut stream) => stream.write(buf),
+            TlsStream(ref mut stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match *self {
+            PlainStream(ref mut stream) => stream.flush(),
+            TlsStream(ref mut stream) => stream.flush(),
+        }
+    }
+}
+
+/// Whether a name from a certificate matches the host. A wildcard may only stand for the whole
+/// leftmost label (RFC 6125 § 6.4.3).
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_ascii_lower();
+    let host = host.to_ascii_lower();
+    if pattern.as_slice().starts_with("*.") {
+        match host.as_slice().find('.') {
+            Some(dot) => dot > 0 && host.as_slice().slice_from(dot) == pattern.slice_from(1),
+            None => false,
+        }
+    } else {
+        pattern == host
+    }
+}
+
+/// Starts a TLS session on the socket, checking that the server's certificate is signed by a
+/// trusted CA and was issued for the host.
+fn connect_tls(mut socket: TcpStream, host: &str) -> Result<SslStream<TlsTransport>, String> {
+    let mut context = match SslContext::new(Sslv23) {
+        Ok(context) => context,
+        Err(error) => return Err(format!("{}", error)),
+    };
+    context.set_verify(SslVerifyPeer, None);
+    let ca_bundle = match CA_BUNDLE_PATHS.iter().map(|path| Path::new(*path))
+                                         .find(|path| path.exists()) {
+        Some(path) => path,
+        None => return Err("no CA certificate bundle found".to_string()),
+    };
+    match context.set_CA_file(&ca_bundle) {
+        Some(error) => return Err(format!("{}", error)),
+        None => {}
+    }
+
+    let ssl = match Ssl::new(&context) {
+        Ok(ssl) => ssl,
+        Err(error) => return Err(format!("{}", error)),
+    };
+    // Servers that host several names need SNI to pick the right certificate.
+    match ssl.set_hostname(host) {
+        Ok(()) => {}
+        Err(error) => return Err(format!("{}", error)),
+    }
+
+    socket.set_read_timeout(Some(HANDSHAKE_TIMEOUT));
+    let transport = TlsTransport {
+        socket: socket,
+        incoming: vec!(),
+        handshaking: true,
+    };
+    let stream = match SslStream::new_from(ssl, transport) {
+        Ok(stream) => stream,
+        Err(error) => return Err(format!("{}", error)),
+    };
+
+    // OpenSSL verified the chain of the certificate, but not the name it was issued for.
+    let certificate = match stream.get_peer_certificate() {
+        Some(certificate) => certificate,
+        None => return Err("the server sent no certificate".to_string()),
+    };
+    let dns_names = unsafe { subject_alt_dns_names(certificate.get_handle() as *mut c_void) };
+    let common_name = certificate.subject_name().text_by_nid(nid::CN)
+                                 .map(|name| name.as_slice().to_string());
+    if certificate_matches(dns_names, common_name, host) {
+        Ok(stream)
+    } else {
+        Err(format!("the certificate was not issued for {}", host))
+    }
+}
+
+/// Whether a certificate was issued for the host, given the DNS names of its subjectAltName
+/// extension, or `None` if it has no such extension, and its subject common name. The common name
+/// is only looked at when there is no subjectAltName extension (RFC 6125 § 6.4.4).
+fn certificate_matches(dns_names: Option<Vec<String>>, common_name: Option<String>, host: &str)
+                       -> bool {
+    match dns_names {
+        Some(dns_names) => dns_names.iter().any(|name| host_matches(name.as_slice(), host)),
+        None => common_name.map_or(false, |name| host_matches(name.as_slice(), host)),
+    }
+}
+
+// The parts of OpenSSL needed to read the subjectAltName extension of a certificate, which the
+// openssl crate does not expose.
+const NID_SUBJECT_ALT_NAME: c_int = 85;
+const GEN_DNS: c_int = 2;
+
+#[repr(C)]
+struct Asn1String {
+    length: c_int,
+    kind: c_int,
+    data: *const u8,
+    flags: c_long,
+}
+
+#[repr(C)]
+struct GeneralName {
+    kind: c_int,
+    value: *const c_void,
+}
+
+extern {
+    fn X509_get_ext_d2i(certificate: *mut c_void, nid: c_int, critical: *mut c_int,
+                        index: *mut c_int) -> *mut c_void;
+    fn sk_num(stack: *const c_void) -> c_int;
+    fn sk_value(stack: *const c_void, index: c_int) -> *const c_void;
+    fn GENERAL_NAMES_free(names: *mut c_void);
+}
+
+/// Returns the DNS names in the subjectAltName extension of the given OpenSSL certificate, or
+/// `None` if it has no such extension. Names that are not valid UTF-8 are skipped.
+unsafe fn subject_alt_dns_names(certificate: *mut c_void) -> Option<Vec<String>> {
+    let names = X509_get_ext_d2i(certificate, NID_SUBJECT_ALT_NAME, null_mut(), null_mut());
+    if names.is_null() {
+        return None
+    }
+    let mut dns_names = vec!();
+    for index in range(0, sk_num(names as *const c_void)) {
+        let name = sk_value(names as *const c_void, index) as *const GeneralName;
+        if (*name).kind != GEN_DNS {
+            continue
+        }
+        let string = (*name).value as *const Asn1String;
+        let bytes = buf_as_slice((*string).data, (*string).length as uint, |bytes| bytes.to_vec());
+        match String::from_utf8(bytes) {
+            Ok(dns_name) => dns_names.push(dns_name),
+            Err(_) => {}
+        }
+    }
+    GENERAL_NAMES_free(names);
+    Some(dns_names)
+}
+
+/// Opens the stream and performs the opening handshake. Returns the stream, the bytes read past
+/// the end of the handshake, and the subprotocol selected by the server.
+fn open(url: &Url, protocol: &Option<String>, origin: &str)
+        -> Result<(Stream, Vec<u8>, Option<String>), String> {
+    let secure = match url.scheme.as_slice() {
+        "ws" => false,
+        "wss" => true,
+        _ => return Err("unsupported scheme".to_string()),
+    };
+    let host = match url.serialize_host() {
+        Some(host) => host,
+        None => return Err("missing host".to_string()),
+    };
+    let port = url.port().unwrap_or(if secure { 443 } else { 80 });
+
+    let mut socket = match TcpStream::connect((host.as_slice(), port)) {
+        Ok(socket) => socket,
+        Err(error) => return Err(error.to_string()),
+    };
+    let mut stream = if secure {
+        TlsStream(try!(connect_tls(socket, host.as_slice())))
+    } else {
+        socket.set_read_timeout(Some(HANDSHAKE_TIMEOUT));
+        PlainStream(socket)
+    };
+
+    let mut key = [0u8, ..16];
+    task_rng().fill_bytes(key.as_mut_slice());
+    let key = key.as_slice().to_base64(STANDARD);
+
+    let mut resource = url.serialize_path().unwrap_or("/".to_string());
+    match url.query {
+        Some(ref query) => {
+            resource.push('?');
+            resource.push_str(query.as_slice());
+        }
+        None => {}
+    }
+    let host_header = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.clone(),
+    };
+
+    let mut request = format!("GET {} HTTP/1.1\r\n\
+                               Host: {}\r\n\
+                               Upgrade: websocket\r\n\
+                               Connection: Upgrade\r\n\
+                               Sec-WebSocket-Key: {}\r\n\
+                               Sec-WebSocket-Version: 13\r\n\
+                               Origin: {}\r\n",
+                              resource, host_header, key, origin);
+    match *protocol {
+        Some(ref protocol) => request.push_str(format!("Sec-WebSocket-Protocol: {}\r\n",
+                                                       protocol).as_slice()),
+        None => {}
+    }
+    request.push_str("\r\n");
+    match stream.write(request.as_bytes()).and_then(|_| stream.flush()) {
+        Ok(()) => {}
+        Err(error) => return Err(error.to_string()),
+    }
+
+    // Read until the end of the response headers; anything after them is already framed data.
+    let mut response = vec!();
+    let mut buf = [0u8, ..4096];
+    let header_end;
+    loop {
+        match find_header_end(response.as_slice()) {
+            Some(end) => {
+                header_end = end;
+                break
+            }
+            None => {}
+        }
+        if response.len() > MAX_HANDSHAKE_SIZE {
+            return Err("handshake response too large".to_string())
+        }
+        match stream.read(buf.as_mut_slice()) {
+            Ok(length) => response.push_all(buf.slice_to(length)),
+            Err(error) => return Err(error.to_string()),
+        }
+    }
+
+    let selected_protocol = try!(check_handshake_response(response.slice_to(header_end),
+                                                          key.as_slice(),
+                                                          protocol));
+    Ok((stream, response.slice_from(header_end).to_vec(), selected_protocol))
+}
+
+/// Returns the offset just past the blank line that ends the HTTP headers, if there is one.
+fn find_header_end(response: &[u8]) -> Option<uint> {
+    response.windows(4).position(|window| window == b"\r\n\r\n").map(|position| position + 4)
+}
+
+/// Checks the server's response to the opening handshake (RFC 6455 § 4.1), and returns the
+/// subprotocol it selected.
+fn check_handshake_response(response: &[u8], key: &str, protocol: &Option<String>)
+                            -> Result<Option<String>, String> {
+    let response = match String::from_utf8(response.to_vec()) {
+        Ok(response) => response,
+        Err(_) => return Err("handshake response is not UTF-8".to_string()),
+    };
+    let mut lines = response.as_slice().split_str("\r\n");
+
+    let status_line = lines.next().unwrap_or("");
+    if status_line.split(' ').nth(1) != Some("101") {
+        return Err(format!("unexpected status line: {}", status_line))
+    }
+
+    let mut upgrade = false;
+    let mut connection = false;
+    let mut accepted = false;
+    let mut selected_protocol = None;
+    for line in lines {
+        let colon = match line.find(':') {
+            Some(colon) => colon,
+            None => continue,
+        };
+        let name = line.slice_to(colon).trim();
+        let value = line.slice_from(colon + 1).trim();
+        if name.eq_ignore_ascii_case("upgrade") {
+            upgrade = value.eq_ignore_ascii_case("websocket");
+        } else if name.eq_ignore_ascii_case("connection") {
+            connection = value.split(',').any(|token| token.trim().eq_ignore_ascii_case("upgrade"));
+        } else if name.eq_ignore_ascii_case("sec-websocket-accept") {
+            accepted = value == accept_key(key).as_slice();
+        } else if name.eq_ignore_ascii_case("sec-websocket-protocol") {
+            selected_protocol = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("sec-websocket-extensions") {
+            // We never ask for extensions, so the server must not select any.
+            return Err("server selected an extension".to_string())
+        }
+    }
+
+    if !upgrade || !connection {
+        return Err("missing upgrade headers".to_string())
+    }
+    if !accepted {
+        return Err("missing or invalid Sec-WebSocket-Accept".to_string())
+    }
+    match selected_protocol {
+        Some(ref selected) if Some(selected) != protocol.as_ref() => {
+            return Err(format!("server selected an unrequested subprotocol: {}", selected))
+        }
+        _ => {}
+    }
+    Ok(selected_protocol)
+}
+
+/// Computes the `Sec-WebSocket-Accept` value that the server must send for the given key.
+fn accept_key(key: &str) -> String {
+    let mut input = key.to_string();
+    input.push_str(ACCEPT_GUID);
+    hash(SHA1, input.as_bytes()).as_slice().to_base64(STANDARD)
+}
+
+/// A single frame, with its payload unmasked.
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// Parses the frame at the start of the buffer (RFC 6455 § 5.2). Returns the frame and its
+/// length in the buffer, `None` if the buffer does not hold a whole frame yet, or the status
+/// code to fail the connection with. Frames sent by servers must not be masked and frames sent
+/// by clients must be.
+fn parse_frame(buffer: &[u8], expect_masked: bool) -> Result<Option<(Frame, uint)>, u16> {
+    if buffer.len() < 2 {
+        return Ok(None)
+    }
+
+    let fin = buffer[0] & 0x80 != 0;
+    // No extensions are negotiated, so the reserved bits must be clear.
+    if buffer[0] & 0x70 != 0 {
+        return Err(PROTOCOL_ERROR)
+    }
+    let opcode = buffer[0] & 0x0F;
+    match opcode {
+        CONTINUATION_OPCODE | TEXT_OPCODE | BINARY_OPCODE |
+        CLOSE_OPCODE | PING_OPCODE | PONG_OPCODE => {}
+        _ => return Err(PROTOCOL_ERROR),
+    }
+    let masked = buffer[1] & 0x80 != 0;
+    if masked != expect_masked {
+        return Err(PROTOCOL_ERROR)
+    }
+
+    let mut offset = 2u;
+    let length = match buffer[1] & 0x7F {
+        126 => {
+            if buffer.len() < 4 {
+                return Ok(None)
+            }
+            offset = 4;
+            (buffer[2] as u64 << 8) | buffer[3] as u64
+        }
+        127 => {
+            if buffer.len() < 10 {
+                return Ok(None)
+            }
+            offset = 10;
+            let mut length = 0u64;
+            for &byte in buffer.slice(2, 10).iter() {
+                length = (length << 8) | byte as u64;
+            }
+            if length >> 63 != 0 {
+                return Err(PROTOCOL_ERROR)
+            }
+            length
+        }
+        length => length as u64,
+    };
+    // Control frames cannot be fragmented and carry at most 125 bytes.
+    if opcode >= CLOSE_OPCODE && (!fin || length > 125) {
+        return Err(PROTOCOL_ERROR)
+    }
+    if length > MAX_MESSAGE_SIZE {
+        return Err(MESSAGE_TOO_BIG)
+    }
+
+    let mask = if masked {
+        if buffer.len() < offset + 4 {
+            return Ok(None)
+        }
+        let mask = [buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]];
+        offset += 4;
+        Some(mask)
+    } else {
+        None
+    };
+
+    let length = length as uint;
+    if buffer.len() < offset + length {
+        return Ok(None)
+    }
+    let mut payload = buffer.slice(offset, offset + length).to_vec();
+    match mask {
+        Some(mask) => {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+        None => {}
+    }
+
+    let frame = Frame {
+        fin: fin,
+        opcode: opcode,
+        payload: payload,
+    };
+    Ok(Some((frame, offset + length)))
+}
+
+/// Encodes an unfragmented frame, masking it with the given key if there is one.
+fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8, ..4]>) -> Vec<u8> {
+    let mut frame = vec!(0x80 | opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    let length = payload.len();
+    if length < 126 {
+        frame.push(mask_bit | length as u8);
+    } else if length < 65536 {
+        frame.push(mask_bit | 126);
+        frame.push((length >> 8) as u8);
+        frame.push(length as u8);
+    } else {
+        frame.push(mask_bit | 127);
+        for i in range(0u, 8).rev() {
+            frame.push(((length as u64) >> (i * 8)) as u8);
+        }
+    }
+
+    match mask {
+        Some(mask) => {
+            frame.push_all(mask.as_slice());
+            for (i, byte) in payload.iter().enumerate() {
+                frame.push(*byte ^ mask[i % 4]);
+            }
+        }
+        None => frame.push_all(payload),
+    }
+    frame
+}
+
+/// Builds the payload of a close frame.
+fn close_payload(code: Option<u16>, reason: &str) -> Vec<u8> {
+    match code {
+        Some(code) => {
+            let mut payload = vec!((code >> 8) as u8, code as u8);
+            payload.push_all(reason.as_bytes());
+            payload
+        }
+        None => vec!(),
+    }
+}
+
+/// Whether a status code may appear in a close frame (RFC 6455 § 7.4).
+fn is_valid_close_code(code: u16) -> bool {
+    match code {
+        1000...1003 | 1007...1011 | 3000...4999 => true,
+        _ => false,
+    }
+}
+
+/// What to do after handling a frame.
+enum FrameResult {
+    KeepOpen,
+    /// Close the stream, after sending the given events.
+    Finish(Vec<WebSocketEvent>),
+}
+
+/// An input that the connection task waits for.
+enum Input {
+    ControlInput(Option<WebSocketControlMsg>),
+    DataInput(Option<Vec<u8>>),
+    CloseTimeoutInput,
+}
+
+/// An open connection.
+struct Connection {
+    stream: Stream,
+    /// Bytes read from the stream that do not form a whole frame yet.
+    buffer: Vec<u8>,
+    /// The opcode and data of the fragmented message being received, if any.
+    message: Option<(u8, Vec<u8>)>,
+    /// Whether we have sent our close frame.
+    closing: bool,
+    events: Sender<WebSocketEvent>,
+}
+
+impl Connection {
+    fn run(&mut self, control_receiver: Receiver<WebSocketControlMsg>) {
+        let data_receiver = self.stream.start_reader();
+        for event in self.run_until_closed(&control_receiver, &data_receiver).into_iter() {
+            self.events.send(event);
+        }
+        // Unblock the reader task, in case the server did not close the connection.
+        let _ = self.stream.socket().close_read();
+    }
+
+    /// Sends and receives messages until the connection closes, and returns the last events to
+    /// send to the owner.
+    fn run_until_closed(&mut self, control_receiver: &Receiver<WebSocketControlMsg>,
+                        data_receiver: &Receiver<Vec<u8>>) -> Vec<WebSocketEvent> {
+        // Frames may have been read along with the end of the opening handshake.
+        let data = self.stream.decode(vec!());
+        self.buffer.push_all(data.as_slice());
+        match self.handle_buffered_frames() {
+            KeepOpen => {}
+            Finish(events) => return events,
+        }
+
+        let mut timer = Timer::new().unwrap();
+        // Replaced by a timeout once we send our close frame; until then it never fires.
+        let (_close_timeout_sender, mut close_timeout) = channel();
+        loop {
+            let input = {
+                let select = Select::new();
+                let mut control_handle = select.handle(control_receiver);
+                let mut data_handle = select.handle(data_receiver);
+                let mut close_timeout_handle = select.handle(&close_timeout);
+                unsafe {
+                    control_handle.add();
+                    data_handle.add();
+                    close_timeout_handle.add();
+                }
+                let ret = select.wait();
+                if ret == control_handle.id() {
+                    ControlInput(control_receiver.recv_opt().ok())
+                } else if ret == data_handle.id() {
+                    DataInput(data_receiver.recv_opt().ok())
+                } else if ret == close_timeout_handle.id() {
+                    CloseTimeoutInput
+                } else {
+                    panic!("invalid select result");
+                }
+            };
+
+            let was_closing = self.closing;
+            let result = match input {
+                ControlInput(Some(message)) => self.handle_control_msg(message),
+                // Nobody is listening anymore, so there is no point in a clean closure.
+                ControlInput(None) => Finish(vec!()),
+                DataInput(Some(data)) => {
+                    let data = self.stream.decode(data);
+                    self.buffer.push_all(data.as_slice());
+                    self.handle_buffered_frames()
+                }
+                DataInput(None) | CloseTimeoutInput => {
+                    Finish(vec!(ClosedEvent(false, ABNORMAL_CLOSURE, String::new())))
+                }
+            };
+            match result {
+                KeepOpen => {}
+                Finish(events) => return events,
+            }
+            if self.closing && !was_closing {
+                close_timeout = timer.oneshot(Duration::milliseconds(CLOSE_TIMEOUT));
+            }
+        }
+    }
+
+    /// Sends a message queued by the owner of the connection.
+    fn handle_control_msg(&mut self, message: WebSocketControlMsg) -> FrameResult {
+        let (opcode, payload) = match message {
+            SendTextMsg(text) => (TEXT_OPCODE, text.into_bytes()),
+            SendBinaryMsg(data) => (BINARY_OPCODE, data),
+            CloseMsg(code, reason) => (CLOSE_OPCODE, close_payload(code, reason.as_slice())),
+        };
+
+        // Nothing may follow a close frame.
+        if self.closing {
+            return KeepOpen
+        }
+        if self.write_frame(opcode, payload.as_slice()).is_err() {
+            return Finish(vec!(ClosedEvent(false, ABNORMAL_CLOSURE, String::new())))
+        }
+        if opcode == CLOSE_OPCODE {
+            self.closing = true;
+        } else {
+            self.events.send(BytesSentEvent(payload.len()));
+        }
+        KeepOpen
+    }
+
+    /// Handles every whole frame in the buffer.
+    fn handle_buffered_frames(&mut self) -> FrameResult {
+        loop {
+            let (frame, length) = match parse_frame(self.buffer.as_slice(), false) {
+                Ok(Some(result)) => result,
+                Ok(None) => return KeepOpen,
+                Err(code) => return self.fail(code),
+            };
+            self.buffer = self.buffer.slice_from(length).to_vec();
+            match self.handle_frame(frame) {
+                KeepOpen => {}
+                finish => return finish,
+            }
+        }
+    }
+
+    fn handle_frame(&mut self, frame: Frame) -> FrameResult {
+        match frame.opcode {
+            TEXT_OPCODE | BINARY_OPCODE => {
+                if self.message.is_some() {
+                    return self.fail(PROTOCOL_ERROR)
+                }
+                if frame.fin {
+                    return self.deliver_message(frame.opcode, frame.payload)
+                }
+                self.message = Some((frame.opcode, frame.payload));
+                KeepOpen
+            }
+            CONTINUATION_OPCODE => {
+                let (opcode, mut data) = match self.message.take() {
+                    Some(message) => message,
+                    None => return self.fail(PROTOCOL_ERROR),
+                };
+                if (data.len() + frame.payload.len()) as u64 > MAX_MESSAGE_SIZE {
+                    return self.fail(MESSAGE_TOO_BIG)
+                }
+                data.push_all(frame.payload.as_slice());
+                if frame.fin {
+                    return self.deliver_message(opcode, data)
+                }
+                self.message = Some((opcode, data));
+                KeepOpen
+            }
+            PING_OPCODE => {
+                if !self.closing &&
+                        self.write_frame(PONG_OPCODE, frame.payload.as_slice()).is_err() {
+                    return Finish(vec!(ClosedEvent(false, ABNORMAL_CLOSURE, String::new())))
+                }
+                KeepOpen
+            }
+            PONG_OPCODE => KeepOpen,
+            CLOSE_OPCODE => self.handle_close_frame(frame.payload),
+            _ => unreachable!(),
+        }
+    }
+
+    fn deliver_message(&mut self, opcode: u8, data: Vec<u8>) -> FrameResult {
+        // Once we have started closing, incoming messages are discarded.
+        if self.closing {
+            return KeepOpen
+        }
+        let event = if opcode == TEXT_OPCODE {
+            match String::from_utf8(data) {
+                Ok(text) => TextMessageEvent(text),
+                Err(_) => return self.fail(INVALID_FRAME_PAYLOAD_DATA),
+            }
+        } else {
+            BinaryMessageEvent(data)
+        };
+        self.events.send(event);
+        KeepOpen
+    }
+
+    fn handle_close_frame(&mut self, payload: Vec<u8>) -> FrameResult {
+        let (code, reason) = match payload.len() {
+            0 => (None, String::new()),
+            1 => return self.fail(PROTOCOL_ERROR),
+            _ => {
+                let code = (payload[0] as u16 << 8) | payload[1] as u16;
+                if !is_valid_close_code(code) {
+                    return self.fail(PROTOCOL_ERROR)
+                }
+                match String::from_utf8(payload.slice_from(2).to_vec()) {
+                    Ok(reason) => (Some(code), reason),
+                    Err(_) => return self.fail(INVALID_FRAME_PAYLOAD_DATA),
+                }
+            }
+        };
+
+        // If the server started the closing handshake, echo its status code to complete it.
+        if !self.closing {
+            let _ = self.write_frame(CLOSE_OPCODE, close_payload(code, "").as_slice());
+        }
+        Finish(vec!(ClosedEvent(true, code.unwrap_or(NO_STATUS_RECEIVED), reason)))
+    }
+
+    /// Fails the connection (RFC 6455 § 7.1.7), telling the server why.
+    fn fail(&mut self, code: u16) -> FrameResult {
+        if !self.closing {
+            let _ = self.write_frame(CLOSE_OPCODE, close_payload(Some(code), "").as_slice());
+        }
+        Finish(vec!(FailedEvent, ClosedEvent(false, ABNORMAL_CLOSURE, String::new())))
+    }
+
+    /// Writes a frame, masked with a fresh key as every frame sent by a client must be.
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> IoResult<()> {
+        let mut mask = [0u8, ..4];
+        task_rng().fill_bytes(mask.as_mut_slice());
+        try!(self.stream.write(encode_frame(opcode, payload, Some(mask)).as_slice()));
+        self.stream.flush()
+    }
+}
+
+/// Runs a server on the given address that echoes every message back, and returns its port. A
+/// text message of the form `close <code> <reason>` makes the server start the closing handshake
+/// with that status code and reason instead.
+#[cfg(test)]
+fn start_echo_server(address: &str) -> IoResult<u16> {
+    let mut listener = try!(TcpListener::bind(address));
+    let port = try!(listener.socket_name()).port;
+    let acceptor = try!(listener.listen());
+    spawn_named("WebSocket echo server", proc() {
+        let mut acceptor = acceptor;
+        for stream in acceptor.incoming() {
+            match stream {
+                Ok(stream) => spawn_named("WebSocket echo connection", proc() {
+                    let _ = echo(stream);
+                }),
+                Err(_) => return,
+            }
+        }
+    });
+    Ok(port)
+}
+
+/// Serves a single connection of the echo server.
+#[cfg(test)]
+fn echo(mut stream: TcpStream) -> IoResult<()> {
+    let mut buffer = vec!();
+    let mut buf = [0u8, ..4096];
+    let mut header_end = None;
+    while header_end.is_none() {
+        let length = try!(stream.read(buf.as_mut_slice()));
+        buffer.push_all(buf.slice_to(length));
+        header_end = find_header_end(buffer.as_slice());
+    }
+    let header_end = header_end.unwrap();
+
+    let request = String::from_utf8(buffer.slice_to(header_end).to_vec()).unwrap_or(String::new());
+    let key = request.as_slice().split_str("\r\n")
+                     .find(|line| line.starts_with("Sec-WebSocket-Key:"))
+                     .map(|line| line.slice_from(18).trim().to_string())
+                     .unwrap_or(String::new());
+    let response = format!("HTTP/1.1 101 Switching Protocols\r\n\
+                            Upgrade: websocket\r\n\
+                            Connection: Upgrade\r\n\
+                            Sec-WebSocket-Accept: {}\r\n\r\n",
+                           accept_key(key.as_slice()));
+    try!(stream.write(response.as_bytes()));
+    buffer = buffer.slice_from(header_end).to_vec();
+
+    let mut closing = false;
+    loop {
+        let (frame, length) = match parse_frame(buffer.as_slice(), true) {
+            Ok(Some(result)) => result,
+            Ok(None) => {
+                let length = try!(stream.read(buf.as_mut_slice()));
+                buffer.push_all(buf.slice_to(length));
+                continue
+            }
+            Err(code) => {
+                let payload = close_payload(Some(code), "");
+                return stream.write(encode_frame(CLOSE_OPCODE, payload.as_slice(), None).as_slice())
+            }
+        };
+        buffer = buffer.slice_from(length).to_vec();
+
+        let text = if frame.opcode == TEXT_OPCODE {
+            String::from_utf8(frame.payload.clone()).ok()
+        } else {
+            None
+        };
+        let close_command = text.and_then(|text| {
+            let parts: Vec<&str> = text.as_slice().splitn(2, ' ').collect();
+            if parts.len() < 2 || parts[0] != "close" {
+                return None
+            }
+            from_str::<u16>(parts[1]).map(|code| {
+                (code, if parts.len() == 3 { parts[2].to_string() } else { String::new() })
+            })
+        });
+
+        let reply = match (frame.opcode, close_command) {
+            // The closing handshake is over.
+            (CLOSE_OPCODE, _) if closing => return Ok(()),
+            (CLOSE_OPCODE, _) => {
+                try!(stream.write(encode_frame(CLOSE_OPCODE, frame.payload.as_slice(),
+                                               None).as_slice()));
+                return Ok(())
+            }
+            (_, _) if closing => continue,
+            (_, Some((code, reason))) => {
+                closing = true;
+                encode_frame(CLOSE_OPCODE, close_payload(Some(code), reason.as_slice()).as_slice(),
+                             None)
+            }
+            (PING_OPCODE, None) => encode_frame(PONG_OPCODE, frame.payload.as_slice(), None),
+            (PONG_OPCODE, None) => continue,
+            (opcode, None) => encode_frame(opcode, frame.payload.as_slice(), None),
+        };
+        try!(stream.write(reply.as_slice()));
+    }
+}
+
+#[test]
+fn certificate_names() {
+    assert!(host_matches("example.com", "example.com"));
+    assert!(host_matches("Example.COM", "example.com"));
+    assert!(!host_matches("example.com", "www.example.com"));
+    assert!(host_matches("*.example.com", "www.example.com"));
+    assert!(host_matches("*.example.com", "WWW.example.com"));
+    assert!(!host_matches("*.example.com", "example.com"));
+    assert!(!host_matches("*.example.com", "a.b.example.com"));
+    assert!(!host_matches("*.example.com", ".example.com"));
+}
+
+#[test]
+fn certificate_subject_alt_names_only() {
+    let dns_names = vec!("example.com".to_string(), "*.example.org".to_string());
+    assert!(certificate_matches(Some(dns_names.clone()), None, "www.example.org"));
+    assert!(certificate_matches(Some(dns_names), Some("other.net".to_string()), "example.com"));
+}
+
+#[test]
+fn certificate_common_name_ignored_with_subject_alt_names() {
+    let dns_names = vec!("example.com".to_string());
+    assert!(!certificate_matches(Some(dns_names), Some("other.net".to_string()), "other.net"));
+    assert!(!certificate_matches(Some(vec!()), Some("other.net".to_string()), "other.net"));
+}
+
+#[test]
+fn certificate_common_name_without_subject_alt_names() {
+    assert!(certificate_matches(None, Some("*.other.net".to_string()), "www.other.net"));
+    assert!(!certificate_matches(None, Some("other.net".to_string()), "example.com"));
+    assert!(!certificate_matches(None, None, "example.com"));
+}
+
+#[test]
+fn accept_key_from_rfc() {
+    assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ==").as_slice(),
+               "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+}
+
+#[test]
+fn parse_unmasked_text_frame() {
+    let buffer = b"\x81\x05\x48\x65\x6c\x6c\x6f";
+    let (frame, length) = parse_frame(buffer, false).unwrap().unwrap();
+    assert!(frame.fin);
+    assert_eq!(frame.opcode, TEXT_OPCODE);
+    assert_eq!(frame.payload.as_slice(), b"Hello");
+    assert_eq!(length, 7);
+
+    assert!(parse_frame(buffer.slice_to(4), false).unwrap().is_none());
+    assert_eq!(parse_frame(buffer, true).err(), Some(PROTOCOL_ERROR));
+}
+
+#[test]
+fn parse_fragmented_frames() {
+    let buffer = b"\x01\x03\x48\x65\x6c\x80\x02\x6c\x6f";
+    let (first, length) = parse_frame(buffer, false).unwrap().unwrap();
+    assert!(!first.fin);
+    assert_eq!(first.opcode, TEXT_OPCODE);
+    let (second, _) = parse_frame(buffer.slice_from(length), false).unwrap().unwrap();
+    assert!(second.fin);
+    assert_eq!(second.opcode, CONTINUATION_OPCODE);
+    assert_eq!(second.payload.as_slice(), b"lo");
+}
+
+#[test]
+fn parse_invalid_frames() {
+    // Reserved bits set.
+    assert_eq!(parse_frame(b"\xc1\x00", false).err(), Some(PROTOCOL_ERROR));
+    // Unknown opcode.
+    assert_eq!(parse_frame(b"\x83\x00", false).err(), Some(PROTOCOL_ERROR));
+    // Fragmented ping.
+    assert_eq!(parse_frame(b"\x09\x00", false).err(), Some(PROTOCOL_ERROR));
+}
+
+#[test]
+fn encode_masked_frame() {
+    let frame = encode_frame(TEXT_OPCODE, b"Hello", Some([0x37, 0xfa, 0x21, 0x3d]));
+    assert_eq!(frame.as_slice(), b"\x81\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58");
+
+    let (parsed, _) = parse_frame(frame.as_slice(), true).unwrap().unwrap();
+    assert_eq!(parsed.payload.as_slice(), b"Hello");
+}
+
+#[test]
+fn encode_long_frames() {
+    let payload = Vec::from_elem(300, 0u8);
+    let frame = encode_frame(BINARY_OPCODE, payload.as_slice(), None);
+    assert_eq!(frame.slice_to(4), b"\x82\x7e\x01\x2c");
+    let (parsed, length) = parse_frame(frame.as_slice(), false).unwrap().unwrap();
+    assert_eq!(parsed.payload.len(), 300);
+    assert_eq!(length, frame.len());
+
+    let payload = Vec::from_elem(70000, 0u8);
+    let frame = encode_frame(BINARY_OPCODE, payload.as_slice(), None);
+    assert_eq!(frame.slice_to(10), b"\x82\x7f\x00\x00\x00\x00\x00\x01\x11\x70");
+}
+
+/// Returns the next event that is not a `BytesSentEvent`.
+#[cfg(test)]
+fn next_event(events: &Receiver<WebSocketEvent>) -> WebSocketEvent {
+    loop {
+        match events.recv() {
+            BytesSentEvent(_) => {}
+            event => return event,
+        }
+    }
+}
+
+#[test]
+fn echo_round_trip() {
+    let port = start_echo_server("127.0.0.1:0").unwrap();
+    let url = Url::parse(format!("ws://127.0.0.1:{}/echo", port).as_slice()).unwrap();
+    let (control, events) = connect(url, None, "http://localhost".to_string());
+    assert_eq!(next_event(&events), OpenedEvent(None));
+
+    control.send(SendTextMsg("hello".to_string()));
+    assert_eq!(events.recv(), BytesSentEvent(5));
+    assert_eq!(events.recv(), TextMessageEvent("hello".to_string()));
+
+    let data = Vec::from_fn(1000, |i| i as u8);
+    control.send(SendBinaryMsg(data.clone()));
+    assert_eq!(events.recv(), BytesSentEvent(1000));
+    assert_eq!(events.recv(), BinaryMessageEvent(data));
+
+    control.send(CloseMsg(Some(NORMAL_CLOSURE), "bye".to_string()));
+    assert_eq!(next_event(&events), ClosedEvent(true, NORMAL_CLOSURE, "bye".to_string()));
+    assert!(events.recv_opt().is_err());
+}
+
+#[test]
+fn server_initiated_close() {
+    let port = start_echo_server("127.0.0.1:0").unwrap();
+    let url = Url::parse(format!("ws://127.0.0.1:{}/", port).as_slice()).unwrap();
+    let (control, events) = connect(url, None, "http://localhost".to_string());
+    assert_eq!(next_event(&events), OpenedEvent(None));
+
+    control.send(SendTextMsg("close 4000 done".to_string()));
+    assert_eq!(next_event(&events), ClosedEvent(true, 4000, "done".to_string()));
+    assert!(events.recv_opt().is_err());
+}
+
+#[test]
+fn refused_connection() {
+    let url = Url::parse("ws://127.0.0.1:1/").unwrap();
+    let (_control, events) = connect(url, None, "http://localhost".to_string());
+    assert_eq!(events.recv(), FailedEvent);
+    assert_eq!(events.recv(), ClosedEvent(false, ABNORMAL_CLOSURE, String::new()));
+}
@@ -10,6 +10,7 @@ use dom::bindings::global;
 use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
 use dom::element::{Element, AttributeHandlers};
+use dom::mutationobserver::{MutationObserver, AttributeMutation};
 use dom::node::Node;
 use dom::window::Window;
 use dom::virtualmethods::vtable_for;
@@ -206,6 +207,16 @@ impl<'a> AttrHelpers<'a> for JSRef<'a, Attr> {
         let node: JSRef<Node> = NodeCast::from_ref(owner);
         let namespace_is_null = self.namespace == ns!("");
 
+        {
+            let old_value = self.value();
+            let old_value = match set_type {
+                ReplacedAttr => Some(old_value.as_slice()),
+                FirstSetAttr => None,
+            };
+            MutationObserver::queue_a_mutation_record(
+                node, AttributeMutation(self.local_name(), &self.namespace), old_value);
+        }
+
         match set_type {
             ReplacedAttr if namespace_is_null => vtable_for(&node).before_remove_attr(self),
             _ => ()
@@ -6,11 +6,13 @@
 
 //! Base classes to work with IDL callbacks.
 
+use dom::bindings::global;
 use dom::bindings::global::global_object_for_js_object;
 use dom::bindings::js::JSRef;
 use dom::bindings::utils::Reflectable;
+use dom::window::WindowHelpers;
 use js::jsapi::{JSContext, JSObject, JS_WrapObject, JS_ObjectIsCallable};
-use js::jsapi::JS_GetProperty;
+use js::jsapi::{JS_GetProperty, JS_IsRunning};
 use js::jsval::{JSVal, UndefinedValue};
 
 use std::ptr;
@@ -135,6 +137,8 @@ pub fn WrapCallThisObject<T: Reflectable>(cx: *mut JSContext,
 /// A class that performs whatever setup we need to safely make a call while
 /// this class is on the stack. After `new` returns, the call is safe to make.
 pub struct CallSetup {
+    /// The callback object, whose global performs a microtask checkpoint after the call.
+    callback: *mut JSObject,
     /// The `JSContext` used for the call.
     cx: *mut JSContext,
     /// The exception handling used for the call.
@@ -149,6 +153,7 @@ impl CallSetup {
         let global = global.root();
         let cx = global.root_ref().get_cx();
         CallSetup {
+            callback: callback.callback(),
             cx: cx,
             _handling: handling
         }
@@ -159,3 +164,20 @@ impl CallSetup {
         self.cx
     }
 }
+
+impl Drop for CallSetup {
+    // http://www.whatwg.org/html/#clean-up-after-running-a-callback
+    fn drop(&mut self) {
+        // Step 3: callbacks called from running script leave the checkpoint to the outermost one.
+        if unsafe { JS_IsRunning(self.cx) } != 0 {
+            return;
+        }
+        let global = global_object_for_js_object(self.callback).root();
+        match global.root_ref() {
+            global::Window(window) => {
+                window.perform_a_microtask_checkpoint();
+            }
+            global::Worker(_) => (),
+        }
+    }
+}
@@ -579,7 +579,21 @@ def getJSToNativeConversionTemplate(type, descriptorProvider, failureCode=None,
         raise TypeError("Can't handle array arguments yet")
 
     if type.isSequence():
-        raise TypeError("Can't handle sequence arguments yet")
+        # Interfaces in sequences are held as JS<T>, whose conversion from JS
+        # values does not root them.
+        sequenceType = type.inner if type.nullable() else type
+        (_, _, innerDeclType, _) = getJSToNativeConversionTemplate(
+            sequenceType.inner, descriptorProvider, isMember=isMember)
+        declType = CGWrapper(innerDeclType, pre="Vec<", post=">")
+        if type.nullable():
+            declType = CGWrapper(declType, pre="Option<", post=" >")
+
+        templateBody = ("match FromJSValConvertible::from_jsval(cx, ${val}, ()) {\n"
+                        "    Ok(value) => value,\n"
+                        "    Err(()) => { %s },\n"
+                        "}" % exceptionCode)
+
+        return handleOptional(templateBody, declType, handleDefaultNull("None"))
 
     if type.isUnion():
         declType = CGGeneric(union_native_type(type))
@@ -654,7 +668,31 @@ def getJSToNativeConversionTemplate(type, descriptorProvider, failureCode=None,
         return handleOptional(templateBody, declType, handleDefaultNull("None"))
 
     if type.isSpiderMonkeyInterface():
-        raise TypeError("Can't handle SpiderMonkey interface arguments yet")
+        assert not isEnforceRange and not isClamp
+        if type.isArrayBuffer():
+            isType = "JS_IsArrayBufferObject"
+        elif type.isArrayBufferView():
+            isType = "JS_IsArrayBufferViewObject"
+        else:
+            raise TypeError("Can't handle typed array arguments yet")
+
+        templateBody = (
+            "match (${val}).to_object() {\n"
+            "  obj if unsafe { %s(obj, cx) } != 0 => obj,\n"
+            "  _ => {\n"
+            "%s"
+            "  }\n"
+            "}" % (isType, CGIndenter(onFailureBadType(failureCode, type.name), 4).define()))
+
+        declType = CGGeneric("*mut JSObject")
+        if type.nullable():
+            templateBody = "Some(%s)" % templateBody
+            declType = CGWrapper(declType, pre="Option<", post=">")
+
+        templateBody = wrapObjectTemplate(templateBody, isDefinitelyObject,
+                                          type, failureCode)
+
+        return handleOptional(templateBody, declType, handleDefaultNull("None"))
 
     if type.isDOMString():
         assert not isEnforceRange and not isClamp
@@ -832,7 +870,7 @@ def getJSToNativeConversionTemplate(type, descriptorProvider, failureCode=None,
     if not type.isPrimitive():
         raise TypeError("Need conversion for argument type '%s'" % str(type))
 
-    assert not isEnforceRange and not isClamp
+    assert not isEnforceRange
 
     if failureCode is None:
         failureCode = 'return 0'
@@ -841,12 +879,13 @@ def getJSToNativeConversionTemplate(type, descriptorProvider, failureCode=None,
     if type.nullable():
         declType = CGWrapper(declType, pre="Option<", post=">")
 
-    #XXXjdm support conversionBehavior here
+    #XXXjdm support [EnforceRange] here
+    conversionBehavior = "Clamp" if isClamp else "()"
     template = (
-        "match FromJSValConvertible::from_jsval(cx, ${val}, ()) {\n"
+        "match FromJSValConvertible::from_jsval(cx, ${val}, %s) {\n"
         "  Ok(v) => v,\n"
         "  Err(_) => { %s }\n"
-        "}" % exceptionCode)
+        "}" % (conversionBehavior, exceptionCode))
 
     if defaultValue is not None:
         if isinstance(defaultValue, IDLNullValue):
@@ -1095,7 +1134,12 @@ def getRetvalDeclarationForType(returnType, descriptorProvider):
     if returnType.isObject() or returnType.isSpiderMonkeyInterface():
         return CGGeneric("*mut JSObject")
     if returnType.isSequence():
-        raise TypeError("We don't support sequence return values")
+        sequenceType = returnType.inner if returnType.nullable() else returnType
+        inner = getRetvalDeclarationForType(sequenceType.inner, descriptorProvider)
+        result = CGWrapper(inner, pre="Vec<", post=">")
+        if returnType.nullable():
+            result = CGWrapper(result, pre="Option<", post=">")
+        return result
 
     raise TypeError("Don't know how to declare return value for %s" %
                     returnType)
@@ -1173,12 +1217,20 @@ class MethodDefiner(PropertyDefiner):
         #       identifier. For now we check if the name starts with __
         methods = [m for m in descriptor.interface.members if
                    m.isMethod() and m.isStatic() == static and
-                   not m.isIdentifierLess()]
+                   (not m.isIdentifierLess() or m.isStringifier())]
         self.regular = [{"name": m.identifier.name,
                          "methodInfo": not m.isStatic(),
                          "length": methodLength(m),
                          "flags": "JSPROP_ENUMERATE" }
-                        for m in methods]
+                        for m in methods if not m.isIdentifierLess()]
+
+        # An anonymous stringifier is exposed as toString().
+        self.regular.extend([{"name": "toString",
+                              "jitName": m.identifier.name,
+                              "methodInfo": True,
+                              "length": 0,
+                              "flags": "JSPROP_ENUMERATE" }
+                             for m in methods if m.isIdentifierLess()])
 
         # FIXME Check for an existing iterator on the interface first.
         if any(m.isGetter() and m.isIndexed() for m in methods):
@@ -1194,7 +1246,7 @@ class MethodDefiner(PropertyDefiner):
 
         def specData(m):
             if m.get("methodInfo", True):
-                jitinfo = ("&%s_methodinfo" % m["name"])
+                jitinfo = ("&%s_methodinfo" % m.get("jitName", m["name"]))
                 accessor = "genericMethod"
             else:
                 jitinfo = "0 as *const JSJitInfo"
@@ -1677,7 +1729,8 @@ def UnionTypes(descriptors, dictionaries, callbacks, config):
         'dom::bindings::error::throw_not_in_union',
         'dom::bindings::js::JS',
         'dom::types::*',
-        'js::jsapi::JSContext',
+        'js::jsapi::{JSContext, JSObject}',
+        'js::jsfriendapi::bindgen::{JS_IsArrayBufferObject, JS_IsArrayBufferViewObject}',
         'js::jsval::JSVal',
         'servo_util::str::DOMString',
     ]
@@ -2461,7 +2514,11 @@ class CGAbstractStaticBindingMethod(CGAbstractMethod):
         CGAbstractMethod.__init__(self, descriptor, name, "JSBool", args, extern=True)
 
     def definition_body(self):
-        return self.generate_code()
+        preamble = CGGeneric("""\
+let global = global_object_for_js_object(JS_CALLEE(cx, vp).to_object());
+let global = global.root();
+""")
+        return CGList([preamble, self.generate_code()])
 
     def generate_code(self):
         assert False  # Override me
@@ -2504,6 +2561,8 @@ class CGSpecializedMethod(CGAbstractExternMethod):
 
     @staticmethod
     def makeNativeName(descriptor, method):
+        if method.isIdentifierLess() and method.isStringifier():
+            return "Stringifier"
         return MakeNativeName(method.identifier.name)
 
 class CGStaticMethod(CGAbstractStaticBindingMethod):
@@ -2518,7 +2577,8 @@ class CGStaticMethod(CGAbstractStaticBindingMethod):
     def generate_code(self):
         nativeName = CGSpecializedMethod.makeNativeName(self.descriptor,
                                                         self.method)
-        return CGMethodCall([], nativeName, True, self.descriptor, self.method)
+        return CGMethodCall(["&global.root_ref()"], nativeName, True,
+                            self.descriptor, self.method)
 
 
 class CGGenericGetter(CGAbstractBindingMethod):
@@ -2591,8 +2651,8 @@ class CGStaticGetter(CGAbstractStaticBindingMethod):
     def generate_code(self):
         nativeName = CGSpecializedGetter.makeNativeName(self.descriptor,
                                                         self.attr)
-        return CGGetterCall([], self.attr.type, nativeName, self.descriptor,
-                            self.attr)
+        return CGGetterCall(["&global.root_ref()"], self.attr.type, nativeName,
+                            self.descriptor, self.attr)
 
 
 class CGGenericSetter(CGAbstractBindingMethod):
@@ -2669,8 +2729,8 @@ class CGStaticSetter(CGAbstractStaticBindingMethod):
             "  throw_type_error(cx, \"Not enough arguments to %s setter.\");\n"
             "  return 0;\n"
             "}\n" % self.attr.identifier.name)
-        call = CGSetterCall([], self.attr.type, nativeName, self.descriptor,
-                            self.attr)
+        call = CGSetterCall(["&global.root_ref()"], self.attr.type, nativeName,
+                            self.descriptor, self.attr)
         return CGList([checkForArg, call])
 
 
@@ -2836,6 +2896,9 @@ def getUnionTypeTemplateVars(type, descriptorProvider):
     elif type.isDOMString():
         name = type.name
         typeName = "DOMString"
+    elif type.isSpiderMonkeyInterface():
+        name = type.name
+        typeName = "*mut JSObject"
     elif type.isPrimitive():
         name = type.name
         typeName = builtinNames[type.tag()]
@@ -4010,7 +4073,7 @@ class CGInterfaceTrait(CGThing):
         def members():
             for m in descriptor.interface.members:
                 if m.isMethod() and not m.isStatic() \
-                        and not m.isIdentifierLess():
+                        and (not m.isIdentifierLess() or m.isStringifier()):
                     name = CGSpecializedMethod.makeNativeName(descriptor, m)
                     infallible = 'infallible' in descriptor.getExtendedAttributes(m)
                     for idx, (rettype, arguments) in enumerate(m.signatures()):
@@ -4079,7 +4142,7 @@ class CGDescriptor(CGThing):
         (hasMethod, hasGetter, hasLenientGetter,
          hasSetter, hasLenientSetter) = False, False, False, False, False
         for m in descriptor.interface.members:
-            if m.isMethod() and not m.isIdentifierLess():
+            if m.isMethod() and (not m.isIdentifierLess() or m.isStringifier()):
                 if m.isStatic():
                     assert descriptor.interface.hasInterfaceObject()
                     cgThings.append(CGStaticMethod(descriptor, m))
@@ -4484,6 +4547,7 @@ class CGBindingRoot(CGThing):
             'js::jsapi::{JSPropertyOpWrapper, JSPropertySpec, JS_PropertyStub}',
             'js::jsapi::{JSStrictPropertyOpWrapper, JSString, JSTracer, JS_ConvertStub}',
             'js::jsapi::{JS_StrictPropertyStub, JS_EnumerateStub, JS_ResolveStub}',
+            'js::jsfriendapi::bindgen::{JS_IsArrayBufferObject, JS_IsArrayBufferViewObject}',
             'js::jsval::JSVal',
             'js::jsval::{ObjectValue, ObjectOrNullValue, PrivateValue}',
             'js::jsval::{NullValue, UndefinedValue}',
@@ -4517,7 +4581,7 @@ class CGBindingRoot(CGThing):
             'dom::bindings::callback::{WrapCallThisObject}',
             'dom::bindings::conversions::{FromJSValConvertible, ToJSValConvertible}',
             'dom::bindings::conversions::IDLInterface',
-            'dom::bindings::conversions::{Default, Empty}',
+            'dom::bindings::conversions::{Clamp, Default, Empty}',
             'dom::bindings::conversions::jsid_to_str',
             'dom::bindings::codegen::{PrototypeList, RegisterBindings, UnionTypes}',
             'dom::bindings::codegen::Bindings::*',
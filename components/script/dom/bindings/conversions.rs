@@ -6,7 +6,8 @@
 
 //! Conversions of Rust values to and from `JSVal`.
 
-use dom::bindings::js::{JS, JSRef, Root};
+use dom::bindings::error::throw_type_error;
+use dom::bindings::js::{JS, JSRef, Root, Temporary};
 use dom::bindings::str::ByteString;
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::bindings::utils::unwrap_jsmanaged;
@@ -20,13 +21,16 @@ use js::jsapi::{JS_ValueToECMAUint32, JS_ValueToECMAInt32};
 use js::jsapi::{JS_ValueToUint16, JS_ValueToNumber, JS_ValueToBoolean};
 use js::jsapi::{JS_ValueToString, JS_GetStringCharsAndLength};
 use js::jsapi::{JS_NewUCStringCopyN, JS_NewStringCopyN};
-use js::jsapi::{JS_WrapValue};
+use js::jsapi::{JS_WrapValue, JS_NewArrayObject, JS_SetElement};
+use js::jsapi::{JS_GetArrayLength, JS_GetElement};
 use js::jsval::JSVal;
 use js::jsval::{UndefinedValue, NullValue, BooleanValue, Int32Value, UInt32Value};
 use js::jsval::{StringValue, ObjectValue, ObjectOrNullValue};
 
 use libc;
 use std::default;
+use std::num::Float;
+use std::ptr;
 use std::slice;
 
 use dom::bindings::codegen::PrototypeList;
@@ -203,6 +207,44 @@ impl FromJSValConvertible<()> for u64 {
     }
 }
 
+/// Behavior for converting `JSVal`s to integers for arguments with the `[Clamp]` extended
+/// attribute: values out of the range of the type are clamped to it rather than wrapped around.
+/// http://heycam.github.io/webidl/#Clamp
+pub struct Clamp;
+
+/// Clamps the given number to the given range and rounds it to the nearest integer, picking the
+/// even one on ties. NaN becomes zero.
+fn clamp_and_round(number: f64, min: f64, max: f64) -> f64 {
+    if number.is_nan() {
+        return 0.0
+    }
+    let number = number.max(min).min(max);
+    let floor = number.floor();
+    match number - floor {
+        fraction if fraction < 0.5 => floor,
+        fraction if fraction > 0.5 => floor + 1.0,
+        _ if floor % 2.0 == 0.0 => floor,
+        _ => floor + 1.0,
+    }
+}
+
+macro_rules! clamped_integer_conversion(
+    ($t:ty, $min:expr, $max:expr) => (
+        impl FromJSValConvertible<Clamp> for $t {
+            fn from_jsval(cx: *mut JSContext, val: JSVal, _option: Clamp) -> Result<$t, ()> {
+                let number: f64 = try!(FromJSValConvertible::from_jsval(cx, val, ()));
+                Ok(clamp_and_round(number, $min, $max) as $t)
+            }
+        }
+    )
+)
+
+clamped_integer_conversion!(u16, 0.0, 65535.0)
+clamped_integer_conversion!(i32, -2147483648.0, 2147483647.0)
+clamped_integer_conversion!(u32, 0.0, 4294967295.0)
+clamped_integer_conversion!(i64, -9007199254740991.0, 9007199254740991.0)
+clamped_integer_conversion!(u64, 0.0, 9007199254740991.0)
+
 impl ToJSValConvertible for f32 {
     fn to_jsval(&self, _cx: *mut JSContext) -> JSVal {
         unsafe {
@@ -375,6 +417,13 @@ impl<'a, T: Reflectable> ToJSValConvertible for JS<T> {
     }
 }
 
+impl<T: Reflectable> ToJSValConvertible for Temporary<T> {
+    fn to_jsval(&self, cx: *mut JSContext) -> JSVal {
+        let temporary = *self;
+        temporary.root().to_jsval(cx)
+    }
+}
+
 impl<T: ToJSValConvertible> ToJSValConvertible for Option<T> {
     fn to_jsval(&self, cx: *mut JSContext) -> JSVal {
         match self {
@@ -396,6 +445,48 @@ impl<X: default::Default, T: FromJSValConvertible<X>> FromJSValConvertible<()> f
     }
 }
 
+impl<T: ToJSValConvertible> ToJSValConvertible for Vec<T> {
+    fn to_jsval(&self, cx: *mut JSContext) -> JSVal {
+        unsafe {
+            let array = JS_NewArrayObject(cx, 0, ptr::null_mut());
+            if array.is_null() {
+                panic!("JS_NewArrayObject failed");
+            }
+            for (index, item) in self.iter().enumerate() {
+                let mut value = item.to_jsval(cx);
+                assert!(JS_SetElement(cx, array, index as u32, &mut value) != 0);
+            }
+            ObjectValue(&*array)
+        }
+    }
+}
+
+impl<X: default::Default, T: FromJSValConvertible<X>> FromJSValConvertible<()> for Vec<T> {
+    fn from_jsval(cx: *mut JSContext, value: JSVal, _: ()) -> Result<Vec<T>, ()> {
+        if !value.is_object() {
+            throw_type_error(cx, "Value is not a sequence");
+            return Err(());
+        }
+        unsafe {
+            let object = value.to_object();
+            let mut length = 0;
+            if JS_GetArrayLength(cx, object, &mut length) == 0 {
+                return Err(());
+            }
+            let mut result = Vec::with_capacity(length as uint);
+            for index in range(0, length) {
+                let mut item = UndefinedValue();
+                if JS_GetElement(cx, object, index, &mut item) == 0 {
+                    return Err(());
+                }
+                let option: X = default::Default::default();
+                result.push(try!(FromJSValConvertible::from_jsval(cx, item, option)));
+            }
+            Ok(result)
+        }
+    }
+}
+
 impl ToJSValConvertible for *mut JSObject {
     fn to_jsval(&self, cx: *mut JSContext) -> JSVal {
         let mut wrapped = ObjectOrNullValue(*self);
@@ -25,7 +25,9 @@ pub enum Error {
     FailureUnknown,
     NotFound,
     HierarchyRequest,
+    WrongDocument,
     InvalidCharacter,
+    NoModificationAllowed,
     NotSupported,
     InvalidState,
     Syntax,
@@ -35,7 +37,10 @@ pub enum Error {
     Network,
     Abort,
     Timeout,
-    DataClone
+    InvalidNodeType,
+    DataClone,
+    /// A JavaScript `TypeError` with the given message, rather than a `DOMException`.
+    Type(&'static str),
 }
 
 /// The return type for IDL operations that can throw DOM exceptions.
@@ -49,7 +54,10 @@ pub type ErrorResult = Fallible<()>;
 pub fn throw_dom_exception(cx: *mut JSContext, global: &GlobalRef,
                            result: Error) {
     assert!(unsafe { JS_IsExceptionPending(cx) } == 0);
-    let exception = DOMException::new_from_error(*global, result).root();
+    let exception = match result {
+        Type(message) => return throw_type_error(cx, message),
+        result => DOMException::new_from_error(*global, result).root(),
+    };
     let thrown = exception.to_jsval(cx);
     unsafe {
         JS_SetPendingException(cx, thrown);
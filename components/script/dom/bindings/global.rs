@@ -10,6 +10,7 @@
 use dom::bindings::conversions::FromJSValConvertible;
 use dom::bindings::js::{JS, JSRef, Root};
 use dom::bindings::utils::{Reflectable, Reflector};
+use dom::url::BlobURLList;
 use dom::workerglobalscope::WorkerGlobalScope;
 use dom::window;
 use script_task::ScriptChan;
@@ -87,6 +88,14 @@ impl<'a> GlobalRef<'a> {
             Worker(ref worker) => worker.script_chan(),
         }
     }
+
+    /// The blob URLs created by this global object.
+    pub fn blob_urls<'b>(&'b self) -> &'b BlobURLList {
+        match *self {
+            Window(ref window) => window.blob_urls(),
+            Worker(ref worker) => worker.blob_urls(),
+        }
+    }
 }
 
 impl<'a> Reflectable for GlobalRef<'a> {
@@ -46,8 +46,11 @@
 //! - `RootedReference`: makes obtaining an `Option<JSRef<T>>` from an `Option<Root<T>>` easy
 
 use dom::bindings::utils::{Reflector, Reflectable};
+use dom::filereader::{FileReader, TrustedFileReaderAddress};
+use dom::messageport::{MessagePort, TrustedMessagePortAddress};
 use dom::node::Node;
 use dom::xmlhttprequest::{XMLHttpRequest, TrustedXHRAddress};
+use dom::websocket::{WebSocket, TrustedWebSocketAddress};
 use dom::worker::{Worker, TrustedWorkerAddress};
 use js::jsapi::JSObject;
 use layout_interface::TrustedNodeAddress;
@@ -158,6 +161,36 @@ impl JS<Worker> {
     }
 }
 
+impl JS<MessagePort> {
+    pub unsafe fn from_trusted_message_port_address(inner: TrustedMessagePortAddress)
+                                                    -> JS<MessagePort> {
+        let TrustedMessagePortAddress(addr) = inner;
+        JS {
+            ptr: addr as *const MessagePort
+        }
+    }
+}
+
+impl JS<FileReader> {
+    pub unsafe fn from_trusted_file_reader_address(inner: TrustedFileReaderAddress)
+                                                   -> JS<FileReader> {
+        let TrustedFileReaderAddress(addr) = inner;
+        JS {
+            ptr: addr as *const FileReader
+        }
+    }
+}
+
+impl JS<WebSocket> {
+    pub unsafe fn from_trusted_websocket_address(inner: TrustedWebSocketAddress)
+                                                 -> JS<WebSocket> {
+        let TrustedWebSocketAddress(addr) = inner;
+        JS {
+            ptr: addr as *const WebSocket
+        }
+    }
+}
+
 impl<T: Reflectable> JS<T> {
     /// Create a new JS-owned value wrapped from a raw Rust pointer.
     pub unsafe fn from_raw(raw: *const T) -> JS<T> {
@@ -31,8 +31,14 @@ use dom::bindings::js::JS;
 use dom::bindings::utils::{Reflectable, Reflector, WindowProxyHandler};
 use dom::node::{Node, TrustedNodeAddress};
 
+use canvas::canvas_render_task::{FillOrStrokeStyle, LineCapStyle, LineJoinStyle};
+use canvas::canvas_render_task::{TextAlign, TextBaseline};
 use collections::hash::{Hash, Hasher};
+use cssparser::RGBA;
+use geom::matrix2d::Matrix2D;
+use geom::point::Point2D;
 use geom::rect::Rect;
+use gfx::font_cache_task::FontCacheTask;
 use html5ever::tree_builder::QuirksMode;
 use http::headers::request::HeaderCollection as RequestHeaderCollection;
 use http::headers::response::HeaderCollection as ResponseHeaderCollection;
@@ -54,7 +60,8 @@ use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::comm::{Receiver, Sender};
 use std::io::timer::Timer;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
 use string_cache::{Atom, Namespace};
 use style::PropertyDeclarationBlock;
 use url::Url;
@@ -200,8 +207,12 @@ no_jsmanaged_fields!(uint, u8, u16, u32, u64)
 no_jsmanaged_fields!(int, i8, i16, i32, i64)
 no_jsmanaged_fields!(Sender<T>)
 no_jsmanaged_fields!(Receiver<T>)
+no_jsmanaged_fields!(Weak<T>)
+no_jsmanaged_fields!(Arc<T>)
+no_jsmanaged_fields!(Point2D<T>)
 no_jsmanaged_fields!(Rect<T>)
-no_jsmanaged_fields!(ImageCacheTask, ScriptControlChan)
+no_jsmanaged_fields!(Matrix2D<T>)
+no_jsmanaged_fields!(ImageCacheTask, FontCacheTask, ScriptControlChan)
 no_jsmanaged_fields!(Atom, Namespace, Timer)
 no_jsmanaged_fields!(PropertyDeclarationBlock)
 // These three are interdependent, if you plan to put jsmanaged data
@@ -215,6 +226,8 @@ no_jsmanaged_fields!(LayoutChan)
 no_jsmanaged_fields!(WindowProxyHandler)
 no_jsmanaged_fields!(UntrustedNodeAddress)
 no_jsmanaged_fields!(LengthOrPercentageOrAuto)
+no_jsmanaged_fields!(RGBA)
+no_jsmanaged_fields!(FillOrStrokeStyle, LineCapStyle, LineJoinStyle, TextAlign, TextBaseline)
 
 impl<'a> JSTraceable for &'a str {
     #[inline]
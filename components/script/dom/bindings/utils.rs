@@ -20,6 +20,7 @@ use libc::c_uint;
 use std::cell::Cell;
 use std::mem;
 use std::ptr;
+use std::slice::raw::buf_as_slice;
 use js::glue::{js_IsObjectProxyClass, js_IsFunctionProxyClass, IsProxyHandlerFamily};
 use js::glue::{UnwrapObject, GetProxyHandlerExtra};
 use js::glue::{IsWrapper, RUST_JSID_IS_INT, RUST_JSID_TO_INT};
@@ -38,6 +39,8 @@ use js::jsapi::{JS_NewGlobalObject, JS_InitStandardClasses};
 use js::jsapi::JS_DeletePropertyById2;
 use js::jsfriendapi::JS_ObjectToOuterObject;
 use js::jsfriendapi::bindgen::JS_NewObjectWithUniqueType;
+use js::jsfriendapi::bindgen::{JS_GetArrayBufferByteLength, JS_GetArrayBufferData};
+use js::jsfriendapi::bindgen::{JS_GetArrayBufferViewByteLength, JS_GetArrayBufferViewData};
 use js::jsval::JSVal;
 use js::jsval::{PrivateValue, ObjectValue, NullValue};
 use js::jsval::{Int32Value, UInt32Value, DoubleValue, BooleanValue, UndefinedValue};
@@ -672,6 +675,24 @@ pub unsafe fn delete_property_by_id(cx: *mut JSContext, object: *mut JSObject,
     return true;
 }
 
+/// Returns a copy of the contents of an `ArrayBuffer` object.
+pub fn array_buffer_contents(cx: *mut JSContext, buffer: *mut JSObject) -> Vec<u8> {
+    unsafe {
+        let length = JS_GetArrayBufferByteLength(buffer, cx) as uint;
+        let data = JS_GetArrayBufferData(buffer, cx);
+        buf_as_slice(data as *const u8, length, |bytes| bytes.to_vec())
+    }
+}
+
+/// Returns a copy of the bytes that an `ArrayBufferView` object views.
+pub fn array_buffer_view_contents(cx: *mut JSContext, view: *mut JSObject) -> Vec<u8> {
+    unsafe {
+        let length = JS_GetArrayBufferViewByteLength(view, cx) as uint;
+        let data = JS_GetArrayBufferViewData(view, cx);
+        buf_as_slice(data as *const u8, length, |bytes| bytes.to_vec())
+    }
+}
+
 /// Results of `xml_name_type`.
 #[deriving(PartialEq)]
 #[allow(missing_docs)]
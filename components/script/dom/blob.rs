@@ -3,11 +3,20 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use dom::bindings::codegen::InheritTypes::FileDerived;
-use dom::bindings::global::GlobalRef;
-use dom::bindings::js::Temporary;
+use dom::bindings::global::{GlobalField, GlobalRef};
+use dom::bindings::js::{JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::bindings::utils::{array_buffer_contents, array_buffer_view_contents};
 use dom::bindings::error::Fallible;
 use dom::bindings::codegen::Bindings::BlobBinding;
+use dom::bindings::codegen::Bindings::BlobBinding::{BlobMethods, BlobPropertyBag};
+use dom::bindings::codegen::UnionTypes::ArrayBufferOrArrayBufferViewOrBlobOrString::ArrayBufferOrArrayBufferViewOrBlobOrString;
+use dom::bindings::codegen::UnionTypes::ArrayBufferOrArrayBufferViewOrBlobOrString::{eArrayBuffer, eArrayBufferView};
+use dom::bindings::codegen::UnionTypes::ArrayBufferOrArrayBufferViewOrBlobOrString::{eBlob, eString};
+use servo_util::str::DOMString;
+
+use std::ascii::AsciiExt;
+use std::cmp::{max, min};
 
 #[jstraceable]
 pub enum BlobType {
@@ -18,25 +27,109 @@ pub enum BlobType {
 #[dom_struct]
 pub struct Blob {
     reflector_: Reflector,
-    type_: BlobType
+    type_: BlobType,
+    bytes: Vec<u8>,
+    content_type: DOMString,
+    global: GlobalField,
+}
+
+/// Returns the lowercased `type`, or the empty string if it contains
+/// characters outside U+0020 to U+007E.
+/// http://dev.w3.org/2006/webapi/FileAPI/#dfn-type
+fn normalize_type(type_: &str) -> DOMString {
+    if type_.chars().all(|c| c >= ' ' && c <= '~') {
+        type_.to_ascii_lower()
+    } else {
+        "".to_string()
+    }
 }
 
 impl Blob {
-    pub fn new_inherited() -> Blob {
+    pub fn new_inherited(global: &GlobalRef, type_: BlobType, bytes: Vec<u8>,
+                         content_type: DOMString) -> Blob {
         Blob {
             reflector_: Reflector::new(),
-            type_: BlobTypeId
+            type_: type_,
+            bytes: bytes,
+            content_type: content_type,
+            global: GlobalField::from_rooted(global),
         }
     }
 
     pub fn new(global: GlobalRef) -> Temporary<Blob> {
-        reflect_dom_object(box Blob::new_inherited(),
+        Blob::new_with_bytes(global, vec!())
+    }
+
+    pub fn new_with_bytes(global: GlobalRef, bytes: Vec<u8>) -> Temporary<Blob> {
+        Blob::new_with_type(global, bytes, "".to_string())
+    }
+
+    pub fn new_with_type(global: GlobalRef, bytes: Vec<u8>,
+                         content_type: DOMString) -> Temporary<Blob> {
+        reflect_dom_object(box Blob::new_inherited(&global, BlobTypeId, bytes, content_type),
                            global,
                            BlobBinding::Wrap)
     }
 
-    pub fn Constructor(global: &GlobalRef) -> Fallible<Temporary<Blob>> {
-        Ok(Blob::new(*global))
+    // http://dev.w3.org/2006/webapi/FileAPI/#constructorBlob
+    pub fn Constructor(global: &GlobalRef,
+                       blobParts: Option<Vec<ArrayBufferOrArrayBufferViewOrBlobOrString>>,
+                       options: &BlobPropertyBag) -> Fallible<Temporary<Blob>> {
+        let cx = global.get_cx();
+        let mut bytes = vec!();
+        for part in blobParts.unwrap_or(vec!()).iter() {
+            match *part {
+                eArrayBuffer(buffer) => {
+                    bytes.push_all(array_buffer_contents(cx, buffer).as_slice())
+                }
+                eArrayBufferView(view) => {
+                    bytes.push_all(array_buffer_view_contents(cx, view).as_slice())
+                }
+                eString(ref s) => bytes.push_all(s.as_bytes()),
+                eBlob(ref blob) => bytes.push_all(blob.root().bytes()),
+            }
+        }
+        Ok(Blob::new_with_type(*global, bytes, normalize_type(options.type_.as_slice())))
+    }
+}
+
+impl Blob {
+    pub fn bytes<'a>(&'a self) -> &'a [u8] {
+        self.bytes.as_slice()
+    }
+
+    pub fn content_type<'a>(&'a self) -> &'a DOMString {
+        &self.content_type
+    }
+}
+
+impl<'a> BlobMethods for JSRef<'a, Blob> {
+    fn Size(self) -> u64 {
+        self.bytes.len() as u64
+    }
+
+    fn Type(self) -> DOMString {
+        self.content_type.clone()
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#dfn-slice
+    fn Slice(self, start: Option<i64>, end: Option<i64>,
+             contentType: Option<DOMString>) -> Temporary<Blob> {
+        let size = self.bytes.len() as i64;
+        let relative = |pos: i64| {
+            if pos < 0 { max(size + pos, 0) } else { min(pos, size) }
+        };
+        let relative_start = relative(start.unwrap_or(0));
+        let relative_end = relative(end.unwrap_or(size));
+        let span = max(relative_end - relative_start, 0);
+        let bytes = self.bytes.slice(relative_start as uint,
+                                     (relative_start + span) as uint).to_vec();
+        let content_type = match contentType {
+            Some(ref content_type) => normalize_type(content_type.as_slice()),
+            None => "".to_string(),
+        };
+        let global = self.global.root();
+        Blob::new_with_type(global.root_ref(), bytes, content_type)
     }
 }
 
@@ -0,0 +1,80 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::CanvasGradientBinding;
+use dom::bindings::codegen::Bindings::CanvasGradientBinding::CanvasGradientMethods;
+use dom::bindings::error::{ErrorResult, IndexSize, Syntax};
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::canvasrenderingcontext2d::parse_color;
+
+use canvas::canvas_render_task::{CanvasGradientStop, FillOrStrokeStyle, LinearGradientStyle};
+use canvas::canvas_render_task::{RadialGradientStyle, ColorStyle};
+use servo_util::str::DOMString;
+
+#[dom_struct]
+pub struct CanvasGradient {
+    reflector_: Reflector,
+    /// The gradient, as sent to the canvas render task. Always one of the gradient styles.
+    style: DOMRefCell<FillOrStrokeStyle>,
+}
+
+impl CanvasGradient {
+    fn new_inherited(style: FillOrStrokeStyle) -> CanvasGradient {
+        CanvasGradient {
+            reflector_: Reflector::new(),
+            style: DOMRefCell::new(style),
+        }
+    }
+
+    pub fn new(global: GlobalRef, style: FillOrStrokeStyle) -> Temporary<CanvasGradient> {
+        reflect_dom_object(box CanvasGradient::new_inherited(style),
+                           global, CanvasGradientBinding::Wrap)
+    }
+}
+
+pub trait CanvasGradientHelpers {
+    /// Returns the gradient with the color stops added so far.
+    fn to_fill_or_stroke_style(self) -> FillOrStrokeStyle;
+}
+
+impl<'a> CanvasGradientHelpers for JSRef<'a, CanvasGradient> {
+    fn to_fill_or_stroke_style(self) -> FillOrStrokeStyle {
+        self.style.borrow().clone()
+    }
+}
+
+impl<'a> CanvasGradientMethods for JSRef<'a, CanvasGradient> {
+    // http://www.whatwg.org/html/#dom-canvasgradient-addcolorstop
+    fn AddColorStop(self, offset: f64, color: DOMString) -> ErrorResult {
+        if !(offset >= 0.0 && offset <= 1.0) {
+            return Err(IndexSize)
+        }
+        let color = match parse_color(color.as_slice()) {
+            Ok(color) => color,
+            Err(()) => return Err(Syntax),
+        };
+
+        // Stops with the same offset stay in the order they were added in.
+        let stop = CanvasGradientStop { offset: offset, color: color };
+        let mut style = self.style.borrow_mut();
+        let stops = match *style {
+            LinearGradientStyle(ref mut gradient) => &mut gradient.stops,
+            RadialGradientStyle(ref mut gradient) => &mut gradient.stops,
+            ColorStyle(_) => unreachable!(),
+        };
+        let index = stops.iter().position(|existing| existing.offset > offset)
+                                .unwrap_or(stops.len());
+        stops.insert(index, stop);
+        Ok(())
+    }
+}
+
+impl Reflectable for CanvasGradient {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -2,18 +2,101 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding;
 use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasRenderingContext2DMethods;
+use dom::bindings::codegen::Bindings::ImageDataBinding::ImageDataMethods;
+use dom::bindings::codegen::UnionTypes::HTMLImageElementOrHTMLCanvasElement::{HTMLImageElementOrHTMLCanvasElement, eHTMLImageElement, eHTMLCanvasElement};
+use dom::bindings::codegen::UnionTypes::StringOrCanvasGradient::{StringOrCanvasGradient, eString, eCanvasGradient};
+use dom::bindings::error::{ErrorResult, Fallible, IndexSize, InvalidState};
 use dom::bindings::global::{GlobalRef, GlobalField};
 use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflector, Reflectable, reflect_dom_object};
-use dom::htmlcanvaselement::HTMLCanvasElement;
+use dom::canvasgradient::{CanvasGradient, CanvasGradientHelpers};
+use dom::htmlcanvaselement::{HTMLCanvasElement, HTMLCanvasElementHelpers};
+use dom::htmlimageelement::{HTMLImageElement, HTMLImageElementHelpers};
+use dom::imagedata::{ImageData, ImageDataHelpers};
+use dom::textmetrics::TextMetrics;
 
+use cssparser::{Color, RGBA, RGBAColor, tokenize};
+use cssparser::ast::WhiteSpace;
+use geom::matrix2d::Matrix2D;
 use geom::point::Point2D;
 use geom::rect::Rect;
 use geom::size::Size2D;
+use gfx::font::SpecifiedFontStyle;
+use png::{RGB8, RGBA8, K8, KA8};
+use servo_net::image_cache_task::{Decode, GetImage, ImageFailed, ImageNotReady, ImageReady};
+use servo_util::str::DOMString;
+use style::{DeclarationBlock, cascade, parse_style_attribute};
+use sync::Arc;
+use url::Url;
 
-use canvas::canvas_render_task::{CanvasMsg, CanvasRenderTask, ClearRect, Close, FillRect, Recreate, StrokeRect};
+use canvas::canvas_render_task::{CanvasMsg, CanvasRenderTask, Recreate, Close};
+use canvas::canvas_render_task::{FillRect, ClearRect, StrokeRect, BeginPath, ClosePath};
+use canvas::canvas_render_task::{MoveTo, LineTo, QuadraticCurveTo, BezierCurveTo, AddArc, AddRect};
+use canvas::canvas_render_task::{Fill, Stroke, Clip, SaveContext, RestoreContext};
+use canvas::canvas_render_task::{SetFillStyle, SetStrokeStyle, SetLineWidth, SetLineCap};
+use canvas::canvas_render_task::{SetLineJoin, SetMiterLimit, SetGlobalAlpha, SetTransform};
+use canvas::canvas_render_task::{SetFont, SetTextAlign, SetTextBaseline, FillText, MeasureText};
+use canvas::canvas_render_task::{DrawImage, DrawImageSelf, GetImageData, PutImageData};
+use canvas::canvas_render_task::SendPixelContents;
+use canvas::canvas_render_task::{FillOrStrokeStyle, ColorStyle, LinearGradient, RadialGradient};
+use canvas::canvas_render_task::{LinearGradientStyle, RadialGradientStyle};
+use canvas::canvas_render_task::{LineCapStyle, ButtCap, LineJoinStyle, MiterJoin};
+use canvas::canvas_render_task::{TextAlign, StartAlign, TextBaseline, AlphabeticBaseline};
+
+use std::comm;
+use std::num::{Float, FloatMath};
+
+/// The value of `fillStyle` or `strokeStyle`, as script sees it.
+#[deriving(Clone)]
+#[jstraceable]
+#[must_root]
+enum CanvasStyle {
+    ColorCanvasStyle(RGBA),
+    GradientCanvasStyle(JS<CanvasGradient>),
+}
+
+/// The drawing state that `save()` pushes and `restore()` pops, for the attribute getters. The
+/// canvas render task keeps its own copy, which it uses to draw.
+#[deriving(Clone)]
+#[jstraceable]
+#[must_root]
+struct CanvasContextState {
+    global_alpha: f64,
+    fill_style: CanvasStyle,
+    stroke_style: CanvasStyle,
+    line_width: f64,
+    line_cap: LineCapStyle,
+    line_join: LineJoinStyle,
+    miter_limit: f64,
+    transform: Matrix2D<f32>,
+    font: DOMString,
+    text_align: TextAlign,
+    text_baseline: TextBaseline,
+}
+
+impl CanvasContextState {
+    fn new() -> CanvasContextState {
+        let black = RGBA { red: 0., green: 0., blue: 0., alpha: 1. };
+        CanvasContextState {
+            global_alpha: 1.0,
+            fill_style: ColorCanvasStyle(black),
+            stroke_style: ColorCanvasStyle(black),
+            line_width: 1.0,
+            line_cap: ButtCap,
+            line_join: MiterJoin,
+            miter_limit: 10.0,
+            transform: Matrix2D::identity(),
+            font: DEFAULT_FONT.to_string(),
+            text_align: StartAlign,
+            text_baseline: AlphabeticBaseline,
+        }
+    }
+}
+
+static DEFAULT_FONT: &'static str = "10px sans-serif";
 
 #[dom_struct]
 pub struct CanvasRenderingContext2D {
@@ -21,15 +104,26 @@ pub struct CanvasRenderingContext2D {
     global: GlobalField,
     renderer: Sender<CanvasMsg>,
     canvas: JS<HTMLCanvasElement>,
+    state: DOMRefCell<CanvasContextState>,
+    saved_states: DOMRefCell<Vec<CanvasContextState>>,
 }
 
 impl CanvasRenderingContext2D {
     fn new_inherited(global: &GlobalRef, canvas: JSRef<HTMLCanvasElement>, size: Size2D<i32>) -> CanvasRenderingContext2D {
+        let font_cache_task = global.as_window().font_cache_task().clone();
+        let renderer = CanvasRenderTask::start(size, font_cache_task);
+        match parse_font(DEFAULT_FONT, &global.get_url()) {
+            Some(font_style) => renderer.send(SetFont(font_style)),
+            None => {}
+        }
+
         CanvasRenderingContext2D {
             reflector_: Reflector::new(),
             global: GlobalField::from_rooted(global),
-            renderer: CanvasRenderTask::start(size),
+            renderer: renderer,
             canvas: JS::from_rooted(canvas),
+            state: DOMRefCell::new(CanvasContextState::new()),
+            saved_states: DOMRefCell::new(vec!()),
         }
     }
 
@@ -40,6 +134,159 @@ impl CanvasRenderingContext2D {
 
     pub fn recreate(&self, size: Size2D<i32>) {
         self.renderer.send(Recreate(size));
+        let font = self.state.borrow().font.clone();
+        *self.state.borrow_mut() = CanvasContextState { font: font, .. CanvasContextState::new() };
+        self.saved_states.borrow_mut().clear();
+    }
+
+    /// Returns the pixels of the given rectangle of the canvas, as non-premultiplied RGBA.
+    pub fn get_image_data(&self, rect: Rect<i32>) -> Vec<u8> {
+        let (sender, receiver) = comm::channel();
+        self.renderer.send(GetImageData(rect, sender));
+        receiver.recv()
+    }
+
+    /// Returns all the pixels of the canvas, in the format `DrawImage` expects them in.
+    fn get_pixel_contents(&self) -> Vec<u8> {
+        let (sender, receiver) = comm::channel();
+        self.renderer.send(SendPixelContents(sender));
+        receiver.recv()
+    }
+
+    fn set_transform(&self, transform: Matrix2D<f32>) {
+        self.state.borrow_mut().transform = transform;
+        self.renderer.send(SetTransform(transform));
+    }
+
+    /// Multiplies the current transform by the given one. HTML § 4.12.4.1.6.
+    fn apply_transform(&self, transform: Matrix2D<f32>) {
+        let current = self.state.borrow().transform;
+        self.set_transform(current.mul(&transform));
+    }
+
+    /// Returns the pixels and size of the given image source, or `None` if there is nothing to
+    /// draw.
+    fn fetch_image_data(&self, image: &HTMLImageElementOrHTMLCanvasElement)
+                        -> Fallible<Option<(Vec<u8>, Size2D<i32>)>> {
+        match *image {
+            eHTMLImageElement(ref image) => Ok(self.fetch_image_element_data(*image.root())),
+            eHTMLCanvasElement(ref canvas) => {
+                let canvas = canvas.root();
+                let size = canvas.get_size();
+                if size.width == 0 || size.height == 0 {
+                    return Err(InvalidState)
+                }
+                match canvas.get_context() {
+                    Some(context) => Ok(Some((context.root().get_pixel_contents(), size))),
+                    // A canvas nobody has drawn on is transparent.
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    fn fetch_image_element_data(&self, image: JSRef<HTMLImageElement>)
+                                -> Option<(Vec<u8>, Size2D<i32>)> {
+        let url = match image.get_url() {
+            Some(url) => url,
+            None => return None,
+        };
+
+        // Images that are not fully decoded yet are not drawn at all, so there is no need to wait
+        // for them.
+        let global = self.global.root();
+        let global = global.root_ref();
+        let window = global.as_window();
+        let image_cache = window.image_cache_task();
+        let (sender, receiver) = comm::channel();
+        image_cache.send(Decode(url.clone()));
+        image_cache.send(GetImage(url, sender));
+        let image = match receiver.recv() {
+            ImageReady(image) => image,
+            ImageNotReady | ImageFailed => return None,
+        };
+
+        // The image cache stores pixels as premultiplied BGRA, like the canvas.
+        let size = Size2D(image.width as i32, image.height as i32);
+        let pixels = match image.pixels {
+            RGBA8(ref pixels) => pixels.clone(),
+            RGB8(ref pixels) => {
+                let mut bgra = Vec::with_capacity(pixels.len() / 3 * 4);
+                for pixel in pixels.as_slice().chunks(3) {
+                    bgra.push_all(pixel);
+                    bgra.push(255);
+                }
+                bgra
+            }
+            K8(ref pixels) => {
+                pixels.iter().flat_map(|&value| vec!(value, value, value, 255).into_iter())
+                             .collect()
+            }
+            KA8(ref pixels) => {
+                let mut bgra = Vec::with_capacity(pixels.len() * 2);
+                for pixel in pixels.as_slice().chunks(2) {
+                    let value = (pixel[0] as u32 * pixel[1] as u32 / 255) as u8;
+                    bgra.push_all(&[value, value, value, pixel[1]]);
+                }
+                bgra
+            }
+        };
+        Some((pixels, size))
+    }
+
+    fn draw_image(&self,
+                  image: HTMLImageElementOrHTMLCanvasElement,
+                  source_rect: Option<Rect<f64>>,
+                  dest_rect: Option<Rect<f64>>,
+                  dest_origin: Point2D<f64>)
+                  -> ErrorResult {
+        // Drawing a canvas onto itself goes through a copy in the canvas render task.
+        let same_canvas = match image {
+            eHTMLCanvasElement(ref canvas) => *canvas == self.canvas,
+            eHTMLImageElement(_) => false,
+        };
+        let (pixels, image_size) = if same_canvas {
+            (None, self.canvas.root().get_size())
+        } else {
+            match try!(self.fetch_image_data(&image)) {
+                Some((pixels, image_size)) => (Some(pixels), image_size),
+                None => return Ok(()),
+            }
+        };
+
+        let image_rect = Rect(Point2D(0.0, 0.0),
+                              Size2D(image_size.width as f64, image_size.height as f64));
+        let source_rect = source_rect.unwrap_or(image_rect);
+        let dest_rect = dest_rect.unwrap_or(Rect(dest_origin, image_rect.size));
+
+        // Only the part of the source rectangle inside the image is drawn, into the matching part
+        // of the destination rectangle. HTML § 4.12.4.1.14.
+        let source_rect = normalize_rect(source_rect);
+        let dest_rect = normalize_rect(dest_rect);
+        let clipped_source_rect = match source_rect.intersection(&image_rect) {
+            Some(rect) => rect,
+            None => return Ok(()),
+        };
+        if source_rect.size.width == 0.0 || source_rect.size.height == 0.0 {
+            return Ok(())
+        }
+        let scale_x = dest_rect.size.width / source_rect.size.width;
+        let scale_y = dest_rect.size.height / source_rect.size.height;
+        let offset = clipped_source_rect.origin - source_rect.origin;
+        let clipped_dest_rect = Rect(Point2D(dest_rect.origin.x + offset.x * scale_x,
+                                             dest_rect.origin.y + offset.y * scale_y),
+                                     Size2D(clipped_source_rect.size.width * scale_x,
+                                            clipped_source_rect.size.height * scale_y));
+
+        let source_rect = to_f32_rect(clipped_source_rect);
+        let dest_rect = to_f32_rect(clipped_dest_rect);
+        match pixels {
+            Some(pixels) => {
+                self.renderer.send(DrawImage(pixels, image_size, source_rect, dest_rect))
+            }
+            None => self.renderer.send(DrawImageSelf(source_rect, dest_rect)),
+        }
+        Ok(())
     }
 }
 
@@ -48,6 +295,130 @@ impl<'a> CanvasRenderingContext2DMethods for JSRef<'a, CanvasRenderingContext2D>
         Temporary::new(self.canvas)
     }
 
+    fn Save(self) {
+        self.saved_states.borrow_mut().push(self.state.borrow().clone());
+        self.renderer.send(SaveContext);
+    }
+
+    fn Restore(self) {
+        match self.saved_states.borrow_mut().pop() {
+            Some(state) => *self.state.borrow_mut() = state,
+            None => return,
+        }
+        self.renderer.send(RestoreContext);
+    }
+
+    fn Scale(self, x: f64, y: f64) {
+        if !is_finite(&[x, y]) {
+            return
+        }
+        self.apply_transform(Matrix2D::new(x as f32, 0.0, 0.0, y as f32, 0.0, 0.0));
+    }
+
+    fn Rotate(self, angle: f64) {
+        if !angle.is_finite() {
+            return
+        }
+        let (sin, cos) = (angle.sin() as f32, angle.cos() as f32);
+        self.apply_transform(Matrix2D::new(cos, sin, -sin, cos, 0.0, 0.0));
+    }
+
+    fn Translate(self, x: f64, y: f64) {
+        if !is_finite(&[x, y]) {
+            return
+        }
+        self.apply_transform(Matrix2D::new(1.0, 0.0, 0.0, 1.0, x as f32, y as f32));
+    }
+
+    fn Transform(self, a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) {
+        if !is_finite(&[a, b, c, d, e, f]) {
+            return
+        }
+        self.apply_transform(Matrix2D::new(a as f32, b as f32,
+                                           c as f32, d as f32,
+                                           e as f32, f as f32));
+    }
+
+    fn SetTransform(self, a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) {
+        if !is_finite(&[a, b, c, d, e, f]) {
+            return
+        }
+        self.set_transform(Matrix2D::new(a as f32, b as f32,
+                                         c as f32, d as f32,
+                                         e as f32, f as f32));
+    }
+
+    fn ResetTransform(self) {
+        self.set_transform(Matrix2D::identity());
+    }
+
+    fn GlobalAlpha(self) -> f64 {
+        self.state.borrow().global_alpha
+    }
+
+    fn SetGlobalAlpha(self, alpha: f64) {
+        if !alpha.is_finite() || alpha < 0.0 || alpha > 1.0 {
+            return
+        }
+        self.state.borrow_mut().global_alpha = alpha;
+        self.renderer.send(SetGlobalAlpha(alpha as f32));
+    }
+
+    fn StrokeStyle(self) -> StringOrCanvasGradient {
+        style_to_union(&self.state.borrow().stroke_style)
+    }
+
+    fn SetStrokeStyle(self, value: StringOrCanvasGradient) {
+        match union_to_style(value) {
+            Some((style, fill_or_stroke_style)) => {
+                self.state.borrow_mut().stroke_style = style;
+                self.renderer.send(SetStrokeStyle(fill_or_stroke_style));
+            }
+            None => {}
+        }
+    }
+
+    fn FillStyle(self) -> StringOrCanvasGradient {
+        style_to_union(&self.state.borrow().fill_style)
+    }
+
+    fn SetFillStyle(self, value: StringOrCanvasGradient) {
+        match union_to_style(value) {
+            Some((style, fill_or_stroke_style)) => {
+                self.state.borrow_mut().fill_style = style;
+                self.renderer.send(SetFillStyle(fill_or_stroke_style));
+            }
+            None => {}
+        }
+    }
+
+    fn CreateLinearGradient(self, x0: f64, y0: f64, x1: f64, y1: f64)
+                            -> Temporary<CanvasGradient> {
+        let gradient = LinearGradient {
+            start: Point2D(x0, y0),
+            end: Point2D(x1, y1),
+            stops: vec!(),
+        };
+        let global = self.global.root();
+        CanvasGradient::new(global.root_ref(), LinearGradientStyle(gradient))
+    }
+
+    fn CreateRadialGradient(self, x0: f64, y0: f64, r0: f64, x1: f64, y1: f64, r1: f64)
+                            -> Fallible<Temporary<CanvasGradient>> {
+        if r0 < 0.0 || r1 < 0.0 {
+            return Err(IndexSize)
+        }
+        let gradient = RadialGradient {
+            start_center: Point2D(x0, y0),
+            start_radius: r0,
+            end_center: Point2D(x1, y1),
+            end_radius: r1,
+            stops: vec!(),
+        };
+        let global = self.global.root();
+        Ok(CanvasGradient::new(global.root_ref(), RadialGradientStyle(gradient)))
+    }
+
     fn FillRect(self, x: f64, y: f64, width: f64, height: f64) {
         let rect = Rect(Point2D(x as f32, y as f32), Size2D(width as f32, height as f32));
         self.renderer.send(FillRect(rect));
@@ -62,6 +433,292 @@ impl<'a> CanvasRenderingContext2DMethods for JSRef<'a, CanvasRenderingContext2D>
         let rect = Rect(Point2D(x as f32, y as f32), Size2D(width as f32, height as f32));
         self.renderer.send(StrokeRect(rect));
     }
+
+    fn BeginPath(self) {
+        self.renderer.send(BeginPath);
+    }
+
+    fn Fill(self) {
+        self.renderer.send(Fill);
+    }
+
+    fn Stroke(self) {
+        self.renderer.send(Stroke);
+    }
+
+    fn Clip(self) {
+        self.renderer.send(Clip);
+    }
+
+    fn FillText(self, text: DOMString, x: f64, y: f64, max_width: Option<f64>) {
+        if !is_finite(&[x, y]) || !max_width.map_or(true, |width| width.is_finite()) {
+            return
+        }
+        // Whitespace characters are all drawn as spaces. HTML § 4.12.4.1.12.
+        let text = text.as_slice().chars().map(|character| {
+            match character {
+                '\t' | '\n' | '\x0C' | '\r' => ' ',
+                character => character,
+            }
+        }).collect();
+        let origin = Point2D(x as f32, y as f32);
+        self.renderer.send(FillText(text, origin, max_width.map(|width| width as f32)));
+    }
+
+    fn MeasureText(self, text: DOMString) -> Temporary<TextMetrics> {
+        let (sender, receiver) = comm::channel();
+        self.renderer.send(MeasureText(text, sender));
+        let global = self.global.root();
+        TextMetrics::new(global.root_ref(), receiver.recv() as f64)
+    }
+
+    fn DrawImage(self, image: HTMLImageElementOrHTMLCanvasElement, dx: f64, dy: f64)
+                 -> ErrorResult {
+        if !is_finite(&[dx, dy]) {
+            return Ok(())
+        }
+        self.draw_image(image, None, None, Point2D(dx, dy))
+    }
+
+    fn DrawImage_(self, image: HTMLImageElementOrHTMLCanvasElement,
+                  dx: f64, dy: f64, dw: f64, dh: f64) -> ErrorResult {
+        if !is_finite(&[dx, dy, dw, dh]) {
+            return Ok(())
+        }
+        let dest_rect = Rect(Point2D(dx, dy), Size2D(dw, dh));
+        self.draw_image(image, None, Some(dest_rect), dest_rect.origin)
+    }
+
+    fn DrawImage__(self, image: HTMLImageElementOrHTMLCanvasElement,
+                   sx: f64, sy: f64, sw: f64, sh: f64,
+                   dx: f64, dy: f64, dw: f64, dh: f64) -> ErrorResult {
+        if !is_finite(&[sx, sy, sw, sh, dx, dy, dw, dh]) {
+            return Ok(())
+        }
+        let source_rect = Rect(Point2D(sx, sy), Size2D(sw, sh));
+        let dest_rect = Rect(Point2D(dx, dy), Size2D(dw, dh));
+        self.draw_image(image, Some(source_rect), Some(dest_rect), dest_rect.origin)
+    }
+
+    fn CreateImageData(self, sw: f64, sh: f64) -> Fallible<Temporary<ImageData>> {
+        if sw == 0.0 || sh == 0.0 {
+            return Err(IndexSize)
+        }
+        let global = self.global.root();
+        Ok(ImageData::new(global.root_ref(), sw.abs() as u32, sh.abs() as u32, None))
+    }
+
+    fn CreateImageData_(self, imagedata: JSRef<ImageData>) -> Temporary<ImageData> {
+        let global = self.global.root();
+        ImageData::new(global.root_ref(), imagedata.Width(), imagedata.Height(), None)
+    }
+
+    fn GetImageData(self, sx: f64, sy: f64, sw: f64, sh: f64) -> Fallible<Temporary<ImageData>> {
+        if sw == 0.0 || sh == 0.0 {
+            return Err(IndexSize)
+        }
+        let rect = normalize_rect(Rect(Point2D(sx, sy), Size2D(sw, sh)));
+        let rect = Rect(Point2D(rect.origin.x.floor() as i32, rect.origin.y.floor() as i32),
+                        Size2D(rect.size.width.ceil() as i32, rect.size.height.ceil() as i32));
+        let data = self.get_image_data(rect);
+        let global = self.global.root();
+        Ok(ImageData::new(global.root_ref(),
+                          rect.size.width as u32,
+                          rect.size.height as u32,
+                          Some(data)))
+    }
+
+    fn PutImageData(self, imagedata: JSRef<ImageData>, dx: f64, dy: f64) {
+        let size = imagedata.get_size();
+        self.PutImageData_(imagedata, dx, dy, 0.0, 0.0, size.width as f64, size.height as f64)
+    }
+
+    fn PutImageData_(self, imagedata: JSRef<ImageData>, dx: f64, dy: f64,
+                     dirty_x: f64, dirty_y: f64, dirty_width: f64, dirty_height: f64) {
+        // Only the part of the dirty rectangle inside the image data is copied. HTML
+        // § 4.12.4.1.15.
+        let size = imagedata.get_size();
+        let dirty_rect = normalize_rect(Rect(Point2D(dirty_x, dirty_y),
+                                             Size2D(dirty_width, dirty_height)));
+        let dirty_rect = Rect(Point2D(dirty_rect.origin.x.floor() as i32,
+                                      dirty_rect.origin.y.floor() as i32),
+                              Size2D(dirty_rect.size.width.ceil() as i32,
+                                     dirty_rect.size.height.ceil() as i32));
+        let image_rect = Rect(Point2D(0, 0), size);
+        let dirty_rect = match dirty_rect.intersection(&image_rect) {
+            Some(rect) if !rect.is_empty() => rect,
+            _ => return,
+        };
+
+        let global = self.global.root();
+        let data = imagedata.get_data_array(&global.root_ref());
+        let length = dirty_rect.size.width * dirty_rect.size.height * 4;
+        let mut pixels = Vec::with_capacity(length as uint);
+        for y in range(dirty_rect.origin.y, dirty_rect.max_y()) {
+            let start = ((y * size.width + dirty_rect.origin.x) * 4) as uint;
+            let end = start + (dirty_rect.size.width * 4) as uint;
+            pixels.push_all(data.slice(start, end));
+        }
+
+        let dest_rect = Rect(Point2D(dx as i32 + dirty_rect.origin.x,
+                                     dy as i32 + dirty_rect.origin.y),
+                             dirty_rect.size);
+        self.renderer.send(PutImageData(pixels, dest_rect));
+    }
+
+    fn LineWidth(self) -> f64 {
+        self.state.borrow().line_width
+    }
+
+    fn SetLineWidth(self, width: f64) {
+        if !width.is_finite() || width <= 0.0 {
+            return
+        }
+        self.state.borrow_mut().line_width = width;
+        self.renderer.send(SetLineWidth(width as f32));
+    }
+
+    fn LineCap(self) -> DOMString {
+        self.state.borrow().line_cap.as_str().to_string()
+    }
+
+    fn SetLineCap(self, cap: DOMString) {
+        match LineCapStyle::from_str(cap.as_slice()) {
+            Some(cap) => {
+                self.state.borrow_mut().line_cap = cap;
+                self.renderer.send(SetLineCap(cap));
+            }
+            None => {}
+        }
+    }
+
+    fn LineJoin(self) -> DOMString {
+        self.state.borrow().line_join.as_str().to_string()
+    }
+
+    fn SetLineJoin(self, join: DOMString) {
+        match LineJoinStyle::from_str(join.as_slice()) {
+            Some(join) => {
+                self.state.borrow_mut().line_join = join;
+                self.renderer.send(SetLineJoin(join));
+            }
+            None => {}
+        }
+    }
+
+    fn MiterLimit(self) -> f64 {
+        self.state.borrow().miter_limit
+    }
+
+    fn SetMiterLimit(self, limit: f64) {
+        if !limit.is_finite() || limit <= 0.0 {
+            return
+        }
+        self.state.borrow_mut().miter_limit = limit;
+        self.renderer.send(SetMiterLimit(limit as f32));
+    }
+
+    fn Font(self) -> DOMString {
+        self.state.borrow().font.clone()
+    }
+
+    fn SetFont(self, font: DOMString) {
+        let global = self.global.root();
+        match parse_font(font.as_slice(), &global.root_ref().get_url()) {
+            Some(font_style) => {
+                self.state.borrow_mut().font = font;
+                self.renderer.send(SetFont(font_style));
+            }
+            None => {}
+        }
+    }
+
+    fn TextAlign(self) -> DOMString {
+        self.state.borrow().text_align.as_str().to_string()
+    }
+
+    fn SetTextAlign(self, align: DOMString) {
+        match TextAlign::from_str(align.as_slice()) {
+            Some(align) => {
+                self.state.borrow_mut().text_align = align;
+                self.renderer.send(SetTextAlign(align));
+            }
+            None => {}
+        }
+    }
+
+    fn TextBaseline(self) -> DOMString {
+        self.state.borrow().text_baseline.as_str().to_string()
+    }
+
+    fn SetTextBaseline(self, baseline: DOMString) {
+        match TextBaseline::from_str(baseline.as_slice()) {
+            Some(baseline) => {
+                self.state.borrow_mut().text_baseline = baseline;
+                self.renderer.send(SetTextBaseline(baseline));
+            }
+            None => {}
+        }
+    }
+
+    fn ClosePath(self) {
+        self.renderer.send(ClosePath);
+    }
+
+    fn MoveTo(self, x: f64, y: f64) {
+        if !is_finite(&[x, y]) {
+            return
+        }
+        self.renderer.send(MoveTo(Point2D(x as f32, y as f32)));
+    }
+
+    fn LineTo(self, x: f64, y: f64) {
+        if !is_finite(&[x, y]) {
+            return
+        }
+        self.renderer.send(LineTo(Point2D(x as f32, y as f32)));
+    }
+
+    fn QuadraticCurveTo(self, cpx: f64, cpy: f64, x: f64, y: f64) {
+        if !is_finite(&[cpx, cpy, x, y]) {
+            return
+        }
+        self.renderer.send(QuadraticCurveTo(Point2D(cpx as f32, cpy as f32),
+                                            Point2D(x as f32, y as f32)));
+    }
+
+    fn BezierCurveTo(self, cp1x: f64, cp1y: f64, cp2x: f64, cp2y: f64, x: f64, y: f64) {
+        if !is_finite(&[cp1x, cp1y, cp2x, cp2y, x, y]) {
+            return
+        }
+        self.renderer.send(BezierCurveTo(Point2D(cp1x as f32, cp1y as f32),
+                                         Point2D(cp2x as f32, cp2y as f32),
+                                         Point2D(x as f32, y as f32)));
+    }
+
+    fn Rect(self, x: f64, y: f64, width: f64, height: f64) {
+        if !is_finite(&[x, y, width, height]) {
+            return
+        }
+        let rect = Rect(Point2D(x as f32, y as f32), Size2D(width as f32, height as f32));
+        self.renderer.send(AddRect(rect));
+    }
+
+    fn Arc(self, x: f64, y: f64, radius: f64, start_angle: f64, end_angle: f64,
+           anticlockwise: bool) -> ErrorResult {
+        if !is_finite(&[x, y, radius, start_angle, end_angle]) {
+            return Ok(())
+        }
+        if radius < 0.0 {
+            return Err(IndexSize)
+        }
+        self.renderer.send(AddArc(Point2D(x as f32, y as f32),
+                                  radius as f32,
+                                  start_angle as f32,
+                                  end_angle as f32,
+                                  anticlockwise));
+        Ok(())
+    }
 }
 
 impl Reflectable for CanvasRenderingContext2D {
@@ -76,3 +733,97 @@ impl Drop for CanvasRenderingContext2D {
         self.renderer.send(Close);
     }
 }
+
+/// Parses a CSS color, as `fillStyle`, `strokeStyle` and `addColorStop()` accept them.
+///
+/// FIXME: `currentColor` should resolve to the color of the canvas element.
+pub fn parse_color(string: &str) -> Result<RGBA, ()> {
+    let mut tokens = tokenize(string).map(|(token, _)| token).filter(|token| {
+        match *token {
+            WhiteSpace => false,
+            _ => true,
+        }
+    });
+    match (tokens.next(), tokens.next()) {
+        (Some(ref token), None) => {
+            match Color::parse(token) {
+                Ok(RGBAColor(rgba)) => Ok(rgba),
+                _ => Err(()),
+            }
+        }
+        _ => Err(()),
+    }
+}
+
+/// Serializes a color the way the `fillStyle` and `strokeStyle` getters do. HTML § 4.12.4.1.8.
+fn serialize_color(color: &RGBA) -> DOMString {
+    let to_byte = |component: f32| (component * 255.0).round() as u8;
+    let (red, green, blue) = (to_byte(color.red), to_byte(color.green), to_byte(color.blue));
+    if color.alpha == 1.0 {
+        format!("#{:02x}{:02x}{:02x}", red, green, blue)
+    } else {
+        format!("rgba({}, {}, {}, {})", red, green, blue, color.alpha)
+    }
+}
+
+/// Parses the value of the `font` attribute as the `font` shorthand property.
+fn parse_font(value: &str, base_url: &Url) -> Option<Arc<SpecifiedFontStyle>> {
+    let declarations = parse_style_attribute(format!("font: {}", value).as_slice(), base_url);
+    if declarations.normal.is_empty() {
+        return None
+    }
+    let block = DeclarationBlock::from_declarations(declarations.normal);
+    let (style, _) = cascade(&[block], false, None, None);
+    Some(style.get_font_arc())
+}
+
+fn style_to_union(style: &CanvasStyle) -> StringOrCanvasGradient {
+    match *style {
+        ColorCanvasStyle(ref color) => eString(serialize_color(color)),
+        GradientCanvasStyle(ref gradient) => eCanvasGradient(gradient.clone()),
+    }
+}
+
+/// Returns the style to store and the style to send to the canvas render task for the given
+/// value of `fillStyle` or `strokeStyle`, or `None` if the value should be ignored.
+///
+/// FIXME: Color stops added to a gradient after it has been set as the style are not taken into
+/// account.
+fn union_to_style(value: StringOrCanvasGradient) -> Option<(CanvasStyle, FillOrStrokeStyle)> {
+    match value {
+        eString(string) => {
+            parse_color(string.as_slice()).ok().map(|color| {
+                (ColorCanvasStyle(color), ColorStyle(color))
+            })
+        }
+        eCanvasGradient(gradient) => {
+            let style = gradient.root().to_fill_or_stroke_style();
+            Some((GradientCanvasStyle(gradient), style))
+        }
+    }
+}
+
+fn is_finite(values: &[f64]) -> bool {
+    values.iter().all(|value| value.is_finite())
+}
+
+/// Returns the rectangle with its negative width or height made positive, keeping the area it
+/// covers.
+fn normalize_rect(rect: Rect<f64>) -> Rect<f64> {
+    let (mut x, mut y) = (rect.origin.x, rect.origin.y);
+    let (mut width, mut height) = (rect.size.width, rect.size.height);
+    if width < 0.0 {
+        x = x + width;
+        width = -width;
+    }
+    if height < 0.0 {
+        y = y + height;
+        height = -height;
+    }
+    Rect(Point2D(x, y), Size2D(width, height))
+}
+
+fn to_f32_rect(rect: Rect<f64>) -> Rect<f32> {
+    Rect(Point2D(rect.origin.x as f32, rect.origin.y as f32),
+         Size2D(rect.size.width as f32, rect.size.height as f32))
+}
@@ -10,8 +10,9 @@ use dom::bindings::codegen::InheritTypes::{CharacterDataDerived, NodeCast};
 use dom::bindings::error::{Fallible, ErrorResult, IndexSize};
 use dom::bindings::js::JSRef;
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
+use dom::mutationobserver::{MutationObserver, CharacterDataMutation};
 use dom::node::{CommentNodeTypeId, Node, NodeTypeId, TextNodeTypeId, ProcessingInstructionNodeTypeId, NodeHelpers};
 
 use servo_util::str::DOMString;
@@ -70,9 +71,10 @@ impl<'a> CharacterDataMethods for JSRef<'a, CharacterData> {
         self.data.borrow().clone()
     }
 
+    // http://dom.spec.whatwg.org/#dom-characterdata-data
     fn SetData(self, arg: DOMString) -> ErrorResult {
-        *self.data.borrow_mut() = arg;
-        Ok(())
+        let length = self.Length();
+        self.ReplaceData(0, length, arg)
     }
 
     fn Length(self) -> u32 {
@@ -96,21 +98,33 @@ impl<'a> CharacterDataMethods for JSRef<'a, CharacterData> {
         self.ReplaceData(offset, count, "".to_string())
     }
 
+    // http://dom.spec.whatwg.org/#concept-cd-replace
     fn ReplaceData(self, offset: u32, count: u32, arg: DOMString) -> ErrorResult {
+        // Step 1.
         let length = self.data.borrow().len() as u32;
+        // Step 2.
         if offset > length {
             return Err(IndexSize);
         }
+        // Step 3.
         let count = if offset + count > length {
             length - offset
         } else {
             count
         };
+        // Step 4.
+        let node: JSRef<Node> = NodeCast::from_ref(self);
+        MutationObserver::queue_a_mutation_record(node, CharacterDataMutation,
+                                                  Some(self.data.borrow().as_slice()));
+        // Step 5-6.
+        let new_length = arg.len() as u32;
         let mut data = self.data.borrow().as_slice().slice(0, offset as uint).to_string();
         data.push_str(arg.as_slice());
         data.push_str(self.data.borrow().as_slice().slice((offset + count) as uint, length as uint));
         *self.data.borrow_mut() = data;
-        // FIXME: Once we have `Range`, we should implement step7 to step11
+        // Step 7-11.
+        let document = node.owner_doc().root();
+        document.ranges().replace_data(node, offset, count, new_length);
         Ok(())
     }
 
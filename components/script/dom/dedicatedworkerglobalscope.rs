@@ -9,17 +9,22 @@ use dom::bindings::codegen::InheritTypes::DedicatedWorkerGlobalScopeDerived;
 use dom::bindings::codegen::InheritTypes::{EventTargetCast, WorkerGlobalScopeCast};
 use dom::bindings::error::{ErrorResult, DataClone};
 use dom::bindings::global;
-use dom::bindings::js::{JSRef, Temporary, RootCollection};
+use dom::bindings::js::{JS, JSRef, Temporary, RootCollection};
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::eventtarget::{EventTarget, EventTargetHelpers};
 use dom::eventtarget::WorkerGlobalScopeTypeId;
+use dom::filereader::FileReader;
 use dom::messageevent::MessageEvent;
+use dom::messageport::{MessagePort, transfer_ports};
+use dom::websocket::WebSocket;
 use dom::worker::{Worker, TrustedWorkerAddress};
 use dom::workerglobalscope::DedicatedGlobalScope;
 use dom::workerglobalscope::{WorkerGlobalScope, WorkerGlobalScopeHelpers};
 use dom::xmlhttprequest::XMLHttpRequest;
 use script_task::{ScriptTask, ScriptChan};
 use script_task::{ScriptMsg, FromWorker,  DOMMessage, FireTimerMsg, XHRProgressMsg, XHRReleaseMsg, WorkerRelease};
+use script_task::{WebSocketEventMsg, MessagePortMsg};
+use script_task::FileReaderLoadMsg;
 use script_task::WorkerPostMessage;
 use script_task::StackRootTLS;
 
@@ -81,7 +86,10 @@ impl DedicatedWorkerGlobalScope {
 }
 
 impl DedicatedWorkerGlobalScope {
+    /// Starts a worker that runs the script at `worker_url`, for the document or worker at
+    /// `owner_url`.
     pub fn run_worker_scope(worker_url: Url,
+                            owner_url: Url,
                             worker: TrustedWorkerAddress,
                             resource_task: ResourceTask,
                             parent_sender: ScriptChan,
@@ -94,7 +102,8 @@ impl DedicatedWorkerGlobalScope {
             let roots = RootCollection::new();
             let _stack_roots_tls = StackRootTLS::new(&roots);
 
-            let (url, source) = match load_whole_resource(&resource_task, worker_url.clone()) {
+            let (url, source) = match load_whole_resource(&resource_task, worker_url.clone(),
+                                                          Some(owner_url)) {
                 Err(_) => {
                     println!("error loading script {}", worker_url.serialize());
                     return;
@@ -121,7 +130,7 @@ impl DedicatedWorkerGlobalScope {
                 EventTargetCast::from_ref(*global);
             loop {
                 match global.receiver.recv_opt() {
-                    Ok(DOMMessage(data, nbytes)) => {
+                    Ok(DOMMessage(data, nbytes, ports)) => {
                         let mut message = UndefinedValue();
                         unsafe {
                             assert!(JS_ReadStructuredClone(
@@ -130,7 +139,8 @@ impl DedicatedWorkerGlobalScope {
                                 ptr::null(), ptr::null_mut()) != 0);
                         }
 
-                        MessageEvent::dispatch_jsval(target, global::Worker(scope), message);
+                        MessageEvent::dispatch_jsval(target, global::Worker(scope), message,
+                                                     ports);
                         global.delayed_release_worker();
                     },
                     Ok(XHRProgressMsg(addr, progress)) => {
@@ -139,12 +149,21 @@ impl DedicatedWorkerGlobalScope {
                     Ok(XHRReleaseMsg(addr)) => {
                         XMLHttpRequest::handle_release(addr)
                     },
-                    Ok(WorkerPostMessage(addr, data, nbytes)) => {
-                        Worker::handle_message(addr, data, nbytes);
+                    Ok(WorkerPostMessage(addr, data, nbytes, ports)) => {
+                        Worker::handle_message(addr, data, nbytes, ports);
                     },
                     Ok(WorkerRelease(addr)) => {
                         Worker::handle_release(addr)
                     },
+                    Ok(WebSocketEventMsg(addr, event)) => {
+                        WebSocket::handle_event(addr, event)
+                    },
+                    Ok(MessagePortMsg(endpoint, generation)) => {
+                        MessagePort::handle_notification(endpoint, generation)
+                    },
+                    Ok(FileReaderLoadMsg(addr, generation)) => {
+                        FileReader::handle_load(addr, generation)
+                    },
                     Ok(FireTimerMsg(FromWorker, timer_id)) => {
                         scope.handle_fire_timer(timer_id);
                     }
@@ -152,12 +171,16 @@ impl DedicatedWorkerGlobalScope {
                     Err(_) => break,
                 }
             }
+
+            // The worker is going away, so the blob URLs it created go with it.
+            scope.blob_urls().revoke_all(scope.resource_task());
         });
     }
 }
 
 impl<'a> DedicatedWorkerGlobalScopeMethods for JSRef<'a, DedicatedWorkerGlobalScope> {
-    fn PostMessage(self, cx: *mut JSContext, message: JSVal) -> ErrorResult {
+    fn PostMessage(self, cx: *mut JSContext, message: JSVal,
+                   transfer: Option<Vec<JS<MessagePort>>>) -> ErrorResult {
         let mut data = ptr::null_mut();
         let mut nbytes = 0;
         let result = unsafe {
@@ -168,9 +191,10 @@ impl<'a> DedicatedWorkerGlobalScopeMethods for JSRef<'a, DedicatedWorkerGlobalSc
             unsafe { JS_ClearPendingException(cx); }
             return Err(DataClone);
         }
+        let ports = try!(transfer_ports(transfer, None));
 
         let ScriptChan(ref sender) = self.parent_sender;
-        sender.send(WorkerPostMessage(self.worker, data, nbytes));
+        sender.send(WorkerPostMessage(self.worker, data, nbytes, ports));
         Ok(())
     }
 
@@ -0,0 +1,64 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::DissimilarOriginWindowBinding;
+use dom::bindings::codegen::Bindings::DissimilarOriginWindowBinding::DissimilarOriginWindowMethods;
+use dom::bindings::error::ErrorResult;
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::eventtarget::{EventTarget, DissimilarOriginWindowTypeId};
+use dom::messageport::MessagePort;
+use dom::window::{Window, post_message};
+
+use servo_msg::constellation_msg::{ConstellationChan, PipelineId};
+use servo_util::str::DOMString;
+
+use js::jsapi::JSContext;
+use js::jsval::JSVal;
+
+/// A window that lives in another script task, as seen from a window of this one, e.g. as the
+/// `source` of a message it posted.
+#[dom_struct]
+pub struct DissimilarOriginWindow {
+    eventtarget: EventTarget,
+    /// The pipeline of the window.
+    pipeline: PipelineId,
+    constellation_chan: ConstellationChan,
+}
+
+impl DissimilarOriginWindow {
+    fn new_inherited(pipeline: PipelineId, constellation_chan: ConstellationChan)
+                     -> DissimilarOriginWindow {
+        DissimilarOriginWindow {
+            eventtarget: EventTarget::new_inherited(DissimilarOriginWindowTypeId),
+            pipeline: pipeline,
+            constellation_chan: constellation_chan,
+        }
+    }
+
+    pub fn new(window: JSRef<Window>, pipeline: PipelineId)
+               -> Temporary<DissimilarOriginWindow> {
+        let constellation_chan = window.page().constellation_chan.clone();
+        reflect_dom_object(box DissimilarOriginWindow::new_inherited(pipeline,
+                                                                     constellation_chan),
+                           global::Window(window),
+                           DissimilarOriginWindowBinding::Wrap)
+    }
+}
+
+impl<'a> DissimilarOriginWindowMethods for JSRef<'a, DissimilarOriginWindow> {
+    // http://www.whatwg.org/html/#dom-window-postmessage
+    fn PostMessage(self, cx: *mut JSContext, message: JSVal, targetOrigin: DOMString,
+                   transfer: Option<Vec<JS<MessagePort>>>) -> ErrorResult {
+        post_message(cx, &self.constellation_chan, self.pipeline, message, targetOrigin,
+                     transfer)
+    }
+}
+
+impl Reflectable for DissimilarOriginWindow {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.eventtarget.reflector()
+    }
+}
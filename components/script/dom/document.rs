@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use dom::attr::{Attr, AttrHelpers, StringAttrValue};
+use dom::bindings::callback::ReportExceptions;
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::DocumentBinding;
 use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState};
@@ -11,7 +12,8 @@ use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
 use dom::bindings::codegen::Bindings::EventTargetBinding::EventTargetMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
 use dom::bindings::codegen::Bindings::NodeFilterBinding::NodeFilter;
-use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::codegen::Bindings::PerformanceBinding::PerformanceMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::{FrameRequestCallback, WindowMethods};
 use dom::bindings::codegen::InheritTypes::{DocumentDerived, EventCast, HTMLElementCast};
 use dom::bindings::codegen::InheritTypes::{HTMLHeadElementCast, TextCast, ElementCast};
 use dom::bindings::codegen::InheritTypes::{DocumentTypeCast, HTMLHtmlElementCast, NodeCast};
@@ -52,10 +54,13 @@ use dom::node::{CloneChildren, DoNotCloneChildren};
 use dom::nodelist::NodeList;
 use dom::text::Text;
 use dom::processinginstruction::ProcessingInstruction;
-use dom::range::Range;
+use dom::range::{Range, WeakRangeVec};
 use dom::treewalker::TreeWalker;
 use dom::uievent::UIEvent;
 use dom::window::{Window, WindowHelpers};
+use servo_msg::constellation_msg::{AnimationCallbacksPresent, AnimationState};
+use servo_msg::constellation_msg::{ChangeRunningAnimationsStateMsg, ConstellationChan};
+use servo_msg::constellation_msg::NoAnimationCallbacksPresent;
 use servo_util::namespace;
 use servo_util::str::{DOMString, split_html_space_chars};
 
@@ -87,7 +92,7 @@ pub struct Document {
     last_modified: DOMRefCell<Option<DOMString>>,
     encoding_name: DOMRefCell<DOMString>,
     is_html_document: bool,
-    url: Url,
+    url: DOMRefCell<Url>,
     quirks_mode: Cell<QuirksMode>,
     images: MutNullableJS<HTMLCollection>,
     embeds: MutNullableJS<HTMLCollection>,
@@ -101,6 +106,13 @@ pub struct Document {
     possibly_focused: MutNullableJS<Element>,
     /// The element that currently has the document focus context.
     focused: MutNullableJS<Element>,
+    /// The live ranges whose boundary points may be in this document.
+    ranges: WeakRangeVec,
+    /// The identifier of the most recently requested animation frame callback.
+    animation_frame_ident: Cell<u32>,
+    /// The animation frame callbacks waiting for the next tick, with their identifiers. Cancelled
+    /// callbacks are replaced by `None`.
+    animation_frame_list: DOMRefCell<Vec<(u32, Option<FrameRequestCallback>)>>,
 }
 
 impl DocumentDerived for EventTarget {
@@ -170,7 +182,8 @@ pub trait DocumentHelpers<'a> {
     fn window(self) -> Temporary<Window>;
     fn encoding_name(self) -> Ref<'a, DOMString>;
     fn is_html_document(self) -> bool;
-    fn url(self) -> &'a Url;
+    fn url(self) -> Ref<'a, Url>;
+    fn set_url(self, url: Url);
     fn quirks_mode(self) -> QuirksMode;
     fn set_quirks_mode(self, mode: QuirksMode);
     fn set_last_modified(self, value: DOMString);
@@ -187,6 +200,10 @@ pub trait DocumentHelpers<'a> {
     fn begin_focus_transaction(self);
     fn request_focus(self, elem: JSRef<Element>);
     fn commit_focus_transaction(self);
+    fn ranges(self) -> &'a WeakRangeVec;
+    fn request_animation_frame(self, callback: FrameRequestCallback) -> u32;
+    fn cancel_animation_frame(self, ident: u32);
+    fn invoke_animation_callbacks(self);
 }
 
 impl<'a> DocumentHelpers<'a> for JSRef<'a, Document> {
@@ -205,8 +222,12 @@ impl<'a> DocumentHelpers<'a> for JSRef<'a, Document> {
         self.is_html_document
     }
 
-    fn url(self) -> &'a Url {
-        &self.extended_deref().url
+    fn url(self) -> Ref<'a, Url> {
+        self.extended_deref().url.borrow()
+    }
+
+    fn set_url(self, url: Url) {
+        *self.url.borrow_mut() = url;
     }
 
     fn quirks_mode(self) -> QuirksMode {
@@ -360,6 +381,77 @@ impl<'a> DocumentHelpers<'a> for JSRef<'a, Document> {
         //TODO: dispatch blur, focus, focusout, and focusin events
         self.focused.assign(self.possibly_focused.get());
     }
+
+    fn ranges(self) -> &'a WeakRangeVec {
+        &self.extended_deref().ranges
+    }
+
+    // http://www.w3.org/TR/animation-timing/#dom-windowanimationtiming-requestanimationframe
+    fn request_animation_frame(self, callback: FrameRequestCallback) -> u32 {
+        let ident = self.animation_frame_ident.get() + 1;
+        self.animation_frame_ident.set(ident);
+
+        let mut list = self.animation_frame_list.borrow_mut();
+        if list.is_empty() {
+            let window = self.window.root();
+            send_animation_state(*window, AnimationCallbacksPresent);
+        }
+        list.push((ident, Some(callback)));
+        ident
+    }
+
+    // http://www.w3.org/TR/animation-timing/#dom-windowanimationtiming-cancelanimationframe
+    fn cancel_animation_frame(self, ident: u32) {
+        let mut list = self.animation_frame_list.borrow_mut();
+        match list.iter_mut().find(|entry| *entry.ref0() == ident) {
+            Some(entry) => *entry.mut1() = None,
+            None => {}
+        }
+    }
+
+    // http://www.w3.org/TR/animation-timing/#processingmodel
+    fn invoke_animation_callbacks(self) {
+        let window = self.window.root();
+        let performance = window.Performance().root();
+        let time = performance.Now();
+
+        // Callbacks requested by the ones we run now wait for the next tick.
+        let count = self.animation_frame_list.borrow().len();
+        for index in range(0, count) {
+            // Don't hold the borrow while the callback runs, as it may request or cancel frames.
+            let callback = self.animation_frame_list.borrow().as_slice()[index].ref1().clone();
+            match callback {
+                Some(callback) => {
+                    let _ = callback.Call_(*window, time, ReportExceptions);
+                }
+                None => {}
+            }
+        }
+
+        let state = {
+            let mut list = self.animation_frame_list.borrow_mut();
+            let remaining = list.slice_from(count).to_vec();
+            *list = remaining;
+            if list.is_empty() {
+                NoAnimationCallbacksPresent
+            } else {
+                AnimationCallbacksPresent
+            }
+        };
+        // Tell the compositor whether to keep ticking. This also acknowledges the tick: the
+        // compositor doesn't tick us again until it has heard from us.
+        send_animation_state(*window, state);
+
+        window.flush_layout();
+    }
+}
+
+/// Tells the constellation whether the given window has animation frame callbacks waiting for a
+/// tick.
+fn send_animation_state(window: JSRef<Window>, state: AnimationState) {
+    let page = window.page();
+    let ConstellationChan(ref chan) = page.constellation_chan;
+    chan.send(ChangeRunningAnimationsStateMsg(page.id, state));
 }
 
 #[deriving(PartialEq)]
@@ -409,7 +501,7 @@ impl Document {
                 }
             },
             last_modified: DOMRefCell::new(None),
-            url: url,
+            url: DOMRefCell::new(url),
             // http://dom.spec.whatwg.org/#concept-document-quirks
             quirks_mode: Cell::new(NoQuirks),
             // http://dom.spec.whatwg.org/#concept-document-encoding
@@ -425,6 +517,9 @@ impl Document {
             ready_state: Cell::new(ready_state),
             possibly_focused: Default::default(),
             focused: Default::default(),
+            ranges: WeakRangeVec::new(),
+            animation_frame_ident: Cell::new(0),
+            animation_frame_list: DOMRefCell::new(vec!()),
         }
     }
 
@@ -730,7 +825,7 @@ impl<'a> DocumentMethods for JSRef<'a, Document> {
 
     // http://dom.spec.whatwg.org/#dom-document-createrange
     fn CreateRange(self) -> Temporary<Range> {
-        Range::new(self)
+        Range::new_with_doc(self)
     }
 
     // http://dom.spec.whatwg.org/#dom-document-createtreewalker
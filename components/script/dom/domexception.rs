@@ -45,7 +45,9 @@ impl DOMErrorName {
             error::IndexSize => IndexSizeError,
             error::NotFound => NotFoundError,
             error::HierarchyRequest => HierarchyRequestError,
+            error::WrongDocument => WrongDocumentError,
             error::InvalidCharacter => InvalidCharacterError,
+            error::NoModificationAllowed => NoModificationAllowedError,
             error::NotSupported => NotSupportedError,
             error::InvalidState => InvalidStateError,
             error::Syntax => SyntaxError,
@@ -55,7 +57,9 @@ impl DOMErrorName {
             error::Network => NetworkError,
             error::Abort => AbortError,
             error::Timeout => TimeoutError,
+            error::InvalidNodeType => InvalidNodeTypeError,
             error::DataClone => DataCloneError,
+            error::Type(..) |
             error::FailureUnknown => panic!(),
         }
     }
@@ -4,14 +4,17 @@
 
 use dom::bindings::codegen::Bindings::DOMParserBinding;
 use dom::bindings::codegen::Bindings::DOMParserBinding::DOMParserMethods;
-use dom::bindings::codegen::Bindings::DOMParserBinding::SupportedTypeValues::{Text_html, Text_xml};
-use dom::bindings::error::{Fallible, FailureUnknown};
+use dom::bindings::codegen::Bindings::DOMParserBinding::SupportedTypeValues::Text_html;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::error::Fallible;
 use dom::bindings::global::GlobalRef;
 use dom::bindings::global;
 use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflector, Reflectable, reflect_dom_object};
-use dom::document::{Document, HTMLDocument, NonHTMLDocument, NotFromParser};
+use dom::document::{Document, DocumentHelpers, HTMLDocument, NonHTMLDocument, FromParser};
 use dom::window::Window;
+use parse::html::parse_html_string;
+use parse::xml::{parse_xml, replace_with_parser_error};
 use servo_util::str::DOMString;
 
 #[dom_struct]
@@ -39,23 +42,29 @@ impl DOMParser {
 }
 
 impl<'a> DOMParserMethods for JSRef<'a, DOMParser> {
+    // http://domparsing.spec.whatwg.org/#dom-domparser-parsefromstring
     fn ParseFromString(self,
-                       _s: DOMString,
+                       s: DOMString,
                        ty: DOMParserBinding::SupportedType)
                        -> Fallible<Temporary<Document>> {
         let window = self.window.root();
-        //FIXME: these should probably be FromParser when we actually parse the string (#3756).
+        let url = Some(window.Document().root().url().clone());
+        let content_type = DOMParserBinding::SupportedTypeValues::strings[ty as uint].to_string();
         match ty {
             Text_html => {
-                Ok(Document::new(*window, None, HTMLDocument, Some("text/html".to_string()),
-                                 NotFromParser))
-            }
-            Text_xml => {
-                Ok(Document::new(*window, None, NonHTMLDocument, Some("text/xml".to_string()),
-                                 NotFromParser))
+                let document = Document::new(*window, url, HTMLDocument, Some(content_type),
+                                             FromParser).root();
+                parse_html_string(*document, s);
+                Ok(Temporary::from_rooted(*document))
             }
             _ => {
-                Err(FailureUnknown)
+                let document = Document::new(*window, url, NonHTMLDocument, Some(content_type),
+                                             FromParser).root();
+                match parse_xml(*document, s) {
+                    Ok(()) => (),
+                    Err(error) => replace_with_parser_error(*document, error),
+                }
+                Ok(Temporary::from_rooted(*document))
             }
         }
     }
@@ -12,12 +12,14 @@ use dom::bindings::codegen::Bindings::AttrBinding::AttrMethods;
 use dom::bindings::codegen::Bindings::ElementBinding;
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use dom::bindings::codegen::Bindings::NamedNodeMapBinding::NamedNodeMapMethods;
-use dom::bindings::codegen::InheritTypes::{ElementDerived, HTMLInputElementDerived};
+use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use dom::bindings::codegen::InheritTypes::{ElementCast, ElementDerived, HTMLInputElementDerived};
 use dom::bindings::codegen::InheritTypes::{HTMLTableCellElementDerived, NodeCast};
 use dom::bindings::js::{MutNullableJS, JS, JSRef, Temporary, TemporaryPushable};
-use dom::bindings::js::{OptionalSettable, OptionalRootable, Root};
+use dom::bindings::js::{OptionalSettable, OptionalRootable, Root, RootedReference};
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::bindings::error::{ErrorResult, Fallible, NamespaceError, InvalidCharacter, Syntax};
+use dom::bindings::error::NoModificationAllowed;
 use dom::bindings::utils::{QName, Name, InvalidXMLName, xml_name_type};
 use dom::create::create_element;
 use dom::domrect::DOMRect;
@@ -27,21 +29,28 @@ use dom::domtokenlist::DOMTokenList;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlcollection::HTMLCollection;
 use dom::htmlinputelement::{HTMLInputElement, RawLayoutHTMLInputElementHelpers};
-use dom::htmlserializer::serialize;
+use dom::htmlserializer::{fragment_serialization_mode, serialize};
 use dom::htmltablecellelement::{HTMLTableCellElement, HTMLTableCellElementHelpers};
+use dom::mutationobserver::{MutationObserver, AttributeMutation};
 use dom::node::{ElementNodeTypeId, Node, NodeHelpers, NodeIterator, document_from_node};
+use dom::node::{DocumentNodeTypeId, DocumentFragmentNodeTypeId};
 use dom::node::{window_from_node, LayoutNodeHelpers};
 use dom::nodelist::NodeList;
 use dom::virtualmethods::{VirtualMethods, vtable_for};
 use devtools_traits::AttrInfo;
+use parse::{fragment_parsing_context, parse_fragment};
+use geom::point::Point2D;
+use script_traits::{ScriptControlChan, ScrollPositionEvent, SendEventMsg};
+use servo_msg::compositor_msg::{LayerId, ScriptListener};
 use style::{IntegerAttribute, LengthAttribute, SizeIntegerAttribute, WidthLengthAttribute};
+use style::{ColSpanIntegerAttribute, RowSpanIntegerAttribute};
 use style::{matches, parse_selector_list_from_str};
 use style;
 use servo_util::namespace;
 use servo_util::str::{DOMString, LengthOrPercentageOrAuto};
 
 use std::ascii::AsciiExt;
-use std::cell::{Ref, RefMut};
+use std::cell::{Cell, Ref, RefMut};
 use std::default::Default;
 use std::mem;
 use string_cache::{Atom, Namespace, QualName};
@@ -57,6 +66,10 @@ pub struct Element {
     style_attribute: DOMRefCell<Option<style::PropertyDeclarationBlock>>,
     attr_list: MutNullableJS<NamedNodeMap>,
     class_list: MutNullableJS<DOMTokenList>,
+    /// How far the contents of this element are scrolled, in CSS pixels.
+    scroll_position: Cell<Point2D<f32>>,
+    /// Whether script scrolled this element and the `scroll` event for it has not been fired yet.
+    scroll_event_pending: Cell<bool>,
 }
 
 impl ElementDerived for EventTarget {
@@ -175,6 +188,8 @@ impl Element {
             attr_list: Default::default(),
             class_list: Default::default(),
             style_attribute: DOMRefCell::new(None),
+            scroll_position: Cell::new(Point2D(0.0, 0.0)),
+            scroll_event_pending: Cell::new(false),
         }
     }
 
@@ -307,6 +322,20 @@ impl RawLayoutElementHelpers for Element {
                 let this: &HTMLInputElement = mem::transmute(self);
                 Some(this.get_size_for_layout() as i32)
             }
+            ColSpanIntegerAttribute => {
+                if !self.is_htmltablecellelement() {
+                    panic!("I'm not a table cell!")
+                }
+                let this: &HTMLTableCellElement = mem::transmute(self);
+                this.get_colspan().map(|colspan| colspan as i32)
+            }
+            RowSpanIntegerAttribute => {
+                if !self.is_htmltablecellelement() {
+                    panic!("I'm not a table cell!")
+                }
+                let this: &HTMLTableCellElement = mem::transmute(self);
+                this.get_rowspan().map(|rowspan| rowspan as i32)
+            }
         }
     }
 
@@ -356,6 +385,8 @@ pub trait ElementHelpers<'a> {
     fn style_attribute(self) -> &'a DOMRefCell<Option<style::PropertyDeclarationBlock>>;
     fn summarize(self) -> Vec<AttrInfo>;
     fn is_void(self) -> bool;
+    fn scroll_to(self, position: Point2D<f32>);
+    fn update_scroll_position(self, position: Point2D<f32>) -> bool;
 }
 
 impl<'a> ElementHelpers<'a> for JSRef<'a, Element> {
@@ -413,6 +444,48 @@ impl<'a> ElementHelpers<'a> for JSRef<'a, Element> {
             _ => false
         }
     }
+
+    /// Scrolls the contents of this element to the given position, clamped to the scroll range
+    /// that layout reports, and asks the compositor to follow. Nothing happens unless the element
+    /// is a scroll container. The `scroll` event is fired from a task, like the one for a scroll
+    /// that the compositor reports; scrolling again before it runs doesn't queue another.
+    /// http://dev.w3.org/csswg/cssom-view/#scroll-an-element
+    fn scroll_to(self, position: Point2D<f32>) {
+        let node: JSRef<Node> = NodeCast::from_ref(self);
+        let window = window_from_node(self).root();
+        let page = window.page();
+        let range = page.scroll_range_query(node.to_trusted_node_address());
+        let position = Point2D(position.x.max(0.0).min(range.width.to_subpx() as f32),
+                               position.y.max(0.0).min(range.height.to_subpx() as f32));
+        if self.scroll_position.get() == position {
+            return
+        }
+        self.scroll_position.set(position);
+
+        let node_address = self.reflector().get_jsobject() as uint;
+        let layer_id = LayerId::for_scroll_root(node_address);
+        window.compositor().set_scroll_position(page.id, layer_id, position);
+        if !self.scroll_event_pending.get() {
+            self.scroll_event_pending.set(true);
+            let ScriptControlChan(ref chan) = *window.control_chan();
+            chan.send(SendEventMsg(page.id, ScrollPositionEvent(layer_id, position)));
+        }
+    }
+
+    /// Records the position that the contents of this element were scrolled to. Returns true if
+    /// a `scroll` event is due, because the position changed or script scrolled the element. In
+    /// the latter case the position script scrolled to stands.
+    fn update_scroll_position(self, position: Point2D<f32>) -> bool {
+        if self.scroll_event_pending.get() {
+            self.scroll_event_pending.set(false);
+            return true
+        }
+        if self.scroll_position.get() == position {
+            return false
+        }
+        self.scroll_position.set(position);
+        true
+    }
 }
 
 pub trait AttributeHandlers {
@@ -544,8 +617,12 @@ impl<'a> AttributeHandlers for JSRef<'a, Element> {
                 let node: JSRef<Node> = NodeCast::from_ref(self);
                 node.wait_until_safe_to_modify_dom();
 
+                let attr = (*self.attrs.borrow())[idx].root();
+                MutationObserver::queue_a_mutation_record(
+                    node, AttributeMutation(attr.local_name(), attr.namespace()),
+                    Some(attr.value().as_slice()));
+
                 if namespace == ns!("") {
-                    let attr = (*self.attrs.borrow())[idx].root();
                     vtable_for(&NodeCast::from_ref(self)).before_remove_attr(*attr);
                 }
 
@@ -605,7 +682,7 @@ impl<'a> AttributeHandlers for JSRef<'a, Element> {
         let base = doc.url();
         // https://html.spec.whatwg.org/multipage/infrastructure.html#reflect
         // XXXManishearth this doesn't handle `javascript:` urls properly
-        match UrlParser::new().base_url(base).parse(url.as_slice()) {
+        match UrlParser::new().base_url(&*base).parse(url.as_slice()) {
             Ok(parsed) => parsed.serialize(),
             Err(_) => "".to_string()
         }
@@ -925,13 +1002,128 @@ impl<'a> ElementMethods for JSRef<'a, Element> {
             rect.origin.x + rect.size.width)
     }
 
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrollto
+    fn ScrollTo(self, x: f64, y: f64) {
+        self.scroll_to(Point2D(x as f32, y as f32))
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrolltop
+    fn ScrollTop(self) -> f64 {
+        self.scroll_position.get().y as f64
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrolltop
+    fn SetScrollTop(self, y: f64) {
+        let x = self.scroll_position.get().x;
+        self.scroll_to(Point2D(x, y as f32))
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrollleft
+    fn ScrollLeft(self) -> f64 {
+        self.scroll_position.get().x as f64
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrollleft
+    fn SetScrollLeft(self, x: f64) {
+        let y = self.scroll_position.get().y;
+        self.scroll_to(Point2D(x as f32, y))
+    }
+
+    // http://domparsing.spec.whatwg.org/#dom-element-innerhtml
     fn GetInnerHTML(self) -> Fallible<DOMString> {
-        //XXX TODO: XML case
-        Ok(serialize(&mut NodeIterator::new(NodeCast::from_ref(self), false, false)))
+        let node: JSRef<Node> = NodeCast::from_ref(self);
+        Ok(serialize(&mut NodeIterator::new(node, false, false),
+                     fragment_serialization_mode(node)))
     }
 
+    // http://domparsing.spec.whatwg.org/#dom-element-innerhtml
+    fn SetInnerHTML(self, value: DOMString) -> ErrorResult {
+        // Step 1.
+        let fragment = try!(parse_fragment(self, value)).root();
+
+        // Step 2.
+        // FIXME: Replace the contents of template elements instead.
+
+        // Step 3.
+        Node::replace_all(Some(NodeCast::from_ref(*fragment)), NodeCast::from_ref(self));
+        Ok(())
+    }
+
+    // http://domparsing.spec.whatwg.org/#dom-element-outerhtml
     fn GetOuterHTML(self) -> Fallible<DOMString> {
-        Ok(serialize(&mut NodeIterator::new(NodeCast::from_ref(self), true, false)))
+        let node: JSRef<Node> = NodeCast::from_ref(self);
+        Ok(serialize(&mut NodeIterator::new(node, true, false),
+                     fragment_serialization_mode(node)))
+    }
+
+    // http://domparsing.spec.whatwg.org/#dom-element-outerhtml
+    fn SetOuterHTML(self, value: DOMString) -> ErrorResult {
+        let node: JSRef<Node> = NodeCast::from_ref(self);
+
+        // Step 1-2.
+        let parent = match node.parent_node() {
+            Some(parent) => parent.root(),
+            None => return Ok(()),
+        };
+
+        let context = match parent.type_id() {
+            // Step 3.
+            DocumentNodeTypeId => return Err(NoModificationAllowed),
+            // Step 4.
+            DocumentFragmentNodeTypeId => fragment_parsing_context(*parent),
+            _ => Temporary::from_rooted(ElementCast::to_ref(*parent).unwrap()),
+        }.root();
+
+        // Step 5.
+        let fragment = try!(parse_fragment(*context, value)).root();
+
+        // Step 6.
+        try!(parent.ReplaceChild(NodeCast::from_ref(*fragment), node));
+        Ok(())
+    }
+
+    // http://domparsing.spec.whatwg.org/#dom-element-insertadjacenthtml
+    fn InsertAdjacentHTML(self, position: DOMString, text: DOMString) -> ErrorResult {
+        let node: JSRef<Node> = NodeCast::from_ref(self);
+        let position = position.as_slice().to_ascii_lower();
+
+        // Step 1.
+        let context = match position.as_slice() {
+            "beforebegin" | "afterend" => {
+                match node.parent_node().root() {
+                    Some(ref parent) if !parent.is_document() => fragment_parsing_context(**parent),
+                    _ => return Err(NoModificationAllowed),
+                }
+            }
+            "afterbegin" | "beforeend" => fragment_parsing_context(node),
+            _ => return Err(Syntax),
+        }.root();
+
+        // Step 2.
+        let fragment = try!(parse_fragment(*context, text)).root();
+        let fragment: JSRef<Node> = NodeCast::from_ref(*fragment);
+
+        // Step 3.
+        match position.as_slice() {
+            "beforebegin" => {
+                let parent = node.parent_node().unwrap().root();
+                try!(parent.InsertBefore(fragment, Some(node)));
+            }
+            "afterbegin" => {
+                let first_child = node.first_child().root();
+                try!(node.InsertBefore(fragment, first_child.root_ref()));
+            }
+            "beforeend" => {
+                try!(node.AppendChild(fragment));
+            }
+            "afterend" => {
+                let parent = node.parent_node().unwrap().root();
+                let next_sibling = node.next_sibling().root();
+                try!(parent.InsertBefore(fragment, next_sibling.root_ref()));
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
     }
 
     // http://dom.spec.whatwg.org/#dom-parentnode-children
@@ -27,11 +27,14 @@ pub enum EventPhase {
 #[deriving(PartialEq)]
 #[jstraceable]
 pub enum EventTypeId {
+    CloseEventTypeId,
     CustomEventTypeId,
+    HashChangeEventTypeId,
     HTMLEventTypeId,
     KeyboardEventTypeId,
     MessageEventTypeId,
     MouseEventTypeId,
+    PopStateEventTypeId,
     ProgressEventTypeId,
     StorageEventTypeId,
     UIEventTypeId
@@ -37,6 +37,9 @@ pub enum ListenerPhase {
 #[deriving(PartialEq)]
 #[jstraceable]
 pub enum EventTargetTypeId {
+    DissimilarOriginWindowTypeId,
+    FileReaderTypeId,
+    MessagePortTypeId,
     NodeTargetTypeId(NodeTypeId),
     WebSocketTypeId,
     WindowTypeId,
@@ -0,0 +1,340 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::codegen::Bindings::FileReaderBinding;
+use dom::bindings::codegen::Bindings::FileReaderBinding::FileReaderMethods;
+use dom::bindings::codegen::InheritTypes::{EventCast, EventTargetCast};
+use dom::bindings::conversions::ToJSValConvertible;
+use dom::bindings::error::{ErrorResult, Fallible, InvalidState, report_pending_exception};
+use dom::bindings::global::{GlobalRef, GlobalField};
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::blob::Blob;
+use dom::event::Event;
+use dom::eventtarget::{EventTarget, EventTargetHelpers, FileReaderTypeId};
+use dom::progressevent::ProgressEvent;
+use script_task::{ScriptChan, FileReaderLoadMsg};
+use servo_util::str::DOMString;
+
+use encoding::all::{UTF_8, UTF_16BE, UTF_16LE};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::types::{DecodeReplace, EncodingRef};
+
+use js::jsapi::{JSContext, JS_AddObjectRoot, JS_RemoveObjectRoot};
+use js::jsfriendapi::bindgen::{JS_NewArrayBuffer, JS_GetArrayBufferData};
+use js::jsval::{JSVal, NullValue};
+
+use libc::c_void;
+use serialize::base64::{ToBase64, STANDARD};
+use std::ascii::AsciiExt;
+use std::cell::Cell;
+use std::ptr;
+
+pub struct TrustedFileReaderAddress(pub *const c_void);
+no_jsmanaged_fields!(TrustedFileReaderAddress)
+
+#[deriving(PartialEq)]
+#[jstraceable]
+enum FileReaderReadyState {
+    Empty = 0,
+    Loading = 1,
+    Done = 2,
+}
+
+#[jstraceable]
+enum FileReadType {
+    ReadAsArrayBuffer,
+    ReadAsText(Option<DOMString>),
+    ReadAsDataURL,
+}
+
+/// A snapshot of the blob being read, taken when the read starts.
+#[jstraceable]
+struct PendingRead {
+    read_type: FileReadType,
+    bytes: Vec<u8>,
+    content_type: DOMString,
+}
+
+#[dom_struct]
+pub struct FileReader {
+    eventtarget: EventTarget,
+    refcount: Cell<uint>,
+    global: GlobalField,
+    ready_state: Cell<FileReaderReadyState>,
+    result: Cell<JSVal>,
+    /// The number of reads started so far, which lets the task of a read that was aborted or
+    /// superseded tell that it is stale.
+    generation: Cell<uint>,
+    pending_read: DOMRefCell<Option<PendingRead>>,
+}
+
+impl FileReader {
+    fn new_inherited(global: &GlobalRef) -> FileReader {
+        FileReader {
+            eventtarget: EventTarget::new_inherited(FileReaderTypeId),
+            refcount: Cell::new(0),
+            global: GlobalField::from_rooted(global),
+            ready_state: Cell::new(Empty),
+            result: Cell::new(NullValue()),
+            generation: Cell::new(0),
+            pending_read: DOMRefCell::new(None),
+        }
+    }
+
+    pub fn new(global: &GlobalRef) -> Temporary<FileReader> {
+        reflect_dom_object(box FileReader::new_inherited(global),
+                           *global,
+                           FileReaderBinding::Wrap)
+    }
+
+    pub fn Constructor(global: &GlobalRef) -> Fallible<Temporary<FileReader>> {
+        Ok(FileReader::new(global))
+    }
+
+    pub fn handle_load(address: TrustedFileReaderAddress, generation: uint) {
+        let reader = unsafe { JS::from_trusted_file_reader_address(address).root() };
+        reader.process_read(generation);
+        reader.release();
+    }
+}
+
+/// Returns the `charset` parameter of a MIME type, if any.
+fn charset_from_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).filter_map(|parameter| {
+        let parts: Vec<&str> = parameter.splitn(1, '=').collect();
+        if parts.len() == 2 && parts[0].trim().eq_ignore_ascii_case("charset") {
+            Some(parts[1].trim().trim_chars('"'))
+        } else {
+            None
+        }
+    }).next()
+}
+
+// http://dev.w3.org/2006/webapi/FileAPI/#enctype
+fn decode_text(bytes: &[u8], label: Option<DOMString>, content_type: &str) -> DOMString {
+    let encoding = label.and_then(|label| encoding_from_whatwg_label(label.as_slice()))
+        .or_else(|| charset_from_type(content_type).and_then(encoding_from_whatwg_label))
+        .unwrap_or(UTF_8 as EncodingRef);
+
+    // A byte order mark overrides the encoding.
+    let (encoding, bytes) = if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        (UTF_8 as EncodingRef, bytes.slice_from(3))
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        (UTF_16BE as EncodingRef, bytes.slice_from(2))
+    } else if bytes.starts_with(&[0xFF, 0xFE]) {
+        (UTF_16LE as EncodingRef, bytes.slice_from(2))
+    } else {
+        (encoding, bytes)
+    };
+    encoding.decode(bytes, DecodeReplace).unwrap()
+}
+
+// http://dev.w3.org/2006/webapi/FileAPI/#dfn-readAsDataURL
+fn data_url(bytes: &[u8], content_type: &str) -> DOMString {
+    format!("data:{};base64,{}", content_type, bytes.to_base64(STANDARD))
+}
+
+trait PrivateFileReaderHelpers {
+    fn addref(self) -> TrustedFileReaderAddress;
+    fn release(self);
+    fn read(self, blob: JSRef<Blob>, read_type: FileReadType) -> ErrorResult;
+    fn process_read(self, generation: uint);
+    fn read_result(self, read: PendingRead) -> Result<JSVal, ()>;
+    fn dispatch_progress_event(self, type_: &str, loaded: u64, total: u64);
+}
+
+impl<'a> PrivateFileReaderHelpers for JSRef<'a, FileReader> {
+    // Creates a trusted address to the object, and roots it. Always pair this with a release()
+    fn addref(self) -> TrustedFileReaderAddress {
+        let refcount = self.refcount.get();
+        if refcount == 0 {
+            let cx = self.global.root().root_ref().get_cx();
+            unsafe {
+                JS_AddObjectRoot(cx, self.reflector().rootable());
+            }
+        }
+        self.refcount.set(refcount + 1);
+        TrustedFileReaderAddress(self.deref() as *const FileReader as *const c_void)
+    }
+
+    fn release(self) {
+        let refcount = self.refcount.get();
+        assert!(refcount > 0)
+        self.refcount.set(refcount - 1);
+        if refcount == 1 {
+            let cx = self.global.root().root_ref().get_cx();
+            unsafe {
+                JS_RemoveObjectRoot(cx, self.reflector().rootable());
+            }
+        }
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#readAsArrayBuffer
+    fn read(self, blob: JSRef<Blob>, read_type: FileReadType) -> ErrorResult {
+        // Step 1.
+        if self.ready_state.get() == Loading {
+            return Err(InvalidState);
+        }
+
+        // Step 3.
+        self.ready_state.set(Loading);
+        self.result.set(NullValue());
+
+        // Steps 4-5: the blob is read asynchronously, from a snapshot of its contents.
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+        *self.pending_read.borrow_mut() = Some(PendingRead {
+            read_type: read_type,
+            bytes: blob.bytes().to_vec(),
+            content_type: blob.content_type().clone(),
+        });
+        let address = self.addref();
+        let global = self.global.root();
+        let global = global.root_ref();
+        let ScriptChan(ref chan) = *global.script_chan();
+        chan.send(FileReaderLoadMsg(address, generation));
+        Ok(())
+    }
+
+    fn process_read(self, generation: uint) {
+        // Each event handler may abort this read or start another one.
+        if self.generation.get() != generation {
+            return;
+        }
+        let total = self.pending_read.borrow().as_ref().unwrap().bytes.len() as u64;
+
+        self.dispatch_progress_event("loadstart", 0, total);
+        if self.generation.get() != generation {
+            return;
+        }
+
+        // The whole blob is available at once, so a single progress event is fired.
+        self.dispatch_progress_event("progress", total, total);
+        if self.generation.get() != generation {
+            return;
+        }
+
+        let read = self.pending_read.borrow_mut().take().unwrap();
+        let result = match self.read_result(read) {
+            Ok(result) => result,
+            Err(()) => {
+                // The read failed, so it ends with an error event instead.
+                self.ready_state.set(Done);
+                self.dispatch_progress_event("error", 0, total);
+                if self.generation.get() == generation {
+                    self.dispatch_progress_event("loadend", 0, total);
+                }
+                return;
+            }
+        };
+        self.result.set(result);
+        self.ready_state.set(Done);
+        self.dispatch_progress_event("load", total, total);
+
+        // No loadend event is fired if the load handler started a new read.
+        if self.generation.get() != generation {
+            return;
+        }
+        self.dispatch_progress_event("loadend", total, total);
+    }
+
+    /// Returns the result of the given read, or reports the exception thrown while creating it.
+    fn read_result(self, read: PendingRead) -> Result<JSVal, ()> {
+        let global = self.global.root();
+        let cx = global.root_ref().get_cx();
+        match read.read_type {
+            ReadAsArrayBuffer => unsafe {
+                let buffer = JS_NewArrayBuffer(cx, read.bytes.len() as u32);
+                if buffer.is_null() {
+                    // The engine failed to allocate the buffer and left an exception pending.
+                    report_pending_exception(cx, self.reflector().get_jsobject());
+                    return Err(());
+                }
+                let pointer = JS_GetArrayBufferData(buffer, cx);
+                ptr::copy_nonoverlapping_memory(pointer, read.bytes.as_ptr(), read.bytes.len());
+                Ok(buffer.to_jsval(cx))
+            },
+            ReadAsText(label) => {
+                Ok(decode_text(read.bytes.as_slice(), label,
+                               read.content_type.as_slice()).to_jsval(cx))
+            }
+            ReadAsDataURL => {
+                Ok(data_url(read.bytes.as_slice(), read.content_type.as_slice()).to_jsval(cx))
+            }
+        }
+    }
+
+    fn dispatch_progress_event(self, type_: &str, loaded: u64, total: u64) {
+        let global = self.global.root();
+        let progressevent = ProgressEvent::new(global.root_ref(),
+                                               type_.to_string(), false, false,
+                                               true, loaded, total).root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        let event: JSRef<Event> = EventCast::from_ref(*progressevent);
+        target.dispatch_event_with_target(None, event).ok();
+    }
+}
+
+impl<'a> FileReaderMethods for JSRef<'a, FileReader> {
+    fn ReadAsArrayBuffer(self, blob: JSRef<Blob>) -> ErrorResult {
+        self.read(blob, ReadAsArrayBuffer)
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#readAsDataText
+    fn ReadAsText(self, blob: JSRef<Blob>, label: Option<DOMString>) -> ErrorResult {
+        self.read(blob, ReadAsText(label))
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#readAsDataURL
+    fn ReadAsDataURL(self, blob: JSRef<Blob>) -> ErrorResult {
+        self.read(blob, ReadAsDataURL)
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#dfn-abort
+    fn Abort(self) {
+        // Step 1.
+        if self.ready_state.get() != Loading {
+            self.result.set(NullValue());
+            return;
+        }
+
+        // Steps 2-3.
+        self.ready_state.set(Done);
+        self.result.set(NullValue());
+
+        // Step 4: the queued task of the read finds it has been superseded.
+        self.generation.set(self.generation.get() + 1);
+        *self.pending_read.borrow_mut() = None;
+
+        // Steps 5-6.
+        self.dispatch_progress_event("abort", 0, 0);
+        if self.ready_state.get() != Loading {
+            self.dispatch_progress_event("loadend", 0, 0);
+        }
+    }
+
+    fn ReadyState(self) -> u16 {
+        self.ready_state.get() as u16
+    }
+
+    fn Result(self, _cx: *mut JSContext) -> JSVal {
+        self.result.get()
+    }
+
+    event_handler!(loadstart, GetOnloadstart, SetOnloadstart)
+    event_handler!(progress, GetOnprogress, SetOnprogress)
+    event_handler!(load, GetOnload, SetOnload)
+    event_handler!(abort, GetOnabort, SetOnabort)
+    event_handler!(error, GetOnerror, SetOnerror)
+    event_handler!(loadend, GetOnloadend, SetOnloadend)
+}
+
+impl Reflectable for FileReader {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.eventtarget.reflector()
+    }
+}
@@ -5,7 +5,7 @@
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::FormDataBinding;
 use dom::bindings::codegen::Bindings::FormDataBinding::FormDataMethods;
-use dom::bindings::codegen::InheritTypes::FileCast;
+use dom::bindings::codegen::InheritTypes::{BlobCast, FileCast};
 use dom::bindings::codegen::UnionTypes::FileOrString::{FileOrString, eFile, eString};
 use dom::bindings::error::{Fallible};
 use dom::bindings::global::{GlobalRef, GlobalField};
@@ -112,6 +112,53 @@ impl Reflectable for FormData {
     }
 }
 
+/// Returns `name` escaped for use in a quoted `Content-Disposition` parameter.
+fn escape_disposition_parameter(name: &str) -> String {
+    name.replace("\r", "%0D").replace("\n", "%0A").replace("\"", "%22")
+}
+
+pub trait FormDataHelpers {
+    fn encode_multipart(self, boundary: &str) -> Vec<u8>;
+}
+
+impl<'a> FormDataHelpers for JSRef<'a, FormData> {
+    /// Serializes the entries as a `multipart/form-data` body delimited by `boundary`.
+    /// http://www.whatwg.org/html/#multipart/form-data-encoding-algorithm
+    fn encode_multipart(self, boundary: &str) -> Vec<u8> {
+        let mut body = vec!();
+        // FIXME: entries with different names are not kept in the order they were appended in.
+        for (name, data) in self.data.borrow().iter() {
+            let name = escape_disposition_parameter(name.as_slice());
+            for datum in data.iter() {
+                body.push_all(format!("--{}\r\n", boundary).as_bytes());
+                let disposition = format!("Content-Disposition: form-data; name=\"{}\"", name);
+                match *datum {
+                    StringData(ref value) => {
+                        body.push_all(format!("{}\r\n\r\n", disposition).as_bytes());
+                        body.push_all(value.as_bytes());
+                    }
+                    FileData(ref file) => {
+                        let file = file.root();
+                        let filename = escape_disposition_parameter(file.name().as_slice());
+                        let blob: JSRef<Blob> = BlobCast::from_ref(*file);
+                        let content_type = if blob.content_type().is_empty() {
+                            "application/octet-stream"
+                        } else {
+                            blob.content_type().as_slice()
+                        };
+                        body.push_all(format!("{}; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                                              disposition, filename, content_type).as_bytes());
+                        body.push_all(blob.bytes());
+                    }
+                }
+                body.push_all(b"\r\n");
+            }
+        }
+        body.push_all(format!("--{}--\r\n", boundary).as_bytes());
+        body
+    }
+}
+
 trait PrivateFormDataHelpers{
   fn get_file_from_blob(&self, value: JSRef<Blob>, filename: Option<DOMString>) -> Temporary<File>;
 }
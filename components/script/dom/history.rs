@@ -0,0 +1,204 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::HistoryBinding;
+use dom::bindings::codegen::Bindings::HistoryBinding::HistoryMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::error::{DataClone, ErrorResult, Security};
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::document::DocumentHelpers;
+use dom::window::Window;
+use script_task::{ScriptChan, TriggerLoadMsg};
+
+use servo_msg::constellation_msg::{ConstellationChan, HistoryState, LoadData};
+use servo_msg::constellation_msg::PushHistoryStateMsg;
+use servo_msg::constellation_msg::{ReplaceHistoryStateMsg, TraverseHistoryMsg};
+use servo_util::str::DOMString;
+
+use js::glue::JS_STRUCTURED_CLONE_VERSION;
+use js::jsapi::{JSContext, JS_ClearPendingException};
+use js::jsapi::{JS_ReadStructuredClone, JS_WriteStructuredClone};
+use js::jsval::{JSVal, NullValue, UndefinedValue};
+use url::UrlParser;
+
+use libc::size_t;
+use std::cell::Cell;
+use std::ptr;
+use std::slice::raw::buf_as_slice;
+
+#[dom_struct]
+pub struct History {
+    reflector_: Reflector,
+    window: JS<Window>,
+    /// The state object of the current session history entry.
+    state: Cell<JSVal>,
+}
+
+impl History {
+    fn new_inherited(window: JSRef<Window>) -> History {
+        History {
+            reflector_: Reflector::new(),
+            window: JS::from_rooted(window),
+            state: Cell::new(NullValue()),
+        }
+    }
+
+    pub fn new(window: JSRef<Window>) -> Temporary<History> {
+        reflect_dom_object(box History::new_inherited(window),
+                           global::Window(window),
+                           HistoryBinding::Wrap)
+    }
+}
+
+pub trait HistoryHelpers {
+    fn set_state(self, data: Option<Vec<u64>>) -> ErrorResult;
+}
+
+impl<'a> HistoryHelpers for JSRef<'a, History> {
+    /// Sets the state object of the current entry from its serialized form. The state object
+    /// is null if it can't be deserialized.
+    fn set_state(self, data: Option<Vec<u64>>) -> ErrorResult {
+        let state = match data {
+            Some(data) => {
+                let window = self.window.root();
+                let cx = window.get_cx();
+                let mut state = UndefinedValue();
+                let result = unsafe {
+                    JS_ReadStructuredClone(cx, data.as_ptr(), (data.len() * 8) as size_t,
+                                           JS_STRUCTURED_CLONE_VERSION, &mut state,
+                                           ptr::null(), ptr::null_mut())
+                };
+                if result == 0 {
+                    unsafe { JS_ClearPendingException(cx); }
+                    self.state.set(NullValue());
+                    return Err(DataClone);
+                }
+                state
+            }
+            None => NullValue(),
+        };
+        self.state.set(state);
+        Ok(())
+    }
+}
+
+trait PrivateHistoryHelpers {
+    fn add_state(self, cx: *mut JSContext, data: JSVal, url: Option<DOMString>,
+                 replace: bool) -> ErrorResult;
+}
+
+impl<'a> PrivateHistoryHelpers for JSRef<'a, History> {
+    // http://www.whatwg.org/html/#dom-history-pushstate
+    fn add_state(self, cx: *mut JSContext, data: JSVal, url: Option<DOMString>,
+                 replace: bool) -> ErrorResult {
+        let window = self.window.root();
+        let document = window.Document().root();
+
+        // Step 3.
+        let mut buffer = ptr::null_mut();
+        let mut nbytes = 0;
+        let result = unsafe {
+            JS_WriteStructuredClone(cx, data, &mut buffer, &mut nbytes,
+                                    ptr::null(), ptr::null_mut())
+        };
+        if result == 0 {
+            unsafe { JS_ClearPendingException(cx); }
+            return Err(DataClone);
+        }
+        let data = unsafe {
+            buf_as_slice(buffer as *const u64, nbytes as uint / 8, |data| data.to_vec())
+        };
+
+        // Step 4.
+        let document_url = document.url().clone();
+        let new_url = match url {
+            Some(url) => {
+                let new_url = match UrlParser::new().base_url(&document_url)
+                                                    .parse(url.as_slice()) {
+                    Ok(new_url) => new_url,
+                    Err(_) => return Err(Security),
+                };
+                // Only the path, query and fragment of the URL may change.
+                if new_url.scheme != document_url.scheme ||
+                   new_url.host() != document_url.host() ||
+                   new_url.port() != document_url.port() {
+                    return Err(Security);
+                }
+                new_url
+            }
+            None => document_url,
+        };
+
+        // Step 5-6.
+        let page = window.page();
+        let state = HistoryState {
+            url: new_url.clone(),
+            data: Some(data.clone()),
+        };
+        let ConstellationChan(ref chan) = page.constellation_chan;
+        if replace {
+            chan.send(ReplaceHistoryStateMsg(page.id, state));
+        } else {
+            chan.send(PushHistoryStateMsg(page.id, state));
+            page.add_session_history_entry();
+        }
+
+        // Step 7-9.
+        document.set_url(new_url.clone());
+        page.set_url(new_url);
+        self.set_state(Some(data))
+    }
+}
+
+impl<'a> HistoryMethods for JSRef<'a, History> {
+    fn Length(self) -> u32 {
+        let window = self.window.root();
+        let (_, length) = window.page().session_history.get();
+        length as u32
+    }
+
+    fn State(self, _cx: *mut JSContext) -> JSVal {
+        self.state.get()
+    }
+
+    // http://www.whatwg.org/html/#dom-history-go
+    fn Go(self, delta: i32) {
+        let window = self.window.root();
+        let page = window.page();
+        if delta == 0 {
+            // Reload the document.
+            let ScriptChan(ref chan) = *window.script_chan();
+            chan.send(TriggerLoadMsg(page.id, LoadData::new(page.get_url())));
+            return;
+        }
+        let ConstellationChan(ref chan) = page.constellation_chan;
+        chan.send(TraverseHistoryMsg(delta as int));
+    }
+
+    fn Back(self) {
+        self.Go(-1);
+    }
+
+    fn Forward(self) {
+        self.Go(1);
+    }
+
+    fn PushState(self, cx: *mut JSContext, data: JSVal, _title: DOMString,
+                 url: Option<DOMString>) -> ErrorResult {
+        self.add_state(cx, data, url, false)
+    }
+
+    fn ReplaceState(self, cx: *mut JSContext, data: JSVal, _title: DOMString,
+                    url: Option<DOMString>) -> ErrorResult {
+        self.add_state(cx, data, url, true)
+    }
+}
+
+impl Reflectable for History {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
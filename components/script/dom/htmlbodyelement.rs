@@ -47,6 +47,26 @@ impl HTMLBodyElement {
 }
 
 impl<'a> HTMLBodyElementMethods for JSRef<'a, HTMLBodyElement> {
+    fn GetOnhashchange(self) -> Option<EventHandlerNonNull> {
+        let win = window_from_node(self).root();
+        win.GetOnhashchange()
+    }
+
+    fn SetOnhashchange(self, listener: Option<EventHandlerNonNull>) {
+        let win = window_from_node(self).root();
+        win.SetOnhashchange(listener)
+    }
+
+    fn GetOnpopstate(self) -> Option<EventHandlerNonNull> {
+        let win = window_from_node(self).root();
+        win.GetOnpopstate()
+    }
+
+    fn SetOnpopstate(self, listener: Option<EventHandlerNonNull>) {
+        let win = window_from_node(self).root();
+        win.SetOnpopstate(listener)
+    }
+
     fn GetOnunload(self) -> Option<EventHandlerNonNull> {
         let win = window_from_node(self).root();
         win.GetOnunload()
@@ -21,7 +21,11 @@ use dom::virtualmethods::VirtualMethods;
 
 use servo_util::str::{DOMString, parse_unsigned_integer};
 
+use canvas::png_encoder::encode_png;
+use geom::point::Point2D;
+use geom::rect::Rect;
 use geom::size::Size2D;
+use serialize::base64::{ToBase64, STANDARD};
 
 use std::cell::Cell;
 use std::default::Default;
@@ -60,6 +64,22 @@ impl HTMLCanvasElement {
     }
 }
 
+pub trait HTMLCanvasElementHelpers {
+    fn get_size(self) -> Size2D<i32>;
+    /// Returns the 2D context of the canvas, if `getContext()` has created it.
+    fn get_context(self) -> Option<Temporary<CanvasRenderingContext2D>>;
+}
+
+impl<'a> HTMLCanvasElementHelpers for JSRef<'a, HTMLCanvasElement> {
+    fn get_size(self) -> Size2D<i32> {
+        Size2D(self.width.get() as i32, self.height.get() as i32)
+    }
+
+    fn get_context(self) -> Option<Temporary<CanvasRenderingContext2D>> {
+        self.context.get()
+    }
+}
+
 impl<'a> HTMLCanvasElementMethods for JSRef<'a, HTMLCanvasElement> {
     fn Width(self) -> u32 {
         self.width.get()
@@ -92,6 +112,23 @@ impl<'a> HTMLCanvasElementMethods for JSRef<'a, HTMLCanvasElement> {
         }
         self.context.get()
      }
+
+    // http://www.whatwg.org/html/#dom-canvas-todataurl
+    fn ToDataURL(self, _type: Option<DOMString>) -> DOMString {
+        // FIXME: Only PNG images are supported, whatever the requested type is.
+        let size = self.get_size();
+        if size.width == 0 || size.height == 0 {
+            return "data:,".to_string()
+        }
+
+        let pixels = match self.context.get() {
+            Some(context) => context.root().get_image_data(Rect(Point2D(0, 0), size)),
+            // A canvas nobody has drawn on is transparent.
+            None => Vec::from_elem((size.width * size.height * 4) as uint, 0u8),
+        };
+        let png = encode_png(size, pixels.as_slice());
+        format!("data:image/png;base64,{}", png.as_slice().to_base64(STANDARD))
+    }
 }
 
 impl<'a> VirtualMethods for JSRef<'a, HTMLCanvasElement> {
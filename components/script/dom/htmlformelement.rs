@@ -136,7 +136,7 @@ impl<'a> HTMLFormElementHelpers for JSRef<'a, HTMLFormElement> {
         // Step 1
         let doc = document_from_node(self).root();
         let win = window_from_node(self).root();
-        let base = doc.url();
+        let base = doc.url().clone();
         // TODO: Handle browsing contexts
         // TODO: Handle validation
         let event = Event::new(Window(*win),
@@ -156,7 +156,7 @@ impl<'a> HTMLFormElementHelpers for JSRef<'a, HTMLFormElement> {
         }
         // TODO: Resolve the url relative to the submitter element
         // Step 10-15
-        let action_components = UrlParser::new().base_url(base).parse(action.as_slice()).unwrap_or(base.clone());
+        let action_components = UrlParser::new().base_url(&base).parse(action.as_slice()).unwrap_or(base.clone());
         let _action = action_components.serialize();
         let scheme = action_components.scheme.clone();
         let enctype = submitter.enctype();
@@ -81,6 +81,17 @@ impl HTMLImageElement {
     }
 }
 
+pub trait HTMLImageElementHelpers {
+    /// Returns the URL of the image that the `src` attribute refers to, if any.
+    fn get_url(self) -> Option<Url>;
+}
+
+impl<'a> HTMLImageElementHelpers for JSRef<'a, HTMLImageElement> {
+    fn get_url(self) -> Option<Url> {
+        self.image.borrow().clone()
+    }
+}
+
 pub trait LayoutHTMLImageElementHelpers {
     unsafe fn image(&self) -> Option<Url>;
 }
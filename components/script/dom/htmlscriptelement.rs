@@ -10,6 +10,7 @@ use dom::bindings::codegen::Bindings::AttrBinding::AttrMethods;
 use dom::bindings::codegen::Bindings::HTMLScriptElementBinding;
 use dom::bindings::codegen::Bindings::HTMLScriptElementBinding::HTMLScriptElementMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::InheritTypes::{HTMLScriptElementDerived, HTMLScriptElementCast};
 use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, NodeCast};
 use dom::bindings::js::{JSRef, Temporary, OptionalRootable};
@@ -19,7 +20,8 @@ use dom::element::{HTMLScriptElementTypeId, Element, AttributeHandlers};
 use dom::element::{ElementCreator, ParserCreated};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, NodeHelpers, ElementNodeTypeId, window_from_node, CloneChildrenFlag};
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId, CloneChildrenFlag};
+use dom::node::{document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 use dom::window::WindowHelpers;
 
@@ -86,6 +88,10 @@ pub trait HTMLScriptElementHelpers {
 
     /// Set the "already started" flag (<https://whatwg.org/html/#already-started>)
     fn mark_already_started(self);
+
+    /// Unset the "already started" and "parser-inserted" flags, as done for the scripts of a
+    /// fragment created by `Range.createContextualFragment()`.
+    fn unmark_already_started(self);
 }
 
 /// Supported script types as defined by
@@ -152,8 +158,13 @@ impl<'a> HTMLScriptElementHelpers for JSRef<'a, HTMLScriptElement> {
         // not the Document of the parser that created the element, then abort these steps.
 
         // Step 11.
-        // TODO: If scripting is disabled for the script element, then the user agent must abort
-        // these steps at this point. The script is not executed.
+        // Scripting is disabled for documents without a browsing context, like the ones that
+        // fragments are parsed into.
+        let window = window_from_node(self).root();
+        let document = document_from_node(self).root();
+        if *window.Document().root() != *document {
+            return;
+        }
 
         // Step 12.
         // TODO: If the script element has an `event` attribute and a `for` attribute, then run
@@ -167,7 +178,6 @@ impl<'a> HTMLScriptElementHelpers for JSRef<'a, HTMLScriptElement> {
         // Step 14 and 15.
         // TODO: Add support for the `defer` and `async` attributes.  (For now, we fetch all
         // scripts synchronously and execute them immediately.)
-        let window = window_from_node(self).root();
         let page = window.page();
         let base_url = page.get_url();
 
@@ -183,7 +193,8 @@ impl<'a> HTMLScriptElementHelpers for JSRef<'a, HTMLScriptElement> {
                         // state of the element's `crossorigin` content attribute, the origin being
                         // the origin of the script element's node document, and the default origin
                         // behaviour set to taint.
-                        match load_whole_resource(&page.resource_task, url) {
+                        let origin = Some(base_url.clone());
+                        match load_whole_resource(&page.resource_task, url, origin) {
                             Ok((metadata, bytes)) => {
                                 // TODO: use the charset from step 13.
                                 let source = UTF_8.decode(bytes.as_slice(), DecodeReplace).unwrap();
@@ -245,6 +256,11 @@ impl<'a> HTMLScriptElementHelpers for JSRef<'a, HTMLScriptElement> {
     fn mark_already_started(self) {
         self.already_started.set(true);
     }
+
+    fn unmark_already_started(self) {
+        self.already_started.set(false);
+        self.parser_inserted.set(false);
+    }
 }
 
 impl<'a> VirtualMethods for JSRef<'a, HTMLScriptElement> {
@@ -9,6 +9,7 @@ use dom::bindings::codegen::InheritTypes::ProcessingInstructionCast;
 use dom::bindings::js::JSRef;
 use dom::characterdata::CharacterData;
 use dom::comment::Comment;
+use dom::document::DocumentHelpers;
 use dom::documenttype::DocumentType;
 use dom::element::{Element, ElementHelpers};
 use dom::node::{Node, NodeIterator};
@@ -18,21 +19,46 @@ use dom::node::{TextNodeTypeId, NodeHelpers};
 use dom::processinginstruction::ProcessingInstruction;
 use dom::text::Text;
 
+use string_cache::Namespace;
+
+/// Whether nodes are serialized with the HTML or the XML serialization algorithm.
+#[deriving(PartialEq)]
+pub enum SerializationMode {
+    /// <http://www.whatwg.org/html/#serialising-html-fragments>
+    HTMLSerialization,
+    /// <http://domparsing.spec.whatwg.org/#dfn-concept-serialize-xml>
+    XMLSerialization,
+}
+
+/// The serialization algorithm used for the fragments of `node`, depending on its document.
+///
+/// <http://domparsing.spec.whatwg.org/#concept-serialize>
+pub fn fragment_serialization_mode(node: JSRef<Node>) -> SerializationMode {
+    let document = node.owner_doc().root();
+    if document.is_html_document() {
+        HTMLSerialization
+    } else {
+        XMLSerialization
+    }
+}
+
 #[allow(unrooted_must_root)]
-pub fn serialize(iterator: &mut NodeIterator) -> String {
+pub fn serialize(iterator: &mut NodeIterator, mode: SerializationMode) -> String {
     let mut html = String::new();
-    let mut open_elements: Vec<String> = vec!();
+    // The name of each open element, with the default namespace in scope for its children.
+    let mut open_elements: Vec<(String, Namespace)> = vec!();
     let depth = iterator.depth;
     for node in *iterator {
         while open_elements.len() > depth {
+            let (name, _) = open_elements.pop().unwrap();
             html.push_str("</");
-            html.push_str(open_elements.pop().unwrap().as_slice());
+            html.push_str(name.as_slice());
             html.push_str(">");
         }
         match node.type_id() {
             ElementNodeTypeId(..) => {
                 let elem: JSRef<Element> = ElementCast::to_ref(node).unwrap();
-                serialize_elem(elem, &mut open_elements, &mut html)
+                serialize_elem(elem, &mut open_elements, mode, &mut html)
             }
             CommentNodeTypeId => {
                 let comment: JSRef<Comment> = CommentCast::to_ref(node).unwrap();
@@ -40,11 +66,11 @@ pub fn serialize(iterator: &mut NodeIterator) -> String {
             }
             TextNodeTypeId => {
                 let text: JSRef<Text> = TextCast::to_ref(node).unwrap();
-                serialize_text(text, &mut html)
+                serialize_text(text, mode, &mut html)
             }
             DoctypeNodeTypeId => {
                 let doctype: JSRef<DocumentType> = DocumentTypeCast::to_ref(node).unwrap();
-                serialize_doctype(doctype, &mut html)
+                serialize_doctype(doctype, mode, &mut html)
             }
             ProcessingInstructionNodeTypeId => {
                 let processing_instruction: JSRef<ProcessingInstruction> =
@@ -58,8 +84,9 @@ pub fn serialize(iterator: &mut NodeIterator) -> String {
         }
     }
     while open_elements.len() > 0 {
+        let (name, _) = open_elements.pop().unwrap();
         html.push_str("</");
-        html.push_str(open_elements.pop().unwrap().as_slice());
+        html.push_str(name.as_slice());
         html.push_str(">");
     }
     html
@@ -71,7 +98,11 @@ fn serialize_comment(comment: JSRef<Comment>, html: &mut String) {
     html.push_str("-->");
 }
 
-fn serialize_text(text: JSRef<Text>, html: &mut String) {
+fn serialize_text(text: JSRef<Text>, mode: SerializationMode, html: &mut String) {
+    if mode == XMLSerialization {
+        return escape(text.characterdata().data().as_slice(), false, mode, html);
+    }
+
     let text_node: JSRef<Node> = NodeCast::from_ref(text);
     match text_node.parent_node().map(|node| node.root()) {
         Some(ref parent) if parent.is_element() => {
@@ -81,10 +112,10 @@ fn serialize_text(text: JSRef<Text>, html: &mut String) {
                 "noembed" | "noframes" | "plaintext" |
                 "noscript" if *elem.namespace() == ns!(HTML)
                 => html.push_str(text.characterdata().data().as_slice()),
-                _ => escape(text.characterdata().data().as_slice(), false, html)
+                _ => escape(text.characterdata().data().as_slice(), false, mode, html)
             }
         }
-        _ => escape(text.characterdata().data().as_slice(), false, html)
+        _ => escape(text.characterdata().data().as_slice(), false, mode, html)
     }
 }
 
@@ -97,24 +128,82 @@ fn serialize_processing_instruction(processing_instruction: JSRef<ProcessingInst
     html.push_str("?>");
 }
 
-fn serialize_doctype(doctype: JSRef<DocumentType>, html: &mut String) {
-    html.push_str("<!DOCTYPE");
+fn serialize_doctype(doctype: JSRef<DocumentType>, mode: SerializationMode, html: &mut String) {
+    if mode == HTMLSerialization {
+        html.push_str("<!DOCTYPE");
+        html.push_str(doctype.name().as_slice());
+        html.push('>');
+        return;
+    }
+
+    html.push_str("<!DOCTYPE ");
     html.push_str(doctype.name().as_slice());
+    if !doctype.public_id().is_empty() {
+        html.push_str(" PUBLIC \"");
+        html.push_str(doctype.public_id().as_slice());
+        html.push('"');
+    } else if !doctype.system_id().is_empty() {
+        html.push_str(" SYSTEM");
+    }
+    if !doctype.system_id().is_empty() {
+        html.push_str(" \"");
+        html.push_str(doctype.system_id().as_slice());
+        html.push('"');
+    }
     html.push('>');
 }
 
-fn serialize_elem(elem: JSRef<Element>, open_elements: &mut Vec<String>, html: &mut String) {
+fn serialize_elem(elem: JSRef<Element>, open_elements: &mut Vec<(String, Namespace)>,
+                  mode: SerializationMode, html: &mut String) {
+    let name = match (mode, elem.prefix()) {
+        (XMLSerialization, &Some(ref prefix)) => {
+            format!("{}:{}", prefix, elem.local_name().as_slice())
+        }
+        _ => elem.local_name().as_slice().to_string(),
+    };
     html.push('<');
-    html.push_str(elem.local_name().as_slice());
+    html.push_str(name.as_slice());
+
+    // In XML, declare the default namespace of unprefixed elements whose namespace differs
+    // from the one of their parent.
+    // FIXME: Prefixes are only declared by the xmlns attributes of the elements themselves.
+    let mut default_namespace = match open_elements.last() {
+        Some(&(_, ref namespace)) => namespace.clone(),
+        None => ns!(""),
+    };
+    if mode == XMLSerialization && elem.prefix().is_none() &&
+       *elem.namespace() != default_namespace {
+        default_namespace = elem.namespace().clone();
+        let declared = elem.get_attribute(ns!(XMLNS), &atom!("xmlns")).is_some();
+        if !declared {
+            let Namespace(ref url) = *elem.namespace();
+            html.push_str(" xmlns=\"");
+            escape(url.as_slice(), true, mode, html);
+            html.push('"');
+        }
+    }
+
     for attr in elem.attrs().iter() {
         let attr = attr.root();
-        serialize_attr(*attr, html);
+        serialize_attr(*attr, mode, html);
     };
+
+    let node: JSRef<Node> = NodeCast::from_ref(elem);
+    if mode == XMLSerialization && node.first_child().is_none() {
+        // Elements without children are self-closing, except for the non-void HTML elements,
+        // whose end tag is required when parsed as HTML.
+        if *elem.namespace() != ns!(HTML) {
+            return html.push_str("/>");
+        }
+        if elem.is_void() {
+            return html.push_str(" />");
+        }
+    }
     html.push('>');
 
     match elem.local_name().as_slice() {
-        "pre" | "listing" | "textarea" if *elem.namespace() == ns!(HTML) => {
-            let node: JSRef<Node> = NodeCast::from_ref(elem);
+        "pre" | "listing" | "textarea" if *elem.namespace() == ns!(HTML) &&
+                                          mode == HTMLSerialization => {
             match node.first_child().map(|child| child.root()) {
                 Some(ref child) if child.is_text() => {
                     let text: JSRef<CharacterData> = CharacterDataCast::to_ref(**child).unwrap();
@@ -129,11 +218,11 @@ fn serialize_elem(elem: JSRef<Element>, open_elements: &mut Vec<String>, html: &
     }
 
     if !(elem.is_void()) {
-        open_elements.push(elem.local_name().as_slice().to_string());
+        open_elements.push((name, default_namespace));
     }
 }
 
-fn serialize_attr(attr: JSRef<Attr>, html: &mut String) {
+fn serialize_attr(attr: JSRef<Attr>, mode: SerializationMode, html: &mut String) {
     html.push(' ');
     if *attr.namespace() == ns!(XML) {
         html.push_str("xml:");
@@ -151,17 +240,17 @@ fn serialize_attr(attr: JSRef<Attr>, html: &mut String) {
         html.push_str(attr.name().as_slice());
     };
     html.push_str("=\"");
-    escape(attr.value().as_slice(), true, html);
+    escape(attr.value().as_slice(), true, mode, html);
     html.push('"');
 }
 
-fn escape(string: &str, attr_mode: bool, html: &mut String) {
+fn escape(string: &str, attr_mode: bool, mode: SerializationMode, html: &mut String) {
     for c in string.chars() {
         match c {
             '&' => html.push_str("&amp;"),
-            '\u00A0' => html.push_str("&nbsp;"),
+            '\u00A0' if mode == HTMLSerialization => html.push_str("&nbsp;"),
             '"' if attr_mode => html.push_str("&quot;"),
-            '<' if !attr_mode => html.push_str("&lt;"),
+            '<' if !attr_mode || mode == XMLSerialization => html.push_str("&lt;"),
             '>' if !attr_mode => html.push_str("&gt;"),
             c => html.push(c),
         }
@@ -23,6 +23,8 @@ use std::cell::Cell;
 pub struct HTMLTableCellElement {
     htmlelement: HTMLElement,
     width: Cell<LengthOrPercentageOrAuto>,
+    colspan: Cell<Option<u32>>,
+    rowspan: Cell<Option<u32>>,
 }
 
 impl HTMLTableCellElementDerived for EventTarget {
@@ -39,7 +41,9 @@ impl HTMLTableCellElement {
     pub fn new_inherited(type_id: ElementTypeId, tag_name: DOMString, prefix: Option<DOMString>, document: JSRef<Document>) -> HTMLTableCellElement {
         HTMLTableCellElement {
             htmlelement: HTMLElement::new_inherited(type_id, tag_name, prefix, document),
-            width: Cell::new(AutoLpa)
+            width: Cell::new(AutoLpa),
+            colspan: Cell::new(None),
+            rowspan: Cell::new(None),
         }
     }
 
@@ -51,12 +55,22 @@ impl HTMLTableCellElement {
 
 pub trait HTMLTableCellElementHelpers {
     fn get_width(&self) -> LengthOrPercentageOrAuto;
+    fn get_colspan(&self) -> Option<u32>;
+    fn get_rowspan(&self) -> Option<u32>;
 }
 
 impl HTMLTableCellElementHelpers for HTMLTableCellElement {
     fn get_width(&self) -> LengthOrPercentageOrAuto {
         self.width.get()
     }
+
+    fn get_colspan(&self) -> Option<u32> {
+        self.colspan.get()
+    }
+
+    fn get_rowspan(&self) -> Option<u32> {
+        self.rowspan.get()
+    }
 }
 
 impl<'a> VirtualMethods for JSRef<'a, HTMLTableCellElement> {
@@ -73,6 +87,12 @@ impl<'a> VirtualMethods for JSRef<'a, HTMLTableCellElement> {
 
         match attr.local_name() {
             &atom!("width") => self.width.set(str::parse_length(attr.value().as_slice())),
+            &atom!("colspan") => {
+                self.colspan.set(str::parse_unsigned_integer(attr.value().as_slice().chars()))
+            }
+            &atom!("rowspan") => {
+                self.rowspan.set(str::parse_unsigned_integer(attr.value().as_slice().chars()))
+            }
             _ => ()
         }
     }
@@ -85,6 +105,8 @@ impl<'a> VirtualMethods for JSRef<'a, HTMLTableCellElement> {
 
         match attr.local_name() {
             &atom!("width") => self.width.set(AutoLpa),
+            &atom!("colspan") => self.colspan.set(None),
+            &atom!("rowspan") => self.rowspan.set(None),
             _ => ()
         }
     }
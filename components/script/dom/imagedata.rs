@@ -0,0 +1,108 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::ImageDataBinding;
+use dom::bindings::codegen::Bindings::ImageDataBinding::ImageDataMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+
+use geom::size::Size2D;
+use js::jsapi::{JSContext, JSObject};
+use js::jsfriendapi::bindgen::{JS_NewUint8ClampedArray, JS_GetUint8ClampedArrayData};
+use libc::uint8_t;
+
+use std::cell::Cell;
+use std::mem;
+use std::ptr;
+use std::raw::Slice;
+
+#[dom_struct]
+pub struct ImageData {
+    reflector_: Reflector,
+    width: u32,
+    height: u32,
+    /// The `Uint8ClampedArray` holding the pixels, as non-premultiplied RGBA.
+    data: Cell<*mut JSObject>,
+}
+
+impl ImageData {
+    fn new_inherited(width: u32, height: u32) -> ImageData {
+        ImageData {
+            reflector_: Reflector::new(),
+            width: width,
+            height: height,
+            data: Cell::new(ptr::null_mut()),
+        }
+    }
+
+    /// Creates an `ImageData` of the given size, filled with the given pixels or with transparent
+    /// black.
+    pub fn new(global: GlobalRef, width: u32, height: u32, data: Option<Vec<u8>>)
+               -> Temporary<ImageData> {
+        let imagedata = reflect_dom_object(box ImageData::new_inherited(width, height),
+                                           global, ImageDataBinding::Wrap).root();
+
+        // The array is only traced once it is stored in the reflected object, so it is created
+        // afterwards.
+        let cx = global.get_cx();
+        let length = width * height * 4;
+        unsafe {
+            let array = JS_NewUint8ClampedArray(cx, length);
+            imagedata.data.set(array);
+            match data {
+                Some(data) => {
+                    assert!(data.len() == length as uint);
+                    let pointer = JS_GetUint8ClampedArrayData(array, cx);
+                    ptr::copy_nonoverlapping_memory(pointer, data.as_ptr(), data.len());
+                }
+                None => {}
+            }
+        }
+        Temporary::from_rooted(*imagedata)
+    }
+}
+
+pub trait ImageDataHelpers {
+    fn get_size(self) -> Size2D<i32>;
+    /// Returns a copy of the pixels, as non-premultiplied RGBA.
+    fn get_data_array(self, global: &GlobalRef) -> Vec<u8>;
+}
+
+impl<'a> ImageDataHelpers for JSRef<'a, ImageData> {
+    fn get_size(self) -> Size2D<i32> {
+        Size2D(self.width as i32, self.height as i32)
+    }
+
+    fn get_data_array(self, global: &GlobalRef) -> Vec<u8> {
+        let cx = global.get_cx();
+        let length = (self.width * self.height * 4) as uint;
+        unsafe {
+            let data: *const uint8_t =
+                JS_GetUint8ClampedArrayData(self.data.get(), cx) as *const uint8_t;
+            let data: &[u8] = mem::transmute(Slice { data: data, len: length });
+            data.to_vec()
+        }
+    }
+}
+
+impl<'a> ImageDataMethods for JSRef<'a, ImageData> {
+    fn Width(self) -> u32 {
+        self.width
+    }
+
+    fn Height(self) -> u32 {
+        self.height
+    }
+
+    fn Data(self, _cx: *mut JSContext) -> *mut JSObject {
+        self.data.get()
+    }
+}
+
+impl Reflectable for ImageData {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
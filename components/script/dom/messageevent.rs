@@ -6,12 +6,14 @@ use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
 use dom::bindings::codegen::Bindings::MessageEventBinding;
 use dom::bindings::codegen::Bindings::MessageEventBinding::MessageEventMethods;
 use dom::bindings::codegen::InheritTypes::{EventCast, MessageEventDerived};
+use dom::bindings::conversions::ToJSValConvertible;
 use dom::bindings::error::Fallible;
 use dom::bindings::global::GlobalRef;
-use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
 use dom::event::{Event, MessageEventTypeId};
 use dom::eventtarget::{EventTarget, EventTargetHelpers};
+use dom::messageport::{MessagePort, TransferredPort};
 
 use servo_util::str::DOMString;
 
@@ -24,6 +26,8 @@ pub struct MessageEvent {
     data: JSVal,
     origin: DOMString,
     lastEventId: DOMString,
+    source: Option<JS<EventTarget>>,
+    ports: Vec<JS<MessagePort>>,
 }
 
 impl MessageEventDerived for Event {
@@ -33,21 +37,35 @@ impl MessageEventDerived for Event {
 }
 
 impl MessageEvent {
-    fn new_inherited(data: JSVal, origin: DOMString, lastEventId: DOMString)
+    fn new_inherited(data: JSVal, origin: DOMString, lastEventId: DOMString,
+                     source: Option<JSRef<EventTarget>>, ports: Vec<JSRef<MessagePort>>)
                          -> MessageEvent {
         MessageEvent {
             event: Event::new_inherited(MessageEventTypeId),
             data: data,
             origin: origin,
             lastEventId: lastEventId,
+            source: source.map(|source| JS::from_rooted(source)),
+            ports: ports.iter().map(|port| JS::from_rooted(*port)).collect(),
         }
     }
 
     pub fn new(global: GlobalRef, type_: DOMString,
                bubbles: bool, cancelable: bool,
-               data: JSVal, origin: DOMString, lastEventId: DOMString)
+               data: JSVal, origin: DOMString, lastEventId: DOMString,
+               source: Option<JSRef<EventTarget>>)
                -> Temporary<MessageEvent> {
-        let ev = reflect_dom_object(box MessageEvent::new_inherited(data, origin, lastEventId),
+        MessageEvent::new_with_ports(global, type_, bubbles, cancelable, data, origin,
+                                     lastEventId, source, vec!())
+    }
+
+    pub fn new_with_ports(global: GlobalRef, type_: DOMString,
+                          bubbles: bool, cancelable: bool,
+                          data: JSVal, origin: DOMString, lastEventId: DOMString,
+                          source: Option<JSRef<EventTarget>>, ports: Vec<JSRef<MessagePort>>)
+                          -> Temporary<MessageEvent> {
+        let ev = reflect_dom_object(box MessageEvent::new_inherited(data, origin, lastEventId,
+                                                                    source, ports),
                                     global,
                                     MessageEventBinding::Wrap).root();
         let event: JSRef<Event> = EventCast::from_ref(*ev);
@@ -60,18 +78,26 @@ impl MessageEvent {
                        init: &MessageEventBinding::MessageEventInit)
                        -> Fallible<Temporary<MessageEvent>> {
         let ev = MessageEvent::new(*global, type_, init.parent.bubbles, init.parent.cancelable,
-                                   init.data, init.origin.clone(), init.lastEventId.clone());
+                                   init.data, init.origin.clone(), init.lastEventId.clone(),
+                                   None);
         Ok(ev)
     }
 }
 
 impl MessageEvent {
+    /// Dispatches a message event for a message posted by `postMessage()`. The ports transferred
+    /// along with the message become ports of `scope`.
     pub fn dispatch_jsval(target: JSRef<EventTarget>,
                           scope: GlobalRef,
-                          message: JSVal) {
-        let messageevent = MessageEvent::new(
+                          message: JSVal,
+                          ports: Vec<TransferredPort>) {
+        let ports: Vec<_> = ports.into_iter().map(|port| {
+            MessagePort::new_transferred(&scope, port).root()
+        }).collect();
+        let messageevent = MessageEvent::new_with_ports(
             scope, "message".to_string(), false, false, message,
-            "".to_string(), "".to_string()).root();
+            "".to_string(), "".to_string(), None,
+            ports.iter().map(|port| **port).collect()).root();
         let event: JSRef<Event> = EventCast::from_ref(*messageevent);
         target.dispatch_event_with_target(None, event).unwrap();
     }
@@ -89,6 +115,17 @@ impl<'a> MessageEventMethods for JSRef<'a, MessageEvent> {
     fn LastEventId(self) -> DOMString {
         self.lastEventId.clone()
     }
+
+    fn GetSource(self) -> Option<Temporary<EventTarget>> {
+        self.source.map(|source| Temporary::new(source))
+    }
+
+    // http://www.whatwg.org/html/#dom-messageevent-ports
+    fn Ports(self, cx: *mut JSContext) -> JSVal {
+        let ports: Vec<Temporary<MessagePort>> =
+            self.ports.iter().map(|port| Temporary::new(*port)).collect();
+        ports.to_jsval(cx)
+    }
 }
 
 impl Reflectable for MessageEvent {
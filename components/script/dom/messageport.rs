@@ -0,0 +1,388 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::codegen::Bindings::MessagePortBinding;
+use dom::bindings::codegen::Bindings::MessagePortBinding::MessagePortMethods;
+use dom::bindings::codegen::InheritTypes::EventTargetCast;
+use dom::bindings::error::{ErrorResult, Fallible, DataClone};
+use dom::bindings::global::{GlobalRef, GlobalField};
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::eventtarget::{EventTarget, EventTargetHelpers, MessagePortTypeId};
+use dom::messageevent::MessageEvent;
+use script_task::{ScriptChan, MessagePortMsg};
+
+use js::glue::JS_STRUCTURED_CLONE_VERSION;
+use js::jsapi::{JSContext, JS_AddObjectRoot, JS_RemoveObjectRoot};
+use js::jsapi::{JS_ReadStructuredClone, JS_WriteStructuredClone, JS_ClearPendingException};
+use js::jsval::{JSVal, UndefinedValue};
+
+use libc::{c_void, size_t};
+use std::cell::Cell;
+use std::mem;
+use std::ptr;
+use std::slice::raw::buf_as_slice;
+use std::sync::{Arc, Mutex};
+
+pub struct TrustedMessagePortAddress(pub *const c_void);
+no_jsmanaged_fields!(TrustedMessagePortAddress)
+
+/// A message posted to a port, as written by `JS_WriteStructuredClone`, with the ports that
+/// were transferred along with it.
+struct PortMessage {
+    data: Vec<u64>,
+    ports: Vec<TransferredPort>,
+}
+
+/// The task a port lives in, and where to find it there.
+struct PortOwner {
+    script_chan: ScriptChan,
+    address: TrustedMessagePortAddress,
+}
+
+/// The part of a port that the port it is entangled with can reach, from whichever task either
+/// of them lives in.
+pub struct PortEndpoint {
+    /// The task the port lives in, unless it is in transit to another task or was closed.
+    owner: Option<PortOwner>,
+    /// The port message queue: the messages that the port has not dispatched yet.
+    messages: Vec<PortMessage>,
+    /// The number of times the port was attached to a task, which tells the notifications sent
+    /// to a task that the port has since left that they are stale.
+    generation: uint,
+    /// Whether the port was closed, after which the messages posted to it are dropped.
+    closed: bool,
+}
+
+pub type SharedPortEndpoint = Arc<Mutex<PortEndpoint>>;
+
+fn new_endpoint(closed: bool) -> SharedPortEndpoint {
+    Arc::new(Mutex::new(PortEndpoint {
+        owner: None,
+        messages: vec!(),
+        generation: 0,
+        closed: closed,
+    }))
+}
+
+/// Asks the task the port lives in, if any, to dispatch the messages in its queue.
+fn notify_owner(shared: &SharedPortEndpoint, endpoint: &PortEndpoint) {
+    match endpoint.owner {
+        Some(PortOwner { script_chan: ScriptChan(ref chan), .. }) => {
+            // The task may have exited, along with the port.
+            let _ = chan.send_opt(MessagePortMsg(shared.clone(), endpoint.generation));
+        }
+        None => {}
+    }
+}
+
+/// Whether two handles refer to the same endpoint.
+fn same_endpoint(a: &SharedPortEndpoint, b: &SharedPortEndpoint) -> bool {
+    &**a as *const Mutex<PortEndpoint> == &**b as *const Mutex<PortEndpoint>
+}
+
+/// A port on its way to another task, detached from the MessagePort object it was transferred
+/// from.
+pub struct TransferredPort {
+    endpoint: SharedPortEndpoint,
+    entangled: Option<SharedPortEndpoint>,
+}
+
+#[dom_struct]
+pub struct MessagePort {
+    eventtarget: EventTarget,
+    refcount: Cell<uint>,
+    global: GlobalField,
+    /// The state of this port that the port it is entangled with can reach. Replaced by a closed
+    /// endpoint when the port is transferred.
+    endpoint: DOMRefCell<SharedPortEndpoint>,
+    /// The endpoint of the port this one is entangled with, until this one is closed or
+    /// transferred.
+    entangled: DOMRefCell<Option<SharedPortEndpoint>>,
+    /// Whether the port message queue is enabled, by `start()` or by setting `onmessage`.
+    enabled: Cell<bool>,
+    /// Whether the port was transferred, after which it cannot be transferred again.
+    detached: Cell<bool>,
+}
+
+impl MessagePort {
+    fn new_inherited(global: &GlobalRef, endpoint: SharedPortEndpoint,
+                     entangled: Option<SharedPortEndpoint>) -> MessagePort {
+        MessagePort {
+            eventtarget: EventTarget::new_inherited(MessagePortTypeId),
+            refcount: Cell::new(0),
+            global: GlobalField::from_rooted(global),
+            endpoint: DOMRefCell::new(endpoint),
+            entangled: DOMRefCell::new(entangled),
+            enabled: Cell::new(false),
+            detached: Cell::new(false),
+        }
+    }
+
+    fn new_attached(global: &GlobalRef, endpoint: SharedPortEndpoint,
+                    entangled: Option<SharedPortEndpoint>) -> Temporary<MessagePort> {
+        let port = reflect_dom_object(box MessagePort::new_inherited(global, endpoint, entangled),
+                                      *global,
+                                      MessagePortBinding::Wrap).root();
+        port.attach();
+        Temporary::from_rooted(*port)
+    }
+
+    /// Creates a pair of entangled ports.
+    pub fn new_pair(global: &GlobalRef) -> (Temporary<MessagePort>, Temporary<MessagePort>) {
+        let endpoint1 = new_endpoint(false);
+        let endpoint2 = new_endpoint(false);
+        (MessagePort::new_attached(global, endpoint1.clone(), Some(endpoint2.clone())),
+         MessagePort::new_attached(global, endpoint2, Some(endpoint1)))
+    }
+
+    /// Creates the port that a port transferred from another task becomes in this one.
+    pub fn new_transferred(global: &GlobalRef, port: TransferredPort) -> Temporary<MessagePort> {
+        MessagePort::new_attached(global, port.endpoint, port.entangled)
+    }
+
+    /// Dispatches the messages queued on a port, if it still lives in this task.
+    pub fn handle_notification(endpoint: SharedPortEndpoint, generation: uint) {
+        let address = {
+            let endpoint = endpoint.lock();
+            if endpoint.generation != generation {
+                return;
+            }
+            match endpoint.owner {
+                Some(PortOwner { address: TrustedMessagePortAddress(address), .. }) => address,
+                None => return,
+            }
+        };
+        // The port is rooted for as long as it is the owner of its endpoint.
+        let port = unsafe {
+            JS::from_trusted_message_port_address(TrustedMessagePortAddress(address)).root()
+        };
+        port.dispatch_messages();
+    }
+}
+
+/// Detaches the ports of a transfer list from their objects, so that they can be sent to another
+/// task. `source` is the port that the message is posted on, if any, which cannot be transferred
+/// and neither can the port it is entangled with.
+// http://www.whatwg.org/html/#transferable-objects
+pub fn transfer_ports(transfer: Option<Vec<JS<MessagePort>>>, source: Option<JSRef<MessagePort>>)
+                      -> Fallible<Vec<TransferredPort>> {
+    let transfer = transfer.unwrap_or(vec!());
+    let ports: Vec<_> = transfer.iter().map(|port| port.root()).collect();
+    for (i, port) in ports.iter().enumerate() {
+        if port.detached.get() {
+            return Err(DataClone);
+        }
+        if ports.slice_to(i).iter().any(|other| other.deref() as *const MessagePort ==
+                                                port.deref() as *const MessagePort) {
+            return Err(DataClone);
+        }
+        match source {
+            Some(source) => {
+                if source.deref() as *const MessagePort == port.deref() as *const MessagePort {
+                    return Err(DataClone);
+                }
+                match *source.entangled.borrow() {
+                    Some(ref entangled) if same_endpoint(entangled, &*port.endpoint.borrow()) => {
+                        return Err(DataClone);
+                    }
+                    _ => {}
+                }
+            }
+            None => {}
+        }
+    }
+    Ok(ports.iter().map(|port| port.detach()).collect())
+}
+
+trait PrivateMessagePortHelpers {
+    fn addref(self) -> TrustedMessagePortAddress;
+    fn release(self);
+    fn attach(self);
+    fn detach(self) -> TransferredPort;
+    fn dispatch_messages(self);
+}
+
+impl<'a> PrivateMessagePortHelpers for JSRef<'a, MessagePort> {
+    // Creates a trusted address to the object, and roots it. Always pair this with a release()
+    fn addref(self) -> TrustedMessagePortAddress {
+        let refcount = self.refcount.get();
+        if refcount == 0 {
+            let cx = self.global.root().root_ref().get_cx();
+            unsafe {
+                JS_AddObjectRoot(cx, self.reflector().rootable());
+            }
+        }
+        self.refcount.set(refcount + 1);
+        TrustedMessagePortAddress(self.deref() as *const MessagePort as *const c_void)
+    }
+
+    fn release(self) {
+        let refcount = self.refcount.get();
+        assert!(refcount > 0)
+        self.refcount.set(refcount - 1);
+        if refcount == 1 {
+            let cx = self.global.root().root_ref().get_cx();
+            unsafe {
+                JS_RemoveObjectRoot(cx, self.reflector().rootable());
+            }
+        }
+    }
+
+    /// Makes this port the owner of its endpoint, so that the messages posted to it are
+    /// dispatched in this task.
+    // FIXME: A port is kept alive until it is closed or transferred, even when nothing can
+    // post messages to it anymore.
+    fn attach(self) {
+        let address = self.addref();
+        let global = self.global.root();
+        let shared = self.endpoint.borrow();
+        let mut endpoint = shared.lock();
+        endpoint.generation += 1;
+        endpoint.owner = Some(PortOwner {
+            script_chan: global.root_ref().script_chan().clone(),
+            address: address,
+        });
+        if !endpoint.messages.is_empty() {
+            notify_owner(&*shared, &*endpoint);
+        }
+    }
+
+    /// Detaches the endpoints of this port from it, leaving it closed.
+    fn detach(self) -> TransferredPort {
+        self.detached.set(true);
+        let shared = mem::replace(&mut *self.endpoint.borrow_mut(), new_endpoint(true));
+        let was_attached = shared.lock().owner.take().is_some();
+        if was_attached {
+            self.release();
+        }
+        TransferredPort {
+            endpoint: shared,
+            entangled: self.entangled.borrow_mut().take(),
+        }
+    }
+
+    /// Dispatches the messages in the port message queue, if it is enabled.
+    fn dispatch_messages(self) {
+        let global = self.global.root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        // The port can be closed or transferred by the handler of any of the messages, in which
+        // case the remaining ones stay in the queue.
+        while self.enabled.get() {
+            let message = {
+                let shared = self.endpoint.borrow();
+                let mut endpoint = shared.lock();
+                if endpoint.owner.is_none() {
+                    return;
+                }
+                match endpoint.messages.remove(0) {
+                    Some(message) => message,
+                    None => return,
+                }
+            };
+            let mut data = UndefinedValue();
+            unsafe {
+                assert!(JS_ReadStructuredClone(
+                    global.root_ref().get_cx(), message.data.as_ptr(),
+                    (message.data.len() * 8) as size_t, JS_STRUCTURED_CLONE_VERSION, &mut data,
+                    ptr::null(), ptr::null_mut()) != 0);
+            }
+            MessageEvent::dispatch_jsval(target, global.root_ref(), data, message.ports);
+        }
+    }
+}
+
+impl<'a> MessagePortMethods for JSRef<'a, MessagePort> {
+    // http://www.whatwg.org/html/#dom-messageport-postmessage
+    fn PostMessage(self, cx: *mut JSContext, message: JSVal,
+                   transfer: Option<Vec<JS<MessagePort>>>) -> ErrorResult {
+        // Step 4.
+        let mut buffer = ptr::null_mut();
+        let mut nbytes = 0;
+        let result = unsafe {
+            JS_WriteStructuredClone(cx, message, &mut buffer, &mut nbytes,
+                                    ptr::null(), ptr::null_mut())
+        };
+        if result == 0 {
+            unsafe { JS_ClearPendingException(cx); }
+            return Err(DataClone);
+        }
+        let data = unsafe {
+            buf_as_slice(buffer as *const u64, nbytes as uint / 8, |data| data.to_vec())
+        };
+        let message = PortMessage {
+            data: data,
+            ports: try!(transfer_ports(transfer, Some(self))),
+        };
+
+        // Step 5: the message is dropped if this port has been disentangled.
+        let entangled = self.entangled.borrow();
+        let shared = match *entangled {
+            Some(ref shared) => shared,
+            None => return Ok(()),
+        };
+
+        // Steps 6-7: queue the message on the entangled port, which may live in another task.
+        let mut endpoint = shared.lock();
+        if endpoint.closed {
+            return Ok(());
+        }
+        endpoint.messages.push(message);
+        notify_owner(shared, &*endpoint);
+        Ok(())
+    }
+
+    // http://www.whatwg.org/html/#dom-messageport-start
+    fn Start(self) {
+        if self.enabled.get() {
+            return;
+        }
+        self.enabled.set(true);
+
+        // Messages that arrived while the queue was disabled are dispatched asynchronously, and
+        // before any that arrive later.
+        let shared = self.endpoint.borrow();
+        let endpoint = shared.lock();
+        if !endpoint.messages.is_empty() {
+            notify_owner(&*shared, &*endpoint);
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-messageport-close
+    fn Close(self) {
+        let was_attached = {
+            let shared = self.endpoint.borrow();
+            let mut endpoint = shared.lock();
+            endpoint.closed = true;
+            endpoint.messages.clear();
+            endpoint.owner.take().is_some()
+        };
+        if was_attached {
+            self.release();
+        }
+        // The entangled port finds that this one is closed when it posts a message to it.
+        *self.entangled.borrow_mut() = None;
+    }
+
+    fn GetOnmessage(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("message")
+    }
+
+    // http://www.whatwg.org/html/#handler-messageport-onmessage
+    fn SetOnmessage(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("message", listener);
+        // Setting the handler implicitly starts the port.
+        self.Start();
+    }
+}
+
+impl Reflectable for MessagePort {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.eventtarget.reflector()
+    }
+}
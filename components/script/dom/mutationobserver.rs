@@ -0,0 +1,359 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::callback::ReportExceptions;
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::MutationObserverBinding;
+use dom::bindings::codegen::Bindings::MutationObserverBinding::MutationCallback;
+use dom::bindings::codegen::Bindings::MutationObserverBinding::MutationObserverInit;
+use dom::bindings::codegen::Bindings::MutationObserverBinding::MutationObserverMethods;
+use dom::bindings::error::{ErrorResult, Fallible, Type};
+use dom::bindings::global::GlobalRef;
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Root, Temporary, TemporaryPushable};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::mutationrecord::MutationRecord;
+use dom::node::{Node, NodeHelpers};
+use dom::window::{Window, WindowHelpers};
+
+use servo_util::str::DOMString;
+use string_cache::{Atom, Namespace};
+
+use std::mem;
+
+/// A change to a node, as reported to the mutation observers interested in it.
+pub enum Mutation<'a> {
+    /// The attribute with the given local name and namespace was set or removed.
+    AttributeMutation(&'a Atom, &'a Namespace),
+    /// The data of a `CharacterData` node changed.
+    CharacterDataMutation,
+    /// The given nodes were added and removed between the given siblings.
+    ChildListMutation(&'a [JSRef<'a, Node>], &'a [JSRef<'a, Node>],
+                      Option<JSRef<'a, Node>>, Option<JSRef<'a, Node>>),
+}
+
+// http://dom.spec.whatwg.org/#dictdef-mutationobserverinit
+#[deriving(Clone)]
+#[jstraceable]
+pub struct MutationObserverOptions {
+    child_list: bool,
+    attributes: bool,
+    character_data: bool,
+    subtree: bool,
+    attribute_old_value: bool,
+    character_data_old_value: bool,
+    attribute_filter: Vec<DOMString>,
+}
+
+// http://dom.spec.whatwg.org/#registered-observer
+#[jstraceable]
+#[must_root]
+pub struct RegisteredObserver {
+    observer: JS<MutationObserver>,
+    options: MutationObserverOptions,
+    /// For transient registered observers, the node with which the registered observer they
+    /// were created from is registered.
+    /// http://dom.spec.whatwg.org/#transient-registered-observer
+    source: Option<JS<Node>>,
+}
+
+impl RegisteredObserver {
+    /// Whether `observer` should be notified of `mutation` to `target`, which is `node` or one
+    /// of its descendants, and whether it wants to know about the old value.
+    fn interest(&self, node: JSRef<Node>, target: JSRef<Node>, mutation: &Mutation)
+                -> Option<bool> {
+        let options = &self.options;
+        if node != target && !options.subtree {
+            return None;
+        }
+        match *mutation {
+            AttributeMutation(name, namespace) => {
+                if !options.attributes {
+                    return None;
+                }
+                if !options.attribute_filter.is_empty() &&
+                   (*namespace != ns!("") ||
+                    !options.attribute_filter.iter().any(|filter| {
+                        filter.as_slice() == name.as_slice()
+                    })) {
+                    return None;
+                }
+                Some(options.attribute_old_value)
+            }
+            CharacterDataMutation => {
+                if !options.character_data {
+                    return None;
+                }
+                Some(options.character_data_old_value)
+            }
+            ChildListMutation(..) => {
+                if !options.child_list {
+                    return None;
+                }
+                Some(false)
+            }
+        }
+    }
+}
+
+// http://dom.spec.whatwg.org/#interface-mutationobserver
+#[dom_struct]
+pub struct MutationObserver {
+    reflector_: Reflector,
+    window: JS<Window>,
+    callback: MutationCallback,
+    record_queue: DOMRefCell<Vec<JS<MutationRecord>>>,
+    /// The nodes this observer is registered with.
+    nodes: DOMRefCell<Vec<JS<Node>>>,
+    /// The nodes this observer has transient registered observers on.
+    transient_nodes: DOMRefCell<Vec<JS<Node>>>,
+}
+
+impl MutationObserver {
+    fn new_inherited(window: JSRef<Window>, callback: MutationCallback) -> MutationObserver {
+        MutationObserver {
+            reflector_: Reflector::new(),
+            window: JS::from_rooted(window),
+            callback: callback,
+            record_queue: DOMRefCell::new(vec!()),
+            nodes: DOMRefCell::new(vec!()),
+            transient_nodes: DOMRefCell::new(vec!()),
+        }
+    }
+
+    pub fn new(window: JSRef<Window>, callback: MutationCallback)
+               -> Temporary<MutationObserver> {
+        reflect_dom_object(box MutationObserver::new_inherited(window, callback),
+                           global::Window(window),
+                           MutationObserverBinding::Wrap)
+    }
+
+    pub fn Constructor(global: &GlobalRef, callback: MutationCallback)
+                       -> Fallible<Temporary<MutationObserver>> {
+        Ok(MutationObserver::new(global.as_window(), callback))
+    }
+
+    // http://dom.spec.whatwg.org/#queue-a-mutation-record
+    /// The old value is only copied into the records of the observers that asked for it.
+    pub fn queue_a_mutation_record(target: JSRef<Node>, mutation: Mutation,
+                                   old_value: Option<&str>) {
+        // Step 1.
+        let mut interested_observers: Vec<(JS<MutationObserver>, bool)> = vec!();
+
+        // Step 2-3.
+        for node in Some(target).into_iter().chain(target.ancestors()) {
+            for registered in node.registered_mutation_observers().borrow().iter() {
+                let wants_old_value = match registered.interest(node, target, &mutation) {
+                    None => continue,
+                    Some(wants_old_value) => wants_old_value,
+                };
+                let index = interested_observers.iter().position(|&(observer, _)| {
+                    observer == registered.observer
+                });
+                match index {
+                    Some(index) => {
+                        let (observer, old) = interested_observers[index];
+                        interested_observers.as_mut_slice()[index] =
+                            (observer, old || wants_old_value);
+                    }
+                    None => interested_observers.push((registered.observer, wants_old_value)),
+                }
+            }
+        }
+
+        // Step 4.
+        for &(observer, wants_old_value) in interested_observers.iter() {
+            let observer = observer.root();
+            let window = observer.window.root();
+            let old_value = if wants_old_value {
+                old_value.map(|old_value| old_value.to_string())
+            } else {
+                None
+            };
+            let record = MutationRecord::new(*window, target, &mutation, old_value);
+            observer.record_queue.borrow_mut().push_unrooted(&record);
+            window.queue_mutation_observer(*observer);
+        }
+    }
+
+    // http://dom.spec.whatwg.org/#concept-node-remove
+    // Step 10.
+    /// Gives `node`, which was just removed from `parent`, a transient registered observer for
+    /// each registered observer of an inclusive ancestor of `parent` that observes its subtree.
+    pub fn add_transient_registered_observers(node: JSRef<Node>, parent: JSRef<Node>) {
+        let mut transient_observers = vec!();
+        for ancestor in Some(parent).into_iter().chain(parent.ancestors()) {
+            for registered in ancestor.registered_mutation_observers().borrow().iter() {
+                if !registered.options.subtree {
+                    continue;
+                }
+                transient_observers.push(RegisteredObserver {
+                    observer: registered.observer,
+                    options: registered.options.clone(),
+                    source: Some(registered.source.unwrap_or(JS::from_rooted(ancestor))),
+                });
+            }
+        }
+
+        for registered in transient_observers.into_iter() {
+            let observer = registered.observer.root();
+            observer.transient_nodes.borrow_mut().push(JS::from_rooted(node));
+            node.registered_mutation_observers().borrow_mut().push(registered);
+        }
+    }
+}
+
+pub trait MutationObserverHelpers {
+    fn notify(self);
+}
+
+impl<'a> MutationObserverHelpers for JSRef<'a, MutationObserver> {
+    // http://dom.spec.whatwg.org/#notify-mutation-observers
+    // Step 4.
+    fn notify(self) {
+        // Step 4.1-4.2.
+        let records = mem::replace(&mut *self.record_queue.borrow_mut(), vec!());
+
+        // Step 4.3.
+        self.remove_transient_registered_observers(None);
+
+        // Step 4.4.
+        if records.is_empty() {
+            return;
+        }
+        // The records are kept alive for the duration of the callback.
+        let _roots: Vec<Root<MutationRecord>> = records.iter().map(|record| record.root())
+                                                               .collect();
+        let _ = self.callback.Call_(self, records, self, ReportExceptions);
+    }
+}
+
+trait PrivateMutationObserverHelpers {
+    fn remove_transient_registered_observers(self, source: Option<JSRef<Node>>);
+}
+
+impl<'a> PrivateMutationObserverHelpers for JSRef<'a, MutationObserver> {
+    /// Removes the transient registered observers of this observer whose source is registered
+    /// with `source`, or all of them if `source` is `None`.
+    fn remove_transient_registered_observers(self, source: Option<JSRef<Node>>) {
+        let this = JS::from_rooted(self);
+        let source = source.map(JS::from_rooted);
+        let nodes = mem::replace(&mut *self.transient_nodes.borrow_mut(), vec!());
+        let mut remaining_nodes = vec!();
+        for node in nodes.into_iter() {
+            let root = node.root();
+            let mut registered_observers = root.registered_mutation_observers().borrow_mut();
+            registered_observers.retain(|registered| {
+                registered.observer != this || registered.source.is_none() ||
+                    (source.is_some() && registered.source != source)
+            });
+            let is_still_registered = registered_observers.iter().any(|registered| {
+                registered.observer == this && registered.source.is_some()
+            });
+            if is_still_registered && !remaining_nodes.contains(&node) {
+                remaining_nodes.push(node);
+            }
+        }
+        *self.transient_nodes.borrow_mut() = remaining_nodes;
+    }
+}
+
+impl<'a> MutationObserverMethods for JSRef<'a, MutationObserver> {
+    // http://dom.spec.whatwg.org/#dom-mutationobserver-observe
+    fn Observe(self, target: JSRef<Node>, init: &MutationObserverInit) -> ErrorResult {
+        let attribute_filter = init.attributeFilter.clone();
+
+        // Step 1.
+        let attributes = init.attributes.unwrap_or(
+            init.attributeOldValue.is_some() || attribute_filter.is_some());
+
+        // Step 2.
+        let character_data = init.characterData.unwrap_or(
+            init.characterDataOldValue.is_some());
+
+        // Step 3.
+        if !init.childList && !attributes && !character_data {
+            return Err(Type("One of childList, attributes or characterData must be true"));
+        }
+
+        // Step 4.
+        if init.attributeOldValue == Some(true) && !attributes {
+            return Err(Type("attributeOldValue requires attributes"));
+        }
+
+        // Step 5.
+        if attribute_filter.is_some() && !attributes {
+            return Err(Type("attributeFilter requires attributes"));
+        }
+
+        // Step 6.
+        if init.characterDataOldValue == Some(true) && !character_data {
+            return Err(Type("characterDataOldValue requires characterData"));
+        }
+
+        let options = MutationObserverOptions {
+            child_list: init.childList,
+            attributes: attributes,
+            character_data: character_data,
+            subtree: init.subtree,
+            attribute_old_value: init.attributeOldValue.unwrap_or(false),
+            character_data_old_value: init.characterDataOldValue.unwrap_or(false),
+            attribute_filter: attribute_filter.unwrap_or(vec!()),
+        };
+
+        // Step 7.
+        let this = JS::from_rooted(self);
+        {
+            let mut registered_observers = target.registered_mutation_observers().borrow_mut();
+            let index = registered_observers.iter().position(|registered| {
+                registered.observer == this && registered.source.is_none()
+            });
+            match index {
+                Some(index) => registered_observers.as_mut_slice()[index].options = options,
+                None => {
+                    // Step 8.
+                    registered_observers.push(RegisteredObserver {
+                        observer: this,
+                        options: options,
+                        source: None,
+                    });
+                    self.nodes.borrow_mut().push(JS::from_rooted(target));
+                    return Ok(());
+                }
+            }
+        }
+        // The transient registered observers created from the registered observer whose options
+        // were replaced go away.
+        self.remove_transient_registered_observers(Some(target));
+        Ok(())
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationobserver-disconnect
+    fn Disconnect(self) {
+        // Step 1.
+        let this = JS::from_rooted(self);
+        let nodes = mem::replace(&mut *self.nodes.borrow_mut(), vec!());
+        for node in nodes.iter() {
+            let node = node.root();
+            node.registered_mutation_observers().borrow_mut()
+                .retain(|registered| registered.observer != this);
+        }
+        self.remove_transient_registered_observers(None);
+
+        // Step 2.
+        self.record_queue.borrow_mut().clear();
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationobserver-takerecords
+    fn TakeRecords(self) -> Vec<Temporary<MutationRecord>> {
+        let records = mem::replace(&mut *self.record_queue.borrow_mut(), vec!());
+        records.into_iter().map(Temporary::new).collect()
+    }
+}
+
+impl Reflectable for MutationObserver {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
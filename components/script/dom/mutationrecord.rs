@@ -0,0 +1,136 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::MutationRecordBinding;
+use dom::bindings::codegen::Bindings::MutationRecordBinding::MutationRecordMethods;
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::mutationobserver::{Mutation, AttributeMutation, CharacterDataMutation};
+use dom::mutationobserver::ChildListMutation;
+use dom::node::Node;
+use dom::nodelist::NodeList;
+use dom::window::Window;
+
+use servo_util::str::DOMString;
+use string_cache::Namespace;
+
+// http://dom.spec.whatwg.org/#interface-mutationrecord
+#[dom_struct]
+pub struct MutationRecord {
+    reflector_: Reflector,
+    type_: DOMString,
+    target: JS<Node>,
+    added_nodes: JS<NodeList>,
+    removed_nodes: JS<NodeList>,
+    previous_sibling: Option<JS<Node>>,
+    next_sibling: Option<JS<Node>>,
+    attribute_name: Option<DOMString>,
+    attribute_namespace: Option<DOMString>,
+    old_value: Option<DOMString>,
+}
+
+impl MutationRecord {
+    fn new_inherited(target: JSRef<Node>, mutation: &Mutation,
+                     added_nodes: JSRef<NodeList>, removed_nodes: JSRef<NodeList>,
+                     old_value: Option<DOMString>) -> MutationRecord {
+        let (type_, previous_sibling, next_sibling, attribute_name, attribute_namespace) =
+            match *mutation {
+                AttributeMutation(name, namespace) => {
+                    let namespace = match *namespace {
+                        ns!("") => None,
+                        Namespace(ref namespace) => Some(namespace.as_slice().to_string()),
+                    };
+                    ("attributes", None, None, Some(name.as_slice().to_string()), namespace)
+                }
+                CharacterDataMutation => ("characterData", None, None, None, None),
+                ChildListMutation(_, _, previous_sibling, next_sibling) => {
+                    ("childList", previous_sibling, next_sibling, None, None)
+                }
+            };
+
+        MutationRecord {
+            reflector_: Reflector::new(),
+            type_: type_.to_string(),
+            target: JS::from_rooted(target),
+            added_nodes: JS::from_rooted(added_nodes),
+            removed_nodes: JS::from_rooted(removed_nodes),
+            previous_sibling: previous_sibling.map(JS::from_rooted),
+            next_sibling: next_sibling.map(JS::from_rooted),
+            attribute_name: attribute_name,
+            attribute_namespace: attribute_namespace,
+            old_value: old_value,
+        }
+    }
+
+    pub fn new(window: JSRef<Window>, target: JSRef<Node>, mutation: &Mutation,
+               old_value: Option<DOMString>) -> Temporary<MutationRecord> {
+        let (added_nodes, removed_nodes) = match *mutation {
+            ChildListMutation(added_nodes, removed_nodes, _, _) => {
+                (added_nodes.to_vec(), removed_nodes.to_vec())
+            }
+            _ => (vec!(), vec!()),
+        };
+        let added_nodes = NodeList::new_simple_list(window, added_nodes).root();
+        let removed_nodes = NodeList::new_simple_list(window, removed_nodes).root();
+        reflect_dom_object(box MutationRecord::new_inherited(target, mutation,
+                                                             *added_nodes, *removed_nodes,
+                                                             old_value),
+                           global::Window(window),
+                           MutationRecordBinding::Wrap)
+    }
+}
+
+impl<'a> MutationRecordMethods for JSRef<'a, MutationRecord> {
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-type
+    fn Type(self) -> DOMString {
+        self.type_.clone()
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-target
+    fn Target(self) -> Temporary<Node> {
+        Temporary::new(self.target)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-addednodes
+    fn AddedNodes(self) -> Temporary<NodeList> {
+        Temporary::new(self.added_nodes)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-removednodes
+    fn RemovedNodes(self) -> Temporary<NodeList> {
+        Temporary::new(self.removed_nodes)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-previoussibling
+    fn GetPreviousSibling(self) -> Option<Temporary<Node>> {
+        self.previous_sibling.map(Temporary::new)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-nextsibling
+    fn GetNextSibling(self) -> Option<Temporary<Node>> {
+        self.next_sibling.map(Temporary::new)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-attributename
+    fn GetAttributeName(self) -> Option<DOMString> {
+        self.attribute_name.clone()
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-attributenamespace
+    fn GetAttributeNamespace(self) -> Option<DOMString> {
+        self.attribute_namespace.clone()
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationrecord-oldvalue
+    fn GetOldValue(self) -> Option<DOMString> {
+        self.old_value.clone()
+    }
+}
+
+impl Reflectable for MutationRecord {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -19,7 +19,7 @@ use dom::bindings::codegen::InheritTypes::{CharacterDataCast, NodeBase, NodeDeri
 use dom::bindings::codegen::InheritTypes::{ProcessingInstructionCast, EventTargetCast};
 use dom::bindings::codegen::InheritTypes::{HTMLLegendElementDerived, HTMLFieldSetElementDerived};
 use dom::bindings::codegen::InheritTypes::HTMLOptGroupElementDerived;
-use dom::bindings::error::{Fallible, NotFound, HierarchyRequest, Syntax};
+use dom::bindings::error::{ErrorResult, Fallible, NotFound, HierarchyRequest, Syntax};
 use dom::bindings::global::GlobalRef;
 use dom::bindings::global;
 use dom::bindings::js::{JS, JSRef, RootedReference, Temporary, Root};
@@ -38,6 +38,7 @@ use dom::element::{HTMLAnchorElementTypeId, HTMLButtonElementTypeId, ElementHelp
 use dom::element::{HTMLInputElementTypeId, HTMLSelectElementTypeId};
 use dom::element::{HTMLTextAreaElementTypeId, HTMLOptGroupElementTypeId};
 use dom::element::{HTMLOptionElementTypeId, HTMLFieldSetElementTypeId};
+use dom::mutationobserver::{MutationObserver, RegisteredObserver, ChildListMutation};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::nodelist::NodeList;
 use dom::processinginstruction::ProcessingInstruction;
@@ -110,6 +111,9 @@ pub struct Node {
     layout_data: LayoutDataRef,
 
     unique_id: DOMRefCell<String>,
+
+    /// The mutation observers registered with this node.
+    registered_mutation_observers: DOMRefCell<Vec<RegisteredObserver>>,
 }
 
 impl NodeDerived for EventTarget {
@@ -386,6 +390,9 @@ pub trait NodeHelpers<'a> {
     fn is_in_doc(self) -> bool;
     fn is_inclusive_ancestor_of(self, parent: JSRef<'a, Node>) -> bool;    // FIXME: See #3960
     fn is_parent_of(self, child: JSRef<Node>) -> bool;
+    fn index(self) -> u32;
+    fn len(self) -> u32;
+    fn registered_mutation_observers(self) -> &'a DOMRefCell<Vec<RegisteredObserver>>;
 
     fn type_id(self) -> NodeTypeId;
 
@@ -687,6 +694,32 @@ impl<'a> NodeHelpers<'a> for JSRef<'a, Node> {
         }
     }
 
+    // http://dom.spec.whatwg.org/#concept-tree-index
+    fn index(self) -> u32 {
+        match self.parent_node().root() {
+            None => 0,
+            Some(parent) => parent.children().position(|child| child == self).unwrap() as u32,
+        }
+    }
+
+    // http://dom.spec.whatwg.org/#concept-node-length
+    fn len(self) -> u32 {
+        match self.type_id {
+            DoctypeNodeTypeId => 0,
+            CommentNodeTypeId |
+            TextNodeTypeId |
+            ProcessingInstructionNodeTypeId => {
+                let characterdata: JSRef<CharacterData> = CharacterDataCast::to_ref(self).unwrap();
+                characterdata.Length()
+            }
+            _ => self.children().count() as u32,
+        }
+    }
+
+    fn registered_mutation_observers(self) -> &'a DOMRefCell<Vec<RegisteredObserver>> {
+        &self.extended_deref().registered_mutation_observers
+    }
+
     fn to_trusted_node_address(self) -> TrustedNodeAddress {
         TrustedNodeAddress(self.deref() as *const Node as *const libc::c_void)
     }
@@ -1146,6 +1179,8 @@ impl Node {
             layout_data: LayoutDataRef::new(),
 
             unique_id: DOMRefCell::new(String::new()),
+
+            registered_mutation_observers: DOMRefCell::new(vec!()),
         }
     }
 
@@ -1186,9 +1221,9 @@ impl Node {
         // If node is an element, it is _affected by a base URL change_.
     }
 
-    // http://dom.spec.whatwg.org/#concept-node-pre-insert
-    fn pre_insert(node: JSRef<Node>, parent: JSRef<Node>, child: Option<JSRef<Node>>)
-                  -> Fallible<Temporary<Node>> {
+    // http://dom.spec.whatwg.org/#concept-node-ensure-pre-insertion-validity
+    pub fn ensure_pre_insertion_validity(node: JSRef<Node>, parent: JSRef<Node>,
+                                         child: Option<JSRef<Node>>) -> ErrorResult {
         // Step 1.
         match parent.type_id() {
             DocumentNodeTypeId |
@@ -1302,6 +1337,15 @@ impl Node {
             _ => (),
         }
 
+        Ok(())
+    }
+
+    // http://dom.spec.whatwg.org/#concept-node-pre-insert
+    fn pre_insert(node: JSRef<Node>, parent: JSRef<Node>, child: Option<JSRef<Node>>)
+                  -> Fallible<Temporary<Node>> {
+        // Step 1-6.
+        try!(Node::ensure_pre_insertion_validity(node, parent, child));
+
         // Step 7-8.
         let referenceChild = match child {
             Some(child) if child.clone() == node => node.next_sibling().map(|node| (*node.root()).clone()),
@@ -1346,20 +1390,49 @@ impl Node {
         }
 
         // XXX assert owner_doc
-        // Step 1-3: ranges.
+        // Step 1.
+        let count = match node.type_id() {
+            DocumentFragmentNodeTypeId => node.children().count() as u32,
+            _ => 1,
+        };
+
+        // Step 2-3.
+        match child {
+            Some(child) => {
+                let document = parent.owner_doc().root();
+                document.ranges().increase_above(parent, child.index(), count);
+            }
+            None => (),
+        }
+
+        let previous_sibling = match child {
+            Some(child) => child.prev_sibling(),
+            None => parent.last_child(),
+        }.root();
 
         match node.type_id() {
             DocumentFragmentNodeTypeId => {
                 // Step 4.
-                // Step 5: DocumentFragment, mutation records.
-                // Step 6: DocumentFragment.
-                let mut kids = Vec::new();
-                for kid in node.children() {
-                    kids.push(kid.clone());
-                    Node::remove(kid, node, Suppressed);
+                let kids: Vec<JSRef<Node>> = node.children().collect();
+
+                // Step 5.
+                MutationObserver::queue_a_mutation_record(
+                    node, ChildListMutation(&[], kids.as_slice(), None, None), None);
+
+                // Step 6.
+                for kid in kids.iter() {
+                    Node::remove(*kid, node, Suppressed);
+                }
+
+                // Step 7.
+                match suppress_observers {
+                    Unsuppressed => MutationObserver::queue_a_mutation_record(
+                        parent,
+                        ChildListMutation(kids.as_slice(), &[], previous_sibling.root_ref(), child),
+                        None),
+                    Suppressed => (),
                 }
 
-                // Step 7: mutation records.
                 // Step 8.
                 for kid in kids.iter() {
                     do_insert((*kid).clone(), parent, child);
@@ -1371,9 +1444,14 @@ impl Node {
             }
             _ => {
                 // Step 4.
-                // Step 5: DocumentFragment, mutation records.
-                // Step 6: DocumentFragment.
-                // Step 7: mutation records.
+                // Step 5-6: DocumentFragment.
+                // Step 7.
+                match suppress_observers {
+                    Unsuppressed => MutationObserver::queue_a_mutation_record(
+                        parent, ChildListMutation(&[node], &[], previous_sibling.root_ref(), child),
+                        None),
+                    Suppressed => (),
+                }
                 // Step 8.
                 do_insert(node, parent, child);
                 // Step 9.
@@ -1383,7 +1461,7 @@ impl Node {
     }
 
     // http://dom.spec.whatwg.org/#concept-node-replace-all
-    fn replace_all(node: Option<JSRef<Node>>, parent: JSRef<Node>) {
+    pub fn replace_all(node: Option<JSRef<Node>>, parent: JSRef<Node>) {
 
         // Step 1.
         match node {
@@ -1417,7 +1495,12 @@ impl Node {
             None => (),
         }
 
-        // Step 6: mutation records.
+        // Step 6.
+        if !addedNodes.is_empty() || !removedNodes.is_empty() {
+            let mutation = ChildListMutation(addedNodes.as_slice(), removedNodes.as_slice(),
+                                             None, None);
+            MutationObserver::queue_a_mutation_record(parent, mutation, None);
+        }
 
         // Step 7.
         let parent_in_doc = parent.is_in_doc();
@@ -1448,8 +1531,19 @@ impl Node {
     fn remove(node: JSRef<Node>, parent: JSRef<Node>, suppress_observers: SuppressObserver) {
         assert!(node.parent_node().map_or(false, |node_parent| node_parent == Temporary::from_rooted(parent)));
 
-        // Step 1-5: ranges.
-        // Step 6-7: mutation observers.
+        // Step 1-5. Finding the index of the node is linear, so it is skipped when there is no
+        // range to update.
+        let document = parent.owner_doc().root();
+        let ranges = document.ranges();
+        if !ranges.is_empty() {
+            let index = node.index();
+            ranges.node_removed(node, parent, index);
+        }
+
+        // Step 6-7.
+        let old_previous_sibling = node.prev_sibling().root();
+        let old_next_sibling = node.next_sibling().root();
+
         // Step 8.
         parent.remove_child(node);
 
@@ -1458,8 +1552,18 @@ impl Node {
         // Step 9.
         match suppress_observers {
             Suppressed => (),
-            Unsuppressed => node.node_removed(parent.is_in_doc()),
+            Unsuppressed => {
+                MutationObserver::queue_a_mutation_record(
+                    parent,
+                    ChildListMutation(&[], &[node], old_previous_sibling.root_ref(),
+                                      old_next_sibling.root_ref()),
+                    None);
+                node.node_removed(parent.is_in_doc());
+            }
         }
+
+        // Step 10.
+        MutationObserver::add_transient_registered_observers(node, parent);
     }
 
     // http://dom.spec.whatwg.org/#concept-node-clone
@@ -1784,7 +1888,7 @@ impl<'a> NodeMethods for JSRef<'a, Node> {
                 self.wait_until_safe_to_modify_dom();
 
                 let characterdata: JSRef<CharacterData> = CharacterDataCast::to_ref(self).unwrap();
-                characterdata.set_data(value);
+                let _ = characterdata.SetData(value);
 
                 // Notify the document that the content of this node is different
                 let document = self.owner_doc().root();
@@ -1907,10 +2011,21 @@ impl<'a> NodeMethods for JSRef<'a, Node> {
             _ => next_sibling
         };
 
+        let previous_sibling = match child.prev_sibling() {
+            Some(sibling) if sibling == Temporary::from_rooted(node) => node.prev_sibling(),
+            previous_sibling => previous_sibling,
+        }.root();
+
         // Step 9.
         let document = document_from_node(self).root();
         Node::adopt(node, *document);
 
+        // Step 12.
+        let nodes: Vec<JSRef<Node>> = match node.type_id() {
+            DocumentFragmentNodeTypeId => node.children().collect(),
+            _ => vec!(node),
+        };
+
         {
             // Step 10.
             Node::remove(child, self, Suppressed);
@@ -1919,15 +2034,17 @@ impl<'a> NodeMethods for JSRef<'a, Node> {
             Node::insert(node, self, reference_child, Suppressed);
         }
 
-        // Step 12-14.
-        // Step 13: mutation records.
+        // Step 13.
+        MutationObserver::queue_a_mutation_record(
+            self,
+            ChildListMutation(nodes.as_slice(), &[child], previous_sibling.root_ref(),
+                              reference_child),
+            None);
+
+        // Step 14.
         child.node_removed(self.is_in_doc());
-        if node.type_id() == DocumentFragmentNodeTypeId {
-            for child_node in node.children() {
-                child_node.node_inserted();
-            }
-        } else {
-            node.node_inserted();
+        for child_node in nodes.iter() {
+            child_node.node_inserted();
         }
 
         // Step 15.
@@ -1947,13 +2064,16 @@ impl<'a> NodeMethods for JSRef<'a, Node> {
             if child.is_text() {
                 let characterdata: JSRef<CharacterData> = CharacterDataCast::to_ref(child).unwrap();
                 if characterdata.Length() == 0 {
-                    self.remove_child(child);
+                    Node::remove(child, self, Unsuppressed);
                 } else {
                     match prev_text {
                         Some(text_node) => {
                             let prev_characterdata: JSRef<CharacterData> = CharacterDataCast::to_ref(text_node).unwrap();
+                            let length = prev_characterdata.Length();
                             let _ = prev_characterdata.AppendData(characterdata.Data());
-                            self.remove_child(child);
+                            let document = self.owner_doc().root();
+                            document.ranges().merge_text(child, text_node, length);
+                            Node::remove(child, self, Unsuppressed);
                         },
                         None => prev_text = Some(child)
                     }
@@ -0,0 +1,70 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use dom::bindings::codegen::Bindings::PopStateEventBinding;
+use dom::bindings::codegen::Bindings::PopStateEventBinding::PopStateEventMethods;
+use dom::bindings::codegen::InheritTypes::{EventCast, PopStateEventDerived};
+use dom::bindings::error::Fallible;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::event::{Event, PopStateEventTypeId};
+
+use servo_util::str::DOMString;
+
+use js::jsapi::JSContext;
+use js::jsval::JSVal;
+
+#[dom_struct]
+pub struct PopStateEvent {
+    event: Event,
+    state: JSVal,
+}
+
+impl PopStateEventDerived for Event {
+    fn is_popstateevent(&self) -> bool {
+        *self.type_id() == PopStateEventTypeId
+    }
+}
+
+impl PopStateEvent {
+    fn new_inherited(state: JSVal) -> PopStateEvent {
+        PopStateEvent {
+            event: Event::new_inherited(PopStateEventTypeId),
+            state: state,
+        }
+    }
+
+    pub fn new(global: GlobalRef, type_: DOMString, bubbles: bool, cancelable: bool,
+               state: JSVal) -> Temporary<PopStateEvent> {
+        let ev = reflect_dom_object(box PopStateEvent::new_inherited(state),
+                                    global,
+                                    PopStateEventBinding::Wrap).root();
+        let event: JSRef<Event> = EventCast::from_ref(*ev);
+        event.InitEvent(type_, bubbles, cancelable);
+        Temporary::from_rooted(*ev)
+    }
+
+    pub fn Constructor(global: &GlobalRef,
+                       type_: DOMString,
+                       init: &PopStateEventBinding::PopStateEventInit)
+                       -> Fallible<Temporary<PopStateEvent>> {
+        let ev = PopStateEvent::new(*global, type_, init.parent.bubbles, init.parent.cancelable,
+                                    init.state);
+        Ok(ev)
+    }
+}
+
+impl<'a> PopStateEventMethods for JSRef<'a, PopStateEvent> {
+    fn State(self, _cx: *mut JSContext) -> JSVal {
+        self.state
+    }
+}
+
+impl Reflectable for PopStateEvent {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.event.reflector()
+    }
+}
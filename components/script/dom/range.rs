@@ -2,45 +2,895 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::CharacterDataBinding::CharacterDataMethods;
+use dom::bindings::codegen::Bindings::NodeBinding::{NodeConstants, NodeMethods};
 use dom::bindings::codegen::Bindings::RangeBinding;
-use dom::bindings::codegen::Bindings::RangeBinding::RangeMethods;
+use dom::bindings::codegen::Bindings::RangeBinding::{RangeConstants, RangeMethods};
+use dom::bindings::codegen::Bindings::TextBinding::TextMethods;
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
-use dom::bindings::error::Fallible;
+use dom::bindings::codegen::InheritTypes::{CharacterDataCast, HTMLScriptElementCast, NodeCast};
+use dom::bindings::codegen::InheritTypes::TextCast;
+use dom::bindings::error::{ErrorResult, Fallible, HierarchyRequest, IndexSize};
+use dom::bindings::error::{InvalidNodeType, InvalidState, NotSupported, WrongDocument};
 use dom::bindings::global::{GlobalRef, Window};
-use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::js::{JS, JSRef, OptionalRootable, Root, RootedReference, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::characterdata::CharacterData;
 use dom::document::{Document, DocumentHelpers};
+use dom::documentfragment::DocumentFragment;
+use dom::domrect::DOMRect;
+use dom::domrectlist::DOMRectList;
+use dom::htmlscriptelement::HTMLScriptElementHelpers;
+use dom::node::{Node, NodeHelpers, CloneChildren, DoNotCloneChildren};
+use dom::node::{CommentNodeTypeId, DoctypeNodeTypeId, DocumentFragmentNodeTypeId};
+use dom::node::{DocumentNodeTypeId, ProcessingInstructionNodeTypeId, TextNodeTypeId};
+use dom::node::window_from_node;
+use parse::{fragment_parsing_context, parse_fragment};
+
+use geom::rect::Rect;
+use servo_util::geometry::Au;
+use servo_util::str::DOMString;
+
+use std::cell::Cell;
+use std::cmp::{Ordering, Less, Equal, Greater};
+use std::rc::{Rc, Weak};
 
 #[dom_struct]
 pub struct Range {
-    reflector_: Reflector
+    reflector_: Reflector,
+    inner: Rc<RangeInner>,
 }
 
 impl Range {
-    fn new_inherited() -> Range {
+    fn new_inherited(start_container: JSRef<Node>, start_offset: u32,
+                     end_container: JSRef<Node>, end_offset: u32) -> Range {
         Range {
-            reflector_: Reflector::new()
+            reflector_: Reflector::new(),
+            inner: Rc::new(RangeInner::new(BoundaryPoint::new(start_container, start_offset),
+                                           BoundaryPoint::new(end_container, end_offset))),
         }
     }
 
-    pub fn new(document: JSRef<Document>) -> Temporary<Range> {
+    pub fn new_with_doc(document: JSRef<Document>) -> Temporary<Range> {
+        let root: JSRef<Node> = NodeCast::from_ref(document);
+        Range::new(document, root, 0, root, 0)
+    }
+
+    pub fn new(document: JSRef<Document>,
+               start_container: JSRef<Node>, start_offset: u32,
+               end_container: JSRef<Node>, end_offset: u32) -> Temporary<Range> {
         let window = document.window().root();
-        reflect_dom_object(box Range::new_inherited(),
-                           Window(*window),
-                           RangeBinding::Wrap)
+        let range = reflect_dom_object(box Range::new_inherited(start_container, start_offset,
+                                                                end_container, end_offset),
+                                       Window(*window),
+                                       RangeBinding::Wrap).root();
+        range.register(start_container);
+        range.register(end_container);
+        Temporary::from_rooted(*range)
     }
 
     pub fn Constructor(global: &GlobalRef) -> Fallible<Temporary<Range>> {
         let document = global.as_window().Document().root();
-        Ok(Range::new(*document))
+        Ok(Range::new_with_doc(*document))
+    }
+}
+
+trait PrivateRangeHelpers {
+    fn register(self, node: JSRef<Node>);
+    fn set_start(self, node: JSRef<Node>, offset: u32);
+    fn set_end(self, node: JSRef<Node>, offset: u32);
+    fn set_boundary_point(self, node: JSRef<Node>, offset: u32, start: bool) -> ErrorResult;
+    fn contains(self, node: JSRef<Node>) -> bool;
+    fn partially_contains(self, node: JSRef<Node>) -> bool;
+    fn extract_or_clone(self, extract: bool) -> Fallible<Temporary<DocumentFragment>>;
+    fn client_rects(self) -> Vec<Rect<Au>>;
+}
+
+impl<'a> PrivateRangeHelpers for JSRef<'a, Range> {
+    /// Makes the mutations of the owner document of `node` update this range.
+    fn register(self, node: JSRef<Node>) {
+        let document = node.owner_doc().root();
+        document.ranges().push(&self.inner);
+    }
+
+    fn set_start(self, node: JSRef<Node>, offset: u32) {
+        self.register(node);
+        self.inner.start.set(node, offset);
+    }
+
+    fn set_end(self, node: JSRef<Node>, offset: u32) {
+        self.register(node);
+        self.inner.end.set(node, offset);
+    }
+
+    // http://dom.spec.whatwg.org/#concept-range-bp-set
+    fn set_boundary_point(self, node: JSRef<Node>, offset: u32, start: bool) -> ErrorResult {
+        // Step 1.
+        if node.is_doctype() {
+            return Err(InvalidNodeType);
+        }
+
+        // Step 2.
+        if offset > node.len() {
+            return Err(IndexSize);
+        }
+
+        // Step 3-4.
+        if start {
+            let end = self.EndContainer().root();
+            if root_of(node) != root_of(*end) ||
+               bp_position(node, offset, *end, self.EndOffset()) == Greater {
+                self.set_end(node, offset);
+            }
+            self.set_start(node, offset);
+        // Step 5.
+        } else {
+            let start = self.StartContainer().root();
+            if root_of(node) != root_of(*start) ||
+               bp_position(node, offset, *start, self.StartOffset()) == Less {
+                self.set_start(node, offset);
+            }
+            self.set_end(node, offset);
+        }
+        Ok(())
+    }
+
+    // http://dom.spec.whatwg.org/#contained
+    fn contains(self, node: JSRef<Node>) -> bool {
+        let start = self.StartContainer().root();
+        let end = self.EndContainer().root();
+        root_of(node) == root_of(*start) &&
+            bp_position(node, 0, *start, self.StartOffset()) == Greater &&
+            bp_position(node, node.len(), *end, self.EndOffset()) == Less
+    }
+
+    // http://dom.spec.whatwg.org/#partially-contained
+    fn partially_contains(self, node: JSRef<Node>) -> bool {
+        let start = self.StartContainer().root();
+        let end = self.EndContainer().root();
+        node.is_inclusive_ancestor_of(*start) != node.is_inclusive_ancestor_of(*end)
+    }
+
+    // http://dom.spec.whatwg.org/#concept-range-extract
+    // http://dom.spec.whatwg.org/#concept-range-clone
+    fn extract_or_clone(self, extract: bool) -> Fallible<Temporary<DocumentFragment>> {
+        // Step 3.
+        let start_node = self.StartContainer().root();
+        let start_offset = self.StartOffset();
+        let end_node = self.EndContainer().root();
+        let end_offset = self.EndOffset();
+
+        // Step 1.
+        let document = start_node.owner_doc().root();
+        let fragment = DocumentFragment::new(*document).root();
+        let fragment_node: JSRef<Node> = NodeCast::from_ref(*fragment);
+
+        // Step 2.
+        if self.Collapsed() {
+            return Ok(Temporary::from_rooted(*fragment));
+        }
+
+        // Step 4.
+        if *start_node == *end_node {
+            match CharacterDataCast::to_ref(*start_node) {
+                Some(characterdata) => {
+                    try!(clone_character_data(characterdata, start_offset,
+                                              end_offset - start_offset, fragment_node, extract));
+                    return Ok(Temporary::from_rooted(*fragment));
+                }
+                None => (),
+            }
+        }
+
+        // Step 5.
+        let common_ancestor = common_ancestor(*start_node, *end_node);
+
+        // Step 6.
+        let first_partially_contained_child =
+            if start_node.is_inclusive_ancestor_of(*end_node) {
+                None
+            } else {
+                common_ancestor.children().find(|child| self.partially_contains(*child))
+            };
+
+        // Step 7.
+        let last_partially_contained_child =
+            if end_node.is_inclusive_ancestor_of(*start_node) {
+                None
+            } else {
+                common_ancestor.rev_children().find(|child| self.partially_contains(*child))
+            };
+
+        // Step 8.
+        let contained_children: Vec<JSRef<Node>> =
+            common_ancestor.children().filter(|child| self.contains(*child)).collect();
+
+        // Step 9.
+        if contained_children.iter().any(|child| child.is_doctype()) {
+            return Err(HierarchyRequest);
+        }
+
+        // Step 10-11, which only matter for extraction.
+        let (new_node, new_offset) = if start_node.is_inclusive_ancestor_of(*end_node) {
+            (Temporary::from_rooted(*start_node), start_offset)
+        } else {
+            let reference_node = start_node.ancestors()
+                                           .take_while(|ancestor| {
+                                               !ancestor.is_inclusive_ancestor_of(*end_node)
+                                           })
+                                           .last()
+                                           .unwrap_or(*start_node);
+            (reference_node.parent_node().unwrap(), reference_node.index() + 1)
+        };
+
+        match first_partially_contained_child {
+            // Step 12.
+            Some(child) if CharacterDataCast::to_ref(child).is_some() => {
+                let characterdata: JSRef<CharacterData> =
+                    CharacterDataCast::to_ref(*start_node).unwrap();
+                try!(clone_character_data(characterdata, start_offset,
+                                          start_node.len() - start_offset,
+                                          fragment_node, extract));
+            }
+            // Step 13.
+            Some(child) => {
+                let clone = Node::clone(child, None, DoNotCloneChildren).root();
+                assert!(fragment_node.AppendChild(*clone).is_ok());
+                let subrange = Range::new(*document, *start_node, start_offset,
+                                          child, child.len()).root();
+                let subfragment = try!(subrange.extract_or_clone(extract)).root();
+                let subfragment: JSRef<Node> = NodeCast::from_ref(*subfragment);
+                assert!(clone.AppendChild(subfragment).is_ok());
+            }
+            None => (),
+        }
+
+        // Step 14.
+        for child in contained_children.iter() {
+            if extract {
+                assert!(fragment_node.AppendChild(*child).is_ok());
+            } else {
+                let clone = Node::clone(*child, None, CloneChildren).root();
+                assert!(fragment_node.AppendChild(*clone).is_ok());
+            }
+        }
+
+        match last_partially_contained_child {
+            // Step 15.
+            Some(child) if CharacterDataCast::to_ref(child).is_some() => {
+                let characterdata: JSRef<CharacterData> =
+                    CharacterDataCast::to_ref(*end_node).unwrap();
+                try!(clone_character_data(characterdata, 0, end_offset, fragment_node, extract));
+            }
+            // Step 16.
+            Some(child) => {
+                let clone = Node::clone(child, None, DoNotCloneChildren).root();
+                assert!(fragment_node.AppendChild(*clone).is_ok());
+                let subrange = Range::new(*document, child, 0, *end_node, end_offset).root();
+                let subfragment = try!(subrange.extract_or_clone(extract)).root();
+                let subfragment: JSRef<Node> = NodeCast::from_ref(*subfragment);
+                assert!(clone.AppendChild(subfragment).is_ok());
+            }
+            None => (),
+        }
+
+        // Step 17.
+        if extract {
+            let new_node = new_node.root();
+            self.set_start(*new_node, new_offset);
+            self.set_end(*new_node, new_offset);
+        }
+
+        // Step 18.
+        Ok(Temporary::from_rooted(*fragment))
+    }
+
+    /// The boxes of the elements and text selected by this range, in tree order.
+    fn client_rects(self) -> Vec<Rect<Au>> {
+        let start_node = self.StartContainer().root();
+        let start_offset = self.StartOffset();
+        let end_node = self.EndContainer().root();
+        let end_offset = self.EndOffset();
+        let window = window_from_node(*start_node).root();
+
+        let mut rects = vec!();
+        let common_ancestor = common_ancestor(*start_node, *end_node);
+        for node in common_ancestor.traverse_preorder() {
+            let contained = self.contains(node);
+            if node.is_text() {
+                if !contained && node != *start_node && node != *end_node {
+                    continue;
+                }
+                let characterdata: JSRef<CharacterData> = CharacterDataCast::to_ref(node).unwrap();
+                let data = characterdata.Data();
+                // Layout counts characters rather than code units.
+                let start = if node == *start_node {
+                    char_index(data.as_slice(), start_offset)
+                } else {
+                    0
+                };
+                let end = if node == *end_node {
+                    char_index(data.as_slice(), end_offset)
+                } else {
+                    data.as_slice().char_len()
+                };
+                let text_rects = window.page().text_boxes_query(node.to_trusted_node_address(),
+                                                                start, end);
+                rects.extend(text_rects.into_iter());
+            } else if node.is_element() && contained {
+                let parent = node.parent_node().root();
+                if parent.map_or(true, |parent| !self.contains(*parent)) {
+                    rects.extend(node.get_content_boxes().into_iter());
+                }
+            }
+        }
+        rects
     }
 }
 
 impl<'a> RangeMethods for JSRef<'a, Range> {
-    /// http://dom.spec.whatwg.org/#dom-range-detach
+    // http://dom.spec.whatwg.org/#dom-range-startcontainer
+    fn StartContainer(self) -> Temporary<Node> {
+        self.inner.start.node()
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-startoffset
+    fn StartOffset(self) -> u32 {
+        self.inner.start.offset()
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-endcontainer
+    fn EndContainer(self) -> Temporary<Node> {
+        self.inner.end.node()
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-endoffset
+    fn EndOffset(self) -> u32 {
+        self.inner.end.offset()
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-collapsed
+    fn Collapsed(self) -> bool {
+        self.inner.start.node.get() == self.inner.end.node.get() &&
+            self.StartOffset() == self.EndOffset()
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-commonancestorcontainer
+    fn CommonAncestorContainer(self) -> Temporary<Node> {
+        let start = self.StartContainer().root();
+        let end = self.EndContainer().root();
+        Temporary::from_rooted(common_ancestor(*start, *end))
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-setstartnode-offset
+    fn SetStart(self, node: JSRef<Node>, offset: u32) -> ErrorResult {
+        self.set_boundary_point(node, offset, true)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-setendnode-offset
+    fn SetEnd(self, node: JSRef<Node>, offset: u32) -> ErrorResult {
+        self.set_boundary_point(node, offset, false)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-setstartbeforenode
+    fn SetStartBefore(self, node: JSRef<Node>) -> ErrorResult {
+        let parent = try!(node.parent_node().ok_or(InvalidNodeType)).root();
+        self.set_boundary_point(*parent, node.index(), true)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-setstartafternode
+    fn SetStartAfter(self, node: JSRef<Node>) -> ErrorResult {
+        let parent = try!(node.parent_node().ok_or(InvalidNodeType)).root();
+        self.set_boundary_point(*parent, node.index() + 1, true)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-setendbeforenode
+    fn SetEndBefore(self, node: JSRef<Node>) -> ErrorResult {
+        let parent = try!(node.parent_node().ok_or(InvalidNodeType)).root();
+        self.set_boundary_point(*parent, node.index(), false)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-setendafternode
+    fn SetEndAfter(self, node: JSRef<Node>) -> ErrorResult {
+        let parent = try!(node.parent_node().ok_or(InvalidNodeType)).root();
+        self.set_boundary_point(*parent, node.index() + 1, false)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-collapsetostart
+    fn Collapse(self, to_start: bool) {
+        if to_start {
+            let start = self.StartContainer().root();
+            self.set_end(*start, self.StartOffset());
+        } else {
+            let end = self.EndContainer().root();
+            self.set_start(*end, self.EndOffset());
+        }
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-selectnodenode
+    fn SelectNode(self, node: JSRef<Node>) -> ErrorResult {
+        // Step 1.
+        let parent = try!(node.parent_node().ok_or(InvalidNodeType)).root();
+
+        // Step 2.
+        let index = node.index();
+
+        // Step 3.
+        self.set_start(*parent, index);
+
+        // Step 4.
+        self.set_end(*parent, index + 1);
+        Ok(())
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-selectnodecontentsnode
+    fn SelectNodeContents(self, node: JSRef<Node>) -> ErrorResult {
+        // Step 1.
+        if node.is_doctype() {
+            return Err(InvalidNodeType);
+        }
+
+        // Step 2.
+        let length = node.len();
+
+        // Step 3.
+        self.set_start(node, 0);
+
+        // Step 4.
+        self.set_end(node, length);
+        Ok(())
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-compareboundarypointshow-sourcerange
+    fn CompareBoundaryPoints(self, how: u16, source_range: JSRef<Range>) -> Fallible<i16> {
+        // Step 1.
+        if how > RangeConstants::END_TO_START {
+            return Err(NotSupported);
+        }
+
+        // Step 2.
+        let start = self.StartContainer().root();
+        let source_start = source_range.StartContainer().root();
+        if root_of(*start) != root_of(*source_start) {
+            return Err(WrongDocument);
+        }
+
+        // Step 3-4.
+        let (this_node, this_offset, other_node, other_offset) = match how {
+            RangeConstants::START_TO_START => {
+                (self.StartContainer(), self.StartOffset(),
+                 source_range.StartContainer(), source_range.StartOffset())
+            }
+            RangeConstants::START_TO_END => {
+                (self.EndContainer(), self.EndOffset(),
+                 source_range.StartContainer(), source_range.StartOffset())
+            }
+            RangeConstants::END_TO_END => {
+                (self.EndContainer(), self.EndOffset(),
+                 source_range.EndContainer(), source_range.EndOffset())
+            }
+            _ => {
+                (self.StartContainer(), self.StartOffset(),
+                 source_range.EndContainer(), source_range.EndOffset())
+            }
+        };
+        let this_node = this_node.root();
+        let other_node = other_node.root();
+        Ok(match bp_position(*this_node, this_offset, *other_node, other_offset) {
+            Less => -1,
+            Equal => 0,
+            Greater => 1,
+        })
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-deletecontents
+    fn DeleteContents(self) -> ErrorResult {
+        // Step 1.
+        if self.Collapsed() {
+            return Ok(());
+        }
+
+        // Step 2.
+        let start_node = self.StartContainer().root();
+        let start_offset = self.StartOffset();
+        let end_node = self.EndContainer().root();
+        let end_offset = self.EndOffset();
+
+        // Step 3.
+        if *start_node == *end_node {
+            match CharacterDataCast::to_ref(*start_node) {
+                Some(characterdata) => {
+                    return characterdata.ReplaceData(start_offset, end_offset - start_offset,
+                                                     "".to_string());
+                }
+                None => (),
+            }
+        }
+
+        // Step 4.
+        let common_ancestor = common_ancestor(*start_node, *end_node);
+        let nodes_to_remove: Vec<JSRef<Node>> = common_ancestor.traverse_preorder().filter(|node| {
+            self.contains(*node) &&
+                node.parent_node().root().map_or(true, |parent| !self.contains(*parent))
+        }).collect();
+
+        // Step 5-6.
+        let (new_node, new_offset) = if start_node.is_inclusive_ancestor_of(*end_node) {
+            (Temporary::from_rooted(*start_node), start_offset)
+        } else {
+            let reference_node = start_node.ancestors()
+                                           .take_while(|ancestor| {
+                                               !ancestor.is_inclusive_ancestor_of(*end_node)
+                                           })
+                                           .last()
+                                           .unwrap_or(*start_node);
+            (reference_node.parent_node().unwrap(), reference_node.index() + 1)
+        };
+
+        // Step 7.
+        match CharacterDataCast::to_ref(*start_node) {
+            Some(characterdata) => {
+                try!(characterdata.ReplaceData(start_offset, start_node.len() - start_offset,
+                                               "".to_string()));
+            }
+            None => (),
+        }
+
+        // Step 8.
+        for node in nodes_to_remove.iter() {
+            let parent = node.parent_node().unwrap().root();
+            assert!(parent.RemoveChild(*node).is_ok());
+        }
+
+        // Step 9.
+        match CharacterDataCast::to_ref(*end_node) {
+            Some(characterdata) => {
+                try!(characterdata.ReplaceData(0, end_offset, "".to_string()));
+            }
+            None => (),
+        }
+
+        // Step 10.
+        let new_node = new_node.root();
+        self.set_start(*new_node, new_offset);
+        self.set_end(*new_node, new_offset);
+        Ok(())
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-extractcontents
+    fn ExtractContents(self) -> Fallible<Temporary<DocumentFragment>> {
+        self.extract_or_clone(true)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-clonecontents
+    fn CloneContents(self) -> Fallible<Temporary<DocumentFragment>> {
+        self.extract_or_clone(false)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-insertnodenode
+    fn InsertNode(self, node: JSRef<Node>) -> ErrorResult {
+        let start_node = self.StartContainer().root();
+        let start_offset = self.StartOffset();
+
+        // Step 1.
+        match start_node.type_id() {
+            ProcessingInstructionNodeTypeId | CommentNodeTypeId => return Err(HierarchyRequest),
+            TextNodeTypeId if start_node.parent_node().is_none() => return Err(HierarchyRequest),
+            _ if *start_node == node => return Err(HierarchyRequest),
+            _ => (),
+        }
+
+        // Step 2-4.
+        let reference_node = if start_node.is_text() {
+            Some(Temporary::from_rooted(*start_node))
+        } else {
+            start_node.children().nth(start_offset as uint).map(Temporary::from_rooted)
+        }.root();
+
+        // Step 5.
+        let parent = match reference_node {
+            None => Temporary::from_rooted(*start_node),
+            Some(ref reference_node) => reference_node.parent_node().unwrap(),
+        }.root();
+
+        // Step 6.
+        try!(Node::ensure_pre_insertion_validity(node, *parent, reference_node.root_ref()));
+
+        // Step 7.
+        let reference_node = match TextCast::to_ref(*start_node) {
+            Some(text) => Some(NodeCast::from_temporary(try!(text.SplitText(start_offset)))),
+            None => reference_node.map(|reference_node| Temporary::from_rooted(*reference_node)),
+        }.root();
+
+        // Step 8.
+        let reference_node = if reference_node.root_ref() == Some(node) {
+            node.next_sibling()
+        } else {
+            reference_node.map(|reference_node| Temporary::from_rooted(*reference_node))
+        }.root();
+
+        // Step 9.
+        match node.parent_node().root() {
+            Some(node_parent) => assert!(node_parent.RemoveChild(node).is_ok()),
+            None => (),
+        }
+
+        // Step 10.
+        let new_offset = match reference_node {
+            None => parent.len(),
+            Some(ref reference_node) => reference_node.index(),
+        };
+
+        // Step 11.
+        let new_offset = new_offset + match node.type_id() {
+            DocumentFragmentNodeTypeId => node.len(),
+            _ => 1,
+        };
+
+        // Step 12.
+        try!(parent.InsertBefore(node, reference_node.root_ref()));
+
+        // Step 13.
+        if self.Collapsed() {
+            self.set_end(*parent, new_offset);
+        }
+        Ok(())
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-surroundcontentsnewparent
+    fn SurroundContents(self, new_parent: JSRef<Node>) -> ErrorResult {
+        // Step 1.
+        let start_node = self.StartContainer().root();
+        let end_node = self.EndContainer().root();
+        let common_ancestor = common_ancestor(*start_node, *end_node);
+        if has_non_text_ancestor_below(*start_node, common_ancestor) ||
+           has_non_text_ancestor_below(*end_node, common_ancestor) {
+            return Err(InvalidState);
+        }
+
+        // Step 2.
+        match new_parent.type_id() {
+            DocumentNodeTypeId |
+            DoctypeNodeTypeId |
+            DocumentFragmentNodeTypeId => return Err(InvalidNodeType),
+            _ => (),
+        }
+
+        // Step 3.
+        let fragment = try!(self.ExtractContents()).root();
+
+        // Step 4.
+        if new_parent.HasChildNodes() {
+            Node::replace_all(None, new_parent);
+        }
+
+        // Step 5.
+        try!(self.InsertNode(new_parent));
+
+        // Step 6.
+        let fragment: JSRef<Node> = NodeCast::from_ref(*fragment);
+        try!(new_parent.AppendChild(fragment));
+
+        // Step 7.
+        self.SelectNode(new_parent)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-clonerange
+    fn CloneRange(self) -> Temporary<Range> {
+        let start = self.StartContainer().root();
+        let end = self.EndContainer().root();
+        let document = start.owner_doc().root();
+        Range::new(*document, *start, self.StartOffset(), *end, self.EndOffset())
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-detach
     fn Detach(self) {
         // This method intentionally left blank.
     }
+
+    // http://dom.spec.whatwg.org/#dom-range-ispointinrangenode-offset
+    fn IsPointInRange(self, node: JSRef<Node>, offset: u32) -> Fallible<bool> {
+        let start = self.StartContainer().root();
+        let end = self.EndContainer().root();
+
+        // Step 1.
+        if root_of(node) != root_of(*start) {
+            return Ok(false);
+        }
+
+        // Step 2.
+        if node.is_doctype() {
+            return Err(InvalidNodeType);
+        }
+
+        // Step 3.
+        if offset > node.len() {
+            return Err(IndexSize);
+        }
+
+        // Step 4.
+        if bp_position(node, offset, *start, self.StartOffset()) == Less ||
+           bp_position(node, offset, *end, self.EndOffset()) == Greater {
+            return Ok(false);
+        }
+
+        // Step 5.
+        Ok(true)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-comparepointnode-offset
+    fn ComparePoint(self, node: JSRef<Node>, offset: u32) -> Fallible<i16> {
+        let start = self.StartContainer().root();
+        let end = self.EndContainer().root();
+
+        // Step 1.
+        if root_of(node) != root_of(*start) {
+            return Err(WrongDocument);
+        }
+
+        // Step 2.
+        if node.is_doctype() {
+            return Err(InvalidNodeType);
+        }
+
+        // Step 3.
+        if offset > node.len() {
+            return Err(IndexSize);
+        }
+
+        // Step 4.
+        if bp_position(node, offset, *start, self.StartOffset()) == Less {
+            return Ok(-1);
+        }
+
+        // Step 5.
+        if bp_position(node, offset, *end, self.EndOffset()) == Greater {
+            return Ok(1);
+        }
+
+        // Step 6.
+        Ok(0)
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-intersectsnodenode
+    fn IntersectsNode(self, node: JSRef<Node>) -> bool {
+        let start = self.StartContainer().root();
+        let end = self.EndContainer().root();
+
+        // Step 1.
+        if root_of(node) != root_of(*start) {
+            return false;
+        }
+
+        // Step 2.
+        let parent = match node.parent_node().root() {
+            // Step 3.
+            None => return true,
+            Some(parent) => parent,
+        };
+
+        // Step 4.
+        let offset = node.index();
+
+        // Step 5-6.
+        bp_position(*parent, offset, *end, self.EndOffset()) == Less &&
+            bp_position(*parent, offset + 1, *start, self.StartOffset()) == Greater
+    }
+
+    // http://dom.spec.whatwg.org/#dom-range-stringifier
+    fn Stringifier(self) -> DOMString {
+        let start_node = self.StartContainer().root();
+        let start_offset = self.StartOffset();
+        let end_node = self.EndContainer().root();
+        let end_offset = self.EndOffset();
+
+        // Step 1.
+        let mut s = String::new();
+
+        // Step 2.
+        if *start_node == *end_node && start_node.is_text() {
+            let characterdata: JSRef<CharacterData> =
+                CharacterDataCast::to_ref(*start_node).unwrap();
+            return characterdata.SubstringData(start_offset, end_offset - start_offset).unwrap();
+        }
+
+        // Step 3.
+        if start_node.is_text() {
+            let characterdata: JSRef<CharacterData> =
+                CharacterDataCast::to_ref(*start_node).unwrap();
+            let length = characterdata.Length();
+            s.push_str(characterdata.SubstringData(start_offset, length - start_offset)
+                                    .unwrap().as_slice());
+        }
+
+        // Step 4.
+        let common_ancestor = common_ancestor(*start_node, *end_node);
+        for node in common_ancestor.traverse_preorder() {
+            if node.is_text() && self.contains(node) {
+                let characterdata: JSRef<CharacterData> = CharacterDataCast::to_ref(node).unwrap();
+                s.push_str(characterdata.Data().as_slice());
+            }
+        }
+
+        // Step 5.
+        if end_node.is_text() {
+            let characterdata: JSRef<CharacterData> =
+                CharacterDataCast::to_ref(*end_node).unwrap();
+            s.push_str(characterdata.SubstringData(0, end_offset).unwrap().as_slice());
+        }
+
+        // Step 6.
+        s
+    }
+
+    // http://domparsing.spec.whatwg.org/#dom-range-createcontextualfragment
+    fn CreateContextualFragment(self, fragment: DOMString)
+                                -> Fallible<Temporary<DocumentFragment>> {
+        // Step 1.
+        let node = self.StartContainer().root();
+        let document = node.owner_doc().root();
+        let document_node: JSRef<Node> = NodeCast::from_ref(*document);
+
+        // Step 2.
+        let element = match node.type_id() {
+            DocumentNodeTypeId | DocumentFragmentNodeTypeId => None,
+            _ if node.is_element() => Some(Temporary::from_rooted(*node)),
+            _ => node.parent_node(),
+        }.root();
+
+        // Step 3.
+        let element = match element {
+            Some(ref element) => fragment_parsing_context(**element),
+            None => fragment_parsing_context(document_node),
+        }.root();
+
+        // Step 4.
+        let fragment_node = try!(parse_fragment(*element, fragment)).root();
+
+        // Step 5.
+        let node: JSRef<Node> = NodeCast::from_ref(*fragment_node);
+        for node in node.traverse_preorder() {
+            match HTMLScriptElementCast::to_ref(node) {
+                Some(script) => script.unmark_already_started(),
+                None => (),
+            }
+        }
+
+        // Step 6.
+        Ok(Temporary::from_rooted(*fragment_node))
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-range-getclientrects
+    fn GetClientRects(self) -> Temporary<DOMRectList> {
+        let start = self.StartContainer().root();
+        let window = window_from_node(*start).root();
+        let rects: Vec<Root<DOMRect>> = self.client_rects().iter().map(|r| {
+            DOMRect::new(
+                *window,
+                r.origin.y,
+                r.origin.y + r.size.height,
+                r.origin.x,
+                r.origin.x + r.size.width).root()
+        }).collect();
+
+        DOMRectList::new(*window, rects.iter().map(|rect| rect.deref().clone()).collect())
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-range-getboundingclientrect
+    fn GetBoundingClientRect(self) -> Temporary<DOMRect> {
+        let start = self.StartContainer().root();
+        let window = window_from_node(*start).root();
+        let rect = self.client_rects().iter().fold(None, |union: Option<Rect<Au>>, rect| {
+            Some(union.map_or(*rect, |union| union.union(rect)))
+        }).unwrap_or(Rect::zero());
+        DOMRect::new(
+            *window,
+            rect.origin.y,
+            rect.origin.y + rect.size.height,
+            rect.origin.x,
+            rect.origin.x + rect.size.width)
+    }
 }
 
 impl Reflectable for Range {
@@ -48,3 +898,283 @@ impl Reflectable for Range {
         &self.reflector_
     }
 }
+
+/// The boundary points of a range, shared with the documents whose mutations update it.
+#[jstraceable]
+#[must_root]
+pub struct RangeInner {
+    start: BoundaryPoint,
+    end: BoundaryPoint,
+}
+
+impl RangeInner {
+    fn new(start: BoundaryPoint, end: BoundaryPoint) -> RangeInner {
+        RangeInner {
+            start: start,
+            end: end,
+        }
+    }
+}
+
+// http://dom.spec.whatwg.org/#concept-range-bp
+#[jstraceable]
+#[must_root]
+struct BoundaryPoint {
+    node: Cell<JS<Node>>,
+    offset: Cell<u32>,
+}
+
+impl BoundaryPoint {
+    fn new(node: JSRef<Node>, offset: u32) -> BoundaryPoint {
+        BoundaryPoint {
+            node: Cell::new(JS::from_rooted(node)),
+            offset: Cell::new(offset),
+        }
+    }
+
+    fn node(&self) -> Temporary<Node> {
+        Temporary::new(self.node.get())
+    }
+
+    fn offset(&self) -> u32 {
+        self.offset.get()
+    }
+
+    fn set(&self, node: JSRef<Node>, offset: u32) {
+        self.node.set(JS::from_rooted(node));
+        self.offset.set(offset);
+    }
+
+    fn is_in(&self, node: JSRef<Node>) -> bool {
+        self.node.get() == JS::from_rooted(node)
+    }
+
+    // http://dom.spec.whatwg.org/#concept-node-insert
+    fn increase_above(&self, parent: JSRef<Node>, index: u32, count: u32) {
+        if self.is_in(parent) && self.offset() > index {
+            self.offset.set(self.offset() + count);
+        }
+    }
+
+    // http://dom.spec.whatwg.org/#concept-node-remove
+    fn node_removed(&self, node: JSRef<Node>, parent: JSRef<Node>, index: u32) {
+        let container = self.node().root();
+        if node.is_inclusive_ancestor_of(*container) {
+            self.set(parent, index);
+        } else if *container == parent && self.offset() > index {
+            self.offset.set(self.offset() - 1);
+        }
+    }
+
+    // http://dom.spec.whatwg.org/#concept-cd-replace
+    fn replace_data(&self, node: JSRef<Node>, offset: u32, count: u32, new_length: u32) {
+        if !self.is_in(node) {
+            return;
+        }
+        if self.offset() > offset + count {
+            self.offset.set(self.offset() + new_length - count);
+        } else if self.offset() > offset {
+            self.offset.set(offset);
+        }
+    }
+
+    // http://dom.spec.whatwg.org/#concept-text-split
+    fn split_text(&self, node: JSRef<Node>, offset: u32, new_node: JSRef<Node>,
+                  parent: JSRef<Node>, index: u32) {
+        if self.is_in(node) && self.offset() > offset {
+            self.set(new_node, self.offset() - offset);
+        } else if self.is_in(parent) && self.offset() == index + 1 {
+            self.offset.set(index + 2);
+        }
+    }
+
+    // http://dom.spec.whatwg.org/#dom-node-normalize
+    fn merge_text(&self, node: JSRef<Node>, into: JSRef<Node>, length: u32,
+                  parent: JSRef<Node>, index: u32) {
+        if self.is_in(node) {
+            self.set(into, self.offset() + length);
+        } else if self.is_in(parent) && self.offset() == index {
+            self.set(into, length);
+        }
+    }
+}
+
+/// The live ranges that may have boundary points among the nodes of a document, which the
+/// mutations of these nodes update. Ranges are forgotten once they are garbage collected.
+#[jstraceable]
+pub struct WeakRangeVec {
+    ranges: DOMRefCell<Vec<Weak<RangeInner>>>,
+}
+
+impl WeakRangeVec {
+    pub fn new() -> WeakRangeVec {
+        WeakRangeVec {
+            ranges: DOMRefCell::new(vec!()),
+        }
+    }
+
+    /// Whether no range was ever registered, or all of them were dropped since the last update.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.borrow().is_empty()
+    }
+
+    /// Adds `range`, unless it is already there.
+    fn push(&self, range: &Rc<RangeInner>) {
+        let address = range.deref() as *const RangeInner;
+        let mut is_present = false;
+        self.for_each(|other| is_present = is_present || other as *const RangeInner == address);
+        if !is_present {
+            self.ranges.borrow_mut().push(range.downgrade());
+        }
+    }
+
+    /// Calls `f` on each live range, dropping the ones that are gone.
+    fn for_each(&self, f: |&RangeInner|) {
+        let mut ranges = self.ranges.borrow_mut();
+        let mut i = 0;
+        while i < ranges.len() {
+            match (*ranges)[i].upgrade() {
+                Some(range) => {
+                    f(&*range);
+                    i += 1;
+                }
+                None => {
+                    ranges.swap_remove(i);
+                }
+            }
+        }
+    }
+
+    /// Updates the ranges after `count` nodes are inserted in `parent` at `index`.
+    pub fn increase_above(&self, parent: JSRef<Node>, index: u32, count: u32) {
+        self.for_each(|range| {
+            range.start.increase_above(parent, index, count);
+            range.end.increase_above(parent, index, count);
+        });
+    }
+
+    /// Updates the ranges before `node` is removed from `parent`, where it is at `index`.
+    pub fn node_removed(&self, node: JSRef<Node>, parent: JSRef<Node>, index: u32) {
+        self.for_each(|range| {
+            range.start.node_removed(node, parent, index);
+            range.end.node_removed(node, parent, index);
+        });
+    }
+
+    /// Updates the ranges after `count` code units of the data of `node` from `offset` were
+    /// replaced by `new_length` others.
+    pub fn replace_data(&self, node: JSRef<Node>, offset: u32, count: u32, new_length: u32) {
+        self.for_each(|range| {
+            range.start.replace_data(node, offset, count, new_length);
+            range.end.replace_data(node, offset, count, new_length);
+        });
+    }
+
+    /// Updates the ranges after the data of `node` from `offset` was copied into `new_node`,
+    /// which was inserted as its next sibling.
+    pub fn split_text(&self, node: JSRef<Node>, offset: u32, new_node: JSRef<Node>) {
+        let parent = node.parent_node().unwrap().root();
+        let index = node.index();
+        self.for_each(|range| {
+            range.start.split_text(node, offset, new_node, *parent, index);
+            range.end.split_text(node, offset, new_node, *parent, index);
+        });
+    }
+
+    /// Updates the ranges before the text `node` is removed, after its data was appended to
+    /// the one of `into`, which was `length` code units long.
+    pub fn merge_text(&self, node: JSRef<Node>, into: JSRef<Node>, length: u32) {
+        let parent = node.parent_node().unwrap().root();
+        let index = node.index();
+        self.for_each(|range| {
+            range.start.merge_text(node, into, length, *parent, index);
+            range.end.merge_text(node, into, length, *parent, index);
+        });
+    }
+}
+
+/// Returns the index of the character of `data` at the given offset in UTF-16 code units, which is
+/// how range offsets count. An offset in the middle of a surrogate pair counts the whole pair as
+/// before it, and an offset past the end gives the number of characters.
+fn char_index(data: &str, offset: u32) -> uint {
+    let mut code_units = 0;
+    for (index, c) in data.chars().enumerate() {
+        if code_units >= offset {
+            return index;
+        }
+        code_units += if c as u32 > 0xFFFF { 2 } else { 1 };
+    }
+    data.char_len()
+}
+
+/// http://dom.spec.whatwg.org/#concept-tree-root
+fn root_of<'a>(node: JSRef<'a, Node>) -> JSRef<'a, Node> {
+    node.ancestors().last().unwrap_or(node)
+}
+
+/// The inclusive ancestor of `node` that is the closest inclusive ancestor of `other`.
+fn common_ancestor<'a>(node: JSRef<'a, Node>, other: JSRef<'a, Node>) -> JSRef<'a, Node> {
+    if node.is_inclusive_ancestor_of(other) {
+        return node;
+    }
+    node.ancestors().find(|ancestor| ancestor.is_inclusive_ancestor_of(other)).unwrap()
+}
+
+/// Whether an inclusive ancestor of `node` that is a descendant of `ancestor` is not a `Text`.
+fn has_non_text_ancestor_below(node: JSRef<Node>, ancestor: JSRef<Node>) -> bool {
+    node != ancestor &&
+        (!node.is_text() ||
+         node.ancestors().take_while(|other| *other != ancestor).any(|other| !other.is_text()))
+}
+
+// http://dom.spec.whatwg.org/#concept-range-bp-position
+fn bp_position(node: JSRef<Node>, offset: u32, other: JSRef<Node>, other_offset: u32)
+               -> Ordering {
+    // Step 1.
+    assert!(root_of(node) == root_of(other));
+
+    // Step 2.
+    if node == other {
+        return offset.cmp(&other_offset);
+    }
+
+    // Step 3.
+    let position = other.CompareDocumentPosition(node);
+    if position & NodeConstants::DOCUMENT_POSITION_FOLLOWING != 0 {
+        return match bp_position(other, other_offset, node, offset) {
+            Less => Greater,
+            Equal => Equal,
+            Greater => Less,
+        };
+    }
+
+    // Step 4.
+    if node.is_inclusive_ancestor_of(other) {
+        let child = other.ancestors()
+                         .take_while(|ancestor| *ancestor != node)
+                         .last()
+                         .unwrap_or(other);
+        if child.index() < offset {
+            return Greater;
+        }
+    }
+
+    // Step 5.
+    Less
+}
+
+/// Appends to `fragment` a clone of `node` holding `count` code units of its data from
+/// `offset`, which are also removed from `node` when extracting.
+fn clone_character_data(node: JSRef<CharacterData>, offset: u32, count: u32,
+                        fragment: JSRef<Node>, extract: bool) -> ErrorResult {
+    let data = try!(node.SubstringData(offset, count));
+    let node_: JSRef<Node> = NodeCast::from_ref(node);
+    let clone = Node::clone(node_, None, DoNotCloneChildren).root();
+    let characterdata: JSRef<CharacterData> = CharacterDataCast::to_ref(*clone).unwrap();
+    try!(characterdata.SetData(data));
+    assert!(fragment.AppendChild(*clone).is_ok());
+    if extract {
+        try!(node.ReplaceData(offset, count, "".to_string()));
+    }
+    Ok(())
+}
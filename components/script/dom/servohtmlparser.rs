@@ -7,12 +7,15 @@
 
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::ServoHTMLParserBinding;
+use dom::bindings::codegen::InheritTypes::NodeCast;
 use dom::bindings::global;
 use dom::bindings::trace::JSTraceable;
 use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
-use dom::node::TrustedNodeAddress;
+use dom::element::Element;
+use dom::node::{Node, NodeHelpers, TrustedNodeAddress};
 use dom::document::{Document, DocumentHelpers};
+use dom::window::Window;
 use parse::Parser;
 
 use servo_util::task_state;
@@ -21,6 +24,7 @@ use std::default::Default;
 use url::Url;
 use js::jsapi::JSTracer;
 use html5ever::tokenizer;
+use html5ever::tokenizer::TokenizerOpts;
 use html5ever::tree_builder;
 use html5ever::tree_builder::{TreeBuilder, TreeBuilderOpts};
 
@@ -67,12 +71,53 @@ impl ServoHTMLParser {
 
         let tok = tokenizer::Tokenizer::new(tb, Default::default());
 
+        ServoHTMLParser::new_with_tokenizer(*window, tok)
+    }
+
+    /// Creates a parser for the HTML fragment parsing algorithm, which builds the nodes of the
+    /// fragment into `document` as if they were the children of `context`.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/syntax.html#parsing-html-fragments>
+    #[allow(unrooted_must_root)]
+    pub fn new_for_fragment(base_url: Option<Url>, document: JSRef<Document>,
+                            context: JSRef<Element>, form: Option<JSRef<Element>>)
+                            -> Temporary<ServoHTMLParser> {
+        let window = document.window().root();
+        let sink = Sink {
+            base_url: base_url,
+            document: JS::from_rooted(document),
+        };
+
+        let context: JSRef<Node> = NodeCast::from_ref(context);
+        let form = form.map(|form| {
+            let form: JSRef<Node> = NodeCast::from_ref(form);
+            form.to_trusted_node_address()
+        });
+        let tb = TreeBuilder::new_for_fragment(sink, context.to_trusted_node_address(), form,
+                                               TreeBuilderOpts {
+            ignore_missing_rules: true,
+            .. Default::default()
+        });
+
+        // Step 4 of the fragment parsing algorithm: the tokenizer starts in the state given by
+        // the context element.
+        let tok_opts = TokenizerOpts {
+            initial_state: Some(tb.tokenizer_state_for_context_elem()),
+            .. Default::default()
+        };
+        let tok = tokenizer::Tokenizer::new(tb, tok_opts);
+
+        ServoHTMLParser::new_with_tokenizer(*window, tok)
+    }
+
+    #[allow(unrooted_must_root)]
+    fn new_with_tokenizer(window: JSRef<Window>, tok: Tokenizer) -> Temporary<ServoHTMLParser> {
         let parser = ServoHTMLParser {
             reflector_: Reflector::new(),
             tokenizer: DOMRefCell::new(tok),
         };
 
-        reflect_dom_object(box parser, global::Window(*window), ServoHTMLParserBinding::Wrap)
+        reflect_dom_object(box parser, global::Window(window), ServoHTMLParserBinding::Wrap)
     }
 
     #[inline]
@@ -10,7 +10,7 @@ use dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use dom::bindings::codegen::UnionTypes::BlobOrString::BlobOrString;
 use dom::bindings::codegen::UnionTypes::EventOrString::{EventOrString, eString};
 use dom::bindings::codegen::UnionTypes::HTMLElementOrLong::{HTMLElementOrLong, eLong};
-use dom::bindings::global::GlobalField;
+use dom::bindings::global::{GlobalField, GlobalRef};
 use dom::bindings::js::{JSRef, Temporary};
 use dom::bindings::str::ByteString;
 use dom::bindings::utils::{Reflector, Reflectable};
@@ -299,9 +299,9 @@ impl<'a> TestBindingMethods for JSRef<'a, TestBinding> {
 }
 
 impl TestBinding {
-    pub fn BooleanAttributeStatic() -> bool { false }
-    pub fn SetBooleanAttributeStatic(_: bool) {}
-    pub fn ReceiveVoidStatic() {}
+    pub fn BooleanAttributeStatic(_: &GlobalRef) -> bool { false }
+    pub fn SetBooleanAttributeStatic(_: &GlobalRef, _: bool) {}
+    pub fn ReceiveVoidStatic(_: &GlobalRef) {}
 }
 
 impl Reflectable for TestBinding {
@@ -2,17 +2,20 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::codegen::Bindings::CharacterDataBinding::CharacterDataMethods;
+use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
 use dom::bindings::codegen::Bindings::TextBinding;
+use dom::bindings::codegen::Bindings::TextBinding::TextMethods;
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
-use dom::bindings::codegen::InheritTypes::TextDerived;
-use dom::bindings::error::Fallible;
+use dom::bindings::codegen::InheritTypes::{CharacterDataCast, NodeCast, TextDerived};
+use dom::bindings::error::{Fallible, IndexSize};
 use dom::bindings::global::GlobalRef;
-use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::js::{JSRef, OptionalRootable, RootedReference, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::characterdata::CharacterData;
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
-use dom::node::{Node, TextNodeTypeId};
+use dom::node::{Node, NodeHelpers, TextNodeTypeId};
 use servo_util::str::DOMString;
 
 /// An HTML text node.
@@ -50,6 +53,54 @@ impl Text {
     }
 }
 
+impl<'a> TextMethods for JSRef<'a, Text> {
+    // http://dom.spec.whatwg.org/#dom-text-splittext
+    fn SplitText(self, offset: u32) -> Fallible<Temporary<Text>> {
+        let characterdata: JSRef<CharacterData> = CharacterDataCast::from_ref(self);
+
+        // Step 1.
+        let length = characterdata.Length();
+
+        // Step 2.
+        if offset > length {
+            return Err(IndexSize);
+        }
+
+        // Step 3.
+        let count = length - offset;
+
+        // Step 4.
+        let new_data = try!(characterdata.SubstringData(offset, count));
+
+        // Step 5.
+        let node: JSRef<Node> = NodeCast::from_ref(self);
+        let document = node.owner_doc().root();
+        let new_text = Text::new(new_data, *document).root();
+        let new_node: JSRef<Node> = NodeCast::from_ref(*new_text);
+
+        // Step 6.
+        match node.parent_node().root() {
+            Some(parent) => {
+                // Step 7.1.
+                let next_sibling = node.next_sibling().root();
+                assert!(parent.InsertBefore(new_node, next_sibling.root_ref()).is_ok());
+
+                // Step 7.2-7.5.
+                document.ranges().split_text(node, offset, new_node);
+            }
+            None => (),
+        }
+
+        // Step 8.
+        try!(characterdata.ReplaceData(offset, count, "".to_string()));
+
+        // Step 9 is taken care of by the replace data algorithm.
+
+        // Step 10.
+        Ok(Temporary::from_rooted(*new_text))
+    }
+}
+
 impl Reflectable for Text {
     fn reflector<'a>(&'a self) -> &'a Reflector {
         self.characterdata.reflector()
@@ -0,0 +1,41 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::TextMetricsBinding;
+use dom::bindings::codegen::Bindings::TextMetricsBinding::TextMetricsMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+
+#[dom_struct]
+pub struct TextMetrics {
+    reflector_: Reflector,
+    width: f64,
+}
+
+impl TextMetrics {
+    fn new_inherited(width: f64) -> TextMetrics {
+        TextMetrics {
+            reflector_: Reflector::new(),
+            width: width,
+        }
+    }
+
+    pub fn new(global: GlobalRef, width: f64) -> Temporary<TextMetrics> {
+        reflect_dom_object(box TextMetrics::new_inherited(width),
+                           global, TextMetricsBinding::Wrap)
+    }
+}
+
+impl<'a> TextMetricsMethods for JSRef<'a, TextMetrics> {
+    fn Width(self) -> f64 {
+        self.width
+    }
+}
+
+impl Reflectable for TextMetrics {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
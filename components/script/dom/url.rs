@@ -0,0 +1,93 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::JSRef;
+use dom::bindings::utils::{Reflectable, Reflector};
+use dom::blob::Blob;
+use dom::urlhelper::UrlHelper;
+
+use servo_net::resource_task::{RegisterBlobURL, ResourceTask, RevokeBlobURL};
+use servo_util::str::DOMString;
+
+use std::mem;
+use url::{Url, NonRelativeSchemeData};
+use uuid::Uuid;
+
+#[dom_struct]
+pub struct URL {
+    reflector_: Reflector,
+}
+
+/// The blob URLs created by a global object, which are revoked when it is unloaded.
+/// http://dev.w3.org/2006/webapi/FileAPI/#lifeTime
+#[jstraceable]
+#[privatize]
+pub struct BlobURLList {
+    urls: DOMRefCell<Vec<Url>>,
+}
+
+impl BlobURLList {
+    pub fn new() -> BlobURLList {
+        BlobURLList {
+            urls: DOMRefCell::new(vec!()),
+        }
+    }
+
+    pub fn add(&self, url: Url) {
+        self.urls.borrow_mut().push(url);
+    }
+
+    pub fn remove(&self, url: &Url) {
+        self.urls.borrow_mut().retain(|existing| existing != url);
+    }
+
+    /// Revokes every blob URL in the list.
+    pub fn revoke_all(&self, resource_task: &ResourceTask) {
+        let urls = mem::replace(&mut *self.urls.borrow_mut(), vec!());
+        for url in urls.into_iter() {
+            resource_task.send(RevokeBlobURL(url));
+        }
+    }
+}
+
+impl URL {
+    // http://dev.w3.org/2006/webapi/FileAPI/#dfn-createObjectURL
+    pub fn CreateObjectURL(global: &GlobalRef, blob: JSRef<Blob>) -> DOMString {
+        let origin = UrlHelper::Origin(&global.get_url());
+        let url = format!("blob:{}/{}", origin, Uuid::new_v4().to_hyphenated_string());
+        let parsed_url = Url::parse(url.as_slice()).unwrap();
+        global.blob_urls().add(parsed_url.clone());
+        global.resource_task().send(RegisterBlobURL(parsed_url,
+                                                    blob.bytes().to_vec(),
+                                                    blob.content_type().clone()));
+        url
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#dfn-revokeObjectURL
+    pub fn RevokeObjectURL(global: &GlobalRef, url: DOMString) {
+        let url = match Url::parse(url.as_slice()) {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        // Only the origin that created a blob URL may revoke it.
+        let mut prefix = UrlHelper::Origin(&global.get_url());
+        prefix.push_str("/");
+        let same_origin = match url.scheme_data {
+            NonRelativeSchemeData(ref data) => data.as_slice().starts_with(prefix.as_slice()),
+            _ => false,
+        };
+        if url.scheme.as_slice() == "blob" && same_origin {
+            global.blob_urls().remove(&url);
+            global.resource_task().send(RevokeBlobURL(url));
+        }
+    }
+}
+
+impl Reflectable for URL {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -28,6 +28,15 @@ impl UrlHelper {
         }
     }
 
+    /// https://html.spec.whatwg.org/multipage/browsers.html#ascii-serialisation-of-an-origin
+    pub fn Origin(url: &Url) -> DOMString {
+        let mut origin = url.scheme.clone();
+        origin.push_str("://");
+        url.serialize_host().map(|ref host| origin.push_str(host.as_slice()));
+        url.port().map(|port| origin.push_str(format!(":{}", port).as_slice()));
+        origin
+    }
+
     /// https://html.spec.whatwg.org/multipage/browsers.html#same-origin
     pub fn SameOrigin(urlA: &Url, urlB: &Url) -> bool {
         if urlA.host() != urlB.host() {
@@ -2,37 +2,264 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::codegen::Bindings::EventTargetBinding::EventTargetMethods;
 use dom::bindings::codegen::Bindings::WebSocketBinding;
+use dom::bindings::codegen::Bindings::WebSocketBinding::{BinaryType, BinaryTypeValues};
 use dom::bindings::codegen::Bindings::WebSocketBinding::WebSocketMethods;
-use dom::bindings::error::Fallible;
-use dom::bindings::global::GlobalRef;
-use dom::bindings::js::{Temporary, JSRef};
+use dom::bindings::codegen::InheritTypes::{EventCast, EventTargetCast};
+use dom::bindings::codegen::UnionTypes::StringOrBlobOrArrayBufferOrArrayBufferView::StringOrBlobOrArrayBufferOrArrayBufferView;
+use dom::bindings::codegen::UnionTypes::StringOrBlobOrArrayBufferOrArrayBufferView::{eString, eBlob};
+use dom::bindings::codegen::UnionTypes::StringOrBlobOrArrayBufferOrArrayBufferView::{eArrayBuffer, eArrayBufferView};
+use dom::bindings::conversions::ToJSValConvertible;
+use dom::bindings::error::{ErrorResult, Fallible, InvalidAccess, InvalidState, Syntax};
+use dom::bindings::global::{GlobalRef, GlobalField};
+use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::bindings::utils::{array_buffer_contents, array_buffer_view_contents};
+use dom::blob::Blob;
+use dom::closeevent::CloseEvent;
+use dom::event::{Event, DoesNotBubble, NotCancelable};
 use dom::eventtarget::{EventTarget, WebSocketTypeId};
+use dom::messageevent::MessageEvent;
+use dom::urlhelper::UrlHelper;
+use script_task::{ScriptChan, WebSocketEventMsg};
+
+use servo_net::websocket;
+use servo_net::websocket::{WebSocketEvent, WebSocketControlMsg, SendTextMsg, SendBinaryMsg};
+use servo_net::websocket::{CloseMsg, OpenedEvent, TextMessageEvent, BinaryMessageEvent};
+use servo_net::websocket::{BytesSentEvent, FailedEvent, ClosedEvent, ABNORMAL_CLOSURE};
 use servo_util::str::DOMString;
+use servo_util::task::spawn_named;
+
+use js::jsapi::{JS_AddObjectRoot, JS_RemoveObjectRoot};
+use js::jsfriendapi::bindgen::{JS_NewArrayBuffer, JS_GetArrayBufferData};
+use js::jsval::JSVal;
+use libc::c_void;
+use std::cell::Cell;
+use std::ptr;
+use url::{Url, UrlParser};
+
+pub struct TrustedWebSocketAddress(pub *const c_void);
+no_jsmanaged_fields!(TrustedWebSocketAddress)
+
+#[deriving(PartialEq)]
+#[jstraceable]
+enum WebSocketReadyState {
+    Connecting = 0,
+    Open = 1,
+    Closing = 2,
+    Closed = 3,
+}
 
 #[dom_struct]
 pub struct WebSocket {
     eventtarget: EventTarget,
-    url: DOMString
+    url: Url,
+    global: GlobalField,
+    ready_state: Cell<WebSocketReadyState>,
+    buffered_amount: Cell<u32>,
+    protocol: DOMRefCell<DOMString>,
+    binary_type: Cell<BinaryType>,
+    /// Whether the error event has been fired, in which case the close event reports an
+    /// abnormal closure.
+    failed: Cell<bool>,
+    sender: Sender<WebSocketControlMsg>,
 }
 
 impl WebSocket {
-    pub fn new_inherited(url: DOMString) -> WebSocket {
+    fn new_inherited(global: &GlobalRef, url: Url, sender: Sender<WebSocketControlMsg>)
+                     -> WebSocket {
         WebSocket {
             eventtarget: EventTarget::new_inherited(WebSocketTypeId),
-            url: url
+            url: url,
+            global: GlobalField::from_rooted(global),
+            ready_state: Cell::new(Connecting),
+            buffered_amount: Cell::new(0),
+            protocol: DOMRefCell::new("".to_string()),
+            binary_type: Cell::new(BinaryTypeValues::Blob),
+            failed: Cell::new(false),
+            sender: sender,
         }
     }
 
-    pub fn new(global: GlobalRef, url: DOMString) -> Temporary<WebSocket> {
-        reflect_dom_object(box WebSocket::new_inherited(url),
-                           global,
+    fn new(global: &GlobalRef, url: Url, sender: Sender<WebSocketControlMsg>)
+           -> Temporary<WebSocket> {
+        reflect_dom_object(box WebSocket::new_inherited(global, url, sender),
+                           *global,
                            WebSocketBinding::Wrap)
     }
 
-    pub fn Constructor(global: &GlobalRef, url: DOMString) -> Fallible<Temporary<WebSocket>> {
-        Ok(WebSocket::new(*global, url))
+    // https://html.spec.whatwg.org/multipage/comms.html#dom-websocket
+    pub fn Constructor(global: &GlobalRef, url: DOMString, protocols: Option<DOMString>)
+                       -> Fallible<Temporary<WebSocket>> {
+        // Steps 1-4.
+        let url = match UrlParser::new().parse(url.as_slice()) {
+            Ok(url) => url,
+            Err(_) => return Err(Syntax),
+        };
+        match url.scheme.as_slice() {
+            "ws" | "wss" => {}
+            _ => return Err(Syntax),
+        }
+        if url.fragment.is_some() {
+            return Err(Syntax)
+        }
+
+        // Steps 5-6.
+        // FIXME: Only a single subprotocol can be requested until the bindings support
+        // sequence arguments.
+        match protocols {
+            Some(ref protocol) if !is_valid_protocol(protocol.as_slice()) => return Err(Syntax),
+            _ => {}
+        }
+
+        // Step 7.
+        let origin = UrlHelper::Origin(&global.get_url());
+        let (sender, receiver) = websocket::connect(url.clone(), protocols, origin);
+        let ws = WebSocket::new(global, url, sender).root();
+
+        // Keep the object alive until the connection closes, since events can still be fired
+        // at it. The address is released when the ClosedEvent is handled, which is always the
+        // last event of a connection.
+        let address = unsafe { ws.to_trusted() };
+        let ScriptChan(chan) = global.script_chan().clone();
+        spawn_named("WebSocketTask", proc() {
+            for event in receiver.iter() {
+                chan.send(WebSocketEventMsg(address, event));
+            }
+        });
+
+        Ok(Temporary::from_rooted(*ws))
+    }
+
+    pub fn handle_event(address: TrustedWebSocketAddress, event: WebSocketEvent) {
+        let ws = unsafe { JS::from_trusted_websocket_address(address).root() };
+        ws.process_event(event);
+    }
+}
+
+/// Whether the string is a valid subprotocol name, i.e. an HTTP token.
+fn is_valid_protocol(protocol: &str) -> bool {
+    !protocol.is_empty() && protocol.chars().all(|c| {
+        c > ' ' && c <= '~' && !"()<>@,;:\\\"/[]?={}".contains_char(c)
+    })
+}
+
+/// Returns the message that sends the given data, and its length.
+fn binary_message(bytes: Vec<u8>) -> (WebSocketControlMsg, uint) {
+    let length = bytes.len();
+    (SendBinaryMsg(bytes), length)
+}
+
+trait PrivateWebSocketHelpers {
+    unsafe fn to_trusted(self) -> TrustedWebSocketAddress;
+    fn release(self);
+    fn process_event(self, event: WebSocketEvent);
+    fn dispatch_simple_event(self, type_: &str);
+    fn fail(self);
+    fn message_data(self, event: WebSocketEvent) -> JSVal;
+}
+
+impl<'a> PrivateWebSocketHelpers for JSRef<'a, WebSocket> {
+    // Creates a trusted address to the object, and roots it. Always pair this with a release()
+    unsafe fn to_trusted(self) -> TrustedWebSocketAddress {
+        JS_AddObjectRoot(self.global.root().root_ref().get_cx(), self.reflector().rootable());
+        TrustedWebSocketAddress(self.deref() as *const WebSocket as *const c_void)
+    }
+
+    fn release(self) {
+        unsafe {
+            JS_RemoveObjectRoot(self.global.root().root_ref().get_cx(),
+                                self.reflector().rootable());
+        }
+    }
+
+    fn process_event(self, event: WebSocketEvent) {
+        match event {
+            OpenedEvent(protocol) => {
+                // If close() was called while connecting, the connection is failed instead.
+                if self.ready_state.get() != Connecting {
+                    return self.fail()
+                }
+                self.ready_state.set(Open);
+                *self.protocol.borrow_mut() = protocol.unwrap_or("".to_string());
+                self.dispatch_simple_event("open");
+            }
+            TextMessageEvent(..) | BinaryMessageEvent(..) => {
+                if self.ready_state.get() != Open {
+                    return
+                }
+                let data = self.message_data(event);
+                let global = self.global.root();
+                let message = MessageEvent::new(global.root_ref(), "message".to_string(),
+                                                false, false, data,
+                                                UrlHelper::Origin(&self.url),
+                                                "".to_string(), None).root();
+                let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+                let event: JSRef<Event> = EventCast::from_ref(*message);
+                let _ = target.DispatchEvent(event);
+            }
+            BytesSentEvent(length) => {
+                self.buffered_amount.set(self.buffered_amount.get() - length as u32);
+            }
+            FailedEvent => self.fail(),
+            ClosedEvent(clean, code, reason) => {
+                self.ready_state.set(Closed);
+                let (clean, code, reason) = if self.failed.get() {
+                    (false, ABNORMAL_CLOSURE, "".to_string())
+                } else {
+                    (clean, code, reason)
+                };
+                let global = self.global.root();
+                let close = CloseEvent::new(global.root_ref(), "close".to_string(),
+                                            false, false, clean, code, reason).root();
+                let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+                let event: JSRef<Event> = EventCast::from_ref(*close);
+                let _ = target.DispatchEvent(event);
+                self.release();
+            }
+        }
+    }
+
+    fn dispatch_simple_event(self, type_: &str) {
+        let global = self.global.root();
+        let event = Event::new(global.root_ref(), type_.to_string(),
+                               DoesNotBubble, NotCancelable).root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        let _ = target.DispatchEvent(*event);
+    }
+
+    // https://html.spec.whatwg.org/multipage/comms.html#fail-the-websocket-connection
+    fn fail(self) {
+        if self.failed.get() {
+            return
+        }
+        self.failed.set(true);
+        self.ready_state.set(Closing);
+        self.dispatch_simple_event("error");
+    }
+
+    /// Converts a received message to the `data` of its message event.
+    fn message_data(self, event: WebSocketEvent) -> JSVal {
+        let global = self.global.root();
+        let cx = global.root_ref().get_cx();
+        match event {
+            TextMessageEvent(text) => text.to_jsval(cx),
+            BinaryMessageEvent(data) => match self.binary_type.get() {
+                BinaryTypeValues::Blob => {
+                    Blob::new_with_bytes(global.root_ref(), data).root().to_jsval(cx)
+                }
+                BinaryTypeValues::Arraybuffer => unsafe {
+                    let buffer = JS_NewArrayBuffer(cx, data.len() as u32);
+                    let pointer = JS_GetArrayBufferData(buffer, cx);
+                    ptr::copy_nonoverlapping_memory(pointer, data.as_ptr(), data.len());
+                    buffer.to_jsval(cx)
+                },
+            },
+            _ => unreachable!(),
+        }
     }
 }
 
@@ -44,6 +271,94 @@ impl Reflectable for WebSocket {
 
 impl<'a> WebSocketMethods for JSRef<'a, WebSocket> {
     fn Url(self) -> DOMString {
-        self.url.clone()
+        self.url.serialize()
+    }
+
+    fn ReadyState(self) -> u16 {
+        self.ready_state.get() as u16
+    }
+
+    fn BufferedAmount(self) -> u32 {
+        self.buffered_amount.get()
+    }
+
+    event_handler!(open, GetOnopen, SetOnopen)
+    event_handler!(error, GetOnerror, SetOnerror)
+    event_handler!(close, GetOnclose, SetOnclose)
+    event_handler!(message, GetOnmessage, SetOnmessage)
+
+    fn Extensions(self) -> DOMString {
+        // No extensions are ever negotiated.
+        "".to_string()
+    }
+
+    fn Protocol(self) -> DOMString {
+        self.protocol.borrow().clone()
+    }
+
+    // https://html.spec.whatwg.org/multipage/comms.html#dom-websocket-close
+    fn Close(self, code: Option<u16>, reason: Option<DOMString>) -> ErrorResult {
+        // Step 1.
+        match code {
+            Some(code) if code != 1000 && (code < 3000 || code > 4999) => {
+                return Err(InvalidAccess)
+            }
+            _ => {}
+        }
+
+        // Step 2.
+        let reason = reason.unwrap_or("".to_string());
+        if reason.len() > 123 {
+            return Err(Syntax)
+        }
+
+        // Step 3.
+        match self.ready_state.get() {
+            Closing | Closed => {}
+            Connecting => {
+                // The connection is failed once it opens, and then closed without a status code.
+                self.ready_state.set(Closing);
+                let _ = self.sender.send_opt(CloseMsg(None, "".to_string()));
+            }
+            Open => {
+                self.ready_state.set(Closing);
+                let _ = self.sender.send_opt(CloseMsg(code, reason));
+            }
+        }
+        Ok(())
+    }
+
+    fn BinaryType(self) -> BinaryType {
+        self.binary_type.get()
+    }
+
+    fn SetBinaryType(self, binary_type: BinaryType) {
+        self.binary_type.set(binary_type)
+    }
+
+    // https://html.spec.whatwg.org/multipage/comms.html#dom-websocket-send
+    fn Send(self, data: StringOrBlobOrArrayBufferOrArrayBufferView) -> ErrorResult {
+        if self.ready_state.get() == Connecting {
+            return Err(InvalidState)
+        }
+
+        let global = self.global.root();
+        let cx = global.root_ref().get_cx();
+        let (message, length) = match data {
+            eString(text) => {
+                let length = text.len();
+                (SendTextMsg(text), length)
+            }
+            eBlob(blob) => binary_message(blob.root().bytes().to_vec()),
+            eArrayBuffer(buffer) => binary_message(array_buffer_contents(cx, buffer)),
+            eArrayBufferView(view) => binary_message(array_buffer_view_contents(cx, view)),
+        };
+
+        // Data sent after the closing handshake started is discarded, but still counted.
+        self.buffered_amount.set(self.buffered_amount.get() + length as u32);
+        if self.ready_state.get() == Open {
+            let _ = self.sender.send_opt(message);
+        }
+        Ok(())
     }
 }
@@ -6,21 +6,27 @@ use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::EventHandlerBinding::{OnErrorEventHandlerNonNull, EventHandlerNonNull};
 use dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use dom::bindings::codegen::Bindings::WindowBinding;
-use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::{FrameRequestCallback, WindowMethods};
 use dom::bindings::codegen::InheritTypes::EventTargetCast;
-use dom::bindings::error::{Fallible, InvalidCharacter};
+use dom::bindings::error::{ErrorResult, Fallible, DataClone, InvalidCharacter, Syntax};
 use dom::bindings::global;
-use dom::bindings::js::{MutNullableJS, JSRef, Temporary, OptionalSettable};
+use dom::bindings::global::global_object_for_js_object;
+use dom::bindings::js::{JS, MutNullableJS, JSRef, Temporary, OptionalSettable};
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::browsercontext::BrowserContext;
 use dom::console::Console;
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::eventtarget::{EventTarget, WindowTypeId, EventTargetHelpers};
+use dom::history::History;
 use dom::location::Location;
+use dom::messageport::{MessagePort, transfer_ports};
+use dom::mutationobserver::{MutationObserver, MutationObserverHelpers};
 use dom::navigator::Navigator;
 use dom::performance::Performance;
 use dom::screen::Screen;
 use dom::storage::Storage;
+use dom::url::BlobURLList;
+use dom::urlhelper::UrlHelper;
 use layout_interface::NoQuery;
 use page::Page;
 use script_task::{ExitWindowMsg, ScriptChan, TriggerLoadMsg, TriggerFragmentMsg};
@@ -28,14 +34,17 @@ use script_task::FromWindow;
 use script_traits::ScriptControlChan;
 use timers::{Interval, NonInterval, TimerId, TimerManager};
 
+use gfx::font_cache_task::FontCacheTask;
 use servo_msg::compositor_msg::ScriptListener;
-use servo_msg::constellation_msg::LoadData;
+use servo_msg::constellation_msg::{ConstellationChan, LoadData, PipelineId, PostMessageMsg};
+use servo_msg::constellation_msg::PostedMessage;
 use servo_net::image_cache_task::ImageCacheTask;
 use servo_net::storage_task::StorageTask;
 use servo_util::str::{DOMString,HTML_SPACE_CHARACTERS};
 
 use js::jsapi::JS_EvaluateUCScript;
 use js::jsapi::JSContext;
+use js::jsapi::{JS_ClearPendingException, JS_GetGlobalForScopeChain, JS_WriteStructuredClone};
 use js::jsapi::{JS_GC, JS_GetRuntime};
 use js::jsval::{JSVal, UndefinedValue};
 use js::rust::with_compartment;
@@ -43,11 +52,18 @@ use url::{Url, UrlParser};
 
 use libc;
 use serialize::base64::{FromBase64, ToBase64, STANDARD};
+use std::any::Any;
 use std::cell::{Ref, RefMut};
 use std::default::Default;
+use std::ptr;
 use std::rc::Rc;
+use std::slice::raw::buf_as_slice;
 use time;
 
+/// Set while the script task performs a microtask checkpoint, so that the callbacks it runs don't
+/// start another one.
+local_data_key!(PerformingMicrotaskCheckpoint: ())
+
 #[dom_struct]
 pub struct Window {
     eventtarget: EventTarget,
@@ -55,8 +71,10 @@ pub struct Window {
     control_chan: ScriptControlChan,
     console: MutNullableJS<Console>,
     location: MutNullableJS<Location>,
+    history: MutNullableJS<History>,
     navigator: MutNullableJS<Navigator>,
     image_cache_task: ImageCacheTask,
+    font_cache_task: FontCacheTask,
     compositor: DOMRefCell<Box<ScriptListener+'static>>,
     browser_context: DOMRefCell<Option<BrowserContext>>,
     page: Rc<Page>,
@@ -65,7 +83,10 @@ pub struct Window {
     navigation_start_precise: f64,
     screen: MutNullableJS<Screen>,
     session_storage: MutNullableJS<Storage>,
-    timers: TimerManager
+    timers: TimerManager,
+    blob_urls: BlobURLList,
+    /// The mutation observers with queued records, in the order they should be notified.
+    mutation_observers: DOMRefCell<Vec<JS<MutationObserver>>>,
 }
 
 impl Window {
@@ -86,6 +107,10 @@ impl Window {
         &self.image_cache_task
     }
 
+    pub fn font_cache_task<'a>(&'a self) -> &'a FontCacheTask {
+        &self.font_cache_task
+    }
+
     pub fn compositor(&self) -> RefMut<Box<ScriptListener+'static>> {
         self.compositor.borrow_mut()
     }
@@ -113,6 +138,10 @@ impl Window {
     pub fn storage_task(&self) -> StorageTask {
         self.page().storage_task.clone()
     }
+
+    pub fn blob_urls<'a>(&'a self) -> &'a BlobURLList {
+        &self.blob_urls
+    }
 }
 
 // http://www.whatwg.org/html/#atob
@@ -190,12 +219,73 @@ pub fn base64_atob(atob: DOMString) -> Fallible<DOMString> {
 }
 
 
+/// Posts a message to the window of the given pipeline, on behalf of the script running in `cx`.
+/// The target window is either a `Window` or, when it lives in another script task, a
+/// `DissimilarOriginWindow`.
+// http://www.whatwg.org/html/#dom-window-postmessage
+pub fn post_message(cx: *mut JSContext, constellation_chan: &ConstellationChan,
+                    target: PipelineId, message: JSVal, target_origin: DOMString,
+                    transfer: Option<Vec<JS<MessagePort>>>) -> ErrorResult {
+    // The window whose script is posting the message.
+    let incumbent = global_object_for_js_object(unsafe { JS_GetGlobalForScopeChain(cx) });
+    let incumbent = incumbent.root();
+    let incumbent = incumbent.root_ref();
+    let source = incumbent.as_window();
+    let source_url = source.get_url();
+
+    // Steps 3-4.
+    let target_origin = match target_origin.as_slice() {
+        "*" => None,
+        "/" => Some(source_url.clone()),
+        _ => match Url::parse(target_origin.as_slice()) {
+            Ok(url) => Some(url),
+            Err(_) => return Err(Syntax),
+        },
+    };
+
+    // Step 5.
+    let mut buffer = ptr::null_mut();
+    let mut nbytes = 0;
+    let result = unsafe {
+        JS_WriteStructuredClone(cx, message, &mut buffer, &mut nbytes,
+                                ptr::null(), ptr::null_mut())
+    };
+    if result == 0 {
+        unsafe { JS_ClearPendingException(cx); }
+        return Err(DataClone);
+    }
+    let data = unsafe {
+        buf_as_slice(buffer as *const u64, nbytes as uint / 8, |data| data.to_vec())
+    };
+    let ports = try!(transfer_ports(transfer, None));
+
+    // Step 6. The message is delivered asynchronously, by way of the constellation, since
+    // the target window may live in another script task.
+    let message = PostedMessage {
+        source: source.page().id,
+        origin: UrlHelper::Origin(&source_url),
+        target_origin: target_origin,
+        data: data,
+        ports: box ports as Box<Any+Send>,
+    };
+    let ConstellationChan(ref chan) = *constellation_chan;
+    chan.send(PostMessageMsg(target, message));
+    Ok(())
+}
+
 impl<'a> WindowMethods for JSRef<'a, Window> {
     fn Alert(self, s: DOMString) {
         // Right now, just print to the console
         println!("ALERT: {:s}", s);
     }
 
+    // http://www.whatwg.org/html/#dom-window-postmessage
+    fn PostMessage(self, cx: *mut JSContext, message: JSVal, targetOrigin: DOMString,
+                   transfer: Option<Vec<JS<MessagePort>>>) -> ErrorResult {
+        post_message(cx, &self.page().constellation_chan, self.page().id, message, targetOrigin,
+                     transfer)
+    }
+
     fn Close(self) {
         let ScriptChan(ref chan) = self.script_chan;
         chan.send(ExitWindowMsg(self.page.id.clone()));
@@ -231,6 +321,14 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         self.console.get().unwrap()
     }
 
+    fn History(self) -> Temporary<History> {
+        if self.history.get().is_none() {
+            let history = History::new(self);
+            self.history.assign(Some(history));
+        }
+        self.history.get().unwrap()
+    }
+
     fn Navigator(self) -> Temporary<Navigator> {
         if self.navigator.get().is_none() {
             let navigator = Navigator::new(self);
@@ -252,6 +350,16 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         self.timers.clear_timeout_or_interval(handle);
     }
 
+    fn RequestAnimationFrame(self, callback: FrameRequestCallback) -> u32 {
+        let document = self.Document().root();
+        document.request_animation_frame(callback)
+    }
+
+    fn CancelAnimationFrame(self, handle: u32) {
+        let document = self.Document().root();
+        document.cancel_animation_frame(handle);
+    }
+
     fn SetInterval(self, _cx: *mut JSContext, callback: Function, timeout: i32, args: Vec<JSVal>) -> i32 {
         self.timers.set_timeout_or_interval(callback,
                                             args,
@@ -278,9 +386,15 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         self.Window()
     }
 
+    // http://www.whatwg.org/html/#dom-parent
     fn Parent(self) -> Temporary<Window> {
-        //TODO - Once we support iframes correctly this needs to return the parent frame
-        self.Window()
+        let parent = self.page().parent.as_ref().and_then(|parent| parent.upgrade());
+        let parent_window = parent.and_then(|parent| {
+            let frame = parent.frame();
+            let window = frame.as_ref().map(|frame| Temporary::new(frame.window.clone()));
+            window
+        });
+        parent_window.unwrap_or_else(|| self.Window())
     }
 
     fn Performance(self) -> Temporary<Performance> {
@@ -293,6 +407,8 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
 
     event_handler!(click, GetOnclick, SetOnclick)
     event_handler!(load, GetOnload, SetOnload)
+    event_handler!(hashchange, GetOnhashchange, SetOnhashchange)
+    event_handler!(popstate, GetOnpopstate, SetOnpopstate)
     event_handler!(unload, GetOnunload, SetOnunload)
     event_handler!(storage, GetOnstorage, SetOnstorage)
     error_event_handler!(error, GetOnerror, SetOnerror)
@@ -339,6 +455,9 @@ pub trait WindowHelpers {
     fn handle_fire_timer(self, timer_id: TimerId);
     fn evaluate_js_with_result(self, code: &str) -> JSVal;
     fn evaluate_script_with_result(self, code: &str, filename: &str) -> JSVal;
+    fn queue_mutation_observer(self, observer: JSRef<MutationObserver>);
+    fn notify_mutation_observers(self) -> bool;
+    fn perform_a_microtask_checkpoint(self) -> bool;
 }
 
 
@@ -403,6 +522,52 @@ impl<'a> WindowHelpers for JSRef<'a, Window> {
         self.timers.fire_timer(timer_id, self.clone());
         self.flush_layout();
     }
+
+    // http://dom.spec.whatwg.org/#queue-a-mutation-observer-compound-microtask
+    fn queue_mutation_observer(self, observer: JSRef<MutationObserver>) {
+        let observer = JS::from_rooted(observer);
+        let mut observers = self.mutation_observers.borrow_mut();
+        if !observers.contains(&observer) {
+            observers.push(observer);
+        }
+    }
+
+    // http://dom.spec.whatwg.org/#notify-mutation-observers
+    /// Returns whether any observer was notified.
+    fn notify_mutation_observers(self) -> bool {
+        let mut notified = false;
+        loop {
+            // Observers are only removed from the list once they are rooted.
+            let observer = match self.mutation_observers.borrow().as_slice().head() {
+                None => break,
+                Some(observer) => observer.root(),
+            };
+            self.mutation_observers.borrow_mut().remove(0);
+            observer.notify();
+            notified = true;
+        }
+        notified
+    }
+
+    // http://www.whatwg.org/html/#perform-a-microtask-checkpoint
+    /// Notifies the mutation observers of this window, unless a microtask checkpoint is already
+    /// being performed. Returns whether any observer was notified.
+    fn perform_a_microtask_checkpoint(self) -> bool {
+        // Step 1.
+        if PerformingMicrotaskCheckpoint.get().is_some() {
+            return false;
+        }
+
+        // Step 2.
+        PerformingMicrotaskCheckpoint.replace(Some(()));
+
+        // Step 3-5.
+        let notified = self.notify_mutation_observers();
+
+        // Step 9.
+        PerformingMicrotaskCheckpoint.replace(None);
+        notified
+    }
 }
 
 impl Window {
@@ -411,7 +576,8 @@ impl Window {
                script_chan: ScriptChan,
                control_chan: ScriptControlChan,
                compositor: Box<ScriptListener+'static>,
-               image_cache_task: ImageCacheTask)
+               image_cache_task: ImageCacheTask,
+               font_cache_task: FontCacheTask)
                -> Temporary<Window> {
         let win = box Window {
             eventtarget: EventTarget::new_inherited(WindowTypeId),
@@ -421,15 +587,19 @@ impl Window {
             compositor: DOMRefCell::new(compositor),
             page: page,
             location: Default::default(),
+            history: Default::default(),
             navigator: Default::default(),
             image_cache_task: image_cache_task,
+            font_cache_task: font_cache_task,
             browser_context: DOMRefCell::new(None),
             performance: Default::default(),
             navigation_start: time::get_time().sec as u64,
             navigation_start_precise: time::precise_time_s(),
             screen: Default::default(),
             session_storage: Default::default(),
-            timers: TimerManager::new()
+            timers: TimerManager::new(),
+            blob_urls: BlobURLList::new(),
+            mutation_observers: DOMRefCell::new(vec!()),
         };
 
         WindowBinding::Wrap(cx, win)
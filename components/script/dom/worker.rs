@@ -14,6 +14,7 @@ use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
 use dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use dom::eventtarget::{EventTarget, EventTargetHelpers, WorkerTypeId};
 use dom::messageevent::MessageEvent;
+use dom::messageport::{MessagePort, TransferredPort, transfer_ports};
 use script_task::{ScriptChan, DOMMessage};
 
 use servo_util::str::DOMString;
@@ -73,14 +74,14 @@ impl Worker {
         let worker_ref = worker.addref();
 
         DedicatedWorkerGlobalScope::run_worker_scope(
-            worker_url, worker_ref, resource_task, global.script_chan().clone(),
+            worker_url, global.get_url(), worker_ref, resource_task, global.script_chan().clone(),
             sender, receiver);
 
         Ok(Temporary::from_rooted(*worker))
     }
 
     pub fn handle_message(address: TrustedWorkerAddress,
-                          data: *mut u64, nbytes: size_t) {
+                          data: *mut u64, nbytes: size_t, ports: Vec<TransferredPort>) {
         let worker = unsafe { JS::from_trusted_worker_address(address).root() };
 
         let global = worker.global.root();
@@ -94,7 +95,7 @@ impl Worker {
         }
 
         let target: JSRef<EventTarget> = EventTargetCast::from_ref(*worker);
-        MessageEvent::dispatch_jsval(target, global.root_ref(), message);
+        MessageEvent::dispatch_jsval(target, global.root_ref(), message, ports);
     }
 }
 
@@ -131,7 +132,8 @@ impl Worker {
 }
 
 impl<'a> WorkerMethods for JSRef<'a, Worker> {
-    fn PostMessage(self, cx: *mut JSContext, message: JSVal) -> ErrorResult {
+    fn PostMessage(self, cx: *mut JSContext, message: JSVal,
+                   transfer: Option<Vec<JS<MessagePort>>>) -> ErrorResult {
         let mut data = ptr::null_mut();
         let mut nbytes = 0;
         let result = unsafe {
@@ -142,10 +144,11 @@ impl<'a> WorkerMethods for JSRef<'a, Worker> {
             unsafe { JS_ClearPendingException(cx); }
             return Err(DataClone);
         }
+        let ports = try!(transfer_ports(transfer, None));
 
         self.addref();
         let ScriptChan(ref sender) = self.sender;
-        sender.send(DOMMessage(data, nbytes));
+        sender.send(DOMMessage(data, nbytes, ports));
         Ok(())
     }
 
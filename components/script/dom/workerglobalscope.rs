@@ -9,6 +9,7 @@ use dom::bindings::global;
 use dom::bindings::js::{MutNullableJS, JSRef, Temporary, OptionalSettable};
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::console::Console;
+use dom::url::BlobURLList;
 use dom::eventtarget::{EventTarget, WorkerGlobalScopeTypeId};
 use dom::workerlocation::WorkerLocation;
 use dom::workernavigator::WorkerNavigator;
@@ -44,6 +45,7 @@ pub struct WorkerGlobalScope {
     navigator: MutNullableJS<WorkerNavigator>,
     console: MutNullableJS<Console>,
     timers: TimerManager,
+    blob_urls: BlobURLList,
 }
 
 impl WorkerGlobalScope {
@@ -61,7 +63,8 @@ impl WorkerGlobalScope {
             location: Default::default(),
             navigator: Default::default(),
             console: Default::default(),
-            timers: TimerManager::new()
+            timers: TimerManager::new(),
+            blob_urls: BlobURLList::new(),
         }
     }
 
@@ -85,6 +88,10 @@ impl WorkerGlobalScope {
     pub fn script_chan<'a>(&'a self) -> &'a ScriptChan {
         &self.script_chan
     }
+
+    pub fn blob_urls<'a>(&'a self) -> &'a BlobURLList {
+        &self.blob_urls
+    }
 }
 
 impl<'a> WorkerGlobalScopeMethods for JSRef<'a, WorkerGlobalScope> {
@@ -112,7 +119,8 @@ impl<'a> WorkerGlobalScopeMethods for JSRef<'a, WorkerGlobalScope> {
         }
 
         for url in urls.into_iter() {
-            let (url, source) = match load_whole_resource(&self.resource_task, url) {
+            let origin = Some(self.worker_url.clone());
+            let (url, source) = match load_whole_resource(&self.resource_task, url, origin) {
                 Err(_) => return Err(Network),
                 Ok((metadata, bytes)) => {
                     (metadata.final_url, String::from_utf8(bytes).unwrap())
@@ -13,13 +13,14 @@ use dom::bindings::codegen::InheritTypes::{EventCast, EventTargetCast, XMLHttpRe
 use dom::bindings::conversions::ToJSValConvertible;
 use dom::bindings::error::{Error, ErrorResult, Fallible, InvalidState, InvalidAccess};
 use dom::bindings::error::{Network, Syntax, Security, Abort, Timeout};
-use dom::bindings::global::{GlobalField, GlobalRef, WorkerRoot};
+use dom::bindings::global::{GlobalField, GlobalRef, WindowRoot, WorkerRoot};
 use dom::bindings::js::{MutNullableJS, JS, JSRef, Temporary, OptionalRootedRootable};
 use dom::bindings::str::ByteString;
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
-use dom::document::Document;
+use dom::document::{Document, HTMLDocument, NonHTMLDocument, NotFromParser};
 use dom::event::{Event, DoesNotBubble, Cancelable};
 use dom::eventtarget::{EventTarget, EventTargetHelpers, XMLHttpRequestTargetTypeId};
+use dom::formdata::FormDataHelpers;
 use dom::progressevent::ProgressEvent;
 use dom::urlsearchparams::URLSearchParamsHelpers;
 use dom::xmlhttprequesteventtarget::XMLHttpRequestEventTarget;
@@ -32,6 +33,7 @@ use encoding::types::{DecodeReplace, Encoding, EncodingRef, EncodeReplace};
 use http::headers::response::HeaderCollection as ResponseHeaderCollection;
 use http::headers::request::HeaderCollection as RequestHeaderCollection;
 use http::headers::content_type::MediaType;
+use http::headers::test_utils::from_stream_with_str;
 use http::headers::{HeaderEnum, HeaderValueByteIterator};
 use http::headers::request::Header;
 use http::method::{Method, Get, Head, Connect, Trace, ExtensionMethod};
@@ -45,6 +47,9 @@ use libc;
 use libc::c_void;
 
 use net::resource_task::{ResourceTask, ResourceCORSData, Load, LoadData, LoadResponse, Payload, Done};
+use parse::html::parse_html_string;
+use parse::xml::parse_xml;
+use parse::is_xml_mime_type;
 use cors::{allow_cross_origin_request, CORSRequest, CORSMode, ForcedPreflightMode};
 use script_task::{ScriptChan, XHRProgressMsg, XHRReleaseMsg};
 use servo_util::str::DOMString;
@@ -60,9 +65,11 @@ use std::time::duration::Duration;
 use std::num::Zero;
 use time;
 use url::{Url, UrlParser};
+use uuid::Uuid;
 
-use dom::bindings::codegen::UnionTypes::StringOrURLSearchParams::{eString, eURLSearchParams, StringOrURLSearchParams};
-pub type SendParam = StringOrURLSearchParams;
+use dom::bindings::codegen::UnionTypes::BlobOrFormDataOrStringOrURLSearchParams::BlobOrFormDataOrStringOrURLSearchParams;
+use dom::bindings::codegen::UnionTypes::BlobOrFormDataOrStringOrURLSearchParams::{eBlob, eFormData, eString, eURLSearchParams};
+pub type SendParam = BlobOrFormDataOrStringOrURLSearchParams;
 
 
 #[deriving(PartialEq)]
@@ -391,6 +398,7 @@ impl<'a> XMLHttpRequestMethods for JSRef<'a, XMLHttpRequest> {
                 *self.request_url.borrow_mut() = Some(parsed_url);
                 *self.request_headers.borrow_mut() = RequestHeaderCollection::new();
                 self.send_flag.set(false);
+                self.response_xml.clear();
                 *self.status_text.borrow_mut() = ByteString::new(vec!());
                 self.status.set(0);
 
@@ -517,7 +525,10 @@ impl<'a> XMLHttpRequestMethods for JSRef<'a, XMLHttpRequest> {
             Get | Head => None, // Step 3
             _ => data
         };
-        let extracted = data.as_ref().map(|d| d.extract());
+        let (extracted, content_type) = match data.as_ref().map(|d| d.extract()) {
+            Some((body, content_type)) => (Some(body), content_type),
+            None => (None, None),
+        };
         self.request_body_len.set(extracted.as_ref().map(|e| e.len()).unwrap_or(0));
 
         // Step 6
@@ -560,26 +571,12 @@ impl<'a> XMLHttpRequestMethods for JSRef<'a, XMLHttpRequest> {
         let (start_chan, start_port) = channel();
         let mut load_data = LoadData::new(self.request_url.borrow().clone().unwrap(), start_chan);
         load_data.data = extracted;
+        load_data.origin = Some(global.root_ref().get_url());
 
         // Default headers
         let ref request_headers = self.request_headers;
         if request_headers.borrow().content_type.is_none() {
-            let parameters = vec!((String::from_str("charset"), String::from_str("UTF-8")));
-            request_headers.borrow_mut().content_type = match data {
-                Some(eString(_)) =>
-                    Some(MediaType {
-                        type_: String::from_str("text"),
-                        subtype: String::from_str("plain"),
-                        parameters: parameters
-                    }),
-                Some(eURLSearchParams(_)) =>
-                    Some(MediaType {
-                        type_: String::from_str("application"),
-                        subtype: String::from_str("x-www-form-urlencoded"),
-                        parameters: parameters
-                    }),
-                None => None
-            }
+            request_headers.borrow_mut().content_type = content_type;
         }
 
         if request_headers.borrow().accept.is_none() {
@@ -724,6 +721,7 @@ impl<'a> XMLHttpRequestMethods for JSRef<'a, XMLHttpRequest> {
                 }
             },
             _ if self.ready_state.get() != XHRDone => NullValue(),
+            XMLHttpRequestResponseTypeValues::Document => self.response_xml.get().to_jsval(cx),
             Json => {
                 let decoded = UTF_8.decode(self.response.borrow().as_slice(), DecodeReplace).unwrap().to_string();
                 let decoded: Vec<u16> = decoded.as_slice().utf16_units().collect();
@@ -795,6 +793,7 @@ trait PrivateXMLHttpRequestHelpers {
     fn dispatch_upload_progress_event(self, type_: DOMString, partial_load: Option<u64>);
     fn dispatch_response_progress_event(self, type_: DOMString);
     fn text_response(self) -> DOMString;
+    fn document_response(self) -> Option<Temporary<Document>>;
     fn set_timeout(self, timeout:u32);
     fn cancel_timeout(self);
     fn filter_response_headers(self) -> ResponseHeaderCollection;
@@ -916,6 +915,7 @@ impl<'a> PrivateXMLHttpRequestHelpers for JSRef<'a, XMLHttpRequest> {
 
                 // Subsubsteps 5-7
                 self.send_flag.set(false);
+                self.response_xml.assign(self.document_response());
                 self.change_ready_state(XHRDone);
                 return_if_fetch_was_terminated!();
                 // Subsubsteps 10-12
@@ -1044,6 +1044,53 @@ impl<'a> PrivateXMLHttpRequestHelpers for JSRef<'a, XMLHttpRequest> {
         // the result should be fine. XXXManishearth have a closer look at this later
         encoding.decode(self.response.borrow().as_slice(), DecodeReplace).unwrap().to_string()
     }
+    // http://xhr.spec.whatwg.org/#document-response
+    fn document_response(self) -> Option<Temporary<Document>> {
+        // Step 1.
+        let global = self.global.root();
+        let window = match global {
+            WindowRoot(ref window) => **window,
+            WorkerRoot(_) => return None,
+        };
+
+        // Step 2-5.
+        // http://xhr.spec.whatwg.org/#final-mime-type
+        let (type_, subtype) = match self.response_headers.borrow().content_type {
+            Some(ref media_type) => {
+                (media_type.type_.as_slice().to_ascii_lower(),
+                 media_type.subtype.as_slice().to_ascii_lower())
+            }
+            None => ("text".to_string(), "xml".to_string()),
+        };
+        let is_html = type_.as_slice() == "text" && subtype.as_slice() == "html";
+        if is_html {
+            if self.response_type.get() != XMLHttpRequestResponseTypeValues::Document {
+                return None;
+            }
+        } else if !is_xml_mime_type(type_.as_slice(), subtype.as_slice()) {
+            return None;
+        }
+
+        // Step 6-10.
+        let text = self.text_response();
+        let url = self.request_url.borrow().clone();
+        let content_type = Some(format!("{}/{}", type_, subtype));
+        if is_html {
+            let document = Document::new(window, url, HTMLDocument, content_type,
+                                         NotFromParser).root();
+            parse_html_string(*document, text);
+            return Some(Temporary::from_rooted(*document));
+        }
+
+        let document = Document::new(window, url, NonHTMLDocument, content_type,
+                                     NotFromParser).root();
+        match parse_xml(*document, text) {
+            Ok(()) => Some(Temporary::from_rooted(*document)),
+            // Step 11.
+            Err(_) => None,
+        }
+    }
+
     fn filter_response_headers(self) -> ResponseHeaderCollection {
         // http://fetch.spec.whatwg.org/#concept-response-header-list
         let mut headers = ResponseHeaderCollection::new();
@@ -1059,15 +1106,48 @@ impl<'a> PrivateXMLHttpRequestHelpers for JSRef<'a, XMLHttpRequest> {
 }
 
 trait Extractable {
-    fn extract(&self) -> Vec<u8>;
+    /// Returns the body and its default `Content-Type`.
+    fn extract(&self) -> (Vec<u8>, Option<MediaType>);
 }
 impl Extractable for SendParam {
-    fn extract(&self) -> Vec<u8> {
+    fn extract(&self) -> (Vec<u8>, Option<MediaType>) {
         // http://fetch.spec.whatwg.org/#concept-fetchbodyinit-extract
         let encoding = UTF_8 as EncodingRef;
+        let charset = vec!((String::from_str("charset"), String::from_str("UTF-8")));
         match *self {
-            eString(ref s) => encoding.encode(s.as_slice(), EncodeReplace).unwrap(),
-            eURLSearchParams(ref usp) => usp.root().serialize(None) // Default encoding is UTF8
+            eBlob(ref blob) => {
+                let blob = blob.root();
+                let content_type = if blob.content_type().is_empty() {
+                    None
+                } else {
+                    from_stream_with_str(blob.content_type().as_slice())
+                };
+                (blob.bytes().to_vec(), content_type)
+            }
+            eFormData(ref form_data) => {
+                let boundary = Uuid::new_v4().to_simple_string();
+                let body = form_data.root().encode_multipart(boundary.as_slice());
+                (body, Some(MediaType {
+                    type_: String::from_str("multipart"),
+                    subtype: String::from_str("form-data"),
+                    parameters: vec!((String::from_str("boundary"), boundary))
+                }))
+            }
+            eString(ref s) => {
+                (encoding.encode(s.as_slice(), EncodeReplace).unwrap(), Some(MediaType {
+                    type_: String::from_str("text"),
+                    subtype: String::from_str("plain"),
+                    parameters: charset
+                }))
+            }
+            eURLSearchParams(ref usp) => {
+                // Default encoding is UTF8
+                (usp.root().serialize(None), Some(MediaType {
+                    type_: String::from_str("application"),
+                    subtype: String::from_str("x-www-form-urlencoded"),
+                    parameters: charset
+                }))
+            }
         }
     }
 }
@@ -0,0 +1,52 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::XMLSerializerBinding;
+use dom::bindings::codegen::Bindings::XMLSerializerBinding::XMLSerializerMethods;
+use dom::bindings::error::Fallible;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::global;
+use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::utils::{Reflector, Reflectable, reflect_dom_object};
+use dom::htmlserializer::{serialize, XMLSerialization};
+use dom::node::{Node, NodeHelpers, NodeIterator};
+use dom::window::Window;
+use servo_util::str::DOMString;
+
+#[dom_struct]
+pub struct XMLSerializer {
+    reflector_: Reflector,
+}
+
+impl XMLSerializer {
+    fn new_inherited() -> XMLSerializer {
+        XMLSerializer {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(window: JSRef<Window>) -> Temporary<XMLSerializer> {
+        reflect_dom_object(box XMLSerializer::new_inherited(), global::Window(window),
+                           XMLSerializerBinding::Wrap)
+    }
+
+    pub fn Constructor(global: &GlobalRef) -> Fallible<Temporary<XMLSerializer>> {
+        Ok(XMLSerializer::new(global.as_window()))
+    }
+}
+
+impl<'a> XMLSerializerMethods for JSRef<'a, XMLSerializer> {
+    // http://domparsing.spec.whatwg.org/#dom-xmlserializer-serializetostring
+    fn SerializeToString(self, root: JSRef<Node>) -> DOMString {
+        // A document is serialized as the list of its children.
+        let include_root = !root.is_document();
+        serialize(&mut NodeIterator::new(root, include_root, false), XMLSerialization)
+    }
+}
+
+impl Reflectable for XMLSerializer {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
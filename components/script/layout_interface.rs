@@ -10,6 +10,7 @@ use dom::node::LayoutDataRef;
 
 use geom::point::Point2D;
 use geom::rect::Rect;
+use geom::size::Size2D;
 use script_traits::{ScriptControlChan, OpaqueScriptLayoutChannel, UntrustedNodeAddress};
 use servo_msg::constellation_msg::WindowSizeData;
 use servo_util::geometry::Au;
@@ -66,12 +67,15 @@ pub trait LayoutRPC {
     /// Requests the node containing the point of interest
     fn hit_test(&self, node: TrustedNodeAddress, point: Point2D<f32>) -> Result<HitTestResponse, ()>;
     fn mouse_over(&self, node: TrustedNodeAddress, point: Point2D<f32>) -> Result<MouseOverResponse, ()>;
+    /// Requests how far the contents of a scroll container can be scrolled.
+    fn scroll_range(&self) -> ScrollRangeResponse;
 }
 
 pub struct ContentBoxResponse(pub Rect<Au>);
 pub struct ContentBoxesResponse(pub Vec<Rect<Au>>);
 pub struct HitTestResponse(pub UntrustedNodeAddress);
 pub struct MouseOverResponse(pub Vec<UntrustedNodeAddress>);
+pub struct ScrollRangeResponse(pub Size2D<Au>);
 
 /// Why we're doing reflow.
 #[deriving(PartialEq, Show)]
@@ -87,6 +91,12 @@ pub enum ReflowQueryType {
     NoQuery,
     ContentBoxQuery(TrustedNodeAddress),
     ContentBoxesQuery(TrustedNodeAddress),
+    /// The boxes of the characters in the given range of a text node. The offsets count
+    /// characters, not bytes.
+    TextBoxesQuery(TrustedNodeAddress, uint, uint),
+    /// How far the contents of the given element can be scrolled, which is nothing unless it is a
+    /// scroll container.
+    ScrollRangeQuery(TrustedNodeAddress),
 }
 
 /// Information needed for a reflow.
@@ -20,6 +20,7 @@ extern crate devtools_traits;
 extern crate cssparser;
 extern crate collections;
 extern crate geom;
+extern crate gfx;
 extern crate html5ever;
 extern crate encoding;
 extern crate http;
@@ -28,6 +29,7 @@ extern crate libc;
 extern crate msg;
 extern crate native;
 extern crate net;
+extern crate png;
 extern crate rustrt;
 extern crate serialize;
 extern crate time;
@@ -42,6 +44,7 @@ extern crate sync;
 extern crate "msg" as servo_msg;
 extern crate url;
 extern crate uuid;
+extern crate xml;
 extern crate string_cache;
 #[phase(plugin)]
 extern crate string_cache_macros;
@@ -85,8 +88,10 @@ pub mod dom {
     pub mod attr;
     pub mod blob;
     pub mod browsercontext;
+    pub mod canvasgradient;
     pub mod canvasrenderingcontext2d;
     pub mod characterdata;
+    pub mod closeevent;
     pub mod domrect;
     pub mod domrectlist;
     pub mod domstringmap;
@@ -95,6 +100,7 @@ pub mod dom {
     mod create;
     pub mod customevent;
     pub mod dedicatedworkerglobalscope;
+    pub mod dissimilaroriginwindow;
     pub mod document;
     pub mod documentfragment;
     pub mod documenttype;
@@ -107,7 +113,10 @@ pub mod dom {
     pub mod eventdispatcher;
     pub mod eventtarget;
     pub mod file;
+    pub mod filereader;
     pub mod formdata;
+    pub mod hashchangeevent;
+    pub mod history;
     pub mod htmlanchorelement;
     pub mod htmlappletelement;
     pub mod htmlareaelement;
@@ -178,10 +187,15 @@ pub mod dom {
     pub mod htmlulistelement;
     pub mod htmlvideoelement;
     pub mod htmlunknownelement;
+    pub mod imagedata;
     pub mod keyboardevent;
     pub mod location;
+    pub mod messagechannel;
     pub mod messageevent;
+    pub mod messageport;
     pub mod mouseevent;
+    pub mod mutationobserver;
+    pub mod mutationrecord;
     pub mod namednodemap;
     pub mod navigator;
     pub mod navigatorinfo;
@@ -191,6 +205,7 @@ pub mod dom {
     pub mod processinginstruction;
     pub mod performance;
     pub mod performancetiming;
+    pub mod popstateevent;
     pub mod progressevent;
     pub mod range;
     pub mod screen;
@@ -198,8 +213,10 @@ pub mod dom {
     pub mod storage;
     pub mod storageevent;
     pub mod text;
+    pub mod textmetrics;
     pub mod treewalker;
     pub mod uievent;
+    pub mod url;
     pub mod urlhelper;
     pub mod urlsearchparams;
     pub mod validitystate;
@@ -213,6 +230,7 @@ pub mod dom {
     pub mod xmlhttprequest;
     pub mod xmlhttprequesteventtarget;
     pub mod xmlhttprequestupload;
+    pub mod xmlserializer;
 
     pub mod testbinding;
 }
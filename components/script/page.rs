@@ -15,7 +15,7 @@ use layout_interface::{
     ContentBoxQuery, ContentBoxResponse, ContentBoxesQuery, ContentBoxesResponse,
     GetRPCMsg, HitTestResponse, LayoutChan, LayoutRPC, MouseOverResponse, NoQuery,
     Reflow, ReflowForDisplay, ReflowForScriptQuery, ReflowGoal, ReflowMsg,
-    ReflowQueryType, TrustedNodeAddress
+    ReflowQueryType, ScrollRangeQuery, ScrollRangeResponse, TextBoxesQuery, TrustedNodeAddress
 };
 use script_traits::{UntrustedNodeAddress, ScriptControlChan};
 
@@ -35,7 +35,7 @@ use std::cell::{Cell, Ref, RefMut};
 use std::comm::{channel, Receiver, Empty, Disconnected};
 use std::mem::replace;
 use std::num::abs;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use url::Url;
 
 /// Encapsulates a handle to a frame and its associated layout information.
@@ -47,6 +47,9 @@ pub struct Page {
     /// Subpage id associated with this page, if any.
     pub subpage_id: Option<SubpageId>,
 
+    /// The page containing this one, if any.
+    pub parent: Option<Weak<Page>>,
+
     /// Unique id for last reflow request; used for confirming completion reply.
     pub last_reflow_id: Cell<uint>,
 
@@ -108,6 +111,10 @@ pub struct Page {
     /// An enlarged rectangle around the page contents visible in the viewport, used
     /// to prevent creating display list items for content that is far away from the viewport.
     pub page_clip_rect: Cell<Rect<Au>>,
+
+    /// The index of the current entry of the joint session history and its number of entries,
+    /// as last reported by the constellation.
+    pub session_history: Cell<(uint, uint)>,
 }
 
 pub struct PageIterator {
@@ -138,6 +145,7 @@ impl IterablePage for Rc<Page> {
 
 impl Page {
     pub fn new(id: PipelineId, subpage_id: Option<SubpageId>,
+           parent: Option<Weak<Page>>,
            layout_chan: LayoutChan,
            window_size: WindowSizeData,
            resource_task: ResourceTask,
@@ -157,6 +165,7 @@ impl Page {
         Page {
             id: id,
             subpage_id: subpage_id,
+            parent: parent,
             frame: DOMRefCell::new(None),
             layout_chan: layout_chan,
             layout_rpc: layout_rpc,
@@ -177,6 +186,7 @@ impl Page {
             pending_reflows: Cell::new(0),
             avoided_reflows: Cell::new(0),
             page_clip_rect: Cell::new(MAX_RECT),
+            session_history: Cell::new((0, 1)),
         }
     }
 
@@ -187,7 +197,10 @@ impl Page {
             (ReflowForDisplay, true)
         } else {
             match query {
-                ContentBoxQuery(_) | ContentBoxesQuery(_) => (ReflowForScriptQuery, true),
+                ContentBoxQuery(_) | ContentBoxesQuery(_) | TextBoxesQuery(..) |
+                ScrollRangeQuery(_) => {
+                    (ReflowForScriptQuery, true)
+                }
                 NoQuery => (ReflowForDisplay, false),
             }
         };
@@ -222,6 +235,21 @@ impl Page {
         rects
     }
 
+    pub fn text_boxes_query(&self, text_node: TrustedNodeAddress, start: uint, end: uint)
+                            -> Vec<Rect<Au>> {
+        self.flush_layout(TextBoxesQuery(text_node, start, end));
+        self.join_layout(); //FIXME: is this necessary, or is layout_rpc's mutex good enough?
+        let ContentBoxesResponse(rects) = self.layout_rpc.content_boxes();
+        rects
+    }
+
+    pub fn scroll_range_query(&self, node: TrustedNodeAddress) -> Size2D<Au> {
+        self.flush_layout(ScrollRangeQuery(node));
+        self.join_layout(); //FIXME: is this necessary, or is layout_rpc's mutex good enough?
+        let ScrollRangeResponse(range) = self.layout_rpc.scroll_range();
+        range
+    }
+
     // must handle root case separately
     pub fn remove(&self, id: PipelineId) -> Option<Rc<Page>> {
         let remove_idx = {
@@ -344,6 +372,18 @@ impl Page {
         self.url().as_ref().unwrap().ref0().clone()
     }
 
+    /// Changes the URL of this page without loading it, as on a same-document navigation.
+    pub fn set_url(&self, url: Url) {
+        *self.mut_url().as_mut().unwrap().mut0() = url;
+    }
+
+    /// Records that this page added an entry after the current one of the joint session
+    /// history, which replaces the entries after it, before the constellation reports it.
+    pub fn add_session_history_entry(&self) {
+        let (index, _) = self.session_history.get();
+        self.session_history.set((index + 1, index + 2));
+    }
+
     // FIXME(cgaebel): join_layout is racey. What if the compositor triggers a
     // reflow between the "join complete" message and returning from this
     // function?
@@ -3,11 +3,14 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use dom::attr::AttrHelpers;
+use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
 use dom::bindings::codegen::InheritTypes::{NodeCast, ElementCast, HTMLScriptElementCast};
+use dom::bindings::codegen::InheritTypes::HTMLFormElementDerived;
 use dom::bindings::js::{JS, JSRef, Temporary, OptionalRootable, Root};
 use dom::comment::Comment;
-use dom::document::{Document, DocumentHelpers};
+use dom::document::{Document, DocumentHelpers, FromParser, HTMLDocument};
+use dom::documentfragment::DocumentFragment;
 use dom::documenttype::DocumentType;
 use dom::element::{Element, AttributeHandlers, ElementHelpers, ParserCreated};
 use dom::htmlscriptelement::HTMLScriptElement;
@@ -17,54 +20,21 @@ use dom::servohtmlparser;
 use dom::servohtmlparser::ServoHTMLParser;
 use dom::text::Text;
 use page::Page;
-use parse::Parser;
+use parse::{Parser, ParserInput, InputString, InputUrl, process_response_metadata};
 
 use encoding::all::UTF_8;
 use encoding::types::{Encoding, DecodeReplace};
 
-use servo_net::resource_task::{Load, LoadData, Payload, Done, ResourceTask};
-use servo_msg::constellation_msg::LoadData as MsgLoadData;
+use servo_net::resource_task::{Payload, Done};
+use servo_util::str::DOMString;
 use servo_util::task_state;
 use servo_util::task_state::IN_HTML_PARSER;
 use std::ascii::AsciiExt;
-use std::comm::channel;
 use std::str::MaybeOwned;
-use url::Url;
-use http::headers::HeaderEnum;
-use time;
 use html5ever::Attribute;
 use html5ever::tree_builder::{TreeSink, QuirksMode, NodeOrText, AppendNode, AppendText};
 use string_cache::QualName;
 
-pub enum HTMLInput {
-    InputString(String),
-    InputUrl(Url),
-}
-
-// Parses an RFC 2616 compliant date/time string, and returns a localized
-// date/time string in a format suitable for document.lastModified.
-fn parse_last_modified(timestamp: &str) -> String {
-    let format = "%m/%d/%Y %H:%M:%S";
-
-    // RFC 822, updated by RFC 1123
-    match time::strptime(timestamp, "%a, %d %b %Y %T %Z") {
-        Ok(t) => return t.to_local().strftime(format).unwrap(),
-        Err(_) => ()
-    }
-
-    // RFC 850, obsoleted by RFC 1036
-    match time::strptime(timestamp, "%A, %d-%b-%y %T %Z") {
-        Ok(t) => return t.to_local().strftime(format).unwrap(),
-        Err(_) => ()
-    }
-
-    // ANSI C's asctime() format
-    match time::strptime(timestamp, "%c") {
-        Ok(t) => t.to_local().strftime(format).unwrap(),
-        Err(_) => String::from_str("")
-    }
-}
-
 trait SinkHelpers {
     fn get_or_create(&self, child: NodeOrText<TrustedNodeAddress>) -> Temporary<Node>;
 }
@@ -190,55 +160,12 @@ impl<'a> TreeSink<TrustedNodeAddress> for servohtmlparser::Sink {
     }
 }
 
-// The url from msg_load_data is ignored here
-pub fn parse_html(page: &Page,
-                  document: JSRef<Document>,
-                  input: HTMLInput,
-                  resource_task: ResourceTask,
-                  msg_load_data: Option<MsgLoadData>) {
-    let (base_url, load_response) = match input {
-        InputUrl(ref url) => {
-            // Wait for the LoadResponse so that the parser knows the final URL.
-            let (input_chan, input_port) = channel();
-            let mut load_data = LoadData::new(url.clone(), input_chan);
-            msg_load_data.map(|m| {
-                load_data.headers = m.headers;
-                load_data.method = m.method;
-                load_data.data = m.data;
-            });
-            resource_task.send(Load(load_data));
-
-            let load_response = input_port.recv();
-
-            load_response.metadata.headers.as_ref().map(|headers| {
-                let header = headers.iter().find(|h|
-                    h.header_name().as_slice().to_ascii_lower() == "last-modified".to_string()
-                );
-
-                match header {
-                    Some(h) => document.set_last_modified(
-                        parse_last_modified(h.header_value().as_slice())),
-                    None => {},
-                };
-            });
-
-            let base_url = load_response.metadata.final_url.clone();
-
-            {
-                // Store the final URL before we start parsing, so that DOM routines
-                // (e.g. HTMLImageElement::update_image) can resolve relative URLs
-                // correctly.
-                *page.mut_url() = Some((base_url.clone(), true));
-            }
-
-            (Some(base_url), Some(load_response))
-        },
-        InputString(_) => {
-            match *page.url() {
-                Some((ref page_url, _)) => (Some(page_url.clone()), None),
-                None => (None, None),
-            }
-        },
+pub fn parse_html(page: &Page, document: JSRef<Document>, input: ParserInput) {
+    let base_url = match input {
+        InputUrl(ref load_response) => {
+            Some(process_response_metadata(page, document, &load_response.metadata))
+        }
+        InputString(_) => page.url().as_ref().map(|&(ref page_url, _)| page_url.clone()),
     };
 
     let parser = ServoHTMLParser::new(base_url.clone(), document).root();
@@ -250,8 +177,8 @@ pub fn parse_html(page: &Page,
         InputString(s) => {
             parser.parse_chunk(s);
         }
-        InputUrl(url) => {
-            let load_response = load_response.unwrap();
+        InputUrl(load_response) => {
+            let url = load_response.metadata.final_url.clone();
             match load_response.metadata.content_type {
                 Some((ref t, _)) if t.as_slice().eq_ignore_ascii_case("image") => {
                     let page = format!("<html><body><img src='{:s}' /></body></html>", base_url.as_ref().unwrap().serialize());
@@ -282,3 +209,64 @@ pub fn parse_html(page: &Page,
 
     debug!("finished parsing");
 }
+
+/// Parses `input` into `document`, which has no browsing context, as done by `DOMParser`.
+/// Scripts are not run.
+pub fn parse_html_string(document: JSRef<Document>, input: DOMString) {
+    let url = Some(document.url().clone());
+    let parser = ServoHTMLParser::new(url, document).root();
+    parse_string(*parser, input);
+}
+
+/// Parses `input` with the HTML fragment parsing algorithm, as if it were the contents of
+/// `context`, and appends the resulting nodes to `fragment`. Scripts are not run, and are left
+/// marked as already started.
+///
+/// <https://html.spec.whatwg.org/multipage/syntax.html#parsing-html-fragments>
+pub fn parse_html_fragment(context: JSRef<Element>, input: DOMString,
+                           fragment: JSRef<DocumentFragment>) {
+    let context_node: JSRef<Node> = NodeCast::from_ref(context);
+    let context_document = context_node.owner_doc().root();
+    let window = context_document.window().root();
+    let url = Some(context_document.url().clone());
+
+    // Step 1.
+    let document = Document::new(*window, url.clone(), HTMLDocument, None, FromParser).root();
+
+    // Step 2.
+    document.set_quirks_mode(context_document.quirks_mode());
+
+    // Step 11.
+    let form = Some(context_node).into_iter().chain(context_node.ancestors())
+                                 .find(|node| node.is_htmlformelement())
+                                 .map(|node| ElementCast::to_ref(node).unwrap());
+
+    // Step 3-10, 12.
+    let parser = ServoHTMLParser::new_for_fragment(url, *document, context, form).root();
+    parse_string(*parser, input);
+
+    // Step 13-14.
+    let root = document.GetDocumentElement().root()
+                       .expect("fragment parsing did not create a root element");
+    let root: JSRef<Node> = NodeCast::from_ref(*root);
+    let fragment: JSRef<Node> = NodeCast::from_ref(fragment);
+    for child in root.children() {
+        assert!(fragment.AppendChild(child).is_ok());
+    }
+}
+
+/// Feeds all of `input` to `parser`. Scripts run by the document parser may parse strings in
+/// turn.
+fn parse_string(parser: JSRef<ServoHTMLParser>, input: DOMString) {
+    let nested = task_state::get().contains(IN_HTML_PARSER);
+    if !nested {
+        task_state::enter(IN_HTML_PARSER);
+    }
+
+    parser.parse_chunk(input);
+    parser.finish();
+
+    if !nested {
+        task_state::exit(IN_HTML_PARSER);
+    }
+}
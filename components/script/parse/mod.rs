@@ -2,9 +2,167 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::codegen::InheritTypes::{ElementCast, NodeCast};
+use dom::bindings::error::{Fallible, Syntax};
+use dom::bindings::js::{JSRef, Temporary};
+use dom::document::{Document, DocumentHelpers};
+use dom::documentfragment::DocumentFragment;
+use dom::element::{Element, ElementHelpers, ScriptCreated};
+use dom::node::{Node, NodeHelpers};
+use page::Page;
+
+use http::headers::HeaderEnum;
+use servo_msg::constellation_msg::LoadData as MsgLoadData;
+use servo_net::resource_task::{Load, LoadData, LoadResponse, Metadata, ResourceTask};
+use servo_util::str::DOMString;
+use std::ascii::AsciiExt;
+use std::comm::channel;
+use string_cache::QualName;
+use time;
+use url::Url;
+
 pub mod html;
+pub mod xml;
 
 pub trait Parser {
     fn parse_chunk(&self,input: String);
     fn finish(&self);
 }
+
+pub enum ParserInput {
+    InputString(String),
+    InputUrl(LoadResponse),
+}
+
+/// Starts loading `url` for a new document, and waits for the response metadata, which decides
+/// the kind of document to create. The url from `msg_load_data` is ignored here.
+pub fn load_document(resource_task: &ResourceTask, url: Url, msg_load_data: Option<MsgLoadData>)
+                     -> LoadResponse {
+    let (input_chan, input_port) = channel();
+    let mut load_data = LoadData::new(url, input_chan);
+    msg_load_data.map(|m| {
+        load_data.headers = m.headers;
+        load_data.method = m.method;
+        load_data.data = m.data;
+    });
+    resource_task.send(Load(load_data));
+    input_port.recv()
+}
+
+/// Whether `type_/subtype` is an XML MIME type, whose documents use the XML parser.
+///
+/// <http://mimesniff.spec.whatwg.org/#xml-mime-type>
+pub fn is_xml_mime_type(type_: &str, subtype: &str) -> bool {
+    let subtype = subtype.to_ascii_lower();
+    subtype.as_slice().ends_with("+xml") ||
+        (subtype.as_slice() == "xml" &&
+         (type_.eq_ignore_ascii_case("text") || type_.eq_ignore_ascii_case("application")))
+}
+
+/// Parses the document of a page, with the parser given by the type of `document`.
+pub fn parse_document(page: &Page, document: JSRef<Document>, input: ParserInput) {
+    if document.is_html_document() {
+        html::parse_html(page, document, input);
+    } else {
+        xml::parse_xml_document(page, document, input);
+    }
+}
+
+// Parses an RFC 2616 compliant date/time string, and returns a localized
+// date/time string in a format suitable for document.lastModified.
+fn parse_last_modified(timestamp: &str) -> String {
+    let format = "%m/%d/%Y %H:%M:%S";
+
+    // RFC 822, updated by RFC 1123
+    match time::strptime(timestamp, "%a, %d %b %Y %T %Z") {
+        Ok(t) => return t.to_local().strftime(format).unwrap(),
+        Err(_) => ()
+    }
+
+    // RFC 850, obsoleted by RFC 1036
+    match time::strptime(timestamp, "%A, %d-%b-%y %T %Z") {
+        Ok(t) => return t.to_local().strftime(format).unwrap(),
+        Err(_) => ()
+    }
+
+    // ANSI C's asctime() format
+    match time::strptime(timestamp, "%c") {
+        Ok(t) => t.to_local().strftime(format).unwrap(),
+        Err(_) => String::from_str("")
+    }
+}
+
+/// Applies the response metadata of a page to its document before parsing it, and returns the
+/// final URL, which is the base URL of the parser.
+fn process_response_metadata(page: &Page, document: JSRef<Document>, metadata: &Metadata)
+                             -> Url {
+    metadata.headers.as_ref().map(|headers| {
+        let header = headers.iter().find(|h|
+            h.header_name().as_slice().to_ascii_lower() == "last-modified".to_string()
+        );
+
+        match header {
+            Some(h) => document.set_last_modified(
+                parse_last_modified(h.header_value().as_slice())),
+            None => {},
+        };
+    });
+
+    let base_url = metadata.final_url.clone();
+
+    {
+        // Store the final URL before we start parsing, so that DOM routines
+        // (e.g. HTMLImageElement::update_image) can resolve relative URLs
+        // correctly.
+        *page.mut_url() = Some((base_url.clone(), true));
+    }
+
+    base_url
+}
+
+/// Parses `markup` as the contents of `context`, with the HTML or XML parser depending on the
+/// type of its document.
+///
+/// <http://domparsing.spec.whatwg.org/#concept-parse-fragment>
+pub fn parse_fragment(context: JSRef<Element>, markup: DOMString)
+                      -> Fallible<Temporary<DocumentFragment>> {
+    let node: JSRef<Node> = NodeCast::from_ref(context);
+    let document = node.owner_doc().root();
+    let fragment = DocumentFragment::new(*document).root();
+    if document.is_html_document() {
+        // Step 1.
+        html::parse_html_fragment(context, markup, *fragment);
+    } else {
+        // Step 2.
+        match xml::parse_xml_fragment(context, markup, *fragment) {
+            Ok(()) => (),
+            Err(error) => {
+                debug!("XML fragment parsing failed: {:s}", error);
+                return Err(Syntax);
+            }
+        }
+    }
+
+    // Step 3-4.
+    Ok(Temporary::from_rooted(*fragment))
+}
+
+/// Returns `node` if it can be the context element of the fragment parsing algorithm, or a new
+/// body element otherwise, as done by `insertAdjacentHTML()` and `createContextualFragment()`.
+pub fn fragment_parsing_context(node: JSRef<Node>) -> Temporary<Element> {
+    let document = node.owner_doc().root();
+    match ElementCast::to_ref(node) {
+        Some(element) if !(document.is_html_document() &&
+                           *element.local_name() == atom!("html") &&
+                           *element.namespace() == ns!(HTML)) => {
+            Temporary::from_rooted(element)
+        }
+        _ => {
+            let name = QualName {
+                ns: ns!(HTML),
+                local: atom!("body"),
+            };
+            Element::create(name, None, *document, ScriptCreated)
+        }
+    }
+}
@@ -0,0 +1,292 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::attr::AttrHelpers;
+use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use dom::bindings::codegen::InheritTypes::{NodeCast, ElementCast, HTMLScriptElementCast};
+use dom::bindings::js::{JSRef, Temporary, OptionalRootable};
+use dom::comment::Comment;
+use dom::document::{Document, DocumentHelpers, FromParser, NonHTMLDocument};
+use dom::documentfragment::DocumentFragment;
+use dom::element::{Element, AttributeHandlers, ElementHelpers, ParserCreated};
+use dom::htmlscriptelement::HTMLScriptElementHelpers;
+use dom::node::{Node, NodeHelpers};
+use dom::processinginstruction::ProcessingInstruction;
+use dom::text::Text;
+use page::Page;
+use parse::{ParserInput, InputString, InputUrl, process_response_metadata};
+
+use encoding::all::{UTF_8, UTF_16BE, UTF_16LE};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::types::{DecodeReplace, EncodingRef};
+use servo_net::resource_task::{Payload, Done};
+use servo_util::str::DOMString;
+use std::io::MemReader;
+use string_cache::{Atom, Namespace, QualName};
+use xml::name::OwnedName;
+use xml::reader::EventReader;
+use xml::reader::events;
+
+/// The namespace of the root element of the documents that report a well-formedness error.
+static PARSER_ERROR_NAMESPACE: &'static str =
+    "http://www.mozilla.org/newlayout/xml/parsererror.xml";
+
+/// Returns the encoding label given by the XML declaration at the start of `data`, if any.
+fn xml_declaration_encoding(data: &[u8]) -> Option<String> {
+    if !data.starts_with(b"<?xml") {
+        return None;
+    }
+    let end = match data.iter().position(|&byte| byte == b'>') {
+        Some(end) => end,
+        None => return None,
+    };
+    let declaration: String = data.slice_to(end).iter().map(|&byte| byte as char).collect();
+    let rest = match declaration.as_slice().find_str("encoding") {
+        Some(start) => declaration.as_slice().slice_from(start + "encoding".len()).trim_left(),
+        None => return None,
+    };
+    if !rest.starts_with("=") {
+        return None;
+    }
+    let rest = rest.slice_from(1).trim_left();
+    let quote = match rest.chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => quote,
+        _ => return None,
+    };
+    let rest = rest.slice_from(1);
+    rest.find(quote).map(|end| rest.slice_to(end).to_string())
+}
+
+/// Decodes the bytes of an XML document. The encoding is given by a byte order mark, the
+/// `charset` of the response, or the XML declaration, in that order, and is UTF-8 otherwise.
+///
+/// <http://www.whatwg.org/html/#parsing-xhtml-documents>
+fn decode_xml_document(data: &[u8], charset: Option<&str>) -> (DOMString, EncodingRef) {
+    let (encoding, data) = if data.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        (UTF_8 as EncodingRef, data.slice_from(3))
+    } else if data.starts_with(&[0xFE, 0xFF]) {
+        (UTF_16BE as EncodingRef, data.slice_from(2))
+    } else if data.starts_with(&[0xFF, 0xFE]) {
+        (UTF_16LE as EncodingRef, data.slice_from(2))
+    } else {
+        let encoding = charset.and_then(encoding_from_whatwg_label).or_else(|| {
+            xml_declaration_encoding(data).and_then(|label| {
+                encoding_from_whatwg_label(label.as_slice())
+            })
+        });
+        (encoding.unwrap_or(UTF_8 as EncodingRef), data)
+    };
+    (encoding.decode(data, DecodeReplace).unwrap(), encoding)
+}
+
+/// Parses the XML document of a page. Documents that are not well-formed or that fail to load
+/// are replaced by an error page.
+pub fn parse_xml_document(page: &Page, document: JSRef<Document>, input: ParserInput) {
+    let input = match input {
+        InputString(s) => s,
+        InputUrl(load_response) => {
+            let url = process_response_metadata(page, document, &load_response.metadata);
+            let mut data = vec!();
+            for msg in load_response.progress_port.iter() {
+                match msg {
+                    Payload(chunk) => data.push_all(chunk.as_slice()),
+                    Done(Err(error)) => {
+                        let message = format!("XML loading error: {}\nLocation: {}", error,
+                                              url.serialize());
+                        replace_with_parser_error(document, message);
+                        return;
+                    }
+                    Done(Ok(())) => break,
+                }
+            }
+            let charset = load_response.metadata.charset.as_ref().map(|charset| {
+                charset.as_slice()
+            });
+            let (input, encoding) = decode_xml_document(data.as_slice(), charset);
+            document.set_encoding_name(encoding.whatwg_name().unwrap_or(encoding.name())
+                                               .to_string());
+            input
+        }
+    };
+
+    match parse_xml(document, input) {
+        Ok(()) => (),
+        Err(error) => {
+            let message = format!("XML parsing error: {}\nLocation: {}", error,
+                                  document.url().serialize());
+            replace_with_parser_error(document, message);
+        }
+    }
+
+    debug!("finished parsing");
+}
+
+/// Replaces the contents of `document` with a `parsererror` element that contains `message`.
+pub fn replace_with_parser_error(document: JSRef<Document>, message: DOMString) {
+    let document_node: JSRef<Node> = NodeCast::from_ref(document);
+    Node::replace_all(None, document_node);
+
+    let name = QualName {
+        ns: Namespace(Atom::from_slice(PARSER_ERROR_NAMESPACE)),
+        local: Atom::from_slice("parsererror"),
+    };
+    let root = Element::create(name, None, document, ParserCreated).root();
+    let root: JSRef<Node> = NodeCast::from_ref(*root);
+    let text = Text::new(message, document).root();
+    assert!(root.AppendChild(NodeCast::from_ref(*text)).is_ok());
+    assert!(document_node.AppendChild(root).is_ok());
+}
+
+/// Parses `input` as an XML document into `document`, which must be empty. Returns a
+/// description of the first well-formedness error, if any; the nodes parsed before it are left
+/// in the document.
+pub fn parse_xml(document: JSRef<Document>, input: DOMString) -> Result<(), String> {
+    build_tree(document, NodeCast::from_ref(document), input)
+}
+
+/// Parses `input` with the XML fragment parsing algorithm, as if it were the contents of
+/// `context`, and appends the resulting nodes to `fragment`. Returns a description of the first
+/// well-formedness error, if any.
+///
+/// <http://domparsing.spec.whatwg.org/#xml-fragment-parsing-algorithm>
+pub fn parse_xml_fragment(context: JSRef<Element>, input: DOMString,
+                          fragment: JSRef<DocumentFragment>) -> Result<(), String> {
+    let context_node: JSRef<Node> = NodeCast::from_ref(context);
+    let context_document = context_node.owner_doc().root();
+    let window = context_document.window().root();
+    let url = Some(context_document.url().clone());
+    let document = Document::new(*window, url, NonHTMLDocument, None, FromParser).root();
+
+    // Step 1-2.
+    // Wrap the markup in an element that declares the default namespace and the prefixes in
+    // scope for the context element, since the nodes are parsed as its children.
+    let Namespace(ref namespace) = *context.namespace();
+    let mut declarations: Vec<(Option<DOMString>, DOMString)> =
+        vec!((context.prefix().clone(), namespace.as_slice().to_string()));
+    for ancestor in Some(context_node).into_iter().chain(context_node.ancestors()) {
+        let element: JSRef<Element> = match ElementCast::to_ref(ancestor) {
+            Some(element) => element,
+            None => continue,
+        };
+        for attr in element.attrs().iter() {
+            let attr = attr.root();
+            if *attr.namespace() != ns!(XMLNS) {
+                continue;
+            }
+            let prefix = match attr.local_name().as_slice() {
+                "xmlns" => None,
+                prefix => Some(prefix.to_string()),
+            };
+            if declarations.iter().all(|&(ref declared, _)| *declared != prefix) {
+                declarations.push((prefix, attr.value().as_slice().to_string()));
+            }
+        }
+    }
+
+    let mut markup = String::from_str("<fragment");
+    for &(ref prefix, ref namespace) in declarations.iter() {
+        match *prefix {
+            Some(ref prefix) => markup.push_str(format!(" xmlns:{}=\"", prefix).as_slice()),
+            None => markup.push_str(" xmlns=\""),
+        }
+        markup.push_str(namespace.replace("&", "&amp;").replace("<", "&lt;")
+                                 .replace("\"", "&quot;").as_slice());
+        markup.push('"');
+    }
+    markup.push('>');
+    // Markup that closes the wrapper can't leak out of it: whatever follows is either a second
+    // root element or the end tag of the wrapper without a start tag, and both are rejected by
+    // `build_tree`.
+    markup.push_str(input.as_slice());
+    markup.push_str("</fragment>");
+
+    // Step 3.
+    try!(parse_xml(*document, markup));
+
+    // Step 4.
+    let root = document.GetDocumentElement().root()
+                       .expect("XML fragment parsing did not create a root element");
+    let root: JSRef<Node> = NodeCast::from_ref(*root);
+    let fragment: JSRef<Node> = NodeCast::from_ref(fragment);
+    for child in root.children() {
+        assert!(fragment.AppendChild(child).is_ok());
+    }
+    Ok(())
+}
+
+fn qualified_name(name: &OwnedName) -> QualName {
+    let namespace = match name.namespace {
+        Some(ref namespace) => Namespace(Atom::from_slice(namespace.as_slice())),
+        None => ns!(""),
+    };
+    QualName {
+        ns: namespace,
+        local: Atom::from_slice(name.local_name.as_slice()),
+    }
+}
+
+/// Builds the nodes parsed from `input` into `root`.
+fn build_tree(document: JSRef<Document>, root: JSRef<Node>, input: DOMString)
+              -> Result<(), String> {
+    let mut reader = EventReader::new(MemReader::new(input.into_bytes()));
+    let mut open_nodes: Vec<Temporary<Node>> = vec!(Temporary::from_rooted(root));
+    let mut has_document_element = false;
+
+    for event in reader.events() {
+        let parent = (*open_nodes.last().unwrap()).root();
+        let node: Temporary<Node> = match event {
+            events::StartElement { name, attributes, .. } => {
+                if *parent == root && root.is_document() {
+                    if has_document_element {
+                        return Err(format!("Unexpected second root element {}", name.local_name));
+                    }
+                    has_document_element = true;
+                }
+                // FIXME: The namespace declarations themselves are not kept as xmlns
+                // attributes.
+                let elem = Element::create(qualified_name(&name), name.prefix.clone(), document,
+                                           ParserCreated).root();
+                for attribute in attributes.into_iter() {
+                    elem.set_attribute_from_parser(qualified_name(&attribute.name),
+                                                   attribute.value,
+                                                   attribute.name.prefix.clone());
+                }
+                let node: JSRef<Node> = NodeCast::from_ref(*elem);
+                assert!(parent.AppendChild(node).is_ok());
+                open_nodes.push(Temporary::from_rooted(node));
+                continue;
+            }
+            events::EndElement { name } => {
+                if open_nodes.len() == 1 {
+                    return Err(format!("Unexpected end tag {}", name.local_name));
+                }
+                // Scripts run as soon as their end tag is parsed.
+                let script = HTMLScriptElementCast::to_ref(*parent);
+                script.map(|script| script.prepare());
+                open_nodes.pop();
+                continue;
+            }
+            events::Characters(text) |
+            events::Whitespace(text) |
+            events::CData(text) => {
+                // Documents can't have text children.
+                if *parent == root && root.is_document() {
+                    continue;
+                }
+                NodeCast::from_temporary(Text::new(text, document))
+            }
+            events::Comment(text) => NodeCast::from_temporary(Comment::new(text, document)),
+            events::ProcessingInstruction { name, data } => {
+                let data = data.unwrap_or(String::new());
+                NodeCast::from_temporary(ProcessingInstruction::new(name, data, document))
+            }
+            events::Error(error) => return Err(error.to_string()),
+            events::StartDocument { .. } | events::EndDocument => continue,
+        };
+        let node = node.root();
+        assert!(parent.AppendChild(*node).is_ok());
+    }
+    Ok(())
+}
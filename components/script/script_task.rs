@@ -11,28 +11,39 @@ use dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
 use dom::bindings::codegen::Bindings::EventTargetBinding::EventTargetMethods;
+use dom::bindings::codegen::Bindings::HistoryBinding::HistoryMethods;
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::InheritTypes::{EventTargetCast, NodeCast, EventCast, ElementCast};
 use dom::bindings::conversions;
 use dom::bindings::conversions::{FromJSValConvertible, Empty};
+use dom::bindings::error::{report_pending_exception, throw_dom_exception};
 use dom::bindings::global;
 use dom::bindings::js::{JS, JSRef, RootCollection, Temporary, OptionalRootable};
 use dom::bindings::trace::JSTraceable;
-use dom::bindings::utils::{wrap_for_same_compartment, pre_wrap};
-use dom::document::{Document, HTMLDocument, DocumentHelpers, FromParser};
-use dom::element::{Element, HTMLButtonElementTypeId, HTMLInputElementTypeId};
+use dom::bindings::utils::{Reflectable, wrap_for_same_compartment, pre_wrap};
+use dom::dissimilaroriginwindow::DissimilarOriginWindow;
+use dom::document::{Document, HTMLDocument, NonHTMLDocument, DocumentHelpers, FromParser};
+use dom::element::{Element, ElementHelpers, HTMLButtonElementTypeId, HTMLInputElementTypeId};
 use dom::element::{HTMLSelectElementTypeId, HTMLTextAreaElementTypeId, HTMLOptionElementTypeId};
 use dom::event::{Event, Bubbles, DoesNotBubble, Cancelable, NotCancelable};
+use dom::filereader::{FileReader, TrustedFileReaderAddress};
+use dom::hashchangeevent::HashChangeEvent;
+use dom::history::HistoryHelpers;
 use dom::uievent::UIEvent;
 use dom::eventtarget::{EventTarget, EventTargetHelpers};
 use dom::keyboardevent::KeyboardEvent;
+use dom::messageevent::MessageEvent;
+use dom::messageport::{MessagePort, SharedPortEndpoint, TransferredPort};
 use dom::node;
 use dom::node::{ElementNodeTypeId, Node, NodeHelpers};
+use dom::popstateevent::PopStateEvent;
 use dom::storageevent::StorageEvent;
+use dom::urlhelper::UrlHelper;
+use dom::websocket::{TrustedWebSocketAddress, WebSocket};
 use dom::window::{Window, WindowHelpers};
 use dom::worker::{Worker, TrustedWorkerAddress};
 use dom::xmlhttprequest::{TrustedXHRAddress, XMLHttpRequest, XHRProgress};
-use parse::html::{InputString, InputUrl, parse_html};
+use parse::{InputString, InputUrl, is_xml_mime_type, load_document, parse_document};
 use layout_interface::{ScriptLayoutChan, LayoutChan, NoQuery, ReflowForDisplay};
 use layout_interface;
 use page::{Page, IterablePage, Frame};
@@ -47,16 +58,21 @@ use script_traits::{MouseMoveEvent, MouseUpEvent, ConstellationControlMsg, Scrip
 use script_traits::{ResizeMsg, AttachLayoutMsg, LoadMsg, ViewportMsg, SendEventMsg, StorageEventMsg};
 use script_traits::{ResizeInactiveMsg, ExitPipelineMsg, NewLayoutInfo, OpaqueScriptLayoutChannel};
 use script_traits::{ScriptControlChan, ReflowCompleteMsg, UntrustedNodeAddress, KeyEvent};
+use script_traits::{ScrollPositionEvent, HistoryTraversalMsg, TickAllAnimationsMsg};
+use script_traits::SessionHistoryChangedMsg;
+use script_traits::PostMessageMsg;
 use servo_msg::compositor_msg::{FinishedLoading, LayerId, Loading};
 use servo_msg::compositor_msg::{ScriptListener};
 use servo_msg::constellation_msg::{ConstellationChan, LoadCompleteMsg, LoadUrlMsg, NavigationDirection};
 use servo_msg::constellation_msg::{LoadData, PipelineId, Failure, FailureMsg, WindowSizeData, Key, KeyState};
 use servo_msg::constellation_msg::{KeyModifiers, SUPER, SHIFT, CONTROL, ALT, Repeated, Pressed};
-use servo_msg::constellation_msg::{Released};
+use servo_msg::constellation_msg::{Released, HistoryState, PushHistoryStateMsg, PostedMessage};
+use servo_msg::constellation_msg::{ChangeRunningAnimationsStateMsg, NoAnimationCallbacksPresent};
 use servo_msg::constellation_msg;
 use servo_net::image_cache_task::ImageCacheTask;
 use servo_net::resource_task::ResourceTask;
 use servo_net::storage_task::StorageTask;
+use servo_net::websocket::WebSocketEvent;
 use servo_util::geometry::to_frac_px;
 use servo_util::smallvec::{SmallVec1, SmallVec};
 use servo_util::str::DOMString;
@@ -64,19 +80,26 @@ use servo_util::task::spawn_named_with_send_on_failure;
 use servo_util::task_state;
 
 use geom::point::Point2D;
+use gfx::font_cache_task::FontCacheTask;
+use js::glue::JS_STRUCTURED_CLONE_VERSION;
 use js::jsapi::{JS_SetWrapObjectCallbacks, JS_SetGCZeal, JS_DEFAULT_ZEAL_FREQ, JS_GC};
 use js::jsapi::{JSContext, JSRuntime, JSTracer};
 use js::jsapi::{JS_SetGCParameter, JSGC_MAX_BYTES};
 use js::jsapi::{JS_SetGCCallback, JSGCStatus, JSGC_BEGIN, JSGC_END};
+use js::jsapi::JS_ReadStructuredClone;
+use js::jsval::UndefinedValue;
 use js::rust::{Cx, RtUtils};
 use js;
 use url::Url;
 
 use libc::size_t;
 use std::any::{Any, AnyRefExt};
+use std::ascii::AsciiExt;
+use std::boxed::BoxAny;
 use std::collections::HashSet;
 use std::comm::{channel, Sender, Receiver, Select};
 use std::mem::replace;
+use std::ptr;
 use std::rc::Rc;
 use std::u32;
 
@@ -112,11 +135,25 @@ pub enum ScriptMsg {
     XHRReleaseMsg(TrustedXHRAddress),
     /// Message sent through Worker.postMessage (only dispatched to
     /// DedicatedWorkerGlobalScope).
-    DOMMessage(*mut u64, size_t),
+    DOMMessage(*mut u64, size_t, Vec<TransferredPort>),
     /// Posts a message to the Worker object (dispatched to all tasks).
-    WorkerPostMessage(TrustedWorkerAddress, *mut u64, size_t),
+    WorkerPostMessage(TrustedWorkerAddress, *mut u64, size_t, Vec<TransferredPort>),
     /// Releases one reference to the Worker object (dispatched to all tasks).
     WorkerRelease(TrustedWorkerAddress),
+    /// Notifies the script of an event on a WebSocket connection (dispatched to all tasks).
+    WebSocketEventMsg(TrustedWebSocketAddress, WebSocketEvent),
+    /// Dispatches the messages queued on a MessagePort, if the port has not left the task since
+    /// (dispatched to all tasks).
+    MessagePortMsg(SharedPortEndpoint, uint),
+    /// Performs a read started by a FileReader, if it is still current (dispatched to all
+    /// tasks).
+    FileReaderLoadMsg(TrustedFileReaderAddress, uint),
+    /// Fires a `hashchange` event at the window of the specified pipeline, whose URL changed from
+    /// the first URL to the second (only dispatched to ScriptTask).
+    FireHashChangeMsg(PipelineId, Url, Url),
+    /// Fires a `popstate` event at the window of the specified pipeline (only dispatched to
+    /// ScriptTask).
+    FirePopStateMsg(PipelineId),
 }
 
 /// Encapsulates internal communication within the script task.
@@ -157,6 +194,8 @@ pub struct ScriptTask {
     page: DOMRefCell<Rc<Page>>,
     /// A handle to the image cache task.
     image_cache_task: ImageCacheTask,
+    /// A handle to the font cache task.
+    font_cache_task: FontCacheTask,
     /// A handle to the resource task.
     resource_task: ResourceTask,
 
@@ -267,6 +306,7 @@ impl ScriptTaskFactory for ScriptTask {
                  resource_task: ResourceTask,
                  storage_task: StorageTask,
                  image_cache_task: ImageCacheTask,
+                 font_cache_task: FontCacheTask,
                  devtools_chan: Option<DevtoolsControlChan>,
                  window_size: WindowSizeData)
                  where C: ScriptListener + Send + 'static {
@@ -285,6 +325,7 @@ impl ScriptTaskFactory for ScriptTask {
                                               resource_task,
                                               storage_task,
                                               image_cache_task,
+                                              font_cache_task,
                                               devtools_chan,
                                               window_size);
             let mut failsafe = ScriptMemoryFailsafe::new(&script_task);
@@ -317,6 +358,7 @@ impl ScriptTask {
                resource_task: ResourceTask,
                storage_task: StorageTask,
                img_cache_task: ImageCacheTask,
+               font_cache_task: FontCacheTask,
                devtools_chan: Option<DevtoolsControlChan>,
                window_size: WindowSizeData)
                -> ScriptTask {
@@ -336,7 +378,7 @@ impl ScriptTask {
                                       Some(pre_wrap));
         }
 
-        let page = Page::new(id, None, layout_chan, window_size,
+        let page = Page::new(id, None, None, layout_chan, window_size,
                              resource_task.clone(),
                              storage_task,
                              constellation_chan.clone(),
@@ -353,6 +395,7 @@ impl ScriptTask {
             page: DOMRefCell::new(Rc::new(page)),
 
             image_cache_task: img_cache_task,
+            font_cache_task: font_cache_task,
             resource_task: resource_task,
 
             port: port,
@@ -552,19 +595,30 @@ impl ScriptTask {
                 FromConstellation(ExitPipelineMsg(id)) => if self.handle_exit_pipeline_msg(id) { return false },
                 FromConstellation(ViewportMsg(..)) => panic!("should have handled ViewportMsg already"),
                 FromConstellation(StorageEventMsg(..)) => panic!("should have handled SorageEventMsg already"),
+                FromConstellation(HistoryTraversalMsg(id, state)) => self.handle_history_traversal_msg(id, state),
+                FromConstellation(SessionHistoryChangedMsg(id, index, length)) => self.handle_session_history_changed_msg(id, index, length),
+                FromConstellation(TickAllAnimationsMsg(id)) => self.handle_tick_all_animations(id),
+                FromConstellation(PostMessageMsg(id, message)) => self.handle_post_message_msg(id, message),
                 FromScript(ExitWindowMsg(id)) => self.handle_exit_window_msg(id),
                 FromConstellation(ResizeMsg(..)) => panic!("should have handled ResizeMsg already"),
                 FromScript(XHRProgressMsg(addr, progress)) => XMLHttpRequest::handle_progress(addr, progress),
                 FromScript(XHRReleaseMsg(addr)) => XMLHttpRequest::handle_release(addr),
                 FromScript(DOMMessage(..)) => panic!("unexpected message"),
-                FromScript(WorkerPostMessage(addr, data, nbytes)) => Worker::handle_message(addr, data, nbytes),
+                FromScript(WorkerPostMessage(addr, data, nbytes, ports)) => Worker::handle_message(addr, data, nbytes, ports),
                 FromScript(WorkerRelease(addr)) => Worker::handle_release(addr),
+                FromScript(WebSocketEventMsg(addr, event)) => WebSocket::handle_event(addr, event),
+                FromScript(MessagePortMsg(endpoint, generation)) => MessagePort::handle_notification(endpoint, generation),
+                FromScript(FileReaderLoadMsg(addr, generation)) => FileReader::handle_load(addr, generation),
+                FromScript(FireHashChangeMsg(id, old_url, new_url)) => self.handle_fire_hashchange_msg(id, old_url, new_url),
+                FromScript(FirePopStateMsg(id)) => self.handle_fire_popstate_msg(id),
                 FromDevtools(EvaluateJS(id, s, reply)) => self.handle_evaluate_js(id, s, reply),
                 FromDevtools(GetRootNode(id, reply)) => self.handle_get_root_node(id, reply),
                 FromDevtools(GetDocumentElement(id, reply)) => self.handle_get_document_element(id, reply),
                 FromDevtools(GetChildren(id, node_id, reply)) => self.handle_get_children(id, node_id, reply),
                 FromDevtools(GetLayout(id, node_id, reply)) => self.handle_get_layout(id, node_id, reply),
             }
+
+            self.perform_a_microtask_checkpoint();
         }
 
         // Now process any pending reflows.
@@ -575,6 +629,25 @@ impl ScriptTask {
         true
     }
 
+    // http://www.whatwg.org/html/#perform-a-microtask-checkpoint
+    /// Performs a microtask checkpoint for every page, until no observer has records left.
+    fn perform_a_microtask_checkpoint(&self) {
+        let pages: Vec<Rc<Page>> = self.page.borrow().iter().collect();
+        loop {
+            let mut notified = false;
+            for page in pages.iter() {
+                let window = match *page.frame() {
+                    Some(ref frame) => Temporary::new(frame.window.clone()),
+                    None => continue,
+                }.root();
+                notified = window.perform_a_microtask_checkpoint() || notified;
+            }
+            if !notified {
+                break;
+            }
+        }
+    }
+
     fn handle_evaluate_js(&self, pipeline: PipelineId, eval: String, reply: Sender<EvaluateJSReply>) {
         let page = get_page(&*self.page.borrow(), pipeline);
         let frame = page.frame();
@@ -659,7 +732,7 @@ impl ScriptTask {
             task's page tree. This is a bug.");
         let new_page = {
             let window_size = parent_page.window_size.get();
-            Page::new(new_pipeline_id, Some(subpage_id),
+            Page::new(new_pipeline_id, Some(subpage_id), Some(parent_page.downgrade()),
                       LayoutChan(layout_chan.downcast_ref::<Sender<layout_interface::Msg>>().unwrap().clone()),
                       window_size,
                       parent_page.resource_task.clone(),
@@ -794,7 +867,8 @@ impl ScriptTask {
                                  self.chan.clone(),
                                  self.control_chan.clone(),
                                  self.compositor.borrow_mut().dup(),
-                                 self.image_cache_task.clone()).root();
+                                 self.image_cache_task.clone(),
+                                 self.font_cache_task.clone()).root();
         let doc_url = if is_javascript {
             let doc_url = last_url.unwrap_or_else(|| {
                 Url::parse("about:blank").unwrap()
@@ -804,22 +878,45 @@ impl ScriptTask {
         } else {
             url.clone()
         };
-        let document = Document::new(*window, Some(doc_url), HTMLDocument,
-                                     None, FromParser).root();
+        // Wait for the response metadata, since its content type decides the kind of document
+        // to create and the parser to use.
+        let load_response = if !is_javascript {
+            Some(load_document(&self.resource_task, url.clone(), Some(load_data)))
+        } else {
+            None
+        };
+        let content_type = load_response.as_ref().and_then(|load_response| {
+            load_response.metadata.content_type.clone()
+        });
+        // Images, SVG ones included, are shown in an HTML document.
+        let is_html_document = match content_type {
+            Some((ref type_, ref subtype)) if !type_.as_slice().eq_ignore_ascii_case("image") &&
+                                              is_xml_mime_type(type_.as_slice(),
+                                                               subtype.as_slice()) => {
+                NonHTMLDocument
+            }
+            _ => HTMLDocument,
+        };
+        let content_type = content_type.map(|(type_, subtype)| format!("{}/{}", type_, subtype));
+        let document = Document::new(*window, Some(doc_url), is_html_document,
+                                     content_type, FromParser).root();
 
         window.init_browser_context(*document);
 
         self.compositor.borrow_mut().set_ready_state(pipeline_id, Loading);
 
-        let parser_input = if !is_javascript {
-            InputUrl(url.clone())
-        } else {
-            let evalstr = load_data.url.non_relative_scheme_data().unwrap();
-            let jsval = window.evaluate_js_with_result(evalstr);
-            let strval = FromJSValConvertible::from_jsval(self.get_cx(), jsval, Empty);
-            InputString(strval.unwrap_or("".to_string()))
+        let parser_input = match load_response {
+            Some(load_response) => InputUrl(load_response),
+            None => {
+                let evalstr = url.non_relative_scheme_data().unwrap();
+                let jsval = window.evaluate_js_with_result(evalstr);
+                let strval = FromJSValConvertible::from_jsval(self.get_cx(), jsval, Empty);
+                InputString(strval.unwrap_or("".to_string()))
+            }
         };
 
+        // The document being replaced is unloaded.
+        revoke_blob_urls(&*page);
         {
             // Create the root frame.
             let mut frame = page.mut_frame();
@@ -829,7 +926,7 @@ impl ScriptTask {
             });
         }
 
-        parse_html(&*page, *document, parser_input, self.resource_task.clone(), Some(load_data));
+        parse_document(&*page, *document, parser_input);
         url = page.get_url().clone();
 
         document.set_ready_state(DocumentReadyStateValues::Interactive);
@@ -929,6 +1026,10 @@ impl ScriptTask {
             KeyEvent(key, state, modifiers) => {
                 self.dispatch_key_event(key, state, modifiers, pipeline_id);
             }
+
+            ScrollPositionEvent(layer_id, position) => {
+                self.handle_scroll_position_event(pipeline_id, layer_id, position);
+            }
         }
     }
 
@@ -1012,14 +1113,193 @@ impl ScriptTask {
     /// for the given pipeline.
     fn trigger_fragment(&self, pipeline_id: PipelineId, url: Url) {
         let page = get_page(&*self.page.borrow(), pipeline_id);
-        match page.find_fragment_node(url.fragment.unwrap()).root() {
+        let window = match *page.frame() {
+            Some(ref frame) => frame.window.root(),
+            None => return,
+        };
+        let old_url = page.get_url();
+        if url != old_url {
+            // Navigating to another fragment adds a same-document session history entry.
+            let document = window.Document().root();
+            document.set_url(url.clone());
+            page.set_url(url.clone());
+            let _ = window.History().root().set_state(None);
+            let ConstellationChan(ref chan) = self.constellation_chan;
+            chan.send(PushHistoryStateMsg(pipeline_id, HistoryState {
+                url: url.clone(),
+                data: None,
+            }));
+            page.add_session_history_entry();
+        }
+
+        match page.find_fragment_node(url.fragment.clone().unwrap()).root() {
             Some(node) => {
                 self.scroll_fragment_point(pipeline_id, *node);
             }
             None => {}
         }
+
+        self.queue_hashchange_event(pipeline_id, old_url, url);
+    }
+
+    /// The session history was traversed to another same-document entry of the given pipeline,
+    /// whose URL and state object are restored.
+    fn handle_history_traversal_msg(&self, pipeline_id: PipelineId, state: HistoryState) {
+        let page = get_page(&*self.page.borrow(), pipeline_id);
+        let window = match *page.frame() {
+            Some(ref frame) => frame.window.root(),
+            None => return,
+        };
+        let document = window.Document().root();
+        let old_url = page.get_url();
+        document.set_url(state.url.clone());
+        page.set_url(state.url.clone());
+
+        match window.History().root().set_state(state.data) {
+            Ok(()) => {}
+            Err(error) => {
+                let cx = window.get_cx();
+                throw_dom_exception(cx, &global::Window(*window), error);
+                report_pending_exception(cx, window.reflector().get_jsobject());
+            }
+        }
+
+        let ScriptChan(ref chan) = self.chan;
+        chan.send(FirePopStateMsg(pipeline_id));
+        self.queue_hashchange_event(pipeline_id, old_url, state.url);
+    }
+
+    /// Queues a task to fire a `hashchange` event at the window of the given pipeline, whose URL
+    /// changed from `old_url` to `new_url`, if only their fragments differ.
+    fn queue_hashchange_event(&self, pipeline_id: PipelineId, old_url: Url, new_url: Url) {
+        if old_url.fragment == new_url.fragment {
+            return;
+        }
+        let (mut old_document_url, mut new_document_url) = (old_url.clone(), new_url.clone());
+        old_document_url.fragment = None;
+        new_document_url.fragment = None;
+        if old_document_url != new_document_url {
+            return;
+        }
+        let ScriptChan(ref chan) = self.chan;
+        chan.send(FireHashChangeMsg(pipeline_id, old_url, new_url));
+    }
+
+    fn handle_fire_hashchange_msg(&self, pipeline_id: PipelineId, old_url: Url, new_url: Url) {
+        // The document may have been discarded since the task was queued.
+        let page = match self.page.borrow().find(pipeline_id) {
+            Some(page) => page,
+            None => return,
+        };
+        let window = match *page.frame() {
+            Some(ref frame) => frame.window.root(),
+            None => return,
+        };
+        let event = HashChangeEvent::new(global::Window(*window), "hashchange".to_string(),
+                                         false, false, old_url.serialize(),
+                                         new_url.serialize()).root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(*window);
+        let _ = target.DispatchEvent(EventCast::from_ref(*event));
+    }
+
+    fn handle_fire_popstate_msg(&self, pipeline_id: PipelineId) {
+        // The document may have been discarded since the task was queued.
+        let page = match self.page.borrow().find(pipeline_id) {
+            Some(page) => page,
+            None => return,
+        };
+        let window = match *page.frame() {
+            Some(ref frame) => frame.window.root(),
+            None => return,
+        };
+        let state = window.History().root().State(window.get_cx());
+        let event = PopStateEvent::new(global::Window(*window), "popstate".to_string(),
+                                       false, false, state).root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(*window);
+        let _ = target.DispatchEvent(EventCast::from_ref(*event));
+    }
+
+    fn handle_session_history_changed_msg(&self, pipeline_id: PipelineId, index: uint,
+                                          length: uint) {
+        // The pipeline may be exiting.
+        match self.page.borrow().find(pipeline_id) {
+            Some(page) => page.session_history.set((index, length)),
+            None => {}
+        }
+    }
+
+    /// Delivers a message posted with `window.postMessage()` to a window of this script task.
+    fn handle_post_message_msg(&self, pipeline_id: PipelineId, message: PostedMessage) {
+        let page = get_page(&*self.page.borrow(), pipeline_id);
+        let frame = page.frame();
+        // The target window may have been discarded since the message was posted.
+        let window = match *frame {
+            Some(ref frame) => frame.window.root(),
+            None => return,
+        };
+        let document = window.Document().root();
+
+        // The target window may have navigated to another origin since the message was posted.
+        match message.target_origin {
+            Some(ref target_origin) if !UrlHelper::SameOrigin(target_origin, &*document.url()) => {
+                return;
+            }
+            _ => {}
+        }
+
+        let mut data = UndefinedValue();
+        unsafe {
+            assert!(JS_ReadStructuredClone(
+                window.get_cx(), message.data.as_ptr(), (message.data.len() * 8) as size_t,
+                JS_STRUCTURED_CLONE_VERSION, &mut data,
+                ptr::null(), ptr::null_mut()) != 0);
+        }
+
+        let ports = *message.ports.downcast::<Vec<TransferredPort>>().unwrap();
+        let ports: Vec<_> = ports.into_iter().map(|port| {
+            MessagePort::new_transferred(&global::Window(*window), port).root()
+        }).collect();
+
+        // A source window that lives in another script task can only be posted messages to.
+        let local_source = self.page.borrow().find(message.source).and_then(|page| {
+            let frame = page.frame();
+            let window = frame.as_ref().map(|frame| Temporary::new(frame.window.clone()));
+            window
+        }).root();
+        let remote_source = match local_source {
+            Some(_) => None,
+            None => Some(DissimilarOriginWindow::new(*window, message.source).root()),
+        };
+        let source: Option<JSRef<EventTarget>> = match (&local_source, &remote_source) {
+            (&Some(ref source), _) => Some(EventTargetCast::from_ref(**source)),
+            (_, &Some(ref source)) => Some(EventTargetCast::from_ref(**source)),
+            _ => None,
+        };
+
+        let event = MessageEvent::new_with_ports(global::Window(*window), "message".to_string(),
+                                                 false, false, data, message.origin,
+                                                 "".to_string(), source,
+                                                 ports.iter().map(|port| **port).collect())
+                                                 .root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(*window);
+        let _ = target.DispatchEvent(EventCast::from_ref(*event));
     }
 
+    /// Runs the animation frame callbacks of a pipeline on a tick from the compositor.
+    fn handle_tick_all_animations(&self, pipeline_id: PipelineId) {
+        let page = get_page(&*self.page.borrow(), pipeline_id);
+        let document = match *page.frame() {
+            Some(ref frame) => frame.document.root(),
+            None => {
+                // Without a document there are no callbacks to run; stop the ticks.
+                let ConstellationChan(ref chan) = page.constellation_chan;
+                chan.send(ChangeRunningAnimationsStateMsg(pipeline_id,
+                                                          NoAnimationCallbacksPresent));
+                return
+            }
+        };
+        document.invoke_animation_callbacks();
+    }
 
     fn handle_resize_event(&self, pipeline_id: PipelineId, new_size: WindowSizeData) {
         let window = {
@@ -1190,6 +1470,39 @@ impl ScriptTask {
             None => {}
         }
     }
+
+    /// Records the new scroll position of a scroll container and fires a `scroll` event at it.
+    /// http://dev.w3.org/csswg/cssom-view/#scrolling-events
+    fn handle_scroll_position_event(&self,
+                                    pipeline_id: PipelineId,
+                                    layer_id: LayerId,
+                                    position: Point2D<f32>) {
+        let node_address = match layer_id.scroll_root_node_address() {
+            Some(node_address) => node_address as UntrustedNodeAddress,
+            None => return,
+        };
+        let page = get_page(&*self.page.borrow(), pipeline_id);
+        let node = node::from_untrusted_node_address(self.js_runtime.ptr, node_address).root();
+        let element: JSRef<Element> = match ElementCast::to_ref(*node) {
+            Some(element) => element,
+            None => return,
+        };
+        if !element.update_scroll_position(position) {
+            return
+        }
+
+        match *page.frame() {
+            Some(ref frame) => {
+                let window = frame.window.root();
+                let event = Event::new(global::Window(*window),
+                                       "scroll".to_string(),
+                                       DoesNotBubble, NotCancelable).root();
+                let target: JSRef<EventTarget> = EventTargetCast::from_ref(element);
+                let _ = target.dispatch_event_with_target(None, *event);
+            }
+            None => {}
+        }
+    }
 }
 
 /// Shuts down layout for the given page tree.
@@ -1207,6 +1520,7 @@ fn shut_down_layout(page_tree: &Rc<Page>, rt: *mut JSRuntime) {
 
     // Remove our references to the DOM objects in this page tree.
     for page in page_tree.iter() {
+        revoke_blob_urls(&*page);
         *page.mut_frame() = None;
     }
 
@@ -1228,6 +1542,13 @@ fn shut_down_layout(page_tree: &Rc<Page>, rt: *mut JSRuntime) {
     }
 }
 
+/// Revokes the blob URLs created by the document of the given page, which is being unloaded.
+fn revoke_blob_urls(page: &Page) {
+    match *page.frame() {
+        Some(ref frame) => frame.window.root().blob_urls().revoke_all(&page.resource_task),
+        None => {}
+    }
+}
 
 fn get_page(page: &Rc<Page>, pipeline_id: PipelineId) -> Rc<Page> {
     page.find(pipeline_id).expect("ScriptTask: received an event \
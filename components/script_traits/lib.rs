@@ -10,6 +10,7 @@
 
 extern crate devtools_traits;
 extern crate geom;
+extern crate gfx;
 extern crate libc;
 extern crate "msg" as servo_msg;
 extern crate "net" as servo_net;
@@ -23,10 +24,12 @@ extern crate serialize;
 //   that these modules won't have to depend on script.
 
 use devtools_traits::DevtoolsControlChan;
+use gfx::font_cache_task::FontCacheTask;
 use libc::c_void;
 use servo_msg::constellation_msg::{ConstellationChan, PipelineId, Failure, WindowSizeData};
+use servo_msg::constellation_msg::{HistoryState, PostedMessage};
 use servo_msg::constellation_msg::{LoadData, SubpageId, Key, KeyState, KeyModifiers};
-use servo_msg::compositor_msg::ScriptListener;
+use servo_msg::compositor_msg::{LayerId, ScriptListener};
 use servo_net::image_cache_task::ImageCacheTask;
 use servo_net::resource_task::ResourceTask;
 use servo_net::storage_task::StorageTask;
@@ -69,6 +72,16 @@ pub enum ConstellationControlMsg {
     ReflowCompleteMsg(PipelineId, uint),
     ViewportMsg(PipelineId, Rect<f32>),
     StorageEventMsg(Url, PipelineId, PipelineId, Option<DOMString>, Option<DOMString>, Option<DOMString>),
+    /// Notifies script that the session history was traversed to another same-document entry
+    /// of the specified pipeline.
+    HistoryTraversalMsg(PipelineId, HistoryState),
+    /// Notifies script of the index of the current entry of the joint session history and of
+    /// its number of entries, for the specified pipeline.
+    SessionHistoryChangedMsg(PipelineId, uint, uint),
+    /// Runs the animation frame callbacks of the specified pipeline.
+    TickAllAnimationsMsg(PipelineId),
+    /// Delivers a message posted with `window.postMessage()` to the window of the given pipeline.
+    PostMessageMsg(PipelineId, PostedMessage),
 }
 
 /// Events from the compositor that the script task needs to know about
@@ -80,6 +93,8 @@ pub enum CompositorEvent {
     MouseUpEvent(uint, Point2D<f32>),
     MouseMoveEvent(Point2D<f32>),
     KeyEvent(Key, KeyState, KeyModifiers),
+    /// The contents of the scroll root layer with the given ID are scrolled to the given position.
+    ScrollPositionEvent(LayerId, Point2D<f32>),
 }
 
 /// An opaque wrapper around script<->layout channels to avoid leaking message types into
@@ -108,6 +123,7 @@ pub trait ScriptTaskFactory {
                  resource_task: ResourceTask,
                  storage_task: StorageTask,
                  image_cache_task: ImageCacheTask,
+                 font_cache_task: FontCacheTask,
                  devtools_chan: Option<DevtoolsControlChan>,
                  window_size: WindowSizeData)
                  where C: ScriptListener + Send;
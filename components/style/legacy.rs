@@ -15,5 +15,9 @@ pub enum LengthAttribute {
 pub enum IntegerAttribute {
     /// `<input size>`
     SizeIntegerAttribute,
+    /// `<td colspan>`
+    ColSpanIntegerAttribute,
+    /// `<td rowspan>`
+    RowSpanIntegerAttribute,
 }
 
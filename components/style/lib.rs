@@ -34,7 +34,7 @@ extern crate "util" as servo_util;
 
 
 // Public API
-pub use media_queries::{Device, Screen};
+pub use media_queries::{Device, Screen, Print};
 pub use stylesheets::{Stylesheet, iter_font_face_rules};
 pub use selector_matching::{Stylist, StylesheetOrigin, UserAgentOrigin, AuthorOrigin, UserOrigin};
 pub use selector_matching::{DeclarationBlock, CommonStyleAffectingAttributes};
@@ -54,7 +54,9 @@ pub use selectors::{AttrSelector, NamespaceConstraint, SpecificNamespace, AnyNam
 pub use selectors::{SimpleSelector,LocalNameSelector};
 pub use cssparser::{Color, RGBA};
 pub use legacy::{IntegerAttribute, LengthAttribute, SizeIntegerAttribute, WidthLengthAttribute};
+pub use legacy::{ColSpanIntegerAttribute, RowSpanIntegerAttribute};
 pub use font_face::{Source, LocalSource, UrlSource_};
+pub use page::{PageStyle, PageSize, AutoPageSize, AbsolutePageSize};
 
 mod stylesheets;
 mod errors;
@@ -66,4 +68,5 @@ mod node;
 mod media_queries;
 mod parsing_utils;
 mod font_face;
+mod page;
 mod legacy;
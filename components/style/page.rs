@@ -0,0 +1,419 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! `@page` rules, which give the size and margins of the page boxes of paginated output.
+//!
+//! "CSS Paged Media" refers to the W3C "CSS Paged Media Module Level 3" specification available
+//! here:
+//!
+//!   http://dev.w3.org/csswg/css-page/
+
+use cssparser::ast::*;
+use cssparser::parse_declaration_list;
+use errors::{ErrorLoggerIterator, log_css_error};
+use geom::side_offsets::SideOffsets2D;
+use geom::size::Size2D;
+use std::ascii::AsciiExt;
+use properties::common_types::*;
+use properties::longhands;
+use stylesheets::{CSSRule, CSSFontFaceRule, CSSMediaRule, CSSPageRule, CSSStyleRule};
+use media_queries::Device;
+
+/// The orientation of a page box, if the `size` descriptor gives one without a size.
+#[deriving(Clone, PartialEq, Show)]
+pub enum PageOrientation {
+    Portrait,
+    Landscape,
+}
+
+/// The value of the `size` descriptor. CSS Paged Media § 7.1.
+#[deriving(Clone, PartialEq)]
+pub enum PageSize {
+    /// `auto`, `portrait` or `landscape`: the size of the target medium, turned to the given
+    /// orientation if there is one.
+    AutoPageSize(Option<PageOrientation>),
+    /// An absolute width and height.
+    AbsolutePageSize(Size2D<Au>),
+}
+
+/// A pseudo-class that restricts an `@page` rule to some pages. CSS Paged Media § 5.
+///
+/// FIXME: Page names and `:blank` are not supported; rules that use them are dropped.
+#[deriving(Clone, PartialEq, Show)]
+pub enum PageSelector {
+    FirstPageSelector,
+    LeftPageSelector,
+    RightPageSelector,
+}
+
+impl PageSelector {
+    /// Returns true if the selector matches the page at the given index. The first page is a
+    /// right page.
+    pub fn matches(&self, page_index: uint) -> bool {
+        match *self {
+            FirstPageSelector => page_index == 0,
+            LeftPageSelector => page_index % 2 == 1,
+            RightPageSelector => page_index % 2 == 0,
+        }
+    }
+}
+
+/// Returns the specificity of a page selector, by which rules of the same origin cascade.
+/// CSS Paged Media § 5.2.
+pub fn page_selector_specificity(selector: &Option<PageSelector>) -> uint {
+    match *selector {
+        None => 0,
+        Some(LeftPageSelector) | Some(RightPageSelector) => 1,
+        Some(FirstPageSelector) => 2,
+    }
+}
+
+/// The descriptors of one `@page` rule. Descriptors that the rule does not set are `None`.
+pub struct PageRule {
+    pub selector: Option<PageSelector>,
+    pub size: Option<PageSize>,
+    pub margin: SideOffsets2D<Option<Au>>,
+}
+
+/// The page style that results from cascading all the `@page` rules that apply.
+#[deriving(Clone)]
+pub struct PageStyle {
+    pub size: PageSize,
+    pub margin: SideOffsets2D<Au>,
+}
+
+impl PageStyle {
+    pub fn new() -> PageStyle {
+        PageStyle {
+            size: AutoPageSize(None),
+            margin: SideOffsets2D::zero(),
+        }
+    }
+
+    /// Applies the descriptors that the given rule sets, overriding the ones set before.
+    pub fn apply(&mut self, rule: &PageRule) {
+        match rule.size {
+            Some(ref size) => self.size = size.clone(),
+            None => {}
+        }
+        self.margin.top = rule.margin.top.unwrap_or(self.margin.top);
+        self.margin.right = rule.margin.right.unwrap_or(self.margin.right);
+        self.margin.bottom = rule.margin.bottom.unwrap_or(self.margin.bottom);
+        self.margin.left = rule.margin.left.unwrap_or(self.margin.left);
+    }
+
+    /// Returns the size of the page box, given the size of the pages of the target medium.
+    pub fn page_box_size(&self, medium_size: Size2D<Au>) -> Size2D<Au> {
+        let is_landscape = medium_size.width > medium_size.height;
+        match self.size {
+            AbsolutePageSize(size) => size,
+            AutoPageSize(Some(Portrait)) if is_landscape => {
+                Size2D(medium_size.height, medium_size.width)
+            }
+            AutoPageSize(Some(Landscape)) if !is_landscape => {
+                Size2D(medium_size.height, medium_size.width)
+            }
+            AutoPageSize(_) => medium_size,
+        }
+    }
+}
+
+pub fn iter_page_rules_inner(rules: &[CSSRule], device: &Device, callback: |&PageRule|) {
+    for rule in rules.iter() {
+        match *rule {
+            CSSStyleRule(_) | CSSFontFaceRule(_) => {},
+            CSSMediaRule(ref rule) => if rule.media_queries.evaluate(device) {
+                iter_page_rules_inner(rule.rules.as_slice(), device, |r| callback(r))
+            },
+            CSSPageRule(ref rule) => callback(rule),
+        }
+    }
+}
+
+pub fn parse_page_rule(rule: AtRule, parent_rules: &mut Vec<CSSRule>) {
+    let selector = match parse_page_selector(rule.prelude.as_slice()) {
+        Ok(selector) => selector,
+        Err(()) => {
+            log_css_error(rule.location, "Unsupported @page selector");
+            return
+        }
+    };
+
+    let block = match rule.block {
+        Some(block) => block,
+        None => {
+            log_css_error(rule.location, "Invalid @page rule");
+            return
+        }
+    };
+
+    let mut page_rule = PageRule {
+        selector: selector,
+        size: None,
+        margin: SideOffsets2D::new(None, None, None, None),
+    };
+
+    for item in ErrorLoggerIterator(parse_declaration_list(block.into_iter())) {
+        match item {
+            DeclAtRule(rule) => {
+                log_css_error(rule.location,
+                              format!("Unsupported at-rule in @page: @{:s}", rule.name).as_slice())
+            }
+            Declaration_(Declaration{ location, name, value, important }) => {
+                if important {
+                    log_css_error(location, "!important is not allowed on @page descriptors");
+                    continue
+                }
+                let name_lower = name.as_slice().to_ascii_lower();
+                let values: Vec<&ComponentValue> = value.as_slice().skip_whitespace().collect();
+                let values = values.as_slice();
+                let result = match name_lower.as_slice() {
+                    "size" => parse_size(values).map(|size| page_rule.size = Some(size)),
+                    "margin" => {
+                        parse_margin(values).map(|margin| {
+                            page_rule.margin = SideOffsets2D::new(Some(margin.top),
+                                                                  Some(margin.right),
+                                                                  Some(margin.bottom),
+                                                                  Some(margin.left))
+                        })
+                    }
+                    "margin-top" => {
+                        parse_one_length(values).map(|v| page_rule.margin.top = Some(v))
+                    }
+                    "margin-right" => {
+                        parse_one_length(values).map(|v| page_rule.margin.right = Some(v))
+                    }
+                    "margin-bottom" => {
+                        parse_one_length(values).map(|v| page_rule.margin.bottom = Some(v))
+                    }
+                    "margin-left" => {
+                        parse_one_length(values).map(|v| page_rule.margin.left = Some(v))
+                    }
+                    _ => {
+                        log_css_error(location,
+                                      format!("Unsupported declaration {:s}", name).as_slice());
+                        continue
+                    }
+                };
+                if result.is_err() {
+                    log_css_error(location, format!("Invalid {:s} in @page", name).as_slice())
+                }
+            }
+        }
+    }
+
+    parent_rules.push(CSSPageRule(page_rule))
+}
+
+/// Nothing, or one of `:first`, `:left` and `:right`.
+fn parse_page_selector(prelude: &[ComponentValue]) -> Result<Option<PageSelector>, ()> {
+    let values: Vec<&ComponentValue> = prelude.skip_whitespace().collect();
+    match values.as_slice() {
+        [] => Ok(None),
+        [&Colon, &Ident(ref name)] => {
+            match name.as_slice().to_ascii_lower().as_slice() {
+                "first" => Ok(Some(FirstPageSelector)),
+                "left" => Ok(Some(LeftPageSelector)),
+                "right" => Ok(Some(RightPageSelector)),
+                _ => Err(()),
+            }
+        }
+        _ => Err(()),
+    }
+}
+
+/// Parses a length. As in media queries, `em` units are relative to the initial font size.
+///
+/// FIXME: Percentages, which refer to the page box, and `auto` are not supported.
+fn parse_length(value: &ComponentValue) -> Result<Au, ()> {
+    let length = try!(specified::Length::parse(value));
+    let initial_font_size = longhands::font_size::get_initial_value();
+    Ok(computed::compute_Au_with_font_size(length, initial_font_size))
+}
+
+fn parse_one_length(values: &[&ComponentValue]) -> Result<Au, ()> {
+    if values.len() != 1 {
+        return Err(())
+    }
+    parse_length(values[0])
+}
+
+/// <length>{1,4}, with the same meaning as for the `margin` property.
+fn parse_margin(values: &[&ComponentValue]) -> Result<SideOffsets2D<Au>, ()> {
+    if values.len() == 0 || values.len() > 4 {
+        return Err(())
+    }
+    let lengths: Vec<Au> = try!(values.iter().map(|value| parse_length(*value)).collect());
+    let top = lengths[0];
+    let right = if lengths.len() > 1 { lengths[1] } else { top };
+    let bottom = if lengths.len() > 2 { lengths[2] } else { top };
+    let left = if lengths.len() > 3 { lengths[3] } else { right };
+    Ok(SideOffsets2D::new(top, right, bottom, left))
+}
+
+/// auto | <length>{1,2} | [ <page-size> || [ portrait | landscape ] ]
+fn parse_size(values: &[&ComponentValue]) -> Result<PageSize, ()> {
+    let mut named_size = None;
+    let mut orientation = None;
+    let mut lengths = vec!();
+    for value in values.iter() {
+        match **value {
+            Ident(ref keyword) => {
+                let keyword = keyword.as_slice().to_ascii_lower();
+                match keyword.as_slice() {
+                    "auto" if values.len() == 1 => return Ok(AutoPageSize(None)),
+                    "portrait" if orientation.is_none() => orientation = Some(Portrait),
+                    "landscape" if orientation.is_none() => orientation = Some(Landscape),
+                    _ if named_size.is_none() => {
+                        named_size = Some(try!(parse_named_page_size(keyword.as_slice())))
+                    }
+                    _ => return Err(()),
+                }
+            }
+            _ => lengths.push(try!(parse_length(*value))),
+        }
+    }
+
+    let size = match (lengths.len(), named_size) {
+        (0, Some(size)) => size,
+        (0, None) => return Ok(AutoPageSize(orientation)),
+        (1, None) if orientation.is_none() => Size2D(lengths[0], lengths[0]),
+        (2, None) if orientation.is_none() => Size2D(lengths[0], lengths[1]),
+        _ => return Err(()),
+    };
+    Ok(AbsolutePageSize(match orientation {
+        Some(Landscape) if size.width < size.height => Size2D(size.height, size.width),
+        Some(Portrait) if size.width > size.height => Size2D(size.height, size.width),
+        _ => size,
+    }))
+}
+
+/// Returns the portrait size of the given <page-size> keyword. CSS Paged Media § 7.1.1.
+fn parse_named_page_size(keyword: &str) -> Result<Size2D<Au>, ()> {
+    let (width, height, unit) = match keyword {
+        "a5" => (148., 210., "mm"),
+        "a4" => (210., 297., "mm"),
+        "a3" => (297., 420., "mm"),
+        "b5" => (176., 250., "mm"),
+        "b4" => (250., 353., "mm"),
+        "letter" => (8.5, 11., "in"),
+        "legal" => (8.5, 14., "in"),
+        "ledger" => (11., 17., "in"),
+        _ => return Err(()),
+    };
+    let to_au = |value: CSSFloat| {
+        match specified::Length::parse_dimension(value, unit) {
+            Ok(specified::Au_(au)) => au,
+            _ => panic!("Unexpected unit for a page size"),
+        }
+    };
+    Ok(Size2D(to_au(width), to_au(height)))
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::side_offsets::SideOffsets2D;
+    use geom::size::{Size2D, TypedSize2D};
+    use media_queries::{Device, Print};
+    use properties::common_types::*;
+    use selector_matching::AuthorOrigin;
+    use stylesheets::{iter_page_rules, Stylesheet};
+    use super::*;
+    use url::Url;
+
+    fn page_style(css: &str) -> PageStyle {
+        let url = Url::parse("http://localhost").unwrap();
+        let stylesheet = Stylesheet::from_str(css, url, AuthorOrigin);
+        let device = Device::new(Print, TypedSize2D(816.0, 1056.0));
+        let mut page_style = PageStyle::new();
+        iter_page_rules(&stylesheet, &device, |rule| page_style.apply(rule));
+        page_style
+    }
+
+    fn page_selectors(css: &str) -> Vec<Option<PageSelector>> {
+        let url = Url::parse("http://localhost").unwrap();
+        let stylesheet = Stylesheet::from_str(css, url, AuthorOrigin);
+        let device = Device::new(Print, TypedSize2D(816.0, 1056.0));
+        let mut selectors = vec!();
+        iter_page_rules(&stylesheet, &device, |rule| selectors.push(rule.selector.clone()));
+        selectors
+    }
+
+    #[test]
+    fn test_size_auto() {
+        assert!(page_style("@page { size: auto }").size == AutoPageSize(None));
+        assert!(page_style("@page { size: landscape }").size == AutoPageSize(Some(Landscape)));
+        assert!(page_style("@page { }").size == AutoPageSize(None));
+    }
+
+    #[test]
+    fn test_size_lengths() {
+        let square = Size2D(Au::from_px(100), Au::from_px(100));
+        assert!(page_style("@page { size: 100px }").size == AbsolutePageSize(square));
+        let size = Size2D(Au::from_px(100), Au::from_px(200));
+        assert!(page_style("@page { size: 100px 200px }").size == AbsolutePageSize(size));
+    }
+
+    #[test]
+    fn test_size_named() {
+        let a4 = page_style("@page { size: 210mm 297mm }").size;
+        assert!(page_style("@page { size: a4 }").size == a4);
+        assert!(page_style("@page { size: A4 portrait }").size == a4);
+        let a4_landscape = page_style("@page { size: 297mm 210mm }").size;
+        assert!(page_style("@page { size: a4 landscape }").size == a4_landscape);
+        assert!(page_style("@page { size: landscape a4 }").size == a4_landscape);
+    }
+
+    #[test]
+    fn test_size_invalid() {
+        let css = "@page { size: 100px } @page { size: 50px a4 }";
+        let square = Size2D(Au::from_px(100), Au::from_px(100));
+        assert!(page_style(css).size == AbsolutePageSize(square));
+        for value in ["portrait landscape", "a4 a5", "a6", "auto 100px", "1px 2px 3px"].iter() {
+            let css = format!("@page {{ size: {} }}", value);
+            assert!(page_style(css.as_slice()).size == AutoPageSize(None), css);
+        }
+    }
+
+    #[test]
+    fn test_margin() {
+        let px = |top, right, bottom, left| {
+            SideOffsets2D::new(Au::from_px(top), Au::from_px(right),
+                               Au::from_px(bottom), Au::from_px(left))
+        };
+        assert!(page_style("@page { margin: 1px }").margin == px(1, 1, 1, 1));
+        assert!(page_style("@page { margin: 1px 2px }").margin == px(1, 2, 1, 2));
+        assert!(page_style("@page { margin: 1px 2px 3px }").margin == px(1, 2, 3, 2));
+        assert!(page_style("@page { margin: 1px 2px 3px 4px }").margin == px(1, 2, 3, 4));
+        assert!(page_style("@page { margin: 1px 2px 3px 4px 5px }").margin == px(0, 0, 0, 0));
+        assert!(page_style("@page { margin: 1px; margin-left: 4px }").margin == px(1, 1, 1, 4));
+        assert!(page_style("@page { margin: 1px } @page { margin-top: 5px }").margin ==
+                px(5, 1, 1, 1));
+    }
+
+    #[test]
+    fn test_page_box_size() {
+        let portrait = Size2D(Au::from_px(816), Au::from_px(1056));
+        let landscape = Size2D(Au::from_px(1056), Au::from_px(816));
+        assert!(page_style("@page { }").page_box_size(portrait) == portrait);
+        assert!(page_style("@page { size: landscape }").page_box_size(portrait) == landscape);
+        assert!(page_style("@page { size: portrait }").page_box_size(portrait) == portrait);
+        assert!(page_style("@page { size: portrait }").page_box_size(landscape) == portrait);
+        let size = Size2D(Au::from_px(100), Au::from_px(200));
+        assert!(page_style("@page { size: 100px 200px }").page_box_size(landscape) == size);
+    }
+
+    #[test]
+    fn test_page_selectors() {
+        let css = "@page { } @page :first { } @page :LEFT { } @page :right { } \
+                   @page :blank { } @page named { }";
+        assert!(page_selectors(css) == vec!(None,
+                                            Some(FirstPageSelector),
+                                            Some(LeftPageSelector),
+                                            Some(RightPageSelector)));
+        assert!(FirstPageSelector.matches(0) && !FirstPageSelector.matches(2));
+        assert!(RightPageSelector.matches(0) && RightPageSelector.matches(2));
+        assert!(LeftPageSelector.matches(1) && !LeftPageSelector.matches(2));
+    }
+}
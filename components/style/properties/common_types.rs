@@ -56,7 +56,6 @@ pub mod specified {
                 _ => Err(())
             }
         }
-        #[allow(dead_code)]
         pub fn parse(input: &ComponentValue) -> Result<Length, ()> {
             Length::parse_internal(input, /* negative_ok = */ true)
         }
@@ -211,6 +210,88 @@ pub mod specified {
         }
     }
 
+    /// Specified values for a `<position>`, as used by `background-position` and the centers of
+    /// radial gradients.
+    #[deriving(Clone)]
+    pub struct Position {
+        pub horizontal: LengthOrPercentage,
+        pub vertical: LengthOrPercentage,
+    }
+
+    // Collapse `PositionComponent` into a few categories to simplify `Position::new()`.
+    enum PositionCategory {
+        HorizontalKeyword,
+        VerticalKeyword,
+        OtherKeyword,
+        LengthOrPercentageCategory,
+    }
+
+    fn position_category(p: PositionComponent) -> PositionCategory {
+        match p {
+            Pos_Left | Pos_Right => HorizontalKeyword,
+            Pos_Top | Pos_Bottom => VerticalKeyword,
+            Pos_Center => OtherKeyword,
+            Pos_Length(_) | Pos_Percentage(_) => LengthOrPercentageCategory,
+        }
+    }
+
+    impl Position {
+        pub fn new(first: PositionComponent, second: PositionComponent)
+                   -> Result<Position, ()> {
+            let (horiz, vert) = match (position_category(first), position_category(second)) {
+                // Don't allow two vertical keywords or two horizontal keywords.
+                (HorizontalKeyword, HorizontalKeyword) |
+                (VerticalKeyword, VerticalKeyword) => return Err(()),
+
+                // Swap if both are keywords and vertical precedes horizontal.
+                (VerticalKeyword, HorizontalKeyword) |
+                (VerticalKeyword, OtherKeyword) |
+                (OtherKeyword, HorizontalKeyword) => (second, first),
+
+                // By default, horizontal is first.
+                _ => (first, second),
+            };
+            Ok(Position {
+                horizontal: horiz.to_length_or_percentage(),
+                vertical: vert.to_length_or_percentage(),
+            })
+        }
+
+        pub fn parse_one(first: &ComponentValue) -> Result<Position, ()> {
+            let first = try!(PositionComponent::parse(first));
+            // If only one value is provided, use `center` for the second.
+            Position::new(first, Pos_Center)
+        }
+
+        pub fn parse_two(first: &ComponentValue, second: &ComponentValue)
+                         -> Result<Position, ()> {
+            let first = try!(PositionComponent::parse(first));
+            let second = try!(PositionComponent::parse(second));
+            Position::new(first, second)
+        }
+
+        /// Parses one or two position components from the front of `source`. Any following
+        /// component value that is not part of the position is left in `source`.
+        pub fn parse(source: ParserIter) -> Result<Position, ()> {
+            let first = match source.next() {
+                Some(first) => try!(PositionComponent::parse(first)),
+                None => return Err(()),
+            };
+            match source.next() {
+                None => Position::new(first, Pos_Center),
+                Some(value) => {
+                    match PositionComponent::parse(value) {
+                        Ok(second) => Position::new(first, second),
+                        Err(()) => {
+                            source.push_back(value);
+                            Position::new(first, Pos_Center)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     #[deriving(Clone, PartialEq, PartialOrd)]
     pub struct Angle(pub CSSFloat);
 
@@ -246,6 +327,7 @@ pub mod specified {
     pub enum Image {
         UrlImage(Url),
         LinearGradientImage(LinearGradient),
+        RadialGradientImage(RadialGradient),
     }
 
     impl Image {
@@ -257,12 +339,19 @@ pub mod specified {
                     Ok(UrlImage(image_url))
                 },
                 &ast::Function(ref name, ref args) => {
-                    if name.as_slice().eq_ignore_ascii_case("linear-gradient") {
-                        Ok(LinearGradientImage(try!(
-                                    super::specified::LinearGradient::parse_function(
-                                    args.as_slice()))))
-                    } else {
-                        Err(())
+                    let name = name.as_slice().to_ascii_lower();
+                    match name.as_slice() {
+                        "linear-gradient" | "repeating-linear-gradient" => {
+                            let repeating = name.as_slice().starts_with("repeating-");
+                            Ok(LinearGradientImage(try!(LinearGradient::parse_function(
+                                        args.as_slice(), repeating))))
+                        }
+                        "radial-gradient" | "repeating-radial-gradient" => {
+                            let repeating = name.as_slice().starts_with("repeating-");
+                            Ok(RadialGradientImage(try!(RadialGradient::parse_function(
+                                        args.as_slice(), repeating))))
+                        }
+                        _ => Err(()),
                     }
                 }
                 _ => Err(()),
@@ -277,6 +366,10 @@ pub mod specified {
                     super::computed::LinearGradientImage(
                         super::computed::LinearGradient::compute(linear_gradient, context))
                 }
+                RadialGradientImage(radial_gradient) => {
+                    super::computed::RadialGradientImage(
+                        super::computed::RadialGradient::compute(radial_gradient, context))
+                }
             }
         }
     }
@@ -289,6 +382,9 @@ pub mod specified {
 
         /// The color stops.
         pub stops: Vec<ColorStop>,
+
+        /// True if this is a `repeating-linear-gradient()`.
+        pub repeating: bool,
     }
 
     /// Specified values for an angle or a corner in a linear gradient.
@@ -348,7 +444,8 @@ pub mod specified {
 
     impl LinearGradient {
         /// Parses a linear gradient from the given arguments.
-        pub fn parse_function(args: &[ComponentValue]) -> Result<LinearGradient,()> {
+        pub fn parse_function(args: &[ComponentValue], repeating: bool)
+                              -> Result<LinearGradient,()> {
             let mut source = BufferedIter::new(args.skip_whitespace());
 
             // Parse the angle.
@@ -441,6 +538,154 @@ pub mod specified {
             Ok(LinearGradient {
                 angle_or_corner: angle_or_corner,
                 stops: stops,
+                repeating: repeating,
+            })
+        }
+    }
+
+    /// Specified values for a CSS radial gradient.
+    #[deriving(Clone)]
+    pub struct RadialGradient {
+        /// The shape of the ending shape of the gradient.
+        pub shape: EndingShape,
+
+        /// The size of the ending shape.
+        pub size: RadialGradientSize,
+
+        /// The center of the gradient.
+        pub center: Position,
+
+        /// The color stops.
+        pub stops: Vec<ColorStop>,
+
+        /// True if this is a `repeating-radial-gradient()`.
+        pub repeating: bool,
+    }
+
+    /// The ending shape of a radial gradient.
+    #[deriving(Clone, PartialEq)]
+    pub enum EndingShape {
+        Circle,
+        Ellipse,
+    }
+
+    /// The keywords that size the ending shape of a radial gradient relative to the box.
+    #[deriving(Clone, PartialEq)]
+    pub enum ShapeExtent {
+        ClosestSide,
+        FarthestSide,
+        ClosestCorner,
+        FarthestCorner,
+    }
+
+    /// Specified values for the size of the ending shape of a radial gradient.
+    #[deriving(Clone)]
+    pub enum RadialGradientSize {
+        ExtentSize(ShapeExtent),
+        /// The horizontal and vertical radii. For circles, both radii are the same length.
+        ExplicitSize(LengthOrPercentage, LengthOrPercentage),
+    }
+
+    impl RadialGradient {
+        /// Parses a radial gradient from the given arguments.
+        pub fn parse_function(args: &[ComponentValue], repeating: bool)
+                              -> Result<RadialGradient,()> {
+            let mut source = BufferedIter::new(args.skip_whitespace());
+
+            // Parse `[ <ending-shape> || <size> ]? [ at <position> ]?`, in any order of shape
+            // and size.
+            let (mut shape, mut extent, mut lengths) = (None, None, Vec::new());
+            let mut center = None;
+            let mut any = false;
+            loop {
+                let token = match source.next() {
+                    None => return Err(()),
+                    Some(token) => token,
+                };
+                match *token {
+                    Comma if any => break,
+                    Ident(ref ident) if ident.as_slice().eq_ignore_ascii_case("at") &&
+                            center.is_none() => {
+                        center = Some(try!(Position::parse(&mut source)));
+                        any = true;
+                        continue
+                    }
+                    Ident(ref ident) if center.is_none() => {
+                        let ident = ident.as_slice().to_ascii_lower();
+                        match ident.as_slice() {
+                            "circle" if shape.is_none() => shape = Some(Circle),
+                            "ellipse" if shape.is_none() => shape = Some(Ellipse),
+                            "closest-side" if extent.is_none() => extent = Some(ClosestSide),
+                            "farthest-side" if extent.is_none() => extent = Some(FarthestSide),
+                            "closest-corner" if extent.is_none() => {
+                                extent = Some(ClosestCorner)
+                            }
+                            "farthest-corner" if extent.is_none() => {
+                                extent = Some(FarthestCorner)
+                            }
+                            _ if any => return Err(()),
+                            _ => {
+                                // No shape or size; this is the first color stop.
+                                source.push_back(token);
+                                break
+                            }
+                        }
+                        any = true;
+                    }
+                    _ if center.is_none() && extent.is_none() && lengths.len() < 2 => {
+                        match LengthOrPercentage::parse_non_negative(token) {
+                            Ok(length) => {
+                                lengths.push(length);
+                                any = true;
+                            }
+                            Err(()) if any => return Err(()),
+                            Err(()) => {
+                                source.push_back(token);
+                                break
+                            }
+                        }
+                    }
+                    _ => return Err(()),
+                }
+            }
+
+            // Resolve the shape and size per CSS-IMAGES § 3.2.1.
+            let (shape, size) = match (shape, extent, lengths.as_slice()) {
+                (None, None, []) | (Some(Ellipse), None, []) => {
+                    (Ellipse, ExtentSize(FarthestCorner))
+                }
+                (Some(Circle), None, []) => (Circle, ExtentSize(FarthestCorner)),
+                (shape, Some(extent), []) => (shape.unwrap_or(Ellipse), ExtentSize(extent)),
+                (None, None, [radius]) | (Some(Circle), None, [radius]) => {
+                    match radius {
+                        LP_Length(_) => (Circle, ExplicitSize(radius, radius)),
+                        LP_Percentage(_) => return Err(()),
+                    }
+                }
+                (None, None, [horizontal, vertical]) |
+                (Some(Ellipse), None, [horizontal, vertical]) => {
+                    (Ellipse, ExplicitSize(horizontal, vertical))
+                }
+                _ => return Err(()),
+            };
+
+            let center = match center {
+                Some(center) => center,
+                None => Position::new(Pos_Center, Pos_Center).unwrap(),
+            };
+
+            let stops = try!(parsing_utils::parse_comma_separated(&mut source,
+                                                                  parse_color_stop));
+            if stops.len() < 2 {
+                return Err(())
+            }
+
+            Ok(RadialGradient {
+                shape: shape,
+                size: size,
+                center: center,
+                stops: stops,
+                repeating: repeating,
             })
         }
     }
@@ -448,7 +693,8 @@ pub mod specified {
 
 pub mod computed {
     pub use super::specified::{Angle, AngleAoc, AngleOrCorner, CornerAoc, HorizontalDirection};
-    pub use super::specified::{VerticalDirection};
+    pub use super::specified::{VerticalDirection, EndingShape, Circle, Ellipse, ShapeExtent};
+    pub use super::specified::{ClosestSide, FarthestSide, ClosestCorner, FarthestCorner};
     pub use cssparser::Color as CSSColor;
     pub use super::super::longhands::computed_as_specified as compute_CSSColor;
     use super::*;
@@ -460,6 +706,7 @@ pub mod computed {
         pub inherited_font_size: longhands::font_size::computed_value::T,
         pub inherited_text_decorations_in_effect: longhands::_servo_text_decorations_in_effect::T,
         pub inherited_height: longhands::height::T,
+        pub inherited_display: longhands::display::computed_value::T,
         pub color: longhands::color::computed_value::T,
         pub text_decoration: longhands::text_decoration::computed_value::T,
         pub font_size: longhands::font_size::computed_value::T,
@@ -555,6 +802,22 @@ pub mod computed {
     pub enum Image {
         UrlImage(Url),
         LinearGradientImage(LinearGradient),
+        RadialGradientImage(RadialGradient),
+    }
+
+    /// Computed values for a `<position>`.
+    #[deriving(Clone, PartialEq)]
+    pub struct Position {
+        pub horizontal: LengthOrPercentage,
+        pub vertical: LengthOrPercentage,
+    }
+
+    #[inline]
+    pub fn compute_Position(value: specified::Position, context: &Context) -> Position {
+        Position {
+            horizontal: compute_LengthOrPercentage(value.horizontal, context),
+            vertical: compute_LengthOrPercentage(value.vertical, context),
+        }
     }
 
     /// Computed values for a CSS linear gradient.
@@ -565,6 +828,36 @@ pub mod computed {
 
         /// The color stops.
         pub stops: Vec<ColorStop>,
+
+        /// True if this is a `repeating-linear-gradient()`.
+        pub repeating: bool,
+    }
+
+    /// Computed values for a CSS radial gradient.
+    #[deriving(Clone, PartialEq)]
+    pub struct RadialGradient {
+        /// The shape of the ending shape of the gradient.
+        pub shape: EndingShape,
+
+        /// The size of the ending shape.
+        pub size: RadialGradientSize,
+
+        /// The center of the gradient.
+        pub center: Position,
+
+        /// The color stops.
+        pub stops: Vec<ColorStop>,
+
+        /// True if this is a `repeating-radial-gradient()`.
+        pub repeating: bool,
+    }
+
+    /// Computed values for the size of the ending shape of a radial gradient.
+    #[deriving(Clone, PartialEq)]
+    pub enum RadialGradientSize {
+        ExtentSize(ShapeExtent),
+        /// The horizontal and vertical radii.
+        ExplicitSize(LengthOrPercentage, LengthOrPercentage),
     }
 
     /// Computed values for one color stop in a linear gradient.
@@ -578,23 +871,55 @@ pub mod computed {
         pub position: Option<LengthOrPercentage>,
     }
 
+    fn compute_color_stops(stops: Vec<specified::ColorStop>, context: &Context)
+                           -> Vec<ColorStop> {
+        stops.into_iter().map(|stop| {
+            ColorStop {
+                color: stop.color,
+                position: match stop.position {
+                    None => None,
+                    Some(value) => Some(compute_LengthOrPercentage(value, context)),
+                },
+            }
+        }).collect()
+    }
+
     impl LinearGradient {
         pub fn compute(value: specified::LinearGradient, context: &Context) -> LinearGradient {
             let specified::LinearGradient {
                 angle_or_corner,
-                stops
+                stops,
+                repeating
             } = value;
             LinearGradient {
                 angle_or_corner: angle_or_corner,
-                stops: stops.into_iter().map(|stop| {
-                    ColorStop {
-                        color: stop.color,
-                        position: match stop.position {
-                            None => None,
-                            Some(value) => Some(compute_LengthOrPercentage(value, context)),
-                        },
+                stops: compute_color_stops(stops, context),
+                repeating: repeating,
+            }
+        }
+    }
+
+    impl RadialGradient {
+        pub fn compute(value: specified::RadialGradient, context: &Context) -> RadialGradient {
+            let specified::RadialGradient {
+                shape,
+                size,
+                center,
+                stops,
+                repeating
+            } = value;
+            RadialGradient {
+                shape: shape,
+                size: match size {
+                    specified::ExtentSize(extent) => ExtentSize(extent),
+                    specified::ExplicitSize(horizontal, vertical) => {
+                        ExplicitSize(compute_LengthOrPercentage(horizontal, context),
+                                     compute_LengthOrPercentage(vertical, context))
                     }
-                }).collect()
+                },
+                center: compute_Position(center, context),
+                stops: compute_color_stops(stops, context),
+                repeating: repeating,
             }
         }
     }
@@ -371,7 +371,7 @@ pub mod longhands {
             values="inline block inline-block
             table inline-table table-row-group table-header-group table-footer-group
             table-row table-column-group table-column table-cell table-caption
-            list-item
+            list-item flex inline-flex
             none">
         #[inline]
         pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
@@ -379,9 +379,15 @@ pub mod longhands {
 //            if context.is_root_element && value == list_item {
 //                return block
 //            }
-            if context.positioned || context.floated || context.is_root_element {
+            // Per CSS Flexbox, Section 4, the children of a flex container are blockified too.
+            let is_flex_item = match context.inherited_display {
+                flex | inline_flex => true,
+                _ => false,
+            };
+            if context.positioned || context.floated || context.is_root_element || is_flex_item {
                 match value {
                     inline_table => table,
+                    inline_flex => flex,
                     inline | inline_block
                     | table_row_group | table_column | table_column_group
                     | table_header_group | table_footer_group | table_row
@@ -395,8 +401,18 @@ pub mod longhands {
         }
     </%self:single_keyword_computed>
 
-    ${single_keyword("position", "static absolute relative fixed")}
-    ${single_keyword("float", "none left right")}
+    ${single_keyword("position", "static absolute relative fixed sticky")}
+    <%self:single_keyword_computed name="float" values="none left right">
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                              -> computed_value::T {
+            // Per CSS Flexbox, Section 4, `float` does not apply to flex items.
+            match context.inherited_display {
+                super::display::flex | super::display::inline_flex => none,
+                _ => value,
+            }
+        }
+    </%self:single_keyword_computed>
     ${single_keyword("clear", "none left right both")}
 
     <%self:longhand name="-servo-display-for-hypothetical-box" derived_from="display" no_super="True">
@@ -455,7 +471,7 @@ pub mod longhands {
 
     ${new_style_struct("InheritedBox", is_inherited=True)}
 
-    ${single_keyword("direction", "ltr rtl", experimental=True)}
+    ${single_keyword("direction", "ltr rtl")}
 
     // CSS 2.1, Section 10 - Visual formatting model details
 
@@ -612,7 +628,6 @@ pub mod longhands {
 
 
     // CSS 2.1, Section 11 - Visual effects
-    // FIXME: Implement scrolling for `scroll` and `auto` (#2742).
     ${single_keyword("overflow", "visible hidden scroll auto")}
 
     ${switch_to_style_struct("InheritedBox")}
@@ -674,21 +689,71 @@ pub mod longhands {
     ${predefined_type("background-color", "CSSColor",
                       "RGBAColor(RGBA { red: 0., green: 0., blue: 0., alpha: 0. }) /* transparent */")}
 
-    <%self:single_component_value name="background-image">
+    // Every background property other than `background-color` takes a comma-separated list of
+    // values, one per background layer. The number of layers is determined by
+    // `background-image`; the other lists are repeated as necessary when painting.
+
+    <%def name="background_layer_keyword(name, values)">
+        <%self:longhand name="${name}">
+            pub use super::computed_as_specified as to_computed_value;
+            pub mod computed_value {
+                #[allow(non_camel_case_types)]
+                #[deriving(PartialEq, Clone, FromPrimitive, Show)]
+                pub enum SingleComputedValue {
+                    % for value in values.split():
+                        ${to_rust_ident(value)},
+                    % endfor
+                }
+                pub type T = Vec<SingleComputedValue>;
+            }
+            pub type SpecifiedValue = computed_value::T;
+            #[inline] pub fn get_initial_single_value() -> SingleComputedValue {
+                ${to_rust_ident(values.split()[0])}
+            }
+            #[inline] pub fn get_initial_value() -> computed_value::T {
+                vec![get_initial_single_value()]
+            }
+            pub fn from_component_value(v: &ComponentValue, _base_url: &Url)
+                                        -> Result<SingleComputedValue, ()> {
+                get_ident_lower(v).and_then(|keyword| {
+                    match keyword.as_slice() {
+                        % for value in values.split():
+                            "${value}" => Ok(${to_rust_ident(value)}),
+                        % endfor
+                        _ => Err(()),
+                    }
+                })
+            }
+            pub fn parse(input: &[ComponentValue], base_url: &Url) -> Result<SpecifiedValue, ()> {
+                parse_slice_comma_separated(input, |iter| {
+                    match iter.next() {
+                        Some(value) => from_component_value(value, base_url),
+                        None => Err(()),
+                    }
+                })
+            }
+        </%self:longhand>
+    </%def>
+
+    <%self:longhand name="background-image">
         use super::common_types::specified as common_specified;
         pub mod computed_value {
             use super::super::super::common_types::computed;
-            #[deriving(Clone, PartialEq)]
-            pub type T = Option<computed::Image>;
+            pub type SingleComputedValue = Option<computed::Image>;
+            pub type T = Vec<SingleComputedValue>;
         }
-        #[deriving(Clone)]
-        pub type SpecifiedValue = Option<common_specified::Image>;
+        pub type SingleSpecifiedValue = Option<common_specified::Image>;
+        pub type SpecifiedValue = Vec<SingleSpecifiedValue>;
         #[inline]
-        pub fn get_initial_value() -> computed_value::T {
+        pub fn get_initial_single_value() -> SingleSpecifiedValue {
             None
         }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            vec![None]
+        }
         pub fn from_component_value(component_value: &ComponentValue, base_url: &Url)
-                                    -> Result<SpecifiedValue, ()> {
+                                    -> Result<SingleSpecifiedValue, ()> {
             match component_value {
                 &ast::Ident(ref value) if value.as_slice().eq_ignore_ascii_case("none") => {
                     Ok(None)
@@ -702,124 +767,154 @@ pub mod longhands {
                 }
             }
         }
+        pub fn parse(input: &[ComponentValue], base_url: &Url) -> Result<SpecifiedValue, ()> {
+            parse_slice_comma_separated(input, |iter| {
+                match iter.next() {
+                    Some(value) => from_component_value(value, base_url),
+                    None => Err(()),
+                }
+            })
+        }
         pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
                                  -> computed_value::T {
-            match value {
-                None => None,
-                Some(image) => Some(image.to_computed_value(context)),
-            }
+            value.into_iter().map(|image| {
+                match image {
+                    None => None,
+                    Some(image) => Some(image.to_computed_value(context)),
+                }
+            }).collect()
         }
-    </%self:single_component_value>
+    </%self:longhand>
 
     <%self:longhand name="background-position">
-            pub mod computed_value {
-                use super::super::super::common_types::computed::LengthOrPercentage;
+        pub mod computed_value {
+            use super::super::super::common_types::computed;
+            pub type SingleComputedValue = computed::Position;
+            pub type T = Vec<SingleComputedValue>;
+        }
 
-                #[deriving(PartialEq, Clone)]
-                pub struct T {
-                    pub horizontal: LengthOrPercentage,
-                    pub vertical: LengthOrPercentage,
-                }
-            }
+        pub type SingleSpecifiedValue = specified::Position;
+        pub type SpecifiedValue = Vec<SingleSpecifiedValue>;
 
-            #[deriving(Clone)]
-            pub struct SpecifiedValue {
-                pub horizontal: specified::LengthOrPercentage,
-                pub vertical: specified::LengthOrPercentage,
-            }
-
-            impl SpecifiedValue {
-                fn new(first: specified::PositionComponent, second: specified::PositionComponent)
-                        -> Result<SpecifiedValue,()> {
-                    let (horiz, vert) = match (category(first), category(second)) {
-                        // Don't allow two vertical keywords or two horizontal keywords.
-                        (HorizontalKeyword, HorizontalKeyword) |
-                        (VerticalKeyword, VerticalKeyword) => return Err(()),
-
-                        // Swap if both are keywords and vertical precedes horizontal.
-                        (VerticalKeyword, HorizontalKeyword) |
-                        (VerticalKeyword, OtherKeyword) |
-                        (OtherKeyword, HorizontalKeyword) => (second, first),
-
-                        // By default, horizontal is first.
-                        _ => (first, second),
-                    };
-                    Ok(SpecifiedValue {
-                        horizontal: horiz.to_length_or_percentage(),
-                        vertical: vert.to_length_or_percentage(),
-                    })
-                }
-            }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            value.into_iter().map(|position| {
+                computed::compute_Position(position, context)
+            }).collect()
+        }
 
-            // Collapse `Position` into a few categories to simplify the above `match` expression.
-            enum PositionCategory {
-                HorizontalKeyword,
-                VerticalKeyword,
-                OtherKeyword,
-                LengthOrPercentage,
-            }
-            fn category(p: specified::PositionComponent) -> PositionCategory {
-                match p {
-                    specified::Pos_Left | specified::Pos_Right => HorizontalKeyword,
-                    specified::Pos_Top | specified::Pos_Bottom => VerticalKeyword,
-                    specified::Pos_Center => OtherKeyword,
-                    specified::Pos_Length(_) |
-                    specified::Pos_Percentage(_) => LengthOrPercentage,
-                }
+        #[inline]
+        pub fn get_initial_single_value() -> SingleSpecifiedValue {
+            specified::Position {
+                horizontal: specified::LP_Percentage(0.0),
+                vertical: specified::LP_Percentage(0.0),
             }
+        }
 
-            #[inline]
-            pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
-                                     -> computed_value::T {
-                computed_value::T {
-                    horizontal: computed::compute_LengthOrPercentage(value.horizontal, context),
-                    vertical: computed::compute_LengthOrPercentage(value.vertical, context),
-                }
-            }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            vec![computed::Position {
+                horizontal: computed::LP_Percentage(0.0),
+                vertical: computed::LP_Percentage(0.0),
+            }]
+        }
 
-            #[inline]
-            pub fn get_initial_value() -> computed_value::T {
-                computed_value::T {
-                    horizontal: computed::LP_Percentage(0.0),
-                    vertical: computed::LP_Percentage(0.0),
-                }
-            }
+        pub fn parse(input: &[ComponentValue], _: &Url) -> Result<SpecifiedValue, ()> {
+            parse_slice_comma_separated(input, specified::Position::parse)
+        }
+    </%self:longhand>
 
-            pub fn parse_one(first: &ComponentValue) -> Result<SpecifiedValue, ()> {
-                let first = try!(specified::PositionComponent::parse(first));
-                // If only one value is provided, use `center` for the second.
-                SpecifiedValue::new(first, specified::Pos_Center)
-            }
+    ${background_layer_keyword("background-repeat", "repeat repeat-x repeat-y no-repeat")}
 
-            pub fn parse_two(first: &ComponentValue, second: &ComponentValue)
-                    -> Result<SpecifiedValue, ()> {
-                let first = try!(specified::PositionComponent::parse(first));
-                let second = try!(specified::PositionComponent::parse(second));
-                SpecifiedValue::new(first, second)
+    ${background_layer_keyword("background-attachment", "scroll fixed")}
+
+    <%self:longhand name="background-size">
+        pub mod computed_value {
+            use super::super::super::common_types::computed;
+            #[deriving(PartialEq, Clone)]
+            pub enum SingleComputedValue {
+                Cover,
+                Contain,
+                /// The width and height of the image. `auto` preserves the intrinsic size or
+                /// aspect ratio of the image.
+                Explicit(computed::LengthOrPercentageOrAuto, computed::LengthOrPercentageOrAuto),
             }
+            pub type T = Vec<SingleComputedValue>;
+        }
 
-            pub fn parse(input: &[ComponentValue], _: &Url) -> Result<SpecifiedValue, ()> {
-                let mut input_iter = input.skip_whitespace();
-                let first = input_iter.next();
-                let second = input_iter.next();
-                if input_iter.next().is_some() {
-                    return Err(())
-                }
-                match (first, second) {
-                    (Some(first), Some(second)) => {
-                        parse_two(first, second)
-                    }
-                    (Some(first), None) => {
-                        parse_one(first)
+        #[deriving(Clone)]
+        pub enum SingleSpecifiedValue {
+            SpecifiedCover,
+            SpecifiedContain,
+            SpecifiedExplicit(specified::LengthOrPercentageOrAuto,
+                              specified::LengthOrPercentageOrAuto),
+        }
+        pub type SpecifiedValue = Vec<SingleSpecifiedValue>;
+
+        #[inline]
+        pub fn get_initial_single_value() -> SingleSpecifiedValue {
+            SpecifiedExplicit(specified::LPA_Auto, specified::LPA_Auto)
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            vec![Explicit(computed::LPA_Auto, computed::LPA_Auto)]
+        }
+
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            value.into_iter().map(|size| {
+                match size {
+                    SpecifiedCover => Cover,
+                    SpecifiedContain => Contain,
+                    SpecifiedExplicit(width, height) => {
+                        Explicit(computed::compute_LengthOrPercentageOrAuto(width, context),
+                                 computed::compute_LengthOrPercentageOrAuto(height, context))
                     }
-                    _ => Err(())
                 }
+            }).collect()
+        }
+
+        /// [ <length> | <percentage> | auto ]{1,2} | cover | contain
+        pub fn parse_one(iter: ParserIter) -> Result<SingleSpecifiedValue, ()> {
+            let first = match iter.next() {
+                Some(first) => first,
+                None => return Err(()),
+            };
+            match first {
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("cover") => {
+                    return Ok(SpecifiedCover)
+                }
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("contain") => {
+                    return Ok(SpecifiedContain)
+                }
+                _ => {}
             }
+            let width = try!(specified::LengthOrPercentageOrAuto::parse_non_negative(first));
+            let height = match iter.next() {
+                None => specified::LPA_Auto,
+                Some(second) => {
+                    match specified::LengthOrPercentageOrAuto::parse_non_negative(second) {
+                        Ok(height) => height,
+                        Err(()) => {
+                            iter.push_back(second);
+                            specified::LPA_Auto
+                        }
+                    }
+                }
+            };
+            Ok(SpecifiedExplicit(width, height))
+        }
+
+        pub fn parse(input: &[ComponentValue], _: &Url) -> Result<SpecifiedValue, ()> {
+            parse_slice_comma_separated(input, parse_one)
+        }
     </%self:longhand>
 
-    ${single_keyword("background-repeat", "repeat repeat-x repeat-y no-repeat")}
+    ${background_layer_keyword("background-origin", "padding-box border-box content-box")}
 
-    ${single_keyword("background-attachment", "scroll fixed")}
+    ${background_layer_keyword("background-clip", "border-box padding-box content-box")}
 
     ${new_style_struct("Color", is_inherited=True)}
 
@@ -1053,11 +1148,57 @@ pub mod longhands {
 
     ${new_style_struct("InheritedText", is_inherited=True)}
 
+    ${predefined_type("text-indent", "LengthOrPercentage", "computed::LP_Length(Au(0))")}
+
     // TODO: initial value should be 'start' (CSS Text Level 3, direction-dependent.)
     ${single_keyword("text-align", "left right center justify")}
 
+    % for name in ["letter-spacing", "word-spacing"]:
+    <%self:single_component_value name="${name}">
+        #[deriving(Clone)]
+        pub enum SpecifiedValue {
+            SpecifiedNormal,
+            SpecifiedLength(specified::Length),
+        }
+        pub mod computed_value {
+            use super::super::Au;
+            pub type T = Option<Au>;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            None
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            match value {
+                SpecifiedNormal => None,
+                SpecifiedLength(value) => Some(computed::compute_Au(value, context)),
+            }
+        }
+        /// normal | <length>
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Result<SpecifiedValue, ()> {
+            match input {
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("normal")
+                => Ok(SpecifiedNormal),
+                _ => specified::Length::parse(input).map(SpecifiedLength),
+            }
+        }
+    </%self:single_component_value>
+    % endfor
+
+    ${single_keyword("text-transform", "none capitalize uppercase lowercase")}
+
+    ${single_keyword("word-break", "normal break-all keep-all")}
+
+    ${single_keyword("overflow-wrap", "normal break-word")}
+
     ${new_style_struct("Text", is_inherited=False)}
 
+    // CSS 2.1, Section 9.10 - Text direction
+    ${single_keyword("unicode-bidi", "normal embed bidi-override")}
+
     <%self:longhand name="text-decoration">
         pub use super::computed_as_specified as to_computed_value;
         #[deriving(PartialEq, Clone)]
@@ -1183,13 +1324,97 @@ pub mod longhands {
         }
     </%self:longhand>
 
-    ${single_keyword("white-space", "normal pre nowrap")}
+    ${single_keyword("white-space", "normal pre nowrap pre-wrap pre-line")}
+
+    // CSS Text Level 3, Section 4.2 - Tab character size
+    <%self:single_component_value name="tab-size">
+        pub use super::computed_as_specified as to_computed_value;
+        pub type SpecifiedValue = computed_value::T;
+        pub mod computed_value {
+            /// The width of a tab, in multiples of the advance of a space.
+            pub type T = u32;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            8
+        }
+        /// <integer>
+        fn from_component_value(input: &ComponentValue, _: &Url) -> Result<SpecifiedValue,()> {
+            match *input {
+                ast::Number(ast::NumericValue {
+                    int_value: Some(value),
+                    ..
+                }) if value >= 0 => Ok(value as u32),
+                _ => Err(())
+            }
+        }
+    </%self:single_component_value>
 
     // CSS 2.1, Section 17 - Tables
     ${new_style_struct("Table", is_inherited=False)}
 
     ${single_keyword("table-layout", "auto fixed")}
 
+    ${new_style_struct("InheritedTable", is_inherited=True)}
+
+    ${single_keyword("border-collapse", "separate collapse")}
+
+    ${single_keyword("empty-cells", "show hide")}
+
+    ${single_keyword("caption-side", "top bottom")}
+
+    <%self:longhand name="border-spacing">
+        #[deriving(Clone)]
+        pub struct SpecifiedValue {
+            pub horizontal: specified::Length,
+            pub vertical: specified::Length,
+        }
+        pub mod computed_value {
+            use super::super::Au;
+            #[deriving(PartialEq, Clone, Show)]
+            pub struct T {
+                pub horizontal: Au,
+                pub vertical: Au,
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T {
+                horizontal: Au(0),
+                vertical: Au(0),
+            }
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            computed_value::T {
+                horizontal: computed::compute_Au(value.horizontal, context),
+                vertical: computed::compute_Au(value.vertical, context),
+            }
+        }
+        /// <length> <length>?
+        pub fn parse(input: &[ComponentValue], _base_url: &Url) -> Result<SpecifiedValue, ()> {
+            let mut lengths = vec!();
+            for component_value in input.skip_whitespace() {
+                if lengths.len() == 2 {
+                    return Err(())
+                }
+                lengths.push(try!(specified::Length::parse_non_negative(component_value)));
+            }
+            match lengths.as_slice() {
+                [horizontal] => Ok(SpecifiedValue {
+                    horizontal: horizontal,
+                    vertical: horizontal,
+                }),
+                [horizontal, vertical] => Ok(SpecifiedValue {
+                    horizontal: horizontal,
+                    vertical: vertical,
+                }),
+                _ => Err(()),
+            }
+        }
+    </%self:longhand>
+
     // CSS 2.1, Section 18 - User interface
 
 
@@ -1197,11 +1422,10 @@ pub mod longhands {
     // http://dev.w3.org/csswg/css-writing-modes/
     ${switch_to_style_struct("InheritedBox")}
 
-    ${single_keyword("writing-mode", "horizontal-tb vertical-rl vertical-lr", experimental=True)}
+    ${single_keyword("writing-mode", "horizontal-tb vertical-rl vertical-lr")}
 
-    // FIXME(SimonSapin): Add 'mixed' and 'upright' (needs vertical text support)
-    // FIXME(SimonSapin): initial (first) value should be 'mixed', when that's implemented
-    ${single_keyword("text-orientation", "sideways sideways-left sideways-right", experimental=True)}
+    // FIXME: `mixed` rotates all glyphs, but should set those of upright scripts like CJK upright.
+    ${single_keyword("text-orientation", "mixed upright sideways sideways-left sideways-right")}
 
     // CSS Basic User Interface Module Level 3
     // http://dev.w3.org/csswg/css-ui/
@@ -1239,6 +1463,228 @@ pub mod longhands {
             }
         }
     </%self:single_component_value>
+
+    // CSS Flexible Box Layout Module Level 1
+    ${new_style_struct("Flex", is_inherited=False)}
+
+    // Section 5 - Ordering and Orientation
+    ${single_keyword("flex-direction", "row row-reverse column column-reverse")}
+    ${single_keyword("flex-wrap", "nowrap wrap wrap-reverse")}
+
+    <%self:single_component_value name="order">
+        pub use super::computed_as_specified as to_computed_value;
+        pub type SpecifiedValue = computed_value::T;
+        pub mod computed_value {
+            pub type T = i32;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            0
+        }
+        /// <integer>
+        fn from_component_value(input: &ComponentValue, _: &Url) -> Result<SpecifiedValue,()> {
+            match *input {
+                ast::Number(ast::NumericValue {
+                    int_value: Some(value),
+                    ..
+                }) => Ok(value as i32),
+                _ => Err(())
+            }
+        }
+    </%self:single_component_value>
+
+    // Section 7 - Flexibility
+    % for name, initial_value in [("flex-grow", "0.0"), ("flex-shrink", "1.0")]:
+    <%self:single_component_value name="${name}">
+        pub use super::computed_as_specified as to_computed_value;
+        pub type SpecifiedValue = computed_value::T;
+        pub mod computed_value {
+            use super::super::CSSFloat;
+            pub type T = CSSFloat;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            ${initial_value}
+        }
+        /// <number>
+        fn from_component_value(input: &ComponentValue, _: &Url) -> Result<SpecifiedValue,()> {
+            match *input {
+                Number(ref value) if value.value >= 0.0 => Ok(value.value),
+                _ => Err(())
+            }
+        }
+    </%self:single_component_value>
+    % endfor
+
+    ${predefined_type("flex-basis", "LengthOrPercentageOrAuto", "computed::LPA_Auto")}
+
+    // Section 8 - Alignment
+    ${single_keyword("justify-content", "flex-start flex-end center space-between space-around")}
+    ${single_keyword("align-items", "stretch flex-start flex-end center baseline")}
+    ${single_keyword("align-self", "auto stretch flex-start flex-end center baseline")}
+    ${single_keyword("align-content",
+                     "stretch flex-start flex-end center space-between space-around")}
+
+    // CSS Multi-column Layout Module Level 1
+    ${new_style_struct("Column", is_inherited=False)}
+
+    // Section 3 - The number and width of columns
+    <%self:single_component_value name="column-width">
+        #[deriving(Clone)]
+        pub enum SpecifiedValue {
+            SpecifiedAuto,
+            SpecifiedLength(specified::Length),
+        }
+        pub mod computed_value {
+            use super::super::Au;
+            pub type T = Option<Au>;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            None
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            match value {
+                SpecifiedAuto => None,
+                SpecifiedLength(value) => Some(computed::compute_Au(value, context)),
+            }
+        }
+        /// auto | <length>
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Result<SpecifiedValue, ()> {
+            match input {
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("auto")
+                => Ok(SpecifiedAuto),
+                _ => specified::Length::parse_non_negative(input).map(SpecifiedLength),
+            }
+        }
+    </%self:single_component_value>
+
+    <%self:single_component_value name="column-count">
+        pub use super::computed_as_specified as to_computed_value;
+        pub type SpecifiedValue = computed_value::T;
+        pub mod computed_value {
+            pub type T = Option<u32>;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            None
+        }
+        /// auto | <integer>
+        pub fn from_component_value(input: &ComponentValue, _: &Url)
+                                    -> Result<SpecifiedValue, ()> {
+            match *input {
+                Ident(ref keyword) if keyword.as_slice().eq_ignore_ascii_case("auto") => Ok(None),
+                ast::Number(ast::NumericValue {
+                    int_value: Some(value),
+                    ..
+                }) if value > 0 => Ok(Some(value as u32)),
+                _ => Err(())
+            }
+        }
+    </%self:single_component_value>
+
+    // Section 4 - Column gaps and rules
+    <%self:single_component_value name="column-gap">
+        #[deriving(Clone)]
+        pub enum SpecifiedValue {
+            SpecifiedNormal,
+            SpecifiedLength(specified::Length),
+        }
+        pub mod computed_value {
+            use super::super::Au;
+            pub type T = Au;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            Au::from_px(16)  // 1em at the initial font size
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            match value {
+                SpecifiedNormal => context.font_size,
+                SpecifiedLength(value) => computed::compute_Au(value, context),
+            }
+        }
+        /// normal | <length>
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Result<SpecifiedValue, ()> {
+            match input {
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("normal")
+                => Ok(SpecifiedNormal),
+                _ => specified::Length::parse_non_negative(input).map(SpecifiedLength),
+            }
+        }
+    </%self:single_component_value>
+
+    ${predefined_type("column-rule-color", "CSSColor", "CurrentColor")}
+
+    <%self:longhand name="column-rule-style">
+        pub use super::border_top_style::{get_initial_value, parse, to_computed_value};
+        pub type SpecifiedValue = super::border_top_style::SpecifiedValue;
+        pub mod computed_value {
+            pub type T = super::super::border_top_style::computed_value::T;
+        }
+    </%self:longhand>
+
+    <%self:longhand name="column-rule-width">
+        pub type SpecifiedValue = specified::Length;
+        pub mod computed_value {
+            use super::super::Au;
+            pub type T = Au;
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            Au::from_px(3)  // medium
+        }
+        pub fn parse(input: &[ComponentValue], base_url: &Url) -> Result<SpecifiedValue, ()> {
+            one_component_value(input).and_then(|c| parse_border_width(c, base_url))
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                              -> computed_value::T {
+            computed::compute_Au(value, context)
+        }
+    </%self:longhand>
+
+    // Section 7 - Column breaks and filling columns
+    ${single_keyword("column-fill", "balance auto")}
+
+    // CSS 2.1, Section 13 - Paged media
+    ${switch_to_style_struct("Box")}
+
+    ${single_keyword("page-break-before", "auto always avoid left right")}
+    ${single_keyword("page-break-after", "auto always avoid left right")}
+    ${single_keyword("page-break-inside", "auto avoid")}
+
+    ${switch_to_style_struct("InheritedText")}
+
+    % for name in ["orphans", "widows"]:
+        <%self:single_component_value name="${name}">
+            pub use super::computed_as_specified as to_computed_value;
+            pub type SpecifiedValue = computed_value::T;
+            pub mod computed_value {
+                pub type T = u32;
+            }
+            #[inline]
+            pub fn get_initial_value() -> computed_value::T {
+                2
+            }
+            /// <integer>
+            pub fn from_component_value(input: &ComponentValue, _: &Url)
+                                        -> Result<SpecifiedValue, ()> {
+                match *input {
+                    ast::Number(ast::NumericValue {
+                        int_value: Some(value),
+                        ..
+                    }) if value > 0 => Ok(value as u32),
+                    _ => Err(())
+                }
+            }
+        </%self:single_component_value>
+    % endfor
 }
 
 
@@ -1291,123 +1737,165 @@ pub mod shorthands {
         </%self:shorthand>
     </%def>
 
-    // TODO: other background-* properties
     <%self:shorthand name="background"
-                     sub_properties="background-color background-position background-repeat background-attachment background-image">
-                use std::mem;
+                     sub_properties="background-color background-position background-repeat background-attachment background-image background-size background-origin background-clip">
+                // Each layer is `<bg-image> || <position> [ / <bg-size> ]? || <repeat> ||
+                // <attachment> || <box>{1,2}`. Only the final layer may include a color.
+                let layers: Vec<&[ComponentValue]> = input.split(|component_value| {
+                    match *component_value {
+                        Comma => true,
+                        _ => false,
+                    }
+                }).collect();
+
+                let mut color = None;
+                let (mut images, mut positions, mut repeats, mut attachments) =
+                    (vec![], vec![], vec![], vec![]);
+                let (mut sizes, mut origins, mut clips) = (vec![], vec![], vec![]);
+
+                for (layer_index, layer) in layers.iter().enumerate() {
+                    let is_final_layer = layer_index == layers.len() - 1;
+                    let (mut image, mut position, mut size, mut repeat, mut attachment) =
+                        (None, None, None, None, None);
+                    let (mut origin, mut clip) = (None, None);
+                    let mut any = false;
+
+                    let mut iter = BufferedIter::new(layer.skip_whitespace());
+                    loop {
+                        let component_value = match iter.next() {
+                            None => break,
+                            Some(component_value) => component_value,
+                        };
 
-                let (mut color, mut image, mut position, mut repeat, mut attachment) =
-                    (None, None, None, None, None);
-                let mut unused_component_value = None;
-                let mut any = false;
+                        if position.is_none() &&
+                                specified::PositionComponent::parse(component_value).is_ok() {
+                            iter.push_back(component_value);
+                            position = Some(try!(specified::Position::parse(&mut iter)));
+                            any = true;
 
-                for component_value in input.skip_whitespace() {
-                    // Try `background-position` first because it might not use the value.
-                    if position.is_none() {
-                        match mem::replace(&mut unused_component_value, None) {
-                            Some(saved_component_value) => {
-                                // First try parsing a pair of values, then a single value.
-                                match background_position::parse_two(saved_component_value,
-                                                                     component_value) {
-                                    Ok(v) => {
-                                        position = Some(v);
-                                        any = true;
-                                        continue
-                                    },
-                                    Err(()) => {
-                                        match background_position::parse_one(saved_component_value) {
-                                            Ok(v) => {
-                                                position = Some(v);
-                                                any = true;
-                                                // We haven't used the current `component_value`;
-                                                // keep attempting to parse it below.
-                                            },
-                                            // If we get here, parsing failed.
-                                            Err(()) => return Err(())
-                                        }
-                                    }
+                            // The size may only appear directly after the position.
+                            match iter.next() {
+                                Some(&Delim('/')) => {
+                                    size = Some(try!(background_size::parse_one(&mut iter)));
                                 }
+                                Some(component_value) => iter.push_back(component_value),
+                                None => {}
                             }
-                            None => () // Wait until we have a pair of potential values.
+                            continue
                         }
-                    }
 
-                    if color.is_none() {
-                        match background_color::from_component_value(component_value, base_url) {
-                            Ok(v) => {
-                                color = Some(v);
-                                any = true;
-                                continue
-                            },
-                            Err(()) => ()
-                        }
-                    }
-
-                    if image.is_none() {
-                        match background_image::from_component_value(component_value, base_url) {
-                            Ok(v) => {
-                                image = Some(v);
-                                any = true;
-                                continue
-                            },
-                            Err(()) => (),
+                        if color.is_none() && is_final_layer {
+                            match background_color::from_component_value(component_value,
+                                                                         base_url) {
+                                Ok(v) => {
+                                    color = Some(v);
+                                    any = true;
+                                    continue
+                                },
+                                Err(()) => ()
+                            }
                         }
-                    }
 
-                    if repeat.is_none() {
-                        match background_repeat::from_component_value(component_value, base_url) {
-                            Ok(v) => {
-                                repeat = Some(v);
-                                any = true;
-                                continue
-                            },
-                            Err(()) => ()
+                        if image.is_none() {
+                            match background_image::from_component_value(component_value,
+                                                                         base_url) {
+                                Ok(v) => {
+                                    image = Some(v);
+                                    any = true;
+                                    continue
+                                },
+                                Err(()) => (),
+                            }
                         }
-                    }
 
-                    if attachment.is_none() {
-                        match background_attachment::from_component_value(component_value,
+                        if repeat.is_none() {
+                            match background_repeat::from_component_value(component_value,
                                                                           base_url) {
-                            Ok(v) => {
-                                attachment = Some(v);
-                                any = true;
-                                continue
-                            },
-                            Err(()) => ()
+                                Ok(v) => {
+                                    repeat = Some(v);
+                                    any = true;
+                                    continue
+                                },
+                                Err(()) => ()
+                            }
                         }
-                    }
 
-                    // Save the component value.  It may the first of a background-position pair.
-                    unused_component_value = Some(component_value);
-                }
+                        if attachment.is_none() {
+                            match background_attachment::from_component_value(component_value,
+                                                                              base_url) {
+                                Ok(v) => {
+                                    attachment = Some(v);
+                                    any = true;
+                                    continue
+                                },
+                                Err(()) => ()
+                            }
+                        }
 
-                if position.is_none() {
-                    // Check for a lone trailing background-position value.
-                    match mem::replace(&mut unused_component_value, None) {
-                        Some(saved_component_value) => {
-                            match background_position::parse_one(saved_component_value) {
+                        // The first box keyword sets both the origin and the clip; a second one
+                        // sets just the clip.
+                        if clip.is_none() {
+                            match background_origin::from_component_value(component_value,
+                                                                          base_url) {
                                 Ok(v) => {
-                                    position = Some(v);
+                                    if origin.is_none() {
+                                        origin = Some(v);
+                                    } else {
+                                        clip = Some(try!(background_clip::from_component_value(
+                                                    component_value, base_url)));
+                                    }
                                     any = true;
+                                    continue
                                 },
-                                Err(()) => return Err(())
+                                Err(()) => ()
                             }
                         }
-                        None => ()
+
+                        return Err(())
                     }
-                }
 
-                if any && unused_component_value.is_none() {
-                    Ok(Longhands {
-                        background_color: color,
-                        background_image: image,
-                        background_position: position,
-                        background_repeat: repeat,
-                        background_attachment: attachment,
-                    })
-                } else {
-                    Err(())
+                    if !any {
+                        return Err(())
+                    }
+
+                    if clip.is_none() {
+                        clip = match origin {
+                            None => None,
+                            Some(background_origin::border_box) => {
+                                Some(background_clip::border_box)
+                            }
+                            Some(background_origin::padding_box) => {
+                                Some(background_clip::padding_box)
+                            }
+                            Some(background_origin::content_box) => {
+                                Some(background_clip::content_box)
+                            }
+                        };
+                    }
+
+                    images.push(image.unwrap_or_else(background_image::get_initial_single_value));
+                    positions.push(position.unwrap_or_else(
+                            background_position::get_initial_single_value));
+                    sizes.push(size.unwrap_or_else(background_size::get_initial_single_value));
+                    repeats.push(repeat.unwrap_or_else(
+                            background_repeat::get_initial_single_value));
+                    attachments.push(attachment.unwrap_or_else(
+                            background_attachment::get_initial_single_value));
+                    origins.push(origin.unwrap_or_else(
+                            background_origin::get_initial_single_value));
+                    clips.push(clip.unwrap_or_else(background_clip::get_initial_single_value));
                 }
+
+                Ok(Longhands {
+                    background_color: color,
+                    background_image: Some(images),
+                    background_position: Some(positions),
+                    background_repeat: Some(repeats),
+                    background_attachment: Some(attachments),
+                    background_size: Some(sizes),
+                    background_origin: Some(origins),
+                    background_clip: Some(clips),
+                })
     </%self:shorthand>
 
     ${four_sides_shorthand("margin", "margin-%s", "margin_top::from_component_value")}
@@ -1618,6 +2106,147 @@ pub mod shorthands {
         })
     </%self:shorthand>
 
+    // CSS Flexbox, Section 5.3
+    <%self:shorthand name="flex-flow" sub_properties="flex-direction flex-wrap">
+        let mut direction = None;
+        let mut wrap = None;
+        let mut any = false;
+        for component_value in input.skip_whitespace() {
+            if direction.is_none() {
+                match flex_direction::from_component_value(component_value, base_url) {
+                    Ok(value) => { direction = Some(value); any = true; continue },
+                    Err(()) => ()
+                }
+            }
+            if wrap.is_none() {
+                match flex_wrap::from_component_value(component_value, base_url) {
+                    Ok(value) => { wrap = Some(value); any = true; continue },
+                    Err(()) => ()
+                }
+            }
+            return Err(())
+        }
+        if any {
+            Ok(Longhands {
+                flex_direction: direction,
+                flex_wrap: wrap,
+            })
+        } else {
+            Err(())
+        }
+    </%self:shorthand>
+
+    // CSS Flexbox, Section 7.1
+    <%self:shorthand name="flex" sub_properties="flex-grow flex-shrink flex-basis">
+        let mut iter = input.skip_whitespace();
+        let first = match iter.next() {
+            Some(value) => value,
+            None => return Err(()),
+        };
+        match *first {
+            Ident(ref value) if value.as_slice().eq_ignore_ascii_case("none") => {
+                if iter.next().is_some() {
+                    return Err(())
+                }
+                return Ok(Longhands {
+                    flex_grow: Some(0.0),
+                    flex_shrink: Some(0.0),
+                    flex_basis: Some(specified::LPA_Auto),
+                })
+            }
+            _ => {}
+        }
+
+        // Omitted numbers default to 1, and an omitted basis to 0.
+        let mut grow = None;
+        let mut shrink = None;
+        let mut basis = None;
+        for component_value in Some(first).into_iter().chain(iter) {
+            if grow.is_none() {
+                match flex_grow::from_component_value(component_value, base_url) {
+                    Ok(value) => { grow = Some(value); continue },
+                    Err(()) => ()
+                }
+            } else if shrink.is_none() && basis.is_none() {
+                match flex_shrink::from_component_value(component_value, base_url) {
+                    Ok(value) => { shrink = Some(value); continue },
+                    Err(()) => ()
+                }
+            }
+            if basis.is_none() {
+                match flex_basis::from_component_value(component_value, base_url) {
+                    Ok(value) => { basis = Some(value); continue },
+                    Err(()) => ()
+                }
+            }
+            return Err(())
+        }
+        Ok(Longhands {
+            flex_grow: Some(grow.unwrap_or(1.0)),
+            flex_shrink: Some(shrink.unwrap_or(1.0)),
+            flex_basis: Some(basis.unwrap_or(specified::LPA_Length(specified::Au_(Au(0))))),
+        })
+    </%self:shorthand>
+
+    // CSS Multi-column Layout, Section 3.4
+    <%self:shorthand name="columns" sub_properties="column-width column-count">
+        // `auto` may stand for either longhand, so it only fills whichever one is left over.
+        let mut width = None;
+        let mut count = None;
+        let mut values = 0u;
+        for component_value in input.skip_whitespace() {
+            values += 1;
+            if values > 2 {
+                return Err(())
+            }
+            match *component_value {
+                Ident(ref value) if value.as_slice().eq_ignore_ascii_case("auto") => continue,
+                _ => {}
+            }
+            if width.is_none() {
+                match column_width::from_component_value(component_value, base_url) {
+                    Ok(value) => { width = Some(value); continue },
+                    Err(()) => ()
+                }
+            }
+            if count.is_none() {
+                match column_count::from_component_value(component_value, base_url) {
+                    Ok(value) => { count = Some(value); continue },
+                    Err(()) => ()
+                }
+            }
+            return Err(())
+        }
+        if values == 0 {
+            return Err(())
+        }
+        Ok(Longhands {
+            column_width: Some(width.unwrap_or(column_width::SpecifiedAuto)),
+            column_count: Some(count.unwrap_or(None)),
+        })
+    </%self:shorthand>
+
+    // CSS Multi-column Layout, Section 4.5
+    <%self:shorthand name="column-rule"
+                     sub_properties="column-rule-width column-rule-style column-rule-color">
+        parse_border(input, base_url).map(|(color, style, width)| {
+            Longhands {
+                column_rule_width: width,
+                column_rule_style: style,
+                column_rule_color: color,
+            }
+        })
+    </%self:shorthand>
+
+    // CSS Text Level 3, Section 6.2: `word-wrap` is a legacy name for `overflow-wrap`.
+    <%self:shorthand name="word-wrap" sub_properties="overflow-wrap">
+        overflow_wrap::parse(input, base_url).map(|value| {
+            Longhands {
+                overflow_wrap: Some(value),
+            }
+        })
+    </%self:shorthand>
+
 }
 
 
@@ -2008,10 +2637,17 @@ fn get_writing_mode(inheritedbox_style: &style_structs::InheritedBox) -> Writing
             flags.insert(logical_geometry::FLAG_VERTICAL_LR);
         },
     }
+    if !flags.intersects(logical_geometry::FLAG_VERTICAL) {
+        return flags
+    }
     match inheritedbox_style.text_orientation {
+        computed_values::text_orientation::mixed |
         computed_values::text_orientation::sideways_right => {},
+        computed_values::text_orientation::upright => {
+            flags.insert(logical_geometry::FLAG_UPRIGHT);
+        },
         computed_values::text_orientation::sideways_left => {
-            flags.insert(logical_geometry::FLAG_VERTICAL_LR);
+            flags.insert(logical_geometry::FLAG_SIDEWAYS_LEFT);
         },
         computed_values::text_orientation::sideways => {
             if flags.intersects(logical_geometry::FLAG_VERTICAL_LR) {
@@ -2126,6 +2762,17 @@ fn cascade_with_cached_declarations(applicable_declarations: &[DeclarationBlock]
         }
     }
 
+    // The cached style may have been computed for an element whose parent was not a flex
+    // container, so make sure that flex items are blockified and not floated.
+    match context.inherited_display {
+        longhands::display::flex | longhands::display::inline_flex => {
+            let box_ = style_box_.make_unique();
+            box_.display = longhands::display::to_computed_value(box_.display, context);
+            box_.float = longhands::float::none;
+        }
+        _ => {}
+    }
+
     ComputedValues {
         writing_mode: get_writing_mode(&*style_inheritedbox),
         % for style_struct in STYLE_STRUCTS:
@@ -2169,6 +2816,7 @@ pub fn cascade(applicable_declarations: &[DeclarationBlock],
             inherited_font_weight: inherited_font_style.font_weight,
             inherited_font_size: inherited_font_style.font_size,
             inherited_height: inherited_style.get_box().height,
+            inherited_display: inherited_style.get_box().display,
             inherited_text_decorations_in_effect:
                 inherited_style.get_inheritedtext()._servo_text_decorations_in_effect,
             // To be overridden by applicable declarations:
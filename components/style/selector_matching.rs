@@ -24,7 +24,9 @@ use node::{TElement, TElementAttributes, TNode};
 use properties::{PropertyDeclaration, PropertyDeclarationBlock, SpecifiedValue, WidthDeclaration};
 use properties::{specified};
 use selectors::*;
-use stylesheets::{Stylesheet, iter_stylesheet_media_rules, iter_stylesheet_style_rules};
+use page::{PageStyle, page_selector_specificity};
+use stylesheets::{Stylesheet, iter_page_rules, iter_stylesheet_media_rules};
+use stylesheets::iter_stylesheet_style_rules;
 
 pub enum StylesheetOrigin {
     UserAgentOrigin,
@@ -396,6 +398,37 @@ impl Stylist {
         self.is_dirty = true;
     }
 
+    /// Returns the style of the page box at the given index, which results from cascading the
+    /// `@page` rules of all stylesheets that apply to the current device and match the page, by
+    /// origin and then by the specificity of their page selector. CSS Paged Media § 5.2, § 6.
+    pub fn page_style(&self, page_index: uint) -> PageStyle {
+        let mut page_style = PageStyle::new();
+        for &pass in [0u, 1, 2].iter() {
+            for &specificity in [0u, 1, 2].iter() {
+                for stylesheet in self.stylesheets.iter() {
+                    let stylesheet_pass = match stylesheet.origin {
+                        UserAgentOrigin => 0,
+                        UserOrigin => 1,
+                        AuthorOrigin => 2,
+                    };
+                    if stylesheet_pass != pass {
+                        continue
+                    }
+                    iter_page_rules(stylesheet, &self.device, |rule| {
+                        let matches = match rule.selector {
+                            None => true,
+                            Some(ref selector) => selector.matches(page_index),
+                        };
+                        if matches && page_selector_specificity(&rule.selector) == specificity {
+                            page_style.apply(rule)
+                        }
+                    });
+                }
+            }
+        }
+        page_style
+    }
+
     /// Returns the applicable CSS declarations for the given element. This corresponds to
     /// `ElementRuleCollector` in WebKit.
     ///
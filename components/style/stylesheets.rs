@@ -17,6 +17,7 @@ use namespaces::{NamespaceMap, parse_namespace_rule};
 use media_queries::{Device, MediaRule, parse_media_rule};
 use media_queries;
 use font_face::{FontFaceRule, Source, parse_font_face_rule, iter_font_face_rules_inner};
+use page::{PageRule, parse_page_rule, iter_page_rules_inner};
 use selector_matching::StylesheetOrigin;
 
 
@@ -32,6 +33,7 @@ pub enum CSSRule {
     CSSStyleRule(StyleRule),
     CSSMediaRule(MediaRule),
     CSSFontFaceRule(FontFaceRule),
+    CSSPageRule(PageRule),
 }
 
 
@@ -151,6 +153,7 @@ pub fn parse_nested_at_rule(lower_name: &str, rule: AtRule,
     match lower_name {
         "media" => parse_media_rule(rule, parent_rules, namespaces, base_url),
         "font-face" => parse_font_face_rule(rule, parent_rules, base_url),
+        "page" => parse_page_rule(rule, parent_rules),
         _ => log_css_error(rule.location,
                            format!("Unsupported at-rule: @{:s}", lower_name).as_slice())
     }
@@ -165,7 +168,7 @@ pub fn iter_style_rules<'a>(rules: &[CSSRule], device: &media_queries::Device,
             CSSMediaRule(ref rule) => if rule.media_queries.evaluate(device) {
                 iter_style_rules(rule.rules.as_slice(), device, |s| callback(s))
             },
-            CSSFontFaceRule(_) => {},
+            CSSFontFaceRule(_) | CSSPageRule(_) => {},
         }
     }
 }
@@ -191,3 +194,8 @@ pub fn iter_font_face_rules(stylesheet: &Stylesheet, device: &Device,
                             callback: |family: &str, source: &Source|) {
     iter_font_face_rules_inner(stylesheet.rules.as_slice(), device, callback)
 }
+
+#[inline]
+pub fn iter_page_rules(stylesheet: &Stylesheet, device: &Device, callback: |&PageRule|) {
+    iter_page_rules_inner(stylesheet.rules.as_slice(), device, callback)
+}
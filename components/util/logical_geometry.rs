@@ -15,7 +15,8 @@ bitflags!(
         const FLAG_RTL = 1 << 0,
         const FLAG_VERTICAL = 1 << 1,
         const FLAG_VERTICAL_LR = 1 << 2,
-        const FLAG_SIDEWAYS_LEFT = 1 << 3
+        const FLAG_SIDEWAYS_LEFT = 1 << 3,
+        const FLAG_UPRIGHT = 1 << 4
     }
 )
 
@@ -46,6 +47,20 @@ impl WritingMode {
     pub fn is_sideways_left(&self) -> bool {
         self.intersects(FLAG_SIDEWAYS_LEFT)
     }
+
+    /// Asuming .is_vertical(), are glyphs set upright rather than rotated?
+    #[inline]
+    pub fn is_upright(&self) -> bool {
+        self.intersects(FLAG_UPRIGHT)
+    }
+
+    /// Is the line-over side, towards which the ascenders of glyphs point, the block-start side?
+    /// It is in horizontal writing modes, and in vertical ones unless glyphs are rotated towards
+    /// the block-end side.
+    #[inline]
+    pub fn is_line_over_block_start(&self) -> bool {
+        !self.is_vertical() || self.is_vertical_lr() == self.is_sideways_left()
+    }
 }
 
 impl Show for WritingMode {
@@ -60,6 +75,9 @@ impl Show for WritingMode {
             if self.intersects(FLAG_SIDEWAYS_LEFT) {
                 try!(write!(formatter, " SidewaysL"));
             }
+            if self.intersects(FLAG_UPRIGHT) {
+                try!(write!(formatter, " Upright"));
+            }
         } else {
             try!(write!(formatter, "H"));
         }
@@ -896,6 +914,19 @@ impl<T: Copy + Add<T, T> + Sub<T, T>> LogicalRect<T> {
     }
 }
 
+impl<T: Copy + Zero + Add<T, T> + Sub<T, T>> LogicalRect<T> {
+    /// Returns where the start corner of a box of writing mode `inner_mode`, laid out at this
+    /// rectangle, ends up relative to the start corner of `mode`, in which this rectangle is given.
+    /// The container size is taken to be zero, as it is wherever boxes are positioned relative to
+    /// the start corner of their container; this only differs from `self.start.to_physical` when
+    /// the two writing modes do.
+    pub fn inner_start_corner_to_physical(&self, mode: WritingMode, inner_mode: WritingMode)
+                                          -> Point2D<T> {
+        let rect = self.to_physical(mode, Size2D::zero());
+        rect.origin + LogicalPoint::zero(inner_mode).to_physical(inner_mode, rect.size)
+    }
+}
+
 impl<T: Copy + Ord + Add<T, T> + Sub<T, T>> LogicalRect<T> {
     #[inline]
     pub fn union(&self, other: &LogicalRect<T>) -> LogicalRect<T> {
@@ -1016,6 +1047,31 @@ fn test_margin_round_trip() {
     }
 }
 
+#[test]
+fn test_inner_start_corner() {
+    let rect = Rect(Point2D(10i32, 20i32), Size2D(30i32, 40i32));
+    for &mode in modes().iter() {
+        let logical = LogicalRect::from_physical(mode, rect, Size2D(0, 0));
+        assert!(logical.inner_start_corner_to_physical(mode, mode) ==
+                logical.start.to_physical(mode, Size2D(0, 0)));
+        for &inner_mode in modes().iter() {
+            let corner = logical.inner_start_corner_to_physical(mode, inner_mode);
+            let expected_x = if !inner_mode.is_vertical() && inner_mode.is_bidi_ltr() ||
+                    inner_mode.is_vertical() && inner_mode.is_vertical_lr() {
+                10
+            } else {
+                40
+            };
+            let expected_y = if !inner_mode.is_vertical() || inner_mode.is_inline_tb() {
+                20
+            } else {
+                60
+            };
+            assert!(corner == Point2D(expected_x, expected_y));
+        }
+    }
+}
+
 #[test]
 fn test_rect_round_trip() {
     let physical = Rect(Point2D(1u32, 2u32), Size2D(3u32, 4u32));
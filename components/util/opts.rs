@@ -5,7 +5,7 @@
 //! Configuration options for a single run of the servo application. Created
 //! from command line arguments.
 
-use geometry::ScreenPx;
+use geometry::{PagePx, ScreenPx};
 
 use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
@@ -103,6 +103,11 @@ pub struct Opts {
     /// The initial requested size of the window.
     pub initial_window_size: TypedSize2D<ScreenPx, uint>,
 
+    /// If set with `--paginate`, lay documents out in pages of this size, evaluating `print`
+    /// media queries, and write each page to its own numbered file when an output file is given.
+    /// `@page` rules can override the size.
+    pub page_size: Option<TypedSize2D<PagePx, f32>>,
+
     /// An optional string allowing the user agent to be set for testing.
     pub user_agent: Option<String>,
 
@@ -175,6 +180,7 @@ pub fn default_opts() -> Opts {
         trace_layout: false,
         devtools_port: None,
         initial_window_size: TypedSize2D(800, 600),
+        page_size: None,
         user_agent: None,
         dump_flow_tree: false,
         validate_display_list_geometry: false,
@@ -204,6 +210,7 @@ pub fn from_cmdline_args(args: &[String]) -> bool {
         getopts::optflag("f", "hard-fail", "Exit on task failure instead of displaying about:failure"),
         getopts::optflagopt("", "devtools", "Start remote devtools server on port", "6000"),
         getopts::optopt("", "resolution", "Set window resolution.", "800x600"),
+        getopts::optflagopt("", "paginate", "Lay out in pages of the given size in px", "816x1056"),
         getopts::optopt("u", "user-agent", "Set custom user agent string", "NCSA Mosaic/1.0 (X11;SunOS 4.1.4 sun4m)"),
         getopts::optopt("Z", "debug", "A comma-separated string of debug options. Pass help to show available options.", ""),
         getopts::optflag("h", "help", "Print this message"),
@@ -297,6 +304,13 @@ pub fn from_cmdline_args(args: &[String]) -> bool {
         }
     };
 
+    // The default page size is US Letter at 96 px per inch.
+    let page_size = opt_match.opt_default("paginate", "816x1056").map(|size_string| {
+        let size: Vec<f32> =
+            size_string.as_slice().split('x').map(|s| from_str(s).unwrap()).collect();
+        TypedSize2D(size[0], size[1])
+    });
+
     let render_api = match opt_match.opt_str("r").unwrap_or("gl".to_string()).as_slice() {
         "mesa" => Mesa,
         "gl" => OpenGL,
@@ -325,6 +339,7 @@ pub fn from_cmdline_args(args: &[String]) -> bool {
         trace_layout: trace_layout,
         devtools_port: devtools_port,
         initial_window_size: initial_window_size,
+        page_size: page_size,
         user_agent: opt_match.opt_str("u"),
         show_debug_borders: debug_options.contains(&"show-compositor-borders"),
         show_debug_fragment_borders: debug_options.contains(&"show-fragment-borders"),
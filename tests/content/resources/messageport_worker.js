@@ -0,0 +1,13 @@
+// Answers over the port it is sent, and hands a port of its own back to the page.
+onmessage = function(event) {
+  let port = event.ports[0];
+  port.onmessage = function(event) {
+    port.postMessage(event.data + " from the worker");
+  };
+
+  let channel = new MessageChannel();
+  channel.port1.onmessage = function(event) {
+    channel.port1.postMessage(event.data + " from the worker's channel");
+  };
+  postMessage(event.data, [channel.port2]);
+};
@@ -11,14 +11,18 @@
 #![deny(unused_variables)]
 
 extern crate getopts;
+extern crate openssl;
 extern crate regex;
+extern crate serialize;
 extern crate test;
 
 use test::{AutoColor, TestOpts, run_tests_console, TestDesc, TestDescAndFn, DynTestFn, DynTestName};
 use getopts::{getopts, reqopt};
+use openssl::crypto::hash::{hash, SHA1};
+use serialize::base64::{ToBase64, STANDARD};
 use std::{os, str};
 use std::io::fs;
-use std::io::Reader;
+use std::io::{Acceptor, BufferedReader, IoResult, Listener, Reader, TcpListener, TcpStream};
 use std::io::process::{Command, Ignored, CreatePipe, InheritFd, ExitStatus};
 use regex::Regex;
 
@@ -32,7 +36,11 @@ fn main() {
     let args = os::args();
     let config = parse_config(args.into_iter().collect());
     let opts = test_options(config.clone());
-    let tests = find_tests(config);
+    let websocket_port = match start_websocket_echo_server() {
+        Ok(port) => port,
+        Err(error) => panic!("Unable to start the WebSocket echo server: {}", error),
+    };
+    let tests = find_tests(config, websocket_port);
     match run_tests_console(&opts, tests) {
         Ok(false) => os::set_exit_status(1), // tests failed
         Err(_) => os::set_exit_status(2),    // I/O-related failure
@@ -70,31 +78,34 @@ fn test_options(config: Config) -> TestOpts {
     }
 }
 
-fn find_tests(config: Config) -> Vec<TestDescAndFn> {
+fn find_tests(config: Config, websocket_port: u16) -> Vec<TestDescAndFn> {
     let files_res = fs::readdir(&Path::new(config.source_dir));
     let mut files = match files_res {
         Ok(files) => files,
         _ => panic!("Error reading directory."),
     };
     files.retain(|file| file.extension_str() == Some("html") );
-    return files.iter().map(|file| make_test(format!("{}", file.display()))).collect();
+    return files.iter().map(|file| {
+        make_test(format!("{}", file.display()), websocket_port)
+    }).collect();
 }
 
-fn make_test(file: String) -> TestDescAndFn {
+fn make_test(file: String, websocket_port: u16) -> TestDescAndFn {
     TestDescAndFn {
         desc: TestDesc {
             name: DynTestName(file.clone()),
             ignore: false,
             should_fail: false
         },
-        testfn: DynTestFn(proc() { run_test(file) })
+        testfn: DynTestFn(proc() { run_test(file, websocket_port) })
     }
 }
 
-fn run_test(file: String) {
+fn run_test(file: String, websocket_port: u16) {
     let path = os::make_absolute(&Path::new(file));
     // FIXME (#1094): not the right way to transform a path
-    let infile = format!("file://{}", path.display());
+    // The tests find the port of the WebSocket echo server in the query string.
+    let infile = format!("file://{}?websocket_port={}", path.display(), websocket_port);
     let stdout = CreatePipe(false, true);
     let stderr = InheritFd(2);
     let args = ["-z", "-f", infile.as_slice()];
@@ -134,3 +145,113 @@ fn run_test(file: String) {
         panic!("Servo exited with non-zero status {}", retval);
     }
 }
+
+/// Runs a WebSocket server on a port picked by the system that echoes every message back, and
+/// returns the port. A text message of the form `close <code> <reason>` makes the server start the
+/// closing handshake with that status code and reason instead.
+fn start_websocket_echo_server() -> IoResult<u16> {
+    let mut listener = try!(TcpListener::bind("127.0.0.1:0"));
+    let port = try!(listener.socket_name()).port;
+    let acceptor = try!(listener.listen());
+    spawn(proc() {
+        let mut acceptor = acceptor;
+        for stream in acceptor.incoming() {
+            match stream {
+                Ok(stream) => spawn(proc() {
+                    let _ = echo(stream);
+                }),
+                Err(_) => return,
+            }
+        }
+    });
+    Ok(port)
+}
+
+/// Serves a single connection of the WebSocket echo server.
+fn echo(mut stream: TcpStream) -> IoResult<()> {
+    let mut reader = BufferedReader::new(stream.clone());
+    let mut key = String::new();
+    loop {
+        let line = try!(reader.read_line());
+        let line = line.as_slice().trim();
+        if line.is_empty() {
+            break
+        }
+        if line.starts_with("Sec-WebSocket-Key:") {
+            key = line.slice_from(18).trim().to_string();
+        }
+    }
+    key.push_str("258EAFA5-E914-47DA-95CA-C5AB0DC85B11");
+    let accept = hash(SHA1, key.as_bytes()).as_slice().to_base64(STANDARD);
+    try!(stream.write(format!("HTTP/1.1 101 Switching Protocols\r\n\
+                               Upgrade: websocket\r\n\
+                               Connection: Upgrade\r\n\
+                               Sec-WebSocket-Accept: {}\r\n\r\n", accept).as_bytes()));
+
+    let mut closing = false;
+    loop {
+        let (opcode, payload) = try!(read_frame(&mut reader));
+        let command = if opcode == 0x1 {
+            String::from_utf8(payload.clone()).ok().and_then(|text| {
+                let parts: Vec<&str> = text.as_slice().splitn(2, ' ').collect();
+                if parts.len() < 2 || parts[0] != "close" {
+                    return None
+                }
+                from_str::<u16>(parts[1]).map(|code| {
+                    let reason = if parts.len() == 3 { parts[2] } else { "" };
+                    let mut payload = vec!((code >> 8) as u8, code as u8);
+                    payload.push_all(reason.as_bytes());
+                    payload
+                })
+            })
+        } else {
+            None
+        };
+
+        match (opcode, command) {
+            // The closing handshake is over.
+            (0x8, _) if closing => return Ok(()),
+            (0x8, _) => return write_frame(&mut stream, 0x8, payload.as_slice()),
+            (_, _) if closing => {}
+            (_, Some(close_payload)) => {
+                closing = true;
+                try!(write_frame(&mut stream, 0x8, close_payload.as_slice()))
+            }
+            // Pings are answered with pongs, and pongs are ignored.
+            (0x9, None) => try!(write_frame(&mut stream, 0xA, payload.as_slice())),
+            (0xA, None) => {}
+            (opcode, None) => try!(write_frame(&mut stream, opcode, payload.as_slice())),
+        }
+    }
+}
+
+/// Reads a single masked frame sent by a client, and returns its opcode and unmasked payload.
+fn read_frame<R: Reader>(reader: &mut R) -> IoResult<(u8, Vec<u8>)> {
+    let header = try!(reader.read_exact(2));
+    let length = match header[1] & 0x7F {
+        126 => try!(reader.read_be_u16()) as uint,
+        127 => try!(reader.read_be_u64()) as uint,
+        length => length as uint,
+    };
+    let mask = try!(reader.read_exact(4));
+    let mut payload = try!(reader.read_exact(length));
+    for (index, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[index % 4];
+    }
+    Ok((header[0] & 0xF, payload))
+}
+
+/// Writes a single unfragmented, unmasked frame.
+fn write_frame(stream: &mut TcpStream, opcode: u8, payload: &[u8]) -> IoResult<()> {
+    try!(stream.write_u8(0x80 | opcode));
+    if payload.len() < 126 {
+        try!(stream.write_u8(payload.len() as u8));
+    } else if payload.len() <= 0xFFFF {
+        try!(stream.write_u8(126));
+        try!(stream.write_be_u16(payload.len() as u16));
+    } else {
+        try!(stream.write_u8(127));
+        try!(stream.write_be_u64(payload.len() as u64));
+    }
+    stream.write(payload)
+}
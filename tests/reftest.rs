@@ -143,6 +143,8 @@ struct Reftest {
     is_flaky: bool,
     experimental: bool,
     fragment_identifier: Option<String>,
+    /// The page size to paginate both files with, in which case each page is compared.
+    page_size: Option<String>,
 }
 
 struct TestLine<'a> {
@@ -202,6 +204,7 @@ fn parse_lists(file: &Path, servo_args: &[String], render_mode: RenderMode, id_o
         let mut flakiness = RenderMode::empty();
         let mut experimental = false;
         let mut fragment_identifier = None;
+        let mut page_size = None;
         for condition in conditions_list {
             match condition {
                 "flaky_cpu" => flakiness.insert(CPU_RENDERING),
@@ -214,6 +217,9 @@ fn parse_lists(file: &Path, servo_args: &[String], render_mode: RenderMode, id_o
             if condition.starts_with("fragment=") {
                 fragment_identifier = Some(condition.slice_from("fragment=".len()).to_string());
             }
+            if condition.starts_with("paginate=") {
+                page_size = Some(condition.slice_from("paginate=".len()).to_string());
+            }
         }
 
         let reftest = Reftest {
@@ -226,6 +232,7 @@ fn parse_lists(file: &Path, servo_args: &[String], render_mode: RenderMode, id_o
             is_flaky: render_mode.intersects(flakiness),
             experimental: experimental,
             fragment_identifier: fragment_identifier,
+            page_size: page_size,
         };
 
         tests.push(make_test(reftest));
@@ -247,8 +254,31 @@ fn make_test(reftest: Reftest) -> TestDescAndFn {
     }
 }
 
-fn capture(reftest: &Reftest, side: uint) -> (u32, u32, Vec<u8>) {
+fn load_png(path: &Path) -> (u32, u32, Vec<u8>) {
+    let image = png::load_png(path).unwrap();
+    let rgba8_bytes = match image.pixels {
+        png::RGBA8(pixels) => pixels,
+        _ => panic!(),
+    };
+    (image.width, image.height, rgba8_bytes)
+}
+
+/// Renders one side of the reftest and returns its pages, or the whole rendering as the only page
+/// when the test is not paginated. Paginated output is written one page per file, with the page
+/// number before the extension.
+fn capture(reftest: &Reftest, side: uint) -> Vec<(u32, u32, Vec<u8>)> {
     let png_filename = format!("/tmp/servo-reftest-{:06u}-{:u}.png", reftest.id, side);
+    let page_filename = |page: uint| {
+        format!("/tmp/servo-reftest-{:06u}-{:u}-{:u}.png", reftest.id, side, page)
+    };
+    if reftest.page_size.is_some() {
+        let mut page = 1;
+        while Path::new(page_filename(page)).exists() {
+            io::fs::unlink(&Path::new(page_filename(page))).unwrap();
+            page += 1;
+        }
+    }
+
     let mut command = Command::new(os::self_exe_path().unwrap().join("servo"));
     command
         .args(reftest.servo_args.as_slice())
@@ -272,34 +302,85 @@ fn capture(reftest: &Reftest, side: uint) -> (u32, u32, Vec<u8>) {
     if reftest.experimental {
         command.arg("--experimental");
     }
+    match reftest.page_size {
+        Some(ref page_size) => { command.arg(format!("--paginate={}", page_size)); }
+        None => {}
+    }
     let retval = match command.status() {
         Ok(status) => status,
         Err(e) => panic!("failed to execute process: {}", e),
     };
     assert_eq!(retval, ExitStatus(0));
 
-    let image = png::load_png(&from_str::<Path>(png_filename.as_slice()).unwrap()).unwrap();
-    let rgba8_bytes = match image.pixels {
-        png::RGBA8(pixels) => pixels,
-        _ => panic!(),
-    };
-    (image.width, image.height, rgba8_bytes)
+    if reftest.page_size.is_none() {
+        return vec!(load_png(&from_str::<Path>(png_filename.as_slice()).unwrap()))
+    }
+    let mut pages = vec!();
+    loop {
+        let path = Path::new(page_filename(pages.len() + 1));
+        if !path.exists() {
+            break
+        }
+        pages.push(load_png(&path));
+    }
+    assert!(!pages.is_empty(), "no pages were output");
+    pages
 }
 
 fn check_reftest(reftest: Reftest) {
-    let (left_width, left_height, left_bytes) = capture(&reftest, 0);
-    let (right_width, right_height, right_bytes) = capture(&reftest, 1);
+    let left_pages = capture(&reftest, 0);
+    let right_pages = capture(&reftest, 1);
+
+    let is_paginated = reftest.page_size.is_some();
+    let mut all_empty = true;
+    // Renderings with different page counts differ, whatever the pages they have in common.
+    let mut difference = if left_pages.len() != right_pages.len() {
+        Some(format!("{} pages against {}", left_pages.len(), right_pages.len()))
+    } else {
+        None
+    };
+    for (page, (left, right)) in left_pages.into_iter().zip(right_pages.into_iter()).enumerate() {
+        let output_str = if is_paginated {
+            format!("/tmp/servo-reftest-{:06u}-diff-{:u}.png", reftest.id, page + 1)
+        } else {
+            format!("/tmp/servo-reftest-{:06u}-diff.png", reftest.id)
+        };
+        let (is_empty, is_different) = compare_pages(left, right, output_str.as_slice());
+        all_empty = all_empty && is_empty;
+        if is_different && difference.is_none() {
+            difference = Some(output_str);
+        }
+    }
+
+    // Blank pages are fine in paginated tests, as long as some page has content.
+    if all_empty {
+        panic!("Both renderings are empty")
+    }
+
+    match difference {
+        Some(output_str) => {
+            match (reftest.kind, reftest.is_flaky) {
+                (Same, true) => println!("flaky test - rendering difference: {}", output_str),
+                (Same, false) => panic!("rendering difference: {}", output_str),
+                (Different, _) => {}   // Result was different and that's what was expected
+            }
+        }
+        None => assert!(reftest.is_flaky || reftest.kind == Same),
+    }
+}
 
+/// Compares one page of both renderings, and writes an image of the differences to `output_str`
+/// if there are any. Returns whether both pages are empty and whether they differ.
+fn compare_pages((left_width, left_height, left_bytes): (u32, u32, Vec<u8>),
+                 (right_width, right_height, right_bytes): (u32, u32, Vec<u8>),
+                 output_str: &str)
+                 -> (bool, bool) {
     assert_eq!(left_width, right_width);
     assert_eq!(left_height, right_height);
 
     let left_all_white = left_bytes.iter().all(|&p| p == 255);
     let right_all_white = right_bytes.iter().all(|&p| p == 255);
 
-    if left_all_white && right_all_white {
-        panic!("Both renderings are empty")
-    }
-
     let pixels = left_bytes.iter().zip(right_bytes.iter()).map(|(&a, &b)| {
         if a as i8 - b as i8 == 0 {
             // White for correct
@@ -313,9 +394,9 @@ fn check_reftest(reftest: Reftest) {
         }
     }).collect::<Vec<u8>>();
 
-    if pixels.iter().any(|&a| a < 255) {
-        let output_str = format!("/tmp/servo-reftest-{:06u}-diff.png", reftest.id);
-        let output = from_str::<Path>(output_str.as_slice()).unwrap();
+    let is_different = pixels.iter().any(|&a| a < 255);
+    if is_different {
+        let output = from_str::<Path>(output_str).unwrap();
 
         let mut img = png::Image {
             width: left_width,
@@ -324,13 +405,6 @@ fn check_reftest(reftest: Reftest) {
         };
         let res = png::store_png(&mut img, &output);
         assert!(res.is_ok());
-
-        match (reftest.kind, reftest.is_flaky) {
-            (Same, true) => println!("flaky test - rendering difference: {}", output_str),
-            (Same, false) => panic!("rendering difference: {}", output_str),
-            (Different, _) => {}   // Result was different and that's what was expected
-        }
-    } else {
-        assert!(reftest.is_flaky || reftest.kind == Same);
     }
+    (left_all_white && right_all_white, is_different)
 }